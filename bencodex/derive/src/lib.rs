@@ -0,0 +1,764 @@
+//! `#[derive(Schema)]` for the `bencodex` crate. See `bencodex::schema` for
+//! the attribute reference.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, LitInt, LitStr};
+
+struct FieldSpec {
+    ident: syn::Ident,
+    ty: syn::Type,
+    tag: String,
+    optional: bool,
+    since: u32,
+    deprecated: bool,
+    unknown: bool,
+    skip: bool,
+}
+
+enum VariantShape {
+    Unit,
+    Newtype(syn::Type),
+    Tuple(Vec<syn::Type>),
+    Struct(Vec<FieldSpec>),
+}
+
+struct VariantSpec {
+    ident: syn::Ident,
+    tag: String,
+    since: u32,
+    deprecated: bool,
+    shape: VariantShape,
+}
+
+#[proc_macro_derive(Schema, attributes(bencodex, serde))]
+pub fn derive_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "bencodex::Schema does not support generic types",
+        ));
+    }
+    if let Some(attr) = input.attrs.iter().find(|a| a.path().is_ident("serde")) {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "container-level #[serde(...)] is not supported by bencodex::Schema",
+        ));
+    }
+
+    let mut version: u32 = 1;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("bencodex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("version") {
+                version = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `version = N`"))
+            }
+        })?;
+    }
+
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => {
+                let fields = named
+                    .named
+                    .iter()
+                    .map(|field| parse_field(field, version, true))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                expand_struct(&input.ident, version, fields)
+            }
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                "bencodex::Schema needs a struct with named fields",
+            )),
+        },
+        Data::Enum(data) => {
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| parse_variant(variant, version))
+                .collect::<syn::Result<Vec<_>>>()?;
+            expand_enum(&input.ident, version, variants)
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "bencodex::Schema can only be derived for structs and enums",
+        )),
+    }
+}
+
+/// Apply the `#[serde(...)]` attributes that mean the same thing here:
+/// `rename` sets the tag, `default` makes the field optional and `skip` leaves
+/// it out of the encoding.  Anything else would be silently ignored, so it is
+/// an error instead.
+fn parse_serde_attrs(
+    attrs: &[Attribute],
+    tag: &mut String,
+    optional: Option<&mut bool>,
+    skip: Option<&mut bool>,
+) -> syn::Result<()> {
+    let mut optional = optional;
+    let mut skip = skip;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                *tag = meta.value()?.parse::<LitStr>()?.value();
+                return Ok(());
+            }
+            if meta.path.is_ident("default") && meta.input.is_empty() {
+                if let Some(optional) = optional.as_deref_mut() {
+                    *optional = true;
+                    return Ok(());
+                }
+            }
+            if meta.path.is_ident("skip") {
+                if let Some(skip) = skip.as_deref_mut() {
+                    *skip = true;
+                    return Ok(());
+                }
+            }
+            Err(meta.error(
+                "bencodex::Schema only understands #[serde(rename = \"..\")] and, on fields, \
+                 #[serde(default)] and #[serde(skip)]; use #[bencodex(...)] instead",
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+fn parse_field(field: &syn::Field, version: u32, named: bool) -> syn::Result<FieldSpec> {
+    let ident = field.ident.clone().expect("named field");
+    let mut spec = FieldSpec {
+        tag: ident.to_string(),
+        ident,
+        ty: field.ty.clone(),
+        optional: false,
+        since: 1,
+        deprecated: false,
+        unknown: false,
+        skip: false,
+    };
+    let mut serde_tag = spec.tag.clone();
+    parse_serde_attrs(
+        &field.attrs,
+        &mut serde_tag,
+        Some(&mut spec.optional),
+        Some(&mut spec.skip),
+    )?;
+    let mut bencodex_tag = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("bencodex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") {
+                bencodex_tag = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("optional") {
+                spec.optional = true;
+            } else if meta.path.is_ident("since") {
+                spec.since = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                spec.optional = true;
+            } else if meta.path.is_ident("deprecated") {
+                spec.deprecated = true;
+                spec.optional = true;
+            } else if meta.path.is_ident("unknown") && named {
+                spec.unknown = true;
+            } else {
+                return Err(meta.error(
+                    "expected `tag = \"..\"`, `optional`, `since = N`, `deprecated` or `unknown`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+    spec.tag = match bencodex_tag {
+        Some(tag) if spec.ident != serde_tag => {
+            return Err(syn::Error::new_spanned(
+                &spec.ident,
+                format!("field has both #[serde(rename)] and #[bencodex(tag = {tag:?})]"),
+            ))
+        }
+        Some(tag) => tag,
+        None => serde_tag,
+    };
+    if spec.since > version {
+        return Err(syn::Error::new_spanned(
+            &spec.ident,
+            format!(
+                "field added in version {} but the type is version {version}",
+                spec.since
+            ),
+        ));
+    }
+    Ok(spec)
+}
+
+fn parse_variant(variant: &syn::Variant, version: u32) -> syn::Result<VariantSpec> {
+    let mut spec = VariantSpec {
+        tag: variant.ident.to_string(),
+        ident: variant.ident.clone(),
+        since: 1,
+        deprecated: false,
+        shape: VariantShape::Unit,
+    };
+    parse_serde_attrs(&variant.attrs, &mut spec.tag, None, None)?;
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("bencodex"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") {
+                spec.tag = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("since") {
+                spec.since = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+            } else if meta.path.is_ident("deprecated") {
+                spec.deprecated = true;
+            } else {
+                return Err(meta.error("expected `tag = \"..\"`, `since = N` or `deprecated`"));
+            }
+            Ok(())
+        })?;
+    }
+    if spec.since > version {
+        return Err(syn::Error::new_spanned(
+            &spec.ident,
+            format!(
+                "variant added in version {} but the enum is version {version}",
+                spec.since
+            ),
+        ));
+    }
+    spec.shape = match &variant.fields {
+        Fields::Unit => VariantShape::Unit,
+        Fields::Unnamed(unnamed) => {
+            for field in unnamed.unnamed.iter() {
+                if let Some(attr) = field
+                    .attrs
+                    .iter()
+                    .find(|a| a.path().is_ident("serde") || a.path().is_ident("bencodex"))
+                {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "attributes on tuple variant fields are not supported",
+                    ));
+                }
+            }
+            let mut types: Vec<syn::Type> = unnamed.unnamed.iter().map(|f| f.ty.clone()).collect();
+            if types.len() == 1 {
+                VariantShape::Newtype(types.remove(0))
+            } else {
+                VariantShape::Tuple(types)
+            }
+        }
+        Fields::Named(named) => {
+            let fields = named
+                .named
+                .iter()
+                .map(|field| parse_field(field, version, false))
+                .collect::<syn::Result<Vec<_>>>()?;
+            check_duplicate_tags(&fields)?;
+            VariantShape::Struct(fields)
+        }
+    };
+    Ok(spec)
+}
+
+fn check_duplicate_tags(fields: &[FieldSpec]) -> syn::Result<()> {
+    let known: Vec<&FieldSpec> = fields.iter().filter(|f| !f.unknown && !f.skip).collect();
+    for (i, a) in known.iter().enumerate() {
+        if let Some(b) = known[i + 1..].iter().find(|b| b.tag == a.tag) {
+            return Err(syn::Error::new_spanned(
+                &b.ident,
+                format!("duplicate bencodex tag {:?}", a.tag),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn type_string(ty: &syn::Type) -> String {
+    quote!(#ty).to_string().replace(' ', "")
+}
+
+fn field_schema(f: &FieldSpec) -> TokenStream2 {
+    let tag = &f.tag;
+    let ty_str = type_string(&f.ty);
+    let required = !f.optional;
+    let since = f.since;
+    let deprecated = f.deprecated;
+    quote! {
+        ::bencodex::schema::FieldSchema {
+            tag: #tag.to_string(),
+            ty: #ty_str.to_string(),
+            required: #required,
+            since: #since,
+            deprecated: #deprecated,
+        }
+    }
+}
+
+/// Statements that read a dict of `fields` from `map` and then build
+/// `constructor { .. }`, for the body of a `visit_map`.
+fn visit_map_body(
+    constructor: TokenStream2,
+    fields: &[FieldSpec],
+    unknown: Option<&syn::Ident>,
+) -> TokenStream2 {
+    let known: Vec<&FieldSpec> = fields.iter().filter(|f| !f.unknown && !f.skip).collect();
+    let slots: Vec<syn::Ident> = known
+        .iter()
+        .map(|f| format_ident!("__field_{}", f.ident))
+        .collect();
+    let slot_decls = known.iter().zip(slots.iter()).map(|(f, slot)| {
+        let ty = &f.ty;
+        quote! { let mut #slot: ::std::option::Option<#ty> = ::std::option::Option::None; }
+    });
+    let match_arms = known.iter().zip(slots.iter()).map(|(f, slot)| {
+        let tag = &f.tag;
+        quote! {
+            ::bencodex::Key::Text(t) if t == #tag => {
+                if #slot.is_some() {
+                    return ::std::result::Result::Err(
+                        <A::Error as ::serde::de::Error>::duplicate_field(#tag),
+                    );
+                }
+                #slot = ::std::option::Option::Some(map.next_value()?);
+            }
+        }
+    });
+    let unknown_arm = match unknown {
+        Some(_) => quote! {
+            _ => {
+                let value = map.next_value::<::bencodex::Value>()?;
+                __unknown.0.insert(key, value);
+            }
+        },
+        None => quote! {
+            _ => {
+                map.next_value::<::serde::de::IgnoredAny>()?;
+            }
+        },
+    };
+    let unknown_decl = unknown.map(|_| {
+        quote! { let mut __unknown = ::bencodex::UnknownFields::default(); }
+    });
+    let field_inits = known.iter().zip(slots.iter()).map(|(f, slot)| {
+        let ident = &f.ident;
+        let tag = &f.tag;
+        if f.optional {
+            quote! { #ident: #slot.unwrap_or_default(), }
+        } else {
+            quote! {
+                #ident: match #slot {
+                    ::std::option::Option::Some(v) => v,
+                    ::std::option::Option::None => {
+                        return ::std::result::Result::Err(
+                            <A::Error as ::serde::de::Error>::missing_field(#tag),
+                        )
+                    }
+                },
+            }
+        }
+    });
+    let skipped_inits = fields.iter().filter(|f| f.skip).map(|f| {
+        let ident = &f.ident;
+        quote! { #ident: ::std::default::Default::default(), }
+    });
+    let unknown_init = unknown.map(|ident| quote! { #ident: __unknown, });
+    quote! {
+        #(#slot_decls)*
+        #unknown_decl
+        while let ::std::option::Option::Some(key) = map.next_key::<::bencodex::Key>()? {
+            match &key {
+                #(#match_arms)*
+                #unknown_arm
+            }
+        }
+        ::std::result::Result::Ok(#constructor {
+            #(#field_inits)*
+            #(#skipped_inits)*
+            #unknown_init
+        })
+    }
+}
+
+fn expand_struct(
+    name: &syn::Ident,
+    version: u32,
+    fields: Vec<FieldSpec>,
+) -> syn::Result<TokenStream2> {
+    let name_str = name.to_string();
+    let unknown: Vec<&FieldSpec> = fields.iter().filter(|f| f.unknown).collect();
+    if unknown.len() > 1 {
+        return Err(syn::Error::new_spanned(
+            &unknown[1].ident,
+            "only one `#[bencodex(unknown)]` field is allowed",
+        ));
+    }
+    let unknown = unknown.first().map(|f| f.ident.clone());
+    check_duplicate_tags(&fields)?;
+    let known: Vec<&FieldSpec> = fields.iter().filter(|f| !f.unknown && !f.skip).collect();
+
+    let ser_entries = known.iter().map(|f| {
+        let ident = &f.ident;
+        let tag = &f.tag;
+        quote! { map.serialize_entry(#tag, &self.#ident)?; }
+    });
+    let ser_unknown = unknown.as_ref().map(|ident| {
+        quote! {
+            for (k, v) in self.#ident.0.iter() {
+                map.serialize_entry(k, v)?;
+            }
+        }
+    });
+    let visit_map = visit_map_body(quote!(#name), &fields, unknown.as_ref());
+    let schema_fields = known.iter().map(|f| field_schema(f));
+    let preserves_unknown = unknown.is_some();
+    let expecting = format!("struct {name_str}");
+
+    Ok(quote! {
+        impl ::serde::Serialize for #name {
+            fn serialize<S: ::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> ::std::result::Result<S::Ok, S::Error> {
+                use ::serde::ser::SerializeMap;
+                let mut map = serializer.serialize_map(::std::option::Option::None)?;
+                #(#ser_entries)*
+                #ser_unknown
+                map.end()
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for #name {
+            fn deserialize<D: ::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> ::std::result::Result<Self, D::Error> {
+                struct __Visitor;
+
+                impl<'de> ::serde::de::Visitor<'de> for __Visitor {
+                    type Value = #name;
+
+                    fn expecting(
+                        &self,
+                        f: &mut ::std::fmt::Formatter<'_>,
+                    ) -> ::std::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_map<A: ::serde::de::MapAccess<'de>>(
+                        self,
+                        mut map: A,
+                    ) -> ::std::result::Result<#name, A::Error> {
+                        #visit_map
+                    }
+                }
+
+                deserializer.deserialize_map(__Visitor)
+            }
+        }
+
+        impl ::bencodex::schema::HasSchema for #name {
+            type Schema = ::bencodex::schema::StructSchema;
+
+            fn schema() -> ::bencodex::schema::StructSchema {
+                ::bencodex::schema::StructSchema {
+                    name: #name_str.to_string(),
+                    version: #version,
+                    fields: ::std::vec![#(#schema_fields),*],
+                    preserves_unknown: #preserves_unknown,
+                }
+            }
+        }
+    })
+}
+
+fn expand_enum(
+    name: &syn::Ident,
+    version: u32,
+    variants: Vec<VariantSpec>,
+) -> syn::Result<TokenStream2> {
+    let name_str = name.to_string();
+    for (i, a) in variants.iter().enumerate() {
+        if let Some(b) = variants[i + 1..].iter().find(|b| b.tag == a.tag) {
+            return Err(syn::Error::new_spanned(
+                &b.ident,
+                format!("duplicate bencodex tag {:?}", a.tag),
+            ));
+        }
+    }
+    let tags: Vec<&String> = variants.iter().map(|v| &v.tag).collect();
+
+    let ser_arms = variants.iter().enumerate().map(|(index, v)| {
+        let ident = &v.ident;
+        let tag = &v.tag;
+        let index = index as u32;
+        match &v.shape {
+            VariantShape::Unit => quote! {
+                #name::#ident => serializer.serialize_unit_variant(#name_str, #index, #tag),
+            },
+            VariantShape::Newtype(_) => quote! {
+                #name::#ident(value) => {
+                    serializer.serialize_newtype_variant(#name_str, #index, #tag, value)
+                }
+            },
+            VariantShape::Tuple(types) => {
+                let bindings: Vec<syn::Ident> = (0..types.len())
+                    .map(|i| format_ident!("__f{}", i))
+                    .collect();
+                let len = types.len();
+                quote! {
+                    #name::#ident(#(#bindings),*) => {
+                        use ::serde::ser::SerializeTupleVariant;
+                        let mut tuple = serializer
+                            .serialize_tuple_variant(#name_str, #index, #tag, #len)?;
+                        #(tuple.serialize_field(#bindings)?;)*
+                        tuple.end()
+                    }
+                }
+            }
+            VariantShape::Struct(fields) => {
+                let known: Vec<&FieldSpec> = fields.iter().filter(|f| !f.skip).collect();
+                let idents: Vec<&syn::Ident> = known.iter().map(|f| &f.ident).collect();
+                let field_tags: Vec<&String> = known.iter().map(|f| &f.tag).collect();
+                let len = known.len();
+                quote! {
+                    #name::#ident { #(#idents,)* .. } => {
+                        use ::serde::ser::SerializeStructVariant;
+                        let mut fields = serializer
+                            .serialize_struct_variant(#name_str, #index, #tag, #len)?;
+                        #(fields.serialize_field(#field_tags, #idents)?;)*
+                        fields.end()
+                    }
+                }
+            }
+        }
+    });
+
+    let mut helper_visitors = Vec::new();
+    let de_arms = variants.iter().map(|v| {
+        let ident = &v.ident;
+        let tag = &v.tag;
+        match &v.shape {
+            VariantShape::Unit => quote! {
+                #tag => {
+                    ::serde::de::VariantAccess::unit_variant(variant)?;
+                    ::std::result::Result::Ok(#name::#ident)
+                }
+            },
+            VariantShape::Newtype(_) => quote! {
+                #tag => ::std::result::Result::Ok(#name::#ident(
+                    ::serde::de::VariantAccess::newtype_variant(variant)?,
+                )),
+            },
+            VariantShape::Tuple(types) => {
+                let visitor = format_ident!("__Tuple{}", ident);
+                let len = types.len();
+                let bindings: Vec<syn::Ident> =
+                    (0..len).map(|i| format_ident!("__f{}", i)).collect();
+                let reads =
+                    bindings
+                        .iter()
+                        .zip(types.iter())
+                        .enumerate()
+                        .map(|(i, (binding, ty))| {
+                            quote! {
+                                let #binding: #ty = match seq.next_element()? {
+                                    ::std::option::Option::Some(v) => v,
+                                    ::std::option::Option::None => {
+                                        return ::std::result::Result::Err(
+                                            <A::Error as ::serde::de::Error>::invalid_length(
+                                                #i, &self,
+                                            ),
+                                        )
+                                    }
+                                };
+                            }
+                        });
+                let expecting = format!("tuple variant {name_str}::{tag}");
+                helper_visitors.push(quote! {
+                    struct #visitor;
+
+                    impl<'de> ::serde::de::Visitor<'de> for #visitor {
+                        type Value = #name;
+
+                        fn expecting(
+                            &self,
+                            f: &mut ::std::fmt::Formatter<'_>,
+                        ) -> ::std::fmt::Result {
+                            f.write_str(#expecting)
+                        }
+
+                        fn visit_seq<A: ::serde::de::SeqAccess<'de>>(
+                            self,
+                            mut seq: A,
+                        ) -> ::std::result::Result<#name, A::Error> {
+                            #(#reads)*
+                            ::std::result::Result::Ok(#name::#ident(#(#bindings),*))
+                        }
+                    }
+                });
+                quote! {
+                    #tag => ::serde::de::VariantAccess::tuple_variant(variant, #len, #visitor),
+                }
+            }
+            VariantShape::Struct(fields) => {
+                let visitor = format_ident!("__Struct{}", ident);
+                let field_tags: Vec<&String> =
+                    fields.iter().filter(|f| !f.skip).map(|f| &f.tag).collect();
+                let body = visit_map_body(quote!(#name::#ident), fields, None);
+                let expecting = format!("struct variant {name_str}::{tag}");
+                helper_visitors.push(quote! {
+                    struct #visitor;
+
+                    impl<'de> ::serde::de::Visitor<'de> for #visitor {
+                        type Value = #name;
+
+                        fn expecting(
+                            &self,
+                            f: &mut ::std::fmt::Formatter<'_>,
+                        ) -> ::std::fmt::Result {
+                            f.write_str(#expecting)
+                        }
+
+                        fn visit_map<A: ::serde::de::MapAccess<'de>>(
+                            self,
+                            mut map: A,
+                        ) -> ::std::result::Result<#name, A::Error> {
+                            #body
+                        }
+                    }
+                });
+                quote! {
+                    #tag => ::serde::de::VariantAccess::struct_variant(
+                        variant,
+                        &[#(#field_tags),*],
+                        #visitor,
+                    ),
+                }
+            }
+        }
+    });
+    let de_arms: Vec<TokenStream2> = de_arms.collect();
+
+    let schema_variants = variants.iter().map(|v| {
+        let tag = &v.tag;
+        let since = v.since;
+        let deprecated = v.deprecated;
+        let (kind, fields) = match &v.shape {
+            VariantShape::Unit => (quote!(Unit), Vec::new()),
+            VariantShape::Newtype(ty) => (quote!(Newtype), vec![positional_schema(0, ty)]),
+            VariantShape::Tuple(types) => (
+                quote!(Tuple),
+                types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| positional_schema(i, ty))
+                    .collect(),
+            ),
+            VariantShape::Struct(fields) => (
+                quote!(Struct),
+                fields
+                    .iter()
+                    .filter(|f| !f.skip)
+                    .map(field_schema)
+                    .collect(),
+            ),
+        };
+        quote! {
+            ::bencodex::schema::VariantSchema {
+                tag: #tag.to_string(),
+                kind: ::bencodex::schema::VariantKind::#kind,
+                fields: ::std::vec![#(#fields),*],
+                since: #since,
+                deprecated: #deprecated,
+            }
+        }
+    });
+    let expecting = format!("enum {name_str}");
+
+    Ok(quote! {
+        impl ::serde::Serialize for #name {
+            fn serialize<S: ::serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> ::std::result::Result<S::Ok, S::Error> {
+                match self {
+                    #(#ser_arms)*
+                }
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for #name {
+            fn deserialize<D: ::serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> ::std::result::Result<Self, D::Error> {
+                const VARIANTS: &[&str] = &[#(#tags),*];
+
+                #(#helper_visitors)*
+
+                struct __Visitor;
+
+                impl<'de> ::serde::de::Visitor<'de> for __Visitor {
+                    type Value = #name;
+
+                    fn expecting(
+                        &self,
+                        f: &mut ::std::fmt::Formatter<'_>,
+                    ) -> ::std::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_enum<A: ::serde::de::EnumAccess<'de>>(
+                        self,
+                        data: A,
+                    ) -> ::std::result::Result<#name, A::Error> {
+                        let (tag, variant) = data.variant::<::std::string::String>()?;
+                        match tag.as_str() {
+                            #(#de_arms)*
+                            _ => ::std::result::Result::Err(
+                                <A::Error as ::serde::de::Error>::unknown_variant(&tag, VARIANTS),
+                            ),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_enum(#name_str, VARIANTS, __Visitor)
+            }
+        }
+
+        impl ::bencodex::schema::HasSchema for #name {
+            type Schema = ::bencodex::schema::EnumSchema;
+
+            fn schema() -> ::bencodex::schema::EnumSchema {
+                ::bencodex::schema::EnumSchema {
+                    name: #name_str.to_string(),
+                    version: #version,
+                    variants: ::std::vec![#(#schema_variants),*],
+                }
+            }
+        }
+    })
+}
+
+fn positional_schema(index: usize, ty: &syn::Type) -> TokenStream2 {
+    let tag = index.to_string();
+    let ty_str = type_string(ty);
+    quote! {
+        ::bencodex::schema::FieldSchema {
+            tag: #tag.to_string(),
+            ty: #ty_str.to_string(),
+            required: true,
+            since: 1,
+            deprecated: false,
+        }
+    }
+}
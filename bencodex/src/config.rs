@@ -0,0 +1,65 @@
+/// Limits and strictness applied while decoding.
+///
+/// [`from_slice`](crate::from_slice) uses [`DecoderConfig::UNBOUNDED`], which
+/// matches the historical behaviour. Input from anything we do not control (a
+/// peer, a save file) should go through
+/// [`from_slice_with`](crate::from_slice_with) and a bounded config so that
+/// hostile or corrupted bytes fail before they cost much memory or time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecoderConfig {
+    /// Maximum nesting of lists, dicts and enum wrappers.
+    pub max_depth: usize,
+    /// Maximum number of elements in any single list or entries in a dict.
+    pub max_collection_len: usize,
+    /// Maximum length of any single byte or unicode string.
+    pub max_bytestring_len: usize,
+    /// Budget for the memory the decoded value is expected to need: the sum of
+    /// all string lengths plus [`DecoderConfig::NODE_COST`] per element.
+    pub max_total_alloc: usize,
+    /// Reject encodings our own serializer would never produce: unsorted or
+    /// duplicate dict keys, non-string dict keys, `+` signs, and zero-padded
+    /// string lengths.
+    pub canonical: bool,
+}
+
+impl DecoderConfig {
+    /// Charged against `max_total_alloc` for every value decoded, so a long
+    /// list of tiny elements is not free.
+    pub const NODE_COST: usize = 32;
+
+    pub const UNBOUNDED: DecoderConfig = DecoderConfig {
+        max_depth: usize::MAX,
+        max_collection_len: usize::MAX,
+        max_bytestring_len: usize::MAX,
+        max_total_alloc: usize::MAX,
+        canonical: false,
+    };
+
+    pub const fn bounded(
+        max_depth: usize,
+        max_collection_len: usize,
+        max_bytestring_len: usize,
+        max_total_alloc: usize,
+    ) -> DecoderConfig {
+        DecoderConfig {
+            max_depth,
+            max_collection_len,
+            max_bytestring_len,
+            max_total_alloc,
+            canonical: false,
+        }
+    }
+
+    pub const fn strict(self) -> DecoderConfig {
+        DecoderConfig {
+            canonical: true,
+            ..self
+        }
+    }
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        DecoderConfig::UNBOUNDED
+    }
+}
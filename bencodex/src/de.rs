@@ -1,9 +1,22 @@
 use serde::de::{self, Deserialize, DeserializeSeed, MapAccess, SeqAccess, Visitor};
 
-use crate::Error;
+use crate::{DecoderConfig, Error};
 
 pub fn from_slice<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error> {
-    let mut de = Deserializer { input };
+    from_slice_with(input, &DecoderConfig::UNBOUNDED)
+}
+
+/// Decode `input` under `config`'s limits, failing as soon as one is crossed.
+pub fn from_slice_with<'de, T: Deserialize<'de>>(
+    input: &'de [u8],
+    config: &DecoderConfig,
+) -> Result<T, Error> {
+    let mut de = Deserializer {
+        input,
+        config: *config,
+        depth: 0,
+        alloc: 0,
+    };
     let value = T::deserialize(&mut de)?;
     if de.input.is_empty() {
         Ok(value)
@@ -17,6 +30,9 @@ pub fn from_slice<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error
 
 struct Deserializer<'de> {
     input: &'de [u8],
+    config: DecoderConfig,
+    depth: usize,
+    alloc: usize,
 }
 
 impl<'de> Deserializer<'de> {
@@ -28,6 +44,31 @@ impl<'de> Deserializer<'de> {
         self.input = &self.input[n..];
     }
 
+    fn charge(&mut self, bytes: usize) -> Result<(), Error> {
+        self.alloc = self.alloc.saturating_add(bytes);
+        if self.alloc > self.config.max_total_alloc {
+            return Err(Error::LimitExceeded(format!(
+                "decoded size exceeds {} bytes",
+                self.config.max_total_alloc
+            )));
+        }
+        Ok(())
+    }
+
+    /// Run `f` one container level deeper, enforcing `max_depth`.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        if self.depth >= self.config.max_depth {
+            return Err(Error::LimitExceeded(format!(
+                "nesting deeper than {}",
+                self.config.max_depth
+            )));
+        }
+        self.depth += 1;
+        let result = f(self)?;
+        self.depth -= 1;
+        Ok(result)
+    }
+
     fn consume_end(&mut self) -> Result<(), Error> {
         if self.peek()? == b'e' {
             self.advance(1);
@@ -50,28 +91,67 @@ impl<'de> Deserializer<'de> {
         if s.starts_with("-0") || (s.starts_with('0') && s.len() > 1) {
             return Err(Error::InvalidData("invalid integer encoding".into()));
         }
+        if self.config.canonical && s.starts_with('+') {
+            return Err(Error::InvalidData("non-canonical integer encoding".into()));
+        }
         let val: i128 = s.parse()
             .map_err(|_| Error::InvalidData(format!("cannot parse integer: {s}")))?;
         self.advance(end + 1);
         Ok(val)
     }
 
-    fn parse_bytestring(&mut self) -> Result<&'de [u8], Error> {
-        let colon = self.input.iter().position(|&b| b == b':')
+    /// Locate the byte string whose length prefix starts at `offset` without
+    /// consuming it. Returns the payload and the offset just past it.
+    fn peek_bytestring_at(&self, offset: usize) -> Result<(&'de [u8], usize), Error> {
+        let input = &self.input[offset.min(self.input.len())..];
+        let colon = input
+            .iter()
+            .position(|&b| b == b':')
             .ok_or_else(|| Error::InvalidData("missing ':' in bytestring".into()))?;
-        let len_str = std::str::from_utf8(&self.input[..colon])
+        let len_str = std::str::from_utf8(&input[..colon])
             .map_err(|_| Error::InvalidData("non-utf8 in bytestring length".into()))?;
+        if self.config.canonical
+            && (len_str.starts_with('+') || (len_str.starts_with('0') && len_str.len() > 1))
+        {
+            return Err(Error::InvalidData(format!(
+                "non-canonical bytestring length: {len_str}"
+            )));
+        }
         let len: usize = len_str.parse()
             .map_err(|_| Error::InvalidData(format!("bad bytestring length: {len_str}")))?;
+        if len > self.config.max_bytestring_len {
+            return Err(Error::LimitExceeded(format!(
+                "bytestring of {len} bytes exceeds {}",
+                self.config.max_bytestring_len
+            )));
+        }
         let start = colon + 1;
-        if self.input.len() < start + len {
+        let end = start.checked_add(len).ok_or(Error::Eof)?;
+        if input.len() < end {
             return Err(Error::Eof);
         }
-        let data = &self.input[start..start + len];
-        self.advance(start + len);
+        Ok((&input[start..end], offset + end))
+    }
+
+    fn parse_bytestring(&mut self) -> Result<&'de [u8], Error> {
+        let (data, end) = self.peek_bytestring_at(0)?;
+        self.charge(data.len())?;
+        self.advance(end);
         Ok(data)
     }
 
+    /// The next dict key as `(is_unicode, bytes)`, which orders the same way
+    /// the serializer sorts keys: byte-string keys first, then unicode keys.
+    fn peek_dict_key(&self) -> Result<(bool, &'de [u8]), Error> {
+        match self.peek()? {
+            b'u' => Ok((true, self.peek_bytestring_at(1)?.0)),
+            b'0'..=b'9' => Ok((false, self.peek_bytestring_at(0)?.0)),
+            other => Err(Error::InvalidData(format!(
+                "dict key must be a string, got 0x{other:02x}"
+            ))),
+        }
+    }
+
     fn parse_unicode(&mut self) -> Result<&'de str, Error> {
         // 'u' already consumed
         let bytes = self.parse_bytestring()?;
@@ -110,15 +190,19 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             }
             b'l' => {
                 self.advance(1);
-                let result = visitor.visit_seq(ListAccess { de: self })?;
-                self.consume_end()?;
-                Ok(result)
+                self.nested(|de| {
+                    let result = visitor.visit_seq(ListAccess::new(de))?;
+                    de.consume_end()?;
+                    Ok(result)
+                })
             }
             b'd' => {
                 self.advance(1);
-                let result = visitor.visit_map(DictAccess { de: self })?;
-                self.consume_end()?;
-                Ok(result)
+                self.nested(|de| {
+                    let result = visitor.visit_map(DictAccess::new(de))?;
+                    de.consume_end()?;
+                    Ok(result)
+                })
             }
             b'u' => {
                 self.advance(1);
@@ -205,9 +289,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         match self.peek()? {
             b'l' => {
                 self.advance(1);
-                let result = visitor.visit_seq(ListAccess { de: self })?;
-                self.consume_end()?;
-                Ok(result)
+                self.nested(|de| {
+                    let result = visitor.visit_seq(ListAccess::new(de))?;
+                    de.consume_end()?;
+                    Ok(result)
+                })
             }
             // A `Vec<u8>`/`&[u8]` target encoded as a byte string: present the
             // raw bytes as a sequence of `u8` elements. This is the read side of
@@ -231,9 +317,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
         if self.peek()? == b'd' {
             self.advance(1);
-            let result = visitor.visit_map(DictAccess { de: self })?;
-            self.consume_end()?;
-            Ok(result)
+            self.nested(|de| {
+                let result = visitor.visit_map(DictAccess::new(de))?;
+                de.consume_end()?;
+                Ok(result)
+            })
         } else {
             self.deserialize_any(visitor)
         }
@@ -262,7 +350,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             b'd' => {
                 // Newtype/struct/tuple variant: dict with one key
                 self.advance(1);
-                visitor.visit_enum(DictVariantAccess { de: self })
+                self.nested(|de| visitor.visit_enum(DictVariantAccess { de }))
             }
             _ => self.deserialize_any(visitor),
         }
@@ -295,6 +383,26 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
 struct ListAccess<'a, 'de> {
     de: &'a mut Deserializer<'de>,
+    len: usize,
+}
+
+impl<'a, 'de> ListAccess<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        ListAccess { de, len: 0 }
+    }
+}
+
+/// Count one more element of a list or dict against the collection and
+/// allocation limits.
+fn count_element(de: &mut Deserializer<'_>, len: &mut usize) -> Result<(), Error> {
+    *len += 1;
+    if *len > de.config.max_collection_len {
+        return Err(Error::LimitExceeded(format!(
+            "collection longer than {}",
+            de.config.max_collection_len
+        )));
+    }
+    de.charge(DecoderConfig::NODE_COST)
 }
 
 impl<'a, 'de> SeqAccess<'de> for ListAccess<'a, 'de> {
@@ -304,6 +412,7 @@ impl<'a, 'de> SeqAccess<'de> for ListAccess<'a, 'de> {
         if self.de.peek()? == b'e' {
             return Ok(None);
         }
+        count_element(self.de, &mut self.len)?;
         seed.deserialize(&mut *self.de).map(Some)
     }
 }
@@ -336,6 +445,18 @@ impl<'de> SeqAccess<'de> for BytesSeqAccess<'de> {
 
 struct DictAccess<'a, 'de> {
     de: &'a mut Deserializer<'de>,
+    len: usize,
+    last_key: Option<(bool, &'de [u8])>,
+}
+
+impl<'a, 'de> DictAccess<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        DictAccess {
+            de,
+            len: 0,
+            last_key: None,
+        }
+    }
 }
 
 impl<'a, 'de> MapAccess<'de> for DictAccess<'a, 'de> {
@@ -345,6 +466,17 @@ impl<'a, 'de> MapAccess<'de> for DictAccess<'a, 'de> {
         if self.de.peek()? == b'e' {
             return Ok(None);
         }
+        count_element(self.de, &mut self.len)?;
+        if self.de.config.canonical {
+            let key = self.de.peek_dict_key()?;
+            if self.last_key.is_some_and(|last| last >= key) {
+                return Err(Error::InvalidData(format!(
+                    "dict key {:?} is unsorted or duplicated",
+                    String::from_utf8_lossy(key.1)
+                )));
+            }
+            self.last_key = Some(key);
+        }
         seed.deserialize(&mut *self.de).map(Some)
     }
 
@@ -421,8 +553,11 @@ impl<'a, 'de> de::VariantAccess<'de> for DictVariantValue<'a, 'de> {
     fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
         if self.de.peek()? == b'l' {
             self.de.advance(1);
-            let result = visitor.visit_seq(ListAccess { de: self.de })?;
-            self.de.consume_end()?;
+            let result = self.de.nested(|de| {
+                let result = visitor.visit_seq(ListAccess::new(de))?;
+                de.consume_end()?;
+                Ok(result)
+            })?;
             // consume outer dict 'e'
             self.de.consume_end()?;
             Ok(result)
@@ -434,8 +569,11 @@ impl<'a, 'de> de::VariantAccess<'de> for DictVariantValue<'a, 'de> {
     fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
         if self.de.peek()? == b'd' {
             self.de.advance(1);
-            let result = visitor.visit_map(DictAccess { de: self.de })?;
-            self.de.consume_end()?;
+            let result = self.de.nested(|de| {
+                let result = visitor.visit_map(DictAccess::new(de))?;
+                de.consume_end()?;
+                Ok(result)
+            })?;
             // consume outer dict 'e'
             self.de.consume_end()?;
             Ok(result)
@@ -5,6 +5,8 @@ pub enum Error {
     Message(String),
     Eof,
     InvalidData(String),
+    /// A [`DecoderConfig`](crate::DecoderConfig) limit was crossed.
+    LimitExceeded(String),
 }
 
 impl fmt::Display for Error {
@@ -13,6 +15,7 @@ impl fmt::Display for Error {
             Error::Message(msg) => write!(f, "{msg}"),
             Error::Eof => write!(f, "unexpected end of input"),
             Error::InvalidData(msg) => write!(f, "invalid data: {msg}"),
+            Error::LimitExceeded(msg) => write!(f, "decode limit exceeded: {msg}"),
         }
     }
 }
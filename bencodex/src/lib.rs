@@ -1,11 +1,24 @@
+// Lets `#[derive(Schema)]` output, which names `::bencodex`, build inside
+// this crate's own tests.
+extern crate self as bencodex;
+
 mod ser;
 mod de;
+mod config;
 mod error;
+pub mod schema;
+mod stream;
 pub mod string_key_map;
+mod value;
 
+pub use bencodex_derive::Schema;
+pub use config::DecoderConfig;
+pub use de::{from_slice, from_slice_with};
 pub use error::Error;
+pub use schema::UnknownFields;
 pub use ser::to_vec;
-pub use de::from_slice;
+pub use stream::StreamDecoder;
+pub use value::{Key, Value};
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,251 @@
+//! Schema evolution for structs and enums encoded as bencodex.
+//!
+//! `#[derive(bencodex::Schema)]` replaces `#[derive(Serialize, Deserialize)]`
+//! on a struct with named fields and encodes it as a dict keyed by stable
+//! field tags. A tag defaults to the field's name, so switching an existing
+//! serde struct over leaves its encoding unchanged. After that the Rust field
+//! can be renamed freely as long as its tag stays put.
+//!
+//! ```ignore
+//! #[derive(bencodex::Schema)]
+//! #[bencodex(version = 2)]
+//! pub struct Example {
+//!     pub id: u64,
+//!     #[bencodex(tag = "amt")]
+//!     pub amount: u64,
+//!     #[bencodex(since = 2)]
+//!     pub memo: Option<String>,
+//!     #[bencodex(deprecated)]
+//!     pub legacy_flag: bool,
+//!     #[bencodex(unknown)]
+//!     pub unknown: bencodex::UnknownFields,
+//! }
+//! ```
+//!
+//! Field attributes:
+//!
+//! - `tag = "..."`: the dict key. Never change it once data has been written.
+//! - `optional`: a missing key decodes as `Default::default()`.
+//! - `since = N`: added in schema version `N`. Implies `optional`, because
+//!   writers older than `N` never send it.
+//! - `deprecated`: still read and written, so older peers keep working, but
+//!   new code should not rely on it. Implies `optional`, so a later writer
+//!   may drop it.
+//! - `unknown`: a single [`UnknownFields`] field. It collects keys this build
+//!   does not recognise and writes them back unchanged. Without it, unknown
+//!   keys are skipped.
+//!
+//! The serde field attributes with the same meaning are honoured:
+//! `#[serde(rename = "...")]` sets the tag, `#[serde(default)]` is
+//! `optional` and `#[serde(skip)]` leaves the field out of both the encoding
+//! and the schema. Any other `#[serde(...)]` attribute is a compile error
+//! rather than being silently ignored.
+//!
+//! On an enum the derive keeps serde's external tagging: a unit variant is
+//! its tag as a string and any other variant a one-entry dict from its tag to
+//! the payload. Variants take `tag`, `since` and `deprecated` (or serde
+//! `rename`); struct-variant fields take the field attributes above except
+//! `unknown`.
+//!
+//! The derive also implements [`HasSchema`]. Two builds can exchange their
+//! [`StructSchema`]s or [`EnumSchema`]s and call `check_compatible` before
+//! relying on each other's encodings.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Key, Value};
+
+/// Dict entries that the decoding build did not recognise, kept so they can
+/// be written back unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnknownFields(pub BTreeMap<Key, Value>);
+
+impl UnknownFields {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldSchema {
+    pub tag: String,
+    /// The field's Rust type as written in the struct, whitespace removed.
+    pub ty: String,
+    /// Decoding fails when a required field is missing.
+    pub required: bool,
+    /// Schema version that introduced the field.
+    pub since: u32,
+    pub deprecated: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructSchema {
+    pub name: String,
+    pub version: u32,
+    pub fields: Vec<FieldSchema>,
+    pub preserves_unknown: bool,
+}
+
+/// Implemented by `#[derive(Schema)]`: [`StructSchema`] for structs,
+/// [`EnumSchema`] for enums.
+pub trait HasSchema {
+    type Schema;
+
+    fn schema() -> Self::Schema;
+}
+
+impl StructSchema {
+    pub fn field(&self, tag: &str) -> Option<&FieldSchema> {
+        find_field(&self.fields, tag)
+    }
+
+    /// Problems a build with this schema would hit decoding what a build with
+    /// `writer` encodes. Every field is always written, so the only failures
+    /// are required fields the writer lacks and tags whose types differ.
+    pub fn read_incompatibilities(&self, writer: &StructSchema) -> Vec<String> {
+        let mut problems = self.missing_required(writer);
+        problems.extend(type_mismatches(
+            &self.name,
+            (&self.fields, self.version),
+            (&writer.fields, writer.version),
+        ));
+        problems
+    }
+
+    /// Check that both sides can read what the other writes.
+    pub fn check_compatible(&self, peer: &StructSchema) -> Result<(), Vec<String>> {
+        let mut problems = self.read_incompatibilities(peer);
+        problems.extend(peer.missing_required(self));
+        into_result(problems)
+    }
+
+    fn missing_required(&self, writer: &StructSchema) -> Vec<String> {
+        missing_required(
+            &self.name,
+            (&self.fields, self.version),
+            (&writer.fields, writer.version),
+        )
+    }
+}
+
+/// How a variant is encoded: a unit variant is its tag as a string, the
+/// others a one-entry dict from the tag to the payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VariantKind {
+    Unit,
+    /// The payload is the single field's own encoding.
+    Newtype,
+    /// The payload is a list.
+    Tuple,
+    /// The payload is a dict keyed by field tags.
+    Struct,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VariantSchema {
+    pub tag: String,
+    pub kind: VariantKind,
+    /// Newtype and tuple fields are tagged by position: "0", "1", ...
+    pub fields: Vec<FieldSchema>,
+    /// Schema version that introduced the variant.
+    pub since: u32,
+    pub deprecated: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnumSchema {
+    pub name: String,
+    pub version: u32,
+    pub variants: Vec<VariantSchema>,
+}
+
+impl EnumSchema {
+    pub fn variant(&self, tag: &str) -> Option<&VariantSchema> {
+        self.variants.iter().find(|v| v.tag == tag)
+    }
+
+    /// Tags of variants `peer` has and we don't. Decoding one of those fails,
+    /// so callers must not send them to us; this is for them to gate on.
+    pub fn unknown_variants<'a>(&self, peer: &'a EnumSchema) -> Vec<&'a str> {
+        peer.variants
+            .iter()
+            .filter(|v| self.variant(&v.tag).is_none())
+            .map(|v| v.tag.as_str())
+            .collect()
+    }
+
+    /// Check that every variant both sides know is encoded the same way.
+    /// Variants only one side has are left to capability negotiation; see
+    /// [`EnumSchema::unknown_variants`].
+    pub fn check_compatible(&self, peer: &EnumSchema) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        for ours in self.variants.iter() {
+            let Some(theirs) = peer.variant(&ours.tag) else {
+                continue;
+            };
+            let context = format!("{}::{}", self.name, ours.tag);
+            if ours.kind != theirs.kind {
+                problems.push(format!(
+                    "{context}: variant is {:?} in version {} but {:?} in version {}",
+                    ours.kind, self.version, theirs.kind, peer.version
+                ));
+                continue;
+            }
+            let ours = (&ours.fields[..], self.version);
+            let theirs = (&theirs.fields[..], peer.version);
+            problems.extend(missing_required(&context, ours, theirs));
+            problems.extend(missing_required(&context, theirs, ours));
+            problems.extend(type_mismatches(&context, ours, theirs));
+        }
+        into_result(problems)
+    }
+}
+
+fn find_field<'a>(fields: &'a [FieldSchema], tag: &str) -> Option<&'a FieldSchema> {
+    fields.iter().find(|f| f.tag == tag)
+}
+
+fn into_result(problems: Vec<String>) -> Result<(), Vec<String>> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+fn missing_required(
+    context: &str,
+    (reader, reader_version): (&[FieldSchema], u32),
+    (writer, writer_version): (&[FieldSchema], u32),
+) -> Vec<String> {
+    reader
+        .iter()
+        .filter(|f| f.required && find_field(writer, &f.tag).is_none())
+        .map(|f| {
+            format!(
+                "{context}: field {:?} is required by version {reader_version} but not written by version {writer_version}",
+                f.tag
+            )
+        })
+        .collect()
+}
+
+fn type_mismatches(
+    context: &str,
+    (ours, our_version): (&[FieldSchema], u32),
+    (theirs, their_version): (&[FieldSchema], u32),
+) -> Vec<String> {
+    ours.iter()
+        .filter_map(|field| {
+            let other = find_field(theirs, &field.tag)?;
+            (other.ty != field.ty).then(|| {
+                format!(
+                    "{context}: field {:?} is {} in version {our_version} but {} in version {their_version}",
+                    field.tag, field.ty, other.ty
+                )
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+
+use serde::de::DeserializeOwned;
+
+use crate::{from_slice_with, DecoderConfig, Error};
+
+/// Where the scanner is inside the value currently being buffered.
+#[derive(Clone, Copy, Debug)]
+enum Scan {
+    /// Expecting the first byte of a value (or an `e` closing a container).
+    Token,
+    /// Inside `i...e`.
+    Int,
+    /// Reading the decimal length prefix of a string.
+    Length { len: usize, digits: usize },
+    /// Skipping the payload of a string.
+    Payload { remaining: usize },
+}
+
+/// Incremental decoder for a stream of concatenated bencodex values.
+///
+/// Bytes are pushed with [`feed`](StreamDecoder::feed) as they arrive and
+/// complete values are taken with [`next_value`](StreamDecoder::next_value). Each byte is
+/// scanned once; the depth and string-length limits of the config are checked
+/// as the bytes come in, so an oversized or over-nested value is rejected
+/// before it has been buffered in full. A value is only handed to serde once
+/// all of its bytes are present, and then with the same config.
+pub struct StreamDecoder {
+    config: DecoderConfig,
+    buf: Vec<u8>,
+    scanned: usize,
+    scan: Scan,
+    depth: usize,
+    /// Ends of the complete values sitting at the front of `buf`.
+    complete: VecDeque<usize>,
+}
+
+impl StreamDecoder {
+    pub fn new(config: DecoderConfig) -> Self {
+        StreamDecoder {
+            config,
+            buf: Vec::new(),
+            scanned: 0,
+            scan: Scan::Token,
+            depth: 0,
+            complete: VecDeque::new(),
+        }
+    }
+
+    /// Bytes received but not yet returned as part of a value.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        self.buf.extend_from_slice(chunk);
+        while self.scanned < self.buf.len() {
+            let byte = self.buf[self.scanned];
+            self.scanned += 1;
+            self.step(byte)?;
+        }
+        let pending = self.buf.len() - self.complete.back().copied().unwrap_or(0);
+        if pending > self.config.max_total_alloc {
+            return Err(Error::LimitExceeded(format!(
+                "incomplete value exceeds {} bytes",
+                self.config.max_total_alloc
+            )));
+        }
+        Ok(())
+    }
+
+    /// Decode the next complete value, or `None` if more bytes are needed.
+    pub fn next_value<T: DeserializeOwned>(&mut self) -> Result<Option<T>, Error> {
+        let Some(end) = self.complete.pop_front() else {
+            return Ok(None);
+        };
+        let value = from_slice_with(&self.buf[..end], &self.config)?;
+        self.buf.drain(..end);
+        self.scanned -= end;
+        for e in self.complete.iter_mut() {
+            *e -= end;
+        }
+        Ok(Some(value))
+    }
+
+    fn value_finished(&mut self) {
+        self.scan = Scan::Token;
+        if self.depth == 0 {
+            self.complete.push_back(self.scanned);
+        }
+    }
+
+    fn step(&mut self, byte: u8) -> Result<(), Error> {
+        match self.scan {
+            Scan::Token => match byte {
+                b'n' | b't' | b'f' => self.value_finished(),
+                b'i' => self.scan = Scan::Int,
+                b'l' | b'd' => {
+                    if self.depth >= self.config.max_depth {
+                        return Err(Error::LimitExceeded(format!(
+                            "nesting deeper than {}",
+                            self.config.max_depth
+                        )));
+                    }
+                    self.depth += 1;
+                }
+                b'e' if self.depth > 0 => {
+                    self.depth -= 1;
+                    self.value_finished();
+                }
+                b'u' => self.scan = Scan::Length { len: 0, digits: 0 },
+                b'0'..=b'9' => {
+                    self.scan = Scan::Length {
+                        len: (byte - b'0') as usize,
+                        digits: 1,
+                    }
+                }
+                other => {
+                    return Err(Error::InvalidData(format!(
+                        "unexpected byte: 0x{other:02x}"
+                    )))
+                }
+            },
+            Scan::Int => match byte {
+                b'e' => self.value_finished(),
+                b'0'..=b'9' | b'-' | b'+' => {}
+                other => {
+                    return Err(Error::InvalidData(format!(
+                        "unexpected byte in integer: 0x{other:02x}"
+                    )))
+                }
+            },
+            Scan::Length { len, digits } => match byte {
+                b'0'..=b'9' => {
+                    let len = len
+                        .checked_mul(10)
+                        .and_then(|l| l.checked_add((byte - b'0') as usize))
+                        .filter(|l| *l <= self.config.max_bytestring_len)
+                        .ok_or_else(|| {
+                            Error::LimitExceeded(format!(
+                                "bytestring exceeds {}",
+                                self.config.max_bytestring_len
+                            ))
+                        })?;
+                    self.scan = Scan::Length {
+                        len,
+                        digits: digits + 1,
+                    };
+                }
+                b':' if digits > 0 => {
+                    if len == 0 {
+                        self.value_finished();
+                    } else {
+                        self.scan = Scan::Payload { remaining: len };
+                    }
+                }
+                other => {
+                    return Err(Error::InvalidData(format!(
+                        "unexpected byte in bytestring length: 0x{other:02x}"
+                    )))
+                }
+            },
+            Scan::Payload { remaining } => {
+                // Skip the rest of the payload in one go.
+                let available = self.buf.len() - self.scanned;
+                let take = (remaining - 1).min(available);
+                self.scanned += take;
+                if remaining - 1 == take {
+                    self.value_finished();
+                } else {
+                    self.scan = Scan::Payload {
+                        remaining: remaining - 1 - take,
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -295,3 +295,393 @@ fn tuple_variant_round_trip() {
     let decoded: Msg = from_slice(&encoded).unwrap();
     assert_eq!(decoded, val);
 }
+
+// --- Bounded, canonical and streaming decoding ---
+
+use crate::{from_slice_with, DecoderConfig, Error, StreamDecoder};
+
+const TIGHT: DecoderConfig = DecoderConfig::bounded(4, 8, 16, 1024);
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Simple {
+    x: u32,
+    y: String,
+}
+
+#[test]
+fn bounded_accepts_values_within_limits() {
+    let v = vec![vec![1u32, 2], vec![3]];
+    let encoded = to_vec(&v).unwrap();
+    assert_eq!(
+        from_slice_with::<Vec<Vec<u32>>>(&encoded, &TIGHT.strict()).unwrap(),
+        v
+    );
+}
+
+#[test]
+fn bounded_rejects_deep_nesting() {
+    let encoded = b"lllllnneeeee";
+    assert!(from_slice::<serde::de::IgnoredAny>(encoded).is_ok());
+    assert!(matches!(
+        from_slice_with::<serde::de::IgnoredAny>(encoded, &TIGHT),
+        Err(Error::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn bounded_rejects_long_collections_and_strings() {
+    let long_list = to_vec(&vec![1u32; 9]).unwrap();
+    assert!(matches!(
+        from_slice_with::<Vec<u32>>(&long_list, &TIGHT),
+        Err(Error::LimitExceeded(_))
+    ));
+
+    let long_bytes = to_vec(&serde_bytes::ByteBuf::from(vec![7u8; 17])).unwrap();
+    assert!(matches!(
+        from_slice_with::<serde_bytes::ByteBuf>(&long_bytes, &TIGHT),
+        Err(Error::LimitExceeded(_))
+    ));
+
+    // A huge declared length fails on the prefix, without overflow.
+    assert!(matches!(
+        from_slice_with::<serde_bytes::ByteBuf>(b"99999999999999999999999:x", &TIGHT),
+        Err(Error::InvalidData(_)) | Err(Error::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn bounded_rejects_total_allocation() {
+    let config = DecoderConfig::bounded(4, 100, 16, 3 * DecoderConfig::NODE_COST);
+    let encoded = to_vec(&vec![1u32, 2, 3, 4]).unwrap();
+    assert!(matches!(
+        from_slice_with::<Vec<u32>>(&encoded, &config),
+        Err(Error::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn strict_rejects_non_canonical_encodings() {
+    let strict = DecoderConfig::UNBOUNDED.strict();
+    // Unsorted keys.
+    assert!(from_slice::<Simple>(b"du1:yu2:hiu1:xi1ee").is_ok());
+    assert!(from_slice_with::<Simple>(b"du1:yu2:hiu1:xi1ee", &strict).is_err());
+    // Duplicate keys.
+    assert!(from_slice_with::<std::collections::BTreeMap<String, u32>>(
+        b"du1:ai1eu1:ai2ee",
+        &strict
+    )
+    .is_err());
+    // Byte keys sort before unicode keys.
+    assert!(from_slice_with::<serde::de::IgnoredAny>(b"d1:ai1eu1:ai2ee", &strict).is_ok());
+    assert!(from_slice_with::<serde::de::IgnoredAny>(b"du1:ai2e1:ai1ee", &strict).is_err());
+    // Non-minimal integers and lengths.
+    assert!(from_slice_with::<u32>(b"i+5e", &strict).is_err());
+    assert!(from_slice_with::<u32>(b"i05e", &strict).is_err());
+    assert!(from_slice_with::<serde_bytes::ByteBuf>(b"02:ab", &strict).is_err());
+    assert!(from_slice::<serde_bytes::ByteBuf>(b"02:ab").is_ok());
+    // Whatever we serialize is canonical.
+    let encoded = to_vec(&Simple {
+        x: 1,
+        y: "hi".into(),
+    })
+    .unwrap();
+    assert!(from_slice_with::<Simple>(&encoded, &strict).is_ok());
+}
+
+#[test]
+fn stream_decoder_yields_values_as_they_complete() {
+    let a = to_vec(&Simple {
+        x: 1,
+        y: "first".into(),
+    })
+    .unwrap();
+    let b = to_vec(&Simple {
+        x: 2,
+        y: "second".into(),
+    })
+    .unwrap();
+    let mut stream: Vec<u8> = a.clone();
+    stream.extend_from_slice(&b);
+
+    let mut decoder = StreamDecoder::new(DecoderConfig::bounded(8, 64, 64, 4096).strict());
+    let mut out = Vec::new();
+    for byte in stream.iter() {
+        decoder.feed(&[*byte]).unwrap();
+        while let Some(v) = decoder.next_value::<Simple>().unwrap() {
+            out.push(v);
+        }
+    }
+    assert_eq!(out.len(), 2);
+    assert_eq!(out[1].y, "second");
+    assert_eq!(decoder.buffered(), 0);
+}
+
+#[test]
+fn stream_decoder_rejects_oversized_values_early() {
+    let mut decoder = StreamDecoder::new(TIGHT);
+    // Rejected on the length prefix alone.
+    assert!(matches!(
+        decoder.feed(b"100000:"),
+        Err(Error::LimitExceeded(_))
+    ));
+
+    let mut decoder = StreamDecoder::new(TIGHT);
+    assert!(matches!(
+        decoder.feed(b"lllll"),
+        Err(Error::LimitExceeded(_))
+    ));
+}
+
+// --- Schema evolution derive ---
+
+use crate::schema::HasSchema;
+use crate::UnknownFields;
+
+mod v1 {
+    #[derive(crate::Schema, Debug, PartialEq)]
+    pub struct Record {
+        pub id: u64,
+        #[bencodex(tag = "amt")]
+        pub amount: u64,
+        #[bencodex(deprecated)]
+        pub legacy: bool,
+    }
+}
+
+mod v2 {
+    #[derive(crate::Schema, Debug, PartialEq)]
+    #[bencodex(version = 2)]
+    pub struct Record {
+        pub id: u64,
+        #[bencodex(tag = "amt")]
+        pub total: u64,
+        #[bencodex(since = 2)]
+        pub memo: Option<String>,
+        #[bencodex(unknown)]
+        pub unknown: crate::UnknownFields,
+    }
+}
+
+#[test]
+fn schema_derive_matches_serde_encoding() {
+    #[derive(Serialize)]
+    struct Plain {
+        id: u64,
+        amt: u64,
+        legacy: bool,
+    }
+    let record = v1::Record {
+        id: 1,
+        amount: 5,
+        legacy: true,
+    };
+    let plain = Plain {
+        id: 1,
+        amt: 5,
+        legacy: true,
+    };
+    assert_eq!(to_vec(&record).unwrap(), to_vec(&plain).unwrap());
+}
+
+#[test]
+fn schema_derive_reads_older_and_newer_writers() {
+    let old = to_vec(&v1::Record {
+        id: 1,
+        amount: 5,
+        legacy: true,
+    })
+    .unwrap();
+    let upgraded: v2::Record = from_slice(&old).unwrap();
+    assert_eq!(upgraded.total, 5);
+    assert_eq!(upgraded.memo, None);
+    // v2 dropped the deprecated field; it rides along as an unknown field so
+    // a v1 reader of the re-encoded record still sees it.
+    assert!(upgraded
+        .unknown
+        .0
+        .contains_key(&crate::Key::Text("legacy".into())));
+    let reencoded = to_vec(&upgraded).unwrap();
+    assert!(from_slice::<v1::Record>(&reencoded).unwrap().legacy);
+
+    let new = to_vec(&v2::Record {
+        id: 2,
+        total: 7,
+        memo: Some("hi".into()),
+        unknown: UnknownFields::default(),
+    })
+    .unwrap();
+    let downgraded: v1::Record = from_slice(&new).unwrap();
+    assert_eq!(
+        downgraded,
+        v1::Record {
+            id: 2,
+            amount: 7,
+            legacy: false
+        }
+    );
+
+    assert!(from_slice::<v2::Record>(b"du2:idi1ee").is_err());
+}
+
+#[test]
+fn schema_descriptions_flag_incompatibilities() {
+    let v1 = v1::Record::schema();
+    let v2 = v2::Record::schema();
+    assert_eq!(v2.version, 2);
+    assert!(v2.preserves_unknown);
+    assert!(v2
+        .field("memo")
+        .is_some_and(|f| !f.required && f.since == 2));
+    assert!(v1.field("legacy").is_some_and(|f| f.deprecated));
+    assert_eq!(v1.check_compatible(&v2), Ok(()));
+
+    let mut retyped = v2.clone();
+    retyped.fields[0].ty = "String".into();
+    let problems = v1.check_compatible(&retyped).unwrap_err();
+    assert!(problems[0].contains("\"id\""), "{problems:?}");
+
+    let mut dropped = v2.clone();
+    dropped.fields.retain(|f| f.tag != "amt");
+    let problems = v1.check_compatible(&dropped).unwrap_err();
+    assert!(problems[0].contains("required"), "{problems:?}");
+}
+
+#[derive(crate::Schema, Debug, PartialEq)]
+struct Renamed {
+    #[serde(rename = "n")]
+    number: u64,
+    #[serde(default)]
+    note: Option<String>,
+    #[serde(skip)]
+    cache: Vec<u8>,
+}
+
+#[test]
+fn schema_derive_honours_serde_field_attributes() {
+    #[derive(Serialize)]
+    struct Plain {
+        n: u64,
+        note: Option<String>,
+    }
+    let value = Renamed {
+        number: 3,
+        note: None,
+        cache: vec![1],
+    };
+    let bytes = to_vec(&value).unwrap();
+    assert_eq!(bytes, to_vec(&Plain { n: 3, note: None }).unwrap());
+    let decoded: Renamed = from_slice(b"du1:ni3ee").unwrap();
+    assert_eq!(
+        decoded,
+        Renamed {
+            number: 3,
+            note: None,
+            cache: vec![]
+        }
+    );
+
+    let schema = Renamed::schema();
+    assert!(schema.field("n").is_some_and(|f| f.required));
+    assert!(schema.field("note").is_some_and(|f| !f.required));
+    assert!(schema.field("cache").is_none());
+}
+
+mod wire_v1 {
+    #[derive(crate::Schema, Debug, Clone, PartialEq)]
+    pub enum Message {
+        Ping,
+        Data(Vec<u8>),
+        Pair(u64, String),
+        Move {
+            id: u64,
+            #[serde(rename = "mv")]
+            bytes: Vec<u8>,
+        },
+    }
+}
+
+mod wire_v2 {
+    #[derive(crate::Schema, Debug, Clone, PartialEq)]
+    #[bencodex(version = 2)]
+    pub enum Message {
+        Ping,
+        #[bencodex(tag = "Data")]
+        Payload(Vec<u8>),
+        Pair(u64, String),
+        Move {
+            id: u64,
+            #[serde(rename = "mv")]
+            bytes: Vec<u8>,
+            #[bencodex(since = 2)]
+            fee: u64,
+        },
+        #[bencodex(since = 2)]
+        Stop,
+    }
+}
+
+#[test]
+fn schema_derive_enum_matches_serde_encoding() {
+    #[derive(Serialize)]
+    enum Plain {
+        Ping,
+        Data(Vec<u8>),
+        Pair(u64, String),
+        Move { id: u64, mv: Vec<u8> },
+    }
+    let cases = [
+        (wire_v1::Message::Ping, Plain::Ping),
+        (wire_v1::Message::Data(vec![1, 2]), Plain::Data(vec![1, 2])),
+        (
+            wire_v1::Message::Pair(4, "x".into()),
+            Plain::Pair(4, "x".into()),
+        ),
+        (
+            wire_v1::Message::Move {
+                id: 9,
+                bytes: vec![3],
+            },
+            Plain::Move { id: 9, mv: vec![3] },
+        ),
+    ];
+    for (derived, plain) in cases {
+        let bytes = to_vec(&derived).unwrap();
+        assert_eq!(bytes, to_vec(&plain).unwrap());
+        assert_eq!(from_slice::<wire_v1::Message>(&bytes).unwrap(), derived);
+    }
+
+    let moved = to_vec(&wire_v1::Message::Move {
+        id: 9,
+        bytes: vec![3],
+    })
+    .unwrap();
+    assert_eq!(
+        from_slice::<wire_v2::Message>(&moved).unwrap(),
+        wire_v2::Message::Move {
+            id: 9,
+            bytes: vec![3],
+            fee: 0
+        }
+    );
+    let stop = to_vec(&wire_v2::Message::Stop).unwrap();
+    assert!(from_slice::<wire_v1::Message>(&stop).is_err());
+}
+
+#[test]
+fn enum_schemas_compare_shared_variants() {
+    let v1 = wire_v1::Message::schema();
+    let v2 = wire_v2::Message::schema();
+    assert_eq!(v1.check_compatible(&v2), Ok(()));
+    assert_eq!(v2.check_compatible(&v1), Ok(()));
+    assert_eq!(v1.unknown_variants(&v2), vec!["Stop"]);
+    assert!(v2.variant("Stop").is_some_and(|v| v.since == 2));
+
+    let mut retyped = v2.clone();
+    retyped.variants[1].fields[0].ty = "String".into();
+    let problems = v1.check_compatible(&retyped).unwrap_err();
+    assert!(problems[0].contains("Message::Data"), "{problems:?}");
+
+    let mut reshaped = v2.clone();
+    reshaped.variants[0].kind = crate::schema::VariantKind::Struct;
+    assert!(v1.check_compatible(&reshaped).is_err());
+}
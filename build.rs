@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use clvmr::allocator::Allocator;
 use toml::{Table, Value};
@@ -9,29 +10,34 @@ use chialisp::classic::clvm_tools::clvmc::CompileError;
 use chialisp::classic::clvm_tools::comp_input::RunAndCompileInputData;
 use chialisp::classic::platform::argparse::ArgumentValue;
 use chialisp::compiler::comptypes::CompileErr;
+use chialisp::compiler::sexp::SExp;
 use chialisp::compiler::srcloc::Srcloc;
+use clvm_utils::tree_hash_from_bytes;
 
-fn do_compile(title: &str, filename: &str) -> Result<(), CompileError> {
+/// Compile the copy of a source at `target`, with includes resolved inside
+/// `root`. Returns the names the module exports, from the compiler's summary.
+fn do_compile(title: &str, root: &Path, target: &Path) -> Result<Vec<String>, CompileError> {
     let mut allocator = Allocator::new();
     let mut arguments: HashMap<String, ArgumentValue> = HashMap::new();
     arguments.insert(
         "include".to_string(),
         ArgumentValue::ArgArray(vec![
-            ArgumentValue::ArgString(None, "clsp".to_string()),
-            ArgumentValue::ArgString(None, ".".to_string()),
+            ArgumentValue::ArgString(None, root.join("clsp").to_string_lossy().to_string()),
+            ArgumentValue::ArgString(None, root.to_string_lossy().to_string()),
         ]),
     );
 
-    let file_content = fs::read_to_string(filename).map_err(|e| {
+    let target = target.to_string_lossy().to_string();
+    let file_content = fs::read_to_string(&target).map_err(|e| {
         CompileErr(
-            Srcloc::start(filename),
-            format!("failed to read {filename}: {e:?}"),
+            Srcloc::start(&target),
+            format!("failed to read {target}: {e:?}"),
         )
     })?;
 
     arguments.insert(
         "path_or_code".to_string(),
-        ArgumentValue::ArgString(Some(filename.to_string()), file_content),
+        ArgumentValue::ArgString(Some(target.clone()), file_content),
     );
 
     let parsed = RunAndCompileInputData::new(&mut allocator, &arguments).map_err(|e| {
@@ -42,12 +48,175 @@ fn do_compile(title: &str, filename: &str) -> Result<(), CompileError> {
     })?;
     let mut symbol_table = HashMap::new();
 
-    parsed.compile_modern(&mut allocator, &mut symbol_table)?;
+    let summary = parsed.compile_modern(&mut allocator, &mut symbol_table)?;
 
-    Ok(())
+    write_symbols(Path::new(&target), &symbol_table);
+
+    // The summary lists `(name . hash)` for each program written.
+    let mut exports = Vec::new();
+    let mut rest = summary.as_ref();
+    while let SExp::Cons(_, first, tail) = rest {
+        if let SExp::Cons(_, name, _) = first.as_ref() {
+            if let SExp::Atom(_, name) = name.as_ref() {
+                exports.push(String::from_utf8_lossy(name).to_string());
+            }
+        }
+        rest = tail.as_ref();
+    }
+    Ok(exports)
+}
+
+/// Copy the chialisp sources under `from` to `to`, leaving build outputs
+/// behind.
+fn copy_sources(from: &Path, to: &Path) {
+    fs::create_dir_all(to).expect("create source copy dir");
+    for entry in fs::read_dir(from).expect("read chialisp sources").flatten() {
+        let path = entry.path();
+        let dest = to.join(entry.file_name());
+        if path.is_dir() {
+            copy_sources(&path, &dest);
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("clsp" | "clinc")
+        ) {
+            fs::copy(&path, &dest).expect("copy chialisp source");
+        }
+    }
+}
+
+/// Compile the entry `key` in a private copy of the sources under `$OUT_DIR`.
+/// The compiler writes outputs for imported modules next to their source and
+/// reuses them later, so compiling in place would both touch the source tree
+/// and let one entry pick up another's stale outputs. Returns this entry's
+/// `.hex` files by export name (empty for a single program).
+fn compile_entry(
+    out_dir: &Path,
+    key: &str,
+    source: &str,
+) -> Result<Vec<(String, PathBuf)>, CompileError> {
+    let target = compile_target(out_dir, key, source);
+    let root = out_dir.join("chialisp").join(key);
+    let _ = fs::remove_dir_all(&root);
+    copy_sources(Path::new("clsp"), &root.join("clsp"));
+    let exports = do_compile(key, &root, &target)?;
+
+    let stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let main = target.with_extension("hex");
+    let mut outputs: Vec<(String, PathBuf)> = exports
+        .into_iter()
+        .map(|export| {
+            if export == "program" && main.exists() {
+                (String::new(), main.clone())
+            } else {
+                let path = target.with_file_name(format!("{stem}_{export}.hex"));
+                (export, path)
+            }
+        })
+        .collect();
+    outputs.sort();
+    for (_, path) in outputs.iter() {
+        if !path.exists() {
+            panic!("puzzle registry: {key} did not write {}", path.display());
+        }
+    }
+    Ok(outputs)
+}
+
+/// The `.sym` file kept next to a compiled entry's `.hex` outputs.
+fn symbol_file(target: &Path) -> PathBuf {
+    target.with_extension("sym")
+}
+
+/// Keep the compiler's function symbols (tree hash, qualified name and
+/// argument list) so tracing can name the functions on a failing call stack.
+fn write_symbols(target: &Path, symbol_table: &HashMap<String, String>) {
+    let mut lines: Vec<String> = symbol_table
+        .iter()
+        .filter(|(hash, _)| hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+        .filter_map(|(hash, name)| {
+            symbol_table
+                .get(&format!("{hash}_arguments"))
+                .map(|args| format!("{hash}\t{name}\t{args}"))
+        })
+        .collect();
+    lines.sort();
+    lines.push(String::new());
+    fs::write(symbol_file(target), lines.join("\n")).expect("write symbol file");
+}
+
+/// Find the `(defun NAME ...)` (or inline/macro) defining `name`. Qualified
+/// names carry their module path; unqualified ones live in the entry source.
+fn locate_definition(name: &str, entry_source: &str) -> Option<(String, usize)> {
+    let (candidates, function) = match name.rsplit_once('.') {
+        Some((module, function)) => {
+            let base = format!("clsp/{}", module.replace('.', "/"));
+            (
+                vec![format!("{base}.clinc"), format!("{base}.clsp")],
+                function,
+            )
+        }
+        None => (vec![entry_source.to_string()], name),
+    };
+    for file in candidates {
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for (index, line) in text.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            let head = tokens.next().unwrap_or("");
+            if matches!(head, "(defun" | "(defun-inline" | "(defmacro")
+                && tokens.next() == Some(function)
+            {
+                return Some((file, index + 1));
+            }
+        }
+    }
+    None
+}
+
+/// Merge the `.sym` files of all entries into `$OUT_DIR/puzzle_symbols.rs`,
+/// sorted by hash for binary search.
+fn generate_symbols(entries: &[CompiledEntry], out_dir: &Path) {
+    let mut symbols: BTreeMap<String, String> = BTreeMap::new();
+    for CompiledEntry { key, source, .. } in entries.iter() {
+        let Ok(text) = fs::read_to_string(symbol_file(&compile_target(out_dir, key, source)))
+        else {
+            continue;
+        };
+        for line in text.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(hash), Some(name), Some(arguments)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if symbols.contains_key(hash) {
+                continue;
+            }
+            let Ok(hash_bytes) = hex::decode(hash) else {
+                continue;
+            };
+            let (file, line) = locate_definition(name, source).unwrap_or_default();
+            let hash_bytes: Vec<String> = hash_bytes.iter().map(|b| b.to_string()).collect();
+            symbols.insert(
+                hash.to_string(),
+                format!(
+                    "    FunctionSymbol {{ hash: [{}], name: {name:?}, arguments: {arguments:?}, file: {file:?}, line: {line} }},",
+                    hash_bytes.join(", ")
+                ),
+            );
+        }
+    }
+    let mut code = String::from("pub static FUNCTION_SYMBOLS: &[FunctionSymbol] = &[\n");
+    for line in symbols.values() {
+        code.push_str(line);
+        code.push('\n');
+    }
+    code.push_str("];\n");
+    fs::write(out_dir.join("puzzle_symbols.rs"), code).expect("write puzzle symbols");
 }
 
-fn compile_chialisp() -> Result<(), CompileError> {
+fn compile_entries() -> Result<Vec<(String, String)>, CompileError> {
     let srcloc = Srcloc::start("chialisp.toml");
     let chialisp_toml_text = fs::read_to_string("chialisp.toml").map_err(|e| {
         CompileError::Modern(
@@ -60,15 +229,157 @@ fn compile_chialisp() -> Result<(), CompileError> {
         .parse::<Table>()
         .map_err(|e| CompileError::Modern(srcloc, format!("Error parsing chialisp.toml: {e:?}")))?;
 
+    let mut entries = Vec::new();
     if let Some(Value::Table(t)) = chialisp_toml.get("compile") {
         for (k, v) in t.iter() {
             if let Value::String(s) = v {
-                do_compile(k, s)?;
+                entries.push((k.clone(), s.clone()));
             }
         }
     }
+    Ok(entries)
+}
+
+/// A `chialisp.toml` entry and the `.hex` files it compiled to, by export
+/// name.
+struct CompiledEntry {
+    key: String,
+    source: String,
+    outputs: Vec<(String, PathBuf)>,
+}
+
+/// Where the copy of `source` compiled for the entry `key` lives.
+fn compile_target(out_dir: &Path, key: &str, source: &str) -> PathBuf {
+    out_dir.join("chialisp").join(key).join(source)
+}
+
+/// Drop `_hash` companions, which are checked against their program rather
+/// than embedded.
+fn embedded_outputs(outputs: &[(String, PathBuf)]) -> Vec<(String, PathBuf)> {
+    outputs
+        .iter()
+        .filter(|(export, _)| {
+            !export
+                .strip_suffix("_hash")
+                .is_some_and(|program| outputs.iter().any(|(e, _)| e == program))
+        })
+        .cloned()
+        .collect()
+}
 
-    Ok(())
+fn read_hex_file(path: &Path) -> Vec<u8> {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("puzzle registry: cannot read {}: {e}", path.display()));
+    hex::decode(text.trim())
+        .unwrap_or_else(|e| panic!("puzzle registry: bad hex in {}: {e}", path.display()))
+}
+
+fn const_name(key: &str, export: &str) -> String {
+    let mut name = key.to_string();
+    if !export.is_empty() {
+        name.push('_');
+        name.push_str(export);
+    }
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Embed every compiled `chialisp.toml` entry and write the registry source
+/// that `src/common/puzzle_registry.rs` includes.
+fn generate_registry(entries: &[CompiledEntry], out_dir: &Path) {
+    let blob_dir = out_dir.join("puzzles");
+    fs::create_dir_all(&blob_dir).expect("create puzzle blob dir");
+
+    let mut code = String::new();
+    let mut all = Vec::new();
+    for CompiledEntry {
+        key,
+        source,
+        outputs,
+    } in entries.iter()
+    {
+        let outputs = embedded_outputs(outputs);
+        if outputs.is_empty() {
+            panic!("puzzle registry: {key} ({source}) produced no .hex output");
+        }
+        for (export, path) in outputs {
+            let bytes = read_hex_file(&path);
+            let hash = tree_hash_from_bytes(&bytes).unwrap_or_else(|e| {
+                panic!(
+                    "puzzle registry: {} is not valid CLVM: {e:?}",
+                    path.display()
+                )
+            });
+            let companion = path.with_file_name(format!(
+                "{}_hash.hex",
+                path.file_stem().and_then(|s| s.to_str()).unwrap_or("")
+            ));
+            if companion.exists() {
+                let expected = read_hex_file(&companion);
+                // The companion is a serialized 32-byte atom: 0xa0 || hash.
+                if expected.get(1..) != Some(hash.as_ref()) {
+                    panic!(
+                        "puzzle registry: {} does not hash to {}",
+                        path.display(),
+                        companion.display()
+                    );
+                }
+            }
+
+            let ident = const_name(key, &export);
+            let blob = blob_dir.join(format!("{ident}.clvm"));
+            fs::write(&blob, &bytes).expect("write puzzle blob");
+            let name = if export.is_empty() {
+                key.clone()
+            } else {
+                format!("{key}:{export}")
+            };
+            // Named by where `tools/build-chialisp.sh` puts the file, which
+            // is what code loading by path asks for.
+            let source_dir = Path::new(source).parent().unwrap_or(Path::new(""));
+            let rel_path = source_dir
+                .join(path.file_name().expect("output file name"))
+                .to_string_lossy()
+                .replace('\\', "/");
+            let hash_bytes: Vec<String> = hash.as_ref().iter().map(|b| b.to_string()).collect();
+            writeln!(
+                code,
+                "pub const {ident}: EmbeddedPuzzle = EmbeddedPuzzle {{\n    \
+                 name: {name:?},\n    \
+                 source: {source:?},\n    \
+                 path: {rel_path:?},\n    \
+                 bytes: include_bytes!({blob:?}),\n    \
+                 tree_hash: [{}],\n}};",
+                hash_bytes.join(", ")
+            )
+            .unwrap();
+            all.push(ident);
+        }
+    }
+    writeln!(code, "\npub static ALL_PUZZLES: &[&EmbeddedPuzzle] = &[").unwrap();
+    for ident in all.iter() {
+        writeln!(code, "    &{ident},").unwrap();
+    }
+    writeln!(code, "];").unwrap();
+    fs::write(out_dir.join("puzzle_registry.rs"), code).expect("write puzzle registry");
+    generate_symbols(entries, out_dir);
+}
+
+/// Copy an entry's `.hex` outputs next to its source, for tools that load
+/// puzzles by path.
+fn install_outputs(outputs: &[(String, PathBuf)], source: &str) {
+    let source_dir = Path::new(source).parent().unwrap_or(Path::new("."));
+    for (_, path) in outputs.iter() {
+        let name = path.file_name().expect("output file name");
+        fs::copy(path, source_dir.join(name)).expect("install compiled puzzle");
+    }
 }
 
 fn emit_rerun_directives(dir: &Path) {
@@ -91,9 +402,24 @@ fn main() {
     println!("cargo:rerun-if-changed=chialisp.toml");
     println!("cargo:rerun-if-env-changed=CHIALISP_COMPILE");
 
-    if std::env::var("CHIALISP_COMPILE").is_ok() {
-        if let Err(e) = compile_chialisp() {
-            panic!("error compiling chialisp: {e:?}");
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR"));
+    let entries =
+        compile_entries().unwrap_or_else(|e| panic!("error reading chialisp.toml: {e:?}"));
+    // Everything is compiled into `$OUT_DIR`; the source tree only gets the
+    // `.hex` files when `tools/build-chialisp.sh` asks for them.
+    let install = std::env::var("CHIALISP_COMPILE").is_ok();
+    let mut compiled = Vec::new();
+    for (title, source) in entries.into_iter() {
+        let outputs = compile_entry(&out_dir, &title, &source)
+            .unwrap_or_else(|e| panic!("error compiling chialisp: {e:?}"));
+        if install {
+            install_outputs(&outputs, &source);
         }
+        compiled.push(CompiledEntry {
+            key: title,
+            source,
+            outputs,
+        });
     }
+    generate_registry(&compiled, &out_dir);
 }
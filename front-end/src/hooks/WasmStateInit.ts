@@ -7,14 +7,9 @@ let cg: WasmConnection | undefined = undefined;
 let logInitialized = false;
 
 /** Manual mirror of native startup loads (game_collection + channel/referee). Not auto-traced. */
-export const PRESET_FILES = [
-  'clsp/unroll/unroll_puzzle_state_channel_unrolling.hex',
-  'clsp/referee/onchain/referee.hex',
-  'clsp/games/calpoker/calpoker_include_calpoker_factory.hex',
-  'clsp/games/spacepoker/spacepoker_include_spacepoker_factory.hex',
-  'clsp/games/krunk/krunk_include_krunk_factory.hex',
-  'clsp/games/krunk/krunk_signed_dict_tree.dat',
-];
+// Compiled puzzles are embedded in the wasm module; only data files that are
+// not built from chialisp still need to be fetched.
+export const PRESET_FILES = ['clsp/games/krunk/krunk_signed_dict_tree.dat'];
 
 const WASM_URL = 'chia_gaming_wasm_bg.wasm';
 
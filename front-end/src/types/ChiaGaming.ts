@@ -79,10 +79,30 @@ export interface CoinsetOrgBlockSpend {
   solution: string;
 }
 
+/// Operator fee taken from each game's pot at settlement. Set exactly one of
+/// `basis_points` and `flat` (mojos per game).
+export interface OperatorFeeParams {
+  puzzle_hash: string;
+  basis_points?: number;
+  flat?: bigint;
+}
+
 export interface ProposeGameParams {
   game_type: string;
   timeout: bigint;
   parameters: Program | null;
+  fee?: OperatorFeeParams;
+}
+
+/// Terms of a match: the same hand proposed repeatedly. Both players register
+/// the same terms; `stop_loss` (mojos) is local to each side.
+export interface MatchTermsParams {
+  game_type: string;
+  timeout: bigint;
+  hands?: number;
+  stop_loss?: bigint;
+  alternate_proposer?: boolean;
+  fee?: OperatorFeeParams;
 }
 
 interface IChiaIdentity {
@@ -123,7 +143,10 @@ type WasmNotificationTag =
   | 'ProposalCancelled'
   | 'InsufficientBalance'
   | 'MoveRejected'
-  | 'ActionFailed';
+  | 'ActionFailed'
+  | 'MatchHandStarted'
+  | 'MatchHandSettled'
+  | 'MatchEnded';
 
 export type GameStatusState =
   | 'my-turn'
@@ -158,6 +181,7 @@ export interface GameSettledPayload {
   outcome: string;
   our_share: unknown;
   coin_id?: unknown;
+  operator_fee?: unknown;
 }
 
 export type ChannelStatus =
@@ -211,6 +235,8 @@ export interface MoveRejectedPayload {
   id: bigint | number | string;
   tag: string;
   message: string;
+  /** Chialisp call trace, present when CLVM tracing is enabled. */
+  trace?: string;
 }
 
 export interface ActionFailedPayload {
@@ -251,6 +277,26 @@ interface GameSessionCreateConfig {
   channel_timeout: number;
   unroll_timeout: number;
   reward_puzzle_hash: string;
+  liveness?: LivenessPolicy;
+  peer_limits?: PeerLimits;
+}
+
+/// Block thresholds after which Rust warns about, then goes on chain against,
+/// an unresponsive peer. Omitted thresholds are disabled.
+interface LivenessPolicy {
+  stall_warning_blocks?: number;
+  stall_go_on_chain_blocks?: number;
+  potato_warning_blocks?: number;
+  potato_go_on_chain_blocks?: number;
+}
+
+/// Budgets on what the peer may send; exceeding one sends the channel on
+/// chain. Omitted fields keep Rust's defaults.
+interface PeerLimits {
+  max_batch_actions?: number;
+  max_game_message_bytes?: number;
+  max_message_clvm_cost?: number;
+  max_queued_messages?: number;
 }
 
 /// A labeled coin id (hex) surfaced in the dashboard for explorer lookup.
@@ -317,6 +363,18 @@ export interface WasmConnection {
     readable: Uint8Array,
   ) => WasmResult | undefined;
   cancel_proposal: (cid: number, game_id: string) => WasmResult | undefined;
+  start_match: (
+    cid: number,
+    terms: MatchTermsParams,
+    parameters: Uint8Array,
+  ) => WasmResult | undefined;
+  accept_match: (
+    cid: number,
+    game_id: string,
+    terms: MatchTermsParams,
+    parameters: Uint8Array,
+  ) => WasmResult | undefined;
+  stop_match: (cid: number) => WasmResult | undefined;
   make_move_with_entropy_for_testing: (
     cid: number,
     id: string,
@@ -383,6 +441,22 @@ export class ChiaGame {
     return this.wasm.cancel_proposal(this.session, game_id);
   }
 
+  start_match(terms: MatchTermsParams, parameters: Uint8Array): WasmResult | undefined {
+    return this.wasm.start_match(this.session, terms, parameters);
+  }
+
+  accept_match(
+    game_id: string,
+    terms: MatchTermsParams,
+    parameters: Uint8Array,
+  ): WasmResult | undefined {
+    return this.wasm.accept_match(this.session, game_id, terms, parameters);
+  }
+
+  stop_match(): WasmResult | undefined {
+    return this.wasm.stop_match(this.session);
+  }
+
   amount(): bigint {
     return BigInt(this.wasm.game_session_amount(this.session));
   }
@@ -0,0 +1,236 @@
+//! Game-result attestations: how a game settled, signed by both players.
+//!
+//! `GameSettled` only tells our own host what happened.  To give ratings
+//! services something they can check without trusting either client, each
+//! side builds the same [`GameResult`] from its own copy of a game when the
+//! game is accepted off-chain, signs it with its referee key once the game
+//! settles, and sends the signature to the peer as
+//! `PeerMessage::GameResultSignature`.  With both signatures in hand the
+//! session emits a [`GameResultAttestation`]
+//! (`GameSessionEvent::GameResultAttested`), which anyone can check with
+//! [`GameResultAttestation::verify`] knowing only the keys it names.
+//!
+//! The two signatures are kept apart rather than aggregated.  An aggregate
+//! over one message lets a player choose a key that cancels the other's and
+//! sign for both.
+//!
+//! An attestation shows that both keys agreed on the result, not who holds
+//! them: tying keys to players, and spotting someone playing themselves, is
+//! up to the service.  Games that end on chain get no attestation, since by
+//! then the peers no longer exchange messages, and neither do games with a
+//! peer that doesn't advertise `FEATURE_RESULT_ATTESTATION`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::{
+    Aggsig, Amount, Error, GameID, GameType, Hash, PrivateKey, PublicKey, PuzzleHash,
+};
+
+/// Which factory made a game, recorded when the game starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameOrigin {
+    pub game_type: GameType,
+    /// Tree hash of the factory program, as advertised in the handshake.
+    pub factory_hash: PuzzleHash,
+}
+
+/// The result of one game as both players see it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameResult {
+    pub game_id: GameID,
+    pub game_type: GameType,
+    pub factory_hash: PuzzleHash,
+    /// The players' referee keys, in ascending byte order.
+    pub players: [PublicKey; 2],
+    /// What each of `players` took from the game, in the same order.
+    pub payouts: [Amount; 2],
+    /// Puzzle hash of the game coin after the last move.  It commits to that
+    /// move and, through each move's validation info, to the states before
+    /// it.  The state a final move leaves isn't used: the two sides needn't
+    /// compute the same one.
+    pub final_state_hash: Hash,
+}
+
+impl GameResult {
+    /// `players` pairs each referee key with its payout, in any order.
+    pub fn new(
+        game_id: GameID,
+        origin: &GameOrigin,
+        mut players: [(PublicKey, Amount); 2],
+        final_state_hash: Hash,
+    ) -> Self {
+        players.sort_by_key(|(key, _)| key.bytes());
+        let [(first, first_payout), (second, second_payout)] = players;
+        GameResult {
+            game_id,
+            game_type: origin.game_type.clone(),
+            factory_hash: origin.factory_hash.clone(),
+            players: [first, second],
+            payouts: [first_payout, second_payout],
+            final_state_hash,
+        }
+    }
+
+    fn message(&self) -> Hash {
+        let mut bytes = b"game result".to_vec();
+        bytes.extend_from_slice(&self.game_id.0.to_be_bytes());
+        bytes.extend_from_slice(&(self.game_type.0.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.game_type.0);
+        bytes.extend_from_slice(self.factory_hash.bytes());
+        for player in &self.players {
+            bytes.extend_from_slice(&player.bytes());
+        }
+        for payout in &self.payouts {
+            bytes.extend_from_slice(&payout.to_u64().to_be_bytes());
+        }
+        bytes.extend_from_slice(self.final_state_hash.bytes());
+        Hash::new(&bytes)
+    }
+
+    pub fn sign(&self, referee_private_key: &PrivateKey) -> Aggsig {
+        referee_private_key.sign(self.message().bytes())
+    }
+}
+
+/// A [`GameResult`] with both players' signatures.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameResultAttestation {
+    pub result: GameResult,
+    /// One per entry of `result.players`, in the same order.
+    pub signatures: [Aggsig; 2],
+}
+
+impl GameResultAttestation {
+    /// Pair our signature with the peer's, checking both.
+    pub fn combine(
+        result: GameResult,
+        our_key: &PublicKey,
+        our_signature: Aggsig,
+        their_signature: Aggsig,
+    ) -> Result<Self, Error> {
+        let signatures = if result.players[0] == *our_key {
+            [our_signature, their_signature]
+        } else {
+            [their_signature, our_signature]
+        };
+        let attestation = GameResultAttestation { result, signatures };
+        if !attestation.verify() {
+            return Err(Error::StrErr(format!(
+                "bad result signature from peer for game {:?}",
+                attestation.result.game_id
+            )));
+        }
+        Ok(attestation)
+    }
+
+    /// True if the two players are distinct and each signed the result.
+    pub fn verify(&self) -> bool {
+        let message = self.result.message();
+        self.result.players[0] != self.result.players[1]
+            && self
+                .result
+                .players
+                .iter()
+                .zip(&self.signatures)
+                .all(|(player, signature)| signature.verify(player, message.bytes()))
+    }
+}
+
+/// Our half of one game's signature exchange.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct PendingAttestation {
+    /// Our record of the game, taken when it was accepted.
+    pub(crate) result: Option<GameResult>,
+    /// Ours, once the game settled and we sent it.
+    pub(crate) our_signature: Option<Aggsig>,
+    /// The peer's, which may arrive before our game settles.
+    pub(crate) their_signature: Option<Aggsig>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::common::standard_coin::private_to_public_key;
+
+    fn signed_result() -> (GameResult, [PrivateKey; 2]) {
+        let mut rng = ChaCha8Rng::from_seed([3; 32]);
+        let keys: [PrivateKey; 2] = [rng.random(), rng.random()];
+        let origin = GameOrigin {
+            game_type: GameType(b"calpoker".to_vec()),
+            factory_hash: PuzzleHash::from_bytes([7; 32]),
+        };
+        let result = GameResult::new(
+            GameID(5),
+            &origin,
+            [
+                (private_to_public_key(&keys[0]), Amount::new(150)),
+                (private_to_public_key(&keys[1]), Amount::new(50)),
+            ],
+            Hash::from_bytes([9; 32]),
+        );
+        (result, keys)
+    }
+
+    #[test]
+    fn players_are_ordered_with_their_payouts() {
+        let (result, keys) = signed_result();
+        let flipped = GameResult::new(
+            result.game_id,
+            &GameOrigin {
+                game_type: result.game_type.clone(),
+                factory_hash: result.factory_hash.clone(),
+            },
+            [
+                (private_to_public_key(&keys[1]), Amount::new(50)),
+                (private_to_public_key(&keys[0]), Amount::new(150)),
+            ],
+            result.final_state_hash.clone(),
+        );
+        assert_eq!(result, flipped);
+        let first = result
+            .players
+            .iter()
+            .position(|p| *p == private_to_public_key(&keys[0]))
+            .unwrap();
+        assert_eq!(result.payouts[first], Amount::new(150));
+    }
+
+    #[test]
+    fn attestation_needs_both_signatures_on_the_same_result() {
+        let (result, keys) = signed_result();
+        let ours = private_to_public_key(&keys[0]);
+        let attestation = GameResultAttestation::combine(
+            result.clone(),
+            &ours,
+            result.sign(&keys[0]),
+            result.sign(&keys[1]),
+        )
+        .expect("both signed");
+        assert!(attestation.verify());
+
+        let mut claimed = attestation.clone();
+        claimed.result.payouts.swap(0, 1);
+        assert!(!claimed.verify());
+
+        let mut other = result.clone();
+        other.final_state_hash = Hash::from_bytes([1; 32]);
+        assert!(GameResultAttestation::combine(
+            result.clone(),
+            &ours,
+            result.sign(&keys[0]),
+            other.sign(&keys[1]),
+        )
+        .is_err());
+        assert!(GameResultAttestation::combine(
+            result.clone(),
+            &ours,
+            result.sign(&keys[0]),
+            result.sign(&keys[0]),
+        )
+        .is_err());
+    }
+}
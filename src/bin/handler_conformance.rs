@@ -0,0 +1,88 @@
+use std::process::exit;
+
+use chia_gaming::common::load_clvm::read_hex_puzzle;
+use chia_gaming::common::types::{AllocEncoder, GameType, Program, Puzzle};
+use chia_gaming::games::conformance::{check_factory, ConformanceConfig};
+use chia_gaming::session_phases::game_collection::game_collection;
+
+const USAGE: &str = "usage: handler-conformance <game|factory.hex> <parameters-hex> \
+[--input <hex>]... [--playouts <n>] [--max-moves <n>] [--seed <n>]
+
+<game> is a registered game type (calpoker, spacepoker, krunk); anything else
+is read as a compiled factory, either an embedded puzzle name or a .hex path.
+<parameters-hex> is the serialized CLVM parameters sent with a proposal.
+Each --input adds a my-turn input tried after the generated ones.";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    exit(2);
+}
+
+fn parse_number(flag: &str, value: Option<String>) -> u64 {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| fail(&format!("{flag} needs a number")))
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let (Some(factory_name), Some(parameters_hex)) = (args.next(), args.next()) else {
+        fail("missing arguments");
+    };
+
+    let mut config = ConformanceConfig::default();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--input" => {
+                let hex = args.next().unwrap_or_else(|| fail("--input needs a value"));
+                let input = Program::from_hex(&hex)
+                    .unwrap_or_else(|e| fail(&format!("bad --input {hex}: {e:?}")));
+                config.inputs.push(input);
+            }
+            "--playouts" => config.playouts = parse_number(&flag, args.next()) as usize,
+            "--max-moves" => config.max_moves = parse_number(&flag, args.next()) as usize,
+            "--seed" => config.seed = parse_number(&flag, args.next()),
+            _ => fail(&format!("unknown argument {flag}")),
+        }
+    }
+
+    let mut allocator = AllocEncoder::new();
+    let registered = game_collection(&mut allocator)
+        .remove(&GameType(factory_name.as_bytes().to_vec()))
+        .and_then(|f| f.program);
+    let factory = match registered {
+        Some(program) => Puzzle::from_bytes(program.bytes()),
+        None => read_hex_puzzle(&mut allocator, &factory_name)
+            .unwrap_or_else(|e| fail(&format!("cannot load {factory_name}: {e:?}"))),
+    };
+    let parameters = Program::from_hex(&parameters_hex)
+        .unwrap_or_else(|e| fail(&format!("bad parameters {parameters_hex}: {e:?}")));
+
+    let report = match check_factory(&mut allocator, factory, &parameters, &config) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("conformance check could not run: {e:?}");
+            exit(2);
+        }
+    };
+
+    println!(
+        "{} game(s), {} of {} play-outs finished, {} moves",
+        report.games,
+        report.completed_playouts,
+        report.games * config.playouts,
+        report.moves
+    );
+    for handler in report.handlers.iter() {
+        println!("  exercised {handler}");
+    }
+    for stalled in report.stalled.iter() {
+        println!("stalled: {stalled}");
+    }
+    for violation in report.violations.iter() {
+        println!("VIOLATION {violation}");
+    }
+    if !report.is_conformant() {
+        exit(1);
+    }
+}
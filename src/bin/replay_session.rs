@@ -0,0 +1,87 @@
+use std::process::exit;
+
+use chia_gaming::common::types::AllocEncoder;
+use chia_gaming::game_session::decode_saved_session;
+use chia_gaming::session_journal::{replay, SessionJournal, SessionKeys};
+
+const USAGE: &str = "usage: replay-session <journal> <keys> [--no-state] [--quiet]
+
+Re-runs a session journal written by a journaling GameSession, printing each
+input, the events it produced and the protocol state after it.  Stops at the
+first step whose result differs from the journal and exits with status 1.
+<keys> is the bencodex-encoded SessionKeys the session was created with;
+journals do not carry them.
+--no-state skips the protocol state; --quiet prints only divergences.";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    exit(2);
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        fail("missing journal path");
+    };
+    let Some(keys_path) = args.next() else {
+        fail("missing keys path");
+    };
+    let mut show_state = true;
+    let mut quiet = false;
+    for flag in args {
+        match flag.as_str() {
+            "--no-state" => show_state = false,
+            "--quiet" => quiet = true,
+            _ => fail(&format!("unknown argument {flag}")),
+        }
+    }
+
+    let bytes = std::fs::read(&path).unwrap_or_else(|e| fail(&format!("cannot read {path}: {e}")));
+    let journal = SessionJournal::from_bytes(&bytes)
+        .unwrap_or_else(|e| fail(&format!("cannot decode {path}: {e:?}")));
+    let bytes = std::fs::read(&keys_path)
+        .unwrap_or_else(|e| fail(&format!("cannot read {keys_path}: {e}")));
+    let keys: SessionKeys = decode_saved_session(&bytes)
+        .unwrap_or_else(|e| fail(&format!("cannot decode {keys_path}: {e:?}")));
+
+    let mut allocator = AllocEncoder::new();
+    let report = replay(&mut allocator, &journal, &keys, |step| {
+        if !quiet {
+            println!("== step {}: {:?}", step.index, step.input);
+            if let Some(error) = &step.error {
+                println!("  error: {error}");
+            }
+            for event in step.events.iter() {
+                println!("  event: {event:?}");
+            }
+            if let Some(resync) = step.resync {
+                println!("  resync: {resync:?}");
+            }
+            if show_state {
+                match step.session.protocol_state_pretty() {
+                    Ok(state) => println!("{state}"),
+                    Err(e) => println!("  protocol state unavailable: {e:?}"),
+                }
+            }
+        }
+        if let Some(reason) = &step.divergence {
+            println!("!! step {} diverged: {reason}", step.index);
+        }
+    })
+    .unwrap_or_else(|e| fail(&format!("cannot replay {path}: {e:?}")));
+
+    println!(
+        "{} of {} step(s) replayed",
+        report.steps,
+        journal.entries.len()
+    );
+    if journal.dropped > 0 {
+        println!(
+            "{} later call(s) were not recorded: the journal reached its limit",
+            journal.dropped
+        );
+    }
+    if report.divergence.is_some() {
+        exit(1);
+    }
+}
@@ -0,0 +1,414 @@
+//! Channel manager: one wallet playing many opponents at once.
+//!
+//! A [`ChannelManager`] owns a [`TransactionManager<GameSession>`] per
+//! channel, each with its own peer, and gives the host a single surface for
+//! the parts that are shared:
+//!
+//! - One chain poller.  [`ChannelManager::snapshot_watched_coins`] is the
+//!   union of every channel's watched coins, and each height and coin-state
+//!   report is fanned out to all channels.  A channel ignores coins it does
+//!   not track, exactly as a lone `TransactionManager` does with a full
+//!   coin-set feed.
+//! - One submission queue.  [`ChannelManager::drain_submissions`] drains
+//!   every channel, tagging each bundle with the channel that produced it.
+//! - One wallet.  Given a [`ChannelFundingSource`],
+//!   [`ChannelManager::flush_and_collect`] answers funding requests itself.
+//!   Coins promised to one channel's funding spend are reserved until its
+//!   channel coin is seen on chain, so concurrent handshakes never pick the
+//!   same coin, and handed back to the wallet if the channel gives up or is
+//!   removed before its funding bundle exists.
+//! - Peer routing.  Outbound events carry their [`ChannelId`], and inbound
+//!   messages are delivered with [`ChannelManager::deliver_message`].
+//!
+//! Everything else (proposals, moves, shutdown) goes straight to the channel
+//! through [`ChannelManager::channel_mut`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::channel_state::types::ChannelPrivateKeys;
+use crate::common::constants::SINGLETON_LAUNCHER_HASH;
+use crate::common::types::{AllocEncoder, Amount, CoinString, Error, PuzzleHash, SpendBundle};
+use crate::game_session::{GameSession, GameSessionConfig};
+use crate::session_phases::effects::GameSessionEvent;
+use crate::session_phases::handshake::CoinSpendRequest;
+use crate::transaction_manager::{CoinStateRecord, TransactionManager};
+
+/// The manager's handle on one of its channels.  Assigned locally when the
+/// channel is opened; the peer never sees it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ChannelId(pub u64);
+
+/// The wallet a [`ChannelManager`] funds its channels from.
+///
+/// `reserved` holds coins already promised to other channels' funding spends
+/// that have not landed yet; they must not be chosen again.
+pub trait ChannelFundingSource {
+    /// A coin of at least `amount` to parent the launcher of a channel we
+    /// initiate.  The same channel's funding request later names it as its
+    /// `coin_id`.
+    fn launcher_parent(
+        &mut self,
+        amount: &Amount,
+        reserved: &BTreeSet<CoinString>,
+    ) -> Result<CoinString, Error>;
+
+    /// A signed bundle answering `request`, in the shape an external wallet
+    /// would return it to `GameSession::provide_coin_spend_bundle`.
+    fn fund(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        request: &CoinSpendRequest,
+        reserved: &BTreeSet<CoinString>,
+    ) -> Result<SpendBundle, Error>;
+
+    /// Coins handed out by [`Self::launcher_parent`] or [`Self::fund`] that
+    /// no funding spend will use after all.
+    fn release(&mut self, _coins: &[CoinString]) {}
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManagedChannel {
+    manager: TransactionManager<GameSession>,
+    my_contribution: Amount,
+    /// Wallet coins promised to this channel's funding, released once the
+    /// channel coin is seen or the channel can no longer use them.
+    reserved: BTreeSet<CoinString>,
+    /// The wallet's funding bundle went to the channel, so its coins may be
+    /// spent and are no longer ours to release.
+    #[serde(default)]
+    funded: bool,
+}
+
+impl ManagedChannel {
+    /// The funding coins are spent into the channel coin, or never will be.
+    /// A finished handshake isn't enough: the receiver's part ends when it
+    /// hands its funding to the initiator, before anything reaches the chain.
+    fn funding_settled(&self) -> bool {
+        self.manager.channel_established()
+            || self.manager.is_failed()
+            || self.manager.is_abandoned()
+    }
+}
+
+/// Everything the channels produced in one [`ChannelManager::flush_and_collect`].
+#[derive(Default)]
+pub struct ChannelManagerDrain {
+    /// Events for the host, in order within each channel.
+    pub events: Vec<(ChannelId, GameSessionEvent)>,
+    /// Coins to start polling.
+    pub watch_coins: Vec<CoinString>,
+    /// Coins no channel watches any longer.
+    pub unwatch_coins: Vec<CoinString>,
+    pub resync: Vec<(ChannelId, (usize, bool))>,
+    /// Channels that failed to drain, or whose funding request failed.  What
+    /// they produced before the failure, and everything from the other
+    /// channels, is still in the fields above.
+    pub errors: Vec<(ChannelId, Error)>,
+}
+
+/// Everything the channels queued in one [`ChannelManager::drain_submissions`].
+#[derive(Default)]
+pub struct ChannelSubmissions {
+    /// Bundles to submit, tagged with the channel that produced them.
+    pub bundles: Vec<(ChannelId, SpendBundle)>,
+    /// Channels whose queue failed to drain.  Their bundles stay queued.
+    pub errors: Vec<(ChannelId, Error)>,
+}
+
+/// Many channels against many peers, sharing one chain view and one wallet.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ChannelManager {
+    channels: BTreeMap<ChannelId, ManagedChannel>,
+    next_channel_id: u64,
+}
+
+impl ChannelManager {
+    pub fn new() -> Self {
+        ChannelManager::default()
+    }
+
+    /// Start tracking a new channel.  The host still drives its handshake
+    /// (`start_handshake` for the initiator) through [`Self::channel_mut`].
+    pub fn open_channel(
+        &mut self,
+        config: GameSessionConfig,
+        private_keys: ChannelPrivateKeys,
+    ) -> ChannelId {
+        let id = ChannelId(self.next_channel_id);
+        self.next_channel_id += 1;
+        let my_contribution = config.my_contribution.clone();
+        self.channels.insert(
+            id,
+            ManagedChannel {
+                manager: TransactionManager::new(GameSession::new_with_keys(config, private_keys)),
+                my_contribution,
+                reserved: BTreeSet::new(),
+                funded: false,
+            },
+        );
+        id
+    }
+
+    /// Stop tracking a channel.  Coins reserved for a funding bundle it never
+    /// got are handed back to `wallet`; once it was funded they may be spent,
+    /// and are left to the chain.
+    pub fn remove_channel(
+        &mut self,
+        id: ChannelId,
+        wallet: Option<&mut dyn ChannelFundingSource>,
+    ) -> Option<TransactionManager<GameSession>> {
+        let channel = self.channels.remove(&id)?;
+        if let (false, Some(wallet)) = (channel.funded, wallet) {
+            wallet.release(&channel.reserved.into_iter().collect::<Vec<_>>());
+        }
+        Some(channel.manager)
+    }
+
+    pub fn channel_ids(&self) -> Vec<ChannelId> {
+        self.channels.keys().copied().collect()
+    }
+
+    pub fn channel(&self, id: ChannelId) -> Option<&TransactionManager<GameSession>> {
+        self.channels.get(&id).map(|channel| &channel.manager)
+    }
+
+    pub fn channel_mut(&mut self, id: ChannelId) -> Option<&mut TransactionManager<GameSession>> {
+        self.channels
+            .get_mut(&id)
+            .map(|channel| &mut channel.manager)
+    }
+
+    fn get_mut(&mut self, id: ChannelId) -> Result<&mut ManagedChannel, Error> {
+        self.channels
+            .get_mut(&id)
+            .ok_or_else(|| Error::StrErr(format!("no channel {id:?}")))
+    }
+
+    /// Wallet coins reserved by channels other than `id`.
+    fn reserved_except(&self, id: ChannelId) -> BTreeSet<CoinString> {
+        self.channels
+            .iter()
+            .filter(|(other, _)| **other != id)
+            .flat_map(|(_, channel)| channel.reserved.iter().cloned())
+            .collect()
+    }
+
+    /// Coins reserved for funding channels whose channel coin hasn't been
+    /// seen yet.
+    pub fn reserved_coins(&self) -> BTreeSet<CoinString> {
+        self.channels
+            .values()
+            .flat_map(|channel| channel.reserved.iter().cloned())
+            .collect()
+    }
+
+    /// Queue a message from the peer of channel `id`.
+    pub fn deliver_message(&mut self, id: ChannelId, inbound_message: &[u8]) -> Result<(), Error> {
+        self.get_mut(id)?.manager.deliver_message(inbound_message)
+    }
+
+    /// Every channel's watched coins, for seeding the shared poller.
+    pub fn snapshot_watched_coins(&self) -> Vec<CoinString> {
+        self.channels
+            .values()
+            .flat_map(|channel| channel.manager.snapshot_watched_coins())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Report a trusted height to every channel.  A channel that fails does
+    /// not stop the others hearing of the height; the first failure is
+    /// returned.
+    pub fn report_height(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        height: u64,
+    ) -> Result<(), Error> {
+        let mut first_error = None;
+        for (id, channel) in self.channels.iter_mut() {
+            if let Err(e) = channel.manager.report_height(allocator, height) {
+                first_error.get_or_insert(Error::StrErr(format!("channel {id:?}: {e:?}")));
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Record the latest block timestamp on every channel; see
+    /// [`TransactionManager::report_timestamp`].
+    pub fn report_timestamp(&mut self, timestamp: u64) {
+        for channel in self.channels.values_mut() {
+            channel.manager.report_timestamp(timestamp);
+        }
+    }
+
+    /// Report the shared poller's coin states to every channel, as
+    /// [`TransactionManager::report_coin_states`] does for one.
+    pub fn report_coin_states(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        height: u64,
+        records: &[CoinStateRecord],
+    ) -> Result<(), Error> {
+        let mut first_error = None;
+        for (id, channel) in self.channels.iter_mut() {
+            if let Err(e) = channel
+                .manager
+                .report_coin_states(allocator, height, records)
+            {
+                first_error.get_or_insert(Error::StrErr(format!("channel {id:?}: {e:?}")));
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Drain every channel's transactions queued for submission.  A failing
+    /// channel is reported in [`ChannelSubmissions::errors`] and doesn't stop
+    /// the others.
+    pub fn drain_submissions(&mut self) -> ChannelSubmissions {
+        let mut out = ChannelSubmissions::default();
+        for (id, channel) in self.channels.iter_mut() {
+            match channel.manager.drain_submissions() {
+                Ok(bundles) => out
+                    .bundles
+                    .extend(bundles.into_iter().map(|bundle| (*id, bundle))),
+                Err(e) => out.errors.push((*id, e)),
+            }
+        }
+        out
+    }
+
+    /// Re-queue every channel's retained submissions; see
+    /// [`TransactionManager::requeue_submitted`].
+    pub fn requeue_submitted(&mut self) {
+        for channel in self.channels.values_mut() {
+            channel.manager.requeue_submitted();
+        }
+    }
+
+    /// Drain every channel.  With a `wallet`, launcher and funding requests
+    /// are answered here and never reach the host; without one they are
+    /// passed on like any other event.  A failing channel is reported in
+    /// [`ChannelManagerDrain::errors`] and doesn't stop the others.
+    pub fn flush_and_collect(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        mut wallet: Option<&mut dyn ChannelFundingSource>,
+    ) -> Result<ChannelManagerDrain, Error> {
+        let mut drain = ChannelManagerDrain::default();
+        for id in self.channel_ids() {
+            loop {
+                let result = match self.get_mut(id)?.manager.flush_and_collect(allocator) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        drain.errors.push((id, e));
+                        break;
+                    }
+                };
+                drain.watch_coins.extend(result.watch_coins);
+                drain.unwatch_coins.extend(result.unwatch_coins);
+                drain
+                    .resync
+                    .extend(result.resync.map(|resync| (id, resync)));
+                let mut answered = false;
+                for event in result.events {
+                    let answer = match (event, wallet.as_deref_mut()) {
+                        (GameSessionEvent::NeedLauncherCoin, Some(wallet)) => {
+                            self.provide_launcher_coin(allocator, id, wallet)
+                        }
+                        (GameSessionEvent::NeedCoinSpend(request), Some(wallet)) => {
+                            self.fund_channel(allocator, id, wallet, &request)
+                        }
+                        (event, _) => {
+                            drain.events.push((id, event));
+                            continue;
+                        }
+                    };
+                    match answer {
+                        Ok(()) => answered = true,
+                        Err(e) => drain.errors.push((id, e)),
+                    }
+                }
+                if !answered {
+                    break;
+                }
+            }
+            let channel = self.get_mut(id)?;
+            if channel.funding_settled() {
+                let reserved = std::mem::take(&mut channel.reserved);
+                if !channel.funded && !channel.manager.channel_established() {
+                    if let Some(wallet) = wallet.as_deref_mut() {
+                        wallet.release(&reserved.into_iter().collect::<Vec<_>>());
+                    }
+                }
+            }
+        }
+
+        let mut seen = BTreeSet::new();
+        drain.watch_coins.retain(|coin| seen.insert(coin.clone()));
+        let mut seen = BTreeSet::new();
+        drain.unwatch_coins.retain(|coin| {
+            seen.insert(coin.clone())
+                && self
+                    .channels
+                    .values()
+                    .all(|channel| channel.manager.watched_coin(coin).is_none())
+        });
+        Ok(drain)
+    }
+
+    fn provide_launcher_coin(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        id: ChannelId,
+        wallet: &mut dyn ChannelFundingSource,
+    ) -> Result<(), Error> {
+        let reserved = self.reserved_except(id);
+        let channel = self.get_mut(id)?;
+        let parent = wallet.launcher_parent(&channel.my_contribution, &reserved)?;
+        let launcher = CoinString::from_parts(
+            &parent.to_coin_id(),
+            &PuzzleHash::from_bytes(SINGLETON_LAUNCHER_HASH),
+            &Amount::default(),
+        );
+        channel.reserved.insert(parent.clone());
+        let provided = channel.manager.provide_launcher_coin(allocator, launcher);
+        if provided.is_err() {
+            channel.reserved.remove(&parent);
+            wallet.release(&[parent]);
+        }
+        provided
+    }
+
+    /// Answer a funding request from the wallet.  A wallet failure is the
+    /// channel's to handle, as it would be from an external wallet.
+    fn fund_channel(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        id: ChannelId,
+        wallet: &mut dyn ChannelFundingSource,
+        request: &CoinSpendRequest,
+    ) -> Result<(), Error> {
+        let reserved = self.reserved_except(id);
+        let funded = wallet.fund(allocator, request, &reserved);
+        let channel = self.get_mut(id)?;
+        match funded {
+            Ok(bundle) => {
+                channel
+                    .reserved
+                    .extend(bundle.spends.iter().map(|spend| spend.coin.clone()));
+                channel.funded = true;
+                channel.manager.provide_coin_spend_bundle(allocator, bundle)
+            }
+            Err(e) => {
+                let reserved = std::mem::take(&mut channel.reserved);
+                wallet.release(&reserved.into_iter().collect::<Vec<_>>());
+                channel
+                    .manager
+                    .wallet_callback_failed(allocator, format!("{e:?}"))
+            }
+        }
+    }
+}
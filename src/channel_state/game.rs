@@ -1,17 +1,18 @@
 use std::rc::Rc;
 
 use clvm_traits::ToClvm;
-use clvmr::run_program;
 
 use crate::utils::proper_list;
 
 use crate::channel_state::game_handler::GameHandler;
 use crate::channel_state::game_start_info::GameStartInfo;
 use crate::channel_state::types::StateUpdateProgram;
+use crate::common::clvm_trace::run_traced;
 use crate::common::types::{
-    atom_from_clvm, chia_dialect, u64_from_atom, usize_from_atom, AllocEncoder, Amount, Error,
-    GameID, Hash, IntoErr, Program, Puzzle, Sha256tree, Timeout, MAX_BLOCK_COST_CLVM,
+    atom_from_clvm, u64_from_atom, usize_from_atom, AllocEncoder, Amount, Error, GameID, Hash,
+    IntoErr, Program, Puzzle, Sha256tree, Timeout,
 };
+use crate::operator_fee::GameFee;
 
 /// One canonical game returned by a proposal factory.
 ///
@@ -40,6 +41,7 @@ impl FactoryGame {
         game_id: &GameID,
         timeout: &Timeout,
         sender_side: bool,
+        fee: Option<GameFee>,
     ) -> GameStartInfo {
         let is_my_turn = sender_side == self.sender_goes_first;
         let handler_program = if is_my_turn {
@@ -80,6 +82,10 @@ impl FactoryGame {
             initial_move: self.initial_move.clone(),
             initial_max_move_size: self.initial_max_move_size,
             initial_mover_share: Amount::new(self.initial_mover_share),
+            fee,
+            my_payout: None,
+            their_payout: None,
+            origin: None,
         }
     }
 }
@@ -102,16 +108,9 @@ impl Game {
     ) -> Result<Vec<FactoryGame>, Error> {
         let args = parameters.to_clvm(allocator).into_gen()?;
         let factory_clvm = factory_program.to_clvm(allocator).into_gen()?;
-        let result = run_program(
-            allocator.allocator(),
-            &chia_dialect(),
-            factory_clvm,
-            args,
-            MAX_BLOCK_COST_CLVM,
-        )
-        .into_gen()
-        .map_err(|e| Error::StrErr(format!("proposal factory failed: error={e:?}")))?
-        .1;
+        let result = run_traced(allocator, "proposal factory", factory_clvm, args)
+            .0
+            .map_err(|e| Error::StrErr(format!("proposal factory failed: error={e:?}")))?;
         let records = proper_list(allocator.allocator(), result, true)
             .ok_or_else(|| Error::StrErr("proposal factory did not return a proper list".into()))?;
         if records.is_empty() {
@@ -221,8 +220,8 @@ mod atomic_factory_tests {
     fn factory_game_selects_handlers_and_contributions_for_both_sides() {
         for sender_goes_first in [false, true] {
             let game = factory_game(sender_goes_first);
-            let sender = game.game_start(&GameID(1), &Timeout::new(15), true);
-            let receiver = game.game_start(&GameID(1), &Timeout::new(15), false);
+            let sender = game.game_start(&GameID(1), &Timeout::new(15), true, None);
+            let receiver = game.game_start(&GameID(1), &Timeout::new(15), false, None);
 
             assert_eq!(sender.is_my_turn(), sender_goes_first);
             assert_eq!(receiver.is_my_turn(), !sender_goes_first);
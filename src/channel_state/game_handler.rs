@@ -4,13 +4,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::utils::proper_list;
 use clvm_traits::{ClvmEncoder, ToClvm, ToClvmError};
-use clvmr::run_program;
 use clvmr::NodePtr;
 
 use crate::channel_state::types::{Evidence, ReadableMove, StateUpdateProgram};
+use crate::common::clvm_trace::{run_traced, with_trace, ClvmTrace};
 use crate::common::types::{
-    atom_from_clvm, chia_dialect, u64_from_atom, usize_from_atom, AllocEncoder, Amount, Error,
-    Hash, IntoErr, Node, Program, ProgramRef, MAX_BLOCK_COST_CLVM,
+    atom_from_clvm, u64_from_atom, usize_from_atom, AllocEncoder, Amount, Error, Hash, IntoErr,
+    Node, Program, ProgramRef,
 };
 use crate::referee::types::GameMoveDetails;
 
@@ -74,18 +74,6 @@ pub struct TheirTurnInputs<'a> {
     pub new_move: GameMoveDetails,
 }
 
-fn run_code(allocator: &mut AllocEncoder, code: NodePtr, env: NodePtr) -> Result<NodePtr, Error> {
-    run_program(
-        allocator.allocator(),
-        &chia_dialect(),
-        code,
-        env,
-        MAX_BLOCK_COST_CLVM,
-    )
-    .into_gen()
-    .map(|r| r.1)
-}
-
 fn get_state_update_program(
     allocator: &mut AllocEncoder,
     name: &str,
@@ -157,6 +145,18 @@ impl GameHandler {
         allocator: &mut AllocEncoder,
         inputs: &MyTurnInputs,
     ) -> Result<MyTurnResult, Error> {
+        let (run_result, trace) = self.run_my_turn_handler(allocator, inputs)?;
+        let run_result = run_result?;
+        Self::decode_my_turn_result(allocator, run_result, &trace)
+            .map_err(|e| with_trace(e, &trace))
+    }
+
+    /// Run the my turn handler without interpreting what it returned.
+    pub(crate) fn run_my_turn_handler(
+        &self,
+        allocator: &mut AllocEncoder,
+        inputs: &MyTurnInputs,
+    ) -> Result<(Result<NodePtr, Error>, Option<ClvmTrace>), Error> {
         let handler_args = (
             inputs.readable_new_move.clone(),
             (
@@ -174,10 +174,19 @@ impl GameHandler {
             .into_gen()?;
 
         let handler_node = self.get_my_turn_handler(allocator)?;
-        let run_result = run_code(allocator, handler_node, handler_args);
-
-        let run_result = run_result?;
+        Ok(run_traced(
+            allocator,
+            "my turn handler",
+            handler_node,
+            handler_args,
+        ))
+    }
 
+    pub(crate) fn decode_my_turn_result(
+        allocator: &mut AllocEncoder,
+        run_result: NodePtr,
+        trace: &Option<ClvmTrace>,
+    ) -> Result<MyTurnResult, Error> {
         let pl = if let Some(pl) = proper_list(allocator.allocator(), run_result, true) {
             pl
         } else {
@@ -189,7 +198,11 @@ impl GameHandler {
         if pl.len() == 2 {
             let tag = allocator.allocator().atom(pl[0]).to_vec();
             let message = allocator.allocator().atom(pl[1]).to_vec();
-            return Err(Error::GameMoveRejected { tag, message });
+            return Err(Error::GameMoveRejected {
+                tag,
+                message,
+                trace: trace.clone(),
+            });
         }
 
         if pl.len() < 9 {
@@ -299,14 +312,19 @@ impl GameHandler {
 
         let handler_node = self.get_their_turn_handler(allocator)?;
 
-        let run_result_e = run_code(allocator, handler_node, handler_args);
+        let (run_result_e, trace) =
+            run_traced(allocator, "their turn handler", handler_node, handler_args);
+        let run_result_e = run_result_e.map_err(|e| match e {
+            Error::Traced { error, .. } => *error,
+            e => e,
+        });
 
         let run_result = match run_result_e {
             Ok(v) => v,
             Err(Error::ClvmErr(e)) => {
                 let failing_hex = Node(e.node_ptr()).to_hex(allocator)?;
                 let failing_prefix = &failing_hex[..failing_hex.len().min(96)];
-                return Err(Error::StrErr(format!(
+                let error = Error::StrErr(format!(
                     "their turn handler failed: error={e:?} move_len={} move_hex={} pre_state_len={} state_len={} pre_state={:?} state={:?} node_len={} node_prefix={}{}",
                     inputs.new_move.basic.move_made.len(),
                     hex::encode(&inputs.new_move.basic.move_made),
@@ -325,10 +343,11 @@ impl GameHandler {
                     } else {
                         ""
                     }
-                )));
+                ));
+                return Err(with_trace(error, &trace));
             }
             Err(e) => {
-                return Err(Error::StrErr(format!(
+                let error = Error::StrErr(format!(
                     "their turn handler failed: move_len={} move_hex={} pre_state_len={} state_len={} pre_state={:?} state={:?} error={e:?}",
                     inputs.new_move.basic.move_made.len(),
                     hex::encode(&inputs.new_move.basic.move_made),
@@ -340,10 +359,20 @@ impl GameHandler {
                         .unwrap_or(0),
                     Program::from_nodeptr(allocator, inputs.pre_state)?,
                     Program::from_nodeptr(allocator, inputs.state)?,
-                )));
+                ));
+                return Err(with_trace(error, &trace));
             }
         };
 
+        Self::decode_their_turn_result(allocator, inputs, run_result)
+            .map_err(|e| with_trace(e, &trace))
+    }
+
+    fn decode_their_turn_result(
+        allocator: &mut AllocEncoder,
+        inputs: &TheirTurnInputs,
+        run_result: NodePtr,
+    ) -> Result<TheirTurnResult, Error> {
         let pl = if let Some(pl) = proper_list(allocator.allocator(), run_result, true) {
             pl
         } else {
@@ -423,11 +452,11 @@ impl MessageHandler {
             .to_clvm(allocator)
             .into_gen()?;
         let run_prog = self.0.to_nodeptr(allocator)?;
-        let run_result = run_code(allocator, run_prog, args);
+        let (run_result, trace) = run_traced(allocator, "message parser", run_prog, args);
 
         let run_output = run_result
             .map_err(|e| Error::StrErr(format!("message parser returned error: {e:?}")))?;
 
-        ReadableMove::from_nodeptr(allocator, run_output)
+        ReadableMove::from_nodeptr(allocator, run_output).map_err(|e| with_trace(e, &trace))
     }
 }
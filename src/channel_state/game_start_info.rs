@@ -6,12 +6,15 @@ use clvmr::allocator::NodePtr;
 
 use serde::{Deserialize, Serialize};
 
+use crate::attestation::GameOrigin;
 use crate::channel_state::game_handler::GameHandler;
 use crate::channel_state::types::StateUpdateProgram;
 use crate::common::types::{
     atom_from_clvm, usize_from_atom, AllocEncoder, Amount, Error, GameID, Hash, Program,
     ProgramRef, Timeout,
 };
+use crate::operator_fee::GameFee;
+use crate::payout::{PayoutTerms, SignedPayout};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct GameStartInfo {
@@ -29,6 +32,20 @@ pub struct GameStartInfo {
 
     pub game_id: GameID,
     pub timeout: Timeout,
+
+    #[serde(default)]
+    pub fee: Option<GameFee>,
+
+    /// Where our share goes, when the proposal or accept named it.
+    #[serde(default)]
+    pub my_payout: Option<PayoutTerms>,
+    /// Where the opponent's share goes, as they signed it.
+    #[serde(default)]
+    pub their_payout: Option<SignedPayout>,
+
+    /// The factory this game came from.
+    #[serde(default)]
+    pub origin: Option<GameOrigin>,
 }
 
 impl GameStartInfo {
@@ -112,6 +129,10 @@ impl GameStartInfo {
             initial_move,
             initial_max_move_size,
             initial_mover_share,
+            fee: None,
+            my_payout: None,
+            their_payout: None,
+            origin: None,
         })
     }
 }
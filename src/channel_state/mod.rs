@@ -25,6 +25,7 @@ use crate::channel_state::types::{
     UnrollCoinConditionInputs,
 };
 
+use crate::attestation::GameResult;
 use crate::common::constants::CREATE_COIN;
 use crate::common::standard_coin::{
     private_to_public_key, puzzle_for_pk, puzzle_for_synthetic_public_key,
@@ -33,11 +34,19 @@ use crate::common::standard_coin::{
 use crate::common::types::Sha256Input;
 use crate::common::types::{
     Aggsig, AllocEncoder, Amount, BrokenOutCoinSpendInfo, CoinCondition, CoinID, CoinSpend,
-    CoinString, Error, GameID, Hash, IntoErr, Node, PrivateKey, Program, PublicKey, Puzzle,
-    PuzzleHash, Sha256tree, Spend, Timeout,
+    CoinString, Error, GameID, Hash, IntoErr, Node, PrivateKey, Program, ProgramRef, PublicKey,
+    Puzzle, PuzzleHash, Sha256tree, Spend, Timeout,
+};
+use crate::operator_fee::GameFee;
+use crate::payout::{PayoutTerms, SignedPayout};
+use crate::referee::types::{
+    curry_referee_puzzle_hash, GameMoveDetails, ParsedRefereeSolution, TheirTurnCoinSpentResult,
 };
-use crate::referee::types::{GameMoveDetails, ParsedRefereeSolution, TheirTurnCoinSpentResult};
 use crate::referee::Referee;
+use crate::spectator::{
+    conditions_hold_position, SignedSpectatorGrant, SpectatorCountersigned, SpectatorGameStart,
+    SpectatorGrant, SpectatorPlayer,
+};
 
 /// A channel handler runs the game by facilitating the phases of game startup
 /// and passing on move information as well as termination to other layers.
@@ -96,6 +105,18 @@ pub struct ChannelState {
     my_allocated_balance: Amount,
     their_allocated_balance: Amount,
 
+    // Operator fees from games settled off-chain, one entry per fee puzzle
+    // hash.  Paid out by the unroll coin and by clean shutdown.
+    #[serde(default)]
+    operator_fees: Vec<(PuzzleHash, Amount)>,
+
+    // Shares of games settled off-chain that per-game payout terms sent
+    // somewhere other than a reward puzzle hash, one entry per puzzle hash and
+    // sorted by it so both sides build the same outputs.  Paid out by the
+    // unroll coin and by clean shutdown.
+    #[serde(default)]
+    routed_payouts: Vec<(PuzzleHash, Amount)>,
+
     have_potato: bool,
 
     // Specifies the time lock that should be used in the unroll coin's conditions.
@@ -138,6 +159,36 @@ pub struct ChannelState {
     proposed_games: Vec<ProposedGame>,
 }
 
+/// One signature a received potato must carry, and the error to report if
+/// it is wrong.
+pub(crate) struct SignatureCheck {
+    pub signature: Aggsig,
+    pub public_key: PublicKey,
+    pub message: Vec<u8>,
+    pub failure: &'static str,
+}
+
+/// Check all of a potato pass's signatures with one batched pairing. Only
+/// if that fails are they checked one at a time, so the error still names
+/// the bad signature.
+pub(crate) fn verify_signature_batch(checks: &[SignatureCheck]) -> Result<(), Error> {
+    let batch: Vec<_> = checks
+        .iter()
+        .map(|c| (&c.signature, &c.public_key, c.message.as_slice()))
+        .collect();
+    if Aggsig::verify_batch(&batch) {
+        return Ok(());
+    }
+    for check in checks {
+        if !check.signature.verify(&check.public_key, &check.message) {
+            return Err(Error::StrErr(check.failure.to_string()));
+        }
+    }
+    Err(Error::StrErr(
+        "potato signatures failed batch verification".to_string(),
+    ))
+}
+
 impl ChannelState {
     pub fn is_initial_potato(&self) -> bool {
         self.latest_sent_unroll.coin.started_with_potato
@@ -222,6 +273,115 @@ impl ChannelState {
         &self.private_keys
     }
 
+    /// Snapshot a game for the start of a spectator feed and sign the grant
+    /// for it.  `None` until the peer has signed a state holding the game.
+    pub fn spectator_start(
+        &self,
+        allocator: &mut AllocEncoder,
+        game_id: GameID,
+        observer: PublicKey,
+        delay_moves: u32,
+    ) -> Result<Option<SpectatorGameStart>, Error> {
+        let game = self
+            .find_live_game(&game_id)
+            .ok_or_else(|| Error::StrErr(format!("no live game {game_id:?}")))?;
+        let Some(received) = self.latest_received_unroll.as_ref() else {
+            return Ok(None);
+        };
+        let conditions = received
+            .coin
+            .get_internal_conditions_for_unroll_coin_spend()?;
+        let mut countersigned = None;
+        for candidate in game.countersigned_candidates() {
+            if conditions_hold_position(allocator, &conditions, &candidate)? {
+                countersigned = Some(SpectatorCountersigned {
+                    position: candidate,
+                    conditions: conditions.clone(),
+                    signature: received.signatures.unroll_preempt_half_sig.clone(),
+                });
+                break;
+            }
+        }
+        let Some(countersigned) = countersigned else {
+            return Ok(None);
+        };
+
+        let (position, state) = game.spectator_position();
+        let grant = SpectatorGrant {
+            game_id,
+            observer,
+            delay_moves,
+            players: [
+                SpectatorPlayer {
+                    referee_pubkey: private_to_public_key(
+                        &self.private_keys.my_referee_private_key,
+                    ),
+                    unroll_pubkey: private_to_public_key(
+                        &self.private_keys.my_unroll_coin_private_key,
+                    ),
+                },
+                SpectatorPlayer {
+                    referee_pubkey: self.their_referee_pubkey.clone(),
+                    unroll_pubkey: self.their_unroll_coin_public_key.clone(),
+                },
+            ],
+            position_hash: curry_referee_puzzle_hash(
+                allocator,
+                &position.referee_coin_puzzle_hash,
+                &position,
+            )?,
+            state_hash: (*state).sha256tree(allocator),
+        };
+        Ok(Some(SpectatorGameStart {
+            grant: SignedSpectatorGrant::sign(&self.private_keys.my_referee_private_key, grant),
+            position,
+            state,
+            countersigned,
+        }))
+    }
+
+    /// Our record of how a live or just-settled game stands, for its result
+    /// attestation.  `None` for games started without a recorded origin.
+    pub fn game_result(&self, game_id: &GameID) -> Option<GameResult> {
+        let game = self
+            .live_games
+            .iter()
+            .chain(self.pending_settlements.iter())
+            .find(|g| g.game_id == *game_id)?;
+        let origin = game.game_origin()?;
+        Some(GameResult::new(
+            *game_id,
+            &origin,
+            [
+                (
+                    private_to_public_key(&self.private_keys.my_referee_private_key),
+                    game.get_our_current_share().ok()?,
+                ),
+                (
+                    self.their_referee_pubkey.clone(),
+                    game.get_their_current_share().ok()?,
+                ),
+            ],
+            game.last_referee_puzzle_hash.hash().clone(),
+        ))
+    }
+
+    /// The unroll conditions we signed in our latest batch.
+    pub fn latest_sent_unroll_conditions(&self) -> Result<ProgramRef, Error> {
+        self.latest_sent_unroll
+            .coin
+            .get_internal_conditions_for_unroll_coin_spend()
+    }
+
+    /// The unroll conditions the peer signed in its latest batch.
+    pub fn latest_received_unroll_conditions(&self) -> Result<ProgramRef, Error> {
+        self.latest_received_unroll
+            .as_ref()
+            .ok_or_else(|| Error::StrErr("no unroll received yet".to_string()))?
+            .coin
+            .get_internal_conditions_for_unroll_coin_spend()
+    }
+
     pub fn my_allocated_balance(&self) -> Amount {
         self.my_allocated_balance.clone()
     }
@@ -253,7 +413,121 @@ impl ChannelState {
             return allocated;
         }
 
-        allocated + self.my_out_of_game_balance.clone() + self.their_out_of_game_balance.clone()
+        allocated
+            + self.my_out_of_game_balance.clone()
+            + self.their_out_of_game_balance.clone()
+            + self.total_operator_fees()
+            + self.total_routed_payouts()
+    }
+
+    /// Fees owed to each operator from games settled off-chain.
+    pub fn operator_fees(&self) -> &[(PuzzleHash, Amount)] {
+        &self.operator_fees
+    }
+
+    pub fn total_operator_fees(&self) -> Amount {
+        self.operator_fees
+            .iter()
+            .fold(Amount::default(), |total, (_, amount)| {
+                total + amount.clone()
+            })
+    }
+
+    pub fn is_operator_fee_puzzle_hash(&self, puzzle_hash: &PuzzleHash) -> bool {
+        self.operator_fees.iter().any(|(ph, _)| ph == puzzle_hash)
+    }
+
+    /// Owe a settled game's operator fee.  Like [`Self::credit_payout`], a
+    /// fee to a reward puzzle hash or a routed payout shares that output.
+    /// Proposals refuse fees to reward puzzle hashes, since the referee would
+    /// pay them on chain beside the rewards, but the merge keeps the unroll
+    /// and shutdown outputs distinct whatever got here.
+    fn accrue_operator_fee(&mut self, fee: Option<GameFee>) {
+        let Some(fee) = fee else {
+            return;
+        };
+        if fee.puzzle_hash == self.reward_puzzle_hash
+            || fee.puzzle_hash == self.their_reward_puzzle_hash
+        {
+            self.credit_payout(fee.puzzle_hash, fee.amount);
+        } else if let Some((_, owed)) = self
+            .operator_fees
+            .iter_mut()
+            .chain(self.routed_payouts.iter_mut())
+            .find(|(ph, _)| *ph == fee.puzzle_hash)
+        {
+            *owed += fee.amount;
+        } else {
+            self.operator_fees.push((fee.puzzle_hash, fee.amount));
+        }
+    }
+
+    /// Game payouts owed to destinations named by per-game payout terms.
+    pub fn routed_payouts(&self) -> &[(PuzzleHash, Amount)] {
+        &self.routed_payouts
+    }
+
+    pub fn total_routed_payouts(&self) -> Amount {
+        self.routed_payouts
+            .iter()
+            .fold(Amount::default(), |total, (_, amount)| {
+                total + amount.clone()
+            })
+    }
+
+    /// Whether an unroll output to `puzzle_hash` is an operator fee or a
+    /// routed payout, rather than a reward or a game coin.
+    pub fn is_settlement_output_puzzle_hash(&self, puzzle_hash: &PuzzleHash) -> bool {
+        self.is_operator_fee_puzzle_hash(puzzle_hash)
+            || self.routed_payouts.iter().any(|(ph, _)| ph == puzzle_hash)
+    }
+
+    /// Credit both players' shares of a game settled off-chain, to their
+    /// out-of-game balances or to the destinations their payout terms name,
+    /// and accrue the game's operator fee.
+    fn credit_game_payouts(&mut self, game: &LiveGame, ours: &Amount, theirs: &Amount) {
+        match game.my_payout() {
+            Some(terms) => {
+                for (ph, amount) in terms.split_amount(ours) {
+                    self.credit_payout(ph, amount);
+                }
+            }
+            None => self.my_out_of_game_balance += ours.clone(),
+        }
+        match game.their_payout() {
+            Some(terms) => {
+                for (ph, amount) in terms.split_amount(theirs) {
+                    self.credit_payout(ph, amount);
+                }
+            }
+            None => self.their_out_of_game_balance += theirs.clone(),
+        }
+        self.accrue_operator_fee(game.operator_fee());
+    }
+
+    /// Pay `amount` to `puzzle_hash` at settlement.  A destination that is a
+    /// reward puzzle hash or an operator fee shares that output, since two
+    /// identical coins could not both be created.
+    fn credit_payout(&mut self, puzzle_hash: PuzzleHash, amount: Amount) {
+        if puzzle_hash == self.reward_puzzle_hash {
+            self.my_out_of_game_balance += amount;
+        } else if puzzle_hash == self.their_reward_puzzle_hash {
+            self.their_out_of_game_balance += amount;
+        } else if let Some((_, owed)) = self
+            .operator_fees
+            .iter_mut()
+            .find(|(ph, _)| *ph == puzzle_hash)
+        {
+            *owed += amount;
+        } else {
+            match self
+                .routed_payouts
+                .binary_search_by(|(ph, _)| ph.bytes().cmp(puzzle_hash.bytes()))
+            {
+                Ok(i) => self.routed_payouts[i].1 += amount,
+                Err(i) => self.routed_payouts.insert(i, (puzzle_hash, amount)),
+            }
+        }
     }
 
     pub fn get_our_current_share(&self) -> Amount {
@@ -282,6 +556,17 @@ impl ChannelState {
         Ok(self.reward_puzzle_hash.clone())
     }
 
+    /// Where the referee of `game_id` pays us: our reward puzzle hash unless
+    /// we named a payout for that game.
+    pub fn game_reward_puzzle_hash(&self, game_id: &GameID) -> PuzzleHash {
+        self.live_games
+            .iter()
+            .chain(self.pending_settlements.iter())
+            .find(|g| g.game_id == *game_id)
+            .map(|g| g.reward_puzzle_hash())
+            .unwrap_or_else(|| self.reward_puzzle_hash.clone())
+    }
+
     pub fn my_reward_puzzle_hash(&self) -> &PuzzleHash {
         &self.reward_puzzle_hash
     }
@@ -324,7 +609,9 @@ impl ChannelState {
             my_balance,
             their_balance,
             puzzle_hashes_and_amounts: puzzle_hashes_and_amounts.to_vec(),
-            unroll_timeout: self.unroll_advance_timeout.to_u64(),
+            operator_fees: self.operator_fees.clone(),
+            routed_payouts: self.routed_payouts.clone(),
+            unroll_timeout: self.unroll_advance_timeout.clone(),
         }
     }
 
@@ -540,6 +827,8 @@ impl ChannelState {
             live_games: Vec::new(),
             pending_settlements: Vec::new(),
             proposed_games: Vec::new(),
+            operator_fees: Vec::new(),
+            routed_payouts: Vec::new(),
 
             private_keys,
         };
@@ -735,44 +1024,58 @@ impl ChannelState {
         self.update_cached_unroll_state(env)
     }
 
-    pub fn verify_channel_coin_from_peer_signatures(
+    /// Our channel coin spend creating `conditions`, carrying the full
+    /// signature (the peer's half plus ours). The signature is not checked.
+    fn channel_coin_spend_with_peer_signature(
         &self,
         env: &mut ChannelEnv<'_>,
         their_channel_half_signature: &Aggsig,
         conditions: Rc<Program>,
     ) -> Result<BrokenOutCoinSpendInfo, Error> {
-        let aggregate_public_key = self.get_aggregate_channel_public_key();
         let spend = self.channel_coin_spend.coin.clone();
         let channel_coin_spend = self.get_solution_and_signature_from_conditions(
             &spend.to_coin_id(),
             env,
             &self.private_keys.my_channel_coin_private_key,
-            &aggregate_public_key,
+            &self.get_aggregate_channel_public_key(),
             conditions,
         )?;
 
-        let full_signature =
-            channel_coin_spend.signature.clone() + their_channel_half_signature.clone();
-        if full_signature.verify(&aggregate_public_key, &channel_coin_spend.message) {
-            Ok(BrokenOutCoinSpendInfo {
-                signature: full_signature,
-                ..channel_coin_spend
-            })
+        Ok(BrokenOutCoinSpendInfo {
+            signature: channel_coin_spend.signature.clone() + their_channel_half_signature.clone(),
+            ..channel_coin_spend
+        })
+    }
+
+    pub fn verify_channel_coin_from_peer_signatures(
+        &self,
+        env: &mut ChannelEnv<'_>,
+        their_channel_half_signature: &Aggsig,
+        conditions: Rc<Program>,
+    ) -> Result<BrokenOutCoinSpendInfo, Error> {
+        let channel_coin_spend = self.channel_coin_spend_with_peer_signature(
+            env,
+            their_channel_half_signature,
+            conditions,
+        )?;
+        if channel_coin_spend.signature.verify(
+            &self.get_aggregate_channel_public_key(),
+            &channel_coin_spend.message,
+        ) {
+            Ok(channel_coin_spend)
         } else {
             Err(Error::StrErr("failed to verify signature".to_string()))
         }
     }
 
-    pub fn received_potato_verify_signatures(
-        &mut self,
+    /// Build the next unroll coin and our channel coin spend for a received
+    /// potato, along with the checks its two signatures have to pass.
+    fn received_potato_signature_checks(
+        &self,
         env: &mut ChannelEnv<'_>,
         signatures: &StateUpdateSignatures,
         inputs: &UnrollCoinConditionInputs,
-    ) -> Result<BrokenOutCoinSpendInfo, Error> {
-        // The potato just arrived, so any prior pending accepts are now
-        // confirmed by the round-trip.
-        self.pending_settlements.clear();
-
+    ) -> Result<(UnrollCoin, BrokenOutCoinSpendInfo, Vec<SignatureCheck>), Error> {
         // Unroll coin section.
         let mut test_unroll = self.latest_sent_unroll.coin.clone();
         test_unroll.state_number = self.state_number + 1;
@@ -783,24 +1086,67 @@ impl ChannelState {
             &self.their_unroll_coin_public_key,
             inputs,
         )?;
-
-        if !test_unroll.verify(
-            env,
-            &self.get_aggregate_unroll_public_key(),
-            &signatures.unroll_preempt_half_sig,
-        )? {
-            return Err(Error::StrErr("bad unroll signature verify".to_string()));
-        }
+        let (unroll_signature, unroll_message) =
+            test_unroll.full_signature_and_message(env, &signatures.unroll_preempt_half_sig)?;
 
         // State coin section
         let channel_coin_spend =
             self.create_conditions_and_signature_of_channel_coin(env, &test_unroll)?;
-        self.verify_channel_coin_from_peer_signatures(
+        let peer_signed_spend = self.channel_coin_spend_with_peer_signature(
             env,
             &signatures.channel_half_sig,
             channel_coin_spend.conditions.p(),
         )?;
 
+        let checks = vec![
+            SignatureCheck {
+                signature: unroll_signature,
+                public_key: self.get_aggregate_unroll_public_key(),
+                message: unroll_message,
+                failure: "bad unroll signature verify",
+            },
+            SignatureCheck {
+                signature: peer_signed_spend.signature,
+                public_key: self.get_aggregate_channel_public_key(),
+                message: peer_signed_spend.message,
+                failure: "failed to verify signature",
+            },
+        ];
+        Ok((test_unroll, channel_coin_spend, checks))
+    }
+
+    /// The signature checks a received batch has to pass (benchmarks only).
+    #[cfg(test)]
+    pub(crate) fn received_batch_signature_checks(
+        &self,
+        env: &mut ChannelEnv<'_>,
+        signatures: &StateUpdateSignatures,
+    ) -> Result<Vec<SignatureCheck>, Error> {
+        let unroll_data = self.compute_unroll_data_for_games(&[], None, &self.live_games)?;
+        let inputs = self.unroll_coin_condition_inputs(
+            self.my_out_of_game_balance.clone(),
+            self.their_out_of_game_balance.clone(),
+            &unroll_data,
+        );
+        Ok(self
+            .received_potato_signature_checks(env, signatures, &inputs)?
+            .2)
+    }
+
+    pub fn received_potato_verify_signatures(
+        &mut self,
+        env: &mut ChannelEnv<'_>,
+        signatures: &StateUpdateSignatures,
+        inputs: &UnrollCoinConditionInputs,
+    ) -> Result<BrokenOutCoinSpendInfo, Error> {
+        // The potato just arrived, so any prior pending accepts are now
+        // confirmed by the round-trip.
+        self.pending_settlements.clear();
+
+        let (test_unroll, channel_coin_spend, checks) =
+            self.received_potato_signature_checks(env, signatures, inputs)?;
+        verify_signature_batch(&checks)?;
+
         self.state_number += 1;
         let received_info = ChannelUnrollSpendInfo {
             coin: test_unroll.clone(),
@@ -1085,6 +1431,47 @@ impl ChannelState {
         self.accept_proposal_inner(game_id)
     }
 
+    /// Pay our share of the peer's proposal `game_id` as `terms` say.  Done
+    /// just before accepting it.
+    pub fn set_proposal_payout(
+        &mut self,
+        game_id: &GameID,
+        terms: PayoutTerms,
+    ) -> Result<(), Error> {
+        if self.is_our_nonce_parity(game_id) {
+            return Err(Error::StrErr(format!(
+                "payout for our own proposal {game_id:?} is set when proposing"
+            )));
+        }
+        let proposal = self
+            .proposed_games
+            .iter_mut()
+            .find(|p| p.game_id == *game_id)
+            .ok_or_else(|| Error::StrErr(format!("no proposal with id {game_id:?}")))?;
+        proposal.referee = Rc::new(proposal.referee.with_my_payout(terms)?);
+        Ok(())
+    }
+
+    /// Apply a received accept-proposal whose sender named their own payout.
+    pub fn apply_received_accept_proposal_with_payout(
+        &mut self,
+        game_id: &GameID,
+        payout: &SignedPayout,
+    ) -> Result<(), Error> {
+        if !self.is_our_nonce_parity(game_id) {
+            return Err(Error::StrErr(format!(
+                "peer attempted to accept their own proposal {game_id:?}"
+            )));
+        }
+        let proposal = self
+            .proposed_games
+            .iter_mut()
+            .find(|p| p.game_id == *game_id)
+            .ok_or_else(|| Error::StrErr(format!("no proposal with id {game_id:?}")))?;
+        proposal.referee = Rc::new(proposal.referee.with_their_payout(payout)?);
+        self.accept_proposal_inner(game_id)
+    }
+
     /// Mutate state for cancelling a proposal. Does NOT finalize signatures.
     pub fn send_cancel_proposal(&mut self, game_id: &GameID) -> Result<(), Error> {
         let idx = self
@@ -1236,6 +1623,15 @@ impl ChannelState {
         resolved
     }
 
+    /// The operator fee of a live or just-settled game.
+    pub fn game_operator_fee(&self, game_id: &GameID) -> Option<GameFee> {
+        self.live_games
+            .iter()
+            .chain(self.pending_settlements.iter())
+            .find(|g| g.game_id == *game_id)
+            .and_then(|g| g.operator_fee())
+    }
+
     pub fn get_game_amount(&self, game_id: &GameID) -> Result<Amount, Error> {
         if let Some(g) = self.live_games.iter().find(|g| g.game_id == *game_id) {
             return Ok(g.get_amount());
@@ -1396,6 +1792,7 @@ impl ChannelState {
             .checked_sub(&live_game.their_contribution)?;
 
         let amount = live_game.get_our_current_share()?;
+        let their_amount = live_game.get_their_current_share()?;
         let at_stake = live_game.get_amount();
 
         let (ref_clone, ph_clone) = live_game.save_referee_state();
@@ -1407,8 +1804,7 @@ impl ChannelState {
             live_game.their_contribution.clone(),
         ));
 
-        self.my_out_of_game_balance += amount.clone();
-        self.their_out_of_game_balance += at_stake.checked_sub(&amount)?;
+        self.credit_game_payouts(&live_game, &amount, &their_amount);
 
         let game_finished = live_game.is_game_over();
         self.push_cached_action(CachedRedoActions::CachedAcceptSettlement(Box::new(
@@ -1441,9 +1837,7 @@ impl ChannelState {
 
         let game_finished = self.live_games[game_idx].is_game_over();
         let game_amount_for_me = self.live_games[game_idx].get_our_current_share()?;
-        let game_amount_for_them = self.live_games[game_idx]
-            .get_amount()
-            .checked_sub(&self.live_games[game_idx].get_our_current_share()?)?;
+        let game_amount_for_them = self.live_games[game_idx].get_their_current_share()?;
 
         self.my_allocated_balance = self
             .my_allocated_balance
@@ -1451,10 +1845,8 @@ impl ChannelState {
         self.their_allocated_balance = self
             .their_allocated_balance
             .checked_sub(&self.live_games[game_idx].their_contribution)?;
-        self.my_out_of_game_balance += game_amount_for_me.clone();
-        self.their_out_of_game_balance += game_amount_for_them;
-
         let removed = self.live_games.remove(game_idx);
+        self.credit_game_payouts(&removed, &game_amount_for_me, &game_amount_for_them);
         self.pending_settlements.push(removed);
         Ok((game_amount_for_me, game_finished))
     }
@@ -2028,7 +2420,7 @@ impl ChannelState {
         conditions: &[CoinCondition],
         parsed_solution: &ParsedRefereeSolution,
     ) -> Result<CoinSpentInformation, Error> {
-        let reward_puzzle_hash = self.get_reward_puzzle_hash(env)?;
+        let reward_puzzle_hash = self.game_reward_puzzle_hash(game_id);
 
         let (ph, amt) = if let Some((ph, amt)) = conditions
             .iter()
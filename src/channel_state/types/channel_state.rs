@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::channel_state::types::{StateUpdateSignatures, UnrollCoin};
 use crate::common::constants::AGG_SIG_ME_ADDITIONAL_DATA;
-use crate::common::load_clvm::read_hex_puzzle;
+use crate::common::puzzle_registry;
 use crate::common::standard_coin::get_standard_coin_puzzle;
 use crate::common::types::{
     Aggsig, AllocEncoder, Error, Hash, PrivateKey, ProgramRef, Puzzle, PuzzleHash, Sha256tree,
@@ -72,11 +72,9 @@ pub struct ChannelEnv<'a> {
 
 impl<'a> ChannelEnv<'a> {
     pub fn new(allocator: &'a mut AllocEncoder) -> Result<ChannelEnv<'a>, Error> {
-        let referee_coin_puzzle = read_hex_puzzle(allocator, "clsp/referee/onchain/referee.hex")?;
-        let unroll_puzzle = read_hex_puzzle(
-            allocator,
-            "clsp/unroll/unroll_puzzle_state_channel_unrolling.hex",
-        )?;
+        let referee_coin_puzzle = puzzle_registry::ONCHAIN_REFEREE.to_puzzle(allocator)?;
+        let unroll_puzzle =
+            puzzle_registry::UNROLL_PUZZLE_STATE_CHANNEL_UNROLLING.to_puzzle(allocator)?;
         let standard_puzzle = get_standard_coin_puzzle(allocator)?;
         let referee_coin_puzzle_hash = referee_coin_puzzle.sha256tree(allocator);
         Ok(ChannelEnv {
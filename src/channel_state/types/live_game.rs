@@ -2,14 +2,19 @@ use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 
+use crate::attestation::GameOrigin;
+use crate::channel_state::game_handler::GameHandler;
+use crate::channel_state::types::StateUpdateProgram;
 use crate::channel_state::ReadableMove;
 use crate::common::types::{
-    AllocEncoder, Amount, CoinCondition, CoinString, Error, GameID, Hash, PuzzleHash, Spend,
-    Timeout,
+    AllocEncoder, Amount, CoinCondition, CoinString, Error, GameID, Hash, Program, PuzzleHash,
+    Spend, Timeout,
 };
+use crate::operator_fee::GameFee;
+use crate::payout::PayoutTerms;
 use crate::referee::types::{
-    GameMoveDetails, GameMoveWireData, ParsedRefereeSolution, TheirTurnCoinSpentResult,
-    TheirTurnMoveResult,
+    GameMoveDetails, GameMoveWireData, ParsedRefereeSolution, RefereePuzzleArgs,
+    TheirTurnCoinSpentResult, TheirTurnMoveResult,
 };
 use crate::referee::Referee;
 
@@ -143,6 +148,51 @@ impl LiveGame {
         self.referee_maker.get_our_current_share()
     }
 
+    pub fn get_their_current_share(&self) -> Result<Amount, Error> {
+        self.referee_maker.get_their_current_share()
+    }
+
+    pub fn operator_fee(&self) -> Option<GameFee> {
+        self.referee_maker.operator_fee()
+    }
+
+    pub fn my_payout(&self) -> Option<PayoutTerms> {
+        self.referee_maker.my_payout()
+    }
+
+    pub fn their_payout(&self) -> Option<PayoutTerms> {
+        self.referee_maker.their_payout()
+    }
+
+    pub fn game_origin(&self) -> Option<GameOrigin> {
+        self.referee_maker.game_origin()
+    }
+
+    /// Where this game's referee pays our share.
+    pub fn reward_puzzle_hash(&self) -> PuzzleHash {
+        self.referee_maker.reward_puzzle_hash()
+    }
+
+    pub fn their_reward_puzzle_hash(&self) -> PuzzleHash {
+        self.referee_maker.their_reward_puzzle_hash()
+    }
+
+    pub fn spectator_position(&self) -> (Rc<RefereePuzzleArgs>, Rc<Program>) {
+        self.referee_maker.spectator_position()
+    }
+
+    pub fn countersigned_candidates(&self) -> [Rc<RefereePuzzleArgs>; 2] {
+        self.referee_maker.countersigned_candidates()
+    }
+
+    pub fn last_move_validator(&self) -> StateUpdateProgram {
+        self.referee_maker.last_move_validator()
+    }
+
+    pub fn their_turn_handler(&self) -> Option<GameHandler> {
+        self.referee_maker.their_turn_handler()
+    }
+
     pub fn get_transaction_for_move(
         &self,
         allocator: &mut AllocEncoder,
@@ -1,12 +1,9 @@
 #[cfg(test)]
-use crate::common::load_clvm::read_hex_puzzle;
+use crate::common::puzzle_registry;
 #[cfg(test)]
 use crate::common::types::{AllocEncoder, Error, Puzzle};
 
 #[cfg(test)]
 pub fn read_unroll_puzzle(allocator: &mut AllocEncoder) -> Result<Puzzle, Error> {
-    read_hex_puzzle(
-        allocator,
-        "clsp/unroll/unroll_puzzle_state_channel_unrolling.hex",
-    )
+    puzzle_registry::UNROLL_PUZZLE_STATE_CHANNEL_UNROLLING.to_puzzle(allocator)
 }
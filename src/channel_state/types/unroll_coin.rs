@@ -7,11 +7,11 @@ use clvmr::allocator::NodePtr;
 use serde::{Deserialize, Serialize};
 
 use crate::channel_state::types::ChannelEnv;
-use crate::common::constants::{ASSERT_HEIGHT_RELATIVE, CREATE_COIN, REM};
+use crate::common::constants::{CREATE_COIN, REM};
 use crate::common::standard_coin::{private_to_public_key, unsafe_sign_partial};
 use crate::common::types::{
     Aggsig, Amount, Error, IntoErr, Node, PrivateKey, Program, ProgramRef, PublicKey, PuzzleHash,
-    Sha256tree,
+    Sha256tree, Timeout,
 };
 
 /// Represents the unroll coin which will come to exist if the channel coin
@@ -190,6 +190,19 @@ impl UnrollCoin {
             result_coins.push(Node(clvm_conditions));
         }
 
+        // Operator fees and routed payouts owed from games settled off-chain
+        // come last.
+        for (ph, a) in inputs
+            .operator_fees
+            .iter()
+            .chain(inputs.routed_payouts.iter())
+        {
+            let clvm_conditions = (CREATE_COIN, (ph.clone(), (a.clone(), ())))
+                .to_clvm(env.allocator)
+                .into_gen()?;
+            result_coins.push(Node(clvm_conditions));
+        }
+
         let result_coins_node = result_coins.to_clvm(env.allocator).into_gen()?;
         let result_node = prepend_rem_conditions(env, self.state_number, result_coins_node)?;
         Ok(ProgramRef::new(Rc::new(Program::from_nodeptr(
@@ -209,11 +222,15 @@ impl UnrollCoin {
     ) -> Result<Aggsig, Error> {
         let base_conditions = self.compute_unroll_coin_conditions(env, inputs)?;
 
-        // Timeout conditions: prepend ASSERT_HEIGHT_RELATIVE to the base
+        // Timeout conditions: prepend ASSERT_HEIGHT_RELATIVE (or
+        // ASSERT_SECONDS_RELATIVE for a seconds timeout) to the base
         // conditions.  The preemption path uses the base conditions (no
         // timelock) so it can execute immediately.
-        let timeout_conditions = if inputs.unroll_timeout > 0 {
-            let timelock_cond = (ASSERT_HEIGHT_RELATIVE, (inputs.unroll_timeout, ()))
+        let timeout_conditions = if inputs.unroll_timeout.to_u64() > 0 {
+            let timelock_cond = (
+                inputs.unroll_timeout.assert_condition(),
+                (inputs.unroll_timeout.to_u64(), ()),
+            )
                 .to_clvm(env.allocator)
                 .into_gen()?;
             let timeout_node = (
@@ -245,24 +262,32 @@ impl UnrollCoin {
         Ok(unroll_signature)
     }
 
-    pub fn verify(
+    /// The full unroll signature (the peer's half `signature` plus ours) and
+    /// the message it has to sign under the aggregate unroll key.
+    pub fn full_signature_and_message(
         &self,
         env: &mut ChannelEnv<'_>,
-        aggregate_unroll_public_key: &PublicKey,
         signature: &Aggsig,
-    ) -> Result<bool, Error> {
+    ) -> Result<(Aggsig, Vec<u8>), Error> {
         let unroll_puzzle_solution = self.get_internal_conditions_for_unroll_coin_spend()?;
         let unroll_puzzle_solution_hash = unroll_puzzle_solution.sha256tree(env.allocator);
 
         let our_half = self.get_unroll_coin_signature()?;
-        let aggregate_unroll_signature = signature.clone() + our_half.clone();
-
-        let result = aggregate_unroll_signature.verify(
-            aggregate_unroll_public_key,
-            unroll_puzzle_solution_hash.bytes(),
-        );
+        Ok((
+            signature.clone() + our_half,
+            unroll_puzzle_solution_hash.bytes().to_vec(),
+        ))
+    }
 
-        Ok(result)
+    pub fn verify(
+        &self,
+        env: &mut ChannelEnv<'_>,
+        aggregate_unroll_public_key: &PublicKey,
+        signature: &Aggsig,
+    ) -> Result<bool, Error> {
+        let (aggregate_unroll_signature, message) =
+            self.full_signature_and_message(env, signature)?;
+        Ok(aggregate_unroll_signature.verify(aggregate_unroll_public_key, &message))
     }
 }
 
@@ -273,7 +298,9 @@ pub struct UnrollCoinConditionInputs {
     pub my_balance: Amount,
     pub their_balance: Amount,
     pub puzzle_hashes_and_amounts: Vec<(PuzzleHash, Amount)>,
-    pub unroll_timeout: u64,
+    pub operator_fees: Vec<(PuzzleHash, Amount)>,
+    pub routed_payouts: Vec<(PuzzleHash, Amount)>,
+    pub unroll_timeout: Timeout,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -0,0 +1,335 @@
+//! Opt-in CLVM call tracing for handler and validator failures.
+//!
+//! `build.rs` keeps the compiler's symbol table for every puzzle it builds and
+//! embeds it here as [`FUNCTION_SYMBOLS`]: the tree hash of each compiled
+//! function with its qualified chialisp name, argument list and defining line.
+//! With tracing enabled, [`run_traced`] hashes each program clvmr is about to
+//! evaluate and keeps a stack of the named functions it has entered. When the
+//! run fails the stack at that point is attached to the error as a
+//! [`ClvmTrace`], so a bare `clvm raise` becomes a chialisp backtrace.
+//!
+//! Tracing makes every run much slower and is off by default.
+//!
+//! [`with_clvm_cost_budget`] meters every run inside it against one shared
+//! cost budget, so a single inbound peer message cannot buy more CLVM work
+//! than the session allows however many handlers and parsers it triggers.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+
+use clvm_utils::tree_hash;
+use clvmr::allocator::SExp;
+use clvmr::error::EvalErr;
+use clvmr::reduction::Response;
+use clvmr::run_program::{run_program_with_pre_eval, PreEval};
+use clvmr::{Allocator, NodePtr};
+
+use crate::common::types::{chia_dialect, AllocEncoder, Error, IntoErr, MAX_BLOCK_COST_CLVM};
+
+#[derive(Debug)]
+pub struct FunctionSymbol {
+    pub hash: [u8; 32],
+    pub name: &'static str,
+    pub arguments: &'static str,
+    /// Defining file relative to the crate root, or empty if not found.
+    pub file: &'static str,
+    pub line: usize,
+}
+
+include!(concat!(env!("OUT_DIR"), "/puzzle_symbols.rs"));
+
+/// How many of the most recent function entries a trace keeps.
+const RECENT_CALLS: usize = 16;
+
+thread_local! {
+    static TRACING: Cell<bool> = const { Cell::new(false) };
+    static COST_BUDGET: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+pub fn set_clvm_tracing(enabled: bool) {
+    TRACING.with(|t| t.set(enabled));
+}
+
+pub fn clvm_tracing_enabled() -> bool {
+    TRACING.with(|t| t.get())
+}
+
+/// Run `f` with every [`run_traced`] call inside it drawing from a shared
+/// budget of `budget` CLVM cost. Once the budget is spent, further runs fail
+/// without being started.
+pub fn with_clvm_cost_budget<T>(budget: u64, f: impl FnOnce() -> T) -> T {
+    let outer = COST_BUDGET.with(|b| b.replace(Some(budget)));
+    let result = f();
+    COST_BUDGET.with(|b| b.set(outer));
+    result
+}
+
+fn cost_budget_exhausted() -> Error {
+    Error::StrErr("CLVM cost budget exhausted".to_string())
+}
+
+/// Charge a finished run against the active budget. Returns the error to
+/// report instead when the budget, not the block cost limit, stopped it.
+fn charge_cost_budget(response: &Response, max_cost: u64) -> Option<Error> {
+    let remaining = COST_BUDGET.with(|b| b.get())?;
+    match response {
+        Ok(reduction) => {
+            COST_BUDGET.with(|b| b.set(Some(remaining.saturating_sub(reduction.0))));
+            None
+        }
+        Err(EvalErr::CostExceeded) if max_cost < MAX_BLOCK_COST_CLVM => {
+            COST_BUDGET.with(|b| b.set(Some(0)));
+            Some(cost_budget_exhausted())
+        }
+        Err(_) => None,
+    }
+}
+
+pub fn lookup_symbol(hash: &[u8; 32]) -> Option<&'static FunctionSymbol> {
+    FUNCTION_SYMBOLS
+        .binary_search_by(|s| s.hash.cmp(hash))
+        .ok()
+        .map(|i| &FUNCTION_SYMBOLS[i])
+}
+
+/// The chialisp functions active when a traced run stopped.
+#[derive(Clone, Default)]
+pub struct ClvmTrace {
+    /// What was being run, e.g. "my turn handler".
+    pub label: String,
+    /// Innermost call last.
+    pub stack: Vec<&'static FunctionSymbol>,
+    /// The most recently entered functions, oldest first, including ones
+    /// that had already returned.
+    pub recent: Vec<&'static FunctionSymbol>,
+}
+
+fn write_symbol(f: &mut fmt::Formatter<'_>, symbol: &FunctionSymbol) -> fmt::Result {
+    write!(f, "{} {}", symbol.name, symbol.arguments)?;
+    if !symbol.file.is_empty() {
+        write!(f, " at {}:{}", symbol.file, symbol.line)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ClvmTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "clvm trace for {}:", self.label)?;
+        if self.stack.is_empty() {
+            write!(f, "\n  (no named function on the stack)")?;
+        }
+        for symbol in self.stack.iter().rev() {
+            write!(f, "\n  in ")?;
+            write_symbol(f, symbol)?;
+        }
+        if !self.recent.is_empty() {
+            write!(f, "\n  recent calls:")?;
+            for symbol in self.recent.iter() {
+                write!(f, "\n    {}", symbol.name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Traces end up inside `Error`, whose `Debug` is what reaches logs and
+// notifications, so render the readable form there too.
+impl fmt::Debug for ClvmTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Default)]
+struct TraceState {
+    names: HashMap<NodePtr, Option<&'static FunctionSymbol>>,
+    stack: Vec<&'static FunctionSymbol>,
+    recent: VecDeque<&'static FunctionSymbol>,
+}
+
+impl TraceState {
+    fn symbol_for(
+        &mut self,
+        allocator: &Allocator,
+        program: NodePtr,
+    ) -> Option<&'static FunctionSymbol> {
+        if !matches!(allocator.sexp(program), SExp::Pair(..)) {
+            return None;
+        }
+        *self
+            .names
+            .entry(program)
+            .or_insert_with(|| lookup_symbol(&tree_hash(allocator, program).to_bytes()))
+    }
+}
+
+/// Run `program` on `env`. With tracing enabled, a failure comes back as
+/// [`Error::Traced`] carrying the chialisp call stack, and a successful run
+/// also returns the trace so callers can attach it to a rejected result.
+pub fn run_traced(
+    allocator: &mut AllocEncoder,
+    label: &str,
+    program: NodePtr,
+    env: NodePtr,
+) -> (Result<NodePtr, Error>, Option<ClvmTrace>) {
+    let max_cost = match COST_BUDGET.with(|b| b.get()) {
+        Some(0) => return (Err(cost_budget_exhausted()), None),
+        Some(remaining) => remaining.min(MAX_BLOCK_COST_CLVM),
+        None => MAX_BLOCK_COST_CLVM,
+    };
+    if !clvm_tracing_enabled() {
+        let response = clvmr::run_program(
+            allocator.allocator(),
+            &chia_dialect(),
+            program,
+            env,
+            max_cost,
+        );
+        if let Some(error) = charge_cost_budget(&response, max_cost) {
+            return (Err(error), None);
+        }
+        return (response.into_gen().map(|r| r.1), None);
+    }
+
+    let state = Rc::new(RefCell::new(TraceState::default()));
+    let pre_state = state.clone();
+    let pre_eval: PreEval = Box::new(move |a: &mut Allocator, program, _env| {
+        let mut st = pre_state.borrow_mut();
+        let Some(symbol) = st.symbol_for(a, program) else {
+            return Ok(None);
+        };
+        st.stack.push(symbol);
+        if st.recent.len() == RECENT_CALLS {
+            st.recent.pop_front();
+        }
+        st.recent.push_back(symbol);
+        let post_state = pre_state.clone();
+        Ok(Some(Box::new(move |_: &mut Allocator, _| {
+            post_state.borrow_mut().stack.pop();
+        })))
+    });
+    let result = run_program_with_pre_eval(
+        allocator.allocator(),
+        &chia_dialect(),
+        program,
+        env,
+        max_cost,
+        Some(pre_eval),
+    );
+    let budget_error = charge_cost_budget(&result, max_cost);
+
+    let st = state.borrow();
+    let trace = ClvmTrace {
+        label: label.to_string(),
+        stack: st.stack.clone(),
+        recent: st.recent.iter().copied().collect(),
+    };
+    if let Some(error) = budget_error {
+        return (Err(error), Some(trace));
+    }
+    match result {
+        Ok(reduction) => (Ok(reduction.1), Some(trace)),
+        Err(e) => (
+            Err(Error::Traced {
+                error: Box::new(Error::ClvmErr(e)),
+                trace: trace.clone(),
+            }),
+            Some(trace),
+        ),
+    }
+}
+
+/// Attach `trace` to an error found in a traced run's result. Move rejections
+/// carry their trace themselves and are passed through unchanged.
+pub fn with_trace(error: Error, trace: &Option<ClvmTrace>) -> Error {
+    match (error, trace) {
+        (error @ (Error::Traced { .. } | Error::GameMoveRejected { .. }), _) => error,
+        (error, Some(trace)) => Error::Traced {
+            error: Box::new(error),
+            trace: trace.clone(),
+        },
+        (error, None) => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::puzzle_registry::TEST_MERGEIN;
+    use crate::common::types::Node;
+    use clvm_traits::ToClvm;
+
+    fn run_bad_mergein(
+        allocator: &mut AllocEncoder,
+    ) -> (Result<NodePtr, Error>, Option<ClvmTrace>) {
+        let program = TEST_MERGEIN.to_puzzle(allocator).unwrap();
+        let program = program.to_clvm(allocator).unwrap();
+        // `outer` must be a list; an atom makes `mergein` take `(f 5)`.
+        let five = allocator.allocator().new_small_number(5).unwrap();
+        let env = ((1, (2, ())), (Node(five), (0, ())))
+            .to_clvm(allocator)
+            .unwrap();
+        run_traced(allocator, "mergein test", program, env)
+    }
+
+    fn run_quote(allocator: &mut AllocEncoder) -> Result<NodePtr, Error> {
+        let a = allocator.allocator();
+        let one = a.one();
+        let program = a.new_pair(one, one).unwrap();
+        let env = a.nil();
+        run_traced(allocator, "quote", program, env).0
+    }
+
+    #[test]
+    fn cost_budget_is_shared_across_runs() {
+        let mut allocator = AllocEncoder::new();
+        let one_run = with_clvm_cost_budget(u64::MAX, || {
+            run_quote(&mut allocator).unwrap();
+            COST_BUDGET.with(|b| u64::MAX - b.get().unwrap())
+        });
+        assert!(one_run > 0);
+
+        with_clvm_cost_budget(one_run * 2, || {
+            assert!(run_quote(&mut allocator).is_ok());
+            assert!(run_quote(&mut allocator).is_ok());
+            assert!(matches!(run_quote(&mut allocator), Err(Error::StrErr(_))));
+        });
+        assert!(with_clvm_cost_budget(one_run - 1, || run_quote(&mut allocator)).is_err());
+        assert!(run_quote(&mut allocator).is_ok());
+    }
+
+    #[test]
+    fn untraced_failure_has_no_trace() {
+        let mut allocator = AllocEncoder::new();
+        let (result, trace) = run_bad_mergein(&mut allocator);
+        assert!(matches!(result, Err(Error::ClvmErr(_))));
+        assert!(trace.is_none());
+    }
+
+    #[test]
+    fn traced_failure_names_the_failing_function() {
+        let mut allocator = AllocEncoder::new();
+        set_clvm_tracing(true);
+        let (result, trace) = run_bad_mergein(&mut allocator);
+        set_clvm_tracing(false);
+
+        let trace = trace.expect("trace");
+        assert!(matches!(result, Err(Error::Traced { .. })));
+        let innermost = trace
+            .stack
+            .iter()
+            .rev()
+            .find(|s| !s.file.is_empty())
+            .expect("a located frame");
+        assert_eq!(innermost.name, "games.calpoker.onchain.make_cards.mergein");
+        assert_eq!(
+            innermost.file,
+            "clsp/games/calpoker/onchain/make_cards.clinc"
+        );
+        assert!(trace
+            .to_string()
+            .contains("clsp/games/calpoker/onchain/make_cards.clinc:47"));
+    }
+}
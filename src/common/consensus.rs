@@ -0,0 +1,163 @@
+//! Consensus validation of spend bundles through chia-consensus.
+//!
+//! The simulator validates every submitted bundle with these helpers, and
+//! [`spend_bundle_cost`] lets the library price spends it has not submitted.
+
+use chia_consensus::consensus_constants::ConsensusConstants;
+use chia_consensus::flags::MEMPOOL_MODE;
+use chia_consensus::spendbundle_validation::{
+    get_flags_for_height_and_constants, validate_clvm_and_signature,
+};
+use clvm_traits::ToClvm;
+
+use crate::common::constants::AGG_SIG_ME_ADDITIONAL_DATA;
+use crate::common::types::{
+    Aggsig, AllocEncoder, Amount, CoinID, CoinSpend, CoinString, Error, GetCoinStringParts, Hash,
+    IntoErr, Program, Puzzle, PuzzleHash, Spend, MAX_BLOCK_COST_CLVM,
+};
+
+/// Consensus constants for the chain this crate signs against: the
+/// `AGG_SIG_ME_ADDITIONAL_DATA` network with every fork active from genesis.
+pub fn consensus_constants() -> ConsensusConstants {
+    let agg_sig_data = chia_protocol::Bytes32::from(AGG_SIG_ME_ADDITIONAL_DATA);
+    let zero32 = chia_protocol::Bytes32::from([0u8; 32]);
+    ConsensusConstants {
+        slot_blocks_target: 32,
+        min_blocks_per_challenge_block: 16,
+        max_sub_slot_blocks: 128,
+        num_sps_sub_slot: 64,
+        sub_slot_iters_starting: 1 << 27,
+        difficulty_constant_factor: 1 << 67,
+        difficulty_starting: 7,
+        difficulty_change_max_factor: 3,
+        sub_epoch_blocks: 384,
+        epoch_blocks: 4608,
+        significant_bits: 8,
+        discriminant_size_bits: 1024,
+        number_zero_bits_plot_filter_v1: 9,
+        number_zero_bits_plot_filter_v2: 9,
+        min_plot_size_v1: 32,
+        max_plot_size_v1: 59,
+        plot_size_v2: 30,
+        sub_slot_time_target: 600,
+        num_sp_intervals_extra: 3,
+        max_future_time2: 120,
+        number_of_timestamps: 11,
+        genesis_challenge: zero32,
+        agg_sig_me_additional_data: agg_sig_data,
+        agg_sig_parent_additional_data: zero32,
+        agg_sig_puzzle_additional_data: zero32,
+        agg_sig_amount_additional_data: zero32,
+        agg_sig_puzzle_amount_additional_data: zero32,
+        agg_sig_parent_amount_additional_data: zero32,
+        agg_sig_parent_puzzle_additional_data: zero32,
+        genesis_pre_farm_pool_puzzle_hash: zero32,
+        genesis_pre_farm_farmer_puzzle_hash: zero32,
+        max_vdf_witness_size: 8,
+        mempool_block_buffer: 10,
+        max_coin_amount: u64::MAX,
+        max_block_cost_clvm: MAX_BLOCK_COST_CLVM,
+        cost_per_byte: 12000,
+        weight_proof_threshold: 2,
+        weight_proof_recent_blocks: 1000,
+        max_block_count_per_requests: 32,
+        blocks_cache_size: 4608 + 128 * 4,
+        max_generator_ref_list_size: 512,
+        pool_sub_slot_iters: 37_600_000_000,
+        hard_fork_height: 0,
+        hard_fork2_height: 0,
+        soft_fork8_height: 0,
+        plot_v1_phase_out_epoch_bits: 0,
+        plot_filter_128_height: u32::MAX,
+        plot_filter_64_height: u32::MAX,
+        plot_filter_32_height: u32::MAX,
+        min_plot_strength: 0,
+        max_plot_strength: 0,
+        plot_filter_v2_first_adjustment_height: 0,
+        plot_filter_v2_second_adjustment_height: 0,
+        plot_filter_v2_third_adjustment_height: 0,
+    }
+}
+
+/// Convert coin spends into a `chia_protocol` bundle with one aggregated
+/// signature, the form chia-consensus validates.
+pub fn to_protocol_spend_bundle(
+    allocator: &mut AllocEncoder,
+    txs: &[CoinSpend],
+) -> Result<chia_protocol::SpendBundle, Error> {
+    let mut protocol_spends = Vec::with_capacity(txs.len());
+    let mut agg_sig = Aggsig::default();
+
+    for (i, tx) in txs.iter().enumerate() {
+        let (parent, ph, amount) = tx.coin.get_coin_string_parts()?;
+        let parent_arr: [u8; 32] = parent
+            .bytes()
+            .try_into()
+            .map_err(|_| Error::StrErr("bad parent".into()))?;
+        let ph_arr: [u8; 32] = ph
+            .bytes()
+            .try_into()
+            .map_err(|_| Error::StrErr("bad ph".into()))?;
+        let coin = chia_protocol::Coin {
+            parent_coin_info: chia_protocol::Bytes32::from(parent_arr),
+            puzzle_hash: chia_protocol::Bytes32::from(ph_arr),
+            amount: amount.to_u64(),
+        };
+        let puzzle_bytes = tx.bundle.puzzle.to_program().bytes().to_vec();
+        let solution_node = tx.bundle.solution.to_clvm(allocator).into_gen()?;
+        let solution_bytes = Program::from_nodeptr(allocator, solution_node)?
+            .bytes()
+            .to_vec();
+        protocol_spends.push(chia_protocol::CoinSpend {
+            coin,
+            puzzle_reveal: chia_protocol::Bytes::from(puzzle_bytes).into(),
+            solution: chia_protocol::Bytes::from(solution_bytes).into(),
+        });
+        if i == 0 {
+            agg_sig = tx.bundle.signature.clone();
+        } else {
+            agg_sig += tx.bundle.signature.clone();
+        }
+    }
+
+    Ok(chia_protocol::SpendBundle {
+        coin_spends: protocol_spends,
+        aggregated_signature: agg_sig.to_bls(),
+    })
+}
+
+/// The reverse of [`to_protocol_spend_bundle`].  The aggregate signature
+/// lands on the first spend; the rest carry the identity signature.
+pub fn from_protocol_spend_bundle(
+    bundle: &chia_protocol::SpendBundle,
+) -> Result<Vec<CoinSpend>, Error> {
+    let mut signature = Some(Aggsig::from_bls(bundle.aggregated_signature.clone()));
+    Ok(bundle
+        .coin_spends
+        .iter()
+        .map(|spend| CoinSpend {
+            coin: CoinString::from_parts(
+                &CoinID::new(Hash::from_bytes(spend.coin.parent_coin_info.to_bytes())),
+                &PuzzleHash::from_bytes(spend.coin.puzzle_hash.to_bytes()),
+                &Amount::new(spend.coin.amount),
+            ),
+            bundle: Spend {
+                puzzle: Puzzle::from_bytes(spend.puzzle_reveal.as_ref()),
+                solution: Program::from_bytes(spend.solution.as_ref()).into(),
+                signature: signature.take().unwrap_or_default(),
+            },
+        })
+        .collect())
+}
+
+/// CLVM cost of `spends` as one bundle, as mempool validation charges it.
+/// The bundle must be valid on its own, signature included.
+pub fn spend_bundle_cost(allocator: &mut AllocEncoder, spends: &[CoinSpend]) -> Result<u64, Error> {
+    let bundle = to_protocol_spend_bundle(allocator, spends)?;
+    let constants = consensus_constants();
+    let flags = get_flags_for_height_and_constants(0, &constants) | MEMPOOL_MODE;
+    let (conditions, _) =
+        validate_clvm_and_signature(&bundle, constants.max_block_cost_clvm, &constants, flags)
+            .map_err(|e| Error::StrErr(format!("spend bundle failed validation: {:?}", e.1)))?;
+    Ok(conditions.cost)
+}
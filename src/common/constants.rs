@@ -9,8 +9,13 @@ lazy_static! {
 pub const CREATE_COIN: u32 = 51;
 pub const CREATE_COIN_ANNOUNCEMENT: u32 = 60;
 pub const ASSERT_COIN_ANNOUNCEMENT: u32 = 61;
+pub const ASSERT_PUZZLE_ANNOUNCEMENT: u32 = 63;
 pub const REM: u32 = 1;
+pub const RESERVE_FEE: u32 = 52;
+pub const ASSERT_SECONDS_RELATIVE: u32 = 80;
 pub const ASSERT_HEIGHT_RELATIVE: u32 = 82;
+pub const ASSERT_BEFORE_SECONDS_RELATIVE: u32 = 84;
+pub const ASSERT_BEFORE_HEIGHT_RELATIVE: u32 = 86;
 pub const ASSERT_BEFORE_HEIGHT_ABSOLUTE: u32 = 87;
 
 pub use chia_puzzles::{SINGLETON_LAUNCHER, SINGLETON_LAUNCHER_HASH};
@@ -4,6 +4,7 @@ use std::{cell::RefCell, collections::HashMap};
 use clvmr::serde::node_from_bytes;
 use clvmr::NodePtr;
 
+use crate::common::puzzle_registry;
 use crate::common::types::Error;
 use crate::common::types::IntoErr;
 use crate::common::types::{AllocEncoder, Puzzle};
@@ -31,7 +32,12 @@ pub fn read_preset_or_file(name: &str) -> Result<Vec<u8>, Error> {
     std::fs::read(name).map_err(|_| Error::StrErr(format!("Couldn't read filename {name}")))
 }
 
+/// Load a compiled puzzle, preferring the copy embedded by `build.rs` over
+/// the `.hex` file on disk.
 pub fn read_hex_puzzle(allocator: &mut AllocEncoder, name: &str) -> Result<Puzzle, Error> {
+    if let Some(embedded) = puzzle_registry::by_name(name) {
+        return embedded.to_puzzle(allocator);
+    }
     let raw = read_preset_or_file(name)?;
     let hex_data = std::str::from_utf8(&raw)
         .map_err(|e| Error::StrErr(format!("non-UTF8 hex file {name}: {e}")))?;
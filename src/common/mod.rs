@@ -1,5 +1,8 @@
 #[macro_use]
 pub mod types;
+pub mod clvm_trace;
+pub mod consensus;
 pub mod constants;
 pub mod load_clvm;
+pub mod puzzle_registry;
 pub mod standard_coin;
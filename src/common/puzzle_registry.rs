@@ -0,0 +1,72 @@
+//! Compiled puzzles embedded in the binary.
+//!
+//! `build.rs` compiles every `[compile]` entry in `chialisp.toml` into
+//! `OUT_DIR`, checks each output against its `_hash.hex` companion, and
+//! generates one [`EmbeddedPuzzle`] constant per output. Constants are named
+//! after the toml key plus the exported program, e.g. `CALPOKER_GENERATE_CALPOKER_FACTORY`
+//! for `calpoker_include_calpoker_factory.hex`. Nothing here touches the
+//! filesystem at runtime, so native and wasm builds load identical bytes.
+
+use clvm_utils::tree_hash_from_bytes;
+use clvmr::serde::node_from_bytes;
+
+use crate::common::types::{AllocEncoder, Error, IntoErr, Puzzle, PuzzleHash};
+
+#[derive(Debug)]
+pub struct EmbeddedPuzzle {
+    /// The `chialisp.toml` key, with `:<export>` for multi-program sources.
+    pub name: &'static str,
+    /// The `.clsp` source named in `chialisp.toml`.
+    pub source: &'static str,
+    /// Where `tools/build-chialisp.sh` puts the `.hex` file, relative to the
+    /// crate root.
+    pub path: &'static str,
+    /// Serialized CLVM.
+    pub bytes: &'static [u8],
+    /// Tree hash computed at build time.
+    pub tree_hash: [u8; 32],
+}
+
+include!(concat!(env!("OUT_DIR"), "/puzzle_registry.rs"));
+
+impl EmbeddedPuzzle {
+    pub fn puzzle_hash(&self) -> PuzzleHash {
+        PuzzleHash::from_bytes(self.tree_hash)
+    }
+
+    pub fn to_puzzle(&self, allocator: &mut AllocEncoder) -> Result<Puzzle, Error> {
+        let node = node_from_bytes(allocator.allocator(), self.bytes).into_gen()?;
+        Puzzle::from_nodeptr(allocator, node)
+    }
+}
+
+/// Look a puzzle up by registry name or by the `.hex` path it was built from.
+pub fn by_name(name: &str) -> Option<&'static EmbeddedPuzzle> {
+    ALL_PUZZLES
+        .iter()
+        .copied()
+        .find(|p| p.name == name || p.path == name)
+}
+
+pub fn by_hash(hash: &PuzzleHash) -> Option<&'static EmbeddedPuzzle> {
+    ALL_PUZZLES
+        .iter()
+        .copied()
+        .find(|p| p.tree_hash[..] == *hash.bytes())
+}
+
+/// Recompute every embedded puzzle's tree hash and compare it with the one
+/// recorded at build time.
+pub fn verify_registry() -> Result<(), Error> {
+    for puzzle in ALL_PUZZLES.iter() {
+        let hash = tree_hash_from_bytes(puzzle.bytes)
+            .map_err(|e| Error::StrErr(format!("{}: invalid CLVM: {e:?}", puzzle.name)))?;
+        if hash.as_ref() != puzzle.tree_hash {
+            return Err(Error::StrErr(format!(
+                "{}: embedded bytes do not match recorded tree hash",
+                puzzle.name
+            )));
+        }
+    }
+    Ok(())
+}
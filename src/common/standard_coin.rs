@@ -256,6 +256,20 @@ pub fn unsafe_sign_partial<Msg: AsRef<[u8]>>(sk: &PrivateKey, pk: &PublicKey, ms
     Aggsig::from_bls(chia_bls::sign_raw(sk.to_bls(), aug_msg))
 }
 
+/// Check one signer's share of an `unsafe_sign_partial` signature on its own,
+/// without the other half of the aggregate.
+pub fn verify_partial_signature<Msg: AsRef<[u8]>>(
+    sig: &Aggsig,
+    signer: &PublicKey,
+    aggregate: &PublicKey,
+    msg: Msg,
+) -> bool {
+    let mut aug_msg = aggregate.bytes().to_vec();
+    aug_msg.extend_from_slice(msg.as_ref());
+    sig.to_bls().pair(&chia_bls::PublicKey::generator())
+        == chia_bls::hash_to_g2(&aug_msg).pair(&signer.to_bls())
+}
+
 // From: https://github.com/Chia-Network/chia_rs/blob/2334c842f694444da317fa7432f308f159f62d70/chia-wallet/src/wallet.rs#L1166
 // which appears to still be in development.
 pub fn agg_sig_me_message(
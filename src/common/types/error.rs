@@ -3,6 +3,8 @@ use clvmr::error::EvalErr;
 use serde::{Deserialize, Serialize, Serializer};
 use std::io;
 
+use crate::common::clvm_trace::ClvmTrace;
+
 /// Error type
 #[derive(Debug)]
 pub enum Error {
@@ -16,7 +18,16 @@ pub enum Error {
     JsonErr(serde_json::Error),
     HexErr(hex::FromHexError),
     Channel(String),
-    GameMoveRejected { tag: Vec<u8>, message: Vec<u8> },
+    GameMoveRejected {
+        tag: Vec<u8>,
+        message: Vec<u8>,
+        trace: Option<ClvmTrace>,
+    },
+    /// A CLVM run failed with tracing enabled; see [`crate::common::clvm_trace`].
+    Traced {
+        error: Box<Error>,
+        trace: ClvmTrace,
+    },
 }
 
 impl std::error::Error for Error {}
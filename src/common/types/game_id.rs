@@ -7,7 +7,9 @@ use crate::common::types::{atom_from_clvm, u64_from_atom, AllocEncoder, Error};
 use clvm_traits::{ClvmEncoder, ToClvm, ToClvmError};
 
 /// Game ID — a nonce number that uniquely identifies a game within a channel.
-#[derive(Default, Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash)]
+#[derive(
+    Default, Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Hash,
+)]
 pub struct GameID(pub u64);
 
 impl GameID {
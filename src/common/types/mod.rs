@@ -48,4 +48,4 @@ pub use self::spend::{
     check_for_hex, convert_coinset_org_spend_to_spend, BrokenOutCoinSpendInfo, CoinSpend,
     CoinsetCoin, CoinsetSpendBundle, CoinsetSpendRecord, Spend, SpendBundle,
 };
-pub use self::timeout::Timeout;
+pub use self::timeout::{Timeout, TimeoutUnit};
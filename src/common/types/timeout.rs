@@ -1,28 +1,101 @@
+use std::cmp::Ordering;
 use std::ops::Add;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use clvmr::allocator::NodePtr;
+use clvmr::allocator::{NodePtr, SExp};
 
 use clvm_traits::{ClvmEncoder, ToClvm, ToClvmError};
 
+use crate::common::constants::{
+    ASSERT_BEFORE_HEIGHT_RELATIVE, ASSERT_BEFORE_SECONDS_RELATIVE, ASSERT_HEIGHT_RELATIVE,
+    ASSERT_SECONDS_RELATIVE,
+};
 use crate::common::types::{atom_from_clvm, u64_from_atom, AllocEncoder, Error};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Timeout(u64);
+/// What a relative `Timeout` counts.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TimeoutUnit {
+    /// Blocks, enforced with `ASSERT_HEIGHT_RELATIVE`.
+    #[default]
+    Blocks,
+    /// Wall-clock seconds, enforced with `ASSERT_SECONDS_RELATIVE`.
+    Seconds,
+}
+
+/// A relative timelock on a coin, in blocks or seconds.
+///
+/// In CLVM a block timeout is a plain integer, as it always was, and a
+/// seconds timeout is the one-element list `(seconds)`.  The referee and the
+/// unroll conditions pick the matching assertion from that shape.
+///
+/// Timeouts of different units neither add nor compare.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeout {
+    value: u64,
+    unit: TimeoutUnit,
+}
 
 impl Timeout {
     pub fn new(t: u64) -> Self {
-        Timeout(t)
+        Timeout {
+            value: t,
+            unit: TimeoutUnit::Blocks,
+        }
+    }
+
+    pub fn seconds(t: u64) -> Self {
+        Timeout {
+            value: t,
+            unit: TimeoutUnit::Seconds,
+        }
     }
 
+    /// The count, in whichever unit this timeout uses.
     pub fn to_u64(&self) -> u64 {
-        self.0
+        self.value
+    }
+
+    pub fn unit(&self) -> TimeoutUnit {
+        self.unit
+    }
+
+    pub fn blocks(&self) -> Option<u64> {
+        (self.unit == TimeoutUnit::Blocks).then_some(self.value)
+    }
+
+    pub fn as_seconds(&self) -> Option<u64> {
+        (self.unit == TimeoutUnit::Seconds).then_some(self.value)
+    }
+
+    /// Condition opcode that holds a coin until this timeout has passed.
+    pub fn assert_condition(&self) -> u32 {
+        match self.unit {
+            TimeoutUnit::Blocks => ASSERT_HEIGHT_RELATIVE,
+            TimeoutUnit::Seconds => ASSERT_SECONDS_RELATIVE,
+        }
+    }
+
+    /// Condition opcode that closes a window of this length.
+    pub fn assert_before_condition(&self) -> u32 {
+        match self.unit {
+            TimeoutUnit::Blocks => ASSERT_BEFORE_HEIGHT_RELATIVE,
+            TimeoutUnit::Seconds => ASSERT_BEFORE_SECONDS_RELATIVE,
+        }
     }
 
     pub fn from_clvm(allocator: &AllocEncoder, clvm: NodePtr) -> Result<Self, Error> {
-        if let Some(amt) = atom_from_clvm(allocator, clvm).and_then(|a| u64_from_atom(&a)) {
-            Ok(Timeout::new(amt))
+        let (node, unit) = match allocator.allocator_ref().sexp(clvm) {
+            SExp::Pair(first, rest)
+                if matches!(allocator.allocator_ref().sexp(rest), SExp::Atom)
+                    && allocator.allocator_ref().atom_len(rest) == 0 =>
+            {
+                (first, TimeoutUnit::Seconds)
+            }
+            _ => (clvm, TimeoutUnit::Blocks),
+        };
+        if let Some(amt) = atom_from_clvm(allocator, node).and_then(|a| u64_from_atom(&a)) {
+            Ok(Timeout { value: amt, unit })
         } else {
             Err(Error::StrErr("bad timeout".to_string()))
         }
@@ -31,20 +104,118 @@ impl Timeout {
 
 impl std::fmt::Display for Timeout {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match self.unit {
+            TimeoutUnit::Blocks => write!(f, "{}", self.value),
+            TimeoutUnit::Seconds => write!(f, "{}s", self.value),
+        }
+    }
+}
+
+impl PartialOrd for Timeout {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.unit == other.unit).then(|| self.value.cmp(&other.value))
     }
 }
 
 impl Add for Timeout {
-    type Output = Timeout;
+    type Output = Result<Timeout, Error>;
 
-    fn add(self, rhs: Self) -> Timeout {
-        Timeout::new(self.0 + rhs.0)
+    fn add(self, rhs: Self) -> Result<Timeout, Error> {
+        if self.unit != rhs.unit {
+            return Err(Error::StrErr(format!(
+                "cannot add timeouts {self} and {rhs} of different units"
+            )));
+        }
+        let value = self
+            .value
+            .checked_add(rhs.value)
+            .ok_or_else(|| Error::StrErr(format!("timeout {self} + {rhs} overflows")))?;
+        Ok(Timeout {
+            value,
+            unit: self.unit,
+        })
     }
 }
 
 impl<E: ClvmEncoder<Node = NodePtr>> ToClvm<E> for Timeout {
     fn to_clvm(&self, encoder: &mut E) -> Result<<E as ClvmEncoder>::Node, ToClvmError> {
-        self.0.to_clvm(encoder)
+        match self.unit {
+            TimeoutUnit::Blocks => self.value.to_clvm(encoder),
+            TimeoutUnit::Seconds => (self.value, ()).to_clvm(encoder),
+        }
+    }
+}
+
+/// Block timeouts keep their historical encoding as a bare integer, so saved
+/// sessions and peers from before seconds timeouts still read them.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TimeoutRepr {
+    Blocks(u64),
+    Seconds { seconds: u64 },
+}
+
+impl Serialize for Timeout {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.unit {
+            TimeoutUnit::Blocks => TimeoutRepr::Blocks(self.value),
+            TimeoutUnit::Seconds => TimeoutRepr::Seconds {
+                seconds: self.value,
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timeout {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match TimeoutRepr::deserialize(deserializer)? {
+            TimeoutRepr::Blocks(value) => Timeout::new(value),
+            TimeoutRepr::Seconds { seconds } => Timeout::seconds(seconds),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_timeout_keeps_integer_encoding() {
+        let timeout = Timeout::new(15);
+        assert_eq!(serde_json::to_string(&timeout).unwrap(), "15");
+        let bytes = bencodex::to_vec(&timeout).expect("serialize");
+        assert_eq!(bencodex::to_vec(&15u64).expect("serialize"), bytes);
+        let back: Timeout = bencodex::from_slice(&bytes).expect("deserialize");
+        assert_eq!(back, timeout);
+    }
+
+    #[test]
+    fn seconds_timeout_round_trips() {
+        let timeout = Timeout::seconds(600);
+        let json = serde_json::to_string(&timeout).unwrap();
+        assert_eq!(serde_json::from_str::<Timeout>(&json).unwrap(), timeout);
+        let bytes = bencodex::to_vec(&timeout).expect("serialize");
+        let back: Timeout = bencodex::from_slice(&bytes).expect("deserialize");
+        assert_eq!(back, timeout);
+    }
+
+    #[test]
+    fn units_do_not_mix() {
+        let sum = (Timeout::new(5) + Timeout::new(10)).expect("same units");
+        assert_eq!(sum, Timeout::new(15));
+        assert!((Timeout::new(5) + Timeout::seconds(10)).is_err());
+        assert!(Timeout::new(5) < Timeout::new(10));
+        assert_eq!(Timeout::new(5).partial_cmp(&Timeout::seconds(10)), None);
+        assert_eq!(Timeout::seconds(10).partial_cmp(&Timeout::new(5)), None);
+    }
+
+    #[test]
+    fn clvm_shape_carries_the_unit() {
+        let mut allocator = AllocEncoder::new();
+        for timeout in [Timeout::new(15), Timeout::seconds(600)] {
+            let node = timeout.to_clvm(&mut allocator).expect("to_clvm");
+            assert_eq!(Timeout::from_clvm(&allocator, node).unwrap(), timeout);
+        }
     }
 }
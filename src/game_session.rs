@@ -4,37 +4,48 @@ use clvm_traits::ToClvm;
 
 use rand::Rng;
 
+use bencodex::DecoderConfig;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+use crate::attestation::{GameResultAttestation, PendingAttestation};
 #[cfg(test)]
 use crate::channel_state::types::ChannelCoinSpendInfo;
 use crate::channel_state::types::{ChannelEnv, ChannelPrivateKeys, ReadableMove};
 use crate::channel_state::ChannelState;
 use crate::common::constants::CREATE_COIN;
 use crate::common::standard_coin::{
-    sign_agg_sig_me, solution_for_conditions, standard_solution_partial, ChiaIdentity,
+    private_to_public_key, sign_agg_sig_me, solution_for_conditions, standard_solution_partial,
+    ChiaIdentity,
 };
 use crate::common::types::{
     Aggsig, AllocEncoder, Amount, CoinCondition, CoinSpend, CoinString, Error, GameID, GameType,
-    Hash, IntoErr, Program, ProgramRef, Puzzle, PuzzleHash, Sha256tree, Spend, SpendBundle,
-    Timeout, ToQuotedProgram,
+    Hash, IntoErr, Program, ProgramRef, PublicKey, Puzzle, PuzzleHash, Sha256tree, Spend,
+    SpendBundle, Timeout, ToQuotedProgram,
 };
+use crate::payout::PayoutTerms;
+use crate::session_journal::{JournalOutput, SessionInput, SessionJournal};
+use crate::session_match::{MatchScore, MatchState, MatchStep, MatchTerms};
 use crate::session_phases::effects::{
     apply_effects, ChannelStatus, ChannelStatusSnapshot, CoinOfInterest, Effect, FailedGameAction,
     GameNotification, GameSessionEvent, GameSessionEventQueue, ResyncInfo, SessionDisposition,
-    TimeoutClaimSemantic,
+    SettlementOutcome, TimeoutClaimSemantic,
+};
+use crate::session_phases::go_on_chain_preview::GoOnChainPreview;
+use crate::session_phases::handshake::{
+    NegotiatedCapabilities, FEATURE_MATCH_STOP, FEATURE_RESULT_ATTESTATION,
 };
 use crate::session_phases::handshake_initiator::HandshakeInitiatorPhase;
 use crate::session_phases::handshake_receiver::HandshakeReceiverPhase;
 use crate::session_phases::proposal::GameProposal;
 use crate::session_phases::types::{
-    ChannelFundingWallet, GameFactory, OffChainPhaseInit, PacketSender, PeerMessage,
+    ChannelFundingWallet, GameFactory, OffChainPhaseInit, PacketSender, PeerLimits, PeerMessage,
     SpendWalletReceiver, ToLocalUI, WalletSpendInterface,
 };
+use crate::spectator::{SpectatorEvent, SpectatorFeed, SpectatorMove};
 
 #[cfg(test)]
 use crate::session_phases::spend_channel_coin_phase::SpendChannelCoinPhase;
-#[cfg(test)]
 use crate::session_phases::OffChainPhase;
 
 #[typetag::serde]
@@ -106,6 +117,12 @@ pub trait PeerLifecyclePhase {
     fn handshake_finished(&self) -> bool {
         true
     }
+    /// Swap the factory used for future proposals of an already registered
+    /// game type. Returns false when this phase no longer makes proposals.
+    #[cfg(feature = "hot-reload")]
+    fn replace_game_factory(&mut self, _game_type: &GameType, _factory: GameFactory) -> bool {
+        false
+    }
     fn channel_offer(
         &mut self,
         _env: &mut ChannelEnv<'_>,
@@ -156,6 +173,16 @@ pub trait PeerLifecyclePhase {
             "accept_proposal: not in off-chain phase".to_string(),
         ))
     }
+    fn accept_proposal_with_payout(
+        &mut self,
+        _env: &mut ChannelEnv<'_>,
+        _game_id: &GameID,
+        _payout: &PayoutTerms,
+    ) -> Result<Vec<Effect>, Error> {
+        Err(Error::StrErr(
+            "accept_proposal_with_payout: not in off-chain phase".to_string(),
+        ))
+    }
     fn cancel_proposal(
         &mut self,
         _env: &mut ChannelEnv<'_>,
@@ -199,6 +226,12 @@ pub trait PeerLifecyclePhase {
         false
     }
 
+    /// True while this phase has sent `RequestPotato` and the potato has not
+    /// come back yet.
+    fn awaiting_potato(&self) -> bool {
+        false
+    }
+
     /// Coin ids worth surfacing in the dashboard (channel/unroll/change/game/
     /// game-change), each tagged with its kind. Defaults to none, which is the
     /// correct answer during handshake before any coin exists.
@@ -254,12 +287,26 @@ pub trait MessagePeerQueue {
 ///
 /// A coin first discovered already spent is represented as `Created` followed by
 /// `Spent`, allowing creation to transition the phase before its spend arrives.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CoinObservation {
     Created(CoinString),
     Spent(CoinString),
 }
 
+/// Decode limits for a saved session.  Saves carry whole puzzles (the krunk
+/// dictionary alone is over half a MiB), so these are far looser than
+/// [`PEER_MESSAGE_DECODE_LIMITS`](crate::session_phases::types::PEER_MESSAGE_DECODE_LIMITS), but a corrupted length prefix or runaway
+/// nesting still fails before allocating without bound.  Saves are written by
+/// the host rather than an adversary, so canonical form is not required.
+pub const SESSION_DECODE_LIMITS: DecoderConfig =
+    DecoderConfig::bounded(256, 1 << 20, 64 << 20, 1 << 30);
+
+/// Decode a saved session (or anything wrapping one) under
+/// [`SESSION_DECODE_LIMITS`].
+pub fn decode_saved_session<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    bencodex::from_slice_with(bytes, &SESSION_DECODE_LIMITS).into_gen()
+}
+
 pub enum WalletBootstrapState {
     PartlySigned(Spend),
     FullySigned(Spend),
@@ -341,9 +388,39 @@ struct GameSessionState {
     channel_established: bool,
     #[serde(default)]
     channel_expired: bool,
+    #[serde(default)]
+    liveness: LivenessPolicy,
+    #[serde(default)]
+    peer_waiting_since: Option<u64>,
+    #[serde(default)]
+    potato_requested_at: Option<u64>,
+    #[serde(default)]
+    liveness_advisory: Option<String>,
+    #[serde(default)]
+    peer_limits: PeerLimits,
+    #[serde(default)]
+    inbound_overflow: bool,
+    #[serde(default)]
+    game_match: Option<MatchState>,
+    /// Operator fee of each game started and not yet settled, reported on
+    /// its `GameSettled`.
+    #[serde(default)]
+    game_fees: BTreeMap<GameID, Amount>,
+    /// Spectator feeds of each game we authorized observers for.
+    #[serde(default)]
+    spectators: BTreeMap<GameID, Vec<SpectatorFeed>>,
+    /// Result signatures being exchanged for games accepted off-chain.
+    #[serde(default)]
+    attestations: BTreeMap<GameID, PendingAttestation>,
+    /// What the handshake agreed with the peer, once it has.
+    #[serde(default)]
+    negotiated: Option<NegotiatedCapabilities>,
 
     #[serde(skip)]
     events: GameSessionEventQueue,
+    /// How many of `events` the match has already looked at.
+    #[serde(skip)]
+    match_cursor: usize,
 }
 
 impl PacketSender for GameSessionState {
@@ -352,6 +429,15 @@ impl PacketSender for GameSessionState {
             return Ok(());
         }
         let msg_data = bencodex::to_vec(&msg).map_err(|e| Error::StrErr(format!("{e:?}")))?;
+        if let Some(negotiated) = &self.negotiated {
+            if msg_data.len() as u64 > negotiated.max_message_size {
+                return Err(Error::StrErr(format!(
+                    "outbound message of {} bytes is over the peer's limit of {}",
+                    msg_data.len(),
+                    negotiated.max_message_size
+                )));
+            }
+        }
         self.events
             .push_back(GameSessionEvent::OutboundMessage(msg_data));
         Ok(())
@@ -406,21 +492,50 @@ pub struct GameSession {
     peer: Box<dyn PeerLifecyclePhase>,
     amount: Amount,
     last_channel_status: Option<ChannelStatusSnapshot>,
+    #[serde(default)]
+    journal: Option<SessionJournal>,
     #[cfg(test)]
     #[serde(skip)]
     saved_unroll_snapshot: Option<ChannelCoinSpendInfo>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameSessionConfig {
     pub game_types: BTreeMap<GameType, GameFactory>,
     pub have_potato: bool,
     pub identity: ChiaIdentity,
     pub my_contribution: Amount,
     pub their_contribution: Amount,
+    /// Blocks until an unconfirmed channel creation expires.  Always counted
+    /// in blocks, since it becomes an absolute height.
     pub channel_timeout: Timeout,
+    /// Preemption window on the unroll coin, in blocks or seconds.  Seconds
+    /// only mature if the host reports block timestamps; see
+    /// [`TransactionManager::report_timestamp`](crate::transaction_manager::TransactionManager::report_timestamp).
     pub unroll_timeout: Timeout,
     pub reward_puzzle_hash: PuzzleHash,
+    #[serde(default)]
+    pub liveness: LivenessPolicy,
+    #[serde(default)]
+    pub peer_limits: PeerLimits,
+}
+
+/// How long, in blocks, the session waits on an unresponsive peer before
+/// warning through the `Active` status advisory and then going on chain by
+/// itself.  Each threshold is optional; the default policy never acts.
+///
+/// The stall clock runs while the peer holds the potato with something still
+/// owed to us (a running game or a clean shutdown) and restarts only when a
+/// peer message hands us the potato or advances the state number.  The potato
+/// clock runs from our `RequestPotato` until the potato comes back, however
+/// chatty the peer is in between.  A quiet channel with no games and nothing
+/// requested never times out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LivenessPolicy {
+    pub stall_warning_blocks: Option<u64>,
+    pub stall_go_on_chain_blocks: Option<u64>,
+    pub potato_warning_blocks: Option<u64>,
+    pub potato_go_on_chain_blocks: Option<u64>,
 }
 
 /// Scan a wallet `SpendBundle` for settlement-payment outputs created by
@@ -504,7 +619,19 @@ impl GameSession {
                 channel_creation_expiry: None,
                 channel_established: false,
                 channel_expired: false,
+                liveness: config.liveness,
+                peer_waiting_since: None,
+                potato_requested_at: None,
+                liveness_advisory: None,
+                peer_limits: config.peer_limits.clone(),
+                inbound_overflow: false,
+                game_match: None,
+                game_fees: BTreeMap::new(),
+                spectators: BTreeMap::new(),
+                attestations: BTreeMap::new(),
+                negotiated: None,
                 events: GameSessionEventQueue::default(),
+                match_cursor: 0,
                 inbound_messages: VecDeque::default(),
             },
             peer: {
@@ -517,6 +644,7 @@ impl GameSession {
                     channel_timeout: config.channel_timeout,
                     unroll_timeout: config.unroll_timeout,
                     reward_puzzle_hash: config.reward_puzzle_hash,
+                    peer_limits: config.peer_limits,
                 };
                 if config.have_potato {
                     Box::new(HandshakeInitiatorPhase::new(phi)) as Box<dyn PeerLifecyclePhase>
@@ -526,6 +654,7 @@ impl GameSession {
             },
             amount: config.my_contribution + config.their_contribution,
             last_channel_status: None,
+            journal: None,
             #[cfg(test)]
             saved_unroll_snapshot: None,
         }
@@ -534,6 +663,56 @@ impl GameSession {
         let private_keys: ChannelPrivateKeys = rng.random();
         GameSession::new_with_keys(config, private_keys)
     }
+
+    /// Like [`GameSession::new_with_keys`], but record every host call in a
+    /// [`SessionJournal`] that [`crate::session_journal::replay`] can re-run.
+    /// Recording stops once the journal's entries reach `limit` encoded bytes
+    /// (see [`crate::session_journal::DEFAULT_JOURNAL_LIMIT`]).
+    pub fn new_with_journal(
+        config: GameSessionConfig,
+        private_keys: ChannelPrivateKeys,
+        limit: usize,
+    ) -> Self {
+        let journal = SessionJournal::new(
+            config.clone(),
+            private_to_public_key(&private_keys.my_referee_private_key),
+            limit,
+        );
+        let mut session = GameSession::new_with_keys(config, private_keys);
+        session.journal = Some(journal);
+        session
+    }
+
+    pub fn journal(&self) -> Option<&SessionJournal> {
+        self.journal.as_ref()
+    }
+
+    /// Stop journaling and hand back what was recorded so far.
+    pub fn take_journal(&mut self) -> Option<SessionJournal> {
+        self.journal.take()
+    }
+
+    /// The journal entry for a host call, built only when journaling.
+    fn journal_input(&self, input: impl FnOnce() -> SessionInput) -> Option<SessionInput> {
+        self.journal.as_ref().map(|_| input())
+    }
+
+    /// Run one host call, recording it if this session keeps a journal. The
+    /// journal is held aside during the call so that calls the session makes
+    /// on itself are not recorded twice.
+    fn journaled<T: JournalOutput>(
+        &mut self,
+        input: Option<SessionInput>,
+        call: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let (Some(input), Some(mut journal)) = (input, self.journal.take()) else {
+            return call(self);
+        };
+        let result = call(self);
+        journal.record(input, &result);
+        self.journal = Some(journal);
+        result
+    }
 }
 
 impl ChannelFundingWallet for GameSessionState {
@@ -741,6 +920,17 @@ impl GameSession {
         &mut self,
         allocator: &mut AllocEncoder,
         launcher_coin: CoinString,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::ProvideLauncherCoin(launcher_coin.clone())),
+            |this| this.provide_launcher_coin_inner(allocator, launcher_coin),
+        )
+    }
+
+    fn provide_launcher_coin_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        launcher_coin: CoinString,
     ) -> Result<(), Error> {
         let effects = {
             let mut env = ChannelEnv::new(allocator)?;
@@ -754,6 +944,17 @@ impl GameSession {
         &mut self,
         allocator: &mut AllocEncoder,
         bundle: SpendBundle,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::ProvideCoinSpendBundle(bundle.clone())),
+            |this| this.provide_coin_spend_bundle_inner(allocator, bundle),
+        )
+    }
+
+    fn provide_coin_spend_bundle_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        bundle: SpendBundle,
     ) -> Result<(), Error> {
         let bundle = claim_settlement_coins(allocator, bundle);
         let effects = {
@@ -768,6 +969,17 @@ impl GameSession {
         &mut self,
         _allocator: &mut AllocEncoder,
         reason: String,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::WalletCallbackFailed(reason.clone())),
+            |this| this.wallet_callback_failed_inner(_allocator, reason),
+        )
+    }
+
+    fn wallet_callback_failed_inner(
+        &mut self,
+        _allocator: &mut AllocEncoder,
+        reason: String,
     ) -> Result<(), Error> {
         self.peer.wallet_callback_failed(reason);
         self.detect_phase_transition();
@@ -777,6 +989,12 @@ impl GameSession {
     /// Stop participating in this session immediately. This is a local user
     /// choice, not a protocol transition or a claim about on-chain resolution.
     pub fn abandon(&mut self) -> Result<(), Error> {
+        self.journaled(self.journal_input(|| SessionInput::Abandon), |this| {
+            this.abandon_inner()
+        })
+    }
+
+    fn abandon_inner(&mut self) -> Result<(), Error> {
         if matches!(
             self.state.session_disposition,
             Some(SessionDisposition::AwaitOutboundTerminal)
@@ -798,6 +1016,7 @@ impl GameSession {
         // terminal snapshot. Older notifications must not race the final
         // Abandoned status through an asynchronous host.
         self.state.events.clear();
+        self.state.match_cursor = 0;
         self.state.resync = None;
         self.emit_channel_status_if_changed();
     }
@@ -805,6 +1024,13 @@ impl GameSession {
     /// Finalize the local half of a zero-payout clean shutdown after the host
     /// has made the complete close spend available to the peer.
     pub fn complete_outbound_terminal_handoff(&mut self) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::CompleteOutboundTerminalHandoff),
+            |this| this.complete_outbound_terminal_handoff_inner(),
+        )
+    }
+
+    fn complete_outbound_terminal_handoff_inner(&mut self) -> Result<(), Error> {
         if !matches!(
             self.state.session_disposition,
             Some(SessionDisposition::AwaitOutboundTerminal)
@@ -824,20 +1050,42 @@ impl GameSession {
     pub fn flush_and_collect(
         &mut self,
         allocator: &mut AllocEncoder,
+    ) -> Result<DrainResult, Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::FlushAndCollect),
+            |this| this.flush_and_collect_inner(allocator),
+        )
+    }
+
+    fn flush_and_collect_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
     ) -> Result<DrainResult, Error> {
         if self.state.session_disposition.is_some() {
+            self.advance_match(allocator)?;
             return Ok(DrainResult {
-                events: std::mem::take(&mut self.state.events),
+                events: self.take_events(),
                 resync: self.state.resync.take(),
             });
         }
+        if std::mem::take(&mut self.state.inbound_overflow) {
+            self.state.inbound_messages.clear();
+            self.handle_peer_protocol_error(
+                allocator,
+                Error::StrErr("peer overran the inbound message queue".to_string()),
+            )?;
+        }
         while let Some(msg) = self.state.inbound_messages.pop_front() {
+            let progress = self.peer_progress_marker();
             let recv_result = {
                 let mut env = ChannelEnv::new(allocator)?;
                 self.peer.received_message(&mut env, msg)
             };
             match recv_result {
-                Ok(effects) => self.process_effects(effects, allocator)?,
+                Ok(effects) => {
+                    self.process_effects(effects, allocator)?;
+                    self.note_peer_progress(progress);
+                }
                 Err(e) => {
                     self.handle_peer_protocol_error(allocator, e)?;
                     break;
@@ -849,6 +1097,26 @@ impl GameSession {
             }
         }
 
+        // Match steps queue actions of their own, so keep flushing
+        // until the match has seen everything and has nothing to do.
+        self.advance_match(allocator)?;
+        loop {
+            self.flush_pending_actions_into_events(allocator)?;
+            if !self.advance_match(allocator)? {
+                break;
+            }
+        }
+
+        Ok(DrainResult {
+            events: self.take_events(),
+            resync: self.state.resync.take(),
+        })
+    }
+
+    fn flush_pending_actions_into_events(
+        &mut self,
+        allocator: &mut AllocEncoder,
+    ) -> Result<(), Error> {
         let res = if self.state.session_disposition.is_some() {
             None
         } else {
@@ -869,11 +1137,108 @@ impl GameSession {
             }
             None => {}
         }
+        Ok(())
+    }
 
-        Ok(DrainResult {
-            events: std::mem::take(&mut self.state.events),
-            resync: self.state.resync.take(),
-        })
+    fn take_events(&mut self) -> GameSessionEventQueue {
+        self.state.match_cursor = 0;
+        std::mem::take(&mut self.state.events)
+    }
+
+    /// Feed the match the notifications queued since it last looked and
+    /// carry out whatever it asks for: accepting or declining the peer's
+    /// next hand, or proposing ours.  Returns whether it acted.
+    fn advance_match(&mut self, allocator: &mut AllocEncoder) -> Result<bool, Error> {
+        let Some(mut game_match) = self.state.game_match.take() else {
+            self.state.match_cursor = self.state.events.len();
+            return Ok(false);
+        };
+        let notifications: Vec<GameNotification> = self
+            .state
+            .events
+            .iter()
+            .skip(self.state.match_cursor)
+            .filter_map(|event| match event {
+                GameSessionEvent::Notification(notification) => Some(notification.clone()),
+                _ => None,
+            })
+            .collect();
+        self.state.match_cursor = self.state.events.len();
+
+        if let Ok(ch) = self.peer.channel_state() {
+            game_match.learn_stakes(&|id| {
+                ch.find_proposal(id)
+                    .map(|p| (p.my_contribution.clone(), p.their_contribution.clone()))
+                    .or_else(|| {
+                        ch.find_live_game(id)
+                            .map(|g| (g.my_contribution.clone(), g.their_contribution.clone()))
+                    })
+            });
+        }
+        let mut steps = Vec::new();
+        for notification in &notifications {
+            let (more_steps, notes) = game_match.observe(notification);
+            steps.extend(more_steps);
+            for note in notes {
+                self.state
+                    .events
+                    .push_back(GameSessionEvent::Notification(note));
+            }
+        }
+        if self.state.is_on_chain
+            || self.state.is_failed
+            || self.state.session_disposition.is_some()
+        {
+            game_match.interrupt();
+        }
+        let can_send_stop = self
+            .state
+            .negotiated
+            .as_ref()
+            .is_some_and(|negotiated| negotiated.supports_feature(FEATURE_MATCH_STOP));
+        steps.extend(game_match.next_step(can_send_stop));
+
+        let acted = !steps.is_empty();
+        for step in steps {
+            let result = {
+                let mut env = ChannelEnv::new(allocator)?;
+                match step {
+                    MatchStep::Propose => self
+                        .peer
+                        .propose_games(&mut env, std::slice::from_ref(&game_match.terms().proposal))
+                        .map(|(ids, effects)| {
+                            if let Some(id) = ids.first() {
+                                game_match.proposed(*id);
+                            }
+                            effects
+                        }),
+                    MatchStep::Accept(id) => self.peer.accept_proposal(&mut env, &id),
+                    MatchStep::Decline(id) => self.peer.cancel_proposal(&mut env, &id),
+                    MatchStep::SendStop => Ok(vec![Effect::PeerStopMatch]),
+                }
+            };
+            match result {
+                Ok(effects) => self.process_effects(effects, allocator)?,
+                Err(e) => {
+                    self.state
+                        .events
+                        .push_back(GameSessionEvent::Log(format!("match step failed: {e:?}")));
+                    game_match.interrupt();
+                }
+            }
+        }
+
+        if let Some(reason) = game_match.ended() {
+            self.state.events.push_back(GameSessionEvent::Notification(
+                GameNotification::MatchEnded {
+                    reason,
+                    score: game_match.score().clone(),
+                },
+            ));
+        } else {
+            self.state.game_match = Some(game_match);
+        }
+        Ok(acted)
     }
 
     fn detect_phase_transition(&mut self) {
@@ -920,6 +1285,14 @@ impl GameSession {
         {
             return true;
         }
+        // Liveness warnings come and go while a clean shutdown waits on the
+        // peer without changing its state.
+        if new_state == Some(&ChannelStatus::ShuttingDown)
+            && new.as_ref().map(|snapshot| &snapshot.advisory)
+                != old.as_ref().map(|snapshot| &snapshot.advisory)
+        {
+            return true;
+        }
         // In Active state, re-emit on balance changes (potato firings).
         // In other states, suppress same-state re-emissions (e.g. coin
         // changes within Unrolling).
@@ -968,7 +1341,17 @@ impl GameSession {
             snapshot.session_disposition = Some(session_disposition);
             Some(snapshot)
         } else {
-            self.peer.channel_status_snapshot()
+            self.peer.channel_status_snapshot().map(|mut snapshot| {
+                if snapshot.advisory.is_none()
+                    && matches!(
+                        snapshot.state,
+                        ChannelStatus::Active | ChannelStatus::ShuttingDown
+                    )
+                {
+                    snapshot.advisory = self.state.liveness_advisory.clone();
+                }
+                snapshot
+            })
         };
         if Self::should_emit_status(&self.last_channel_status, &snapshot) {
             if let Some(ref snap) = snapshot {
@@ -1001,6 +1384,16 @@ impl GameSession {
     pub(crate) fn timeout_claim_submitted(
         &mut self,
         semantic: TimeoutClaimSemantic,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::TimeoutClaimSubmitted(semantic)),
+            |this| this.timeout_claim_submitted_inner(semantic),
+        )
+    }
+
+    fn timeout_claim_submitted_inner(
+        &mut self,
+        semantic: TimeoutClaimSemantic,
     ) -> Result<(), Error> {
         use crate::session_phases::spend_channel_coin_phase::SpendChannelCoinPhase;
 
@@ -1035,6 +1428,13 @@ impl GameSession {
         &mut self,
         semantic: TimeoutClaimSemantic,
     ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::TimeoutClaimRearmed(semantic)),
+            |this| this.timeout_claim_rearmed_inner(semantic),
+        )
+    }
+
+    fn timeout_claim_rearmed_inner(&mut self, semantic: TimeoutClaimSemantic) -> Result<(), Error> {
         use crate::session_phases::spend_channel_coin_phase::SpendChannelCoinPhase;
 
         match semantic {
@@ -1103,10 +1503,309 @@ impl GameSession {
         self.last_channel_status = Some(snapshot);
     }
 
+    /// Whether the peer owes us a response: it holds the potato while a game
+    /// is running or a clean shutdown is pending, or we have asked for it.
+    fn awaiting_peer(&self) -> bool {
+        let Some(snapshot) = self.peer.channel_status_snapshot() else {
+            return false;
+        };
+        if snapshot.have_potato != Some(false) {
+            return false;
+        }
+        match snapshot.state {
+            ChannelStatus::ShuttingDown => true,
+            ChannelStatus::Active => {
+                self.peer.awaiting_potato()
+                    || snapshot
+                        .game_allocated
+                        .is_some_and(|allocated| allocated > Amount::default())
+            }
+            _ => false,
+        }
+    }
+
+    /// Advance the liveness clocks and judge the peer against the policy:
+    /// `Some((true, reason))` once it is time to go on chain, `Some((false,
+    /// reason))` while only a warning is due.
+    fn liveness_verdict(&mut self) -> Option<(bool, String)> {
+        let height = self.state.current_height;
+        if !self.awaiting_peer() {
+            self.state.peer_waiting_since = None;
+            self.state.potato_requested_at = None;
+            return None;
+        }
+        let idle = height.saturating_sub(*self.state.peer_waiting_since.get_or_insert(height));
+        let requested = if self.peer.awaiting_potato() {
+            Some(height.saturating_sub(*self.state.potato_requested_at.get_or_insert(height)))
+        } else {
+            self.state.potato_requested_at = None;
+            None
+        };
+
+        let policy = &self.state.liveness;
+        let past = |blocks: Option<u64>, threshold: Option<u64>| match (blocks, threshold) {
+            (Some(blocks), Some(threshold)) if blocks >= threshold => Some(threshold),
+            _ => None,
+        };
+        if let Some(t) = past(requested, policy.potato_go_on_chain_blocks) {
+            return Some((true, format!("peer withheld the potato for {t} blocks")));
+        }
+        if let Some(t) = past(Some(idle), policy.stall_go_on_chain_blocks) {
+            return Some((true, format!("peer unresponsive for {t} blocks")));
+        }
+        if let Some(t) = past(requested, policy.potato_warning_blocks) {
+            return Some((false, format!("peer has not sent the potato in {t} blocks")));
+        }
+        past(Some(idle), policy.stall_warning_blocks)
+            .map(|t| (false, format!("peer has not responded in {t} blocks")))
+    }
+
+    fn set_liveness_advisory(&mut self, advisory: Option<String>) {
+        if advisory != self.state.liveness_advisory {
+            self.state.liveness_advisory = advisory;
+            self.emit_channel_status_if_changed();
+        }
+    }
+
+    /// Whether we hold the potato, and the channel's state number: a peer
+    /// message that changes neither has not moved the channel forward.
+    fn peer_progress_marker(&self) -> (bool, Option<usize>) {
+        let have_potato = self
+            .peer
+            .channel_status_snapshot()
+            .is_some_and(|snapshot| snapshot.have_potato == Some(true));
+        let state_number = self.peer.channel_state().ok().map(|ch| ch.state_number());
+        (have_potato, state_number)
+    }
+
+    /// Restart the stall clock after a peer message that handed us the
+    /// potato or advanced the state number; anything else the peer sends
+    /// leaves it running.  Going on chain is left to the next block so a late
+    /// answer can still land.
+    fn note_peer_progress(&mut self, before: (bool, Option<usize>)) {
+        if self.state.liveness == LivenessPolicy::default()
+            || self.state.session_disposition.is_some()
+        {
+            return;
+        }
+        let after = self.peer_progress_marker();
+        let progressed = (after.0 && !before.0) || after.1 > before.1;
+        if progressed && self.state.peer_waiting_since.is_some() {
+            self.state.peer_waiting_since = Some(self.state.current_height);
+        }
+        let advisory = self.liveness_verdict().map(|(_, reason)| reason);
+        self.set_liveness_advisory(advisory);
+    }
+
+    /// Enforce the [`LivenessPolicy`] at the current height.
+    fn check_peer_liveness(&mut self, allocator: &mut AllocEncoder) -> Result<(), Error> {
+        if self.state.liveness == LivenessPolicy::default()
+            || self.state.session_disposition.is_some()
+        {
+            return Ok(());
+        }
+        match self.liveness_verdict() {
+            Some((true, reason)) => {
+                self.state.liveness_advisory = None;
+                self.state
+                    .events
+                    .push_back(GameSessionEvent::Log(format!("going on chain: {reason}")));
+                self.go_on_chain_with_advisory(allocator, false, Some(reason))
+            }
+            verdict => {
+                self.set_liveness_advisory(verdict.map(|(_, reason)| reason));
+                Ok(())
+            }
+        }
+    }
+
     pub fn push_event(&mut self, event: GameSessionEvent) {
         self.state.events.push_back(event);
     }
 
+    /// Queue a move for the game's spectators and send what their feeds
+    /// release.  A handler is signed up front, while the channel keys are at
+    /// hand.
+    fn feed_spectators(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        mut record: SpectatorMove,
+        ours: bool,
+    ) -> Result<(), Error> {
+        if !self.state.spectators.contains_key(&record.game_id) {
+            return Ok(());
+        }
+        if record.handler.is_some() {
+            let ch = self.peer.channel_state()?;
+            record.sign_handler(allocator, &ch.private_keys().my_referee_private_key)?;
+        }
+        let feeds = self
+            .state
+            .spectators
+            .get_mut(&record.game_id)
+            .into_iter()
+            .flatten();
+        for feed in feeds.filter(|feed| !feed.awaiting_start) {
+            for released in feed.push(record.clone(), ours) {
+                self.state
+                    .events
+                    .push_back(GameSessionEvent::SpectatorFeed {
+                        observer: feed.observer.clone(),
+                        event: SpectatorEvent::Move(Box::new(released)),
+                    });
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the starts of feeds that were waiting for the peer to sign a
+    /// state holding their game.
+    fn start_waiting_spectators(&mut self, allocator: &mut AllocEncoder) -> Result<(), Error> {
+        let Ok(ch) = self.peer.channel_state() else {
+            return Ok(());
+        };
+        for (game_id, feeds) in self.state.spectators.iter_mut() {
+            if ch.find_live_game(game_id).is_none() {
+                continue;
+            }
+            for feed in feeds.iter_mut().filter(|feed| feed.awaiting_start) {
+                let Some(start) = ch.spectator_start(
+                    allocator,
+                    *game_id,
+                    feed.observer.clone(),
+                    feed.delay_moves(),
+                )?
+                else {
+                    continue;
+                };
+                feed.awaiting_start = false;
+                self.state
+                    .events
+                    .push_back(GameSessionEvent::SpectatorFeed {
+                        observer: feed.observer.clone(),
+                        event: SpectatorEvent::Start(Box::new(start)),
+                    });
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush a settled game's spectator feeds and close them.  A feed that
+    /// never started has nothing to close.
+    fn end_spectator_feeds(&mut self, game_id: &GameID) {
+        let feeds = self.state.spectators.remove(game_id).unwrap_or_default();
+        for mut feed in feeds.into_iter().filter(|feed| !feed.awaiting_start) {
+            for record in feed.finish() {
+                self.state
+                    .events
+                    .push_back(GameSessionEvent::SpectatorFeed {
+                        observer: feed.observer.clone(),
+                        event: SpectatorEvent::Move(Box::new(record)),
+                    });
+            }
+            self.state
+                .events
+                .push_back(GameSessionEvent::SpectatorFeed {
+                    observer: feed.observer,
+                    event: SpectatorEvent::Ended {
+                        game_id: *game_id,
+                        feeder: feed.feeder,
+                    },
+                });
+        }
+    }
+
+    /// Sign the results of games that just settled off-chain, returning the
+    /// signatures to send.  Results of games settled any other way, or with
+    /// a peer that doesn't advertise `FEATURE_RESULT_ATTESTATION`, are
+    /// dropped.
+    fn sign_settled_results(&mut self, settled: &[(GameID, bool)]) -> Vec<Effect> {
+        let peer_attests = self
+            .state
+            .negotiated
+            .as_ref()
+            .is_some_and(|negotiated| negotiated.supports_feature(FEATURE_RESULT_ATTESTATION));
+        let mut effects = Vec::new();
+        for (id, off_chain) in settled {
+            let Some(mut pending) = self.state.attestations.remove(id) else {
+                continue;
+            };
+            let (true, true, Some(result), Ok(ch)) = (
+                *off_chain,
+                peer_attests,
+                &pending.result,
+                self.peer.channel_state(),
+            ) else {
+                continue;
+            };
+            let signature = result.sign(&ch.private_keys().my_referee_private_key);
+            effects.push(Effect::PeerGameResultSignature(*id, signature.clone()));
+            pending.our_signature = Some(signature);
+            self.state.attestations.insert(*id, pending);
+            self.complete_attestation(id);
+        }
+        effects
+    }
+
+    /// Hold the peer's signature until ours is in; it can arrive before the
+    /// game settles on our side.
+    fn received_result_signature(&mut self, game_id: GameID, signature: Aggsig) {
+        self.state
+            .attestations
+            .entry(game_id)
+            .or_default()
+            .their_signature = Some(signature);
+        self.complete_attestation(&game_id);
+    }
+
+    /// Emit the attestation once both signatures are in.  A bad signature
+    /// from the peer only costs the attestation.
+    fn complete_attestation(&mut self, game_id: &GameID) {
+        let Some(PendingAttestation {
+            result: Some(result),
+            our_signature: Some(ours),
+            their_signature: Some(theirs),
+        }) = self.state.attestations.get(game_id).cloned()
+        else {
+            return;
+        };
+        let Ok(ch) = self.peer.channel_state() else {
+            return;
+        };
+        let our_key = private_to_public_key(&ch.private_keys().my_referee_private_key);
+        self.state.attestations.remove(game_id);
+        let event = match GameResultAttestation::combine(result, &our_key, ours, theirs) {
+            Ok(attestation) => GameSessionEvent::GameResultAttested(Box::new(attestation)),
+            Err(e) => GameSessionEvent::Log(format!("no result attestation: {e:?}")),
+        };
+        self.state.events.push_back(event);
+    }
+
+    /// Record the operator fee of each game as it starts, and report it on
+    /// the game's `GameSettled`, which the phases emit without it.
+    fn track_operator_fee(&mut self, effect: &mut Effect) {
+        match effect {
+            Effect::Notify(GameNotification::ProposalAccepted { id, .. }) => {
+                let fee = self
+                    .peer
+                    .channel_state()
+                    .ok()
+                    .and_then(|ch| ch.game_operator_fee(id));
+                if let Some(fee) = fee {
+                    self.state.game_fees.insert(*id, fee.amount);
+                }
+            }
+            Effect::Notify(GameNotification::GameSettled {
+                id, operator_fee, ..
+            }) => {
+                if let Some(fee) = self.state.game_fees.remove(id) {
+                    *operator_fee = fee;
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn process_effects(
         &mut self,
         effects: Vec<Effect>,
@@ -1121,9 +1820,38 @@ impl GameSession {
         let go_on_chain_after_peer_error = effects
             .iter()
             .any(|effect| matches!(effect, Effect::GoOnChainAfterPeerError));
+        if effects
+            .iter()
+            .any(|effect| matches!(effect, Effect::PeerRequestPotato))
+        {
+            self.state
+                .potato_requested_at
+                .get_or_insert(self.state.current_height);
+        }
         let mut passthrough = Vec::new();
-        for effect in effects {
-            if let Effect::QueueTerminalHandoff(coin_spend) = effect {
+        let mut settled = Vec::new();
+        for mut effect in effects {
+            self.track_operator_fee(&mut effect);
+            if let Effect::Notify(GameNotification::GameSettled { id, outcome, .. }) = &effect {
+                settled.push((*id, matches!(outcome, SettlementOutcome::AcceptSettlement)));
+            }
+            if let Effect::SpectatorMove { record, ours } = effect {
+                self.feed_spectators(allocator, *record, ours)?;
+            } else if let Effect::GameResult(result) = effect {
+                let game_id = result.game_id;
+                self.state.attestations.entry(game_id).or_default().result = Some(*result);
+            } else if let Effect::ReceivedGameResultSignature(game_id, signature) = effect {
+                self.received_result_signature(game_id, signature);
+            } else if matches!(effect, Effect::ReceivedStopMatch) {
+                match self.state.game_match.as_mut() {
+                    Some(game_match) => game_match.peer_stopped(),
+                    None => self.state.events.push_back(GameSessionEvent::Log(
+                        "peer stopped a match we aren't playing".to_string(),
+                    )),
+                }
+            } else if let Effect::CapabilitiesNegotiated(negotiated) = effect {
+                self.state.negotiated = Some(negotiated);
+            } else if let Effect::QueueTerminalHandoff(coin_spend) = effect {
                 let message = bencodex::to_vec(&PeerMessage::CleanShutdownComplete(coin_spend))
                     .map_err(|e| Error::StrErr(format!("{e:?}")))?;
                 assert!(
@@ -1152,6 +1880,12 @@ impl GameSession {
                 passthrough.push(effect);
             }
         }
+        self.start_waiting_spectators(allocator)?;
+        for (id, _) in &settled {
+            self.end_spectator_feeds(id);
+        }
+        // After the batch, so the peer has settled the game when these arrive.
+        passthrough.extend(self.sign_settled_results(&settled));
         apply_effects(passthrough, allocator, &mut self.state)?;
         self.detect_phase_transition();
         if complete_zero_payout_shutdown {
@@ -1425,6 +2159,18 @@ impl GameSession {
         allocator: &mut AllocEncoder,
         game_id: &GameID,
         mover_share: Amount,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::Cheat(*game_id, mover_share.clone())),
+            |this| this.cheat_inner(allocator, game_id, mover_share),
+        )
+    }
+
+    fn cheat_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        game_id: &GameID,
+        mover_share: Amount,
     ) -> Result<(), Error> {
         let entropy: Hash = Hash::default();
         let reported_effects = {
@@ -1467,6 +2213,17 @@ impl GameSession {
         &mut self,
         allocator: &mut AllocEncoder,
         coin: CoinString,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::SetFundingCoin(coin.clone())),
+            |this| this.set_funding_coin_inner(allocator, coin),
+        )
+    }
+
+    fn set_funding_coin_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        coin: CoinString,
     ) -> Result<(), Error> {
         self.state.funding_coin = Some(coin.clone());
 
@@ -1494,6 +2251,13 @@ impl GameSession {
     }
 
     pub fn start_handshake(&mut self, allocator: &mut AllocEncoder) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::StartHandshake),
+            |this| this.start_handshake_inner(allocator),
+        )
+    }
+
+    fn start_handshake_inner(&mut self, allocator: &mut AllocEncoder) -> Result<(), Error> {
         if !self.state.is_initiator {
             return Ok(());
         }
@@ -1521,10 +2285,39 @@ impl GameSession {
         self.peer.handshake_finished()
     }
 
+    /// True once the channel coin has been observed on chain.
+    pub fn channel_established(&self) -> bool {
+        self.state.channel_established
+    }
+
+    /// Use `factory` for future proposals of `game_type`. Games already running
+    /// keep the program they started with. See [`crate::hot_reload`].
+    #[cfg(feature = "hot-reload")]
+    pub fn replace_game_factory(&mut self, game_type: &GameType, factory: GameFactory) -> bool {
+        self.journaled(
+            self.journal_input(|| {
+                SessionInput::ReplaceGameFactory(game_type.clone(), factory.clone())
+            }),
+            |this| Ok(this.peer.replace_game_factory(game_type, factory)),
+        )
+        .unwrap_or_default()
+    }
+
     pub fn propose_games(
         &mut self,
         allocator: &mut AllocEncoder,
         games: &[GameProposal],
+    ) -> Result<Vec<GameID>, Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::ProposeGames(games.to_vec())),
+            |this| this.propose_games_inner(allocator, games),
+        )
+    }
+
+    fn propose_games_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        games: &[GameProposal],
     ) -> Result<Vec<GameID>, Error> {
         let (result, reported_effects) = {
             let mut env = ChannelEnv::new(allocator)?;
@@ -1538,6 +2331,17 @@ impl GameSession {
         &mut self,
         allocator: &mut AllocEncoder,
         game_id: &GameID,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::AcceptProposal(*game_id)),
+            |this| this.accept_proposal_inner(allocator, game_id),
+        )
+    }
+
+    fn accept_proposal_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        game_id: &GameID,
     ) -> Result<(), Error> {
         let reported_effects = {
             let mut env = ChannelEnv::new(allocator)?;
@@ -1547,10 +2351,50 @@ impl GameSession {
         Ok(())
     }
 
+    /// Accept a proposal, paying our share of every game in its group as
+    /// `payout` says instead of to our reward puzzle hash.
+    pub fn accept_proposal_with_payout(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        game_id: &GameID,
+        payout: &PayoutTerms,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::AcceptProposalWithPayout(*game_id, payout.clone())),
+            |this| this.accept_proposal_with_payout_inner(allocator, game_id, payout),
+        )
+    }
+
+    fn accept_proposal_with_payout_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        game_id: &GameID,
+        payout: &PayoutTerms,
+    ) -> Result<(), Error> {
+        let reported_effects = {
+            let mut env = ChannelEnv::new(allocator)?;
+            self.peer
+                .accept_proposal_with_payout(&mut env, game_id, payout)?
+        };
+        self.process_effects(reported_effects, allocator)?;
+        Ok(())
+    }
+
     pub fn cancel_proposal(
         &mut self,
         allocator: &mut AllocEncoder,
         game_id: &GameID,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::CancelProposal(*game_id)),
+            |this| this.cancel_proposal_inner(allocator, game_id),
+        )
+    }
+
+    fn cancel_proposal_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        game_id: &GameID,
     ) -> Result<(), Error> {
         let reported_effects = {
             let mut env = ChannelEnv::new(allocator)?;
@@ -1571,6 +2415,21 @@ impl GameSession {
         id: &GameID,
         readable: ReadableMove,
         new_entropy: Hash,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| {
+                SessionInput::MakeMove(*id, readable.clone(), new_entropy.clone())
+            }),
+            |this| this.make_move_inner(allocator, id, readable, new_entropy),
+        )
+    }
+
+    fn make_move_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        id: &GameID,
+        readable: ReadableMove,
+        new_entropy: Hash,
     ) -> Result<(), Error> {
         let reported_effects = {
             let mut env = ChannelEnv::new(allocator)?;
@@ -1586,6 +2445,21 @@ impl GameSession {
         id: &GameID,
         readable: ReadableMove,
         new_entropy: Hash,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| {
+                SessionInput::AcceptProposalAndMove(*id, readable.clone(), new_entropy.clone())
+            }),
+            |this| this.accept_proposal_and_move_inner(allocator, id, readable, new_entropy),
+        )
+    }
+
+    fn accept_proposal_and_move_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        id: &GameID,
+        readable: ReadableMove,
+        new_entropy: Hash,
     ) -> Result<(), Error> {
         let reported_effects = {
             let mut env = ChannelEnv::new(allocator)?;
@@ -1602,6 +2476,17 @@ impl GameSession {
         &mut self,
         allocator: &mut AllocEncoder,
         id: &GameID,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::AcceptSettlement(*id)),
+            |this| this.accept_settlement_inner(allocator, id),
+        )
+    }
+
+    fn accept_settlement_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        id: &GameID,
     ) -> Result<(), Error> {
         let reported_effects = {
             let mut env = ChannelEnv::new(allocator)?;
@@ -1611,8 +2496,166 @@ impl GameSession {
         Ok(())
     }
 
+    /// Start a match: propose its first hand and keep proposing or accepting
+    /// hands on the agreed terms until it ends.  Returns the first hand's id,
+    /// which the peer passes to [`GameSession::accept_match`].
+    pub fn start_match(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        terms: MatchTerms,
+    ) -> Result<GameID, Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::StartMatch(terms.clone())),
+            |this| this.start_match_inner(allocator, terms),
+        )
+    }
+
+    fn start_match_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        terms: MatchTerms,
+    ) -> Result<GameID, Error> {
+        if self.state.game_match.is_some() {
+            return Err(Error::StrErr("a match is already in progress".to_string()));
+        }
+        let mut game_match = MatchState::start(terms)?;
+        self.state.match_cursor = self.state.events.len();
+        let (ids, reported_effects) = {
+            let mut env = ChannelEnv::new(allocator)?;
+            self.peer
+                .propose_games(&mut env, std::slice::from_ref(&game_match.terms().proposal))?
+        };
+        let id = *ids
+            .first()
+            .ok_or_else(|| Error::StrErr("match proposal returned no game id".to_string()))?;
+        game_match.proposed(id);
+        self.state.game_match = Some(game_match);
+        self.process_effects(reported_effects, allocator)?;
+        Ok(id)
+    }
+
+    /// Join the match the peer started by proposing `first_hand` on `terms`,
+    /// accepting that hand.  The caller is expected to have checked the
+    /// proposal against the terms, as for [`GameSession::accept_proposal`].
+    pub fn accept_match(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        first_hand: &GameID,
+        terms: MatchTerms,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::AcceptMatch(*first_hand, terms.clone())),
+            |this| this.accept_match_inner(allocator, first_hand, terms),
+        )
+    }
+
+    fn accept_match_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        first_hand: &GameID,
+        terms: MatchTerms,
+    ) -> Result<(), Error> {
+        if self.state.game_match.is_some() {
+            return Err(Error::StrErr("a match is already in progress".to_string()));
+        }
+        let stake = self
+            .peer
+            .channel_state()?
+            .find_proposal(first_hand)
+            .map(|p| p.my_contribution.clone())
+            .ok_or_else(|| {
+                Error::StrErr(format!("no proposal {first_hand} to accept as a match"))
+            })?;
+        let game_match = MatchState::join(terms, *first_hand, Some(stake))?;
+        self.state.match_cursor = self.state.events.len();
+        let reported_effects = {
+            let mut env = ChannelEnv::new(allocator)?;
+            self.peer.accept_proposal(&mut env, first_hand)?
+        };
+        self.state.game_match = Some(game_match);
+        self.process_effects(reported_effects, allocator)?;
+        Ok(())
+    }
+
+    /// Stop the match once the hands already under way settle.
+    pub fn stop_match(&mut self) -> Result<(), Error> {
+        self.journaled(self.journal_input(|| SessionInput::StopMatch), |this| {
+            this.stop_match_inner()
+        })
+    }
+
+    fn stop_match_inner(&mut self) -> Result<(), Error> {
+        let game_match = self
+            .state
+            .game_match
+            .as_mut()
+            .ok_or_else(|| Error::StrErr("no match in progress".to_string()))?;
+        game_match.request_stop();
+        Ok(())
+    }
+
+    /// Let `observer` follow a live game of ours.  The start of its feed is
+    /// emitted now, or once the peer has signed a state holding the game, and
+    /// each move once `delay_moves` more have been made, as
+    /// [`GameSessionEvent::SpectatorFeed`] events for the host to forward.
+    /// Only off-chain moves are fed.  See [`crate::spectator`].
+    pub fn authorize_spectator(
+        &mut self,
+        game_id: &GameID,
+        observer: PublicKey,
+        delay_moves: u32,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| {
+                SessionInput::AuthorizeSpectator(*game_id, observer.clone(), delay_moves)
+            }),
+            |this| this.authorize_spectator_inner(game_id, observer, delay_moves),
+        )
+    }
+
+    fn authorize_spectator_inner(
+        &mut self,
+        game_id: &GameID,
+        observer: PublicKey,
+        delay_moves: u32,
+    ) -> Result<(), Error> {
+        let ch = self.peer.channel_state()?;
+        let feeder = private_to_public_key(&ch.private_keys().my_referee_private_key);
+        let mut allocator = AllocEncoder::new();
+        let start = ch.spectator_start(&mut allocator, *game_id, observer.clone(), delay_moves)?;
+        let feeds = self.state.spectators.entry(*game_id).or_default();
+        if feeds.iter().any(|f| f.observer == observer) {
+            return Err(Error::StrErr(format!(
+                "observer already follows game {game_id}"
+            )));
+        }
+        let mut feed = SpectatorFeed::new(observer.clone(), feeder, delay_moves);
+        feed.awaiting_start = start.is_none();
+        feeds.push(feed);
+        if let Some(start) = start {
+            self.state
+                .events
+                .push_back(GameSessionEvent::SpectatorFeed {
+                    observer,
+                    event: SpectatorEvent::Start(Box::new(start)),
+                });
+        }
+        Ok(())
+    }
+
+    /// The running match's score, if a match is in progress.
+    pub fn match_score(&self) -> Option<&MatchScore> {
+        self.state.game_match.as_ref().map(|m| m.score())
+    }
+
     /// Signal shutdown.  Forwards to FromLocalUI::shut_down.
     pub fn shut_down(&mut self, allocator: &mut AllocEncoder) -> Result<(), Error> {
+        self.journaled(self.journal_input(|| SessionInput::ShutDown), |this| {
+            this.shut_down_inner(allocator)
+        })
+    }
+
+    fn shut_down_inner(&mut self, allocator: &mut AllocEncoder) -> Result<(), Error> {
         let reported_effects = {
             let mut env = ChannelEnv::new(allocator)?;
             self.peer.shut_down(&mut env)?
@@ -1627,6 +2670,18 @@ impl GameSession {
         allocator: &mut AllocEncoder,
         height: u64,
         observations: &[CoinObservation],
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::NewBlock(height, observations.to_vec())),
+            |this| this.new_block_inner(allocator, height, observations),
+        )
+    }
+
+    fn new_block_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        height: u64,
+        observations: &[CoinObservation],
     ) -> Result<(), Error> {
         if self.state.session_disposition.is_some() {
             return Ok(());
@@ -1647,7 +2702,7 @@ impl GameSession {
         let height_effects = self.peer.new_block(self.state.current_height)?;
         self.process_effects(height_effects, allocator)?;
         self.check_channel_creation_expiry(height, observations);
-        Ok(())
+        self.check_peer_liveness(allocator)
     }
 
     /// Advance handler clocks from a confirmed peak height without accepting a
@@ -1659,17 +2714,36 @@ impl GameSession {
         &mut self,
         allocator: &mut AllocEncoder,
         height: u64,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::NewBlockHeightOnly(height)),
+            |this| this.new_block_height_only_inner(allocator, height),
+        )
+    }
+
+    fn new_block_height_only_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        height: u64,
     ) -> Result<(), Error> {
         if self.state.session_disposition.is_some() {
             return Ok(());
         }
         self.state.current_height = height;
         let height_effects = self.peer.new_block(height)?;
-        self.process_effects(height_effects, allocator)
+        self.process_effects(height_effects, allocator)?;
+        self.check_peer_liveness(allocator)
     }
 
     /// Queue a message from the peer for processing by `flush_and_collect`.
     pub fn deliver_message(&mut self, inbound_message: &[u8]) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::DeliverMessage(inbound_message.to_vec())),
+            |this| this.deliver_message_inner(inbound_message),
+        )
+    }
+
+    fn deliver_message_inner(&mut self, inbound_message: &[u8]) -> Result<(), Error> {
         if self.state.peer_disconnected || self.state.session_disposition.is_some() {
             return Ok(());
         }
@@ -1681,6 +2755,12 @@ impl GameSession {
                 MAX_MESSAGE_SIZE,
             )));
         }
+        if self.state.inbound_messages.len() >= self.state.peer_limits.max_queued_messages {
+            // Judged at the next flush, where going on chain can
+            // report through the usual events.
+            self.state.inbound_overflow = true;
+            return Ok(());
+        }
         self.state
             .inbound_messages
             .push_back(inbound_message.to_vec());
@@ -1696,12 +2776,46 @@ impl GameSession {
             .is_some_and(|ch| ch.has_zero_payout())
     }
 
+    /// What [`GameSession::go_on_chain`] would submit, cost, wait for and pay
+    /// out if the opponent went silent, computed without changing the session.
+    /// Only available while the channel is off-chain.
+    pub fn preview_go_on_chain(
+        &self,
+        allocator: &mut AllocEncoder,
+    ) -> Result<GoOnChainPreview, Error> {
+        let ph = self
+            .peer
+            .as_any()
+            .downcast_ref::<OffChainPhase>()
+            .ok_or_else(|| {
+                Error::StrErr("preview_go_on_chain: channel is not off-chain".to_string())
+            })?;
+        let mut env = ChannelEnv::new(allocator)?;
+        ph.preview_go_on_chain(&mut env)
+    }
+
     /// Trigger going on chain.
     pub fn go_on_chain(
         &mut self,
         allocator: &mut AllocEncoder,
         got_error: bool,
     ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| SessionInput::GoOnChain(got_error)),
+            |this| this.go_on_chain_with_advisory(allocator, got_error, None),
+        )
+    }
+
+    /// Body of [`GameSession::go_on_chain`].  A given `advisory` replaces the
+    /// reason carried by the `GoingOnChain` status that follows.
+    fn go_on_chain_with_advisory(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        got_error: bool,
+        advisory: Option<String>,
+    ) -> Result<(), Error> {
+        use crate::session_phases::spend_channel_coin_phase::SpendChannelCoinPhase;
+
         if matches!(
             self.state.session_disposition,
             Some(SessionDisposition::AwaitOutboundTerminal)
@@ -1720,6 +2834,14 @@ impl GameSession {
             let mut env = ChannelEnv::new(allocator)?;
             self.peer.go_on_chain(&mut env, got_error)?
         };
+        if let Some(advisory) = advisory {
+            if let Some(mut next) = self.peer.take_next_phase() {
+                if let Some(phase) = next.as_any_mut().downcast_mut::<SpendChannelCoinPhase>() {
+                    phase.set_advisory(Some(advisory));
+                }
+                self.peer = next;
+            }
+        }
         if !reported_effects.is_empty() {
             self.process_effects(reported_effects, allocator)?;
         } else {
@@ -1736,6 +2858,23 @@ impl GameSession {
         allocator: &mut AllocEncoder,
         coin_id: &CoinString,
         puzzle_and_solution: Option<(&Program, &Program)>,
+    ) -> Result<(), Error> {
+        self.journaled(
+            self.journal_input(|| {
+                SessionInput::ReportPuzzleAndSolution(
+                    coin_id.clone(),
+                    puzzle_and_solution.map(|(p, s)| (p.clone(), s.clone())),
+                )
+            }),
+            |this| this.report_puzzle_and_solution_inner(allocator, coin_id, puzzle_and_solution),
+        )
+    }
+
+    fn report_puzzle_and_solution_inner(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        coin_id: &CoinString,
+        puzzle_and_solution: Option<(&Program, &Program)>,
     ) -> Result<(), Error> {
         if self.state.session_disposition.is_some() {
             return Ok(());
@@ -1755,6 +2894,20 @@ impl GameSession {
 
 #[cfg(test)]
 impl GameSession {
+    /// Swap the liveness policy of a running session (test harness only).
+    pub fn set_liveness_policy(&mut self, policy: LivenessPolicy) {
+        self.state.liveness = policy;
+    }
+
+    /// Swap the inbound peer limits of a running session (test harness only).
+    /// Takes effect on the off-chain phase if the handshake has finished.
+    pub fn set_peer_limits(&mut self, limits: PeerLimits) {
+        if let Some(phase) = self.peer.as_any_mut().downcast_mut::<OffChainPhase>() {
+            phase.set_peer_limits(limits.clone());
+        }
+        self.state.peer_limits = limits;
+    }
+
     /// Get the on-chain game coin for a game (test harness only). Downcasts to
     /// OnChainPhase when the cradle is in on-chain phase.
     pub fn get_game_coin(&self, game_id: &GameID) -> Option<CoinString> {
@@ -1764,6 +2917,17 @@ impl GameSession {
         }
         None
     }
+
+    /// Whether the game is on chain and waiting on our move (test harness
+    /// only).
+    #[cfg(all(test, feature = "sim-tests"))]
+    pub(crate) fn awaiting_on_chain_move(&self, game_id: &GameID) -> bool {
+        use crate::session_phases::on_chain::OnChainPhase;
+        self.peer
+            .as_any()
+            .downcast_ref::<OnChainPhase>()
+            .is_some_and(|och| och.awaiting_our_move(game_id))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,801 @@
+//! Handler API conformance checking for game authors.
+//!
+//! [`check_factory`] runs a proposal factory and then plays every game it
+//! returns to the end, driving both sides' handlers the way the referee does
+//! and checking each result against `clsp/handler_api.md`: record and return
+//! shapes, validator hashes and the hash chain between moves, move sizes and
+//! shares, validators accepting the honest move, evidence that would slash an
+//! honest move, agreement on when the game ends, and message parsers.
+//!
+//! My-turn inputs are generated: every list (and list prefix) in the readable
+//! move the mover last received, followed by [`ConformanceConfig::inputs`].
+//! Inputs a handler rejects or raises on are skipped; if none is accepted the
+//! play-out is recorded as stalled rather than as a violation. Each play-out
+//! starts from a different candidate and uses different entropy.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::rc::Rc;
+
+use clvmr::allocator::SExp;
+use clvmr::NodePtr;
+
+use crate::channel_state::game::{FactoryGame, Game};
+use crate::channel_state::game_handler::{
+    GameHandler, MessageInputs, MyTurnInputs, MyTurnResult, TheirTurnInputs,
+};
+use crate::channel_state::types::{Evidence, ReadableMove, StateUpdateProgram, ValidationInfo};
+use crate::common::types::{
+    atom_from_clvm, AllocEncoder, Amount, Error, Program, ProgramRef, PublicKey, Puzzle,
+    PuzzleHash, Sha256Input, Sha256tree, Timeout,
+};
+use crate::referee::types::{
+    canonical_atom_from_usize, GameMoveDetails, GameMoveStateInfo, InternalStateUpdateArgs,
+    RefereePuzzleArgs, StateUpdateMoveArgs, StateUpdateResult, ValidationInfoHash,
+};
+use crate::utils::proper_list;
+
+/// Lists longer than this in a readable move are not turned into inputs.
+const MAX_DERIVED_LIST: usize = 16;
+/// Upper bound on generated inputs tried for a single move.
+const MAX_CANDIDATES: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct ConformanceConfig {
+    /// Play-outs per game.
+    pub playouts: usize,
+    /// A game still running after this many moves is a violation.
+    pub max_moves: usize,
+    /// Inputs tried for every my-turn call after the generated ones.
+    pub inputs: Vec<Program>,
+    pub seed: u64,
+}
+
+impl Default for ConformanceConfig {
+    fn default() -> Self {
+        ConformanceConfig {
+            playouts: 4,
+            max_moves: 64,
+            inputs: vec![
+                Program::from_hex("80").expect("nil"),
+                Program::from_hex("01").expect("one"),
+                Program::from_hex("02").expect("two"),
+            ],
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Index of the game in the factory's result.
+    pub game: usize,
+    /// The handler or program at fault.
+    pub handler: String,
+    pub problem: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "game {}: {}: {}", self.game, self.handler, self.problem)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConformanceReport {
+    pub games: usize,
+    /// Play-outs that reached the end of the game.
+    pub completed_playouts: usize,
+    pub moves: usize,
+    /// Labels of every my-turn handler that produced a move.
+    pub handlers: BTreeSet<String>,
+    /// Play-outs abandoned because no generated input was accepted.
+    pub stalled: Vec<String>,
+    pub violations: Vec<Violation>,
+}
+
+impl ConformanceReport {
+    pub fn is_conformant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Run `factory` on `parameters` and exercise every game it returns.
+///
+/// Only failures to run the checker itself are errors; everything wrong
+/// with the game is reported as a [`Violation`].
+pub fn check_factory(
+    allocator: &mut AllocEncoder,
+    factory: Puzzle,
+    parameters: &Program,
+    config: &ConformanceConfig,
+) -> Result<ConformanceReport, Error> {
+    let mut report = ConformanceReport::default();
+    let games = match Game::run_factory(allocator, factory, parameters) {
+        Ok(games) => games,
+        Err(e) => {
+            report.violations.push(Violation {
+                game: 0,
+                handler: "factory".to_string(),
+                problem: format!("{e:?}"),
+            });
+            return Ok(report);
+        }
+    };
+    report.games = games.len();
+
+    for (index, game) in games.iter().enumerate() {
+        let mut checker = Checker {
+            index,
+            game,
+            config,
+            report: &mut report,
+        };
+        checker.check_record();
+        for playout in 0..config.playouts {
+            checker.playout(allocator, playout)?;
+        }
+    }
+    Ok(report)
+}
+
+/// One side's view of the game between moves.
+struct Side {
+    handler: Option<GameHandler>,
+    /// Describes `handler` for reports.
+    name: String,
+}
+
+struct Checker<'a> {
+    index: usize,
+    game: &'a FactoryGame,
+    config: &'a ConformanceConfig,
+    report: &'a mut ConformanceReport,
+}
+
+enum TurnOutcome {
+    Moved(Box<MyTurnResult>),
+    Stalled(String),
+    Broken,
+}
+
+impl Checker<'_> {
+    fn violation(&mut self, handler: &str, problem: String) {
+        self.report.violations.push(Violation {
+            game: self.index,
+            handler: handler.to_string(),
+            problem,
+        });
+    }
+
+    fn check_record(&mut self) {
+        let game = self.game;
+        if game.sender_contribution.clone() + game.receiver_contribution.clone() != game.amount {
+            self.violation(
+                "factory",
+                format!(
+                    "contributions {:?} and {:?} do not add up to amount {:?}",
+                    game.sender_contribution, game.receiver_contribution, game.amount
+                ),
+            );
+        }
+        if game.initial_mover_share > game.amount.to_u64() {
+            self.violation(
+                "factory",
+                format!(
+                    "initial mover share {} exceeds amount {:?}",
+                    game.initial_mover_share, game.amount
+                ),
+            );
+        }
+    }
+
+    fn playout(&mut self, allocator: &mut AllocEncoder, playout: usize) -> Result<(), Error> {
+        let game = self.game;
+        let mut sides = [
+            Side {
+                handler: Some(GameHandler::MyTurnHandler(
+                    game.my_turn_handler.clone().into(),
+                )),
+                name: "initial my-turn handler".to_string(),
+            },
+            Side {
+                handler: Some(GameHandler::TheirTurnHandler(
+                    game.their_turn_handler.clone().into(),
+                )),
+                name: "initial their-turn handler".to_string(),
+            },
+        ];
+        let mut mover = 0;
+        let mut state = game.initial_state.clone();
+        let mut validator = StateUpdateProgram::new_hash(
+            game.initial_validation_program.clone(),
+            "initial",
+            game.initial_validation_program_hash.clone(),
+        );
+        let mut max_move_size = game.initial_max_move_size;
+        let mut mover_share = Amount::new(game.initial_mover_share);
+        let mut readable: Option<Program> = None;
+
+        for turn in 0..self.config.max_moves {
+            let waiter = 1 - mover;
+            let mover_name = sides[mover].name.clone();
+            let Some(handler) = sides[mover].handler.take() else {
+                self.violation(
+                    &sides[waiter].name,
+                    "game continued but the opponent has no my-turn handler".to_string(),
+                );
+                return Ok(());
+            };
+            if !handler.is_my_turn() {
+                self.violation(&mover_name, "expected a my-turn handler".to_string());
+                return Ok(());
+            }
+
+            let candidates = self.candidates(allocator, readable.as_ref())?;
+            let entropy = Sha256Input::Array(vec![
+                Sha256Input::Bytes(b"conformance"),
+                Sha256Input::Bytes(&self.config.seed.to_be_bytes()),
+                Sha256Input::Bytes(&(playout as u64).to_be_bytes()),
+                Sha256Input::Bytes(&(turn as u64).to_be_bytes()),
+            ])
+            .hash();
+            let inputs = |local_move: &Program| MyTurnInputs {
+                readable_new_move: ReadableMove::from_program(Rc::new(local_move.clone())),
+                entropy: entropy.clone(),
+                amount: game.amount.clone(),
+                last_mover_share: mover_share.clone(),
+                state: ProgramRef::new(state.clone()),
+            };
+
+            let start = (playout + turn) % candidates.len();
+            let mut outcome = TurnOutcome::Stalled(String::new());
+            for i in 0..candidates.len() {
+                let local_move = &candidates[(start + i) % candidates.len()];
+                outcome = self.my_turn(allocator, &handler, &mover_name, &inputs(local_move))?;
+                if !matches!(outcome, TurnOutcome::Stalled(_)) {
+                    break;
+                }
+            }
+            let result = match outcome {
+                TurnOutcome::Moved(result) => result,
+                TurnOutcome::Stalled(last) => {
+                    self.report.stalled.push(format!(
+                        "game {} play-out {playout}: no generated input accepted by {mover_name} ({last})",
+                        self.index
+                    ));
+                    return Ok(());
+                }
+                TurnOutcome::Broken => return Ok(()),
+            };
+            let label = result.name.clone();
+            self.report.handlers.insert(label.clone());
+            self.report.moves += 1;
+
+            self.check_my_turn_result(allocator, &result, &validator, max_move_size);
+            let terminal = result.waiting_handler.is_none();
+
+            let details = GameMoveDetails {
+                basic: GameMoveStateInfo {
+                    move_made: result.move_bytes.clone(),
+                    mover_share: result.mover_share.clone(),
+                    max_move_size: result.max_move_size,
+                    max_move_size_raw: canonical_atom_from_usize(result.max_move_size),
+                },
+                validation_info_hash: if terminal {
+                    ValidationInfoHash::None
+                } else {
+                    let info = ValidationInfo::new_state_update(
+                        allocator,
+                        result.outgoing_move_state_update_program.clone(),
+                        state.clone(),
+                    );
+                    ValidationInfoHash::Hash(info.hash().clone())
+                },
+                validation_program_hash: Some(
+                    result.outgoing_move_state_update_program_hash.clone(),
+                ),
+            };
+            let validator_name = format!("outgoing validator from {label}");
+            let moved_validator = result.outgoing_move_state_update_program.clone();
+            let new_state = match run_validator(
+                allocator,
+                &game.amount,
+                &moved_validator,
+                &details,
+                state.clone(),
+                Evidence::nil()?,
+            ) {
+                Ok(Some(new_state)) if !terminal => new_state,
+                Ok(None) => {
+                    self.violation(
+                        &validator_name,
+                        "slashes the honest move with nil evidence".to_string(),
+                    );
+                    return Ok(());
+                }
+                // A terminal move leaves no state; its validator may need
+                // real evidence to finish and is allowed to raise here.
+                _ if terminal => Rc::new(Program::from_hex("80")?),
+                Ok(Some(_)) => unreachable!(),
+                Err(e) => {
+                    self.violation(&validator_name, format!("raised on the honest move: {e:?}"));
+                    return Ok(());
+                }
+            };
+
+            let Some(their_handler) = sides[waiter].handler.take() else {
+                self.violation(
+                    &label,
+                    "moved after the opponent's handler ended the game".to_string(),
+                );
+                return Ok(());
+            };
+            let their_name = sides[waiter].name.clone();
+            let pre_state_node = state.to_nodeptr(allocator)?;
+            let state_node = new_state.to_nodeptr(allocator)?;
+            let their_result = match their_handler.call_their_turn_handler(
+                allocator,
+                &TheirTurnInputs {
+                    amount: game.amount.clone(),
+                    pre_state: pre_state_node,
+                    state: state_node,
+                    last_move: &result.move_bytes,
+                    last_mover_share: result.mover_share.clone(),
+                    new_move: GameMoveDetails {
+                        validation_program_hash: Some(validator.hash().clone()),
+                        ..details.clone()
+                    },
+                },
+            ) {
+                Ok(r) => r,
+                Err(e) => {
+                    self.violation(
+                        &their_name,
+                        format!("failed on the honest move from {label}: {e:?}"),
+                    );
+                    return Ok(());
+                }
+            };
+
+            for evidence in their_result.slash_evidence.iter() {
+                if let Ok(None) = run_validator(
+                    allocator,
+                    &game.amount,
+                    &moved_validator,
+                    &details,
+                    state.clone(),
+                    evidence.clone(),
+                ) {
+                    self.violation(
+                        &their_name,
+                        format!(
+                            "returned evidence {:?} that slashes the honest move from {label}",
+                            evidence.to_program()
+                        ),
+                    );
+                }
+            }
+
+            match (terminal, their_result.next_handler.is_some()) {
+                (true, true) => self.violation(
+                    &their_name,
+                    format!("continued the game after the final move from {label}"),
+                ),
+                (false, false) => self.violation(
+                    &their_name,
+                    format!("ended the game although {label} expects a reply"),
+                ),
+                _ => {}
+            }
+
+            if !their_result.message.is_empty() {
+                match &result.message_parser {
+                    None => self.violation(
+                        &their_name,
+                        format!("sent a message but {label} returned no message parser"),
+                    ),
+                    Some(parser) => {
+                        if let Err(e) = parser.run(
+                            allocator,
+                            &MessageInputs {
+                                message: their_result.message.clone(),
+                                state: ProgramRef::new(new_state.clone()),
+                                amount: game.amount.clone(),
+                            },
+                        ) {
+                            self.violation(
+                                &format!("message parser from {label}"),
+                                format!("rejected the opponent's honest message: {e:?}"),
+                            );
+                        }
+                    }
+                }
+            }
+
+            if terminal || their_result.next_handler.is_none() {
+                self.report.completed_playouts += 1;
+                return Ok(());
+            }
+
+            sides[mover] = Side {
+                handler: result.waiting_handler.clone(),
+                name: format!("their-turn handler returned by {label}"),
+            };
+            sides[waiter] = Side {
+                handler: their_result.next_handler.clone(),
+                name: format!("my-turn handler following {label}"),
+            };
+            validator = result.incoming_move_state_update_program.clone();
+            max_move_size = result.max_move_size;
+            mover_share = result.mover_share.clone();
+            state = new_state;
+            readable = Some(their_result.readable_move.p().as_ref().clone());
+            mover = waiter;
+        }
+
+        self.violation(
+            "game",
+            format!("still running after {} moves", self.config.max_moves),
+        );
+        Ok(())
+    }
+
+    /// Call a my-turn handler and check the raw shape of what it returned.
+    /// Rejections and raises stall the input rather than violating the API.
+    fn my_turn(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        handler: &GameHandler,
+        name: &str,
+        inputs: &MyTurnInputs,
+    ) -> Result<TurnOutcome, Error> {
+        let (run_result, trace) = handler.run_my_turn_handler(allocator, inputs)?;
+        let node = match run_result {
+            Ok(node) => node,
+            Err(e) => return Ok(TurnOutcome::Stalled(format!("raised: {e:?}"))),
+        };
+        let Some(fields) = proper_list(allocator.allocator(), node, true) else {
+            self.violation(name, "returned something other than a list".to_string());
+            return Ok(TurnOutcome::Broken);
+        };
+        match fields.len() {
+            2 => {
+                if fields.iter().all(|f| is_atom(allocator, *f)) {
+                    return Ok(TurnOutcome::Stalled("rejected".to_string()));
+                }
+                self.violation(
+                    name,
+                    "rejection tag and message must both be atoms".to_string(),
+                );
+                return Ok(TurnOutcome::Broken);
+            }
+            9 | 10 => {}
+            n => {
+                self.violation(
+                    name,
+                    format!("returned {n} elements, expected 9 or 10 (or 2 to reject)"),
+                );
+                return Ok(TurnOutcome::Broken);
+            }
+        }
+        for (position, field) in [(0, "label"), (1, "move"), (6, "max_move_size")] {
+            if !is_atom(allocator, fields[position]) {
+                self.violation(name, format!("{field} is not an atom"));
+                return Ok(TurnOutcome::Broken);
+            }
+        }
+        // A final move has no incoming validator, so its hash may be nil.
+        let terminal = fields[8] == allocator.allocator().nil();
+        for (position, field) in [
+            (3, "outgoing_validator_hash"),
+            (5, "incoming_validator_hash"),
+        ] {
+            let len = atom_from_clvm(allocator, fields[position]).map(|a| a.len());
+            if len != Some(32) && !(terminal && position == 5 && len == Some(0)) {
+                self.violation(name, format!("{field} is not a 32 byte atom"));
+                return Ok(TurnOutcome::Broken);
+            }
+        }
+        match GameHandler::decode_my_turn_result(allocator, node, &trace) {
+            Ok(result) => Ok(TurnOutcome::Moved(Box::new(result))),
+            Err(e) => {
+                self.violation(name, format!("{e:?}"));
+                Ok(TurnOutcome::Broken)
+            }
+        }
+    }
+
+    fn check_my_turn_result(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        result: &MyTurnResult,
+        expected_validator: &StateUpdateProgram,
+        max_move_size: usize,
+    ) {
+        let label = &result.name;
+        for (field, program, claimed) in [
+            (
+                "outgoing_validator_hash",
+                &result.outgoing_move_state_update_program,
+                &result.outgoing_move_state_update_program_hash,
+            ),
+            (
+                "incoming_validator_hash",
+                &result.incoming_move_state_update_program,
+                &result.incoming_move_state_update_program_hash,
+            ),
+        ]
+        .into_iter()
+        // After a final move there is no incoming validator to check.
+        .take(if result.waiting_handler.is_some() {
+            2
+        } else {
+            1
+        }) {
+            let actual = program.to_program().sha256tree(allocator);
+            if actual.hash() != claimed {
+                self.violation(
+                    label,
+                    format!("{field} {claimed:?} is not the tree hash {actual:?} of the program"),
+                );
+            }
+        }
+        if &result.outgoing_move_state_update_program_hash != expected_validator.hash() {
+            self.violation(
+                label,
+                format!(
+                    "outgoing validator {:?} is not the one the previous move committed to ({:?})",
+                    result.outgoing_move_state_update_program_hash,
+                    expected_validator.hash()
+                ),
+            );
+        }
+        if result.move_bytes.len() > max_move_size {
+            self.violation(
+                label,
+                format!(
+                    "move of {} bytes exceeds max_move_size {max_move_size}",
+                    result.move_bytes.len()
+                ),
+            );
+        }
+        if result.mover_share > self.game.amount {
+            self.violation(
+                label,
+                format!(
+                    "mover_share {:?} exceeds amount {:?}",
+                    result.mover_share, self.game.amount
+                ),
+            );
+        }
+    }
+
+    /// Inputs to try for the next my-turn call.
+    fn candidates(
+        &self,
+        allocator: &mut AllocEncoder,
+        readable: Option<&Program>,
+    ) -> Result<Vec<Program>, Error> {
+        let mut candidates = Vec::new();
+        if let Some(readable) = readable {
+            let node = readable.to_nodeptr(allocator)?;
+            derive_inputs(allocator, node, 0, &mut candidates)?;
+        }
+        candidates.truncate(MAX_CANDIDATES);
+        candidates.extend(self.config.inputs.iter().cloned());
+        if candidates.is_empty() {
+            candidates.push(Program::from_hex("80")?);
+        }
+        Ok(candidates)
+    }
+}
+
+fn is_atom(allocator: &AllocEncoder, node: NodePtr) -> bool {
+    matches!(allocator.allocator_ref().sexp(node), SExp::Atom)
+}
+
+/// Every list in `node` down to depth 2, and each of its prefixes, as a
+/// candidate input: a handler that shows the player a list of options usually
+/// takes a selection from it back.
+fn derive_inputs(
+    allocator: &mut AllocEncoder,
+    node: NodePtr,
+    depth: usize,
+    out: &mut Vec<Program>,
+) -> Result<(), Error> {
+    let Some(items) = proper_list(allocator.allocator(), node, true) else {
+        return Ok(());
+    };
+    if items.is_empty() || items.len() > MAX_DERIVED_LIST {
+        return Ok(());
+    }
+    for len in 1..=items.len() {
+        let mut prefix = allocator.allocator().nil();
+        for item in items[..len].iter().rev() {
+            prefix = allocator
+                .allocator()
+                .new_pair(*item, prefix)
+                .map_err(|e| Error::StrErr(format!("allocating input: {e:?}")))?;
+        }
+        out.push(Program::from_nodeptr(allocator, prefix)?);
+    }
+    if depth < 2 {
+        for item in items {
+            derive_inputs(allocator, item, depth + 1, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `validator` on the move in `details` the way the referee does before
+/// accepting it. Keys and the referee coin are irrelevant off-chain.
+fn run_validator(
+    allocator: &mut AllocEncoder,
+    amount: &Amount,
+    validator: &StateUpdateProgram,
+    details: &GameMoveDetails,
+    state: Rc<Program>,
+    evidence: Evidence,
+) -> Result<StateUpdateResult, Error> {
+    let referee_args = RefereePuzzleArgs {
+        mover_pubkey: PublicKey::default(),
+        waiter_pubkey: PublicKey::default(),
+        timeout: Timeout::new(0),
+        amount: amount.clone(),
+        nonce: 0,
+        game_move: details.clone(),
+        validation_program: validator.clone(),
+        previous_validation_info_hash: ValidationInfoHash::None,
+        referee_coin_puzzle_hash: PuzzleHash::default(),
+        fee: None,
+    };
+    InternalStateUpdateArgs {
+        validation_program: validator.clone(),
+        referee_args: Rc::new(referee_args),
+        state_update_args: StateUpdateMoveArgs {
+            state,
+            evidence: evidence.to_program(),
+        },
+    }
+    .run(allocator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::{GameType, Hash, Node};
+    use crate::session_phases::game_collection::game_collection;
+    use clvm_traits::{ClvmEncoder, ToClvm};
+
+    fn check_game(
+        allocator: &mut AllocEncoder,
+        game: &[u8],
+        parameters: NodePtr,
+        config: &ConformanceConfig,
+    ) -> ConformanceReport {
+        let factory = game_collection(allocator)
+            .remove(&GameType(game.to_vec()))
+            .and_then(|f| f.program)
+            .expect("registered game");
+        let factory = Puzzle::from_bytes(factory.bytes());
+        let parameters = Program::from_nodeptr(allocator, parameters).unwrap();
+        check_factory(allocator, factory, &parameters, config).unwrap()
+    }
+
+    fn assert_conformant(name: &str, report: &ConformanceReport) {
+        for violation in report.violations.iter() {
+            eprintln!("{name}: {violation}");
+        }
+        assert!(report.is_conformant(), "{name} violates the handler API");
+        assert!(
+            report.completed_playouts > 0,
+            "{name}: no play-out finished: {:?}",
+            report.stalled
+        );
+    }
+
+    fn atom(allocator: &mut AllocEncoder, bytes: &[u8]) -> Node {
+        Node(
+            allocator
+                .encode_atom(clvm_traits::Atom::Borrowed(bytes))
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn production_games_conform() {
+        let mut allocator = AllocEncoder::new();
+        let config = ConformanceConfig {
+            playouts: 2,
+            ..ConformanceConfig::default()
+        };
+
+        let parameters = (100, (1, ())).to_clvm(&mut allocator).unwrap();
+        let report = check_game(&mut allocator, b"calpoker", parameters, &config);
+        assert_conformant("calpoker", &report);
+        assert!(report.handlers.contains("calpoker_bob_handler_d"));
+
+        let parameters = (100, (10, (1, ()))).to_clvm(&mut allocator).unwrap();
+        let report = check_game(&mut allocator, b"spacepoker", parameters, &config);
+        assert_conformant("spacepoker", &report);
+
+        let mut krunk_config = config.clone();
+        for word in [b"CRANE", b"SLATE"] {
+            let word = atom(&mut allocator, word);
+            krunk_config
+                .inputs
+                .push(Program::from_nodeptr(&allocator, word.0).unwrap());
+        }
+        let parameters = 100.to_clvm(&mut allocator).unwrap();
+        let report = check_game(&mut allocator, b"krunk", parameters, &krunk_config);
+        assert_conformant("krunk", &report);
+    }
+
+    #[test]
+    fn wrong_validator_hash_is_reported() {
+        let mut allocator = AllocEncoder::new();
+        // The validator `1` returns its arguments, a non-nil list.
+        let validator = Rc::new(Program::from_hex("01").unwrap());
+        let validator_hash = validator.sha256tree(&mut allocator).hash().clone();
+        let label = atom(&mut allocator, b"bad_handler");
+        let wrong_hash = Hash::default();
+        // A quoted, terminal my-turn result claiming the wrong outgoing hash.
+        let my_turn = (
+            1,
+            (
+                label,
+                (
+                    (),
+                    (
+                        Node(validator.to_nodeptr(&mut allocator).unwrap()),
+                        (
+                            &wrong_hash,
+                            (
+                                Node(validator.to_nodeptr(&mut allocator).unwrap()),
+                                (&validator_hash, (0, (0, ((), ())))),
+                            ),
+                        ),
+                    ),
+                ),
+            ),
+        )
+            .to_clvm(&mut allocator)
+            .unwrap();
+        let their_turn = (1, ((), ((), ()))).to_clvm(&mut allocator).unwrap();
+        let game = FactoryGame {
+            sender_contribution: Amount::new(50),
+            receiver_contribution: Amount::new(50),
+            amount: Amount::new(100),
+            sender_goes_first: true,
+            initial_validation_program_hash: validator_hash,
+            initial_move: vec![],
+            initial_max_move_size: 0,
+            initial_state: Rc::new(Program::from_hex("80").unwrap()),
+            initial_mover_share: 0,
+            my_turn_handler: Program::from_nodeptr(&allocator, my_turn).unwrap(),
+            their_turn_handler: Program::from_nodeptr(&allocator, their_turn).unwrap(),
+            initial_validation_program: validator,
+        };
+
+        let config = ConformanceConfig::default();
+        let mut report = ConformanceReport::default();
+        let mut checker = Checker {
+            index: 0,
+            game: &game,
+            config: &config,
+            report: &mut report,
+        };
+        checker.check_record();
+        checker.playout(&mut allocator, 0).unwrap();
+
+        assert_eq!(report.completed_playouts, 1);
+        let problems: Vec<_> = report.violations.iter().map(|v| v.to_string()).collect();
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.starts_with("game 0: bad_handler: outgoing_validator_hash")),
+            "{problems:?}"
+        );
+    }
+}
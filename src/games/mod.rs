@@ -1,3 +1,4 @@
+pub mod conformance;
 pub mod krunk_dict_tree;
 
 use chia_protocol::Bytes;
@@ -0,0 +1,399 @@
+//! Runtime chialisp compilation for game development (`hot-reload` feature).
+//!
+//! Normally every puzzle is compiled by `build.rs` and embedded through
+//! [`puzzle_registry`], so changing a handler means a rebuild and a restart.
+//! With this feature a host can keep a [`HotReloader`] pointed at the source
+//! tree and call [`HotReloader::poll`] from its event loop. When a `.clsp` or
+//! `.clinc` file changes, the game factories are recompiled with the
+//! `chialisp` crate and every factory whose tree hash changed is reported as a
+//! [`ReloadEvent::Reloaded`], ready for [`GameSession::replace_game_factory`].
+//!
+//! Only future proposals use a swapped factory; games already running keep
+//! the program they started with. Both peers must reload the same sources,
+//! or proposals from one will not validate on the other. This is a
+//! development tool and is not meant to be enabled in release builds.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chialisp::classic::clvm_tools::comp_input::RunAndCompileInputData;
+use chialisp::classic::platform::argparse::ArgumentValue;
+use clvmr::Allocator;
+
+use crate::common::load_clvm::hex_to_sexp;
+use crate::common::puzzle_registry::{self, EmbeddedPuzzle};
+use crate::common::types::{AllocEncoder, Error, GameType, Puzzle, PuzzleHash, Sha256tree};
+use crate::game_session::GameSession;
+use crate::session_phases::game_collection::krunk_factory;
+use crate::session_phases::types::GameFactory;
+
+/// The production games and the registry entries their factories come from.
+const RELOADABLE_GAMES: &[(&[u8], &EmbeddedPuzzle)] = &[
+    (
+        b"calpoker",
+        &puzzle_registry::CALPOKER_GENERATE_CALPOKER_FACTORY,
+    ),
+    (
+        b"spacepoker",
+        &puzzle_registry::SPACEPOKER_GENERATE_SPACEPOKER_FACTORY,
+    ),
+    (b"krunk", &puzzle_registry::KRUNK_GENERATE_KRUNK_FACTORY),
+];
+
+/// A compile error with the location the compiler reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceError {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file, self.line, self.col, self.message
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum ReloadEvent {
+    Reloaded {
+        game_type: GameType,
+        puzzle_hash: PuzzleHash,
+        factory: GameFactory,
+    },
+    CompileFailed {
+        game_type: GameType,
+        error: SourceError,
+    },
+    /// The source compiled but its output could not be turned into a factory.
+    LoadFailed { game_type: GameType, error: Error },
+}
+
+/// Compile `source` (relative to `root`, or absolute) the same way `build.rs`
+/// does, writing its `.hex` outputs next to it. Includes resolve against
+/// `root/clsp` and `root`.
+pub fn compile_source(root: &Path, source: &str) -> Result<(), SourceError> {
+    let filename = root.join(source).to_string_lossy().to_string();
+    let content = fs::read_to_string(&filename).map_err(|e| SourceError {
+        file: filename.clone(),
+        line: 0,
+        col: 0,
+        message: format!("cannot read source: {e}"),
+    })?;
+
+    let mut arguments: HashMap<String, ArgumentValue> = HashMap::new();
+    arguments.insert(
+        "include".to_string(),
+        ArgumentValue::ArgArray(vec![
+            ArgumentValue::ArgString(None, root.join("clsp").to_string_lossy().to_string()),
+            ArgumentValue::ArgString(None, root.to_string_lossy().to_string()),
+        ]),
+    );
+    arguments.insert(
+        "path_or_code".to_string(),
+        ArgumentValue::ArgString(Some(filename.clone()), content),
+    );
+
+    let mut allocator = Allocator::new();
+    let parsed =
+        RunAndCompileInputData::new(&mut allocator, &arguments).map_err(|e| SourceError {
+            file: filename.clone(),
+            line: 0,
+            col: 0,
+            message: e,
+        })?;
+    let mut symbol_table = HashMap::new();
+    parsed
+        .compile_modern(&mut allocator, &mut symbol_table)
+        .map_err(|e| SourceError {
+            file: e.0.file.to_string(),
+            line: e.0.line,
+            col: e.0.col,
+            message: e.1,
+        })?;
+    Ok(())
+}
+
+/// Swap the factory of an already registered game type; the shared body of
+/// each phase's `replace_game_factory`.
+pub(crate) fn replace_factory(
+    game_types: &mut BTreeMap<GameType, GameFactory>,
+    game_type: &GameType,
+    factory: GameFactory,
+) -> bool {
+    match game_types.get_mut(game_type) {
+        Some(existing) => {
+            *existing = factory;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Watches a chialisp source tree and rebuilds game factories on change.
+pub struct HotReloader {
+    root: PathBuf,
+    games: Vec<(GameType, &'static EmbeddedPuzzle)>,
+    stamps: BTreeMap<PathBuf, SystemTime>,
+    current: BTreeMap<GameType, PuzzleHash>,
+}
+
+impl HotReloader {
+    /// `root` is the crate root: the directory holding `clsp/`. The embedded
+    /// factories are taken as the starting point, so nothing is reported
+    /// until a source actually changes what gets compiled.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let games = RELOADABLE_GAMES
+            .iter()
+            .map(|(game_type, puzzle)| (GameType(game_type.to_vec()), *puzzle))
+            .collect();
+        Self::with_games(root, games)
+    }
+
+    fn with_games(
+        root: impl Into<PathBuf>,
+        games: Vec<(GameType, &'static EmbeddedPuzzle)>,
+    ) -> Self {
+        let root = root.into();
+        let stamps = source_stamps(&root.join("clsp"));
+        let current = games
+            .iter()
+            .map(|(game_type, puzzle)| (game_type.clone(), puzzle.puzzle_hash()))
+            .collect();
+        HotReloader {
+            root,
+            games,
+            stamps,
+            current,
+        }
+    }
+
+    /// Recompile if any source changed since the last call.
+    pub fn poll(&mut self, allocator: &mut AllocEncoder) -> Vec<ReloadEvent> {
+        let stamps = source_stamps(&self.root.join("clsp"));
+        if stamps == self.stamps {
+            return vec![];
+        }
+        self.stamps = stamps;
+        self.reload(allocator)
+    }
+
+    /// Recompile every game factory now. Factories that compile to the
+    /// program already in use are not reported.
+    pub fn reload(&mut self, allocator: &mut AllocEncoder) -> Vec<ReloadEvent> {
+        let mut events = Vec::new();
+        for (game_type, puzzle) in self.games.clone() {
+            if let Err(error) = compile_source(&self.root, puzzle.source) {
+                events.push(ReloadEvent::CompileFailed { game_type, error });
+                continue;
+            }
+            let loaded = self.load_output(allocator, puzzle).and_then(|raw| {
+                let puzzle_hash = raw.sha256tree(allocator);
+                let factory = if game_type.0 == b"krunk" {
+                    krunk_factory(allocator, raw)?
+                } else {
+                    GameFactory {
+                        program: Some(raw.to_program()),
+                    }
+                };
+                Ok((puzzle_hash, factory))
+            });
+            match loaded {
+                Ok((puzzle_hash, factory)) => {
+                    if self.current.get(&game_type) == Some(&puzzle_hash) {
+                        continue;
+                    }
+                    self.current.insert(game_type.clone(), puzzle_hash.clone());
+                    events.push(ReloadEvent::Reloaded {
+                        game_type,
+                        puzzle_hash,
+                        factory,
+                    });
+                }
+                Err(error) => events.push(ReloadEvent::LoadFailed { game_type, error }),
+            }
+        }
+        events
+    }
+
+    /// Hand every reloaded factory to `session`. Returns the game types the
+    /// session accepted.
+    pub fn apply(session: &mut GameSession, events: &[ReloadEvent]) -> Vec<GameType> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                ReloadEvent::Reloaded {
+                    game_type, factory, ..
+                } if session.replace_game_factory(game_type, factory.clone()) => {
+                    Some(game_type.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn load_output(
+        &self,
+        allocator: &mut AllocEncoder,
+        puzzle: &EmbeddedPuzzle,
+    ) -> Result<Puzzle, Error> {
+        let path = self.root.join(puzzle.path);
+        let hex = fs::read_to_string(&path)
+            .map_err(|e| Error::StrErr(format!("cannot read {}: {e}", path.display())))?;
+        let node = hex_to_sexp(allocator, &hex)?;
+        Puzzle::from_nodeptr(allocator, node)
+    }
+}
+
+fn source_stamps(dir: &Path) -> BTreeMap<PathBuf, SystemTime> {
+    fn walk(dir: &Path, out: &mut BTreeMap<PathBuf, SystemTime>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(&path, out);
+            } else if matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("clsp" | "clinc")
+            ) {
+                if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+                    out.insert(path, modified);
+                }
+            }
+        }
+    }
+    let mut out = BTreeMap::new();
+    walk(dir, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::channel_state::types::ChannelPrivateKeys;
+    use crate::common::standard_coin::ChiaIdentity;
+    use crate::common::types::{Amount, Timeout};
+    use crate::game_session::GameSessionConfig;
+    use crate::session_phases::game_collection::game_collection;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "chia-gaming-hot-reload-{name}-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(dir.join("clsp")).expect("scratch dir");
+        dir
+    }
+
+    #[test]
+    fn compile_error_carries_source_location() {
+        // The source lives outside the tree; includes still resolve against it.
+        let scratch = scratch_dir("error");
+        let source = scratch.join("clsp/broken.clsp");
+        fs::write(
+            &source,
+            "(include *standard-cl-23*)\n\n(export (X) (+ X (undefined_function X)))\n",
+        )
+        .unwrap();
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let error = compile_source(root, source.to_str().unwrap()).expect_err("should not compile");
+        assert!(error.file.ends_with("broken.clsp"), "{error}");
+        assert_eq!(error.line, 3, "{error}");
+        fs::remove_dir_all(scratch).ok();
+    }
+
+    fn copy_sources(from: &Path, to: &Path) {
+        fs::create_dir_all(to).unwrap();
+        for entry in fs::read_dir(from).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                copy_sources(&path, &to.join(entry.file_name()));
+            } else if matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("clsp" | "clinc")
+            ) {
+                fs::copy(&path, to.join(entry.file_name())).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn edited_source_is_reloaded_into_the_session() {
+        let root = scratch_dir("edit");
+        copy_sources(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("clsp"),
+            &root.join("clsp"),
+        );
+        let debug = GameType(b"debug".to_vec());
+        let mut reloader =
+            HotReloader::with_games(&root, vec![(debug.clone(), &puzzle_registry::DEBUG_GAME)]);
+        let mut allocator = AllocEncoder::new();
+
+        let source = root.join(puzzle_registry::DEBUG_GAME.source);
+        let text = fs::read_to_string(&source).unwrap();
+        let edited = text.replace("\"no such personality\"", "\"no such mode\"");
+        assert_ne!(text, edited);
+        fs::write(&source, edited).unwrap();
+
+        let events = reloader.poll(&mut allocator);
+        let [ReloadEvent::Reloaded {
+            game_type,
+            puzzle_hash,
+            factory,
+        }] = &events[..]
+        else {
+            panic!("expected one reload, got {events:?}");
+        };
+        assert_eq!(*game_type, debug);
+        assert_ne!(*puzzle_hash, puzzle_registry::DEBUG_GAME.puzzle_hash());
+        let program = factory.program.as_ref().expect("program");
+        assert_eq!(program.sha256tree(&mut allocator), *puzzle_hash);
+        assert!(reloader.poll(&mut allocator).is_empty());
+
+        let mut rng = ChaCha8Rng::from_seed([4; 32]);
+        let identity = ChiaIdentity::new(&mut allocator, rng.random()).unwrap();
+        let keys: ChannelPrivateKeys = rng.random();
+        let mut session = GameSession::new_with_keys(
+            GameSessionConfig {
+                game_types: game_collection(&mut allocator),
+                have_potato: true,
+                reward_puzzle_hash: identity.puzzle_hash.clone(),
+                identity,
+                my_contribution: Amount::new(100),
+                their_contribution: Amount::new(100),
+                channel_timeout: Timeout::new(5),
+                unroll_timeout: Timeout::new(5),
+                liveness: Default::default(),
+                peer_limits: Default::default(),
+            },
+            keys,
+        );
+        assert_eq!(HotReloader::apply(&mut session, &events), vec![debug]);
+        assert!(!session.replace_game_factory(&GameType(b"unregistered".to_vec()), factory.clone()));
+        fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn poll_ignores_unchanged_tree() {
+        let root = scratch_dir("idle");
+        fs::write(root.join("clsp/idle.clsp"), "(include *standard-cl-23*)\n").unwrap();
+        let mut reloader = HotReloader::new(&root);
+        let mut allocator = AllocEncoder::new();
+        assert!(reloader.poll(&mut allocator).is_empty());
+        fs::remove_dir_all(root).ok();
+    }
+}
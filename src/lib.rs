@@ -6,19 +6,32 @@
 
 #[macro_use]
 pub mod common;
+pub mod attestation;
+pub mod channel_manager;
 pub mod channel_state;
 /// Provides as simple as possible a full blockchain interface that can be spoken
 /// with via a trait interface that's either local and synchronous or over a pipe.
 pub mod game_session;
 pub mod games;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+pub mod offer_file;
+pub mod operator_fee;
+pub mod payout;
 pub mod protocol_pretty;
 mod referee;
+pub mod reliable_link;
+pub mod secure_transport;
+pub mod session_journal;
+pub mod session_match;
 pub mod session_phases;
 pub mod shutdown;
 #[cfg(feature = "sim-tests")]
 pub mod simulator;
+pub mod spectator;
 pub mod transaction_manager;
 pub mod utils;
+pub mod wallet;
 
 #[cfg(test)]
 mod manifest_guards;
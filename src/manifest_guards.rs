@@ -131,3 +131,21 @@ fn every_referenced_hex_is_built() {
         missing.join("\n  ")
     );
 }
+
+/// The embedded registry must agree with the `.hex` files on disk, so code
+/// that still loads by path sees the same puzzle as code using the constants.
+#[test]
+fn embedded_puzzles_match_disk() {
+    use crate::common::puzzle_registry::{by_hash, by_name, verify_registry, ALL_PUZZLES};
+    use crate::common::types::PuzzleHash;
+
+    verify_registry().expect("registry hashes");
+    for puzzle in ALL_PUZZLES.iter() {
+        let on_disk = hex::decode(read(puzzle.path).trim()).expect("valid hex");
+        assert_eq!(on_disk, puzzle.bytes, "{} differs from disk", puzzle.path);
+        assert!(std::ptr::eq(by_name(puzzle.name).unwrap(), *puzzle));
+        assert!(std::ptr::eq(by_name(puzzle.path).unwrap(), *puzzle));
+        let hash = PuzzleHash::from_bytes(puzzle.tree_hash);
+        assert_eq!(by_hash(&hash).unwrap().tree_hash, puzzle.tree_hash);
+    }
+}
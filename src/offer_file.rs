@@ -0,0 +1,214 @@
+//! Chia offer files: the `offer1…` text the reference wallet reads and writes.
+//!
+//! An offer file is the offer's spend bundle in Chia's streamable encoding,
+//! zlib-compressed behind a two-byte format version, then bech32m-encoded
+//! with the `offer` prefix and no length limit.  The reference wallet
+//! compresses against a preset dictionary of well-known puzzles chosen by the
+//! version; we write plain zlib, which its reader accepts for any version.
+//! Reading a file compressed against a dictionary needs that dictionary's
+//! puzzles, which this build doesn't carry, so such files are rejected.
+//!
+//! Per-spend signatures don't survive the round trip: a file holds one
+//! aggregate, which decoding puts on the first spend.
+
+use std::io::{Read, Write};
+
+use chia_traits::Streamable;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::common::consensus::{from_protocol_spend_bundle, to_protocol_spend_bundle};
+use crate::common::types::{AllocEncoder, Error, SpendBundle};
+
+/// Human-readable prefix of an offer file.
+pub const OFFER_PREFIX: &str = "offer";
+
+/// Format version written ahead of the compressed bundle.
+const OFFER_VERSION: u16 = 2;
+
+/// Largest bundle we'll decompress, as in the reference wallet.
+const MAX_OFFER_BYTES: u64 = 6 * 1024 * 1024;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x01ff_ffff) << 5) ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|c| c >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|c| c & 31))
+}
+
+/// Regroup `data` from `from`-bit to `to`-bit values.  Without `pad`, left
+/// over bits must be zero padding of less than one input value.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Error> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let max = (1u32 << to) - 1;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for value in data {
+        acc = (acc << from) | u32::from(*value);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return Err(Error::StrErr(
+            "offer has invalid bech32m padding".to_string(),
+        ));
+    }
+    Ok(out)
+}
+
+fn bech32m_encode(hrp: &str, data: &[u8]) -> String {
+    let values = convert_bits(data, 8, 5, true).expect("padding never fails");
+    let checksum =
+        polymod(hrp_expand(hrp).chain(values.iter().copied()).chain([0; 6])) ^ BECH32M_CONST;
+    let mut out = String::with_capacity(hrp.len() + 1 + values.len() + 6);
+    out.push_str(hrp);
+    out.push('1');
+    out.extend(values.iter().map(|v| CHARSET[*v as usize] as char));
+    out.extend((0..6).map(|i| CHARSET[((checksum >> (5 * (5 - i))) & 31) as usize] as char));
+    out
+}
+
+fn bech32m_decode(text: &str) -> Result<(String, Vec<u8>), Error> {
+    let text = text.trim();
+    if text.bytes().any(|c| c.is_ascii_lowercase()) && text.bytes().any(|c| c.is_ascii_uppercase())
+    {
+        return Err(Error::StrErr(
+            "offer mixes upper and lower case".to_string(),
+        ));
+    }
+    let text = text.to_ascii_lowercase();
+    let separator = text
+        .rfind('1')
+        .filter(|at| *at > 0 && at + 7 <= text.len())
+        .ok_or_else(|| Error::StrErr("offer is not bech32m".to_string()))?;
+    let (hrp, rest) = text.split_at(separator);
+    let values = rest[1..]
+        .bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|x| *x == c)
+                .map(|v| v as u8)
+                .ok_or_else(|| {
+                    Error::StrErr(format!("offer has invalid character {:?}", c as char))
+                })
+        })
+        .collect::<Result<Vec<u8>, Error>>()?;
+    if polymod(hrp_expand(hrp).chain(values.iter().copied())) != BECH32M_CONST {
+        return Err(Error::StrErr("offer checksum does not match".to_string()));
+    }
+    let data = convert_bits(&values[..values.len() - 6], 5, 8, false)?;
+    Ok((hrp.to_string(), data))
+}
+
+/// Encode an offer bundle, such as one from
+/// [`StandardWallet::create_offer`](crate::wallet::StandardWallet::create_offer),
+/// as an `offer1…` file.
+pub fn encode_offer(allocator: &mut AllocEncoder, offer: &SpendBundle) -> Result<String, Error> {
+    let bundle = to_protocol_spend_bundle(allocator, &offer.spends)?;
+    let bytes = bundle
+        .to_bytes()
+        .map_err(|e| Error::StrErr(format!("offer does not serialize: {e:?}")))?;
+    let mut encoder = ZlibEncoder::new(OFFER_VERSION.to_be_bytes().to_vec(), Compression::best());
+    encoder
+        .write_all(&bytes)
+        .and_then(|()| encoder.finish())
+        .map(|compressed| bech32m_encode(OFFER_PREFIX, &compressed))
+        .map_err(|e| Error::StrErr(format!("offer does not compress: {e:?}")))
+}
+
+/// Decode an `offer1…` file into its bundle.
+pub fn decode_offer(text: &str) -> Result<SpendBundle, Error> {
+    let (hrp, data) = bech32m_decode(text)?;
+    if hrp != OFFER_PREFIX {
+        return Err(Error::StrErr(format!("not an offer: prefix {hrp:?}")));
+    }
+    let stream = data
+        .get(2..)
+        .filter(|stream| stream.len() >= 2)
+        .ok_or_else(|| Error::StrErr("offer is truncated".to_string()))?;
+    if stream[1] & 0x20 != 0 {
+        return Err(Error::StrErr(format!(
+            "offer is compressed against the version {} puzzle dictionary, which is not supported",
+            u16::from_be_bytes([data[0], data[1]])
+        )));
+    }
+    let mut bytes = Vec::new();
+    ZlibDecoder::new(stream)
+        .take(MAX_OFFER_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::StrErr(format!("offer does not decompress: {e:?}")))?;
+    if bytes.len() as u64 > MAX_OFFER_BYTES {
+        return Err(Error::StrErr("offer is too large".to_string()));
+    }
+    let bundle = chia_protocol::SpendBundle::from_bytes(&bytes)
+        .map_err(|e| Error::StrErr(format!("offer is not a spend bundle: {e:?}")))?;
+    Ok(SpendBundle {
+        name: Some("offer".to_string()),
+        spends: from_protocol_spend_bundle(&bundle)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP-350 test vectors.
+    #[test]
+    fn bech32m_matches_reference_vectors() {
+        for valid in [
+            "a1lqfn3a",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+        ] {
+            let (hrp, data) = bech32m_decode(valid).expect(valid);
+            let values = convert_bits(&data, 8, 5, true).expect("regroup");
+            if values.len() * 5 == data.len() * 8 {
+                assert_eq!(bech32m_encode(&hrp, &data), valid);
+            }
+        }
+        for invalid in ["a1lqfn3b", "A1lqfn3a1", "abc1rzg", "ab1"] {
+            assert!(bech32m_decode(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn dictionary_offers_are_rejected_by_name() {
+        // A zlib header with FDICT set, behind version 6.
+        let text = bech32m_encode(OFFER_PREFIX, &[0, 6, 0x78, 0xbb, 0, 0, 0, 0]);
+        let error = decode_offer(&text).unwrap_err();
+        assert!(format!("{error:?}").contains("version 6 puzzle dictionary"));
+    }
+}
@@ -0,0 +1,144 @@
+//! Operator fees: a third party's cut of each game's pot, agreed at proposal
+//! time.
+//!
+//! A proposal may carry [`OperatorFee`] terms.  Each game in the proposal
+//! group resolves them against its own pot to a [`GameFee`], which is curried
+//! into that game's referee puzzle.  However the game ends the fee is paid:
+//! an off-chain `AcceptSettlement` moves it into the channel's operator fee
+//! outputs (paid by the unroll coin and by a clean shutdown), and the
+//! referee's timeout and slash spends create the fee coin on chain.
+//!
+//! The fee comes out of the larger of the two shares, the mover's on a tie.
+//! Fees are capped at half the pot, so the larger share always covers it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::{Amount, Error, PuzzleHash};
+
+/// Basis points in the whole pot.
+const BASIS_POINTS: u64 = 10_000;
+
+/// How much of each game's pot the operator takes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeeRate {
+    /// Parts per ten thousand of the pot, rounded down.
+    BasisPoints(u16),
+    /// The same amount from every game.
+    Flat(Amount),
+}
+
+/// Fee terms as proposed: who gets paid and how much.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperatorFee {
+    pub puzzle_hash: PuzzleHash,
+    pub rate: FeeRate,
+}
+
+impl OperatorFee {
+    /// The fee on a game with pot `amount`, or `None` if it rounds to
+    /// nothing.  Fails if the fee would exceed half the pot.
+    pub fn for_game(&self, amount: &Amount) -> Result<Option<GameFee>, Error> {
+        let fee = match &self.rate {
+            FeeRate::BasisPoints(points) => {
+                let fee = amount.to_u64() as u128 * *points as u128 / BASIS_POINTS as u128;
+                Amount::new(fee as u64)
+            }
+            FeeRate::Flat(fee) => fee.clone(),
+        };
+        if fee > amount.half() {
+            return Err(Error::StrErr(format!(
+                "operator fee {fee} is more than half of the pot {amount}"
+            )));
+        }
+        if fee == Amount::default() {
+            return Ok(None);
+        }
+        Ok(Some(GameFee {
+            puzzle_hash: self.puzzle_hash.clone(),
+            amount: fee,
+        }))
+    }
+}
+
+/// The operator fee on one game, resolved from [`OperatorFee`] against the
+/// game's pot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameFee {
+    pub puzzle_hash: PuzzleHash,
+    pub amount: Amount,
+}
+
+/// What the mover and the waiter are paid when a game with pot `amount` ends
+/// with `mover_share` to the mover, after the fee if there is one.  Mirrors
+/// `net-payouts` in the referee puzzle.
+pub fn net_payouts(
+    fee: Option<&GameFee>,
+    amount: &Amount,
+    mover_share: &Amount,
+) -> Result<(Amount, Amount), Error> {
+    let waiter_share = amount.checked_sub(mover_share)?;
+    let Some(fee) = fee else {
+        return Ok((mover_share.clone(), waiter_share));
+    };
+    if *mover_share >= waiter_share {
+        Ok((mover_share.checked_sub(&fee.amount)?, waiter_share))
+    } else {
+        Ok((mover_share.clone(), waiter_share.checked_sub(&fee.amount)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_terms(rate: FeeRate) -> OperatorFee {
+        OperatorFee {
+            puzzle_hash: PuzzleHash::from_bytes([0x55; 32]),
+            rate,
+        }
+    }
+
+    #[test]
+    fn basis_points_round_down() {
+        let fee = fee_terms(FeeRate::BasisPoints(250))
+            .for_game(&Amount::new(199))
+            .unwrap()
+            .unwrap();
+        assert_eq!(fee.amount, Amount::new(4));
+    }
+
+    #[test]
+    fn fee_rounding_to_zero_is_none() {
+        let fee = fee_terms(FeeRate::BasisPoints(1))
+            .for_game(&Amount::new(200))
+            .unwrap();
+        assert_eq!(fee, None);
+    }
+
+    #[test]
+    fn fee_over_half_the_pot_is_rejected() {
+        let terms = fee_terms(FeeRate::Flat(Amount::new(51)));
+        assert!(terms.for_game(&Amount::new(100)).is_err());
+        assert!(terms.for_game(&Amount::new(102)).unwrap().is_some());
+    }
+
+    #[test]
+    fn larger_share_pays_the_fee() {
+        let fee = fee_terms(FeeRate::Flat(Amount::new(10)))
+            .for_game(&Amount::new(100))
+            .unwrap();
+        let pot = Amount::new(100);
+        assert_eq!(
+            net_payouts(fee.as_ref(), &pot, &Amount::new(70)).unwrap(),
+            (Amount::new(60), Amount::new(30))
+        );
+        assert_eq!(
+            net_payouts(fee.as_ref(), &pot, &Amount::new(30)).unwrap(),
+            (Amount::new(30), Amount::new(60))
+        );
+        assert_eq!(
+            net_payouts(fee.as_ref(), &pot, &Amount::new(50)).unwrap(),
+            (Amount::new(40), Amount::new(50))
+        );
+    }
+}
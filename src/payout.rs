@@ -0,0 +1,314 @@
+//! Payout destinations: where one player's winnings from a game go.
+//!
+//! By default a player's share of every game goes to the reward puzzle hash
+//! they named at handshake.  A proposal (for the proposer) or an accept (for
+//! the acceptor) may name [`PayoutTerms`] instead: one puzzle hash, or a split
+//! of the share across several.  The player signs the terms' payout puzzle
+//! hash the same way they sign their reward puzzle hash at handshake (see
+//! "Reward Payout Signatures" in ON_CHAIN.md), and the peer checks it.
+//!
+//! A game settled off-chain pays each destination directly, from the unroll
+//! coin or the clean shutdown.  On chain the referee pays one coin per player,
+//! so a split's payout puzzle hash is the payout splitter curried with its
+//! destinations, and spending that coin distributes it.
+
+use std::rc::Rc;
+
+use clvm_traits::{clvm_curried_args, ToClvm};
+use clvm_utils::CurriedProgram;
+use serde::{Deserialize, Serialize};
+
+use crate::common::puzzle_registry;
+use crate::common::standard_coin::{
+    calculate_hash_of_quoted_mod_hash, curry_and_treehash, sign_reward_payout,
+    verify_reward_payout_signature,
+};
+use crate::common::types::{
+    Aggsig, AllocEncoder, Amount, CoinSpend, CoinString, Error, IntoErr, PrivateKey, Program,
+    PublicKey, Puzzle, PuzzleHash, Sha256tree, Spend,
+};
+
+/// Basis points in a whole payout.
+const BASIS_POINTS: u64 = 10_000;
+
+/// Most destinations one payout may be split across.
+pub const MAX_PAYOUT_DESTINATIONS: usize = 8;
+
+/// One destination and its part of the payout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutShare {
+    pub puzzle_hash: PuzzleHash,
+    /// Parts per ten thousand of the payout.
+    pub basis_points: u16,
+}
+
+/// Where a player's share of a game is paid.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutTerms {
+    /// The first destination also takes any rounding remainder.
+    pub destinations: Vec<PayoutShare>,
+}
+
+impl PayoutTerms {
+    /// Pay the whole share to one puzzle hash.
+    pub fn to(puzzle_hash: PuzzleHash) -> Self {
+        PayoutTerms {
+            destinations: vec![PayoutShare {
+                puzzle_hash,
+                basis_points: BASIS_POINTS as u16,
+            }],
+        }
+    }
+
+    pub fn split(destinations: Vec<PayoutShare>) -> Result<Self, Error> {
+        let terms = PayoutTerms { destinations };
+        terms.validate()?;
+        Ok(terms)
+    }
+
+    /// Destinations must be distinct, at most [`MAX_PAYOUT_DESTINATIONS`], and
+    /// their basis points must sum to the whole.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.destinations.is_empty() || self.destinations.len() > MAX_PAYOUT_DESTINATIONS {
+            return Err(Error::StrErr(format!(
+                "payout needs 1 to {MAX_PAYOUT_DESTINATIONS} destinations, got {}",
+                self.destinations.len()
+            )));
+        }
+        let total: u64 = self
+            .destinations
+            .iter()
+            .map(|d| d.basis_points as u64)
+            .sum();
+        if total != BASIS_POINTS {
+            return Err(Error::StrErr(format!(
+                "payout basis points sum to {total}, not {BASIS_POINTS}"
+            )));
+        }
+        for (i, d) in self.destinations.iter().enumerate() {
+            if d.basis_points == 0 {
+                return Err(Error::StrErr(format!(
+                    "payout destination {} has no share",
+                    d.puzzle_hash
+                )));
+            }
+            if self.destinations[..i]
+                .iter()
+                .any(|e| e.puzzle_hash == d.puzzle_hash)
+            {
+                return Err(Error::StrErr(format!(
+                    "payout destination {} named twice",
+                    d.puzzle_hash
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.destinations.len() > 1
+    }
+
+    pub fn pays(&self, puzzle_hash: &PuzzleHash) -> bool {
+        self.destinations
+            .iter()
+            .any(|d| d.puzzle_hash == *puzzle_hash)
+    }
+
+    fn split_args(&self, allocator: &mut AllocEncoder) -> Result<Program, Error> {
+        let args: Vec<(PuzzleHash, (u64, ()))> = self
+            .destinations
+            .iter()
+            .map(|d| (d.puzzle_hash.clone(), (d.basis_points as u64, ())))
+            .collect();
+        let node = args.to_clvm(allocator).into_gen()?;
+        Program::from_nodeptr(allocator, node)
+    }
+
+    /// The puzzle hash the referee pays: the destination itself, or the
+    /// payout splitter curried with the destinations.
+    pub fn puzzle_hash(&self) -> Result<PuzzleHash, Error> {
+        if !self.is_split() {
+            return Ok(self.destinations[0].puzzle_hash.clone());
+        }
+        let mut allocator = AllocEncoder::new();
+        let args = self.split_args(&mut allocator)?;
+        let arg_hash = args.sha256tree(&mut allocator);
+        Ok(curry_and_treehash(
+            &PuzzleHash::from_hash(calculate_hash_of_quoted_mod_hash(
+                &puzzle_registry::PAYOUT_SPLIT.puzzle_hash(),
+            )),
+            &[arg_hash],
+        ))
+    }
+
+    /// How `amount` is divided, in destination order.  Mirrors the payout
+    /// splitter: zero shares are left out and the first destination takes the
+    /// remainder.
+    pub fn split_amount(&self, amount: &Amount) -> Vec<(PuzzleHash, Amount)> {
+        let rest: Vec<(PuzzleHash, Amount)> = self.destinations[1..]
+            .iter()
+            .map(|d| {
+                let share = amount.to_u64() as u128 * d.basis_points as u128 / BASIS_POINTS as u128;
+                (d.puzzle_hash.clone(), Amount::new(share as u64))
+            })
+            .collect();
+        let paid = rest.iter().map(|(_, a)| a.to_u64()).sum::<u64>();
+        std::iter::once((
+            self.destinations[0].puzzle_hash.clone(),
+            Amount::new(amount.to_u64() - paid),
+        ))
+        .chain(rest)
+        .filter(|(_, a)| *a != Amount::default())
+        .collect()
+    }
+
+    /// The spend that distributes a coin paid to a split payout.  `None` for a
+    /// single destination, which is paid directly.
+    pub fn distribution_spend(
+        &self,
+        allocator: &mut AllocEncoder,
+        coin: &CoinString,
+    ) -> Result<Option<CoinSpend>, Error> {
+        if !self.is_split() {
+            return Ok(None);
+        }
+        let amount = coin
+            .amount()
+            .ok_or_else(|| Error::StrErr("bad payout coin".to_string()))?;
+        let splitter = puzzle_registry::PAYOUT_SPLIT.to_puzzle(allocator)?;
+        let args = self.split_args(allocator)?;
+        let curried = CurriedProgram {
+            program: &splitter,
+            args: clvm_curried_args!(args),
+        }
+        .to_clvm(allocator)
+        .into_gen()?;
+        let puzzle = Puzzle::from_nodeptr(allocator, curried)?;
+        let solution = (amount, ()).to_clvm(allocator).into_gen()?;
+        Ok(Some(CoinSpend {
+            coin: coin.clone(),
+            bundle: Spend {
+                puzzle,
+                solution: Rc::new(Program::from_nodeptr(allocator, solution)?).into(),
+                signature: Aggsig::default(),
+            },
+        }))
+    }
+}
+
+/// Payout terms with the player's signature on their payout puzzle hash, as
+/// sent to the peer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedPayout {
+    pub terms: PayoutTerms,
+    pub signature: Aggsig,
+}
+
+impl SignedPayout {
+    /// Sign with the referee key, which the referee checks payouts against.
+    pub fn sign(referee_private_key: &PrivateKey, terms: PayoutTerms) -> Result<Self, Error> {
+        terms.validate()?;
+        let signature = sign_reward_payout(referee_private_key, &terms.puzzle_hash()?);
+        Ok(SignedPayout { terms, signature })
+    }
+
+    /// Check the terms and that `referee_pubkey` signed their puzzle hash.
+    pub fn verify(&self, referee_pubkey: &PublicKey) -> Result<PuzzleHash, Error> {
+        self.terms.validate()?;
+        let puzzle_hash = self.terms.puzzle_hash()?;
+        if !verify_reward_payout_signature(referee_pubkey, &puzzle_hash, &self.signature) {
+            return Err(Error::StrErr(
+                "invalid payout signature from peer".to_string(),
+            ));
+        }
+        Ok(puzzle_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::common::types::CoinCondition;
+
+    fn share(byte: u8, basis_points: u16) -> PayoutShare {
+        PayoutShare {
+            puzzle_hash: PuzzleHash::from_bytes([byte; 32]),
+            basis_points,
+        }
+    }
+
+    #[test]
+    fn split_gives_remainder_to_first_destination() {
+        let terms =
+            PayoutTerms::split(vec![share(1, 5000), share(2, 3333), share(3, 1667)]).unwrap();
+        let amounts: Vec<u64> = terms
+            .split_amount(&Amount::new(101))
+            .iter()
+            .map(|(_, a)| a.to_u64())
+            .collect();
+        assert_eq!(amounts, vec![52, 33, 16]);
+    }
+
+    #[test]
+    fn invalid_splits_are_rejected() {
+        assert!(PayoutTerms::split(vec![share(1, 5000), share(2, 4999)]).is_err());
+        assert!(PayoutTerms::split(vec![share(1, 5000), share(1, 5000)]).is_err());
+        assert!(PayoutTerms::split(vec![share(1, 10000), share(2, 0)]).is_err());
+        assert!(PayoutTerms::split(vec![]).is_err());
+    }
+
+    #[test]
+    fn splitter_spend_matches_split_amount() {
+        let mut allocator = AllocEncoder::new();
+        let terms =
+            PayoutTerms::split(vec![share(1, 7000), share(2, 2000), share(3, 1000)]).unwrap();
+        let coin = CoinString::from_parts(
+            &CoinString::from_parts(&Default::default(), &PuzzleHash::default(), &Amount::new(1))
+                .to_coin_id(),
+            &terms.puzzle_hash().unwrap(),
+            &Amount::new(999),
+        );
+        let spend = terms
+            .distribution_spend(&mut allocator, &coin)
+            .unwrap()
+            .expect("split");
+        assert_eq!(
+            spend.bundle.puzzle.sha256tree(&mut allocator),
+            terms.puzzle_hash().unwrap()
+        );
+        let conditions = CoinCondition::from_puzzle_and_solution(
+            &mut allocator,
+            &spend.bundle.puzzle.to_program(),
+            &spend.bundle.solution.p(),
+        )
+        .unwrap();
+        let created: Vec<(PuzzleHash, Amount)> = conditions
+            .into_iter()
+            .filter_map(|c| match c {
+                CoinCondition::CreateCoin(ph, amt) => Some((ph, amt)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(created, terms.split_amount(&Amount::new(999)));
+    }
+
+    #[test]
+    fn signed_payout_verifies_only_for_signer() {
+        let mut rng = ChaCha8Rng::from_seed([7; 32]);
+        let key: PrivateKey = rng.random();
+        let other: PrivateKey = rng.random();
+        let signed =
+            SignedPayout::sign(&key, PayoutTerms::to(PuzzleHash::from_bytes([9; 32]))).unwrap();
+        assert!(signed
+            .verify(&crate::common::standard_coin::private_to_public_key(&key))
+            .is_ok());
+        assert!(signed
+            .verify(&crate::common::standard_coin::private_to_public_key(&other))
+            .is_err());
+    }
+}
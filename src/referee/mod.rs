@@ -6,13 +6,17 @@ use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 
+use crate::attestation::GameOrigin;
+use crate::channel_state::game_handler::GameHandler;
 use crate::channel_state::game_start_info::GameStartInfo;
-use crate::channel_state::types::{ReadableMove, ValidationInfo};
+use crate::channel_state::types::{ReadableMove, StateUpdateProgram, ValidationInfo};
 use crate::common::standard_coin::{sign_reward_payout, ChiaIdentity};
 use crate::common::types::{
     Aggsig, AllocEncoder, Amount, CoinCondition, CoinString, Error, Hash, Program, PublicKey,
     Puzzle, PuzzleHash, Spend, Timeout,
 };
+use crate::operator_fee::{net_payouts, GameFee};
+use crate::payout::{PayoutTerms, SignedPayout};
 use crate::referee::my_turn::MyTurnReferee;
 use crate::referee::their_turn::TheirTurnReferee;
 use crate::referee::types::{
@@ -50,7 +54,7 @@ pub(crate) fn referee_initial_setup(
     };
     let my_turn = game_start_info.game_handler.is_my_turn();
 
-    let fixed = Rc::new(RefereeFixedContext {
+    let mut fixed = RefereeFixedContext {
         referee_coin_puzzle,
         referee_coin_puzzle_hash: referee_coin_puzzle_hash.clone(),
         their_referee_pubkey: their_pubkey.clone(),
@@ -66,7 +70,18 @@ pub(crate) fn referee_initial_setup(
         amount: game_start_info.amount.clone(),
         nonce,
         agg_sig_me_additional_data: agg_sig_me_additional_data.clone(),
-    });
+        fee: game_start_info.fee.clone(),
+        my_payout: None,
+        their_payout: None,
+        origin: game_start_info.origin.clone(),
+    };
+    if let Some(terms) = &game_start_info.my_payout {
+        fixed.set_my_payout(terms.clone())?;
+    }
+    if let Some(signed) = &game_start_info.their_payout {
+        fixed.set_their_payout(signed)?;
+    }
+    let fixed = Rc::new(fixed);
 
     let ip = game_start_info.initial_validation_program.clone();
     let vi_hash =
@@ -229,6 +244,35 @@ impl Referee {
         matches!(self, Referee::MyTurn(_))
     }
 
+    /// The game coin's arguments as of the latest move, with the game state
+    /// that move produced.
+    pub fn spectator_position(&self) -> (Rc<RefereePuzzleArgs>, Rc<Program>) {
+        match self {
+            Referee::MyTurn(t) => (t.spend_this_coin(), t.current_state()),
+            Referee::TheirTurn(t) => (t.spend_this_coin(), t.state.current_state.clone()),
+        }
+    }
+
+    /// The game coin's arguments now, then as the coin was created.  The
+    /// last channel state the opponent signed holds one of them.
+    pub fn countersigned_candidates(&self) -> [Rc<RefereePuzzleArgs>; 2] {
+        [self.spend_this_coin(), self.args_for_this_coin()]
+    }
+
+    /// The validator that checked the latest move.
+    pub fn last_move_validator(&self) -> StateUpdateProgram {
+        self.spend_this_coin().validation_program.clone()
+    }
+
+    /// The handler that will read the opponent's next move, while we wait for
+    /// it.
+    pub fn their_turn_handler(&self) -> Option<GameHandler> {
+        match self {
+            Referee::MyTurn(_) => None,
+            Referee::TheirTurn(t) => t.get_game_handler(),
+        }
+    }
+
     pub fn is_game_over(&self) -> bool {
         match self {
             Referee::MyTurn(r) => r.get_game_handler().is_none(),
@@ -255,10 +299,91 @@ impl Referee {
         self.fixed().timeout.clone()
     }
 
+    /// The operator fee this game pays at settlement, if any.
+    pub fn operator_fee(&self) -> Option<GameFee> {
+        self.fixed().fee.clone()
+    }
+
+    /// Our payout terms for this game, if we named our own.
+    pub fn my_payout(&self) -> Option<PayoutTerms> {
+        self.fixed().my_payout.clone()
+    }
+
+    pub fn their_payout(&self) -> Option<PayoutTerms> {
+        self.fixed().their_payout.clone()
+    }
+
+    /// The factory this game came from, if it was recorded.
+    pub fn game_origin(&self) -> Option<GameOrigin> {
+        self.fixed().origin.clone()
+    }
+
+    /// Our payout terms with our signature on their puzzle hash, as the peer
+    /// needs them.
+    pub fn my_signed_payout(&self) -> Option<SignedPayout> {
+        let fixed = self.fixed();
+        fixed.my_payout.clone().map(|terms| SignedPayout {
+            terms,
+            signature: fixed.my_reward_payout_signature.clone(),
+        })
+    }
+
+    /// Where the referee pays our share.
+    pub fn reward_puzzle_hash(&self) -> PuzzleHash {
+        self.fixed().reward_puzzle_hash.clone()
+    }
+
+    pub fn their_reward_puzzle_hash(&self) -> PuzzleHash {
+        self.fixed().their_reward_puzzle_hash.clone()
+    }
+
+    /// This referee paying our share as `terms` say.  Only meaningful before
+    /// the first move, while the game is still a proposal.
+    pub fn with_my_payout(&self, terms: PayoutTerms) -> Result<Referee, Error> {
+        let mut fixed = (*self.fixed()).clone();
+        fixed.set_my_payout(terms)?;
+        Ok(self.with_fixed(fixed))
+    }
+
+    /// This referee paying the opponent's share as their signed terms say.
+    pub fn with_their_payout(&self, signed: &SignedPayout) -> Result<Referee, Error> {
+        let mut fixed = (*self.fixed()).clone();
+        fixed.set_their_payout(signed)?;
+        Ok(self.with_fixed(fixed))
+    }
+
+    fn with_fixed(&self, fixed: RefereeFixedContext) -> Referee {
+        match self {
+            Referee::MyTurn(t) => {
+                let mut r = (**t).clone();
+                r.fixed = Rc::new(fixed);
+                Referee::MyTurn(Rc::new(r))
+            }
+            Referee::TheirTurn(t) => {
+                let mut r = (**t).clone();
+                r.fixed = Rc::new(fixed);
+                Referee::TheirTurn(Rc::new(r))
+            }
+        }
+    }
+
+    /// Our and their payout if the game settled now, after the operator fee.
+    fn current_payouts(&self) -> Result<(Amount, Amount), Error> {
+        let args = self.spend_this_coin();
+        let (mover_pay, waiter_pay) = net_payouts(
+            args.fee.as_ref(),
+            &self.fixed().amount,
+            &args.game_move.basic.mover_share,
+        )?;
+        if self.is_my_turn() {
+            Ok((mover_pay, waiter_pay))
+        } else {
+            Ok((waiter_pay, mover_pay))
+        }
+    }
+
     pub fn get_their_current_share(&self) -> Result<Amount, Error> {
-        self.fixed()
-            .amount
-            .checked_sub(&self.get_our_current_share()?)
+        Ok(self.current_payouts()?.1)
     }
 
     pub fn enable_cheating(&self, make_move: &[u8], mover_share: Amount) -> Option<Rc<Referee>> {
@@ -399,14 +524,7 @@ impl Referee {
     }
 
     pub fn get_our_current_share(&self) -> Result<Amount, Error> {
-        let args = self.spend_this_coin();
-        if self.is_my_turn() {
-            Ok(args.game_move.basic.mover_share.clone())
-        } else {
-            self.fixed()
-                .amount
-                .checked_sub(&args.game_move.basic.mover_share)
-        }
+        Ok(self.current_payouts()?.0)
     }
 
     /// Timeout unlike other actions applies to the current ph, not the one at the
@@ -439,8 +557,11 @@ impl Referee {
             } else {
                 self.args_for_this_coin()
             };
-        let mover_share = args.game_move.basic.mover_share.clone();
-        let waiter_share = self.fixed().amount.checked_sub(&mover_share)?;
+        let (mover_share, waiter_share) = net_payouts(
+            args.fee.as_ref(),
+            &self.fixed().amount,
+            &args.game_move.basic.mover_share,
+        )?;
 
         let i_am_mover = args.mover_pubkey == self.fixed().my_identity.public_key;
         let (my_ph, their_ph) = if i_am_mover {
@@ -248,6 +248,17 @@ impl MyTurnReferee {
         }
     }
 
+    /// The state our next move applies to.
+    pub fn current_state(&self) -> Rc<Program> {
+        match self.state.borrow() {
+            MyTurnRefereeGameState::Initial { initial_state, .. } => initial_state.clone(),
+            MyTurnRefereeGameState::AfterTheirTurn {
+                state_after_their_turn,
+                ..
+            } => state_after_their_turn.clone(),
+        }
+    }
+
     pub fn get_move_info(&self) -> Option<Rc<OnChainRefereeMoveData>> {
         match self.state.borrow() {
             MyTurnRefereeGameState::Initial { .. } => None,
@@ -536,6 +536,7 @@ impl TheirTurnReferee {
                 referee_coin_puzzle_hash: self.fixed.referee_coin_puzzle_hash.clone(),
                 validation_program: slash_validation_program.clone(),
                 previous_validation_info_hash: after_args.game_move.validation_info_hash.clone(),
+                fee: self.fixed.fee.clone(),
             });
             let puzzle = curry_referee_puzzle(allocator, &self.fixed.referee_coin_puzzle, &args)?;
             let new_puzzle_hash =
@@ -629,7 +630,8 @@ impl TheirTurnReferee {
         }));
         let slashing_coin_solution = solution.to_nodeptr(allocator, &self.fixed)?;
 
-        let reward_amount = self.fixed.amount.clone();
+        let fee = self.fixed.fee.as_ref().map(|fee| fee.amount.clone());
+        let reward_amount = self.fixed.amount.checked_sub(&fee.unwrap_or_default())?;
         let coin_string_of_output_coin = CoinString::from_parts(
             &coin_string.to_coin_id(),
             &self.fixed.reward_puzzle_hash,
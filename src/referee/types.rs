@@ -3,20 +3,24 @@ use std::rc::Rc;
 use clvm_traits::{clvm_curried_args, ClvmEncoder, ToClvm, ToClvmError};
 use clvm_utils::CurriedProgram;
 use clvmr::allocator::NodePtr;
-use clvmr::run_program;
 
 use serde::{Deserialize, Serialize};
 
+use crate::attestation::GameOrigin;
 use crate::channel_state::types::{
     CachedSendMove, Evidence, ReadableMove, StateUpdateProgram, ValidationInfo,
 };
+use crate::common::clvm_trace::{run_traced, with_trace};
 use crate::common::standard_coin::{
-    calculate_hash_of_quoted_mod_hash, curry_and_treehash, sign_agg_sig_me, ChiaIdentity,
+    calculate_hash_of_quoted_mod_hash, curry_and_treehash, sign_agg_sig_me, sign_reward_payout,
+    ChiaIdentity,
 };
 use crate::common::types::{
-    chia_dialect, Aggsig, AllocEncoder, Amount, CoinSpend, CoinString, Error, Hash, IntoErr, Node,
-    Program, ProgramRef, PublicKey, Puzzle, PuzzleHash, Sha256tree, Timeout, MAX_BLOCK_COST_CLVM,
+    Aggsig, AllocEncoder, Amount, CoinSpend, CoinString, Error, Hash, IntoErr, Node, Program,
+    ProgramRef, PublicKey, Puzzle, PuzzleHash, Sha256tree, Timeout,
 };
+use crate::operator_fee::GameFee;
+use crate::payout::{PayoutTerms, SignedPayout};
 use crate::utils::proper_list;
 
 // =============================================================================
@@ -131,7 +135,7 @@ pub enum TheirTurnCoinSpentResult {
     Slash(Box<SlashOutcome>),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RefereeFixedContext {
     pub referee_coin_puzzle: Puzzle,
     pub referee_coin_puzzle_hash: PuzzleHash,
@@ -149,6 +153,67 @@ pub struct RefereeFixedContext {
     pub timeout: Timeout,
     pub amount: Amount,
     pub nonce: u64,
+
+    #[serde(default)]
+    pub fee: Option<GameFee>,
+
+    /// Payout terms behind `reward_puzzle_hash` and
+    /// `their_reward_puzzle_hash` when a player named their own for this game.
+    #[serde(default)]
+    pub my_payout: Option<PayoutTerms>,
+    #[serde(default)]
+    pub their_payout: Option<PayoutTerms>,
+
+    #[serde(default)]
+    pub origin: Option<GameOrigin>,
+}
+
+impl RefereeFixedContext {
+    /// Pay our share of this game as `terms` say instead of to our channel
+    /// reward puzzle hash.
+    pub fn set_my_payout(&mut self, terms: PayoutTerms) -> Result<(), Error> {
+        terms.validate()?;
+        self.check_payout(&terms)?;
+        let puzzle_hash = terms.puzzle_hash()?;
+        if puzzle_hash == self.their_reward_puzzle_hash {
+            return Err(Error::StrErr(
+                "payout puzzle hash is the same as the opponent's".to_string(),
+            ));
+        }
+        self.my_reward_payout_signature =
+            sign_reward_payout(&self.my_identity.private_key, &puzzle_hash);
+        self.reward_puzzle_hash = puzzle_hash;
+        self.my_payout = Some(terms);
+        Ok(())
+    }
+
+    /// Pay the opponent's share as their signed terms say.
+    pub fn set_their_payout(&mut self, signed: &SignedPayout) -> Result<(), Error> {
+        let puzzle_hash = signed.verify(&self.their_referee_pubkey)?;
+        self.check_payout(&signed.terms)?;
+        if puzzle_hash == self.reward_puzzle_hash {
+            return Err(Error::StrErr(
+                "peer payout puzzle hash is the same as ours".to_string(),
+            ));
+        }
+        self.their_reward_payout_signature = signed.signature.clone();
+        self.their_reward_puzzle_hash = puzzle_hash;
+        self.their_payout = Some(signed.terms.clone());
+        Ok(())
+    }
+
+    /// A payout may not pay the operator fee's puzzle hash, where its coin
+    /// could collide with the fee coin.
+    fn check_payout(&self, terms: &PayoutTerms) -> Result<(), Error> {
+        if let Some(fee) = &self.fee {
+            if terms.pays(&fee.puzzle_hash) {
+                return Err(Error::StrErr(
+                    "payout pays the operator fee puzzle hash".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 // =============================================================================
@@ -267,6 +332,9 @@ pub struct RefereePuzzleArgs {
     pub validation_program: StateUpdateProgram,
     pub previous_validation_info_hash: ValidationInfoHash,
     pub referee_coin_puzzle_hash: PuzzleHash,
+    /// Curried after the eleven arguments above only when present.
+    #[serde(default)]
+    pub fee: Option<GameFee>,
 }
 
 impl RefereePuzzleArgs {
@@ -295,6 +363,7 @@ impl RefereePuzzleArgs {
             referee_coin_puzzle_hash: fixed_info.referee_coin_puzzle_hash.clone(),
             game_move: initial_move.clone(),
             previous_validation_info_hash,
+            fee: fixed_info.fee.clone(),
         }
     }
 
@@ -312,7 +381,7 @@ where
     NodePtr: ToClvm<E>,
 {
     fn to_clvm(&self, encoder: &mut E) -> Result<<E as ClvmEncoder>::Node, ToClvmError> {
-        [
+        let mut args = vec![
             self.mover_pubkey.to_clvm(encoder)?,
             self.waiter_pubkey.to_clvm(encoder)?,
             self.timeout.to_clvm(encoder)?,
@@ -326,8 +395,12 @@ where
             self.game_move.validation_info_hash.to_clvm(encoder)?,
             self.game_move.basic.mover_share.to_clvm(encoder)?,
             self.previous_validation_info_hash.to_clvm(encoder)?,
-        ]
-        .to_clvm(encoder)
+        ];
+        if let Some(fee) = &self.fee {
+            args.push(fee.puzzle_hash.to_clvm(encoder)?);
+            args.push(fee.amount.to_clvm(encoder)?);
+        }
+        args.to_clvm(encoder)
     }
 }
 
@@ -417,17 +490,15 @@ impl InternalStateUpdateArgs {
             PuzzleHash::from_hash(validation_program_mod_hash.clone()),
         )?;
 
-        let raw_result_p = run_program(
-            allocator.allocator(),
-            &chia_dialect(),
+        let (raw_result, trace) = run_traced(
+            allocator,
+            "validator",
             validation_program_nodeptr,
             validator_full_args_node,
-            MAX_BLOCK_COST_CLVM,
-        )
-        .into_gen();
-        let raw_result = raw_result_p?;
+        );
+        let raw_result = raw_result?;
 
-        parse_validator_result(allocator, raw_result.1)
+        parse_validator_result(allocator, raw_result).map_err(|e| with_trace(e, &trace))
     }
 }
 
@@ -0,0 +1,629 @@
+//! Reliable, ordered delivery of peer messages across relay drops and reloads.
+//!
+//! This is the Rust counterpart of the JS layer described in
+//! `FRONTEND_ARCHITECTURE.md` § Peer Message Reliability and speaks the same
+//! wire tags, so a native host and the WASM player app interoperate:
+//!
+//! - **Data:** `0x01`, 4-byte big-endian `msgno`, then the opaque peer message.
+//! - **Ack:** `0x02`, 4-byte big-endian `msgno` (cumulative).
+//! - **Keepalive:** `0x03`.
+//!
+//! [`ReliablePeerLink`] wraps a [`GameSession`]: every `OutboundMessage` the
+//! session emits is numbered, retained until acknowledged, and replaced in the
+//! drained events by its data frame. Inbound frames are deduplicated, reordered
+//! and handed to the session in `msgno` order. Because the retained log lives in
+//! the same serialized value as the session, saving the link after each drain
+//! is enough to make resend-after-reload work; hosts should persist before they
+//! transmit the drained frames, exactly as the JS layer does.
+//!
+//! A link built with [`ReliablePeerLink::with_transport`] also seals each
+//! session message in an established [`SecureChannel`] before numbering it, and
+//! opens inbound messages only once they are back in `msgno` order, so the
+//! channel sees every frame exactly once and in sequence however the relay
+//! drops or repeats them. Retransmissions resend the sealed bytes unchanged.
+//!
+//! The link implements [`ManagedGameSession`], so it composes under a
+//! [`crate::transaction_manager::TransactionManager`] in place of a bare session.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::{AllocEncoder, Error};
+use crate::game_session::{CoinObservation, DrainResult, GameSession};
+use crate::secure_transport::SecureChannel;
+use crate::session_phases::effects::{GameSessionEvent, TimeoutClaimSemantic};
+use crate::transaction_manager::ManagedGameSession;
+
+pub const FRAME_TAG_DATA: u8 = 0x01;
+pub const FRAME_TAG_ACK: u8 = 0x02;
+pub const FRAME_TAG_KEEPALIVE: u8 = 0x03;
+
+/// Out-of-order frames buffered beyond this window are dropped; the sender
+/// retransmits them after the gap is acknowledged.
+pub const MAX_REORDER_WINDOW: u32 = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkFrame {
+    Data { msgno: u32, payload: Vec<u8> },
+    Ack(u32),
+    Keepalive,
+}
+
+impl LinkFrame {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            LinkFrame::Data { msgno, payload } => {
+                let mut out = Vec::with_capacity(5 + payload.len());
+                out.push(FRAME_TAG_DATA);
+                out.extend_from_slice(&msgno.to_be_bytes());
+                out.extend_from_slice(payload);
+                out
+            }
+            LinkFrame::Ack(msgno) => {
+                let mut out = vec![FRAME_TAG_ACK];
+                out.extend_from_slice(&msgno.to_be_bytes());
+                out
+            }
+            LinkFrame::Keepalive => vec![FRAME_TAG_KEEPALIVE],
+        }
+    }
+
+    pub fn decode(frame: &[u8]) -> Result<LinkFrame, Error> {
+        let read_msgno = |bytes: &[u8]| -> Result<u32, Error> {
+            if bytes.len() < 4 {
+                return Err(Error::StrErr("link frame: truncated msgno".to_string()));
+            }
+            let mut fixed = [0u8; 4];
+            fixed.copy_from_slice(&bytes[..4]);
+            Ok(u32::from_be_bytes(fixed))
+        };
+        match frame.split_first() {
+            Some((&FRAME_TAG_DATA, rest)) => Ok(LinkFrame::Data {
+                msgno: read_msgno(rest)?,
+                payload: rest[4..].to_vec(),
+            }),
+            Some((&FRAME_TAG_ACK, rest)) => Ok(LinkFrame::Ack(read_msgno(rest)?)),
+            Some((&FRAME_TAG_KEEPALIVE, [])) => Ok(LinkFrame::Keepalive),
+            Some((tag, _)) => Err(Error::StrErr(format!(
+                "link frame: unknown or malformed frame with tag 0x{tag:02x}"
+            ))),
+            None => Err(Error::StrErr("link frame: empty frame".to_string())),
+        }
+    }
+}
+
+/// What an inbound frame did to the link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkReceipt {
+    /// One or more messages were released to the session in order.
+    Delivered(usize),
+    /// The frame was ahead of the next expected `msgno` and was buffered.
+    Buffered,
+    /// Already delivered; dropped. The peer likely lost our ack.
+    Duplicate,
+    /// An ack that pruned this many retained outbound messages.
+    Acked(usize),
+    Keepalive,
+}
+
+/// Sequencing state with no knowledge of the session it serves: numbering,
+/// the retained outbound log, and the inbound reorder window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkSequencer {
+    /// Last `msgno` assigned to an outbound message (0 = none yet).
+    local_number: u32,
+    /// Highest contiguous inbound `msgno` delivered to the session.
+    remote_number: u32,
+    /// Outbound messages not yet acknowledged, in `msgno` order.
+    unacked: VecDeque<(u32, Vec<u8>)>,
+    /// Inbound messages received ahead of a gap.
+    reorder_queue: BTreeMap<u32, Vec<u8>>,
+    ack_due: bool,
+    resend_due: bool,
+}
+
+impl LinkSequencer {
+    pub fn remote_number(&self) -> u32 {
+        self.remote_number
+    }
+
+    pub fn local_number(&self) -> u32 {
+        self.local_number
+    }
+
+    pub fn unacked_count(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// Number and retain an outbound message, returning its data frame.
+    pub fn push_outbound(&mut self, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let msgno = self
+            .local_number
+            .checked_add(1)
+            .ok_or_else(|| Error::StrErr("link: outbound msgno exhausted".to_string()))?;
+        self.local_number = msgno;
+        let frame = LinkFrame::Data {
+            msgno,
+            payload: payload.clone(),
+        }
+        .encode();
+        self.unacked.push_back((msgno, payload));
+        Ok(frame)
+    }
+
+    /// Process one decoded frame. Data is buffered until
+    /// [`LinkSequencer::deliver_ready`] hands it on in `msgno` order.
+    pub fn receive(&mut self, frame: LinkFrame) -> LinkReceipt {
+        match frame {
+            LinkFrame::Data { msgno, payload } => {
+                if msgno <= self.remote_number {
+                    // Re-ack in case ours was lost, and offer anything we hold
+                    // in case the peer reloaded from an older save.
+                    self.ack_due = true;
+                    self.resend_due = true;
+                    return LinkReceipt::Duplicate;
+                }
+                if msgno - self.remote_number <= MAX_REORDER_WINDOW {
+                    self.reorder_queue.entry(msgno).or_insert(payload);
+                }
+                LinkReceipt::Buffered
+            }
+            LinkFrame::Ack(msgno) => {
+                let before = self.unacked.len();
+                self.unacked.retain(|(n, _)| *n > msgno);
+                LinkReceipt::Acked(before - self.unacked.len())
+            }
+            LinkFrame::Keepalive => LinkReceipt::Keepalive,
+        }
+    }
+
+    /// Pass buffered messages to `deliver` in `msgno` order, returning how
+    /// many it accepted. `remote_number` only moves past a message once
+    /// `deliver` succeeds; a message it rejects is dropped unacknowledged so
+    /// that the peer's retransmission can take its place.
+    pub fn deliver_ready<F>(&mut self, mut deliver: F) -> Result<usize, Error>
+    where
+        F: FnMut(&[u8]) -> Result<(), Error>,
+    {
+        let mut delivered = 0;
+        while let Some(payload) = self.reorder_queue.remove(&(self.remote_number + 1)) {
+            self.ack_due = true;
+            deliver(&payload)?;
+            self.remote_number += 1;
+            delivered += 1;
+        }
+        Ok(delivered)
+    }
+
+    /// Ask for every retained outbound frame and a fresh cumulative ack on the
+    /// next drain. Call after the relay reconnects or the host reloads.
+    pub fn request_reconcile(&mut self) {
+        self.resend_due = true;
+        self.ack_due = true;
+    }
+
+    /// Frames that must follow the current drain: retransmissions first, then
+    /// the cumulative ack.
+    pub fn take_control_frames(&mut self) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        if std::mem::take(&mut self.resend_due) {
+            out.extend(self.unacked.iter().map(|(msgno, payload)| {
+                LinkFrame::Data {
+                    msgno: *msgno,
+                    payload: payload.clone(),
+                }
+                .encode()
+            }));
+        }
+        if std::mem::take(&mut self.ack_due) {
+            out.push(LinkFrame::Ack(self.remote_number).encode());
+        }
+        out
+    }
+}
+
+/// Open `payload` in `transport`, if there is one, and hand it to `deliver`.
+/// The transport only moves past the frame once `deliver` accepts it, so the
+/// retransmission of a rejected message still opens.
+fn open_and_deliver<F>(
+    transport: &mut Option<SecureChannel>,
+    payload: &[u8],
+    deliver: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(&[u8]) -> Result<(), Error>,
+{
+    let Some(transport) = transport.as_mut() else {
+        return deliver(payload);
+    };
+    let mut opened = transport.clone();
+    deliver(&opened.open(payload)?)?;
+    *transport = opened;
+    Ok(())
+}
+
+/// A [`GameSession`] with sequencing, acks and resend on its peer traffic.
+#[derive(Serialize, Deserialize)]
+pub struct ReliablePeerLink {
+    session: GameSession,
+    sequencer: LinkSequencer,
+    #[serde(default)]
+    transport: Option<SecureChannel>,
+}
+
+impl ReliablePeerLink {
+    pub fn new(session: GameSession) -> Self {
+        ReliablePeerLink {
+            session,
+            sequencer: LinkSequencer::default(),
+            transport: None,
+        }
+    }
+
+    /// A link whose session messages travel inside `transport`. Run the
+    /// [`crate::secure_transport::TransportHandshake`] to completion with the
+    /// peer before the session's first drain, so that its HandshakeA/B is the
+    /// first message sealed.
+    pub fn with_transport(session: GameSession, transport: SecureChannel) -> Self {
+        ReliablePeerLink {
+            session,
+            sequencer: LinkSequencer::default(),
+            transport: Some(transport),
+        }
+    }
+
+    pub fn transport(&self) -> Option<&SecureChannel> {
+        self.transport.as_ref()
+    }
+
+    pub fn session(&self) -> &GameSession {
+        &self.session
+    }
+
+    /// For game actions and queries. Peer traffic must go through the link's
+    /// own `deliver_message` and `flush_and_collect`, or its numbering breaks.
+    pub fn session_mut(&mut self) -> &mut GameSession {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> GameSession {
+        self.session
+    }
+
+    pub fn sequencer(&self) -> &LinkSequencer {
+        &self.sequencer
+    }
+
+    /// Accept one relay frame from the peer. Data frames reach the session in
+    /// `msgno` order; acks prune the retained log.
+    pub fn deliver_message(&mut self, frame: &[u8]) -> Result<LinkReceipt, Error> {
+        let decoded = LinkFrame::decode(frame)?;
+        let receipt = self.sequencer.receive(decoded);
+        let session = &mut self.session;
+        let transport = &mut self.transport;
+        let delivered = self.sequencer.deliver_ready(|payload| {
+            open_and_deliver(transport, payload, |message| {
+                session.deliver_message(message)
+            })
+        })?;
+        if delivered > 0 {
+            return Ok(LinkReceipt::Delivered(delivered));
+        }
+        Ok(receipt)
+    }
+
+    /// Queue retransmission of everything unacknowledged plus a fresh ack.
+    pub fn reconnect(&mut self) {
+        self.sequencer.request_reconcile();
+    }
+
+    pub fn keepalive_frame() -> Vec<u8> {
+        LinkFrame::Keepalive.encode()
+    }
+
+    /// Drain the session, numbering outbound peer messages. Every
+    /// `OutboundMessage` in the result carries a link frame ready to relay.
+    pub fn flush_and_collect(
+        &mut self,
+        allocator: &mut AllocEncoder,
+    ) -> Result<DrainResult, Error> {
+        let drained = self.session.flush_and_collect(allocator)?;
+        let mut events = VecDeque::with_capacity(drained.events.len());
+        let mut control = self.sequencer.take_control_frames();
+        for event in drained.events {
+            match event {
+                GameSessionEvent::OutboundMessage(payload) => {
+                    let payload = match self.transport.as_mut() {
+                        Some(transport) => transport.seal(&payload)?,
+                        None => payload,
+                    };
+                    events.push_back(GameSessionEvent::OutboundMessage(
+                        self.sequencer.push_outbound(payload)?,
+                    ));
+                }
+                other => events.push_back(other),
+            }
+        }
+        events.extend(control.drain(..).map(GameSessionEvent::OutboundMessage));
+        Ok(DrainResult {
+            events,
+            resync: drained.resync,
+        })
+    }
+}
+
+impl ManagedGameSession for ReliablePeerLink {
+    fn session_observe(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        height: u64,
+        observations: Option<&[CoinObservation]>,
+    ) -> Result<(), Error> {
+        self.session
+            .session_observe(allocator, height, observations)
+    }
+
+    fn session_flush_and_collect(
+        &mut self,
+        allocator: &mut AllocEncoder,
+    ) -> Result<DrainResult, Error> {
+        ReliablePeerLink::flush_and_collect(self, allocator)
+    }
+
+    fn session_timeout_claim_submitted(
+        &mut self,
+        semantic: TimeoutClaimSemantic,
+    ) -> Result<(), Error> {
+        self.session.session_timeout_claim_submitted(semantic)
+    }
+
+    fn session_timeout_claim_rearmed(
+        &mut self,
+        semantic: TimeoutClaimSemantic,
+    ) -> Result<(), Error> {
+        self.session.session_timeout_claim_rearmed(semantic)
+    }
+
+    fn is_abandoned(&self) -> bool {
+        self.session.is_abandoned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::channel_state::types::ChannelPrivateKeys;
+    use crate::common::standard_coin::ChiaIdentity;
+    use crate::common::types::{Amount, Timeout};
+    use crate::game_session::GameSessionConfig;
+    use crate::secure_transport::{TransportConfig, TransportHandshake, TransportRole};
+    use crate::session_phases::game_collection::game_collection;
+
+    fn data(msgno: u32, payload: &[u8]) -> LinkFrame {
+        LinkFrame::Data {
+            msgno,
+            payload: payload.to_vec(),
+        }
+    }
+
+    fn collect(seq: &mut LinkSequencer, out: &mut Vec<Vec<u8>>) -> usize {
+        seq.deliver_ready(|payload| {
+            out.push(payload.to_vec());
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn frames_use_the_js_wire_tags() {
+        let frame = data(0x0102_0304, b"xy").encode();
+        assert_eq!(frame, vec![0x01, 0x01, 0x02, 0x03, 0x04, b'x', b'y']);
+        assert_eq!(LinkFrame::decode(&frame).unwrap(), data(0x0102_0304, b"xy"));
+        assert_eq!(LinkFrame::Ack(7).encode(), vec![0x02, 0, 0, 0, 7]);
+        assert_eq!(LinkFrame::decode(&[0x03]).unwrap(), LinkFrame::Keepalive);
+        assert!(LinkFrame::decode(&[0x02, 0, 0]).is_err());
+        assert_eq!(
+            LinkFrame::decode(&[0x02, 0, 0, 0, 7, 0xff]).unwrap(),
+            LinkFrame::Ack(7)
+        );
+        assert!(LinkFrame::decode(&[0x09]).is_err());
+    }
+
+    #[test]
+    fn inbound_is_reordered_and_deduplicated() {
+        let mut seq = LinkSequencer::default();
+        let mut out = Vec::new();
+        assert_eq!(seq.receive(data(2, b"b")), LinkReceipt::Buffered);
+        assert_eq!(seq.receive(data(3, b"c")), LinkReceipt::Buffered);
+        assert_eq!(collect(&mut seq, &mut out), 0);
+        assert_eq!(seq.receive(data(1, b"a")), LinkReceipt::Buffered);
+        assert_eq!(collect(&mut seq, &mut out), 3);
+        assert_eq!(out, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+        assert_eq!(seq.remote_number(), 3);
+
+        out.clear();
+        assert_eq!(seq.receive(data(2, b"b")), LinkReceipt::Duplicate);
+        assert_eq!(collect(&mut seq, &mut out), 0);
+        let control = seq.take_control_frames();
+        assert_eq!(control, vec![LinkFrame::Ack(3).encode()]);
+    }
+
+    #[test]
+    fn acks_prune_and_reconcile_resends_the_rest() {
+        let mut seq = LinkSequencer::default();
+        for payload in [b"one", b"two", b"thr"] {
+            seq.push_outbound(payload.to_vec()).unwrap();
+        }
+        assert_eq!(seq.receive(LinkFrame::Ack(2)), LinkReceipt::Acked(2));
+        assert_eq!(seq.unacked_count(), 1);
+
+        // Survives a save/restore and retransmits on reconnect.
+        let saved = bencodex::to_vec(&seq).unwrap();
+        let mut restored: LinkSequencer = bencodex::from_slice(&saved).unwrap();
+        restored.request_reconcile();
+        assert_eq!(
+            restored.take_control_frames(),
+            vec![data(3, b"thr").encode(), LinkFrame::Ack(0).encode()]
+        );
+        assert_eq!(
+            restored.push_outbound(b"four".to_vec()).unwrap(),
+            data(4, b"four").encode()
+        );
+    }
+
+    fn secure_pair() -> (ReliablePeerLink, ReliablePeerLink, AllocEncoder) {
+        let mut allocator = AllocEncoder::new();
+        let mut rng = ChaCha8Rng::from_seed([5; 32]);
+        let keys: [ChannelPrivateKeys; 2] = [rng.random(), rng.random()];
+        let mut handshakes = [
+            TransportHandshake::new(
+                &mut rng,
+                TransportRole::Initiator,
+                &keys[0],
+                TransportConfig::default(),
+            ),
+            TransportHandshake::new(
+                &mut rng,
+                TransportRole::Responder,
+                &keys[1],
+                TransportConfig::default(),
+            ),
+        ];
+        for step in 0..3 {
+            let message = handshakes[step % 2].write_message().unwrap();
+            handshakes[1 - step % 2].read_message(&message).unwrap();
+        }
+        let [initiator, responder] = handshakes;
+        let channels = [
+            initiator.into_channel().unwrap(),
+            responder.into_channel().unwrap(),
+        ];
+        let game_types = game_collection(&mut allocator);
+        let mut links = keys
+            .into_iter()
+            .zip(channels)
+            .enumerate()
+            .map(|(i, (keys, channel))| {
+                let identity = ChiaIdentity::new(&mut allocator, rng.random()).unwrap();
+                let session = GameSession::new_with_keys(
+                    GameSessionConfig {
+                        game_types: game_types.clone(),
+                        have_potato: i == 0,
+                        reward_puzzle_hash: identity.puzzle_hash.clone(),
+                        identity,
+                        my_contribution: Amount::new(100),
+                        their_contribution: Amount::new(100),
+                        channel_timeout: Timeout::new(5),
+                        unroll_timeout: Timeout::new(5),
+                        liveness: Default::default(),
+                        peer_limits: Default::default(),
+                    },
+                    keys,
+                );
+                ReliablePeerLink::with_transport(session, channel)
+            });
+        let alice = links.next().unwrap();
+        let bob = links.next().unwrap();
+        (alice, bob, allocator)
+    }
+
+    fn outbound(drained: DrainResult) -> Vec<Vec<u8>> {
+        drained
+            .events
+            .into_iter()
+            .filter_map(|event| match event {
+                GameSessionEvent::OutboundMessage(frame) => Some(frame),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn secure_links_bind_the_session_handshake() {
+        let (mut alice, mut bob, mut allocator) = secure_pair();
+        alice.session_mut().start_handshake(&mut allocator).unwrap();
+        let to_bob = outbound(alice.flush_and_collect(&mut allocator).unwrap());
+        assert_eq!(to_bob.len(), 1);
+
+        // A frame the transport rejects is not taken as delivered, so the
+        // genuine one still gets through.
+        let mut tampered = to_bob[0].clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(bob.deliver_message(&tampered).is_err());
+        assert!(!bob.transport().unwrap().binding_verified());
+        bob.deliver_message(&to_bob[0]).unwrap();
+        let to_alice = outbound(bob.flush_and_collect(&mut allocator).unwrap());
+        assert!(bob.transport().unwrap().binding_verified());
+        for frame in &to_alice {
+            alice.deliver_message(frame).unwrap();
+        }
+        alice.flush_and_collect(&mut allocator).unwrap();
+        assert!(alice.transport().unwrap().binding_verified());
+    }
+
+    #[test]
+    fn far_future_frames_are_not_buffered() {
+        let mut seq = LinkSequencer::default();
+        seq.receive(data(MAX_REORDER_WINDOW + 5, b"x"));
+        assert!(seq.reorder_queue.is_empty());
+    }
+
+    #[test]
+    fn rejected_messages_are_not_acknowledged() {
+        let mut seq = LinkSequencer::default();
+        seq.receive(data(1, b"a"));
+        seq.receive(data(2, b"bad"));
+        seq.receive(data(3, b"c"));
+        let mut out = Vec::new();
+        let result = seq.deliver_ready(|payload| {
+            if payload == b"bad" {
+                return Err(Error::StrErr("rejected".to_string()));
+            }
+            out.push(payload.to_vec());
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(out, vec![b"a".to_vec()]);
+        assert_eq!(seq.remote_number(), 1);
+        assert_eq!(seq.take_control_frames(), vec![LinkFrame::Ack(1).encode()]);
+
+        // The peer's retransmission replaces the rejected message.
+        assert_eq!(seq.receive(data(2, b"b")), LinkReceipt::Buffered);
+        assert_eq!(collect(&mut seq, &mut out), 2);
+        assert_eq!(seq.remote_number(), 3);
+    }
+
+    #[test]
+    fn secure_retransmission_opens_after_a_rejection() {
+        let (mut alice, mut bob, mut allocator) = secure_pair();
+        alice.session_mut().start_handshake(&mut allocator).unwrap();
+        let to_bob = outbound(alice.flush_and_collect(&mut allocator).unwrap());
+        assert_eq!(to_bob.len(), 1);
+
+        // Bob's session turns the message down once, after it opened.
+        bob.sequencer
+            .receive(LinkFrame::decode(&to_bob[0]).unwrap());
+        let transport = &mut bob.transport;
+        let rejected = bob.sequencer.deliver_ready(|payload| {
+            open_and_deliver(transport, payload, |_| {
+                Err(Error::StrErr("rejected".to_string()))
+            })
+        });
+        assert!(rejected.is_err());
+        assert_eq!(bob.sequencer.remote_number(), 0);
+
+        // The byte-identical retransmission still opens and gets through.
+        assert_eq!(
+            bob.deliver_message(&to_bob[0]).unwrap(),
+            LinkReceipt::Delivered(1)
+        );
+        assert!(bob.transport().unwrap().binding_verified());
+        assert!(!outbound(bob.flush_and_collect(&mut allocator).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,657 @@
+//! Optional authenticated, encrypted framing for peer message bytes.
+//!
+//! `PeerMessage`s are otherwise relayed as plain bencodex, so any hub in the
+//! path can read hole cards and inject traffic. A [`TransportHandshake`] runs a
+//! Noise-XX-style exchange (`-> e`, `<- e, ee, s, es`, `-> s, se`) over
+//! BLS12-381 G1 points, using each side's channel coin key as its static key.
+//! Completing it yields a [`SecureChannel`] that seals frames with
+//! ChaCha20-Poly1305, rejects replayed or reordered frames, and rekeys on a
+//! fixed message schedule.
+//!
+//! The static key proven during the transport handshake must be the same key
+//! the peer later advertises as `channel_public_key` in its `HandshakePayloadB`.
+//! [`SecureChannel::open`] enforces that: until the peer's HandshakeA/B has been
+//! seen and checked, no other peer message is passed through.
+//!
+//! [`crate::reliable_link::ReliablePeerLink::with_transport`] carries a
+//! session's traffic through an established channel.
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::channel_state::types::ChannelPrivateKeys;
+use crate::common::standard_coin::private_to_public_key;
+use crate::common::types::{Error, PrivateKey, PublicKey};
+use crate::session_phases::handshake::HandshakePayloadB;
+use crate::session_phases::types::PeerMessage;
+
+const PROTOCOL_NAME: &[u8] = b"chia-gaming-transport-v1_XX_BLS12381G1_ChaChaPoly_SHA256";
+const REKEY_INFO: &[u8] = b"chia-gaming-transport-v1 rekey";
+const KEY_LEN: usize = 32;
+const POINT_LEN: usize = 48;
+const TAG_LEN: usize = 16;
+const FRAME_HEADER_LEN: usize = 8;
+
+/// Frames whose counter would require rekeying this many epochs ahead are
+/// rejected outright rather than burning CPU on key derivation.
+const MAX_REKEY_SKIP: u64 = 64;
+
+/// Which end of the transport handshake this side plays. Mirrors
+/// `have_potato` in `GameSessionConfig`: the potato holder initiates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransportRole {
+    Initiator,
+    Responder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransportConfig {
+    /// Number of frames sent under one key before both directions step to the
+    /// next key in the chain.
+    pub rekey_interval: u64,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        TransportConfig {
+            rekey_interval: 1024,
+        }
+    }
+}
+
+fn transport_err(what: &str) -> Error {
+    Error::StrErr(format!("secure transport: {what}"))
+}
+
+fn hash_two(a: &[u8], b: &[u8]) -> [u8; KEY_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.update(a);
+    hasher.update(b);
+    hasher.finalize().into()
+}
+
+fn hkdf2(salt: &[u8; KEY_LEN], ikm: &[u8]) -> ([u8; KEY_LEN], [u8; KEY_LEN]) {
+    let hk = Hkdf::<Sha256>::new(Some(salt), ikm);
+    let mut okm = [0u8; KEY_LEN * 2];
+    hk.expand(&[], &mut okm)
+        .expect("64 bytes is a valid hkdf-sha256 output length");
+    let mut first = [0u8; KEY_LEN];
+    let mut second = [0u8; KEY_LEN];
+    first.copy_from_slice(&okm[..KEY_LEN]);
+    second.copy_from_slice(&okm[KEY_LEN..]);
+    (first, second)
+}
+
+fn nonce_bytes(counter: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+fn aead_encrypt(
+    key: &[u8; KEY_LEN],
+    counter: u64,
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let cipher = ChaCha20Poly1305::new(&Key::from(*key));
+    let nonce = nonce_bytes(counter);
+    cipher
+        .encrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| transport_err("encryption failed"))
+}
+
+fn aead_decrypt(
+    key: &[u8; KEY_LEN],
+    counter: u64,
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let cipher = ChaCha20Poly1305::new(&Key::from(*key));
+    let nonce = nonce_bytes(counter);
+    cipher
+        .decrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| transport_err("authentication failed"))
+}
+
+/// Diffie-Hellman on G1: scale the peer's point by our scalar. Both the
+/// ephemeral and the static (channel) keys are ordinary BLS key pairs.
+fn dh(ours: &PrivateKey, theirs: &PublicKey) -> Result<[u8; POINT_LEN], Error> {
+    let mut point = theirs.to_bls();
+    if point.is_inf() {
+        return Err(transport_err("peer sent the identity point"));
+    }
+    point.scalar_multiply(&ours.bytes());
+    Ok(point.to_bytes())
+}
+
+fn rekey(key: &[u8; KEY_LEN]) -> [u8; KEY_LEN] {
+    let hk = Hkdf::<Sha256>::new(None, key);
+    let mut next = [0u8; KEY_LEN];
+    hk.expand(REKEY_INFO, &mut next)
+        .expect("32 bytes is a valid hkdf-sha256 output length");
+    next
+}
+
+fn public_key_from(bytes: &[u8]) -> Result<PublicKey, Error> {
+    PublicKey::from_slice(bytes).map_err(|_| transport_err("malformed public key"))
+}
+
+/// Chaining key, handshake hash and the current handshake cipher key.
+struct SymmetricState {
+    ck: [u8; KEY_LEN],
+    h: [u8; KEY_LEN],
+    k: Option<[u8; KEY_LEN]>,
+    n: u64,
+}
+
+impl SymmetricState {
+    fn new() -> Self {
+        let h: [u8; KEY_LEN] = Sha256::digest(PROTOCOL_NAME).into();
+        SymmetricState {
+            ck: h,
+            h,
+            k: None,
+            n: 0,
+        }
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        self.h = hash_two(&self.h, data);
+    }
+
+    fn mix_key(&mut self, ikm: &[u8]) {
+        let (ck, k) = hkdf2(&self.ck, ikm);
+        self.ck = ck;
+        self.k = Some(k);
+        self.n = 0;
+    }
+
+    fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let ciphertext = if let Some(k) = &self.k {
+            let ct = aead_encrypt(k, self.n, &self.h, plaintext)?;
+            self.n += 1;
+            ct
+        } else {
+            plaintext.to_vec()
+        };
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let plaintext = if let Some(k) = &self.k {
+            let pt = aead_decrypt(k, self.n, &self.h, ciphertext)?;
+            self.n += 1;
+            pt
+        } else {
+            ciphertext.to_vec()
+        };
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    fn split(&self) -> ([u8; KEY_LEN], [u8; KEY_LEN]) {
+        hkdf2(&self.ck, &[])
+    }
+}
+
+/// In-progress transport handshake. Drive it by alternating
+/// [`TransportHandshake::write_message`] and [`TransportHandshake::read_message`]
+/// (the initiator writes first) until [`TransportHandshake::is_finished`], then
+/// call [`TransportHandshake::into_channel`].
+pub struct TransportHandshake {
+    role: TransportRole,
+    config: TransportConfig,
+    symmetric: SymmetricState,
+    static_key: PrivateKey,
+    ephemeral: PrivateKey,
+    remote_ephemeral: Option<PublicKey>,
+    remote_static: Option<PublicKey>,
+    step: usize,
+}
+
+impl TransportHandshake {
+    pub fn new<R: Rng>(
+        rng: &mut R,
+        role: TransportRole,
+        private_keys: &ChannelPrivateKeys,
+        config: TransportConfig,
+    ) -> Self {
+        let mut symmetric = SymmetricState::new();
+        // Empty prologue, as in Noise.
+        symmetric.mix_hash(&[]);
+        TransportHandshake {
+            role,
+            config,
+            symmetric,
+            static_key: private_keys.my_channel_coin_private_key.clone(),
+            ephemeral: rng.random(),
+            remote_ephemeral: None,
+            remote_static: None,
+            step: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step >= 3
+    }
+
+    fn our_turn(&self) -> bool {
+        match self.role {
+            TransportRole::Initiator => self.step.is_multiple_of(2),
+            TransportRole::Responder => !self.step.is_multiple_of(2),
+        }
+    }
+
+    fn remote_ephemeral(&self) -> Result<&PublicKey, Error> {
+        self.remote_ephemeral
+            .as_ref()
+            .ok_or_else(|| transport_err("missing remote ephemeral key"))
+    }
+
+    fn remote_static(&self) -> Result<&PublicKey, Error> {
+        self.remote_static
+            .as_ref()
+            .ok_or_else(|| transport_err("missing remote static key"))
+    }
+
+    pub fn write_message(&mut self) -> Result<Vec<u8>, Error> {
+        if self.is_finished() || !self.our_turn() {
+            return Err(transport_err("handshake message written out of turn"));
+        }
+        let mut out = Vec::new();
+        match self.step {
+            0 => {
+                // -> e
+                let e_pub = private_to_public_key(&self.ephemeral).bytes();
+                self.symmetric.mix_hash(&e_pub);
+                out.extend_from_slice(&e_pub);
+            }
+            1 => {
+                // <- e, ee, s, es
+                let e_pub = private_to_public_key(&self.ephemeral).bytes();
+                self.symmetric.mix_hash(&e_pub);
+                out.extend_from_slice(&e_pub);
+                let ee = dh(&self.ephemeral, self.remote_ephemeral()?)?;
+                self.symmetric.mix_key(&ee);
+                let s_pub = private_to_public_key(&self.static_key).bytes();
+                out.extend(self.symmetric.encrypt_and_hash(&s_pub)?);
+                let es = dh(&self.static_key, self.remote_ephemeral()?)?;
+                self.symmetric.mix_key(&es);
+            }
+            _ => {
+                // -> s, se
+                let s_pub = private_to_public_key(&self.static_key).bytes();
+                out.extend(self.symmetric.encrypt_and_hash(&s_pub)?);
+                let se = dh(&self.static_key, self.remote_ephemeral()?)?;
+                self.symmetric.mix_key(&se);
+            }
+        }
+        out.extend(self.symmetric.encrypt_and_hash(&[])?);
+        self.step += 1;
+        Ok(out)
+    }
+
+    pub fn read_message(&mut self, message: &[u8]) -> Result<(), Error> {
+        if self.is_finished() || self.our_turn() {
+            return Err(transport_err("handshake message received out of turn"));
+        }
+        let mut rest = message;
+        match self.step {
+            0 => {
+                // -> e
+                if rest.len() < POINT_LEN {
+                    return Err(transport_err("short handshake message"));
+                }
+                let (e_bytes, tail) = rest.split_at(POINT_LEN);
+                self.remote_ephemeral = Some(public_key_from(e_bytes)?);
+                self.symmetric.mix_hash(e_bytes);
+                rest = tail;
+            }
+            1 => {
+                // <- e, ee, s, es
+                if rest.len() < POINT_LEN * 2 + TAG_LEN {
+                    return Err(transport_err("short handshake message"));
+                }
+                let (e_bytes, tail) = rest.split_at(POINT_LEN);
+                self.remote_ephemeral = Some(public_key_from(e_bytes)?);
+                self.symmetric.mix_hash(e_bytes);
+                let ee = dh(&self.ephemeral, self.remote_ephemeral()?)?;
+                self.symmetric.mix_key(&ee);
+                let (s_bytes, tail) = tail.split_at(POINT_LEN + TAG_LEN);
+                let s_plain = self.symmetric.decrypt_and_hash(s_bytes)?;
+                self.remote_static = Some(public_key_from(&s_plain)?);
+                let es = dh(&self.ephemeral, self.remote_static()?)?;
+                self.symmetric.mix_key(&es);
+                rest = tail;
+            }
+            _ => {
+                // -> s, se
+                if rest.len() < POINT_LEN + TAG_LEN {
+                    return Err(transport_err("short handshake message"));
+                }
+                let (s_bytes, tail) = rest.split_at(POINT_LEN + TAG_LEN);
+                let s_plain = self.symmetric.decrypt_and_hash(s_bytes)?;
+                self.remote_static = Some(public_key_from(&s_plain)?);
+                let se = dh(&self.ephemeral, self.remote_static()?)?;
+                self.symmetric.mix_key(&se);
+                rest = tail;
+            }
+        }
+        let payload = self.symmetric.decrypt_and_hash(rest)?;
+        if !payload.is_empty() {
+            return Err(transport_err("unexpected handshake payload"));
+        }
+        self.step += 1;
+        Ok(())
+    }
+
+    pub fn into_channel(self) -> Result<SecureChannel, Error> {
+        if !self.is_finished() {
+            return Err(transport_err("handshake not finished"));
+        }
+        let remote_static = self.remote_static()?.clone();
+        let (initiator_key, responder_key) = self.symmetric.split();
+        let (send_key, recv_key) = match self.role {
+            TransportRole::Initiator => (initiator_key, responder_key),
+            TransportRole::Responder => (responder_key, initiator_key),
+        };
+        Ok(SecureChannel {
+            config: self.config,
+            handshake_hash: self.symmetric.h,
+            remote_static,
+            send: SendState {
+                key: send_key,
+                epoch: 0,
+                next_counter: 0,
+            },
+            recv: RecvState {
+                key: recv_key,
+                epoch: 0,
+                highest_counter: None,
+            },
+            binding_verified: false,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SendState {
+    key: [u8; KEY_LEN],
+    epoch: u64,
+    next_counter: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct RecvState {
+    key: [u8; KEY_LEN],
+    epoch: u64,
+    highest_counter: Option<u64>,
+}
+
+/// An established transport. Serializable so hosts can persist it alongside
+/// the `GameSession` it carries traffic for.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SecureChannel {
+    config: TransportConfig,
+    handshake_hash: [u8; KEY_LEN],
+    remote_static: PublicKey,
+    send: SendState,
+    recv: RecvState,
+    binding_verified: bool,
+}
+
+impl std::fmt::Debug for SecureChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecureChannel")
+            .field("remote_static", &self.remote_static)
+            .field("send_counter", &self.send.next_counter)
+            .field("recv_counter", &self.recv.highest_counter)
+            .field("binding_verified", &self.binding_verified)
+            .finish()
+    }
+}
+
+impl SecureChannel {
+    /// The peer's proven static key. This is their channel coin public key.
+    pub fn remote_static_key(&self) -> &PublicKey {
+        &self.remote_static
+    }
+
+    /// Unique per-session value both sides agree on; hosts can display or log
+    /// it to detect a relay running two separate handshakes.
+    pub fn handshake_hash(&self) -> [u8; KEY_LEN] {
+        self.handshake_hash
+    }
+
+    pub fn binding_verified(&self) -> bool {
+        self.binding_verified
+    }
+
+    /// Check that the key the peer advertises in its handshake payload is the
+    /// one it proved ownership of in the transport handshake.
+    pub fn verify_channel_binding(&mut self, payload: &HandshakePayloadB) -> Result<(), Error> {
+        if payload.channel_public_key != self.remote_static {
+            return Err(transport_err(
+                "peer channel_public_key does not match the transport static key",
+            ));
+        }
+        self.binding_verified = true;
+        Ok(())
+    }
+
+    /// Encrypt one peer message into a frame: an 8-byte big-endian counter
+    /// followed by the ciphertext, authenticated with the counter as AD.
+    pub fn seal(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let counter = self.send.next_counter;
+        if counter == u64::MAX {
+            return Err(transport_err("send counter exhausted"));
+        }
+        let epoch = counter / self.config.rekey_interval.max(1);
+        while self.send.epoch < epoch {
+            self.send.key = rekey(&self.send.key);
+            self.send.epoch += 1;
+        }
+        let header = counter.to_be_bytes();
+        let mut frame = header.to_vec();
+        frame.extend(aead_encrypt(&self.send.key, counter, &header, plaintext)?);
+        self.send.next_counter += 1;
+        Ok(frame)
+    }
+
+    /// Authenticate and decrypt a frame. Frames must arrive with strictly
+    /// increasing counters; anything at or below the last accepted counter is
+    /// a replay. State only advances once the frame authenticates, so forged
+    /// frames cannot desynchronize the key schedule.
+    pub fn open(&mut self, frame: &[u8]) -> Result<Vec<u8>, Error> {
+        if frame.len() < FRAME_HEADER_LEN + TAG_LEN {
+            return Err(transport_err("short frame"));
+        }
+        let (header, ciphertext) = frame.split_at(FRAME_HEADER_LEN);
+        let mut counter_bytes = [0u8; FRAME_HEADER_LEN];
+        counter_bytes.copy_from_slice(header);
+        let counter = u64::from_be_bytes(counter_bytes);
+        if self.recv.highest_counter.is_some_and(|h| counter <= h) {
+            return Err(transport_err("replayed or reordered frame"));
+        }
+        let epoch = counter / self.config.rekey_interval.max(1);
+        if epoch < self.recv.epoch {
+            return Err(transport_err("frame from a retired key epoch"));
+        }
+        if epoch - self.recv.epoch > MAX_REKEY_SKIP {
+            return Err(transport_err("frame counter too far ahead"));
+        }
+        let mut key = self.recv.key;
+        for _ in self.recv.epoch..epoch {
+            key = rekey(&key);
+        }
+        let plaintext = aead_decrypt(&key, counter, header, ciphertext)?;
+
+        if !self.binding_verified {
+            let msg = PeerMessage::decode(&plaintext)
+                .map_err(|_| transport_err("first peer message is not a PeerMessage"))?;
+            match &msg {
+                PeerMessage::HandshakeA(payload) | PeerMessage::HandshakeB(payload) => {
+                    self.verify_channel_binding(payload)?;
+                }
+                _ => {
+                    return Err(transport_err(
+                        "peer message received before channel-key binding",
+                    ));
+                }
+            }
+        }
+
+        self.recv.key = key;
+        self.recv.epoch = epoch;
+        self.recv.highest_counter = Some(counter);
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::common::types::{Aggsig, Amount, PuzzleHash};
+
+    fn handshake_payload(keys: &ChannelPrivateKeys) -> HandshakePayloadB {
+        HandshakePayloadB {
+            channel_public_key: private_to_public_key(&keys.my_channel_coin_private_key),
+            unroll_public_key: private_to_public_key(&keys.my_unroll_coin_private_key),
+            reward_puzzle_hash: PuzzleHash::default(),
+            referee_pubkey: private_to_public_key(&keys.my_referee_private_key),
+            reward_payout_signature: Aggsig::default(),
+            channel_key_pop: Aggsig::default(),
+            unroll_key_pop: Aggsig::default(),
+            my_contribution: Amount::new(100),
+            their_contribution: Amount::new(100),
+            capabilities: Default::default(),
+        }
+    }
+
+    fn establish(
+        config: TransportConfig,
+    ) -> (
+        SecureChannel,
+        SecureChannel,
+        ChannelPrivateKeys,
+        ChannelPrivateKeys,
+    ) {
+        let mut rng = ChaCha8Rng::from_seed([3; 32]);
+        let alice_keys: ChannelPrivateKeys = rng.random();
+        let bob_keys: ChannelPrivateKeys = rng.random();
+        let mut alice = TransportHandshake::new(
+            &mut rng,
+            TransportRole::Initiator,
+            &alice_keys,
+            config.clone(),
+        );
+        let mut bob =
+            TransportHandshake::new(&mut rng, TransportRole::Responder, &bob_keys, config);
+        let m1 = alice.write_message().expect("m1");
+        bob.read_message(&m1).expect("read m1");
+        let m2 = bob.write_message().expect("m2");
+        alice.read_message(&m2).expect("read m2");
+        let m3 = alice.write_message().expect("m3");
+        bob.read_message(&m3).expect("read m3");
+        (
+            alice.into_channel().expect("alice channel"),
+            bob.into_channel().expect("bob channel"),
+            alice_keys,
+            bob_keys,
+        )
+    }
+
+    fn bound_pair(config: TransportConfig) -> (SecureChannel, SecureChannel) {
+        let (mut alice, mut bob, alice_keys, bob_keys) = establish(config);
+        let a_hello =
+            bencodex::to_vec(&PeerMessage::HandshakeA(handshake_payload(&alice_keys))).unwrap();
+        let b_hello =
+            bencodex::to_vec(&PeerMessage::HandshakeB(handshake_payload(&bob_keys))).unwrap();
+        let frame = alice.seal(&a_hello).unwrap();
+        assert_eq!(bob.open(&frame).unwrap(), a_hello);
+        let frame = bob.seal(&b_hello).unwrap();
+        assert_eq!(alice.open(&frame).unwrap(), b_hello);
+        (alice, bob)
+    }
+
+    #[test]
+    fn handshake_proves_channel_keys_and_agrees_on_hash() {
+        let (alice, bob, alice_keys, bob_keys) = establish(TransportConfig::default());
+        assert_eq!(alice.handshake_hash(), bob.handshake_hash());
+        assert_eq!(
+            alice.remote_static_key(),
+            &private_to_public_key(&bob_keys.my_channel_coin_private_key)
+        );
+        assert_eq!(
+            bob.remote_static_key(),
+            &private_to_public_key(&alice_keys.my_channel_coin_private_key)
+        );
+    }
+
+    #[test]
+    fn frames_round_trip_and_replays_are_rejected() {
+        let (mut alice, mut bob) = bound_pair(TransportConfig::default());
+        let frame = alice.seal(b"potato").unwrap();
+        assert_ne!(&frame[FRAME_HEADER_LEN..], b"potato");
+        assert_eq!(bob.open(&frame).unwrap(), b"potato");
+        assert!(bob.open(&frame).is_err(), "replay must be rejected");
+
+        let mut tampered = alice.seal(b"move").unwrap();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(bob.open(&tampered).is_err(), "tampered frame must fail");
+        // The forged frame did not advance state: a later genuine frame opens.
+        let frame = alice.seal(b"next").unwrap();
+        assert_eq!(bob.open(&frame).unwrap(), b"next");
+    }
+
+    #[test]
+    fn rekey_schedule_stays_in_step_across_dropped_frames() {
+        let (mut alice, mut bob) = bound_pair(TransportConfig { rekey_interval: 3 });
+        for i in 0..20u8 {
+            let frame = alice.seal(&[i]).unwrap();
+            if i % 4 == 1 {
+                // The relay dropped this one; the receiver still follows.
+                continue;
+            }
+            assert_eq!(bob.open(&frame).unwrap(), vec![i]);
+        }
+    }
+
+    #[test]
+    fn mismatched_channel_key_in_handshake_payload_is_rejected() {
+        let (mut alice, mut bob, _alice_keys, _bob_keys) = establish(TransportConfig::default());
+        let mut rng = ChaCha8Rng::from_seed([9; 32]);
+        let other_keys: ChannelPrivateKeys = rng.random();
+        let hello =
+            bencodex::to_vec(&PeerMessage::HandshakeA(handshake_payload(&other_keys))).unwrap();
+        let frame = alice.seal(&hello).unwrap();
+        assert!(bob.open(&frame).is_err());
+
+        let early = bencodex::to_vec(&PeerMessage::RequestPotato(())).unwrap();
+        let frame = alice.seal(&early).unwrap();
+        assert!(bob.open(&frame).is_err(), "no traffic before binding");
+    }
+}
@@ -0,0 +1,389 @@
+//! Optional input journal for a [`GameSession`], and a replayer for it.
+//!
+//! A session is a deterministic function of its config, its channel keys and
+//! the calls made on it.  A journaling session ([`GameSession::new_with_journal`])
+//! records each host call in order, together with what the call returned: the
+//! error, if any, and the encoded result (the drained events for
+//! `flush_and_collect`, the new ids for `propose_games`).  [`replay`] builds a
+//! fresh session from the journal header, re-applies every input and stops at
+//! the first step whose result differs from the recorded one.
+//!
+//! Calls the session makes on itself (for example `go_on_chain` after a peer
+//! protocol error) are not recorded; replaying the outer call repeats them.
+//! The test-only hooks and [`GameSession::push_event`] are not journaled, so a
+//! session driven through them will not replay.  Events still queued when a
+//! session is saved are not persisted, so a host that saves a journaling
+//! session should drain it first.
+//!
+//! The journal carries no private keys: the identity in its config has its
+//! private keys cleared, and only the public half of the referee key is kept.
+//! [`replay`] takes the keys from the caller as [`SessionKeys`].
+//! Recording stops once the encoded entries would pass the journal's byte
+//! limit, so a journal always holds a replayable prefix of the session and
+//! counts the calls it left out.
+
+use bencodex::UnknownFields;
+use serde::{Deserialize, Serialize};
+
+use crate::channel_state::types::{ChannelPrivateKeys, ReadableMove};
+use crate::common::standard_coin::{private_to_public_key, ChiaIdentity};
+#[cfg(feature = "hot-reload")]
+use crate::common::types::GameType;
+use crate::common::types::{
+    AllocEncoder, Amount, CoinString, Error, GameID, Hash, IntoErr, PrivateKey, Program, PublicKey,
+    SpendBundle,
+};
+use crate::game_session::{
+    decode_saved_session, CoinObservation, DrainResult, GameSession, GameSessionConfig,
+};
+use crate::payout::PayoutTerms;
+use crate::session_match::MatchTerms;
+use crate::session_phases::effects::{GameSessionEvent, TimeoutClaimSemantic};
+use crate::session_phases::proposal::GameProposal;
+#[cfg(feature = "hot-reload")]
+use crate::session_phases::types::GameFactory;
+
+/// One host call on a [`GameSession`], with the arguments it was given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SessionInput {
+    SetFundingCoin(CoinString),
+    StartHandshake,
+    ProvideLauncherCoin(CoinString),
+    ProvideCoinSpendBundle(SpendBundle),
+    WalletCallbackFailed(String),
+    Abandon,
+    CompleteOutboundTerminalHandoff,
+    FlushAndCollect,
+    TimeoutClaimSubmitted(TimeoutClaimSemantic),
+    TimeoutClaimRearmed(TimeoutClaimSemantic),
+    Cheat(GameID, Amount),
+    #[cfg(feature = "hot-reload")]
+    ReplaceGameFactory(GameType, GameFactory),
+    ProposeGames(Vec<GameProposal>),
+    AcceptProposal(GameID),
+    AcceptProposalWithPayout(GameID, PayoutTerms),
+    CancelProposal(GameID),
+    MakeMove(GameID, ReadableMove, Hash),
+    AcceptProposalAndMove(GameID, ReadableMove, Hash),
+    AcceptSettlement(GameID),
+    StartMatch(MatchTerms),
+    AcceptMatch(GameID, MatchTerms),
+    StopMatch,
+    AuthorizeSpectator(GameID, PublicKey, u32),
+    ShutDown,
+    NewBlock(u64, Vec<CoinObservation>),
+    NewBlockHeightOnly(u64),
+    DeliverMessage(Vec<u8>),
+    GoOnChain(bool),
+    ReportPuzzleAndSolution(CoinString, Option<(Program, Program)>),
+}
+
+/// The private keys a journal leaves out: the wallet identity's and the
+/// channel's.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionKeys {
+    pub identity: PrivateKey,
+    pub channel: ChannelPrivateKeys,
+}
+
+/// A recorded input and the result the session produced for it.
+#[derive(Debug, Clone, bencodex::Schema)]
+pub struct JournalEntry {
+    pub input: SessionInput,
+    /// The call's error rendered with `{:?}`, or `None` if it succeeded.
+    pub error: Option<String>,
+    /// The call's successful return value, bencodex-encoded.  Empty for calls
+    /// that return nothing.
+    pub output: Vec<u8>,
+    #[bencodex(unknown)]
+    pub unknown: UnknownFields,
+}
+
+/// Default cap on the encoded size of a journal's entries.
+pub const DEFAULT_JOURNAL_LIMIT: usize = 16 << 20;
+
+/// Everything but the private keys needed to rebuild a session and re-run it
+/// call by call.
+#[derive(Clone, bencodex::Schema)]
+pub struct SessionJournal {
+    /// The session's config, with the identity's private keys cleared.
+    pub config: GameSessionConfig,
+    /// Public half of the referee key the session was created with.
+    pub referee_public_key: PublicKey,
+    pub entries: Vec<JournalEntry>,
+    /// Maximum encoded size of `entries`.
+    pub limit: usize,
+    /// Encoded size of `entries` so far.
+    pub recorded: usize,
+    /// Host calls made after the limit was reached, which were not recorded.
+    pub dropped: u64,
+    #[bencodex(unknown)]
+    pub unknown: UnknownFields,
+}
+
+/// Return values worth checking on replay.
+pub(crate) trait JournalOutput {
+    fn journal_output(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+impl JournalOutput for () {}
+
+impl JournalOutput for bool {
+    fn journal_output(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+}
+
+impl JournalOutput for GameID {
+    fn journal_output(&self) -> Vec<u8> {
+        bencodex::to_vec(self).unwrap_or_default()
+    }
+}
+
+impl JournalOutput for Vec<GameID> {
+    fn journal_output(&self) -> Vec<u8> {
+        bencodex::to_vec(self).unwrap_or_default()
+    }
+}
+
+impl JournalOutput for DrainResult {
+    fn journal_output(&self) -> Vec<u8> {
+        bencodex::to_vec(&(&self.events, &self.resync)).unwrap_or_default()
+    }
+}
+
+impl SessionJournal {
+    pub fn new(mut config: GameSessionConfig, referee_public_key: PublicKey, limit: usize) -> Self {
+        config.identity.private_key = PrivateKey::default();
+        config.identity.synthetic_private_key = PrivateKey::default();
+        SessionJournal {
+            config,
+            referee_public_key,
+            entries: Vec::new(),
+            limit,
+            recorded: 0,
+            dropped: 0,
+            unknown: UnknownFields::default(),
+        }
+    }
+
+    pub(crate) fn record<T: JournalOutput>(
+        &mut self,
+        input: SessionInput,
+        result: &Result<T, Error>,
+    ) {
+        if self.dropped > 0 {
+            self.dropped += 1;
+            return;
+        }
+        let (error, output) = outcome(result);
+        let entry = JournalEntry {
+            input,
+            error,
+            output,
+            unknown: UnknownFields::default(),
+        };
+        let size = bencodex::to_vec(&entry).map_or(usize::MAX, |bytes| bytes.len());
+        if size > self.limit.saturating_sub(self.recorded) {
+            self.dropped = 1;
+            return;
+        }
+        self.recorded += size;
+        self.entries.push(entry);
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        bencodex::to_vec(self).into_gen()
+    }
+
+    /// Decode a journal under the saved-session limits.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        decode_saved_session(bytes)
+    }
+}
+
+fn outcome<T: JournalOutput>(result: &Result<T, Error>) -> (Option<String>, Vec<u8>) {
+    match result {
+        Ok(value) => (None, value.journal_output()),
+        Err(e) => (Some(format!("{e:?}")), Vec::new()),
+    }
+}
+
+/// What one replayed step did.
+pub struct ReplayStep<'a> {
+    pub index: usize,
+    pub input: &'a SessionInput,
+    pub error: Option<String>,
+    /// Events drained by this step; only `FlushAndCollect` drains.
+    pub events: Vec<GameSessionEvent>,
+    pub resync: Option<(usize, bool)>,
+    /// Why this step's result differs from the journal, if it does.
+    pub divergence: Option<String>,
+    pub session: &'a GameSession,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub index: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub steps: usize,
+    pub divergence: Option<Divergence>,
+}
+
+fn apply(
+    session: &mut GameSession,
+    allocator: &mut AllocEncoder,
+    input: &SessionInput,
+) -> (Option<String>, Vec<u8>, Option<DrainResult>) {
+    match input {
+        SessionInput::SetFundingCoin(coin) => {
+            outcome_of(session.set_funding_coin(allocator, coin.clone()))
+        }
+        SessionInput::StartHandshake => outcome_of(session.start_handshake(allocator)),
+        SessionInput::ProvideLauncherCoin(coin) => {
+            outcome_of(session.provide_launcher_coin(allocator, coin.clone()))
+        }
+        SessionInput::ProvideCoinSpendBundle(bundle) => {
+            outcome_of(session.provide_coin_spend_bundle(allocator, bundle.clone()))
+        }
+        SessionInput::WalletCallbackFailed(reason) => {
+            outcome_of(session.wallet_callback_failed(allocator, reason.clone()))
+        }
+        SessionInput::Abandon => outcome_of(session.abandon()),
+        SessionInput::CompleteOutboundTerminalHandoff => {
+            outcome_of(session.complete_outbound_terminal_handoff())
+        }
+        SessionInput::FlushAndCollect => match session.flush_and_collect(allocator) {
+            Ok(drain) => (None, drain.journal_output(), Some(drain)),
+            Err(e) => (Some(format!("{e:?}")), Vec::new(), None),
+        },
+        SessionInput::TimeoutClaimSubmitted(semantic) => {
+            outcome_of(session.timeout_claim_submitted(*semantic))
+        }
+        SessionInput::TimeoutClaimRearmed(semantic) => {
+            outcome_of(session.timeout_claim_rearmed(*semantic))
+        }
+        SessionInput::Cheat(id, mover_share) => {
+            outcome_of(session.cheat(allocator, id, mover_share.clone()))
+        }
+        #[cfg(feature = "hot-reload")]
+        SessionInput::ReplaceGameFactory(game_type, factory) => {
+            outcome_of(Ok(session.replace_game_factory(game_type, factory.clone())))
+        }
+        SessionInput::ProposeGames(games) => outcome_of(session.propose_games(allocator, games)),
+        SessionInput::AcceptProposal(id) => outcome_of(session.accept_proposal(allocator, id)),
+        SessionInput::AcceptProposalWithPayout(id, payout) => {
+            outcome_of(session.accept_proposal_with_payout(allocator, id, payout))
+        }
+        SessionInput::CancelProposal(id) => outcome_of(session.cancel_proposal(allocator, id)),
+        SessionInput::MakeMove(id, readable, entropy) => {
+            outcome_of(session.make_move(allocator, id, readable.clone(), entropy.clone()))
+        }
+        SessionInput::AcceptProposalAndMove(id, readable, entropy) => outcome_of(
+            session.accept_proposal_and_move(allocator, id, readable.clone(), entropy.clone()),
+        ),
+        SessionInput::AcceptSettlement(id) => outcome_of(session.accept_settlement(allocator, id)),
+        SessionInput::StartMatch(terms) => {
+            outcome_of(session.start_match(allocator, terms.clone()))
+        }
+        SessionInput::AcceptMatch(id, terms) => {
+            outcome_of(session.accept_match(allocator, id, terms.clone()))
+        }
+        SessionInput::StopMatch => outcome_of(session.stop_match()),
+        SessionInput::AuthorizeSpectator(id, observer, delay_moves) => {
+            outcome_of(session.authorize_spectator(id, observer.clone(), *delay_moves))
+        }
+        SessionInput::ShutDown => outcome_of(session.shut_down(allocator)),
+        SessionInput::NewBlock(height, observations) => {
+            outcome_of(session.new_block(allocator, *height, observations))
+        }
+        SessionInput::NewBlockHeightOnly(height) => {
+            outcome_of(session.new_block_height_only(allocator, *height))
+        }
+        SessionInput::DeliverMessage(message) => outcome_of(session.deliver_message(message)),
+        SessionInput::GoOnChain(got_error) => {
+            outcome_of(session.go_on_chain(allocator, *got_error))
+        }
+        SessionInput::ReportPuzzleAndSolution(coin, puzzle_and_solution) => {
+            outcome_of(session.report_puzzle_and_solution(
+                allocator,
+                coin,
+                puzzle_and_solution.as_ref().map(|(p, s)| (p, s)),
+            ))
+        }
+    }
+}
+
+fn outcome_of<T: JournalOutput>(
+    result: Result<T, Error>,
+) -> (Option<String>, Vec<u8>, Option<DrainResult>) {
+    let (error, output) = outcome(&result);
+    (error, output, None)
+}
+
+fn divergence(entry: &JournalEntry, error: &Option<String>, output: &[u8]) -> Option<String> {
+    if *error != entry.error {
+        return Some(format!(
+            "recorded error {:?}, replay gave {:?}",
+            entry.error, error
+        ));
+    }
+    if output != entry.output.as_slice() {
+        return Some(format!(
+            "recorded result differs ({} bytes recorded, {} bytes replayed)",
+            entry.output.len(),
+            output.len()
+        ));
+    }
+    None
+}
+
+/// Re-run `journal` on a fresh session built with `keys`, calling `on_step`
+/// after every input.  Replay stops after the first step whose result differs
+/// from the journal.  Fails if the keys are not the ones the journal was
+/// recorded with.
+pub fn replay(
+    allocator: &mut AllocEncoder,
+    journal: &SessionJournal,
+    keys: &SessionKeys,
+    mut on_step: impl FnMut(&ReplayStep<'_>),
+) -> Result<ReplayReport, Error> {
+    let identity = ChiaIdentity::new(allocator, keys.identity.clone())?;
+    if identity.public_key != journal.config.identity.public_key
+        || private_to_public_key(&keys.channel.my_referee_private_key) != journal.referee_public_key
+    {
+        return Err(Error::StrErr(
+            "replay: private keys do not match the journal".to_string(),
+        ));
+    }
+    let mut config = journal.config.clone();
+    config.identity = identity;
+    let mut session = GameSession::new_with_keys(config, keys.channel.clone());
+    let mut report = ReplayReport::default();
+    for (index, entry) in journal.entries.iter().enumerate() {
+        let (error, output, drain) = apply(&mut session, allocator, &entry.input);
+        let reason = divergence(entry, &error, &output);
+        let drain = drain.unwrap_or_default();
+        report.steps += 1;
+        on_step(&ReplayStep {
+            index,
+            input: &entry.input,
+            error,
+            events: drain.events.into_iter().collect(),
+            resync: drain.resync,
+            divergence: reason.clone(),
+            session: &session,
+        });
+        if let Some(reason) = reason {
+            report.divergence = Some(Divergence { index, reason });
+            break;
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,387 @@
+//! Matches: a run of hands of one game against the same peer over one channel.
+//!
+//! Both sides register the same [`MatchTerms`] locally; the terms never go
+//! over the wire.  The side that starts the match proposes the first hand and
+//! the peer accepts it with [`GameSession::accept_match`].  After each
+//! `GameSettled` the side whose turn it is proposes the next hand, and the
+//! other side accepts it automatically if it is exactly the agreed proposal
+//! (game type, timeout, parameters, fee and payout) with the agreed stakes.
+//! Any other proposal is left to the UI and does not affect the match.  With
+//! `alternate_proposer` the two sides take turns proposing, which for games
+//! whose parameters say "the proposer goes first" (calpoker's
+//! `sender_goes_first`) alternates who opens each hand.
+//!
+//! A stop ("stop after this hand", or our stop-loss) takes effect at the next
+//! hand boundary.  Once its open hands are done each side sends
+//! `PeerMessage::StopMatch`, and a peer that receives one stops too, so both
+//! matches end when both have sent it.  A peer proposal that crossed the stop
+//! is declined.  With a peer that doesn't advertise `FEATURE_MATCH_STOP` the
+//! stop can't be sent: if the peer proposes next we decline its proposal, and
+//! if we propose next we simply don't.
+//!
+//! [`GameSession::accept_match`]: crate::game_session::GameSession::accept_match
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::types::{Amount, Error, GameID};
+use crate::session_phases::effects::{CancelReason, GameNotification};
+use crate::session_phases::proposal::GameProposal;
+
+/// What both players agreed to play.  Each side registers the same terms.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchTerms {
+    /// Proposed unchanged for every hand.
+    pub proposal: GameProposal,
+    /// End the match after this many hands.
+    #[serde(default)]
+    pub hands: Option<u32>,
+    /// Stop after the hand that takes our net loss over the match to this
+    /// amount or more.  Local to each side.
+    #[serde(default)]
+    pub stop_loss: Option<Amount>,
+    /// Take turns proposing instead of the starter proposing every hand.
+    #[serde(default)]
+    pub alternate_proposer: bool,
+}
+
+/// Running totals over the hands settled so far, from our side.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchScore {
+    pub hands_played: u32,
+    pub hands_won: u32,
+    pub hands_lost: u32,
+    /// Sum of what we gained over our stake on the hands we came out ahead.
+    pub won: Amount,
+    /// Sum of what we lost of our stake on the hands we came out behind.
+    pub lost: Amount,
+}
+
+impl MatchScore {
+    fn record(&mut self, stake: &Amount, our_share: &Amount) {
+        self.hands_played += 1;
+        if our_share > stake {
+            self.hands_won += 1;
+            self.won += Amount::new(our_share.to_u64() - stake.to_u64());
+        } else if our_share < stake {
+            self.hands_lost += 1;
+            self.lost += Amount::new(stake.to_u64() - our_share.to_u64());
+        }
+    }
+
+    /// How far behind we are over the whole match, or zero if we're not.
+    pub fn net_loss(&self) -> Amount {
+        self.lost.checked_sub(&self.won).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchEndReason {
+    /// The agreed number of hands was played.
+    Completed,
+    /// Our stop-loss was reached.
+    StopLoss,
+    /// We asked to stop after the current hand.
+    StoppedByUs,
+    /// The peer asked to stop, or declined or cancelled a match hand.
+    StoppedByPeer,
+    /// A hand could not be played off-chain: a balance shortfall, a bounced
+    /// proposal, or the channel leaving the off-chain phase.
+    Interrupted,
+}
+
+/// What the session should do for the match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MatchStep {
+    /// Propose the next hand and pass its id to [`MatchState::proposed`].
+    Propose,
+    Accept(GameID),
+    Decline(GameID),
+    /// Tell the peer our match is stopping.
+    SendStop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OpenHand {
+    hand: u32,
+    we_proposed: bool,
+    /// Our contribution, once known.
+    stake: Option<Amount>,
+}
+
+/// One side's view of a running match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct MatchState {
+    terms: MatchTerms,
+    we_started: bool,
+    /// Hands proposed so far, counting the open ones.
+    hands_begun: u32,
+    open: BTreeMap<GameID, OpenHand>,
+    /// The proposer's and the acceptor's contribution to one hand, learned
+    /// from the first hand and used to check the peer's later proposals.
+    stakes: Option<(Amount, Amount)>,
+    score: MatchScore,
+    stop: Option<MatchEndReason>,
+    /// Whether we've sent the peer `StopMatch`, and whether it sent us one.
+    #[serde(default)]
+    stop_sent: bool,
+    #[serde(default)]
+    stop_received: bool,
+    ended: Option<MatchEndReason>,
+}
+
+impl MatchState {
+    fn new(terms: MatchTerms, we_started: bool) -> Result<Self, Error> {
+        if terms.hands == Some(0) {
+            return Err(Error::StrErr("a match needs at least one hand".to_string()));
+        }
+        Ok(MatchState {
+            terms,
+            we_started,
+            hands_begun: 0,
+            open: BTreeMap::new(),
+            stakes: None,
+            score: MatchScore::default(),
+            stop: None,
+            stop_sent: false,
+            stop_received: false,
+            ended: None,
+        })
+    }
+
+    /// A match we start; the caller proposes the first hand.
+    pub(crate) fn start(terms: MatchTerms) -> Result<Self, Error> {
+        MatchState::new(terms, true)
+    }
+
+    /// A match the peer started by proposing `first_hand`, which the caller
+    /// accepts.
+    pub(crate) fn join(
+        terms: MatchTerms,
+        first_hand: GameID,
+        stake: Option<Amount>,
+    ) -> Result<Self, Error> {
+        let mut state = MatchState::new(terms, false)?;
+        state.begin_hand(first_hand, false, stake);
+        Ok(state)
+    }
+
+    pub(crate) fn terms(&self) -> &MatchTerms {
+        &self.terms
+    }
+
+    pub(crate) fn score(&self) -> &MatchScore {
+        &self.score
+    }
+
+    pub(crate) fn ended(&self) -> Option<MatchEndReason> {
+        self.ended
+    }
+
+    fn begin_hand(&mut self, id: GameID, we_proposed: bool, stake: Option<Amount>) {
+        self.hands_begun += 1;
+        self.open.insert(
+            id,
+            OpenHand {
+                hand: self.hands_begun,
+                we_proposed,
+                stake,
+            },
+        );
+    }
+
+    /// Record the id of a hand we proposed for [`MatchStep::Propose`].
+    pub(crate) fn proposed(&mut self, id: GameID) {
+        self.begin_hand(id, true, None);
+    }
+
+    fn we_propose(&self, hand: u32) -> bool {
+        let starter_proposes = !self.terms.alternate_proposer || hand % 2 == 1;
+        starter_proposes == self.we_started
+    }
+
+    fn hands_done(&self) -> bool {
+        self.terms
+            .hands
+            .is_some_and(|hands| self.hands_begun >= hands)
+    }
+
+    /// Ask to stop once the open hands settle.
+    pub(crate) fn request_stop(&mut self) {
+        self.stop.get_or_insert(MatchEndReason::StoppedByUs);
+    }
+
+    /// The peer sent `StopMatch`.
+    pub(crate) fn peer_stopped(&mut self) {
+        self.stop_received = true;
+        self.stop.get_or_insert(MatchEndReason::StoppedByPeer);
+    }
+
+    /// Fill in stakes we couldn't see earlier.  `lookup` gives our and the
+    /// peer's contribution to a proposed or live game.
+    pub(crate) fn learn_stakes(&mut self, lookup: &dyn Fn(&GameID) -> Option<(Amount, Amount)>) {
+        for (id, hand) in self.open.iter_mut() {
+            let Some((mine, theirs)) = lookup(id) else {
+                continue;
+            };
+            if self.stakes.is_none() {
+                self.stakes = Some(if hand.we_proposed {
+                    (mine.clone(), theirs)
+                } else {
+                    (theirs, mine.clone())
+                });
+            }
+            hand.stake.get_or_insert(mine);
+        }
+    }
+
+    fn end(&mut self, reason: MatchEndReason) {
+        self.ended.get_or_insert(reason);
+    }
+
+    /// End the match from outside, e.g. when the channel goes on chain.  A
+    /// match whose last hand already settled still counts as completed.
+    pub(crate) fn interrupt(&mut self) {
+        if self.open.is_empty() && self.hands_done() {
+            self.end(MatchEndReason::Completed);
+        } else {
+            self.end(MatchEndReason::Interrupted);
+        }
+    }
+
+    /// Check a peer proposal against the terms.
+    fn is_next_hand(
+        &self,
+        proposal: &GameProposal,
+        my_contribution: &Amount,
+        their_contribution: &Amount,
+    ) -> bool {
+        *proposal == self.terms.proposal
+            && self.stakes.as_ref().is_none_or(|(proposer, acceptor)| {
+                their_contribution == proposer && my_contribution == acceptor
+            })
+    }
+
+    /// Update the match from one notification.  Returns the steps it calls
+    /// for and any match notifications to report.
+    pub(crate) fn observe(
+        &mut self,
+        notification: &GameNotification,
+    ) -> (Vec<MatchStep>, Vec<GameNotification>) {
+        let mut steps = Vec::new();
+        let mut notes = Vec::new();
+        if self.ended.is_some() {
+            return (steps, notes);
+        }
+        match notification {
+            GameNotification::ProposalMade {
+                id,
+                my_contribution,
+                their_contribution,
+                proposal,
+                ..
+            } => {
+                let next = self.hands_begun + 1;
+                if self.open.contains_key(id)
+                    || self.hands_done()
+                    || self.we_propose(next)
+                    || !self.is_next_hand(proposal, my_contribution, their_contribution)
+                {
+                    return (steps, notes);
+                }
+                if let Some(reason) = self.stop {
+                    steps.push(MatchStep::Decline(*id));
+                    self.end(reason);
+                } else {
+                    self.begin_hand(*id, false, Some(my_contribution.clone()));
+                    steps.push(MatchStep::Accept(*id));
+                }
+            }
+            GameNotification::ProposalAccepted { id, .. } => {
+                if let Some(hand) = self.open.get(id) {
+                    notes.push(GameNotification::MatchHandStarted {
+                        id: *id,
+                        hand: hand.hand,
+                        we_proposed: hand.we_proposed,
+                    });
+                }
+            }
+            GameNotification::ProposalCancelled { id, reason } => {
+                if self.open.remove(id).is_some() {
+                    let reason = self.stop.unwrap_or(match reason {
+                        CancelReason::CancelledByPeer => MatchEndReason::StoppedByPeer,
+                        CancelReason::CancelledByUs => MatchEndReason::StoppedByUs,
+                        _ => MatchEndReason::Interrupted,
+                    });
+                    self.end(reason);
+                }
+            }
+            GameNotification::InsufficientBalance { id, .. } => {
+                if self.open.remove(id).is_some() {
+                    self.end(MatchEndReason::Interrupted);
+                }
+            }
+            GameNotification::GameSettled { id, our_share, .. } => {
+                if let Some(hand) = self.open.remove(id) {
+                    let stake = hand
+                        .stake
+                        .or_else(|| {
+                            self.stakes.as_ref().map(|(proposer, acceptor)| {
+                                if hand.we_proposed {
+                                    proposer.clone()
+                                } else {
+                                    acceptor.clone()
+                                }
+                            })
+                        })
+                        .unwrap_or_default();
+                    self.score.record(&stake, our_share);
+                    notes.push(GameNotification::MatchHandSettled {
+                        id: *id,
+                        hand: hand.hand,
+                        our_share: our_share.clone(),
+                        score: self.score.clone(),
+                    });
+                    if let Some(limit) = &self.terms.stop_loss {
+                        if self.score.net_loss() >= *limit {
+                            self.stop.get_or_insert(MatchEndReason::StopLoss);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        (steps, notes)
+    }
+
+    /// What to do once the notifications are in: propose the next hand if
+    /// it's ours, tell the peer we're stopping, or finish the match.
+    /// `can_send_stop` says whether the peer understands `StopMatch`.
+    pub(crate) fn next_step(&mut self, can_send_stop: bool) -> Option<MatchStep> {
+        if self.ended.is_some() || !self.open.is_empty() {
+            return None;
+        }
+        if self.hands_done() {
+            self.end(MatchEndReason::Completed);
+            return None;
+        }
+        let we_propose = self.we_propose(self.hands_begun + 1);
+        if let Some(reason) = self.stop {
+            if can_send_stop {
+                let send = !std::mem::replace(&mut self.stop_sent, true);
+                if self.stop_received {
+                    self.end(reason);
+                }
+                return send.then_some(MatchStep::SendStop);
+            }
+            // Without the message a stop waits to decline the peer's next
+            // hand, or ends now if the next hand is ours.
+            if we_propose {
+                self.end(reason);
+            }
+            return None;
+        }
+        we_propose.then_some(MatchStep::Propose)
+    }
+}
@@ -1,16 +1,21 @@
 use std::collections::VecDeque;
 
+use crate::attestation::{GameResult, GameResultAttestation};
 use crate::channel_state::types::ReadableMove;
 use crate::channel_state::types::StateUpdateSignatures;
 use crate::common::types::{
-    Aggsig, Amount, CoinID, CoinSpend, CoinString, GameID, GameType, Hash, ProgramRef, PuzzleHash,
-    SpendBundle, Timeout,
+    Aggsig, Amount, CoinID, CoinSpend, CoinString, GameID, GameType, Hash, ProgramRef, PublicKey,
+    PuzzleHash, SpendBundle, Timeout,
 };
+use crate::operator_fee::GameFee;
+use crate::session_match::{MatchEndReason, MatchScore};
 use crate::session_phases::handshake::{
     CoinSpendRequest, HandshakePayloadB, HandshakePayloadC, HandshakePayloadD, HandshakePayloadE,
-    HandshakePayloadF,
+    HandshakePayloadF, NegotiatedCapabilities,
 };
+use crate::session_phases::proposal::GameProposal;
 use crate::session_phases::types::{BatchAction, PeerMessage};
+use crate::spectator::{SpectatorEvent, SpectatorMove};
 
 pub fn format_coin(coin: &CoinString) -> String {
     match coin.to_parts() {
@@ -203,6 +208,9 @@ pub enum GameNotification {
         outcome: SettlementOutcome,
         our_share: Amount,
         coin_id: Option<CoinString>,
+        /// The operator fee the game paid, if it had one.
+        #[serde(default)]
+        operator_fee: Amount,
     },
 
     ProposalMade {
@@ -215,6 +223,11 @@ pub enum GameNotification {
         initial_validation_program_hash: Hash,
         initial_state: ProgramRef,
         game_type: GameType,
+        /// The operator fee over the whole group, if the proposal has one.
+        #[serde(default)]
+        operator_fee: Option<GameFee>,
+        /// The proposal exactly as the peer sent it.
+        proposal: Box<GameProposal>,
     },
     ProposalAccepted {
         id: GameID,
@@ -241,6 +254,26 @@ pub enum GameNotification {
         id: GameID,
         tag: String,
         message: String,
+        /// Chialisp call trace, present when CLVM tracing is enabled.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        trace: Option<String>,
+    },
+    /// A hand of the running match went live; `hand` counts from 1.
+    MatchHandStarted {
+        id: GameID,
+        hand: u32,
+        we_proposed: bool,
+    },
+    /// A hand of the running match settled, with the score after it.
+    MatchHandSettled {
+        id: GameID,
+        hand: u32,
+        our_share: Amount,
+        score: MatchScore,
+    },
+    MatchEnded {
+        reason: MatchEndReason,
+        score: MatchScore,
     },
     ChannelStatus {
         state: ChannelStatus,
@@ -298,6 +331,8 @@ impl GameNotification {
         }
     }
 
+    /// A `GameSettled` without its operator fee, which `GameSession` fills
+    /// in from the fee it recorded when the game started.
     pub fn game_settled(
         id: GameID,
         outcome: SettlementOutcome,
@@ -309,6 +344,7 @@ impl GameNotification {
             outcome,
             our_share,
             coin_id,
+            operator_fee: Amount::default(),
         }
     }
 }
@@ -339,6 +375,13 @@ pub enum GameSessionEvent {
         /// Optional UI context emitted only when the manager submits `spend`.
         semantic: Option<TimeoutClaimSemantic>,
     },
+    /// A game's result signed by both players, for the host to publish.
+    GameResultAttested(Box<GameResultAttestation>),
+    /// An event for an authorized spectator, to forward to `observer`.
+    SpectatorFeed {
+        observer: PublicKey,
+        event: SpectatorEvent,
+    },
 }
 
 /// Collect GameSessionEvents in insertion order.
@@ -359,6 +402,9 @@ pub enum Effect {
 
     NeedLauncherCoinId,
     NeedCoinSpend(CoinSpendRequest),
+    /// What HandshakeA/B settled on.  `GameSession` keeps it to hold outbound
+    /// messages to the agreed size and to gate optional messages.
+    CapabilitiesNegotiated(NegotiatedCapabilities),
     PeerBatch {
         actions: Vec<BatchAction>,
         signatures: StateUpdateSignatures,
@@ -376,6 +422,21 @@ pub enum Effect {
     GoOnChainAfterPeerError,
     PeerRequestPotato,
     PeerGameMessage(GameID, Vec<u8>),
+    /// Our record of a game accepted off-chain, to sign once it settles.
+    GameResult(Box<GameResult>),
+    PeerGameResultSignature(GameID, Aggsig),
+    /// The peer's signature on a game's result, for `GameSession` to check.
+    ReceivedGameResultSignature(GameID, Aggsig),
+    PeerStopMatch,
+    /// The peer's match stops at the next hand boundary.  `GameSession`
+    /// passes it to the running match, if any.
+    ReceivedStopMatch,
+    /// An off-chain move now signed into the channel state.  `GameSession`
+    /// feeds it to the game's spectators, if any.
+    SpectatorMove {
+        record: Box<SpectatorMove>,
+        ours: bool,
+    },
 
     // WalletSpendInterface
     /// Submit a spend bundle.  The optional `u64` is the absolute expiry height
@@ -440,6 +501,7 @@ pub fn apply_effects(
             Effect::NeedCoinSpend(_) => {
                 // Handled by the cradle/WASM layer, not by the trait system.
             }
+            Effect::CapabilitiesNegotiated(_) => {}
             Effect::PeerBatch {
                 actions,
                 signatures,
@@ -471,6 +533,16 @@ pub fn apply_effects(
             Effect::PeerGameMessage(id, bytes) => {
                 system.send_message(&PeerMessage::Message(id, bytes))?;
             }
+            Effect::PeerGameResultSignature(id, signature) => {
+                system.send_message(&PeerMessage::GameResultSignature(id, signature))?;
+            }
+            Effect::PeerStopMatch => {
+                system.send_message(&PeerMessage::StopMatch(()))?;
+            }
+            Effect::GameResult(_)
+            | Effect::ReceivedGameResultSignature(..)
+            | Effect::ReceivedStopMatch => {}
+            Effect::SpectatorMove { .. } => {}
             Effect::SpendTransaction(bundle, expiry) => {
                 system.spend_transaction_and_add_fee(&bundle, expiry)?;
             }
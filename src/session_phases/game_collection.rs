@@ -2,8 +2,9 @@ use clvm_traits::{clvm_curried_args, ToClvm};
 use clvm_utils::CurriedProgram;
 use std::collections::BTreeMap;
 
-use crate::common::load_clvm::{read_hex_puzzle, read_krunk_dict_dat};
-use crate::common::types::{AllocEncoder, GameType, Program};
+use crate::common::load_clvm::read_krunk_dict_dat;
+use crate::common::puzzle_registry;
+use crate::common::types::{AllocEncoder, Error, GameType, IntoErr, Program, Puzzle};
 use crate::session_phases::types::GameFactory;
 
 /// Register all production games (calpoker, spacepoker, krunk).
@@ -17,11 +18,9 @@ pub fn game_collection(allocator: &mut AllocEncoder) -> BTreeMap<GameType, GameF
 pub fn register_all(allocator: &mut AllocEncoder) -> BTreeMap<GameType, GameFactory> {
     let mut game_type_map = BTreeMap::new();
 
-    let calpoker_factory = read_hex_puzzle(
-        allocator,
-        "clsp/games/calpoker/calpoker_include_calpoker_factory.hex",
-    )
-    .expect("should load");
+    let calpoker_factory = puzzle_registry::CALPOKER_GENERATE_CALPOKER_FACTORY
+        .to_puzzle(allocator)
+        .expect("should load");
     game_type_map.insert(
         GameType(b"calpoker".to_vec()),
         GameFactory {
@@ -29,11 +28,9 @@ pub fn register_all(allocator: &mut AllocEncoder) -> BTreeMap<GameType, GameFact
         },
     );
 
-    let spacepoker_factory = read_hex_puzzle(
-        allocator,
-        "clsp/games/spacepoker/spacepoker_include_spacepoker_factory.hex",
-    )
-    .expect("should load");
+    let spacepoker_factory = puzzle_registry::SPACEPOKER_GENERATE_SPACEPOKER_FACTORY
+        .to_puzzle(allocator)
+        .expect("should load");
     game_type_map.insert(
         GameType(b"spacepoker".to_vec()),
         GameFactory {
@@ -41,32 +38,19 @@ pub fn register_all(allocator: &mut AllocEncoder) -> BTreeMap<GameType, GameFact
         },
     );
 
-    let krunk_factory_raw = read_hex_puzzle(
-        allocator,
-        "clsp/games/krunk/krunk_include_krunk_factory.hex",
-    )
-    .expect("should load krunk factory");
-    let (dict_pubkey, dict_tree) =
-        read_krunk_dict_dat(allocator, "clsp/games/krunk/krunk_signed_dict_tree.dat")
-            .expect("should load krunk dict dat");
-    let krunk_factory_node = CurriedProgram {
-        program: krunk_factory_raw,
-        args: clvm_curried_args!(dict_pubkey, dict_tree),
-    }
-    .to_clvm(allocator)
-    .expect("curry krunk factory");
-    let krunk_factory = Program::from_nodeptr(allocator, krunk_factory_node).expect("ok");
+    let krunk_factory_raw = puzzle_registry::KRUNK_GENERATE_KRUNK_FACTORY
+        .to_puzzle(allocator)
+        .expect("should load krunk factory");
     game_type_map.insert(
         GameType(b"krunk".to_vec()),
-        GameFactory {
-            program: Some(krunk_factory.into()),
-        },
+        krunk_factory(allocator, krunk_factory_raw).expect("should build krunk factory"),
     );
 
     #[cfg(test)]
     {
-        let debug_game_raw =
-            read_hex_puzzle(allocator, "clsp/test/debug_game.hex").expect("should load");
+        let debug_game_raw = puzzle_registry::DEBUG_GAME
+            .to_puzzle(allocator)
+            .expect("should load");
         let debug_game_node = CurriedProgram {
             program: debug_game_raw.clone(),
             args: clvm_curried_args!("factory", ()),
@@ -84,3 +68,20 @@ pub fn register_all(allocator: &mut AllocEncoder) -> BTreeMap<GameType, GameFact
 
     game_type_map
 }
+
+/// Curry the krunk dictionary (signing pubkey and word tree) into the raw
+/// compiled krunk factory.
+pub fn krunk_factory(allocator: &mut AllocEncoder, raw: Puzzle) -> Result<GameFactory, Error> {
+    let (dict_pubkey, dict_tree) =
+        read_krunk_dict_dat(allocator, "clsp/games/krunk/krunk_signed_dict_tree.dat")?;
+    let krunk_factory_node = CurriedProgram {
+        program: raw,
+        args: clvm_curried_args!(dict_pubkey, dict_tree),
+    }
+    .to_clvm(allocator)
+    .into_gen()?;
+    let krunk_factory = Program::from_nodeptr(allocator, krunk_factory_node)?;
+    Ok(GameFactory {
+        program: Some(krunk_factory.into()),
+    })
+}
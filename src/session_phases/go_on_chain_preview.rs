@@ -0,0 +1,330 @@
+//! Dry run of `go_on_chain` from the current off-chain state.
+//!
+//! The preview works on a clone of the channel state and builds the same
+//! spends the on-chain phases would submit if the opponent went silent: the
+//! channel spend, the unroll (preempt or timeout), a redo of any move the
+//! opponent never acknowledged, and a timeout claim per game coin that pays
+//! us. Each spend is priced by consensus validation. Nothing is submitted and
+//! the live session is not touched.
+
+use clvmr::run_program;
+use serde::{Deserialize, Serialize};
+
+use crate::channel_state::types::{ChannelCoinSpendInfo, ChannelEnv};
+use crate::channel_state::ChannelState;
+use crate::common::consensus::spend_bundle_cost;
+use crate::common::types::{
+    chia_dialect, Amount, CoinCondition, CoinSpend, CoinString, Error, GameID, IntoErr, PuzzleHash,
+    Spend, SpendBundle, Timeout, MAX_BLOCK_COST_CLVM,
+};
+use crate::session_phases::handler_base::{
+    build_channel_to_unroll_bundle, build_unroll_timeout_bundle, classify_unroll, UnrollOutcome,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PreviewSpendKind {
+    /// Channel coin into the unroll coin, using the last co-signed state.
+    ChannelToUnroll,
+    /// Unroll coin spent at once because we hold a newer state.
+    UnrollPreempt,
+    /// Unroll coin spent after `unroll_timeout`.
+    UnrollTimeout,
+    /// Replay of our move the opponent never acknowledged off-chain.
+    RedoMove,
+    /// Game coin claimed after its game timeout.
+    GameTimeoutClaim,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewSpend {
+    pub kind: PreviewSpendKind,
+    pub game_id: Option<GameID>,
+    pub coin: CoinString,
+    pub cost: u64,
+    /// Blocks after the channel spend confirms before this spend can land.
+    pub wait_blocks: u64,
+    /// Seconds on top of `wait_blocks`, from timeouts given in seconds.
+    pub wait_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamePayoutPreview {
+    pub game_id: GameID,
+    /// The game coin the unroll creates.
+    pub coin: CoinString,
+    pub our_turn: bool,
+    pub redo_move: bool,
+    pub game_timeout: Timeout,
+    /// Blocks after the channel spend confirms before the game pays out.
+    pub wait_blocks: u64,
+    pub wait_seconds: u64,
+    pub our_payout_if_silent: Amount,
+}
+
+/// What going on chain now would cost, how long it would take and what it
+/// would pay us if the opponent never acts again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoOnChainPreview {
+    pub spends: Vec<PreviewSpend>,
+    pub total_cost: u64,
+    pub unroll_wait_blocks: u64,
+    pub unroll_wait_seconds: u64,
+    /// Blocks after the channel spend confirms until every coin has resolved.
+    pub max_wait_blocks: u64,
+    pub max_wait_seconds: u64,
+    /// Our out-of-game balance, paid by the unroll itself.
+    pub our_channel_payout: Amount,
+    pub games: Vec<GamePayoutPreview>,
+    pub total_payout_if_silent: Amount,
+}
+
+fn spend_conditions(env: &mut ChannelEnv<'_>, spend: &Spend) -> Result<Vec<CoinCondition>, Error> {
+    CoinCondition::from_puzzle_and_solution(
+        env.allocator,
+        &spend.puzzle.to_program(),
+        &spend.solution.p(),
+    )
+}
+
+fn paid_to(conditions: &[CoinCondition], puzzle_hash: &PuzzleHash) -> Amount {
+    conditions
+        .iter()
+        .filter_map(|c| match c {
+            CoinCondition::CreateCoin(ph, amt) if ph == puzzle_hash => Some(amt.clone()),
+            _ => None,
+        })
+        .fold(Amount::default(), |acc, amt| acc + amt)
+}
+
+/// A wait split by unit, as (blocks, seconds).
+type Wait = (u64, u64);
+
+fn timeout_wait(timeout: &Timeout) -> Wait {
+    (
+        timeout.blocks().unwrap_or(0),
+        timeout.as_seconds().unwrap_or(0),
+    )
+}
+
+struct PreviewBuilder {
+    spends: Vec<PreviewSpend>,
+}
+
+impl PreviewBuilder {
+    fn push(
+        &mut self,
+        env: &mut ChannelEnv<'_>,
+        kind: PreviewSpendKind,
+        game_id: Option<GameID>,
+        coin_spend: CoinSpend,
+        (wait_blocks, wait_seconds): Wait,
+    ) -> Result<(), Error> {
+        let cost = spend_bundle_cost(env.allocator, std::slice::from_ref(&coin_spend))?;
+        self.spends.push(PreviewSpend {
+            kind,
+            game_id,
+            coin: coin_spend.coin,
+            cost,
+            wait_blocks,
+            wait_seconds,
+        });
+        Ok(())
+    }
+}
+
+/// Simulate the unroll and on-chain resolution of `ch` as `go_on_chain` would
+/// start it from `saved`, the last co-signed channel coin spend.
+pub fn preview_go_on_chain(
+    env: &mut ChannelEnv<'_>,
+    ch: &ChannelState,
+    saved: &ChannelCoinSpendInfo,
+    unroll_timeout: &Timeout,
+) -> Result<GoOnChainPreview, Error> {
+    let mut ch = ch.clone();
+    ch.cancel_all_proposals();
+    let mut builder = PreviewBuilder { spends: Vec::new() };
+
+    let channel_coin = ch.channel_coin().clone();
+    let SpendBundle { mut spends, .. } =
+        build_channel_to_unroll_bundle(env, &ch, &channel_coin, saved, "preview")?;
+    let channel_spend = spends
+        .pop()
+        .ok_or_else(|| Error::StrErr("preview: empty channel spend".to_string()))?;
+    let channel_puzzle = channel_spend
+        .bundle
+        .puzzle
+        .to_program()
+        .to_nodeptr(env.allocator)?;
+    let channel_solution = channel_spend
+        .bundle
+        .solution
+        .p()
+        .to_nodeptr(env.allocator)?;
+    let channel_conditions = run_program(
+        env.allocator.allocator(),
+        &chia_dialect(),
+        channel_puzzle,
+        channel_solution,
+        MAX_BLOCK_COST_CLVM,
+    )
+    .into_gen()?
+    .1;
+    builder.push(
+        env,
+        PreviewSpendKind::ChannelToUnroll,
+        None,
+        channel_spend,
+        (0, 0),
+    )?;
+
+    let unroll_coin = CoinCondition::from_nodeptr(env.allocator, channel_conditions)?
+        .iter()
+        .find_map(|c| match c {
+            CoinCondition::CreateCoin(ph, amt) if ch.unroll_puzzle_hash_map().contains_key(ph) => {
+                Some(CoinString::from_parts(&channel_coin.to_coin_id(), ph, amt))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            Error::StrErr("preview: channel spend creates no unroll coin".to_string())
+        })?;
+    let (on_chain_state, _) = ch.resolve_unroll_from_conditions(env, channel_conditions)?;
+    let (unroll_spend, kind, unroll_wait) =
+        match classify_unroll(&ch, env, channel_conditions, &unroll_coin, on_chain_state)? {
+            UnrollOutcome::Preempted(bundle) => (bundle, PreviewSpendKind::UnrollPreempt, (0, 0)),
+            UnrollOutcome::WaitForTimeout => (
+                build_unroll_timeout_bundle(env, &ch, &unroll_coin, on_chain_state)?,
+                PreviewSpendKind::UnrollTimeout,
+                timeout_wait(unroll_timeout),
+            ),
+            UnrollOutcome::Unrecoverable(reason) => {
+                return Err(Error::StrErr(format!("preview: {reason}")));
+            }
+        };
+    let unroll_spend = unroll_spend
+        .spends
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::StrErr("preview: empty unroll spend".to_string()))?;
+    let unroll_conditions = spend_conditions(env, &unroll_spend.bundle)?;
+    builder.push(env, kind, None, unroll_spend, unroll_wait)?;
+
+    // Sort the unroll's outputs the way finish_on_chain_transition does.
+    let reward_puzzle_hash = ch.get_reward_puzzle_hash(env)?;
+    let their_reward_puzzle_hash = ch.get_opponent_reward_puzzle_hash();
+    let our_channel_payout = paid_to(&unroll_conditions, &reward_puzzle_hash);
+    let created_coins: Vec<(PuzzleHash, Amount)> = unroll_conditions
+        .iter()
+        .filter_map(|c| match c {
+            CoinCondition::CreateCoin(ph, amt)
+                if *amt > Amount::default()
+                    && *ph != reward_puzzle_hash
+                    && *ph != their_reward_puzzle_hash
+                    && !ch.is_settlement_output_puzzle_hash(ph) =>
+            {
+                Some((ph.clone(), amt.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    let mut game_coins: Vec<_> = ch
+        .set_state_for_coins(env, &unroll_coin, &created_coins)?
+        .into_iter()
+        .collect();
+    game_coins.sort_by_key(|(_, state)| state.game_id);
+
+    let mut games = Vec::new();
+    for (coin, state) in game_coins {
+        let game_id = state.game_id;
+        let mut preview = GamePayoutPreview {
+            game_id,
+            coin: coin.clone(),
+            our_turn: state.our_turn,
+            redo_move: false,
+            game_timeout: state.game_timeout.clone(),
+            wait_blocks: unroll_wait.0,
+            wait_seconds: unroll_wait.1,
+            our_payout_if_silent: Amount::default(),
+        };
+        let zero_reward = if state.timeout_claim_armed || !state.our_turn {
+            ch.get_game_our_current_share(&game_id)? == Amount::default()
+        } else {
+            ch.is_redo_zero_reward(&game_id)?
+        };
+        if zero_reward {
+            games.push(preview);
+            continue;
+        }
+
+        let mut claim_coin = coin;
+        if state.our_turn {
+            if let Some(cached_move) = ch.take_cached_move_for_game(&game_id) {
+                let (referee, last_ph) = cached_move
+                    .saved_post_move_referee
+                    .clone()
+                    .zip(cached_move.saved_post_move_last_ph.clone())
+                    .ok_or_else(|| {
+                        Error::StrErr("preview: no saved post-move referee".to_string())
+                    })?;
+                ch.restore_game_state(&game_id, referee, last_ph)?;
+                let transaction =
+                    ch.get_transaction_for_game_move(env.allocator, &game_id, &claim_coin)?;
+                let new_ph = ch.get_game_outcome_puzzle_hash(env, &game_id)?;
+                let new_amount = paid_to(&spend_conditions(env, &transaction)?, &new_ph);
+                let moved_coin =
+                    CoinString::from_parts(&claim_coin.to_coin_id(), &new_ph, &new_amount);
+                builder.push(
+                    env,
+                    PreviewSpendKind::RedoMove,
+                    Some(game_id),
+                    CoinSpend {
+                        coin: claim_coin,
+                        bundle: transaction,
+                    },
+                    unroll_wait,
+                )?;
+                preview.redo_move = true;
+                claim_coin = moved_coin;
+            }
+        }
+
+        let (game_blocks, game_seconds) = timeout_wait(&state.game_timeout);
+        preview.wait_blocks += game_blocks;
+        preview.wait_seconds += game_seconds;
+        if let Some(claim) = ch.build_game_timeout_claim_spend(env, &game_id, &claim_coin)? {
+            preview.our_payout_if_silent = paid_to(
+                &spend_conditions(env, &claim)?,
+                &ch.game_reward_puzzle_hash(&game_id),
+            );
+            if preview.our_payout_if_silent > Amount::default() {
+                builder.push(
+                    env,
+                    PreviewSpendKind::GameTimeoutClaim,
+                    Some(game_id),
+                    CoinSpend {
+                        coin: claim_coin,
+                        bundle: claim,
+                    },
+                    (preview.wait_blocks, preview.wait_seconds),
+                )?;
+            }
+        }
+        games.push(preview);
+    }
+
+    let spends = builder.spends;
+    let total_payout_if_silent = games.iter().fold(our_channel_payout.clone(), |acc, g| {
+        acc + g.our_payout_if_silent.clone()
+    });
+    Ok(GoOnChainPreview {
+        total_cost: spends.iter().map(|s| s.cost).sum(),
+        max_wait_blocks: spends.iter().map(|s| s.wait_blocks).max().unwrap_or(0),
+        max_wait_seconds: spends.iter().map(|s| s.wait_seconds).max().unwrap_or(0),
+        spends,
+        unroll_wait_blocks: unroll_wait.0,
+        unroll_wait_seconds: unroll_wait.1,
+        our_channel_payout,
+        games,
+        total_payout_if_silent,
+    })
+}
@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
 
+use clvm_traits::{clvm_curried_args, ToClvm};
+use clvm_utils::CurriedProgram;
 use clvmr::NodePtr;
 
 use serde::{Deserialize, Serialize};
@@ -8,7 +10,8 @@ use crate::channel_state::types::{ChannelCoinSpendInfo, ChannelEnv, ReadableMove
 use crate::channel_state::ChannelState;
 use crate::common::standard_coin::puzzle_for_synthetic_public_key;
 use crate::common::types::{
-    Amount, CoinSpend, CoinString, Error, GameID, Hash, PuzzleHash, Spend, SpendBundle, Timeout,
+    Aggsig, Amount, CoinSpend, CoinString, Error, GameID, Hash, IntoErr, Program, Puzzle,
+    PuzzleHash, Spend, SpendBundle, Timeout,
 };
 use crate::session_phases::effects::GameStatusKind;
 use crate::session_phases::effects::{CancelReason, Effect, GameNotification};
@@ -81,6 +84,44 @@ pub fn build_channel_to_unroll_bundle(
     })
 }
 
+/// Build the unroll-via-timeout spend for `unroll_coin` at `on_chain_state`
+/// from the historical unroll data `ch` stored for that state.
+pub fn build_unroll_timeout_bundle(
+    env: &mut ChannelEnv<'_>,
+    ch: &ChannelState,
+    unroll_coin: &CoinString,
+    on_chain_state: usize,
+) -> Result<SpendBundle, Error> {
+    let matching_unroll = ch.get_historical_unroll_for_state(on_chain_state)?;
+    let curried_unroll_puzzle = CurriedProgram {
+        program: env.unroll_puzzle.clone(),
+        args: clvm_curried_args!(
+            ch.get_aggregate_unroll_public_key(),
+            matching_unroll.state_number,
+            matching_unroll.conditions_hash.clone()
+        ),
+    }
+    .to_clvm(env.allocator)
+    .into_gen()?;
+    let curried_unroll_program = Puzzle::from_nodeptr(env.allocator, curried_unroll_puzzle)?;
+    let timeout_solution = matching_unroll
+        .timeout_conditions
+        .to_nodeptr(env.allocator)?;
+    let timeout_solution_program = Program::from_nodeptr(env.allocator, timeout_solution)?;
+
+    Ok(SpendBundle {
+        name: Some("create unroll (timeout)".to_string()),
+        spends: vec![CoinSpend {
+            bundle: Spend {
+                puzzle: curried_unroll_program,
+                solution: timeout_solution_program.into(),
+                signature: Aggsig::default(),
+            },
+            coin: unroll_coin.clone(),
+        }],
+    })
+}
+
 /// Shared state and methods for handlers that hold a `ChannelState` and
 /// park game actions while waiting for on-chain resolution (Phases 2a and 3).
 #[derive(Serialize, Deserialize)]
@@ -183,8 +224,7 @@ impl ChannelStateBase {
     /// Deserialize a peer message and handle `CleanShutdownComplete`;
     /// ignore everything else.
     pub fn received_message_passive(&self, msg: Vec<u8>) -> Result<Vec<Effect>, Error> {
-        let msg_envelope: PeerMessage = bencodex::from_slice(&msg)
-            .map_err(|e| Error::StrErr(format!("bencodex deserialize error: {e:?}")))?;
+        let msg_envelope = PeerMessage::decode(&msg)?;
 
         if let PeerMessage::CleanShutdownComplete(coin_spend) = &msg_envelope {
             return Ok(vec![Effect::SpendTransaction(
@@ -195,6 +235,11 @@ impl ChannelStateBase {
                 None,
             )]);
         }
+        if let PeerMessage::GameResultSignature(game_id, signature) = msg_envelope {
+            return Ok(vec![Effect::ReceivedGameResultSignature(
+                game_id, signature,
+            )]);
+        }
         Ok(vec![])
     }
 
@@ -1,9 +1,220 @@
 use crate::channel_state::types::StateUpdateSignatures;
+use std::collections::{BTreeMap, BTreeSet};
+
 use crate::common::types::{
-    Aggsig, Amount, CoinID, CoinString, PublicKey, PuzzleHash, SpendBundle,
+    Aggsig, AllocEncoder, Amount, CoinID, CoinString, GameType, PublicKey, PuzzleHash, Sha256tree,
+    SpendBundle,
 };
+use crate::session_phases::types::{BatchAction, GameFactory, PeerMessage, WireGameSpec};
+use bencodex::schema::{EnumSchema, HasSchema, StructSchema};
 use serde::{Deserialize, Serialize};
 
+/// Wire protocol version advertised in HandshakeA/B.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest protocol version this build can still speak.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Smallest inbound message limit a peer may advertise.  Anything lower could
+/// not carry a single batch of moves plus signatures, so the channel would
+/// wedge after funding.
+pub const MIN_NEGOTIABLE_MESSAGE_SIZE: u64 = 64 * 1024;
+/// `PeerMessage::StopMatch`: the sender's match stops at the next hand
+/// boundary.
+pub const FEATURE_MATCH_STOP: &str = "match-stop";
+/// `PeerMessage::GameResultSignature`: the sender's signature on the result
+/// of a game that settled off-chain.
+pub const FEATURE_RESULT_ATTESTATION: &str = "result-attestation";
+/// Optional peer messages this build understands.  Each is only sent to a
+/// peer that advertised it.
+pub const FEATURES: &[&str] = &[FEATURE_MATCH_STOP, FEATURE_RESULT_ATTESTATION];
+
+/// What one side of the handshake can speak.  Exchanged in HandshakeA/B and
+/// checked by both sides before any funds are committed.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct HandshakeCapabilities {
+    pub protocol_version: u32,
+    pub min_protocol_version: u32,
+    /// Supported game types with the tree hash of their factory program.
+    /// `None` means the factory program is not known locally; such a game
+    /// type is never in common with the peer.
+    pub game_types: Vec<(GameType, Option<PuzzleHash>)>,
+    /// Names of the [`BatchAction`] kinds this side understands.
+    pub batch_action_kinds: Vec<String>,
+    /// Largest inbound peer message this side accepts, in bytes.
+    pub max_message_size: u64,
+    /// This build's encoding of [`WireGameSpec`].  Builds from before schemas
+    /// were exchanged leave it out, and then nothing is checked.
+    #[serde(default)]
+    pub wire_game_spec: Option<StructSchema>,
+    /// This build's encoding of [`PeerMessage`].  Only variants both sides
+    /// know are compared; newer ones are gated by the fields above.
+    #[serde(default)]
+    pub peer_message: Option<EnumSchema>,
+    /// The [`FEATURES`] this side understands.  Builds from before the list
+    /// existed leave it out and get none of them.
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+/// Outcome of a successful capability negotiation.  Kept for the life of the
+/// channel: proposals are limited to `game_types`, neither side sends a
+/// message over `max_message_size`, and optional wire additions are only sent
+/// to a peer that advertised them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiatedCapabilities {
+    pub protocol_version: u32,
+    /// Game types both sides know, with the same factory.
+    pub game_types: Vec<GameType>,
+    /// Batch action kinds both sides understand.
+    pub batch_action_kinds: Vec<String>,
+    pub max_message_size: u64,
+    /// Optional features both sides understand.
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+impl NegotiatedCapabilities {
+    pub fn has_game_type(&self, game_type: &GameType) -> bool {
+        self.game_types.contains(game_type)
+    }
+
+    pub fn supports_batch_action(&self, kind: &str) -> bool {
+        self.batch_action_kinds.iter().any(|k| k == kind)
+    }
+
+    pub fn supports_feature(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+impl HandshakeCapabilities {
+    pub fn local(game_types: &BTreeMap<GameType, GameFactory>, max_message_size: usize) -> Self {
+        let allocator = &mut AllocEncoder::new();
+        HandshakeCapabilities {
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            game_types: game_types
+                .iter()
+                .map(|(gt, factory)| {
+                    let hash = factory
+                        .program
+                        .as_ref()
+                        .map(|p| p.as_ref().sha256tree(allocator));
+                    (gt.clone(), hash)
+                })
+                .collect(),
+            batch_action_kinds: BatchAction::KINDS.iter().map(|k| k.to_string()).collect(),
+            max_message_size: max_message_size as u64,
+            wire_game_spec: Some(WireGameSpec::schema()),
+            peer_message: Some(PeerMessage::schema()),
+            features: FEATURES.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    /// Check the peer's advertised capabilities against ours.  The error is a
+    /// human-readable reason suitable for a `ChannelStatus::Failed` advisory.
+    pub fn negotiate(
+        &self,
+        peer: &HandshakeCapabilities,
+    ) -> Result<NegotiatedCapabilities, String> {
+        if peer.protocol_version == 0 {
+            return Err(
+                "peer did not advertise a protocol version (pre-negotiation build)".to_string(),
+            );
+        }
+        let version = self.protocol_version.min(peer.protocol_version);
+        let floor = self.min_protocol_version.max(peer.min_protocol_version);
+        if version < floor {
+            return Err(format!(
+                "protocol version mismatch: we speak {}..={}, peer speaks {}..={}",
+                self.min_protocol_version,
+                self.protocol_version,
+                peer.min_protocol_version,
+                peer.protocol_version
+            ));
+        }
+
+        let peer_games: BTreeMap<&GameType, &Option<PuzzleHash>> =
+            peer.game_types.iter().map(|(gt, h)| (gt, h)).collect();
+        let mut common = Vec::new();
+        for (gt, ours) in self.game_types.iter() {
+            let Some(theirs) = peer_games.get(gt) else {
+                continue;
+            };
+            // Without both hashes there is nothing to show the two sides would
+            // run the same factory, so the game type can't be played.
+            let (Some(ours), Some(theirs)) = (ours, theirs) else {
+                continue;
+            };
+            if ours != theirs {
+                return Err(format!(
+                    "game type {} factory mismatch: ours {:?}, peer's {:?}",
+                    String::from_utf8_lossy(&gt.0),
+                    ours,
+                    theirs
+                ));
+            }
+            common.push(gt.clone());
+        }
+        if common.is_empty() {
+            return Err("no game types in common with peer".to_string());
+        }
+
+        let peer_kinds: BTreeSet<&str> =
+            peer.batch_action_kinds.iter().map(|k| k.as_str()).collect();
+        let missing: Vec<&str> = self
+            .batch_action_kinds
+            .iter()
+            .map(|k| k.as_str())
+            .filter(|k| !peer_kinds.contains(k) && !BatchAction::OPTIONAL_KINDS.contains(k))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "peer does not support batch actions: {}",
+                missing.join(", ")
+            ));
+        }
+
+        let mut wire_problems = Vec::new();
+        if let (Some(ours), Some(theirs)) = (&self.wire_game_spec, &peer.wire_game_spec) {
+            wire_problems.extend(ours.check_compatible(theirs).err().unwrap_or_default());
+        }
+        if let (Some(ours), Some(theirs)) = (&self.peer_message, &peer.peer_message) {
+            wire_problems.extend(ours.check_compatible(theirs).err().unwrap_or_default());
+        }
+        if !wire_problems.is_empty() {
+            return Err(format!(
+                "wire encoding mismatch: {}",
+                wire_problems.join("; ")
+            ));
+        }
+
+        if peer.max_message_size < MIN_NEGOTIABLE_MESSAGE_SIZE {
+            return Err(format!(
+                "peer max message size {} is below the minimum of {MIN_NEGOTIABLE_MESSAGE_SIZE}",
+                peer.max_message_size
+            ));
+        }
+
+        Ok(NegotiatedCapabilities {
+            protocol_version: version,
+            game_types: common,
+            batch_action_kinds: self
+                .batch_action_kinds
+                .iter()
+                .filter(|k| peer_kinds.contains(k.as_str()))
+                .cloned()
+                .collect(),
+            max_message_size: self.max_message_size.min(peer.max_message_size),
+            features: self
+                .features
+                .iter()
+                .filter(|f| peer.features.contains(f))
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct HandshakePayloadB {
     pub channel_public_key: PublicKey,
@@ -15,6 +226,8 @@ pub struct HandshakePayloadB {
     pub unroll_key_pop: Aggsig,
     pub my_contribution: Amount,
     pub their_contribution: Amount,
+    #[serde(default)]
+    pub capabilities: HandshakeCapabilities,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -64,3 +277,252 @@ pub struct HandshakeStepWithSpend {
     pub info: HandshakeStepInfo,
     pub spend: SpendBundle,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::channel_state::types::{ChannelEnv, ChannelPrivateKeys};
+    use crate::common::types::{Program, Timeout};
+    use crate::game_session::PeerLifecyclePhase;
+    use crate::session_phases::effects::{ChannelStatus, Effect};
+    use crate::session_phases::handshake_initiator::HandshakeInitiatorPhase;
+    use crate::session_phases::handshake_receiver::HandshakeReceiverPhase;
+    use crate::session_phases::types::{OffChainPhaseInit, PeerLimits, PeerMessage};
+
+    fn games(factory: &[u8]) -> BTreeMap<GameType, GameFactory> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            GameType(b"calpoker".to_vec()),
+            GameFactory {
+                program: Some(Rc::new(Program::from_bytes(factory))),
+            },
+        );
+        map
+    }
+
+    fn init(
+        rng: &mut ChaCha8Rng,
+        game_types: BTreeMap<GameType, GameFactory>,
+    ) -> OffChainPhaseInit {
+        let private_keys: ChannelPrivateKeys = rng.random();
+        OffChainPhaseInit {
+            have_potato: false,
+            private_keys,
+            game_types,
+            my_contribution: Amount::new(100),
+            their_contribution: Amount::new(100),
+            channel_timeout: Timeout::new(100),
+            unroll_timeout: Timeout::new(5),
+            reward_puzzle_hash: PuzzleHash::default(),
+            peer_limits: PeerLimits::default(),
+        }
+    }
+
+    #[test]
+    fn matching_capabilities_negotiate() {
+        let ours = HandshakeCapabilities::local(&games(&[0x80]), 1 << 20);
+        let negotiated = ours.negotiate(&ours.clone()).expect("compatible");
+        assert_eq!(negotiated.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(negotiated.game_types, vec![GameType(b"calpoker".to_vec())]);
+        assert_eq!(negotiated.max_message_size, 1 << 20);
+        for kind in BatchAction::KINDS {
+            assert!(negotiated.supports_batch_action(kind));
+        }
+    }
+
+    #[test]
+    fn optional_kinds_are_recorded_not_required() {
+        let ours = HandshakeCapabilities::local(&games(&[0x80]), 1 << 20);
+        for optional in BatchAction::OPTIONAL_KINDS {
+            assert!(BatchAction::KINDS.contains(optional));
+        }
+        let mut older = ours.clone();
+        older
+            .batch_action_kinds
+            .retain(|k| !BatchAction::OPTIONAL_KINDS.contains(&k.as_str()));
+        let negotiated = ours.negotiate(&older).expect("compatible");
+        assert!(negotiated.supports_batch_action("Move"));
+        assert!(!negotiated.supports_batch_action("AcceptProposalWithPayout"));
+    }
+
+    #[test]
+    fn features_are_recorded_not_required() {
+        let ours = HandshakeCapabilities::local(&games(&[0x80]), 1 << 20);
+        let older = HandshakeCapabilities {
+            features: Vec::new(),
+            ..ours.clone()
+        };
+        for feature in [FEATURE_MATCH_STOP, FEATURE_RESULT_ATTESTATION] {
+            assert!(ours
+                .negotiate(&ours.clone())
+                .expect("compatible")
+                .supports_feature(feature));
+            assert!(!ours
+                .negotiate(&older)
+                .expect("compatible")
+                .supports_feature(feature));
+        }
+    }
+
+    #[test]
+    fn unknown_factory_hash_is_not_in_common() {
+        let ours = HandshakeCapabilities::local(&games(&[0x80]), 1 << 20);
+        let mut hidden = ours.clone();
+        for (_, hash) in hidden.game_types.iter_mut() {
+            *hash = None;
+        }
+        assert_eq!(
+            ours.negotiate(&hidden).unwrap_err(),
+            "no game types in common with peer"
+        );
+        assert_eq!(
+            hidden.negotiate(&ours).unwrap_err(),
+            "no game types in common with peer"
+        );
+    }
+
+    #[test]
+    fn each_mismatch_is_named() {
+        let ours = HandshakeCapabilities::local(&games(&[0x80]), 1 << 20);
+
+        let legacy = HandshakeCapabilities::default();
+        assert!(ours
+            .negotiate(&legacy)
+            .unwrap_err()
+            .contains("protocol version"));
+
+        let newer = HandshakeCapabilities {
+            protocol_version: PROTOCOL_VERSION + 1,
+            min_protocol_version: PROTOCOL_VERSION + 1,
+            ..ours.clone()
+        };
+        assert!(ours
+            .negotiate(&newer)
+            .unwrap_err()
+            .contains("version mismatch"));
+
+        let other_factory = HandshakeCapabilities::local(&games(&[0x01]), 1 << 20);
+        assert!(ours
+            .negotiate(&other_factory)
+            .unwrap_err()
+            .contains("calpoker factory mismatch"));
+
+        let mut no_settle = ours.clone();
+        no_settle
+            .batch_action_kinds
+            .retain(|k| k != "AcceptSettlement");
+        assert_eq!(
+            ours.negotiate(&no_settle).unwrap_err(),
+            "peer does not support batch actions: AcceptSettlement"
+        );
+
+        let tiny = HandshakeCapabilities {
+            max_message_size: 1024,
+            ..ours.clone()
+        };
+        assert!(ours
+            .negotiate(&tiny)
+            .unwrap_err()
+            .contains("max message size"));
+    }
+
+    #[test]
+    fn retyped_wire_schema_is_rejected() {
+        let ours = HandshakeCapabilities::local(&games(&[0x80]), 1 << 20);
+
+        let mut retyped = ours.clone();
+        let spec = retyped.wire_game_spec.as_mut().expect("schema");
+        spec.fields[0].ty = "String".into();
+        assert!(ours
+            .negotiate(&retyped)
+            .unwrap_err()
+            .starts_with("wire encoding mismatch: WireGameSpec"));
+
+        let mut reshaped = ours.clone();
+        let message = reshaped.peer_message.as_mut().expect("schema");
+        message
+            .variants
+            .iter_mut()
+            .find(|v| v.tag == "Message")
+            .expect("Message variant")
+            .fields
+            .pop();
+        assert!(ours
+            .negotiate(&reshaped)
+            .unwrap_err()
+            .contains("PeerMessage::Message"));
+
+        let mut older = ours.clone();
+        older.peer_message.as_mut().expect("schema").variants.pop();
+        older.wire_game_spec = None;
+        assert!(ours.negotiate(&older).is_ok());
+    }
+
+    #[test]
+    fn receiver_keeps_what_was_negotiated() {
+        let mut rng = ChaCha8Rng::from_seed([9; 32]);
+        let allocator = &mut AllocEncoder::new();
+        let mut alice = HandshakeInitiatorPhase::new(init(&mut rng, games(&[0x80])));
+        let mut bob_games = games(&[0x80]);
+        bob_games.insert(
+            GameType(b"krunk".to_vec()),
+            GameFactory {
+                program: Some(Rc::new(Program::from_bytes(&[0x80]))),
+            },
+        );
+        let mut bob = HandshakeReceiverPhase::new(init(&mut rng, bob_games));
+
+        let handshake_a = {
+            let mut env = ChannelEnv::new(allocator).expect("env");
+            match alice.start(&mut env).expect("start") {
+                Some(Effect::PeerHandshakeA(msg)) => PeerMessage::HandshakeA(msg),
+                other => panic!("expected HandshakeA, got {other:?}"),
+            }
+        };
+        let mut env = ChannelEnv::new(allocator).expect("env");
+        let effects = bob
+            .received_message(&mut env, bencodex::to_vec(&handshake_a).unwrap())
+            .expect("compatible");
+        let negotiated = effects
+            .iter()
+            .find_map(|effect| match effect {
+                Effect::CapabilitiesNegotiated(negotiated) => Some(negotiated),
+                _ => None,
+            })
+            .expect("negotiated");
+        assert_eq!(negotiated.game_types, vec![GameType(b"calpoker".to_vec())]);
+    }
+
+    #[test]
+    fn receiver_rejects_mismatched_factory_before_replying() {
+        let mut rng = ChaCha8Rng::from_seed([9; 32]);
+        let allocator = &mut AllocEncoder::new();
+        let mut alice = HandshakeInitiatorPhase::new(init(&mut rng, games(&[0x80])));
+        let mut bob = HandshakeReceiverPhase::new(init(&mut rng, games(&[0x01])));
+
+        let handshake_a = {
+            let mut env = ChannelEnv::new(allocator).expect("env");
+            match alice.start(&mut env).expect("start") {
+                Some(Effect::PeerHandshakeA(msg)) => PeerMessage::HandshakeA(msg),
+                other => panic!("expected HandshakeA, got {other:?}"),
+            }
+        };
+        let mut env = ChannelEnv::new(allocator).expect("env");
+        let result = bob.received_message(&mut env, bencodex::to_vec(&handshake_a).unwrap());
+        assert!(result.is_err());
+
+        let status = bob.channel_status_snapshot().expect("status");
+        assert_eq!(status.state, ChannelStatus::Failed);
+        let advisory = status.advisory.expect("advisory");
+        assert!(
+            advisory.contains("calpoker factory mismatch"),
+            "advisory was {advisory}"
+        );
+    }
+}
@@ -24,11 +24,12 @@ use crate::session_phases::effects::{
     format_coin, ChannelStatus, ChannelStatusSnapshot, CoinOfInterest, Effect, ResyncInfo,
 };
 use crate::session_phases::handshake::{
-    CoinSpendRequest, HandshakePayloadB, HandshakePayloadC, HandshakePayloadE, HandshakePayloadF,
-    HandshakeStepInfo, HandshakeStepWithSpend, RawCoinCondition,
+    CoinSpendRequest, HandshakeCapabilities, HandshakePayloadB, HandshakePayloadC,
+    HandshakePayloadE, HandshakePayloadF, HandshakeStepInfo, HandshakeStepWithSpend,
+    NegotiatedCapabilities, RawCoinCondition,
 };
 use crate::session_phases::types::{
-    GameFactory, OffChainPhaseInit, PeerMessage, PotatoState, SpendWalletReceiver,
+    GameFactory, OffChainPhaseInit, PeerLimits, PeerMessage, PotatoState, SpendWalletReceiver,
 };
 use crate::session_phases::OffChainPhase;
 
@@ -85,6 +86,8 @@ pub struct HandshakeInitiatorPhase {
     channel_timeout: Timeout,
     unroll_timeout: Timeout,
     reward_puzzle_hash: PuzzleHash,
+    #[serde(default)]
+    peer_limits: PeerLimits,
 
     last_height: u64,
     channel_deadline: Option<u64>,
@@ -99,6 +102,8 @@ pub struct HandshakeInitiatorPhase {
     failed: bool,
     #[serde(default)]
     failure_advisory: Option<String>,
+    #[serde(default)]
+    negotiated: Option<NegotiatedCapabilities>,
 
     #[serde(skip)]
     replacement: Option<Box<OffChainPhase>>,
@@ -121,6 +126,7 @@ impl HandshakeInitiatorPhase {
             channel_timeout: phi.channel_timeout,
             unroll_timeout: phi.unroll_timeout,
             reward_puzzle_hash: phi.reward_puzzle_hash,
+            peer_limits: phi.peer_limits,
             last_height: 0,
             channel_deadline: None,
             pending_coin_spend: false,
@@ -130,6 +136,7 @@ impl HandshakeInitiatorPhase {
             last_channel_coin_spend_info: None,
             failed: false,
             failure_advisory: None,
+            negotiated: None,
             replacement: None,
         }
     }
@@ -235,6 +242,7 @@ impl HandshakeInitiatorPhase {
             unroll_key_pop,
             my_contribution: self.my_contribution.clone(),
             their_contribution: self.their_contribution.clone(),
+            capabilities: HandshakeCapabilities::local(&self.game_types, MAX_MESSAGE_SIZE),
         }
     }
 
@@ -393,8 +401,10 @@ impl HandshakeInitiatorPhase {
                 self.channel_timeout.clone(),
                 self.unroll_timeout.clone(),
                 self.reward_puzzle_hash.clone(),
+                self.peer_limits.clone(),
                 queued_messages,
                 self.last_channel_coin_spend_info.take(),
+                self.negotiated.clone(),
             );
             self.replacement = Some(Box::new(ph));
             self.state = InitiatorState::Done;
@@ -463,6 +473,19 @@ impl HandshakeInitiatorPhase {
                         msg.their_contribution, self.my_contribution
                     )));
                 }
+                match handshake_a.capabilities.negotiate(&msg.capabilities) {
+                    Ok(negotiated) => {
+                        self.negotiated = Some(negotiated.clone());
+                        effects.push(Effect::CapabilitiesNegotiated(negotiated));
+                    }
+                    Err(reason) => {
+                        self.failed = true;
+                        self.failure_advisory = Some(format!("incompatible peer: {reason}"));
+                        return Err(Error::Channel(format!(
+                            "HandshakeB capability negotiation failed: {reason}"
+                        )));
+                    }
+                }
 
                 let our_channel_pk =
                     private_to_public_key(&self.private_keys.my_channel_coin_private_key);
@@ -558,7 +581,7 @@ impl HandshakeInitiatorPhase {
                 msg.len(),
             )));
         }
-        let msg_envelope: PeerMessage = bencodex::from_slice(&msg).into_gen()?;
+        let msg_envelope = PeerMessage::decode(&msg)?;
         self.incoming_messages.push_back(Rc::new(msg_envelope));
         self.process_queued_message(env)
     }
@@ -737,6 +760,10 @@ impl PeerLifecyclePhase for HandshakeInitiatorPhase {
     fn handshake_finished(&self) -> bool {
         false
     }
+    #[cfg(feature = "hot-reload")]
+    fn replace_game_factory(&mut self, game_type: &GameType, factory: GameFactory) -> bool {
+        crate::hot_reload::replace_factory(&mut self.game_types, game_type, factory)
+    }
     fn channel_offer(
         &mut self,
         _env: &mut ChannelEnv<'_>,
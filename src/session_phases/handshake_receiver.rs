@@ -12,19 +12,20 @@ use crate::common::standard_coin::{
     private_to_public_key, sign_reward_payout, verify_reward_payout_signature,
 };
 use crate::common::types::{
-    Amount, CoinID, CoinString, Error, GameID, GameType, GetCoinStringParts, Hash, IntoErr,
-    Program, PuzzleHash, Sha256Input, Sha256tree, SpendBundle, Timeout,
+    Amount, CoinID, CoinString, Error, GameID, GameType, GetCoinStringParts, Hash, Program,
+    PuzzleHash, Sha256Input, Sha256tree, SpendBundle, Timeout,
 };
 use crate::game_session::PeerLifecyclePhase;
 use crate::session_phases::effects::{
     format_coin, ChannelStatus, ChannelStatusSnapshot, CoinOfInterest, Effect, ResyncInfo,
 };
 use crate::session_phases::handshake::{
-    CoinSpendRequest, HandshakePayloadB, HandshakePayloadD, HandshakePayloadE, HandshakePayloadF,
-    HandshakeStepInfo, HandshakeStepWithSpend, RawCoinCondition,
+    CoinSpendRequest, HandshakeCapabilities, HandshakePayloadB, HandshakePayloadD,
+    HandshakePayloadE, HandshakePayloadF, HandshakeStepInfo, HandshakeStepWithSpend,
+    NegotiatedCapabilities, RawCoinCondition,
 };
 use crate::session_phases::types::{
-    GameFactory, OffChainPhaseInit, PeerMessage, PotatoState, SpendWalletReceiver,
+    GameFactory, OffChainPhaseInit, PeerLimits, PeerMessage, PotatoState, SpendWalletReceiver,
 };
 use crate::session_phases::OffChainPhase;
 
@@ -80,6 +81,8 @@ pub struct HandshakeReceiverPhase {
     channel_timeout: Timeout,
     unroll_timeout: Timeout,
     reward_puzzle_hash: PuzzleHash,
+    #[serde(default)]
+    peer_limits: PeerLimits,
 
     last_height: u64,
     channel_deadline: Option<u64>,
@@ -93,6 +96,8 @@ pub struct HandshakeReceiverPhase {
     failed: bool,
     #[serde(default)]
     failure_advisory: Option<String>,
+    #[serde(default)]
+    negotiated: Option<NegotiatedCapabilities>,
 
     #[serde(skip)]
     replacement: Option<Box<OffChainPhase>>,
@@ -115,6 +120,7 @@ impl HandshakeReceiverPhase {
             channel_timeout: phi.channel_timeout,
             unroll_timeout: phi.unroll_timeout,
             reward_puzzle_hash: phi.reward_puzzle_hash,
+            peer_limits: phi.peer_limits,
             last_height: 0,
             channel_deadline: None,
             pending_coin_spend: false,
@@ -123,6 +129,7 @@ impl HandshakeReceiverPhase {
             last_channel_coin_spend_info: None,
             failed: false,
             failure_advisory: None,
+            negotiated: None,
             replacement: None,
         }
     }
@@ -285,8 +292,10 @@ impl HandshakeReceiverPhase {
                 self.channel_timeout.clone(),
                 self.unroll_timeout.clone(),
                 self.reward_puzzle_hash.clone(),
+                self.peer_limits.clone(),
                 queued_messages,
                 self.last_channel_coin_spend_info.take(),
+                self.negotiated.clone(),
             );
             self.replacement = Some(Box::new(ph));
             self.state = ReceiverState::Done;
@@ -349,6 +358,20 @@ impl HandshakeReceiverPhase {
                         msg.their_contribution, self.my_contribution
                     )));
                 }
+                let capabilities = HandshakeCapabilities::local(&self.game_types, MAX_MESSAGE_SIZE);
+                match capabilities.negotiate(&msg.capabilities) {
+                    Ok(negotiated) => {
+                        self.negotiated = Some(negotiated.clone());
+                        effects.push(Effect::CapabilitiesNegotiated(negotiated));
+                    }
+                    Err(reason) => {
+                        self.failed = true;
+                        self.failure_advisory = Some(format!("incompatible peer: {reason}"));
+                        return Err(Error::Channel(format!(
+                            "HandshakeA capability negotiation failed: {reason}"
+                        )));
+                    }
+                }
 
                 let my_hs_info = {
                     let channel_public_key =
@@ -380,6 +403,7 @@ impl HandshakeReceiverPhase {
                         unroll_key_pop,
                         my_contribution: self.my_contribution.clone(),
                         their_contribution: self.their_contribution.clone(),
+                        capabilities,
                     }
                 };
 
@@ -537,7 +561,7 @@ impl HandshakeReceiverPhase {
                 msg.len(),
             )));
         }
-        let msg_envelope: PeerMessage = bencodex::from_slice(&msg).into_gen()?;
+        let msg_envelope = PeerMessage::decode(&msg)?;
         self.incoming_messages.push_back(Rc::new(msg_envelope));
         self.process_queued_message(env)
     }
@@ -722,6 +746,10 @@ impl PeerLifecyclePhase for HandshakeReceiverPhase {
     fn handshake_finished(&self) -> bool {
         false
     }
+    #[cfg(feature = "hot-reload")]
+    fn replace_game_factory(&mut self, game_type: &GameType, factory: GameFactory) -> bool {
+        crate::hot_reload::replace_factory(&mut self.game_types, game_type, factory)
+    }
     fn channel_transaction_completion(
         &mut self,
         _env: &mut ChannelEnv<'_>,
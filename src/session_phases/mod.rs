@@ -5,35 +5,44 @@ use std::rc::Rc;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::attestation::GameOrigin;
 use crate::channel_state::game;
+use crate::channel_state::game_handler::GameHandler;
 use crate::channel_state::game_start_info::GameStartInfo;
 use crate::channel_state::types::{
     ChannelCoinSpendInfo, ChannelEnv, ChannelPrivateKeys, ReadableMove, StateUpdateSignatures,
 };
 use crate::channel_state::ChannelState;
-use crate::common::standard_coin::puzzle_for_synthetic_public_key;
+use crate::common::clvm_trace::with_clvm_cost_budget;
+use crate::common::standard_coin::{private_to_public_key, puzzle_for_synthetic_public_key};
 use crate::common::types::{
-    Aggsig, Amount, CoinSpend, CoinString, Error, GameID, GameType, Hash, IntoErr, Program,
-    ProgramRef, PuzzleHash, Spend, SpendBundle, Timeout,
+    Aggsig, Amount, CoinSpend, CoinString, Error, GameID, GameType, Hash, Program, ProgramRef,
+    PuzzleHash, Sha256tree, Spend, SpendBundle, Timeout,
 };
+use crate::referee::types::GameMoveDetails;
 use crate::session_phases::effects::{
     format_coin, CancelReason, ChannelStatus, ChannelStatusSnapshot, CoinOfInterest, Effect,
     FailedGameAction, GameNotification, GameStatusKind, GameStatusOtherParams, ResyncInfo,
     SettlementOutcome,
 };
 use crate::shutdown::get_conditions_with_channel_state;
+use crate::spectator::SpectatorMove;
 use crate::utils::proper_list;
 
 use crate::game_session::PeerLifecyclePhase;
+use crate::operator_fee::GameFee;
+use crate::payout::{PayoutTerms, SignedPayout};
 use crate::session_phases::types::{
-    BatchAction, FromLocalUI, GameAction, GameFactory, PeerMessage, PotatoState, WireGameSpec,
-    WireProposalGroup,
+    BatchAction, FromLocalUI, GameAction, GameFactory, PeerLimits, PeerMessage, PotatoState,
+    WireGameSpec, WireProposalGroup,
 };
 
+use crate::session_phases::handshake::NegotiatedCapabilities;
 use crate::session_phases::proposal::GameProposal;
 
 pub mod effects;
 pub mod game_collection;
+pub mod go_on_chain_preview;
 pub mod handler_base;
 pub mod handshake;
 pub mod handshake_initiator;
@@ -133,6 +142,15 @@ pub struct OffChainPhase {
 
     pending_clean_shutdown: Option<(CoinString, ProgramRef)>,
 
+    #[serde(default)]
+    peer_limits: PeerLimits,
+    /// Game-message bytes received per game since that game last moved.
+    #[serde(default)]
+    game_message_bytes: BTreeMap<GameID, usize>,
+    /// `None` only for channels saved before negotiation was kept.
+    #[serde(default)]
+    negotiated: Option<NegotiatedCapabilities>,
+
     #[serde(skip)]
     channel_spend_next_phase:
         Option<Box<crate::session_phases::spend_channel_coin_phase::SpendChannelCoinPhase>>,
@@ -149,6 +167,10 @@ fn format_batch_action(action: &BatchAction) -> String {
             )
         }
         BatchAction::AcceptProposal(id) => format!("AcceptProposal id={id}"),
+        BatchAction::AcceptProposalWithPayout(id, payout) => format!(
+            "AcceptProposalWithPayout id={id} destinations={}",
+            payout.terms.destinations.len()
+        ),
         BatchAction::CancelProposal(id) => format!("CancelProposal id={id}"),
         BatchAction::Move(id, details) => {
             format!(
@@ -233,12 +255,51 @@ pub(crate) fn make_send_log(
     parts.join("\n")
 }
 
+/// Spectator records for the moves in a batch, committed to by `signature`
+/// (the mover's half of the unroll signature) over `conditions`.
+fn spectator_move_effects(
+    ch: &ChannelState,
+    moves: Vec<(GameID, GameMoveDetails, Option<GameHandler>)>,
+    conditions: ProgramRef,
+    signature: &Aggsig,
+    ours: bool,
+) -> Vec<Effect> {
+    let feeder = private_to_public_key(&ch.private_keys().my_referee_private_key);
+    moves
+        .into_iter()
+        .filter_map(|(game_id, details, handler)| {
+            let game = ch.find_live_game(&game_id)?;
+            Some(Effect::SpectatorMove {
+                record: Box::new(SpectatorMove {
+                    game_id,
+                    feeder: feeder.clone(),
+                    details,
+                    validator: game.last_move_validator(),
+                    conditions: conditions.clone(),
+                    signature: signature.clone(),
+                    handler,
+                    handler_signature: None,
+                }),
+                ours,
+            })
+        })
+        .collect()
+}
+
 impl OffChainPhase {
+    /// Run the proposal's factory, returning its games and where they came
+    /// from.
     fn factory_games(
         &mut self,
         env: &mut ChannelEnv<'_>,
         start: &GameProposal,
-    ) -> Result<Vec<game::FactoryGame>, Error> {
+    ) -> Result<(Vec<game::FactoryGame>, GameOrigin), Error> {
+        if !self.game_type_agreed(&start.game_type) {
+            return Err(Error::StrErr(format!(
+                "game type {:?} was not agreed with the peer",
+                start.game_type
+            )));
+        }
         let factory = self
             .game_types
             .get(&start.game_type)
@@ -248,7 +309,72 @@ impl OffChainPhase {
             .as_ref()
             .ok_or_else(|| Error::StrErr("GameFactory program missing".to_string()))?
             .clone();
-        game::Game::run_factory(env.allocator, program.into(), &start.parameters)
+        let origin = GameOrigin {
+            game_type: start.game_type.clone(),
+            factory_hash: program.sha256tree(env.allocator),
+        };
+        let games = game::Game::run_factory(env.allocator, program.into(), &start.parameters)?;
+        Ok((games, origin))
+    }
+
+    /// True if we have `game_type` and the handshake found the peer runs the
+    /// same factory for it.
+    fn game_type_agreed(&self, game_type: &GameType) -> bool {
+        self.game_types.contains_key(game_type)
+            && self
+                .negotiated
+                .as_ref()
+                .is_none_or(|negotiated| negotiated.has_game_type(game_type))
+    }
+
+    /// True if the peer said in the handshake that it understands `kind`.
+    fn peer_supports_batch_action(&self, kind: &str) -> bool {
+        self.negotiated
+            .as_ref()
+            .is_none_or(|negotiated| negotiated.supports_batch_action(kind))
+    }
+
+    /// The largest message either side may send.
+    fn max_message_size(&self) -> usize {
+        self.negotiated
+            .as_ref()
+            .map_or(Self::MAX_MESSAGE_SIZE, |negotiated| {
+                (negotiated.max_message_size as usize).min(Self::MAX_MESSAGE_SIZE)
+            })
+    }
+
+    /// The operator fee on each factory game.  The fee may not pay either
+    /// player's reward puzzle hash or a destination of the proposer's payout,
+    /// where its coin could collide with theirs.
+    fn game_fees(
+        &self,
+        start: &GameProposal,
+        games: &[game::FactoryGame],
+    ) -> Result<Vec<Option<GameFee>>, Error> {
+        let Some(fee) = &start.fee else {
+            return Ok(vec![None; games.len()]);
+        };
+        let ch = self.channel_state()?;
+        if fee.puzzle_hash == *ch.my_reward_puzzle_hash()
+            || fee.puzzle_hash == *ch.their_reward_puzzle_hash()
+        {
+            return Err(Error::StrErr(
+                "operator fee pays a player's reward puzzle hash".to_string(),
+            ));
+        }
+        if start
+            .payout
+            .as_ref()
+            .is_some_and(|payout| payout.pays(&fee.puzzle_hash))
+        {
+            return Err(Error::StrErr(
+                "operator fee pays a destination of the proposer's payout".to_string(),
+            ));
+        }
+        games
+            .iter()
+            .map(|game| fee.for_game(&game.amount))
+            .collect()
     }
 
     fn hydrate_wire_proposal_group(
@@ -256,14 +382,28 @@ impl OffChainPhase {
         env: &mut ChannelEnv<'_>,
         wire: &WireProposalGroup,
     ) -> Result<(Vec<Rc<GameStartInfo>>, GameType), Error> {
-        let factory_games = self.factory_games(env, &wire.start)?;
+        let (factory_games, origin) = self.factory_games(env, &wire.start)?;
         let ids = validate_wire_group_structure(wire, factory_games.len())?;
+        let fees = self.game_fees(&wire.start, &factory_games)?;
+        let their_payout = match (&wire.start.payout, &wire.payout_signature) {
+            (Some(terms), Some(signature)) => Some(SignedPayout {
+                terms: terms.clone(),
+                signature: signature.clone(),
+            }),
+            (None, None) => None,
+            _ => {
+                return Err(Error::StrErr(
+                    "proposal payout and its signature must be sent together".to_string(),
+                ))
+            }
+        };
 
         let mut receiver_starts = Vec::with_capacity(factory_games.len());
-        for (index, ((factory_game, member), game_id)) in factory_games
+        for (index, (((factory_game, member), game_id), fee)) in factory_games
             .iter()
             .zip(&wire.members)
             .zip(ids.iter())
+            .zip(fees)
             .enumerate()
         {
             let state = Program::from_bytes(factory_game.initial_state.bytes());
@@ -283,11 +423,10 @@ impl OffChainPhase {
                     "proposal group member {index} does not match factory output"
                 )));
             }
-            receiver_starts.push(Rc::new(factory_game.game_start(
-                game_id,
-                &wire.start.timeout,
-                false,
-            )));
+            let mut start = factory_game.game_start(game_id, &wire.start.timeout, false, fee);
+            start.their_payout = their_payout.clone();
+            start.origin = Some(origin.clone());
+            receiver_starts.push(Rc::new(start));
         }
 
         Ok((receiver_starts, wire.start.game_type.clone()))
@@ -304,8 +443,10 @@ impl OffChainPhase {
         channel_timeout: Timeout,
         unroll_timeout: Timeout,
         reward_puzzle_hash: PuzzleHash,
+        peer_limits: PeerLimits,
         incoming_messages: VecDeque<Rc<PeerMessage>>,
         last_channel_coin_spend_info: Option<ChannelCoinSpendInfo>,
+        negotiated: Option<NegotiatedCapabilities>,
     ) -> OffChainPhase {
         OffChainPhase {
             initiator,
@@ -324,10 +465,18 @@ impl OffChainPhase {
             peer_wants_potato: false,
             last_channel_coin_spend_info,
             pending_clean_shutdown: None,
+            peer_limits,
+            game_message_bytes: BTreeMap::default(),
+            negotiated,
             channel_spend_next_phase: None,
         }
     }
 
+    #[cfg(test)]
+    pub fn set_peer_limits(&mut self, limits: PeerLimits) {
+        self.peer_limits = limits;
+    }
+
     pub fn take_channel_spend_next_phase(
         &mut self,
     ) -> Option<Box<crate::session_phases::spend_channel_coin_phase::SpendChannelCoinPhase>> {
@@ -547,6 +696,13 @@ impl OffChainPhase {
                 signatures,
                 clean_shutdown,
             } => {
+                if actions.len() > self.peer_limits.max_batch_actions {
+                    return Err(Error::StrErr(format!(
+                        "batch of {} actions exceeds limit {}",
+                        actions.len(),
+                        self.peer_limits.max_batch_actions,
+                    )));
+                }
                 let ch_snapshot = self.channel_state.clone();
                 let queue_snapshot = self.game_action_queue.clone();
                 match self.process_received_batch(
@@ -567,6 +723,14 @@ impl OffChainPhase {
                 }
             }
             PeerMessage::Message(game_id, message) => {
+                let received = self.game_message_bytes.entry(*game_id).or_default();
+                *received += message.len();
+                if *received > self.peer_limits.max_game_message_bytes {
+                    return Err(Error::StrErr(format!(
+                        "game {game_id:?} messages total {} bytes since its last move, limit {}",
+                        *received, self.peer_limits.max_game_message_bytes,
+                    )));
+                }
                 let decoded_message = {
                     let ch = self.channel_state_mut()?;
                     ch.received_message(env, game_id, message)?
@@ -630,6 +794,15 @@ impl OffChainPhase {
                     self.channel_spend_next_phase = Some(Box::new(handler));
                 }
             }
+            PeerMessage::GameResultSignature(game_id, signature) => {
+                effects.push(Effect::ReceivedGameResultSignature(
+                    *game_id,
+                    signature.clone(),
+                ));
+            }
+            PeerMessage::StopMatch(()) => {
+                effects.push(Effect::ReceivedStopMatch);
+            }
             _ => {
                 return Err(Error::StrErr(format!(
                     "unhandled passthrough message {msg_envelope:?}"
@@ -659,6 +832,7 @@ impl OffChainPhase {
                     .iter()
                     .filter_map(|action| match (operation, action) {
                         ("accept", BatchAction::AcceptProposal(id))
+                        | ("accept", BatchAction::AcceptProposalWithPayout(id, _))
                         | ("cancel", BatchAction::CancelProposal(id)) => Some(*id),
                         _ => None,
                     })
@@ -679,6 +853,7 @@ impl OffChainPhase {
             }
         }
 
+        let mut spectated = Vec::new();
         for action in actions.iter() {
             match action {
                 BatchAction::ProposeGroup(wire) => {
@@ -701,7 +876,7 @@ impl OffChainPhase {
                         }));
                     }
 
-                    if !self.game_types.contains_key(&wire.start.game_type) {
+                    if !self.game_type_agreed(&wire.start.game_type) {
                         effects.push(Effect::Log(format!(
                             "declining proposal for unknown game type {:?}",
                             wire.start.game_type,
@@ -727,6 +902,12 @@ impl OffChainPhase {
                         let ivp_hash = first.initial_validation_program.hash().clone();
                         let initial_state = first.initial_state.clone();
                         let group_ids: Vec<GameID> = games.iter().map(|g| g.game_id).collect();
+                        let operator_fee = games.iter().filter_map(|game| game.fee.clone()).reduce(
+                            |total, fee| GameFee {
+                                amount: total.amount + fee.amount,
+                                ..total
+                            },
+                        );
                         effects.push(Effect::Notify(GameNotification::ProposalMade {
                             id: game_id,
                             group_ids,
@@ -736,10 +917,13 @@ impl OffChainPhase {
                             initial_validation_program_hash: ivp_hash,
                             initial_state,
                             game_type: resolved_game_type,
+                            operator_fee,
+                            proposal: Box::new(wire.start.clone()),
                         }));
                     }
                 }
-                BatchAction::AcceptProposal(game_id) => {
+                BatchAction::AcceptProposal(game_id)
+                | BatchAction::AcceptProposalWithPayout(game_id, _) => {
                     let amount = {
                         let ch = self.channel_state()?;
                         let proposal = ch.find_proposal(game_id).ok_or_else(|| {
@@ -748,7 +932,11 @@ impl OffChainPhase {
                         proposal.my_contribution.clone() + proposal.their_contribution.clone()
                     };
                     let ch = self.channel_state_mut()?;
-                    ch.apply_received_accept_proposal(game_id)?;
+                    if let BatchAction::AcceptProposalWithPayout(_, payout) = action {
+                        ch.apply_received_accept_proposal_with_payout(game_id, payout)?;
+                    } else {
+                        ch.apply_received_accept_proposal(game_id)?;
+                    }
                     effects.push(Effect::Notify(GameNotification::ProposalAccepted {
                         id: *game_id,
                         amount,
@@ -765,8 +953,13 @@ impl OffChainPhase {
                 BatchAction::Move(game_id, game_move) => {
                     let move_result = {
                         let ch = self.channel_state_mut()?;
+                        let handler = ch
+                            .find_live_game(game_id)
+                            .and_then(|g| g.their_turn_handler());
+                        spectated.push((*game_id, game_move.clone(), handler));
                         ch.apply_received_move(env, game_id, game_move)?
                     };
+                    self.game_message_bytes.remove(game_id);
                     let finished = {
                         let ch = self.channel_state()?;
                         ch.is_game_finished(game_id)?
@@ -799,6 +992,9 @@ impl OffChainPhase {
                 }
                 BatchAction::AcceptSettlement(game_id, _peer_amount) => {
                     let ch = self.channel_state_mut()?;
+                    if let Some(result) = ch.game_result(game_id) {
+                        effects.push(Effect::GameResult(Box::new(result)));
+                    }
                     let (our_reward, _game_finished) =
                         ch.apply_received_accept_settlement(game_id)?;
                     effects.push(Effect::Notify(GameNotification::game_settled(
@@ -818,7 +1014,9 @@ impl OffChainPhase {
         let has_new_game = actions.iter().any(|a| {
             matches!(
                 a,
-                BatchAction::ProposeGroup(_) | BatchAction::AcceptProposal(_)
+                BatchAction::ProposeGroup(_)
+                    | BatchAction::AcceptProposal(_)
+                    | BatchAction::AcceptProposalWithPayout(..)
             )
         });
         if has_new_game {
@@ -961,6 +1159,17 @@ impl OffChainPhase {
             ch.verify_received_batch_signatures(env, signatures)?
         };
 
+        if !spectated.is_empty() {
+            let ch = self.channel_state()?;
+            effects.extend(spectator_move_effects(
+                ch,
+                spectated,
+                ch.latest_received_unroll_conditions()?,
+                &signatures.unroll_preempt_half_sig,
+                false,
+            ));
+        }
+
         {
             let ch = self.channel_state()?;
             let state_num = ch.state_number();
@@ -1044,14 +1253,20 @@ impl OffChainPhase {
                     if let Some(true) = game_is_my_turn {
                         match ch.send_move_no_finalize(env, &game_id, &readable_move, new_entropy) {
                             Ok(move_result) => {
+                                self.game_message_bytes.remove(&game_id);
                                 batch_actions
                                     .push(BatchAction::Move(game_id, move_result.game_move));
                             }
-                            Err(Error::GameMoveRejected { tag, message }) => {
+                            Err(Error::GameMoveRejected {
+                                tag,
+                                message,
+                                trace,
+                            }) => {
                                 effects.push(Effect::Notify(GameNotification::MoveRejected {
                                     id: game_id,
                                     tag: String::from_utf8_lossy(&tag).into_owned(),
                                     message: String::from_utf8_lossy(&message).into_owned(),
+                                    trace: trace.map(|t| t.to_string()),
                                 }));
                             }
                             Err(error) => return Err(error),
@@ -1079,6 +1294,9 @@ impl OffChainPhase {
                         let ch = self.channel_state_mut()?;
                         ch.send_accept_settlement_no_finalize(&game_id)?
                     };
+                    if let Some(result) = self.channel_state()?.game_result(&game_id) {
+                        effects.push(Effect::GameResult(Box::new(result)));
+                    }
                     batch_actions.push(BatchAction::AcceptSettlement(game_id, amount));
                 }
                 GameAction::QueuedProposalGroup(my_games, their_wire) => {
@@ -1094,10 +1312,10 @@ impl OffChainPhase {
                         self.channel_state = saved_channel;
                         return Err(error);
                     }
-                    batch_actions.push(BatchAction::ProposeGroup(their_wire));
+                    batch_actions.push(BatchAction::ProposeGroup(*their_wire));
                 }
                 GameAction::QueuedAcceptProposal(game_id) => {
-                    {
+                    let payout = {
                         let ch = self.channel_state_mut()?;
                         let Some(proposal) = ch.find_proposal(&game_id) else {
                             return Err(Error::StrErr(format!(
@@ -1127,13 +1345,18 @@ impl OffChainPhase {
                             batch_actions.push(BatchAction::CancelProposal(game_id));
                             continue;
                         }
+                        let payout = proposal.referee.my_signed_payout();
                         ch.send_accept_proposal(&game_id)?;
                         effects.push(Effect::Notify(GameNotification::ProposalAccepted {
                             id: game_id,
                             amount,
                         }));
-                    }
-                    batch_actions.push(BatchAction::AcceptProposal(game_id));
+                        payout
+                    };
+                    batch_actions.push(match payout {
+                        Some(payout) => BatchAction::AcceptProposalWithPayout(game_id, payout),
+                        None => BatchAction::AcceptProposal(game_id),
+                    });
                 }
                 GameAction::QueuedCancelProposal(game_id) => {
                     {
@@ -1233,6 +1456,24 @@ impl OffChainPhase {
             ch.update_cached_unroll_state(env)?
         };
 
+        let spectated: Vec<_> = batch_actions
+            .iter()
+            .filter_map(|action| match action {
+                BatchAction::Move(game_id, details) => Some((*game_id, details.clone(), None)),
+                _ => None,
+            })
+            .collect();
+        if !spectated.is_empty() {
+            let ch = self.channel_state()?;
+            effects.extend(spectator_move_effects(
+                ch,
+                spectated,
+                ch.latest_sent_unroll_conditions()?,
+                &sigs.unroll_preempt_half_sig,
+                true,
+            ));
+        }
+
         {
             let ch = self.channel_state()?;
             effects.push(Effect::Log(make_send_log(
@@ -1266,14 +1507,14 @@ impl OffChainPhase {
         env: &mut ChannelEnv<'_>,
         msg: Vec<u8>,
     ) -> Result<Vec<Effect>, Error> {
-        let incoming_result = if msg.len() > Self::MAX_MESSAGE_SIZE {
+        let incoming_result = if msg.len() > self.max_message_size() {
             Err(Error::StrErr(format!(
                 "message too large: {} bytes (max {})",
                 msg.len(),
-                Self::MAX_MESSAGE_SIZE,
+                self.max_message_size(),
             )))
         } else {
-            let msg_envelope: PeerMessage = bencodex::from_slice(&msg).into_gen()?;
+            let msg_envelope = PeerMessage::decode(&msg)?;
             self.incoming_messages.push_back(Rc::new(msg_envelope));
             self.process_queued_message(env)
         };
@@ -1288,10 +1529,17 @@ impl OffChainPhase {
         }
     }
 
+    /// Process the next queued peer message, with all the CLVM it runs
+    /// drawing from one [`PeerLimits::max_message_clvm_cost`] budget.
     pub fn process_queued_message(
         &mut self,
         env: &mut ChannelEnv<'_>,
     ) -> Result<Vec<Effect>, Error> {
+        let budget = self.peer_limits.max_message_clvm_cost;
+        with_clvm_cost_budget(budget, || self.process_next_message(env))
+    }
+
+    fn process_next_message(&mut self, env: &mut ChannelEnv<'_>) -> Result<Vec<Effect>, Error> {
         let mut effects = Vec::new();
         let msg_envelope = if let Some(msg) = self.incoming_messages.pop_front() {
             msg
@@ -1308,6 +1556,10 @@ impl OffChainPhase {
                 PeerMessage::RequestPotato(_) => {
                     return Ok(effects);
                 }
+                PeerMessage::GameResultSignature(..) | PeerMessage::StopMatch(_) => {
+                    effects.extend(self.pass_on_channel_state_message(env, msg_envelope)?);
+                    return Ok(effects);
+                }
                 _ => {
                     return Err(Error::StrErr(format!(
                         "expected CleanShutdownComplete, got {msg_envelope:?}"
@@ -1400,6 +1652,24 @@ impl OffChainPhase {
         Ok((false, vec![]))
     }
 
+    /// Dry run of [`Self::go_on_chain`] against a copy of the channel state:
+    /// the spends it would lead to if the opponent went silent, with their
+    /// costs.  Nothing is submitted; see [`go_on_chain_preview`].
+    pub fn preview_go_on_chain(
+        &self,
+        env: &mut ChannelEnv<'_>,
+    ) -> Result<go_on_chain_preview::GoOnChainPreview, Error> {
+        let saved = self.last_channel_coin_spend_info.as_ref().ok_or_else(|| {
+            Error::StrErr("preview_go_on_chain: no channel coin spend info cached".to_string())
+        })?;
+        go_on_chain_preview::preview_go_on_chain(
+            env,
+            self.channel_state()?,
+            saved,
+            &self.unroll_timeout,
+        )
+    }
+
     /// Submit transactions to move the channel on-chain.  Normal blockchain
     /// monitoring will detect the channel coin spend and route through
     /// `handle_channel_coin_spent`, the same path used when the opponent
@@ -1546,12 +1816,20 @@ impl FromLocalUI for OffChainPhase {
             ));
         }
 
-        let factory_games = self.factory_games(env, start)?;
+        let (factory_games, origin) = self.factory_games(env, start)?;
         if factory_games.is_empty() {
             return Err(Error::StrErr(
                 "propose_games: factory returned empty proposal group".to_string(),
             ));
         }
+        let fees = self.game_fees(start, &factory_games)?;
+        let payout = start
+            .payout
+            .as_ref()
+            .map(|terms| {
+                SignedPayout::sign(&self.private_keys.my_referee_private_key, terms.clone())
+            })
+            .transpose()?;
 
         let mut all_ids = Vec::with_capacity(factory_games.len());
         for _ in &factory_games {
@@ -1565,7 +1843,13 @@ impl FromLocalUI for OffChainPhase {
         let my_games: Vec<Rc<GameStartInfo>> = factory_games
             .iter()
             .zip(&all_ids)
-            .map(|(game, id)| Rc::new(game.game_start(id, &start.timeout, true)))
+            .zip(fees)
+            .map(|((game, id), fee)| {
+                let mut game_start = game.game_start(id, &start.timeout, true, fee);
+                game_start.my_payout = start.payout.clone();
+                game_start.origin = Some(origin.clone());
+                Rc::new(game_start)
+            })
             .collect();
         let members = factory_games
             .iter()
@@ -1585,11 +1869,12 @@ impl FromLocalUI for OffChainPhase {
             .collect();
         self.push_action(GameAction::QueuedProposalGroup(
             my_games,
-            WireProposalGroup {
+            Box::new(WireProposalGroup {
                 start: start.clone(),
                 members,
                 group_id,
-            },
+                payout_signature: payout.map(|payout| payout.signature),
+            }),
         ));
 
         let (_has_potato, effect) = self.send_potato_request_if_needed()?;
@@ -1657,6 +1942,26 @@ impl FromLocalUI for OffChainPhase {
         Ok(all_effects)
     }
 
+    fn accept_proposal_with_payout(
+        &mut self,
+        env: &mut ChannelEnv<'_>,
+        game_id: &GameID,
+        payout: &PayoutTerms,
+    ) -> Result<Vec<Effect>, Error> {
+        if !self.peer_supports_batch_action("AcceptProposalWithPayout") {
+            return Err(Error::StrErr(
+                "peer does not support accepting with a payout".to_string(),
+            ));
+        }
+        {
+            let ch = self.channel_state_mut()?;
+            for id in ch.group_member_ids(game_id)? {
+                ch.set_proposal_payout(&id, payout.clone())?;
+            }
+        }
+        <Self as FromLocalUI>::accept_proposal(self, env, game_id)
+    }
+
     fn cancel_proposal(
         &mut self,
         _env: &mut ChannelEnv<'_>,
@@ -1820,6 +2125,10 @@ impl PeerLifecyclePhase for OffChainPhase {
     fn handshake_finished(&self) -> bool {
         OffChainPhase::handshake_finished(self)
     }
+    #[cfg(feature = "hot-reload")]
+    fn replace_game_factory(&mut self, game_type: &GameType, factory: GameFactory) -> bool {
+        crate::hot_reload::replace_factory(&mut self.game_types, game_type, factory)
+    }
     fn propose_games(
         &mut self,
         env: &mut ChannelEnv<'_>,
@@ -1834,6 +2143,14 @@ impl PeerLifecyclePhase for OffChainPhase {
     ) -> Result<Vec<Effect>, Error> {
         <Self as FromLocalUI>::accept_proposal(self, env, game_id)
     }
+    fn accept_proposal_with_payout(
+        &mut self,
+        env: &mut ChannelEnv<'_>,
+        game_id: &GameID,
+        payout: &PayoutTerms,
+    ) -> Result<Vec<Effect>, Error> {
+        <Self as FromLocalUI>::accept_proposal_with_payout(self, env, game_id, payout)
+    }
     fn cancel_proposal(
         &mut self,
         env: &mut ChannelEnv<'_>,
@@ -1885,6 +2202,9 @@ impl PeerLifecyclePhase for OffChainPhase {
     fn channel_state(&self) -> Result<&ChannelState, Error> {
         OffChainPhase::channel_state(self)
     }
+    fn awaiting_potato(&self) -> bool {
+        matches!(self.have_potato, PotatoState::Requested)
+    }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -1918,9 +2238,12 @@ mod atomic_group_tests {
                 game_type: GameType(b"test".to_vec()),
                 timeout: Timeout::new(15),
                 parameters: Program::from_bytes(&[0x80]),
+                fee: None,
+                payout: None,
             },
             members,
             group_id,
+            payout_signature: None,
         }
     }
 
@@ -186,7 +186,8 @@ impl OnChainPhase {
         &mut self,
         _game_id: &GameID,
         notification: GameNotification,
-    ) -> Option<Effect> {
+    ) -> Vec<Effect> {
+        let mut distribution = None;
         if let GameNotification::GameSettled {
             id,
             coin_id: Some(coin),
@@ -200,9 +201,61 @@ impl OnChainPhase {
                 self.game_payout_coins
                     .retain(|(known_id, _)| known_id != id);
                 self.game_payout_coins.push((*id, coin.clone()));
+                distribution = Some(match self.payout_distribution(id, coin) {
+                    Ok(bundle) => bundle.map(|bundle| Effect::SpendTransaction(bundle, None)),
+                    Err(e) => Some(Effect::Log(format!(
+                        "[payout] cannot distribute {}: {e:?}",
+                        format_coin(coin),
+                    ))),
+                });
             }
         }
-        Some(Effect::Notify(notification))
+        std::iter::once(Effect::Notify(notification))
+            .chain(distribution.flatten())
+            .collect()
+    }
+
+    /// The game `game_id` whether still live or settled off-chain before the
+    /// unroll.
+    fn find_game(&self, game_id: &GameID) -> Option<&LiveGame> {
+        self.live_games
+            .iter()
+            .chain(self.pending_settlements.iter())
+            .find(|g| g.game_id == *game_id)
+    }
+
+    /// Where the referee of `game_id` pays us and the opponent: the channel
+    /// reward puzzle hashes unless a player named a payout for that game.
+    fn game_reward_puzzle_hashes(&self, game_id: &GameID) -> (PuzzleHash, PuzzleHash) {
+        match self.find_game(game_id) {
+            Some(game) => (game.reward_puzzle_hash(), game.their_reward_puzzle_hash()),
+            None => (
+                self.reward_puzzle_hash.clone(),
+                self.their_reward_puzzle_hash.clone(),
+            ),
+        }
+    }
+
+    /// The spend that splits our payout coin from `game_id` between its
+    /// destinations, when our payout for that game is split.
+    fn payout_distribution(
+        &self,
+        game_id: &GameID,
+        coin: &CoinString,
+    ) -> Result<Option<SpendBundle>, Error> {
+        let Some(terms) = self.find_game(game_id).and_then(|game| game.my_payout()) else {
+            return Ok(None);
+        };
+        if coin.to_parts().map(|(_, ph, _)| ph) != Some(terms.puzzle_hash()?) {
+            return Ok(None);
+        }
+        let mut allocator = AllocEncoder::new();
+        Ok(terms
+            .distribution_spend(&mut allocator, coin)?
+            .map(|spend| SpendBundle {
+                name: Some("payout split".to_string()),
+                spends: vec![spend],
+            }))
     }
 
     // --- Getters (duplicated from ChannelState) ---
@@ -258,18 +311,18 @@ impl OnChainPhase {
     }
 
     /// Should we auto-accept this game on our turn?
-    /// True when: mover_share == game_amount (claim — we get 100%)
+    /// True when: mover_share == game_amount (claim — we get 100%, less any
+    ///            operator fee)
     ///        or: game_over && mover_share > 0 (terminal clean end).
     /// When both are true simultaneously, this still returns true (treated as terminal).
     fn should_auto_settle(&self, game_id: &GameID, is_my_turn: bool) -> Result<bool, Error> {
         if !is_my_turn {
             return Ok(false);
         }
-        let our_share = self.get_game_our_current_share(game_id)?;
-        let game_amt = self.get_game_amount(game_id)?;
-        if our_share == game_amt {
+        if self.get_game_their_current_share(game_id)? == Amount::default() {
             return Ok(true);
         }
+        let our_share = self.get_game_our_current_share(game_id)?;
         let game_over = self.is_game_over(game_id)?;
         Ok(game_over && our_share > Amount::default())
     }
@@ -308,6 +361,20 @@ impl OnChainPhase {
         )))
     }
 
+    fn get_game_their_current_share(&self, game_id: &GameID) -> Result<Amount, Error> {
+        self.live_games
+            .iter()
+            .chain(self.pending_settlements.iter())
+            .find(|g| g.game_id == *game_id)
+            .ok_or_else(|| {
+                Error::StrErr(format!(
+                    "get_game_their_current_share: game {:?} not found",
+                    game_id
+                ))
+            })?
+            .get_their_current_share()
+    }
+
     pub fn enable_cheating_for_game(
         &mut self,
         game_id: &GameID,
@@ -343,6 +410,15 @@ impl OnChainPhase {
             .map(|g| g.our_turn)
     }
 
+    /// Whether the game's coin is waiting on our move, with no move of ours
+    /// already in flight on it.
+    #[cfg(all(test, feature = "sim-tests"))]
+    pub(crate) fn awaiting_our_move(&self, game_id: &GameID) -> bool {
+        self.game_map.iter().any(|(coin, g)| {
+            g.game_id == *game_id && g.our_turn && !self.pending_moves.contains_key(coin)
+        })
+    }
+
     pub fn remove_game_coin_info(&mut self, coin_id: &CoinString) -> Option<(GameID, bool)> {
         self.game_map
             .remove(coin_id)
@@ -413,7 +489,7 @@ impl OnChainPhase {
         conditions: &[CoinCondition],
         parsed_solution: &ParsedRefereeSolution,
     ) -> Result<CoinSpentInformation, Error> {
-        let reward_puzzle_hash = self.reward_puzzle_hash.clone();
+        let (reward_puzzle_hash, _) = self.game_reward_puzzle_hashes(game_id);
 
         let (ph, amt) = if let Some((ph, amt)) = conditions
             .iter()
@@ -556,7 +632,7 @@ impl OnChainPhase {
             &tx.puzzle.to_program(),
             &tx.solution.p(),
         )?;
-        let reward_ph = self.reward_puzzle_hash.clone();
+        let (reward_ph, _) = self.game_reward_puzzle_hashes(game_id);
         let pays_us = conditions.iter().any(|c| {
             matches!(c, CoinCondition::CreateCoin(ph, amt) if *ph == reward_ph && *amt > Amount::default())
         });
@@ -746,7 +822,7 @@ impl OnChainPhase {
                     return Ok((effects, None));
                 }
 
-                if create_ph == self.their_reward_puzzle_hash {
+                if create_ph == self.game_reward_puzzle_hashes(&old_def.game_id).1 {
                     // Our pending move never landed and the opponent claimed the
                     // timeout against this coin (the spend pays their reward
                     // puzzle hash).  When the game was already terminal this is a
@@ -757,12 +833,10 @@ impl OnChainPhase {
                     } else {
                         SettlementOutcome::AttemptToMoveFailed
                     };
-                    if let Some(eff) = self.try_emit_terminal(
+                    effects.extend(self.try_emit_terminal(
                         &game_id,
                         GameNotification::game_settled(game_id, outcome, Amount::default(), None),
-                    ) {
-                        effects.push(eff);
-                    }
+                    ));
                     effects.extend(self.process_queued_action(env)?);
                     return Ok((effects, None));
                 }
@@ -790,7 +864,8 @@ impl OnChainPhase {
         if old_definition.pending_slash_amount.is_some() {
             let conditions =
                 CoinCondition::from_puzzle_and_solution(env.allocator, puzzle, solution)?;
-            let reward_ph = self.reward_puzzle_hash.clone();
+            let (reward_ph, their_reward_ph) =
+                self.game_reward_puzzle_hashes(&old_definition.game_id);
             let parent_coin_id = coin_id.to_coin_id();
             let reward_coin = conditions.iter().find_map(|c| {
                 if let CoinCondition::CreateCoin(ph, amt) = c {
@@ -807,7 +882,6 @@ impl OnChainPhase {
                     format_coin(rc),
                 )));
             }
-            let their_reward_ph = self.their_reward_puzzle_hash.clone();
             let opponent_claimed = conditions.iter().any(|c| {
                 matches!(c, CoinCondition::CreateCoin(ph, amt) if *ph == their_reward_ph && *amt > Amount::default())
             });
@@ -818,31 +892,23 @@ impl OnChainPhase {
             // were trying to slash).  That is the confirmed "opponent
             // successfully cheated" outcome -- it rides the opponent's spend,
             // never one we generate ourselves -- and it may still pay us our
-            // `cheating_move_mover_share` consolation.  Otherwise a coin to our
-            // reward puzzle hash is our slash confirming.
+            // `cheating_move_mover_share` consolation (less any operator fee),
+            // which we read off its conditions.  Otherwise a coin to our reward
+            // puzzle hash is our slash confirming.
             let notification = if opponent_claimed {
                 effects.push(Effect::Log(format!(
                     "[timeout-on-chain] {}",
                     format_coin(coin_id),
                 )));
-                let our_reward = old_definition
-                    .cheating_move_mover_share
-                    .clone()
+                let our_reward = reward_coin
+                    .as_ref()
+                    .and_then(|coin| coin.amount())
                     .unwrap_or_default();
-                let our_reward_coin = if our_reward > Amount::default() {
-                    Some(CoinString::from_parts(
-                        &parent_coin_id,
-                        &reward_ph,
-                        &our_reward,
-                    ))
-                } else {
-                    None
-                };
                 GameNotification::game_settled(
                     old_definition.game_id,
                     SettlementOutcome::OpponentCheated,
                     our_reward,
-                    our_reward_coin,
+                    reward_coin,
                 )
             } else if let Some(reward_coin) = reward_coin {
                 GameNotification::game_settled(
@@ -865,9 +931,7 @@ impl OnChainPhase {
                     other_params: None,
                 }
             };
-            if let Some(eff) = self.try_emit_terminal(&old_definition.game_id, notification) {
-                effects.push(eff);
-            }
+            effects.extend(self.try_emit_terminal(&old_definition.game_id, notification));
             effects.extend(self.process_queued_action(env)?);
             return Ok((effects, None));
         }
@@ -875,8 +939,8 @@ impl OnChainPhase {
         let conditions = CoinCondition::from_puzzle_and_solution(env.allocator, puzzle, solution)?;
 
         if old_definition.timeout_claim_armed {
-            let reward_ph = self.reward_puzzle_hash.clone();
-            let their_reward_ph = self.their_reward_puzzle_hash.clone();
+            let (reward_ph, their_reward_ph) =
+                self.game_reward_puzzle_hashes(&old_definition.game_id);
 
             let our_reward_coin = conditions.iter().find_map(|c| match c {
                 CoinCondition::CreateCoin(ph, amt) if *ph == reward_ph => {
@@ -897,7 +961,7 @@ impl OnChainPhase {
                     } else {
                         SettlementOutcome::WeAccepted
                     };
-                    if let Some(eff) = self.try_emit_terminal(
+                    effects.extend(self.try_emit_terminal(
                         &old_definition.game_id,
                         GameNotification::game_settled(
                             old_definition.game_id,
@@ -905,9 +969,7 @@ impl OnChainPhase {
                             amt,
                             reward_coin,
                         ),
-                    ) {
-                        effects.push(eff);
-                    }
+                    ));
                 }
             } else {
                 let is_timeout = conditions.iter().any(
@@ -921,7 +983,7 @@ impl OnChainPhase {
                         } else {
                             SettlementOutcome::WeAccepted
                         };
-                        if let Some(eff) = self.try_emit_terminal(
+                        effects.extend(self.try_emit_terminal(
                             &old_definition.game_id,
                             GameNotification::game_settled(
                                 old_definition.game_id,
@@ -929,9 +991,7 @@ impl OnChainPhase {
                                 Amount::default(),
                                 None,
                             ),
-                        ) {
-                            effects.push(eff);
-                        }
+                        ));
                     }
                 } else {
                     let created = conditions.iter().find_map(|c| match c {
@@ -1007,7 +1067,7 @@ impl OnChainPhase {
                 "[game-error] {} {reason}",
                 format_coin(coin_id),
             )));
-            if let Some(eff) = self.try_emit_terminal(
+            effects.extend(self.try_emit_terminal(
                 &old_definition.game_id,
                 GameNotification::GameStatus {
                     id: old_definition.game_id,
@@ -1017,9 +1077,7 @@ impl OnChainPhase {
                     reason: Some(reason),
                     other_params: None,
                 },
-            ) {
-                effects.push(eff);
-            }
+            ));
             effects.extend(self.process_queued_action(env)?);
             return Ok((effects, None));
         };
@@ -1037,7 +1095,7 @@ impl OnChainPhase {
                     "[game-error] {} {reason}",
                     format_coin(coin_id),
                 )));
-                if let Some(eff) = self.try_emit_terminal(
+                effects.extend(self.try_emit_terminal(
                     &old_definition.game_id,
                     GameNotification::GameStatus {
                         id: old_definition.game_id,
@@ -1047,9 +1105,7 @@ impl OnChainPhase {
                         reason: Some(reason),
                         other_params: None,
                     },
-                ) {
-                    effects.push(eff);
-                }
+                ));
                 effects.extend(self.process_queued_action(env)?);
                 return Ok((effects, None));
             }
@@ -1205,9 +1261,7 @@ impl OnChainPhase {
                             my_reward_coin_string.clone(),
                         )
                     };
-                    if let Some(eff) = self.try_emit_terminal(&old_definition.game_id, notif) {
-                        effects.push(eff);
-                    }
+                    effects.extend(self.try_emit_terminal(&old_definition.game_id, notif));
                 }
                 unblock_queue = true;
             }
@@ -1401,7 +1455,7 @@ impl OnChainPhase {
                                 submitting_timeout_claim: None,
                             }),
                         }));
-                        if let Some(eff) = self.try_emit_terminal(
+                        effects.extend(self.try_emit_terminal(
                             &old_definition.game_id,
                             GameNotification::game_settled(
                                 old_definition.game_id,
@@ -1409,9 +1463,7 @@ impl OnChainPhase {
                                 Amount::default(),
                                 None,
                             ),
-                        ) {
-                            effects.push(eff);
-                        }
+                        ));
                     }
                 }
             }
@@ -1441,9 +1493,7 @@ impl OnChainPhase {
                         amt,
                         reward_coin,
                     );
-                    if let Some(eff) = self.try_emit_terminal(&old_definition.game_id, notif) {
-                        effects.push(eff);
-                    }
+                    effects.extend(self.try_emit_terminal(&old_definition.game_id, notif));
                 }
                 unblock_queue = true;
             }
@@ -1,4 +1,6 @@
 use crate::common::types::{GameType, Program, Timeout};
+use crate::operator_fee::OperatorFee;
+use crate::payout::PayoutTerms;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -6,4 +8,11 @@ pub struct GameProposal {
     pub game_type: GameType,
     pub timeout: Timeout,
     pub parameters: Program,
+    /// Operator fee taken from each game in the group at settlement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<OperatorFee>,
+    /// Where the proposer's share of each game in the group is paid, instead
+    /// of their channel reward puzzle hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout: Option<PayoutTerms>,
 }
@@ -1,7 +1,5 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 
-use clvm_traits::{clvm_curried_args, ToClvm};
-use clvm_utils::CurriedProgram;
 use clvmr::{run_program, NodePtr};
 
 use serde::{Deserialize, Serialize};
@@ -9,8 +7,8 @@ use serde::{Deserialize, Serialize};
 use crate::channel_state::types::{ChannelCoinSpendInfo, ChannelEnv, ReadableMove};
 use crate::channel_state::ChannelState;
 use crate::common::types::{
-    chia_dialect, Aggsig, Amount, CoinCondition, CoinSpend, CoinString, Error, GameID, Hash,
-    IntoErr, Program, ProgramRef, PuzzleHash, Spend, SpendBundle, Timeout, MAX_BLOCK_COST_CLVM,
+    chia_dialect, Amount, CoinCondition, CoinSpend, CoinString, Error, GameID, Hash, IntoErr,
+    Program, ProgramRef, PuzzleHash, SpendBundle, Timeout, MAX_BLOCK_COST_CLVM,
 };
 use crate::game_session::PeerLifecyclePhase;
 use crate::session_phases::effects::{
@@ -511,36 +509,12 @@ impl SpendChannelCoinPhase {
         unroll_coin: &CoinString,
         on_chain_state: usize,
     ) -> Result<SpendBundle, Error> {
-        let player_ch = self.base.channel_state()?;
-        let matching_unroll = player_ch.get_historical_unroll_for_state(on_chain_state)?;
-        let curried_unroll_puzzle = CurriedProgram {
-            program: env.unroll_puzzle.clone(),
-            args: clvm_curried_args!(
-                player_ch.get_aggregate_unroll_public_key(),
-                matching_unroll.state_number,
-                matching_unroll.conditions_hash.clone()
-            ),
-        }
-        .to_clvm(env.allocator)
-        .into_gen()?;
-        let curried_unroll_program =
-            crate::common::types::Puzzle::from_nodeptr(env.allocator, curried_unroll_puzzle)?;
-        let timeout_solution = matching_unroll
-            .timeout_conditions
-            .to_nodeptr(env.allocator)?;
-        let timeout_solution_program = Program::from_nodeptr(env.allocator, timeout_solution)?;
-
-        Ok(SpendBundle {
-            name: Some("create unroll (timeout)".to_string()),
-            spends: vec![CoinSpend {
-                bundle: Spend {
-                    puzzle: curried_unroll_program,
-                    solution: timeout_solution_program.into(),
-                    signature: Aggsig::default(),
-                },
-                coin: unroll_coin.clone(),
-            }],
-        })
+        crate::session_phases::handler_base::build_unroll_timeout_bundle(
+            env,
+            self.base.channel_state()?,
+            unroll_coin,
+            on_chain_state,
+        )
     }
 
     fn handle_channel_coin_spent(
@@ -797,6 +771,7 @@ impl SpendChannelCoinPhase {
                         if *amt > Amount::default()
                             && *ph != reward_puzzle_hash
                             && *ph != their_reward_puzzle_hash
+                            && !player_ch.is_settlement_output_puzzle_hash(ph)
                         {
                             return Some((ph.clone(), amt.clone()));
                         }
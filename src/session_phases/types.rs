@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 use std::rc::Rc;
 
+use bencodex::DecoderConfig;
 use serde::{Deserialize, Serialize};
 
 use crate::channel_state::game_start_info::GameStartInfo;
@@ -8,9 +9,10 @@ use crate::channel_state::types::{
     ChannelEnv, ChannelPrivateKeys, ReadableMove, StateUpdateSignatures,
 };
 use crate::common::types::{
-    Aggsig, Amount, CoinSpend, Error, GameID, GameType, Hash, Program, ProgramRef, PuzzleHash,
-    Timeout,
+    Aggsig, Amount, CoinSpend, Error, GameID, GameType, Hash, IntoErr, Program, ProgramRef,
+    PuzzleHash, Timeout, MAX_BLOCK_COST_CLVM,
 };
+use crate::payout::{PayoutTerms, SignedPayout};
 use crate::referee::types::GameMoveDetails;
 use crate::session_phases::effects::Effect;
 use crate::session_phases::handshake::{
@@ -22,7 +24,9 @@ pub use crate::session_phases::wallet_traits::{
     ChannelFundingWallet, SpendWalletReceiver, WalletSpendInterface,
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Encoded with stable field tags (see `bencodex::schema`) so peers on
+/// adjacent builds can add fields without breaking each other.
+#[derive(bencodex::Schema, Debug, Clone)]
 pub struct WireGameSpec {
     pub game_id: GameID,
     pub amount: Amount,
@@ -42,6 +46,10 @@ pub struct WireProposalGroup {
     pub members: Vec<WireGameSpec>,
     /// Always the first member's game id (including singleton groups).
     pub group_id: GameID,
+    /// The proposer's signature on their payout puzzle hash, present exactly
+    /// when `start.payout` is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_signature: Option<Aggsig>,
 }
 
 pub trait ToLocalUI {
@@ -68,6 +76,14 @@ pub trait FromLocalUI {
         game_id: &GameID,
     ) -> Result<Vec<Effect>, Error>;
 
+    /// Accept, paying our share of every game in the group as `payout` says.
+    fn accept_proposal_with_payout(
+        &mut self,
+        env: &mut ChannelEnv<'_>,
+        game_id: &GameID,
+        payout: &PayoutTerms,
+    ) -> Result<Vec<Effect>, Error>;
+
     fn cancel_proposal(
         &mut self,
         env: &mut ChannelEnv<'_>,
@@ -91,17 +107,49 @@ pub trait FromLocalUI {
     fn shut_down(&mut self, env: &mut ChannelEnv<'_>) -> Result<Vec<Effect>, Error>;
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub enum BatchAction {
+/// Declares [`BatchAction`] along with `BatchAction::KINDS`, so the list of
+/// kinds advertised in the handshake can't drift from the enum.
+macro_rules! batch_actions {
+    ($($(#[$attr:meta])* $kind:ident($($field:ty),*),)*) => {
+        #[derive(Serialize, Deserialize, Debug, Clone)]
+        pub enum BatchAction {
+            $($(#[$attr])* $kind($($field),*),)*
+        }
+
+        impl BatchAction {
+            /// Every kind this build can send and receive, advertised in the
+            /// handshake.
+            pub const KINDS: &'static [&'static str] = &[$(stringify!($kind)),*];
+
+            pub fn kind(&self) -> &'static str {
+                match self {
+                    $(BatchAction::$kind(..) => stringify!($kind),)*
+                }
+            }
+        }
+    };
+}
+
+batch_actions! {
     ProposeGroup(WireProposalGroup),
     AcceptProposal(GameID),
+    /// Accept, paying the acceptor's share as their signed payout says.
+    AcceptProposalWithPayout(GameID, SignedPayout),
     CancelProposal(GameID),
     Move(GameID, GameMoveDetails),
     #[serde(rename = "AcceptSettlement")]
     AcceptSettlement(GameID, Amount),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl BatchAction {
+    /// Kinds added after capability negotiation.  A peer that lacks one of
+    /// these can still open a channel; we just never send it that kind.
+    pub const OPTIONAL_KINDS: &'static [&'static str] = &["AcceptProposalWithPayout"];
+}
+
+/// Encoded with serde's external tagging; the derive also describes it so the
+/// handshake can check both builds agree on each variant's layout.
+#[derive(bencodex::Schema, Debug, Clone)]
 pub enum PeerMessage {
     HandshakeA(HandshakePayloadB),
     HandshakeB(HandshakePayloadB),
@@ -118,9 +166,26 @@ pub enum PeerMessage {
     CleanShutdownComplete(CoinSpend),
     RequestPotato(()),
     Message(GameID, Vec<u8>),
+    /// Our signature on the result of a game that settled off-chain.  Only
+    /// sent to a peer that advertised `FEATURE_RESULT_ATTESTATION`.
+    GameResultSignature(GameID, Aggsig),
+    /// The sender's match stops at the next hand boundary.  Only sent to a
+    /// peer that advertised `FEATURE_MATCH_STOP`.
+    StopMatch(()),
 }
 
+/// Decode limits for bytes received from the peer.  Our serializer always
+/// emits canonical bencodex, so anything else is rejected outright; the
+/// structural limits sit well above what an honest `PeerMessage` needs.
+pub const PEER_MESSAGE_DECODE_LIMITS: DecoderConfig =
+    DecoderConfig::bounded(64, 65_536, 10 * 1024 * 1024, 32 * 1024 * 1024).strict();
+
 impl PeerMessage {
+    /// Decode an untrusted peer message under [`PEER_MESSAGE_DECODE_LIMITS`].
+    pub fn decode(msg: &[u8]) -> Result<PeerMessage, Error> {
+        bencodex::from_slice_with(msg, &PEER_MESSAGE_DECODE_LIMITS).into_gen()
+    }
+
     pub fn is_handshake(&self) -> bool {
         matches!(
             self,
@@ -152,7 +217,7 @@ pub enum GameAction {
     AcceptSettlement(GameID),
     CleanShutdown,
     SendPotato,
-    QueuedProposalGroup(Vec<Rc<GameStartInfo>>, WireProposalGroup),
+    QueuedProposalGroup(Vec<Rc<GameStartInfo>>, Box<WireProposalGroup>),
     QueuedAcceptProposal(GameID),
     QueuedCancelProposal(GameID),
     QueuedCancelProposalSilently(GameID),
@@ -190,6 +255,34 @@ pub struct GameFactory {
     pub program: Option<Rc<Program>>,
 }
 
+/// Resource budgets enforced on what the peer sends us.  Going over any of
+/// them is treated like any other protocol violation: the session goes on
+/// chain.  The defaults are far above anything honest play produces.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerLimits {
+    /// Actions in a single potato pass (`PeerMessage::Batch`).
+    pub max_batch_actions: usize,
+    /// Game-message bytes accepted for one game between two of its moves.
+    pub max_game_message_bytes: usize,
+    /// CLVM cost all handlers, validators and parsers may spend on one
+    /// inbound message.
+    pub max_message_clvm_cost: u64,
+    /// Messages waiting in the inbound queue for `flush_and_collect`.
+    pub max_queued_messages: usize,
+}
+
+impl Default for PeerLimits {
+    fn default() -> Self {
+        PeerLimits {
+            max_batch_actions: 1024,
+            max_game_message_bytes: 64 * 1024,
+            max_message_clvm_cost: MAX_BLOCK_COST_CLVM,
+            max_queued_messages: 1024,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct OffChainPhaseInit {
     pub have_potato: bool,
@@ -200,4 +293,6 @@ pub struct OffChainPhaseInit {
     pub channel_timeout: Timeout,
     pub unroll_timeout: Timeout,
     pub reward_puzzle_hash: PuzzleHash,
+    #[serde(default)]
+    pub peer_limits: PeerLimits,
 }
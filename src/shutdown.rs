@@ -12,6 +12,8 @@ fn compute_shutdown_conditions(
     our_share: &Amount,
     their_reward_ph: &PuzzleHash,
     their_share: &Amount,
+    operator_fees: &[(PuzzleHash, Amount)],
+    routed_payouts: &[(PuzzleHash, Amount)],
 ) -> Result<NodePtr, Error> {
     let mut v = Vec::new();
     if *our_share != Amount::default() {
@@ -28,6 +30,13 @@ fn compute_shutdown_conditions(
                 .into_gen()?,
         ));
     }
+    for (ph, amount) in operator_fees.iter().chain(routed_payouts.iter()) {
+        v.push(Node(
+            (CREATE_COIN, (ph, (amount, ())))
+                .to_clvm(allocator)
+                .into_gen()?,
+        ));
+    }
 
     v.to_clvm(allocator).into_gen()
 }
@@ -47,5 +56,7 @@ pub fn get_conditions_with_channel_state(
         &our_share,
         &their_reward_ph,
         &their_share,
+        ch.operator_fees(),
+        ch.routed_payouts(),
     )
 }
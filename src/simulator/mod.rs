@@ -6,7 +6,6 @@ pub mod tests;
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 
-use chia_consensus::consensus_constants::ConsensusConstants;
 use chia_consensus::flags::MEMPOOL_MODE;
 use chia_consensus::spendbundle_validation::{
     get_flags_for_height_and_constants, validate_clvm_and_signature,
@@ -14,6 +13,7 @@ use chia_consensus::spendbundle_validation::{
 use chia_consensus::validation_error::ErrorCode;
 use clvm_traits::{ClvmEncoder, ToClvm};
 
+use crate::common::consensus::{consensus_constants, to_protocol_spend_bundle};
 use crate::common::constants::AGG_SIG_ME_ADDITIONAL_DATA;
 use crate::common::constants::CREATE_COIN;
 use crate::common::standard_coin::{
@@ -21,18 +21,23 @@ use crate::common::standard_coin::{
     ChiaIdentity,
 };
 use crate::common::types::{
-    Aggsig, AllocEncoder, Amount, CoinID, CoinSpend, CoinString, Error, GetCoinStringParts, Hash,
-    IntoErr, Node, Program, Puzzle, PuzzleHash, Sha256Input, Sha256tree, Spend, ToQuotedProgram,
-    MAX_BLOCK_COST_CLVM,
+    AllocEncoder, Amount, CoinID, CoinSpend, CoinString, Error, GetCoinStringParts, Hash, IntoErr,
+    Node, Program, Puzzle, PuzzleHash, Sha256Input, Sha256tree, Spend, ToQuotedProgram,
 };
 
 use crate::utils::map_m;
 
+#[cfg(test)]
+use crate::simulator::tests::channel_manager_sim::test_funs as channel_manager_sim_tests;
+#[cfg(test)]
+use crate::simulator::tests::playout_equivalence::test_funs as playout_equivalence_tests;
 #[cfg(test)]
 use crate::simulator::tests::session_phases_sim::test_funs as session_phases_sim_tests;
 #[cfg(test)]
 use crate::simulator::tests::simulator_tests::test_funs as simulator_tests;
 #[cfg(test)]
+use crate::simulator::tests::wallet_sim::test_funs as wallet_sim_tests;
+#[cfg(test)]
 use crate::test_support::calpoker_sim::test_funs as calpoker_tests;
 #[cfg(test)]
 use crate::test_support::krunk_sim::test_funs as krunk_sim_tests;
@@ -52,6 +57,8 @@ use crate::tests::calpoker_validation::test_funs as calpoker_validation_tests;
 #[cfg(test)]
 use crate::tests::channel_state::test_funs as channel_handler_tests;
 #[cfg(test)]
+use crate::tests::channel_state_model::test_funs as channel_state_model_tests;
+#[cfg(test)]
 use crate::tests::chialisp::test_funs as chialisp_tests;
 #[cfg(test)]
 use crate::tests::dict_tree_lookup::test_funs as dict_tree_lookup_tests;
@@ -60,6 +67,8 @@ use crate::tests::krunk_handlers::test_funs as krunk_handler_tests;
 #[cfg(test)]
 use crate::tests::krunk_validation::test_funs as krunk_validation_tests;
 #[cfg(test)]
+use crate::tests::potato_verify_bench::test_funs as potato_verify_bench_tests;
+#[cfg(test)]
 use crate::tests::referee_conditions::test_funs as referee_conditions_tests;
 #[cfg(test)]
 use crate::tests::spacepoker_handlers::test_funs as spacepoker_handler_tests;
@@ -77,12 +86,16 @@ pub struct IncludeTransactionResult {
 
 const POOL_REWARD_AMOUNT: u64 = 1_750_000_000_000;
 const FARMER_REWARD_AMOUNT: u64 = 250_000_000_000;
+/// Seconds the simulated clock advances per farmed block, unless changed with
+/// `set_block_seconds`.
+pub const DEFAULT_BLOCK_SECONDS: u64 = 20;
 
 #[derive(Debug, Clone)]
 struct CoinRecord {
     coin: CoinString,
     puzzle_hash: PuzzleHash,
     created_height: u32,
+    created_timestamp: u64,
     spent_height: Option<u32>,
     coinbase: bool,
 }
@@ -100,6 +113,10 @@ struct SimulatorState {
     spent_puzzle_solutions: HashMap<CoinID, (Program, Program)>,
     confirmed_spend_fingerprints: HashSet<Hash>,
     height: u32,
+    /// Timestamp of the tip block.  Relative seconds locks are checked
+    /// against it, as a full node checks them against the previous block.
+    timestamp: u64,
+    block_seconds: u64,
 }
 
 pub struct Simulator {
@@ -115,6 +132,8 @@ impl SimulatorState {
             spent_puzzle_solutions: HashMap::new(),
             confirmed_spend_fingerprints: HashSet::new(),
             height: 0,
+            timestamp: 0,
+            block_seconds: DEFAULT_BLOCK_SECONDS,
         }
     }
 
@@ -133,6 +152,7 @@ impl SimulatorState {
                 coin,
                 puzzle_hash: puzzle_hash.clone(),
                 created_height: self.height,
+                created_timestamp: self.timestamp,
                 spent_height: None,
                 coinbase,
             },
@@ -154,6 +174,9 @@ impl SimulatorState {
         } else {
             self.height + 1
         };
+        if next_height > 0 {
+            self.timestamp += self.block_seconds;
+        }
 
         let pool_parent = Self::reward_parent_id(b"pool_reward", next_height);
         let farmer_parent = Self::reward_parent_id(b"farmer_reward", next_height);
@@ -188,6 +211,7 @@ impl SimulatorState {
                         coin,
                         puzzle_hash: ph.clone(),
                         created_height: next_height,
+                        created_timestamp: self.timestamp,
                         spent_height: None,
                         coinbase: false,
                     },
@@ -209,112 +233,6 @@ impl Default for Simulator {
     }
 }
 
-fn make_sim_consensus_constants() -> ConsensusConstants {
-    let agg_sig_data = chia_protocol::Bytes32::from(AGG_SIG_ME_ADDITIONAL_DATA);
-    let zero32 = chia_protocol::Bytes32::from([0u8; 32]);
-    ConsensusConstants {
-        slot_blocks_target: 32,
-        min_blocks_per_challenge_block: 16,
-        max_sub_slot_blocks: 128,
-        num_sps_sub_slot: 64,
-        sub_slot_iters_starting: 1 << 27,
-        difficulty_constant_factor: 1 << 67,
-        difficulty_starting: 7,
-        difficulty_change_max_factor: 3,
-        sub_epoch_blocks: 384,
-        epoch_blocks: 4608,
-        significant_bits: 8,
-        discriminant_size_bits: 1024,
-        number_zero_bits_plot_filter_v1: 9,
-        number_zero_bits_plot_filter_v2: 9,
-        min_plot_size_v1: 32,
-        max_plot_size_v1: 59,
-        plot_size_v2: 30,
-        sub_slot_time_target: 600,
-        num_sp_intervals_extra: 3,
-        max_future_time2: 120,
-        number_of_timestamps: 11,
-        genesis_challenge: zero32,
-        agg_sig_me_additional_data: agg_sig_data,
-        agg_sig_parent_additional_data: zero32,
-        agg_sig_puzzle_additional_data: zero32,
-        agg_sig_amount_additional_data: zero32,
-        agg_sig_puzzle_amount_additional_data: zero32,
-        agg_sig_parent_amount_additional_data: zero32,
-        agg_sig_parent_puzzle_additional_data: zero32,
-        genesis_pre_farm_pool_puzzle_hash: zero32,
-        genesis_pre_farm_farmer_puzzle_hash: zero32,
-        max_vdf_witness_size: 8,
-        mempool_block_buffer: 10,
-        max_coin_amount: u64::MAX,
-        max_block_cost_clvm: MAX_BLOCK_COST_CLVM,
-        cost_per_byte: 12000,
-        weight_proof_threshold: 2,
-        weight_proof_recent_blocks: 1000,
-        max_block_count_per_requests: 32,
-        blocks_cache_size: 4608 + 128 * 4,
-        max_generator_ref_list_size: 512,
-        pool_sub_slot_iters: 37_600_000_000,
-        hard_fork_height: 0,
-        hard_fork2_height: 0,
-        soft_fork8_height: 0,
-        plot_v1_phase_out_epoch_bits: 0,
-        plot_filter_128_height: u32::MAX,
-        plot_filter_64_height: u32::MAX,
-        plot_filter_32_height: u32::MAX,
-        min_plot_strength: 0,
-        max_plot_strength: 0,
-        plot_filter_v2_first_adjustment_height: 0,
-        plot_filter_v2_second_adjustment_height: 0,
-        plot_filter_v2_third_adjustment_height: 0,
-    }
-}
-
-fn to_protocol_spend_bundle(
-    allocator: &mut AllocEncoder,
-    txs: &[CoinSpend],
-) -> Result<chia_protocol::SpendBundle, Error> {
-    let mut protocol_spends = Vec::with_capacity(txs.len());
-    let mut agg_sig = Aggsig::default();
-
-    for (i, tx) in txs.iter().enumerate() {
-        let (parent, ph, amount) = tx.coin.get_coin_string_parts()?;
-        let parent_arr: [u8; 32] = parent
-            .bytes()
-            .try_into()
-            .map_err(|_| Error::StrErr("bad parent".into()))?;
-        let ph_arr: [u8; 32] = ph
-            .bytes()
-            .try_into()
-            .map_err(|_| Error::StrErr("bad ph".into()))?;
-        let coin = chia_protocol::Coin {
-            parent_coin_info: chia_protocol::Bytes32::from(parent_arr),
-            puzzle_hash: chia_protocol::Bytes32::from(ph_arr),
-            amount: amount.to_u64(),
-        };
-        let puzzle_bytes = tx.bundle.puzzle.to_program().bytes().to_vec();
-        let solution_node = tx.bundle.solution.to_clvm(allocator).into_gen()?;
-        let solution_bytes = Program::from_nodeptr(allocator, solution_node)?
-            .bytes()
-            .to_vec();
-        protocol_spends.push(chia_protocol::CoinSpend {
-            coin,
-            puzzle_reveal: chia_protocol::Bytes::from(puzzle_bytes).into(),
-            solution: chia_protocol::Bytes::from(solution_bytes).into(),
-        });
-        if i == 0 {
-            agg_sig = tx.bundle.signature.clone();
-        } else {
-            agg_sig += tx.bundle.signature.clone();
-        }
-    }
-
-    Ok(chia_protocol::SpendBundle {
-        coin_spends: protocol_spends,
-        aggregated_signature: agg_sig.to_bls(),
-    })
-}
-
 fn format_validation_error(code: ErrorCode) -> String {
     let desc = match code {
         ErrorCode::DuplicateOutput => {
@@ -381,6 +299,21 @@ impl Simulator {
         self.state.borrow_mut().farm_block_inner(puzzle_hash);
     }
 
+    /// Set how far the clock moves with each farmed block.
+    pub fn set_block_seconds(&self, seconds: u64) {
+        self.state.borrow_mut().block_seconds = seconds;
+    }
+
+    /// Move the clock forward without farming.  Spends are checked against the
+    /// new time, and the next block is stamped after it.
+    pub fn advance_time(&self, seconds: u64) {
+        self.state.borrow_mut().timestamp += seconds;
+    }
+
+    pub fn get_current_timestamp(&self) -> u64 {
+        self.state.borrow().timestamp
+    }
+
     /// Roll the chain back by `depth` blocks, modelling a reorganization:
     /// coins minted above the new tip are un-created, spends recorded above the
     /// new tip are reverted, and the peak height is lowered.  Rolled-back
@@ -505,12 +438,16 @@ impl Simulator {
     pub fn get_watched_coin_snapshot(
         &self,
         coin_id: &CoinID,
-    ) -> Option<(CoinString, u32, Option<u32>)> {
+    ) -> Option<(CoinString, u32, u64, Option<u32>)> {
         let state = self.state.borrow();
-        state
-            .coins
-            .get(coin_id)
-            .map(|r| (r.coin.clone(), r.created_height, r.spent_height))
+        state.coins.get(coin_id).map(|r| {
+            (
+                r.coin.clone(),
+                r.created_height,
+                r.created_timestamp,
+                r.spent_height,
+            )
+        })
     }
 
     /// Records for the full live coin set (unspent, non-coinbase), mirroring
@@ -525,6 +462,7 @@ impl Simulator {
             .map(|r| crate::transaction_manager::CoinStateRecord {
                 coin: r.coin.clone(),
                 created_height: Some(r.created_height as u64),
+                created_timestamp: Some(r.created_timestamp),
                 spent_height: None,
             })
             .collect()
@@ -546,6 +484,7 @@ impl Simulator {
                 crate::transaction_manager::CoinStateRecord {
                     coin: coin.clone(),
                     created_height: rec.map(|r| r.created_height as u64),
+                    created_timestamp: rec.map(|r| r.created_timestamp),
                     spent_height: rec.and_then(|r| r.spent_height.map(|h| h as u64)),
                 }
             })
@@ -579,7 +518,7 @@ impl Simulator {
         // This catches: CLVM errors, bad puzzle hashes, duplicate outputs,
         // invalid conditions, bad aggregate signature, overspend, etc.
         let protocol_bundle = to_protocol_spend_bundle(allocator, txs)?;
-        let constants = make_sim_consensus_constants();
+        let constants = consensus_constants();
         let flags = get_flags_for_height_and_constants(state.height, &constants) | MEMPOOL_MODE;
         let validated = match validate_clvm_and_signature(
             &protocol_bundle,
@@ -611,30 +550,31 @@ impl Simulator {
         for (i, tx) in txs.iter().enumerate() {
             let coin_id = tx.coin.to_coin_id();
 
-            let record_created_height = if let Some(record) = state.coins.get(&coin_id) {
-                if record.spent_height.is_some() {
+            let (record_created_height, record_created_timestamp) =
+                if let Some(record) = state.coins.get(&coin_id) {
+                    if record.spent_height.is_some() {
+                        if self.strict {
+                            panic!("Strict mode: Coin already spent: {coin_id:?}");
+                        }
+                        return Ok(IncludeTransactionResult {
+                            code: 3,
+                            e: Some(5),
+                            diagnostic: format!("Coin already spent: {:?}", coin_id),
+                        });
+                    }
+                    (record.created_height, record.created_timestamp)
+                } else if ephemeral_coins.remove(&coin_id).is_some() {
+                    (state.height, state.timestamp)
+                } else {
                     if self.strict {
-                        panic!("Strict mode: Coin already spent: {coin_id:?}");
+                        panic!("Strict mode: Coin not found: {coin_id:?}");
                     }
                     return Ok(IncludeTransactionResult {
                         code: 3,
                         e: Some(5),
-                        diagnostic: format!("Coin already spent: {:?}", coin_id),
+                        diagnostic: format!("Coin not found: {:?}", coin_id),
                     });
-                }
-                record.created_height
-            } else if ephemeral_coins.remove(&coin_id).is_some() {
-                state.height
-            } else {
-                if self.strict {
-                    panic!("Strict mode: Coin not found: {coin_id:?}");
-                }
-                return Ok(IncludeTransactionResult {
-                    code: 3,
-                    e: Some(5),
-                    diagnostic: format!("Coin not found: {:?}", coin_id),
-                });
-            };
+                };
 
             // Use validated conditions for state tracking. Index must match the
             // input spend; a gap would skip CREATE_COIN / relative locks while
@@ -690,6 +630,56 @@ impl Simulator {
                 }
             }
 
+            // Relative seconds locks compare the coin's creation timestamp with
+            // the tip's.
+            let elapsed_seconds = state.timestamp.saturating_sub(record_created_timestamp);
+            if let Some(required) = spend_conds.seconds_relative {
+                if elapsed_seconds < required {
+                    if self.strict {
+                        panic!(
+                            "Strict mode: ASSERT_SECONDS_RELATIVE violated: \
+                             coin {:?} created at {}, now {}, elapsed {}s but required {}s",
+                            coin_id,
+                            record_created_timestamp,
+                            state.timestamp,
+                            elapsed_seconds,
+                            required,
+                        );
+                    }
+                    return Ok(IncludeTransactionResult {
+                        code: 3,
+                        e: Some(8),
+                        diagnostic: format!(
+                            "Relative seconds lock not satisfied: elapsed {}s < required {}s",
+                            elapsed_seconds, required,
+                        ),
+                    });
+                }
+            }
+            if let Some(before) = spend_conds.before_seconds_relative {
+                if elapsed_seconds >= before {
+                    if self.strict {
+                        panic!(
+                            "Strict mode: ASSERT_BEFORE_SECONDS_RELATIVE violated: \
+                             coin {:?} created at {}, now {}, elapsed {}s of {}s",
+                            coin_id,
+                            record_created_timestamp,
+                            state.timestamp,
+                            elapsed_seconds,
+                            before,
+                        );
+                    }
+                    return Ok(IncludeTransactionResult {
+                        code: 3,
+                        e: Some(8),
+                        diagnostic: format!(
+                            "ASSERT_BEFORE_SECONDS_RELATIVE not satisfied: elapsed {}s >= {}s",
+                            elapsed_seconds, before,
+                        ),
+                    });
+                }
+            }
+
             removals.push(coin_id.clone());
             let puzzle_program: Program = (*tx.bundle.puzzle.to_program()).clone();
             let solution_node = tx.bundle.solution.to_clvm(allocator).into_gen()?;
@@ -1020,6 +1010,8 @@ pub fn run_simulation_tests() {
         calpoker_handler_tests(),
         krunk_handler_tests(),
         channel_handler_tests(),
+        channel_state_model_tests(),
+        potato_verify_bench_tests(),
         referee_conditions_tests(),
         debug_game_tests(),
         peer_harness_tests(),
@@ -1028,6 +1020,9 @@ pub fn run_simulation_tests() {
         spacepoker_tests(),
         krunk_sim_tests(),
         session_phases_sim_tests(),
+        channel_manager_sim_tests(),
+        wallet_sim_tests(),
+        playout_equivalence_tests(),
     ];
 
     let from_filter: Option<String> = std::env::var("SIM_TEST_FROM")
@@ -654,7 +654,7 @@ impl GameRunner {
     }
 
     fn coin_record_json(&self, coin_id: &CoinID) -> Option<Value> {
-        let (coin, created_height, spent_height) =
+        let (coin, created_height, timestamp, spent_height) =
             self.simulator.get_watched_coin_snapshot(coin_id)?;
         let (parent, puzzle_hash, amount) = coin.to_parts()?;
         Some(serde_json::json!({
@@ -667,7 +667,7 @@ impl GameRunner {
             "spentBlockIndex": spent_height.unwrap_or(0),
             "spent": spent_height.is_some(),
             "coinbase": false,
-            "timestamp": 0,
+            "timestamp": timestamp,
         }))
     }
 
@@ -0,0 +1,481 @@
+//! A `ChannelManager` host playing two peers at once from one wallet.
+
+use std::collections::BTreeSet;
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::channel_manager::{ChannelFundingSource, ChannelId, ChannelManager};
+use crate::channel_state::types::ChannelPrivateKeys;
+use crate::common::constants::SINGLETON_LAUNCHER_HASH;
+use crate::common::standard_coin::ChiaIdentity;
+use crate::common::types::{
+    AllocEncoder, Amount, CoinString, Error, PrivateKey, PuzzleHash, SpendBundle, Timeout,
+};
+use crate::game_session::{GameSession, GameSessionConfig, LivenessPolicy};
+use crate::session_phases::effects::{ChannelStatus, GameNotification, GameSessionEvent};
+use crate::session_phases::game_collection;
+use crate::session_phases::handshake::CoinSpendRequest;
+use crate::session_phases::types::PeerLimits;
+use crate::simulator::tests::session_phases_sim::build_wallet_bundle_for_request;
+use crate::simulator::Simulator;
+use crate::transaction_manager::TransactionManager;
+
+const CONTRIBUTION: u64 = 100;
+
+/// The hub's wallet: its coins in the simulator, recording every coin it
+/// hands out.
+struct SimWallet<'a> {
+    simulator: &'a Simulator,
+    identity: &'a ChiaIdentity,
+    handed_out: Vec<CoinString>,
+}
+
+impl SimWallet<'_> {
+    fn unreserved_coin(
+        &self,
+        amount: &Amount,
+        reserved: &BTreeSet<CoinString>,
+    ) -> Result<CoinString, Error> {
+        self.simulator
+            .get_my_coins(&self.identity.puzzle_hash)?
+            .into_iter()
+            .filter(|coin| {
+                !reserved.contains(coin)
+                    && coin.to_parts().is_some_and(|(_, _, amt)| amt >= *amount)
+            })
+            .min_by_key(|coin| coin.to_parts().map(|(_, _, amt)| amt.to_u64()))
+            .ok_or_else(|| Error::StrErr("wallet has no free coin".to_string()))
+    }
+}
+
+impl ChannelFundingSource for SimWallet<'_> {
+    fn launcher_parent(
+        &mut self,
+        amount: &Amount,
+        reserved: &BTreeSet<CoinString>,
+    ) -> Result<CoinString, Error> {
+        let coin = self.unreserved_coin(amount, reserved)?;
+        self.handed_out.push(coin.clone());
+        Ok(coin)
+    }
+
+    fn fund(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        request: &CoinSpendRequest,
+        reserved: &BTreeSet<CoinString>,
+    ) -> Result<SpendBundle, Error> {
+        let bundle = build_wallet_bundle_for_request(
+            allocator,
+            self.simulator,
+            self.identity,
+            request,
+            reserved,
+        )?;
+        self.handed_out
+            .extend(bundle.spends.iter().map(|spend| spend.coin.clone()));
+        Ok(bundle)
+    }
+}
+
+pub(crate) fn session_config(
+    allocator: &mut AllocEncoder,
+    identity: &ChiaIdentity,
+    have_potato: bool,
+    contribution: u64,
+) -> GameSessionConfig {
+    GameSessionConfig {
+        game_types: game_collection(allocator),
+        have_potato,
+        identity: identity.clone(),
+        my_contribution: Amount::new(contribution),
+        their_contribution: Amount::new(contribution),
+        channel_timeout: Timeout::new(5),
+        unroll_timeout: Timeout::new(15),
+        reward_puzzle_hash: identity.puzzle_hash.clone(),
+        liveness: LivenessPolicy::default(),
+        peer_limits: PeerLimits::default(),
+    }
+}
+
+pub(crate) fn balance(simulator: &Simulator, identity: &ChiaIdentity) -> u64 {
+    simulator
+        .get_my_coins(&identity.puzzle_hash)
+        .expect("coins")
+        .iter()
+        .filter_map(|coin| coin.to_parts().map(|(_, _, amt)| amt.to_u64()))
+        .sum()
+}
+
+pub(crate) fn push(simulator: &Simulator, allocator: &mut AllocEncoder, bundle: &SpendBundle) {
+    let included = simulator
+        .push_transactions(allocator, &bundle.spends)
+        .expect("push");
+    let duplicate = included.code == 3 && matches!(included.e, Some(5) | Some(20));
+    assert!(
+        included.code == 1 || duplicate,
+        "tx {:?} rejected: {included:?}",
+        bundle.name
+    );
+}
+
+pub(crate) fn is_clean(notifications: &[GameNotification]) -> bool {
+    notifications.iter().any(|n| {
+        matches!(
+            n,
+            GameNotification::ChannelStatus {
+                state: ChannelStatus::ResolvedClean,
+                ..
+            }
+        )
+    })
+}
+
+pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
+    let mut res: Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> = Vec::new();
+
+    res.push(("test_channel_manager_two_concurrent_channels", &|| {
+        let mut allocator = AllocEncoder::new();
+        let mut rng = ChaCha8Rng::from_seed([7; 32]);
+        let mut identity = |allocator: &mut AllocEncoder| {
+            let pk: PrivateKey = rng.random();
+            ChiaIdentity::new(allocator, pk).expect("identity")
+        };
+        let hub = identity(&mut allocator);
+        let peer_ids = [identity(&mut allocator), identity(&mut allocator)];
+        let neutral = identity(&mut allocator);
+
+        let simulator = Simulator::new_strict();
+        simulator.farm_block(&hub.puzzle_hash);
+        for peer in peer_ids.iter() {
+            simulator.farm_block(&peer.puzzle_hash);
+        }
+        simulator.farm_block(&neutral.puzzle_hash);
+        let hub_start = balance(&simulator, &hub);
+
+        // The hub initiates against the first peer and responds to the
+        // second.  No blocks are farmed until the hub has answered all three
+        // funding requests (launcher parent and funding for the first
+        // channel, funding for the second), so both coins are reserved at
+        // once and the wallet must keep them apart.
+        let mut manager = ChannelManager::new();
+        let mut channels: Vec<ChannelId> = Vec::new();
+        let mut peers: Vec<TransactionManager<GameSession>> = Vec::new();
+        for (index, peer) in peer_ids.iter().enumerate() {
+            let hub_initiates = index == 0;
+            let hub_keys: ChannelPrivateKeys = rng.random();
+            let peer_keys: ChannelPrivateKeys = rng.random();
+            let hub_config = session_config(&mut allocator, &hub, hub_initiates, CONTRIBUTION);
+            channels.push(manager.open_channel(hub_config, hub_keys));
+            let peer_config = session_config(&mut allocator, peer, !hub_initiates, CONTRIBUTION);
+            peers.push(TransactionManager::new(GameSession::new_with_keys(
+                peer_config,
+                peer_keys,
+            )));
+        }
+        manager
+            .channel_mut(channels[0])
+            .expect("channel")
+            .start_handshake(&mut allocator)
+            .expect("start");
+        peers[1].start_handshake(&mut allocator).expect("start");
+
+        let mut wallet = SimWallet {
+            simulator: &simulator,
+            identity: &hub,
+            handed_out: Vec::new(),
+        };
+        let mut hub_notifications: [Vec<GameNotification>; 2] = [Vec::new(), Vec::new()];
+        let mut peer_notifications: [Vec<GameNotification>; 2] = [Vec::new(), Vec::new()];
+        let mut most_reserved = 0;
+        let mut shutting_down = false;
+
+        for _ in 0..200 {
+            if wallet.handed_out.len() >= 3 {
+                simulator.farm_block(&neutral.puzzle_hash);
+            }
+            let height = simulator.get_current_height() as u64;
+            let records = simulator.get_all_coin_states();
+            manager
+                .report_coin_states(&mut allocator, height, &records)
+                .expect("report");
+            for peer in peers.iter_mut() {
+                peer.report_coin_states(&mut allocator, height, &records)
+                    .expect("report");
+            }
+
+            let drain = manager
+                .flush_and_collect(&mut allocator, Some(&mut wallet))
+                .expect("flush");
+            assert!(drain.errors.is_empty(), "{:?}", drain.errors);
+            most_reserved = most_reserved.max(manager.reserved_coins().len());
+            for (id, event) in drain.events {
+                let index = channels
+                    .iter()
+                    .position(|c| *c == id)
+                    .expect("known channel");
+                match event {
+                    GameSessionEvent::OutboundMessage(msg) => {
+                        peers[index].deliver_message(&msg).expect("deliver");
+                    }
+                    GameSessionEvent::Notification(n) => hub_notifications[index].push(n),
+                    GameSessionEvent::CoinSolutionRequest(coin) => {
+                        let ps = simulator
+                            .get_puzzle_and_solution(&coin.to_coin_id())
+                            .expect("puzzle and solution");
+                        manager
+                            .channel_mut(id)
+                            .expect("channel")
+                            .report_puzzle_and_solution(
+                                &mut allocator,
+                                &coin,
+                                ps.as_ref().map(|(p, s)| (p, s)),
+                            )
+                            .expect("report");
+                    }
+                    GameSessionEvent::NeedLauncherCoin | GameSessionEvent::NeedCoinSpend(_) => {
+                        panic!("the manager's wallet should answer funding requests");
+                    }
+                    _ => {}
+                }
+            }
+            let submissions = manager.drain_submissions();
+            assert!(submissions.errors.is_empty(), "{:?}", submissions.errors);
+            for (bundle_channel, bundle) in submissions.bundles {
+                assert!(channels.contains(&bundle_channel));
+                push(&simulator, &mut allocator, &bundle);
+            }
+
+            for (index, peer) in peers.iter_mut().enumerate() {
+                let drain = peer.flush_and_collect(&mut allocator).expect("flush");
+                for event in drain.events {
+                    match event {
+                        GameSessionEvent::OutboundMessage(msg) => {
+                            manager
+                                .deliver_message(channels[index], &msg)
+                                .expect("deliver");
+                        }
+                        GameSessionEvent::Notification(n) => peer_notifications[index].push(n),
+                        GameSessionEvent::NeedLauncherCoin => {
+                            let parent = simulator
+                                .get_my_coins(&peer_ids[index].puzzle_hash)
+                                .expect("coins")[0]
+                                .clone();
+                            let launcher = CoinString::from_parts(
+                                &parent.to_coin_id(),
+                                &PuzzleHash::from_bytes(SINGLETON_LAUNCHER_HASH),
+                                &Amount::default(),
+                            );
+                            peer.provide_launcher_coin(&mut allocator, launcher)
+                                .expect("launcher");
+                        }
+                        GameSessionEvent::NeedCoinSpend(request) => {
+                            let bundle = build_wallet_bundle_for_request(
+                                &mut allocator,
+                                &simulator,
+                                &peer_ids[index],
+                                &request,
+                                &BTreeSet::new(),
+                            )
+                            .expect("fund");
+                            peer.provide_coin_spend_bundle(&mut allocator, bundle)
+                                .expect("fund");
+                        }
+                        GameSessionEvent::CoinSolutionRequest(coin) => {
+                            let ps = simulator
+                                .get_puzzle_and_solution(&coin.to_coin_id())
+                                .expect("puzzle and solution");
+                            peer.report_puzzle_and_solution(
+                                &mut allocator,
+                                &coin,
+                                ps.as_ref().map(|(p, s)| (p, s)),
+                            )
+                            .expect("report");
+                        }
+                        _ => {}
+                    }
+                }
+                for bundle in peer.drain_submissions().expect("drain") {
+                    push(&simulator, &mut allocator, &bundle);
+                }
+            }
+
+            let all_open = channels.iter().all(|id| {
+                manager
+                    .channel(*id)
+                    .is_some_and(|c| c.channel_established())
+            }) && peers.iter().all(|p| p.channel_established());
+            if all_open && !shutting_down {
+                shutting_down = true;
+                assert!(manager.reserved_coins().is_empty());
+                for id in channels.iter() {
+                    manager
+                        .channel_mut(*id)
+                        .expect("channel")
+                        .shut_down(&mut allocator)
+                        .expect("shut down");
+                }
+            }
+            if shutting_down
+                && hub_notifications.iter().all(|n| is_clean(n))
+                && peer_notifications.iter().all(|n| is_clean(n))
+            {
+                break;
+            }
+        }
+
+        assert!(shutting_down, "both channels should open");
+        for index in 0..2 {
+            assert!(
+                is_clean(&hub_notifications[index]) && is_clean(&peer_notifications[index]),
+                "channel {index} should shut down cleanly, hub saw {:?}, peer saw {:?}",
+                hub_notifications[index],
+                peer_notifications[index]
+            );
+        }
+        assert_eq!(
+            most_reserved, 2,
+            "both handshakes should have held a coin at the same time: {:?}",
+            wallet.handed_out
+        );
+        let handed_out: BTreeSet<&CoinString> = wallet.handed_out.iter().collect();
+        assert_eq!(
+            handed_out.len(),
+            2,
+            "each channel should be funded from its own coin: {:?}",
+            wallet.handed_out
+        );
+        simulator.farm_block(&neutral.puzzle_hash);
+        assert_eq!(balance(&simulator, &hub), hub_start);
+    }));
+
+    res.push((
+        "test_channel_manager_failure_spares_other_channels",
+        &|| {
+            let mut allocator = AllocEncoder::new();
+            let mut rng = ChaCha8Rng::from_seed([8; 32]);
+            let mut identity = |allocator: &mut AllocEncoder| {
+                let pk: PrivateKey = rng.random();
+                ChiaIdentity::new(allocator, pk).expect("identity")
+            };
+            let hub = identity(&mut allocator);
+            let peer_id = identity(&mut allocator);
+
+            // Two channels the hub initiates, each asking for a launcher parent
+            // once its peer answers.  The wallet fails the first request; the
+            // other channel must still get its coin and carry on, and have it
+            // handed back when funding fails too.
+            struct FailFirst {
+                asked: usize,
+                given: Vec<CoinString>,
+                released: Vec<CoinString>,
+            }
+            impl ChannelFundingSource for FailFirst {
+                fn launcher_parent(
+                    &mut self,
+                    amount: &Amount,
+                    _reserved: &BTreeSet<CoinString>,
+                ) -> Result<CoinString, Error> {
+                    self.asked += 1;
+                    if self.asked == 1 {
+                        return Err(Error::StrErr("wallet is locked".to_string()));
+                    }
+                    let coin =
+                        CoinString::from_parts(&Default::default(), &PuzzleHash::default(), amount);
+                    self.given.push(coin.clone());
+                    Ok(coin)
+                }
+
+                fn fund(
+                    &mut self,
+                    _allocator: &mut AllocEncoder,
+                    _request: &CoinSpendRequest,
+                    _reserved: &BTreeSet<CoinString>,
+                ) -> Result<SpendBundle, Error> {
+                    Err(Error::StrErr("not funding in this test".to_string()))
+                }
+
+                fn release(&mut self, coins: &[CoinString]) {
+                    self.released.extend(coins.iter().cloned());
+                }
+            }
+
+            let mut manager = ChannelManager::new();
+            let mut channels: Vec<ChannelId> = Vec::new();
+            let mut peers: Vec<TransactionManager<GameSession>> = Vec::new();
+            for _ in 0..2 {
+                let config = session_config(&mut allocator, &hub, true, CONTRIBUTION);
+                let keys: ChannelPrivateKeys = rng.random();
+                let id = manager.open_channel(config, keys);
+                manager
+                    .channel_mut(id)
+                    .expect("channel")
+                    .start_handshake(&mut allocator)
+                    .expect("start");
+                channels.push(id);
+                let config = session_config(&mut allocator, &peer_id, false, CONTRIBUTION);
+                let keys: ChannelPrivateKeys = rng.random();
+                peers.push(TransactionManager::new(GameSession::new_with_keys(
+                    config, keys,
+                )));
+            }
+
+            let mut wallet = FailFirst {
+                asked: 0,
+                given: Vec::new(),
+                released: Vec::new(),
+            };
+            manager.report_height(&mut allocator, 1).expect("height");
+            let mut errors = Vec::new();
+            let mut sent_after_launcher = BTreeSet::new();
+            for _ in 0..10 {
+                let drain = manager
+                    .flush_and_collect(&mut allocator, Some(&mut wallet))
+                    .expect("flush");
+                errors.extend(drain.errors.iter().map(|(id, _)| *id));
+                for (id, event) in drain.events {
+                    let index = channels.iter().position(|c| *c == id).expect("channel");
+                    if let GameSessionEvent::OutboundMessage(msg) = event {
+                        if wallet.asked > 0 {
+                            sent_after_launcher.insert(id);
+                        }
+                        peers[index].deliver_message(&msg).expect("deliver");
+                    }
+                }
+                for (index, peer) in peers.iter_mut().enumerate() {
+                    for event in peer
+                        .flush_and_collect(&mut allocator)
+                        .expect("flush")
+                        .events
+                    {
+                        if let GameSessionEvent::OutboundMessage(msg) = event {
+                            manager
+                                .deliver_message(channels[index], &msg)
+                                .expect("deliver");
+                        }
+                    }
+                }
+            }
+
+            assert_eq!(wallet.asked, 2, "both channels should ask for a coin");
+            assert_eq!(errors.len(), 1, "only one request failed: {errors:?}");
+            let survivor = channels
+                .iter()
+                .find(|id| !errors.contains(id))
+                .expect("one channel should survive");
+            assert!(
+                sent_after_launcher.contains(survivor),
+                "the other channel should carry on with its handshake"
+            );
+            assert_eq!(wallet.given.len(), 1);
+            assert_eq!(
+                wallet.released, wallet.given,
+                "the survivor's launcher parent should be handed back when funding fails"
+            );
+            assert!(manager.reserved_coins().is_empty());
+        },
+    ));
+
+    res
+}
@@ -1,2 +1,5 @@
+pub mod channel_manager_sim;
+pub mod playout_equivalence;
 pub mod session_phases_sim;
 pub mod simulator_tests;
+pub mod wallet_sim;
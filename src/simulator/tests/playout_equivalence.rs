@@ -0,0 +1,481 @@
+//! Randomized play-outs checking that forcing a game on chain at any move
+//! pays the same split as resolving it off-chain.
+//!
+//! For each registered game a seeded generator picks a random legal move
+//! sequence.  The sequence is first played entirely off-chain.  Then, for
+//! every prefix of it, the run is forked three ways:
+//!
+//! * continue: go on chain and play the remaining moves there.  The reward
+//!   coins must match the shares reported by the off-chain `GameSettled`.
+//! * timeout: go on chain and stop moving.  The split must match an off-chain
+//!   `AcceptSettlement` by the player to move at the same point.
+//! * slash: go on chain and have the player to move cheat with a nil move
+//!   claiming one less than the whole pot, a share no validator accepts.  The
+//!   honest player must collect the whole pot.  If the cheater had nothing at stake
+//!   it concedes instead and the timeout split applies.
+//!
+//! Every production game type in `game_collection` must have an entry in
+//! [`PLAYOUT_GAMES`].
+//!
+//! `SIM_PLAYOUT_SEED` sets the first seed (default 0) and `SIM_PLAYOUT_RUNS`
+//! the number of play-outs per game (default 1).
+
+use std::rc::Rc;
+
+use clvm_traits::{ClvmEncoder, ToClvm};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::channel_state::types::ReadableMove;
+use crate::common::types::{AllocEncoder, Amount, CoinString, Error, GameID, Program};
+use crate::games::krunk_dictionary;
+use crate::session_phases::effects::{GameNotification, SettlementOutcome};
+use crate::session_phases::game_collection::game_collection;
+use crate::simulator::tests::session_phases_sim::{
+    calpoker_test_moves_with_selected_cards, run_calpoker_container_with_action_list,
+    run_krunk_container_with_action_list_with_success_predicate,
+    run_spacepoker_container_with_action_list, GameRunOutcome,
+};
+use crate::test_support::sim_script::{ProposeTrigger, SimScriptAction};
+
+/// The game every play-out proposes and forks.
+const GAME_ID: GameID = GameID(1);
+
+/// Blocks to wait after the last scripted action; runs end early once both
+/// sides are fully resolved.
+const SETTLE_BLOCKS: usize = 100;
+
+/// The hands dealt by the fixed seeds in `calpoker_test_moves_with_selected_cards`.
+const CALPOKER_HANDS: [[usize; 8]; 2] = [
+    [0, 7, 10, 11, 32, 36, 41, 49],
+    [2, 6, 9, 13, 18, 19, 23, 47],
+];
+
+type GameRunner = fn(&mut AllocEncoder, &[SimScriptAction]) -> Result<GameRunOutcome, Error>;
+type MoveGenerator = fn(&mut AllocEncoder, &mut ChaCha8Rng) -> Vec<SimScriptAction>;
+
+struct PlayoutGame {
+    name: &'static str,
+    run: GameRunner,
+    random_moves: MoveGenerator,
+    /// Other games opened by the same proposal, with the player to move in
+    /// each.  They are left untouched and settled off-chain before shutdown;
+    /// on chain they simply time out.
+    companions: &'static [(usize, GameID)],
+}
+
+#[derive(Clone, Debug)]
+struct Settlement {
+    outcome: SettlementOutcome,
+    share: Amount,
+    coin: Option<CoinString>,
+}
+
+fn readable(program: Program) -> ReadableMove {
+    ReadableMove::from_program(Rc::new(program))
+}
+
+fn nil_move() -> ReadableMove {
+    readable(Program::from_hex("80").expect("nil move"))
+}
+
+fn random_calpoker_moves(
+    allocator: &mut AllocEncoder,
+    rng: &mut ChaCha8Rng,
+) -> Vec<SimScriptAction> {
+    let picks: Vec<Vec<usize>> = CALPOKER_HANDS
+        .iter()
+        .map(|hand| {
+            let mut picked: Vec<usize> = hand.choose_multiple(rng, 4).cloned().collect();
+            picked.sort();
+            picked
+        })
+        .collect();
+    calpoker_test_moves_with_selected_cards(allocator, GAME_ID, &picks[0], &picks[1])
+}
+
+/// Random betting within the limits of `run_spacepoker_container_*`: a bet
+/// unit of 10, an ante of one unit, and at most 50 committed per player so
+/// every raise stays inside the validators' half-pot bound.
+fn random_spacepoker_moves(
+    allocator: &mut AllocEncoder,
+    rng: &mut ChaCha8Rng,
+) -> Vec<SimScriptAction> {
+    const BET_UNIT: u64 = 10;
+    const STAKE_CAP: u64 = 50;
+
+    let mut moves = Vec::new();
+    let push = |allocator: &mut AllocEncoder, moves: &mut Vec<SimScriptAction>, raise: u64| {
+        let the_move = if raise == 0 {
+            nil_move()
+        } else {
+            let node = raise.to_clvm(allocator).expect("raise");
+            readable(Program::from_nodeptr(allocator, node).expect("raise program"))
+        };
+        moves.push(SimScriptAction::Move(
+            moves.len() % 2,
+            GAME_ID,
+            the_move,
+            true,
+        ));
+    };
+
+    // Both commits.
+    push(allocator, &mut moves, 0);
+    push(allocator, &mut moves, 0);
+
+    let mut half_pot = BET_UNIT;
+    for _street in 0..4 {
+        let open = if half_pot + BET_UNIT <= STAKE_CAP && rng.random_bool(0.5) {
+            BET_UNIT
+        } else {
+            0
+        };
+        push(allocator, &mut moves, open);
+        let mut last_raise = open;
+        let mut reraises = 0;
+        loop {
+            half_pot += last_raise;
+            if reraises < 2 && half_pot + BET_UNIT <= STAKE_CAP && rng.random_bool(0.3) {
+                push(allocator, &mut moves, BET_UNIT);
+                last_raise = BET_UNIT;
+                reraises += 1;
+            } else {
+                // Nil in mid-round is a call.
+                push(allocator, &mut moves, 0);
+                break;
+            }
+        }
+    }
+
+    // Showdown.
+    push(allocator, &mut moves, 0);
+    moves
+}
+
+fn random_krunk_moves(allocator: &mut AllocEncoder, rng: &mut ChaCha8Rng) -> Vec<SimScriptAction> {
+    let dictionary = krunk_dictionary();
+    let word = |allocator: &mut AllocEncoder, bytes: &[u8]| {
+        let node = allocator
+            .encode_atom(clvm_traits::Atom::Borrowed(bytes))
+            .expect("word atom");
+        readable(Program::from_nodeptr(allocator, node).expect("word program"))
+    };
+
+    let secret = dictionary.choose(rng).expect("dictionary").clone();
+    let mut moves = vec![SimScriptAction::Move(
+        0,
+        GAME_ID,
+        word(allocator, &secret),
+        true,
+    )];
+    for _guess in 0..5 {
+        let guess = if rng.random_bool(0.25) {
+            secret.clone()
+        } else {
+            dictionary.choose(rng).expect("dictionary").clone()
+        };
+        moves.push(SimScriptAction::Move(
+            1,
+            GAME_ID,
+            word(allocator, &guess),
+            true,
+        ));
+        // The picker's clue (or final reveal) is chosen by the handler.
+        moves.push(SimScriptAction::Move(0, GAME_ID, nil_move(), true));
+        if guess == secret {
+            break;
+        }
+    }
+    moves
+}
+
+fn run_krunk(
+    allocator: &mut AllocEncoder,
+    moves: &[SimScriptAction],
+) -> Result<GameRunOutcome, Error> {
+    run_krunk_container_with_action_list_with_success_predicate(allocator, moves, None, None)
+}
+
+fn mover(moves: &[SimScriptAction], k: usize) -> usize {
+    match &moves[k] {
+        SimScriptAction::Move(who, _, _, _) => *who,
+        other => panic!("play-out step {k} is not a move: {other:?}"),
+    }
+}
+
+/// The same move, made once the game coin is waiting on its player.  The
+/// loser's last move is skipped if they already conceded on chain.
+fn on_chain(action: &SimScriptAction) -> SimScriptAction {
+    match action {
+        SimScriptAction::Move(who, id, readable, _) => {
+            SimScriptAction::OnChainMove(*who, *id, readable.clone())
+        }
+        other => other.clone(),
+    }
+}
+
+fn script(moves: &[SimScriptAction], tail: Vec<SimScriptAction>) -> Vec<SimScriptAction> {
+    let mut result = vec![
+        SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+        SimScriptAction::AcceptProposal(1, GAME_ID),
+    ];
+    result.extend(moves.iter().cloned());
+    result.extend(tail);
+    result
+}
+
+fn off_chain_ending(game: &PlayoutGame) -> Vec<SimScriptAction> {
+    let mut tail: Vec<SimScriptAction> = game
+        .companions
+        .iter()
+        .map(|(who, id)| SimScriptAction::AcceptSettlement(*who, *id))
+        .collect();
+    tail.push(SimScriptAction::CleanShutdown(1));
+    tail.push(SimScriptAction::WaitBlocks(SETTLE_BLOCKS, 0));
+    tail
+}
+
+fn settlements(outcome: &GameRunOutcome, label: &str) -> [Settlement; 2] {
+    let settled = |who: usize| {
+        outcome.local_uis[who]
+            .notifications
+            .iter()
+            .rev()
+            .find_map(|n| match n {
+                GameNotification::GameSettled {
+                    id,
+                    outcome,
+                    our_share,
+                    coin_id,
+                    ..
+                } if *id == GAME_ID => Some(Settlement {
+                    outcome: *outcome,
+                    share: our_share.clone(),
+                    coin: coin_id.clone(),
+                }),
+                _ => None,
+            })
+            .unwrap_or_else(|| {
+                panic!(
+                    "{label}: player {who} never settled {GAME_ID:?}: {:?}",
+                    outcome.local_uis[who].notifications
+                )
+            })
+    };
+    [settled(0), settled(1)]
+}
+
+/// Each positive on-chain share must be backed by a reward coin of that
+/// amount sitting at the player's reward puzzle hash.
+fn check_reward_coins(
+    outcome: &GameRunOutcome,
+    settled: &[Settlement; 2],
+    label: &str,
+    failures: &mut Vec<String>,
+) {
+    for (who, s) in settled.iter().enumerate() {
+        if s.share == Amount::default() {
+            continue;
+        }
+        let Some(coin) = &s.coin else {
+            failures.push(format!(
+                "{label}: player {who} settled {s:?} without a reward coin"
+            ));
+            continue;
+        };
+        let amount = coin.to_parts().map(|(_, _, amt)| amt);
+        if amount.as_ref() != Some(&s.share) {
+            failures.push(format!(
+                "{label}: player {who} reward coin amount {amount:?} != share {:?}",
+                s.share
+            ));
+        }
+        let owned = outcome
+            .simulator
+            .get_my_coins(&outcome.identities[who].puzzle_hash)
+            .map(|coins| coins.contains(coin))
+            .unwrap_or(false);
+        if !owned {
+            failures.push(format!(
+                "{label}: player {who} reward coin {coin:?} is not on chain"
+            ));
+        }
+    }
+}
+
+fn compare_shares(
+    label: &str,
+    expected: &[Settlement; 2],
+    got: &[Settlement; 2],
+    failures: &mut Vec<String>,
+) {
+    for who in 0..2 {
+        if expected[who].share != got[who].share {
+            failures.push(format!(
+                "{label}: player {who} expected {:?} ({:?}) but got {:?} ({:?})",
+                expected[who].share, expected[who].outcome, got[who].share, got[who].outcome
+            ));
+        }
+    }
+}
+
+fn check_playout(game: &PlayoutGame, seed: u64) -> Vec<String> {
+    let mut allocator = AllocEncoder::new();
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let moves = (game.random_moves)(&mut allocator, &mut rng);
+    let run = |allocator: &mut AllocEncoder, label: &str, actions: &[SimScriptAction]| {
+        (game.run)(allocator, actions)
+            .unwrap_or_else(|e| panic!("{label}: run failed: {e:?}; script={actions:?}"))
+    };
+
+    let base_label = format!("{} seed {seed} off-chain", game.name);
+    let base = run(
+        &mut allocator,
+        &base_label,
+        &script(&moves, off_chain_ending(game)),
+    );
+    let finished = settlements(&base, &base_label);
+    let pot = finished[0].share.clone() + finished[1].share.clone();
+
+    let mut failures = Vec::new();
+    for k in 0..moves.len() {
+        let who = mover(&moves, k);
+        let goer = rng.random_range(0..2);
+
+        let label = format!("{} seed {seed} continue@{k} p{goer}", game.name);
+        let mut tail = vec![SimScriptAction::GoOnChain(goer)];
+        tail.extend(moves[k..].iter().map(on_chain));
+        tail.push(SimScriptAction::WaitBlocks(SETTLE_BLOCKS, 0));
+        let outcome = run(&mut allocator, &label, &script(&moves[..k], tail));
+        let settled = settlements(&outcome, &label);
+        compare_shares(&label, &finished, &settled, &mut failures);
+        check_reward_coins(&outcome, &settled, &label, &mut failures);
+
+        let label = format!("{} seed {seed} accept@{k} p{who}", game.name);
+        let mut tail = vec![SimScriptAction::AcceptSettlement(who, GAME_ID)];
+        tail.extend(off_chain_ending(game));
+        let outcome = run(&mut allocator, &label, &script(&moves[..k], tail));
+        let accepted = settlements(&outcome, &label);
+
+        let label = format!("{} seed {seed} timeout@{k} p{goer}", game.name);
+        let tail = vec![
+            SimScriptAction::GoOnChain(goer),
+            SimScriptAction::WaitBlocks(SETTLE_BLOCKS, 0),
+        ];
+        let outcome = run(&mut allocator, &label, &script(&moves[..k], tail));
+        let settled = settlements(&outcome, &label);
+        compare_shares(&label, &accepted, &settled, &mut failures);
+        check_reward_coins(&outcome, &settled, &label, &mut failures);
+
+        let label = format!("{} seed {seed} slash@{k} p{who} cheats", game.name);
+        let tail = vec![
+            SimScriptAction::GoOnChain(goer),
+            SimScriptAction::WaitForOnChainTurn(who, GAME_ID),
+            // Not the whole pot: a move taking all of it is a concession.
+            SimScriptAction::Cheat(who, GAME_ID, Amount::new(pot.to_u64() - 1)),
+            SimScriptAction::WaitBlocks(SETTLE_BLOCKS, 0),
+        ];
+        let outcome = run(&mut allocator, &label, &script(&moves[..k], tail));
+        let settled = settlements(&outcome, &label);
+        if settled[who ^ 1].outcome == SettlementOutcome::SlashedOpponent {
+            let mut slashed = [settled[0].clone(), settled[1].clone()];
+            slashed[who].share = Amount::default();
+            slashed[who ^ 1].share = pot.clone();
+            compare_shares(&label, &slashed, &settled, &mut failures);
+        } else if settled[who].outcome == SettlementOutcome::ForfeitedSkippedReveal {
+            // Nothing to gain at this split, so the cheater concedes instead
+            // of moving and the game times out as it would have off-chain.
+            compare_shares(&label, &accepted, &settled, &mut failures);
+        } else {
+            failures.push(format!(
+                "{label}: cheat neither slashed, conceded nor accepted: {:?} / {:?}",
+                settled[who].outcome,
+                settled[who ^ 1].outcome
+            ));
+        }
+        check_reward_coins(&outcome, &settled, &label, &mut failures);
+    }
+    failures
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+fn check_game(game: &PlayoutGame) {
+    let first_seed = env_u64("SIM_PLAYOUT_SEED", 0);
+    let runs = env_u64("SIM_PLAYOUT_RUNS", 1);
+    let failures: Vec<String> = (first_seed..first_seed + runs)
+        .flat_map(|seed| check_playout(game, seed))
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} off-chain/on-chain payouts diverged:\n{}",
+        game.name,
+        failures.join("\n")
+    );
+}
+
+/// One entry per game type `game_collection` registers, named as registered.
+const PLAYOUT_GAMES: [PlayoutGame; 3] = [
+    PlayoutGame {
+        name: "calpoker",
+        run: run_calpoker_container_with_action_list,
+        random_moves: random_calpoker_moves,
+        companions: &[],
+    },
+    PlayoutGame {
+        name: "spacepoker",
+        run: run_spacepoker_container_with_action_list,
+        random_moves: random_spacepoker_moves,
+        companions: &[],
+    },
+    PlayoutGame {
+        name: "krunk",
+        run: run_krunk,
+        random_moves: random_krunk_moves,
+        companions: &[(1, GameID(3))],
+    },
+];
+
+fn check_every_game_type_has_a_playout() {
+    let mut allocator = AllocEncoder::new();
+    let missing: Vec<String> = game_collection(&mut allocator)
+        .keys()
+        // The debug game is a fixture registered only for tests.
+        .filter(|game_type| game_type.0 != b"debug")
+        .filter(|game_type| {
+            !PLAYOUT_GAMES
+                .iter()
+                .any(|g| g.name.as_bytes() == game_type.0)
+        })
+        .map(|game_type| String::from_utf8_lossy(&game_type.0).to_string())
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "game types without a play-out: {}",
+        missing.join(", ")
+    );
+}
+
+pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
+    vec![
+        (
+            "test_playout_covers_every_game_type",
+            &check_every_game_type_has_a_playout,
+        ),
+        ("test_playout_equivalence_calpoker", &|| {
+            check_game(&PLAYOUT_GAMES[0])
+        }),
+        ("test_playout_equivalence_spacepoker", &|| {
+            check_game(&PLAYOUT_GAMES[1])
+        }),
+        ("test_playout_equivalence_krunk", &|| {
+            check_game(&PLAYOUT_GAMES[2])
+        }),
+    ]
+}
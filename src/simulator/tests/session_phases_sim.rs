@@ -1,5 +1,5 @@
 use std::borrow::Borrow;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::rc::Rc;
 
 use clvm_traits::{ClvmEncoder, ToClvm};
@@ -9,27 +9,41 @@ use rand_chacha::ChaCha8Rng;
 
 use crate::channel_state::types::{ChannelEnv, ChannelPrivateKeys, ReadableMove};
 use crate::common::constants::{CREATE_COIN, SINGLETON_LAUNCHER_HASH};
-use crate::common::standard_coin::{standard_solution_partial, ChiaIdentity};
+use crate::common::standard_coin::{
+    private_to_public_key, standard_solution_partial, ChiaIdentity,
+};
 use crate::common::types::{atom_from_clvm, i64_from_atom, usize_from_atom};
 use crate::common::types::{
-    AllocEncoder, Amount, CoinSpend, CoinString, Error, GameID, GameType, IntoErr, PrivateKey,
-    Program, PuzzleHash, Spend, SpendBundle, Timeout,
+    Aggsig, AllocEncoder, Amount, CoinSpend, CoinString, Error, GameID, GameType, IntoErr,
+    PrivateKey, Program, PuzzleHash, Spend, SpendBundle, Timeout,
+};
+use crate::game_session::{
+    GameSession, GameSessionConfig, LivenessPolicy, MessagePeerQueue, MessagePipe,
 };
-use crate::game_session::{GameSession, GameSessionConfig, MessagePeerQueue, MessagePipe};
+use crate::operator_fee::{FeeRate, OperatorFee};
+use crate::payout::{PayoutShare, PayoutTerms};
+use crate::session_journal::{
+    replay, SessionInput, SessionJournal, SessionKeys, DEFAULT_JOURNAL_LIMIT,
+};
+use crate::session_match::{MatchEndReason, MatchScore, MatchTerms};
 use crate::session_phases::effects::{
     CancelReason, ChannelStatus, GameNotification, GameSessionEvent, GameStatusKind,
     SettlementOutcome, UnrollInitiator,
 };
 use crate::session_phases::game_collection;
+use crate::session_phases::go_on_chain_preview::{GoOnChainPreview, PreviewSpendKind};
 use crate::session_phases::handshake::CoinSpendRequest;
 use crate::session_phases::proposal::GameProposal;
 use crate::session_phases::types::{
-    BatchAction, ChannelFundingWallet, PacketSender, PeerMessage, ToLocalUI, WalletSpendInterface,
+    BatchAction, ChannelFundingWallet, PacketSender, PeerLimits, PeerMessage, ToLocalUI,
+    WalletSpendInterface,
 };
 use crate::transaction_manager::TransactionManager;
 use crate::utils::proper_list;
 
+use crate::attestation::GameResultAttestation;
 use crate::simulator::Simulator;
+use crate::spectator::{Spectator, SpectatorEvent, SpectatorGameStart, SpectatorUpdate};
 use crate::test_support::calpoker_sim::{calpoker_ran_all_the_moves_predicate, prefix_test_moves};
 use crate::test_support::debug_game::{make_debug_games, DebugGameCurry};
 use crate::test_support::sim_script::{ProposeTrigger, SimScriptAction};
@@ -63,17 +77,22 @@ impl MessagePeerQueue for SimulatedPeer {
     }
 }
 
-fn build_wallet_bundle_for_request(
+/// Fund `request` from `identity`'s coins, as an external wallet would,
+/// without spending any of `reserved`.
+pub(crate) fn build_wallet_bundle_for_request(
     allocator: &mut AllocEncoder,
     simulator: &Simulator,
     identity: &ChiaIdentity,
     request: &crate::session_phases::handshake::CoinSpendRequest,
+    reserved: &BTreeSet<CoinString>,
 ) -> Result<SpendBundle, Error> {
     let mut candidate_coins = simulator.get_my_coins(&identity.puzzle_hash)?;
     candidate_coins.retain(|coin| {
-        coin.to_parts()
-            .map(|(_, _, amt)| amt.to_u64() >= request.amount.to_u64())
-            .unwrap_or(false)
+        !reserved.contains(coin)
+            && coin
+                .to_parts()
+                .map(|(_, _, amt)| amt.to_u64() >= request.amount.to_u64())
+                .unwrap_or(false)
     });
     let selected_coin = if let Some(expected_coin_id) = request.coin_id.as_ref() {
         candidate_coins
@@ -480,7 +499,10 @@ fn event_shape(actual: &TestEvent) -> String {
             GameNotification::ProposalCancelled { id, reason } => format!("Notif(ProposalCancelled(id={id:?},reason={reason:?}))"),
             GameNotification::InsufficientBalance { id, our_balance_short, their_balance_short } => format!("Notif(InsufficientBalance(id={id:?},ours={our_balance_short},theirs={their_balance_short}))"),
             GameNotification::ActionFailed { reason, .. } => format!("Notif(ActionFailed(reason={reason}))"),
-            GameNotification::MoveRejected { id, tag, message } => format!("Notif(MoveRejected(id={id:?},tag={tag},message={message}))"),
+            GameNotification::MoveRejected { id, tag, message, .. } => format!("Notif(MoveRejected(id={id:?},tag={tag},message={message}))"),
+            GameNotification::MatchHandStarted { id, hand, .. } => format!("Notif(MatchHandStarted(id={id:?},hand={hand}))"),
+            GameNotification::MatchHandSettled { id, hand, score, .. } => format!("Notif(MatchHandSettled(id={id:?},hand={hand},played={}))", score.hands_played),
+            GameNotification::MatchEnded { reason, score } => format!("Notif(MatchEnded(reason={reason:?},played={}))", score.hands_played),
             GameNotification::ChannelStatus { state, .. } => format!("Notif(ChannelStatus(state={state:?}))"),
         },
     }
@@ -615,6 +637,11 @@ pub struct LocalTestUIReceiver {
     pub game_accepted_ids: HashSet<GameID>,
     pub opponent_moved_in_game: HashSet<GameID>,
     pub game_finished_ids: HashSet<GameID>,
+    /// The session's own preview, taken just before it went on chain.
+    pub go_on_chain_preview: Option<GoOnChainPreview>,
+    /// Everything the player's spectator feeds emitted, in order.
+    pub spectator_feed: Vec<SpectatorEvent>,
+    pub result_attestations: Vec<GameResultAttestation>,
 }
 
 impl LocalTestUIReceiver {
@@ -665,9 +692,15 @@ impl ToLocalUI for LocalTestUIReceiver {
         match notification {
             GameNotification::ChannelStatus {
                 state: ChannelStatus::Active,
+                advisory,
                 ..
             } => {
                 self.channel_created = true;
+                // Balance updates are too chatty to keep; advisories (liveness
+                // warnings) are not.
+                if advisory.is_some() {
+                    self.notifications.push(notification.clone());
+                }
             }
             GameNotification::GameStatus {
                 id,
@@ -825,6 +858,7 @@ type GameRunEarlySuccessPredicate<'a> = Option<&'a dyn Fn(usize, &[ManagedSyncCr
 
 pub struct GameRunOutcome {
     pub identities: [ChiaIdentity; 2],
+    pub private_keys: [ChannelPrivateKeys; 2],
     pub cradles: [ManagedSyncCradle; 2],
     pub local_uis: [LocalTestUIReceiver; 2],
     pub simulator: Simulator,
@@ -865,6 +899,25 @@ fn move_ready(moves: &[SimScriptAction], mn: usize, local_uis: &[LocalTestUIRece
     }
 }
 
+fn on_chain_turn_ready(
+    moves: &[SimScriptAction],
+    mn: usize,
+    cradles: &[TransactionManager<GameSession>; 2],
+    local_uis: &[LocalTestUIReceiver; 2],
+) -> bool {
+    match moves.get(mn) {
+        Some(SimScriptAction::WaitForOnChainTurn(who, gid))
+        | Some(SimScriptAction::OnChainMove(who, gid, _)) => {
+            cradles[*who].awaiting_on_chain_move(gid)
+                || local_uis[*who]
+                    .notifications
+                    .iter()
+                    .any(|n| is_terminal_for_id(n, gid))
+        }
+        _ => false,
+    }
+}
+
 fn accept_resolved(local_uis: &[LocalTestUIReceiver; 2], who: usize, gid: &GameID) -> bool {
     local_uis[who].game_accepted_ids.contains(gid)
         || local_uis[who].notifications.iter().any(|n| {
@@ -884,7 +937,9 @@ fn accept_proposal_ready(
     if mn >= moves.len() {
         return false;
     }
-    if let SimScriptAction::AcceptProposal(who, gid) = &moves[mn] {
+    if let SimScriptAction::AcceptProposal(who, gid)
+    | SimScriptAction::AcceptProposalWithPayout(who, gid, _) = &moves[mn]
+    {
         if local_uis[*who].accepted_proposal_ids.contains(gid) {
             accept_resolved(local_uis, *who, gid)
         } else {
@@ -906,6 +961,8 @@ fn propose_ready(
     match &moves[mn] {
         SimScriptAction::ProposeNewGame(who, trigger)
         | SimScriptAction::ProposeNewGameWithTimeout(who, trigger, _)
+        | SimScriptAction::ProposeNewGameWithFee(who, trigger, _)
+        | SimScriptAction::ProposeNewGameWithPayout(who, trigger, _)
         | SimScriptAction::ProposeNewGameTheirTurn(who, trigger)
         | SimScriptAction::ProposeKrunkGroup(who, trigger) => match trigger {
             ProposeTrigger::Channel => local_uis[*who].channel_created,
@@ -914,6 +971,14 @@ fn propose_ready(
                     || local_uis[1].game_finished_ids.contains(gid)
             }
         },
+        SimScriptAction::StartMatch(who, _) => local_uis[*who].channel_created,
+        SimScriptAction::AcceptMatch(who, gid, _) => {
+            local_uis[*who].received_proposal_ids.contains(gid)
+        }
+        SimScriptAction::AuthorizeSpectator(who, gid, _, _) => local_uis[*who]
+            .notifications
+            .iter()
+            .any(|n| matches!(n, GameNotification::ProposalAccepted { id, .. } if id == gid)),
         _ => false,
     }
 }
@@ -974,7 +1039,22 @@ fn run_game_container_with_action_list_with_success_predicate(
 
     simulator.farm_block(&neutral_identity.puzzle_hash);
 
-    let cradle1 = GameSession::new_with_keys(
+    let unroll_timeout = moves_input
+        .iter()
+        .find_map(|m| match m {
+            SimScriptAction::UnrollTimeout(t) => Some(t.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| Timeout::new(15));
+    let journal_limit = moves_input.iter().find_map(|m| match m {
+        SimScriptAction::JournalSessions(limit) => Some(*limit),
+        _ => None,
+    });
+    let new_session = |config: GameSessionConfig, keys: &ChannelPrivateKeys| match journal_limit {
+        Some(limit) => GameSession::new_with_journal(config, keys.clone(), limit),
+        None => GameSession::new_with_keys(config, keys.clone()),
+    };
+    let cradle1 = new_session(
         GameSessionConfig {
             game_types: game_type_map.clone(),
             have_potato: true,
@@ -982,12 +1062,14 @@ fn run_game_container_with_action_list_with_success_predicate(
             my_contribution: Amount::new(bal),
             their_contribution: Amount::new(bal),
             channel_timeout: Timeout::new(5),
-            unroll_timeout: Timeout::new(15),
+            unroll_timeout: unroll_timeout.clone(),
             reward_puzzle_hash: identities[0].puzzle_hash.clone(),
+            liveness: LivenessPolicy::default(),
+            peer_limits: PeerLimits::default(),
         },
-        private_keys[0].clone(),
+        &private_keys[0],
     );
-    let cradle2 = GameSession::new_with_keys(
+    let cradle2 = new_session(
         GameSessionConfig {
             game_types: game_type_map.clone(),
             have_potato: false,
@@ -995,10 +1077,12 @@ fn run_game_container_with_action_list_with_success_predicate(
             my_contribution: Amount::new(bal),
             their_contribution: Amount::new(bal),
             channel_timeout: Timeout::new(5),
-            unroll_timeout: Timeout::new(15),
+            unroll_timeout: unroll_timeout.clone(),
             reward_puzzle_hash: identities[1].puzzle_hash.clone(),
+            liveness: LivenessPolicy::default(),
+            peer_limits: PeerLimits::default(),
         },
-        private_keys[1].clone(),
+        &private_keys[1],
     );
     let mut cradles = [
         TransactionManager::new(cradle1),
@@ -1045,6 +1129,11 @@ fn run_game_container_with_action_list_with_success_predicate(
                     | SimScriptAction::ForceUnroll(_)
                     | SimScriptAction::NerfMessages(_)
                     | SimScriptAction::UnNerfMessages
+                    | SimScriptAction::SetLivenessPolicy(_, _)
+                    | SimScriptAction::SetPeerLimits(_, _)
+                    | SimScriptAction::UnrollTimeout(_)
+                    | SimScriptAction::JournalSessions(_)
+                    | SimScriptAction::StopMatch(_)
                     | SimScriptAction::SaveUnrollSnapshot(_)
                     | SimScriptAction::ForceStaleUnroll(_)
                     | SimScriptAction::InjectRawMessage(_, _)
@@ -1101,6 +1190,7 @@ fn run_game_container_with_action_list_with_success_predicate(
             if p(move_number, &cradles) {
                 return Ok(GameRunOutcome {
                     identities: [identities[0].clone(), identities[1].clone()],
+                    private_keys,
                     cradles,
                     local_uis,
                     simulator,
@@ -1121,6 +1211,9 @@ fn run_game_container_with_action_list_with_success_predicate(
                     );
                 }
                 local_uis[i].go_on_chain = false;
+                if let Ok(preview) = cradles[i].preview_go_on_chain(allocator) {
+                    local_uis[i].go_on_chain_preview = Some(preview);
+                }
                 let got_error = local_uis[i].got_error;
                 cradles[i].go_on_chain(allocator, got_error)?;
             }
@@ -1137,6 +1230,7 @@ fn run_game_container_with_action_list_with_success_predicate(
                 report_backlogs[i].push((current_height, records));
             } else {
                 let t_nb = std::time::Instant::now();
+                cradles[i].report_timestamp(simulator.get_current_timestamp());
                 cradles[i].report_coin_states(allocator, current_height as u64, &records)?;
                 if timing_enabled {
                     let nb_elapsed = t_nb.elapsed();
@@ -1271,6 +1365,12 @@ fn run_game_container_with_action_list_with_success_predicate(
                                 logs[i].push(line.clone());
                             }
                             GameSessionEvent::WatchCoin { .. } => {}
+                            GameSessionEvent::SpectatorFeed { event, .. } => {
+                                local_uis[i].spectator_feed.push(event.clone());
+                            }
+                            GameSessionEvent::GameResultAttested(attestation) => {
+                                local_uis[i].result_attestations.push(*attestation.clone());
+                            }
                         }
                     }
 
@@ -1297,6 +1397,7 @@ fn run_game_container_with_action_list_with_success_predicate(
                             &simulator,
                             &identities[i],
                             &req,
+                            &BTreeSet::new(),
                         )?;
                         cradles[i].provide_coin_spend_bundle(allocator, wallet_bundle)?;
                     }
@@ -1403,6 +1504,7 @@ fn run_game_container_with_action_list_with_success_predicate(
         } else if can_move
             || global_move(moves_input, move_number)
             || move_ready(moves_input, move_number, &local_uis)
+            || on_chain_turn_ready(moves_input, move_number, &cradles, &local_uis)
             || accept_proposal_ready(moves_input, move_number, &local_uis)
             || propose_ready(moves_input, move_number, &local_uis)
         {
@@ -1430,15 +1532,25 @@ fn run_game_container_with_action_list_with_success_predicate(
                     }
                     SimScriptAction::ProposeNewGame(who, _trigger)
                     | SimScriptAction::ProposeNewGameTheirTurn(who, _trigger)
-                    | SimScriptAction::ProposeNewGameWithTimeout(who, _trigger, _) => {
-                        let my_turn = matches!(
-                            ga,
-                            SimScriptAction::ProposeNewGame(_, _)
-                                | SimScriptAction::ProposeNewGameWithTimeout(_, _, _)
-                        );
+                    | SimScriptAction::ProposeNewGameWithTimeout(who, _trigger, _)
+                    | SimScriptAction::ProposeNewGameWithFee(who, _trigger, _)
+                    | SimScriptAction::ProposeNewGameWithPayout(who, _trigger, _) => {
+                        let my_turn = !matches!(ga, SimScriptAction::ProposeNewGameTheirTurn(_, _));
                         let timeout = match ga {
-                            SimScriptAction::ProposeNewGameWithTimeout(_, _, timeout) => *timeout,
-                            _ => 15,
+                            SimScriptAction::ProposeNewGameWithTimeout(_, _, timeout) => {
+                                timeout.clone()
+                            }
+                            _ => Timeout::new(15),
+                        };
+                        let fee = match ga {
+                            SimScriptAction::ProposeNewGameWithFee(_, _, fee) => Some(fee.clone()),
+                            _ => None,
+                        };
+                        let payout = match ga {
+                            SimScriptAction::ProposeNewGameWithPayout(_, _, payout) => {
+                                Some(payout.clone())
+                            }
+                            _ => None,
                         };
                         let parameters = if game_type == b"calpoker" {
                             let node = (Amount::new(100), (my_turn, ()))
@@ -1465,8 +1577,10 @@ fn run_game_container_with_action_list_with_success_predicate(
                             allocator,
                             &[GameProposal {
                                 game_type: GameType(game_type.to_vec()),
-                                timeout: Timeout::new(timeout),
+                                timeout,
                                 parameters,
+                                fee,
+                                payout,
                             }],
                         )?;
                         local_uis[*who]
@@ -1480,6 +1594,8 @@ fn run_game_container_with_action_list_with_success_predicate(
                                 game_type: GameType(b"krunk".to_vec()),
                                 timeout: Timeout::new(15),
                                 parameters: Program::from_hex("64")?,
+                                fee: None,
+                                payout: None,
                             }],
                         )?;
                         local_uis[*who]
@@ -1496,6 +1612,13 @@ fn run_game_container_with_action_list_with_success_predicate(
                             move_number -= 1;
                         }
                     }
+                    SimScriptAction::AcceptProposalWithPayout(who, gid, payout) => {
+                        if !local_uis[*who].accepted_proposal_ids.contains(gid) {
+                            cradles[*who].accept_proposal_with_payout(allocator, gid, payout)?;
+                            local_uis[*who].accepted_proposal_ids.push(*gid);
+                            move_number -= 1;
+                        }
+                    }
                     SimScriptAction::CancelProposal(who, gid) => {
                         if gid_diag_on {
                             gid_diag(&test_name, action_idx, "CancelProposal", gid, gid);
@@ -1519,6 +1642,20 @@ fn run_game_container_with_action_list_with_success_predicate(
                         }
                         local_uis[*who].go_on_chain = true;
                     }
+                    SimScriptAction::WaitForOnChainTurn(_, _) => {}
+                    SimScriptAction::OnChainMove(who, gid, readable) => {
+                        if gid_diag_on {
+                            gid_diag(&test_name, action_idx, "OnChainMove", gid, gid);
+                        }
+                        let settled = local_uis[*who]
+                            .notifications
+                            .iter()
+                            .any(|n| is_terminal_for_id(n, gid));
+                        if !settled {
+                            let entropy = rng.random();
+                            cradles[*who].make_move(allocator, gid, readable.clone(), entropy)?;
+                        }
+                    }
                     SimScriptAction::FakeMove(who, gid, readable, move_data) => {
                         if gid_diag_on {
                             gid_diag(&test_name, action_idx, "FakeMove", gid, gid);
@@ -1637,6 +1774,27 @@ fn run_game_container_with_action_list_with_success_predicate(
                     SimScriptAction::UnNerfMessages => {
                         nerf_messages_for = 0;
                     }
+                    SimScriptAction::SetLivenessPolicy(who, policy) => {
+                        cradles[*who].set_liveness_policy(policy.clone());
+                    }
+                    SimScriptAction::SetPeerLimits(who, limits) => {
+                        cradles[*who].set_peer_limits(limits.clone());
+                    }
+                    SimScriptAction::UnrollTimeout(_) | SimScriptAction::JournalSessions(_) => {}
+                    SimScriptAction::StartMatch(who, terms) => {
+                        let id = cradles[*who].start_match(allocator, terms.clone())?;
+                        local_uis[*who].proposed_game_ids.push(id);
+                    }
+                    SimScriptAction::AcceptMatch(who, gid, terms) => {
+                        cradles[*who].accept_match(allocator, gid, terms.clone())?;
+                        local_uis[*who].accepted_proposal_ids.push(*gid);
+                    }
+                    SimScriptAction::StopMatch(who) => {
+                        cradles[*who].stop_match()?;
+                    }
+                    SimScriptAction::AuthorizeSpectator(who, gid, observer, delay_moves) => {
+                        cradles[*who].authorize_spectator(gid, observer.clone(), *delay_moves)?;
+                    }
                     SimScriptAction::WaitBlocks(n, players) => {
                         wait_blocks = Some((*n, *players));
                     }
@@ -1701,6 +1859,8 @@ fn run_game_container_with_action_list_with_success_predicate(
                                 game_type: GameType(game_type.to_vec()),
                                 timeout: Timeout::new(15),
                                 parameters,
+                                fee: None,
+                                payout: None,
                             }],
                         )?;
                         cradles[*who].flush_pending(allocator)?;
@@ -1744,6 +1904,8 @@ fn run_game_container_with_action_list_with_success_predicate(
                                 game_type: GameType(game_type.to_vec()),
                                 timeout: Timeout::new(15),
                                 parameters,
+                                fee: None,
+                                payout: None,
                             }],
                         )?;
                         cradles[*who].flush_pending(allocator)?;
@@ -1787,6 +1949,8 @@ fn run_game_container_with_action_list_with_success_predicate(
                                 game_type: GameType(game_type.to_vec()),
                                 timeout: Timeout::new(15),
                                 parameters,
+                                fee: None,
+                                payout: None,
                             }],
                         )?;
                         cradles[*who].flush_pending(allocator)?;
@@ -2117,6 +2281,7 @@ fn run_game_container_with_action_list_with_success_predicate(
 
     Ok(GameRunOutcome {
         identities: [identities[0].clone(), identities[1].clone()],
+        private_keys,
         cradles,
         local_uis,
         simulator,
@@ -2255,7 +2420,61 @@ fn get_balances_from_outcome(outcome: &GameRunOutcome) -> Result<(u64, u64), Err
     Ok((p1_balance, p2_balance))
 }
 
-fn calpoker_test_moves_with_selected_cards(
+/// Sum of the unspent coins paying `puzzle_hash`.
+fn coins_total(outcome: &GameRunOutcome, puzzle_hash: &PuzzleHash) -> u64 {
+    outcome
+        .simulator
+        .get_my_coins(puzzle_hash)
+        .expect("should work")
+        .iter()
+        .map(|c| c.to_parts().map(|(_, _, amt)| amt.to_u64()).unwrap_or(0))
+        .sum()
+}
+
+/// Calpoker hands of 100 each, the proposer opening every hand.
+fn calpoker_match_terms(
+    allocator: &mut AllocEncoder,
+    hands: Option<u32>,
+    stop_loss: Option<u64>,
+) -> MatchTerms {
+    let parameters = (Amount::new(100), (true, ()))
+        .to_clvm(allocator)
+        .expect("should encode");
+    MatchTerms {
+        proposal: GameProposal {
+            game_type: GameType(b"calpoker".to_vec()),
+            timeout: Timeout::new(15),
+            parameters: Program::from_nodeptr(allocator, parameters).expect("should build"),
+            fee: None,
+            payout: None,
+        },
+        hands,
+        stop_loss: stop_loss.map(Amount::new),
+        alternate_proposer: true,
+    }
+}
+
+/// The same moves with the players' seats swapped.
+fn swap_seats(moves: Vec<SimScriptAction>) -> Vec<SimScriptAction> {
+    moves
+        .into_iter()
+        .map(|action| match action {
+            SimScriptAction::Move(who, gid, readable, received) => {
+                SimScriptAction::Move(who ^ 1, gid, readable, received)
+            }
+            other => other,
+        })
+        .collect()
+}
+
+fn match_ended(notifications: &[GameNotification]) -> Option<(MatchEndReason, u32)> {
+    notifications.iter().find_map(|n| match n {
+        GameNotification::MatchEnded { reason, score } => Some((*reason, score.hands_played)),
+        _ => None,
+    })
+}
+
+pub fn calpoker_test_moves_with_selected_cards(
     allocator: &mut AllocEncoder,
     game_id: GameID,
     alice_selected: &[usize],
@@ -2839,6 +3058,408 @@ pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
         );
     }));
 
+    res.push(("test_operator_fee_paid_on_clean_shutdown", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Bob wins the whole 200 pot off-chain; the flat fee of 10 comes out
+        // of his share and the clean shutdown pays it to the operator.
+        let fee_ph = PuzzleHash::from_bytes([0x55; 32]);
+        let fee = OperatorFee {
+            puzzle_hash: fee_ph.clone(),
+            rate: FeeRate::Flat(Amount::new(10)),
+        };
+        let mut moves = vec![
+            SimScriptAction::ProposeNewGameWithFee(0, ProposeTrigger::Channel, fee),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        moves.push(SimScriptAction::CleanShutdown(1));
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .expect("should finish");
+
+        assert!(
+            outcome.local_uis[1].notifications.iter().any(|n| matches!(
+                n,
+                GameNotification::ProposalMade { operator_fee: Some(fee), .. }
+                    if fee.puzzle_hash == fee_ph && fee.amount == Amount::new(10)
+            )),
+            "Bob should see the fee in ProposalMade, got: {:?}",
+            outcome.local_uis[1].notifications
+        );
+        for (who, share) in [(0, 0), (1, 190)] {
+            let notifs = &outcome.local_uis[who].notifications;
+            assert!(
+                notifs.iter().any(|n| matches!(
+                    n,
+                    GameNotification::GameSettled { our_share, operator_fee, .. }
+                        if *our_share == Amount::new(share) && *operator_fee == Amount::new(10)
+                )),
+                "player {who} should settle with share {share} and the fee, got: {notifs:?}"
+            );
+            assert!(
+                outcome.local_uis[who].clean_shutdown_complete,
+                "player {who} should reach ResolvedClean"
+            );
+        }
+
+        let fee_coins = outcome
+            .simulator
+            .get_my_coins(&fee_ph)
+            .expect("should work");
+        let fee_paid: u64 = fee_coins
+            .iter()
+            .map(|c| c.to_parts().map(|(_, _, amt)| amt.to_u64()).unwrap_or(0))
+            .sum();
+        assert_eq!(fee_paid, 10, "the shutdown should pay the operator");
+        let (p1_balance, p2_balance) = get_balances_from_outcome(&outcome).expect("should work");
+        // Alice is down her 100 stake, Bob up 100 less the fee.
+        assert_eq!(p2_balance, p1_balance + 190);
+    }));
+
+    res.push(("test_operator_fee_to_a_reward_puzzle_hash_refused", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Alice names herself as operator, for a fee of 100 on Bob's
+        // winnings: what she'd have left out of the game.  Its coin would be
+        // identical to her reward coin, so the proposal is refused.
+        let mut rng = ChaCha8Rng::from_seed([0; 32]);
+        let alice_key: PrivateKey = rng.random();
+        let alice = ChiaIdentity::new(&mut allocator, alice_key).expect("identity");
+        let fee = OperatorFee {
+            puzzle_hash: alice.puzzle_hash.clone(),
+            rate: FeeRate::Flat(Amount::new(100)),
+        };
+        let moves = vec![
+            SimScriptAction::ProposeNewGameWithFee(0, ProposeTrigger::Channel, fee),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        let refused = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .err()
+        .expect("the proposal should be refused");
+        assert!(
+            format!("{refused:?}").contains("operator fee pays a player's reward puzzle hash"),
+            "got: {refused:?}"
+        );
+    }));
+
+    res.push(("test_operator_fee_paid_on_chain", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // The same hand, forced on chain before Alice's last move and settled
+        // by timeout: the referee pays the fee coin alongside the rewards.
+        let fee_ph = PuzzleHash::from_bytes([0x55; 32]);
+        let fee = OperatorFee {
+            puzzle_hash: fee_ph.clone(),
+            rate: FeeRate::BasisPoints(500),
+        };
+        let mut moves = vec![
+            SimScriptAction::ProposeNewGameWithFee(0, ProposeTrigger::Channel, fee),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        moves.pop();
+        moves.push(SimScriptAction::GoOnChain(0));
+        moves.push(SimScriptAction::AcceptSettlement(0, GameID(1)));
+        moves.push(SimScriptAction::WaitBlocks(120, 1));
+        moves.push(SimScriptAction::WaitBlocks(5, 0));
+
+        let outcome =
+            run_calpoker_container_with_action_list(&mut allocator, &moves).expect("should finish");
+
+        for who in 0..2 {
+            let notifs = &outcome.local_uis[who].notifications;
+            assert_reward_coin_consistency(notifs, "operator_fee_on_chain");
+            assert!(
+                notifs.iter().any(|n| matches!(
+                    n,
+                    GameNotification::GameSettled { operator_fee, .. }
+                        if *operator_fee == Amount::new(10)
+                )),
+                "player {who} should settle with the fee, got: {notifs:?}"
+            );
+        }
+        let fee_coins = outcome
+            .simulator
+            .get_my_coins(&fee_ph)
+            .expect("should work");
+        let fee_paid: u64 = fee_coins
+            .iter()
+            .map(|c| c.to_parts().map(|(_, _, amt)| amt.to_u64()).unwrap_or(0))
+            .sum();
+        assert_eq!(fee_paid, 10, "the referee timeout should pay the operator");
+    }));
+
+    res.push(("test_payouts_routed_on_clean_shutdown", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Alice sends her share to an escrow; Bob splits his 70/30.  Bob wins
+        // the whole 200 pot off-chain and the clean shutdown pays his split
+        // destinations instead of his reward puzzle hash.
+        let escrow_ph = PuzzleHash::from_bytes([0x61; 32]);
+        let team_ph = PuzzleHash::from_bytes([0x71; 32]);
+        let player_ph = PuzzleHash::from_bytes([0x72; 32]);
+        let split = PayoutTerms::split(vec![
+            PayoutShare {
+                puzzle_hash: team_ph.clone(),
+                basis_points: 7000,
+            },
+            PayoutShare {
+                puzzle_hash: player_ph.clone(),
+                basis_points: 3000,
+            },
+        ])
+        .expect("valid split");
+        let mut moves = vec![
+            SimScriptAction::ProposeNewGameWithPayout(
+                0,
+                ProposeTrigger::Channel,
+                PayoutTerms::to(escrow_ph.clone()),
+            ),
+            SimScriptAction::AcceptProposalWithPayout(1, GameID(1), split),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        moves.push(SimScriptAction::CleanShutdown(1));
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .expect("should finish");
+
+        for who in 0..2 {
+            assert!(
+                outcome.local_uis[who].clean_shutdown_complete,
+                "player {who} should reach ResolvedClean"
+            );
+        }
+        assert_eq!(coins_total(&outcome, &team_ph), 140);
+        assert_eq!(coins_total(&outcome, &player_ph), 60);
+        assert_eq!(coins_total(&outcome, &escrow_ph), 0);
+        // Both stakes left the players' wallets; the winnings went elsewhere.
+        let (p1_balance, p2_balance) = get_balances_from_outcome(&outcome).expect("should work");
+        assert_eq!(p1_balance, p2_balance);
+    }));
+
+    res.push(("test_split_payout_distributed_on_chain", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Bob's split payout, with the hand forced on chain before Alice's
+        // last move and settled by timeout: the referee pays Bob's share to
+        // the splitter and Bob's spend of it pays the destinations.
+        let team_ph = PuzzleHash::from_bytes([0x71; 32]);
+        let player_ph = PuzzleHash::from_bytes([0x72; 32]);
+        let split = PayoutTerms::split(vec![
+            PayoutShare {
+                puzzle_hash: team_ph.clone(),
+                basis_points: 7000,
+            },
+            PayoutShare {
+                puzzle_hash: player_ph.clone(),
+                basis_points: 3000,
+            },
+        ])
+        .expect("valid split");
+        let split_ph = split.puzzle_hash().expect("should hash");
+        let mut moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposalWithPayout(1, GameID(1), split),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        moves.pop();
+        moves.push(SimScriptAction::GoOnChain(0));
+        moves.push(SimScriptAction::AcceptSettlement(0, GameID(1)));
+        moves.push(SimScriptAction::WaitBlocks(120, 1));
+        moves.push(SimScriptAction::WaitBlocks(5, 0));
+        moves.push(SimScriptAction::WaitBlocks(5, 1));
+
+        let outcome =
+            run_calpoker_container_with_action_list(&mut allocator, &moves).expect("should finish");
+
+        let notifs = &outcome.local_uis[1].notifications;
+        assert_reward_coin_consistency(notifs, "split_payout_on_chain");
+        let bob_share = notifs
+            .iter()
+            .find_map(|n| match n {
+                GameNotification::GameSettled {
+                    our_share,
+                    coin_id: Some(coin),
+                    ..
+                } => {
+                    assert_eq!(
+                        coin.to_parts().map(|(_, ph, _)| ph),
+                        Some(split_ph.clone()),
+                        "Bob's reward coin should pay the splitter"
+                    );
+                    Some(our_share.to_u64())
+                }
+                _ => None,
+            })
+            .expect("Bob should settle with a reward coin");
+        assert!(bob_share > 0);
+        assert_eq!(coins_total(&outcome, &player_ph), bob_share * 3000 / 10000);
+        assert_eq!(
+            coins_total(&outcome, &team_ph) + coins_total(&outcome, &player_ph),
+            bob_share
+        );
+    }));
+
+    res.push(("test_spectator_verifies_both_feeds", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Both players let the same observer follow a calpoker hand.  The
+        // observer checks every move and reads each one with the receiving
+        // player's handler, except Alice's last, which Bob never answers,
+        // and her first: Bob's feed starts only once Alice has signed a
+        // state holding the game, by which time she has made it.
+        let mut rng = ChaCha8Rng::from_seed([0x5e; 32]);
+        let observer_key: PrivateKey = rng.random();
+        let observer = private_to_public_key(&observer_key);
+        let mut moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+            SimScriptAction::AuthorizeSpectator(0, GameID(1), observer.clone(), 0),
+            SimScriptAction::AuthorizeSpectator(1, GameID(1), observer.clone(), 1),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        moves.push(SimScriptAction::CleanShutdown(1));
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .expect("should finish");
+
+        let mut spectator = Spectator::new(observer.clone());
+        for who in 0..2 {
+            for event in outcome.local_uis[who].spectator_feed.iter() {
+                spectator
+                    .receive(&mut allocator, event)
+                    .unwrap_or_else(|e| panic!("p{who} feed rejected: {e:?} at {event:?}"));
+            }
+        }
+        let game = spectator.game(&GameID(1)).expect("game is watched");
+        assert!(game.ended);
+        assert_eq!(game.moves.len(), 5);
+        for (index, observed) in game.moves.iter().enumerate() {
+            assert_eq!(observed.mover, index % 2);
+            assert!(!observed.illegal, "move {index} should be legal");
+            if index == 0 || index == 4 {
+                assert!(observed.readable.is_none(), "move {index} is never read");
+                continue;
+            }
+            let reader = 1 - observed.mover;
+            assert_eq!(
+                observed.readable.as_ref().map(|r| r.to_program()),
+                Some(
+                    outcome.local_uis[reader].opponent_moves[index / 2]
+                        .2
+                        .to_program()
+                ),
+                "move {index} should read as player {reader} read it"
+            );
+        }
+
+        // A feed can't alter a move or be replayed to another observer.
+        let feed = &outcome.local_uis[0].spectator_feed;
+        let mut tampered = feed[1].clone();
+        if let SpectatorEvent::Move(record) = &mut tampered {
+            record.details.basic.mover_share = Amount::new(1);
+        }
+        let mut spectator = Spectator::new(observer);
+        assert_eq!(
+            spectator.receive(&mut allocator, &feed[0]).expect("start"),
+            Some(SpectatorUpdate::Started(GameID(1)))
+        );
+        assert!(spectator.receive(&mut allocator, &tampered).is_err());
+        let other: PrivateKey = rng.random();
+        let mut stranger = Spectator::new(private_to_public_key(&other));
+        assert!(stranger.receive(&mut allocator, &feed[0]).is_err());
+
+        // Nor can it swap the opponent's keys, the starting point, or the
+        // opponent's countersignature.
+        let SpectatorEvent::Start(start) = &feed[0] else {
+            panic!("feed should open with its start");
+        };
+        let forgeries: [&dyn Fn(&mut SpectatorGameStart); 3] = [
+            &|s| s.grant.grant.players[1].unroll_pubkey = private_to_public_key(&other),
+            &|s| s.state = Rc::new(Program::from_hex("01").unwrap()),
+            &|s| s.countersigned.signature = Aggsig::default(),
+        ];
+        for (index, forge) in forgeries.into_iter().enumerate() {
+            let mut forged = start.clone();
+            forge(&mut forged);
+            let mut fresh = Spectator::new(start.grant.grant.observer.clone());
+            assert!(
+                fresh
+                    .receive(&mut allocator, &SpectatorEvent::Start(forged))
+                    .is_err(),
+                "forgery {index} should be rejected"
+            );
+        }
+    }));
+
+    res.push(("test_game_result_attested_by_both_players", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // The hand settles off-chain, the players swap signatures on its
+        // result, and each ends up holding the same attestation.
+
+        let mut moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        moves.push(SimScriptAction::CleanShutdown(1));
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .expect("should finish");
+
+        let attestation = &outcome.local_uis[0].result_attestations;
+        assert_eq!(attestation.len(), 1, "Alice should get one attestation");
+        assert_eq!(
+            outcome.local_uis[1].result_attestations, *attestation,
+            "both players should end up with the same attestation"
+        );
+        let attestation = &attestation[0];
+        assert!(attestation.verify());
+        assert_eq!(attestation.result.game_id, GameID(1));
+        assert_eq!(attestation.result.game_type, GameType(b"calpoker".to_vec()));
+
+        let total: u64 = attestation.result.payouts.iter().map(|a| a.to_u64()).sum();
+        assert_eq!(total, 200);
+        for ui in outcome.local_uis.iter() {
+            let settled = ui
+                .notifications
+                .iter()
+                .find_map(|n| match n {
+                    GameNotification::GameSettled { our_share, .. } => Some(our_share.clone()),
+                    _ => None,
+                })
+                .expect("game should settle");
+            assert!(attestation.result.payouts.contains(&settled));
+        }
+
+        let mut claimed = attestation.clone();
+        claimed.result.payouts[0] = Amount::new(claimed.result.payouts[0].to_u64() + 1);
+        assert!(!claimed.verify(), "an altered result should not verify");
+    }));
+
     res.push(("test_clean_shutdown_no_games_nerf_p0", &|| {
         let mut allocator = AllocEncoder::new();
         let moves = vec![
@@ -3775,6 +4396,8 @@ pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
                 game_type: GameType(game_type.to_vec()),
                 timeout: Timeout::new(15),
                 parameters: params1,
+                fee: None,
+                payout: None,
             }],
         );
 
@@ -3795,6 +4418,8 @@ pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
                 game_type: GameType(game_type.to_vec()),
                 timeout: Timeout::new(15),
                 parameters: params2,
+                fee: None,
+                payout: None,
             }],
         );
 
@@ -5869,15 +6494,574 @@ pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
         );
     }));
 
-    // ──────────────────────────────────────────────────────────────────
-    // Proposal lifecycle tests
-    // ──────────────────────────────────────────────────────────────────
-
-    res.push(("test_proposal_received_before_channel_coin_report", &|| {
+    res.push(("test_go_on_chain_preview_matches_outcome", &|| {
         let mut allocator = AllocEncoder::new();
 
-        // Bob's peer messages still arrive, but his watched coin reports are
-        // delayed. Alice can observe the channel coin first, enter Active, and
+        // Same script as test_go_on_chain_then_move_queued_and_replayed:
+        // alice goes on chain with her commit unacknowledged, redoes it and
+        // wins on timeout against a silent bob.  Her preview, taken just
+        // before going on chain, has to predict that path.
+        let mut all_moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        all_moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let moves = vec![
+            SimScriptAction::WaitBlocks(5, 0),
+            all_moves[0].clone(),
+            all_moves[1].clone(),
+            SimScriptAction::NerfMessages(0),
+            all_moves[2].clone(),
+            SimScriptAction::GoOnChain(0),
+            SimScriptAction::NerfTransactions(1),
+            SimScriptAction::WaitBlocks(120, 0),
+        ];
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .expect("should finish");
+
+        let preview = outcome.local_uis[0]
+            .go_on_chain_preview
+            .clone()
+            .expect("alice previewed going on chain");
+        assert!(outcome.local_uis[1].go_on_chain_preview.is_none());
+        let kinds: Vec<PreviewSpendKind> = preview.spends.iter().map(|s| s.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                PreviewSpendKind::ChannelToUnroll,
+                PreviewSpendKind::UnrollTimeout,
+                PreviewSpendKind::RedoMove,
+                PreviewSpendKind::GameTimeoutClaim,
+            ]
+        );
+        assert!(preview.spends.iter().all(|s| s.cost > 0));
+        assert_eq!(
+            preview.total_cost,
+            preview.spends.iter().map(|s| s.cost).sum::<u64>()
+        );
+
+        assert_eq!(preview.games.len(), 1);
+        let game = &preview.games[0];
+        assert_eq!(game.game_id, GameID(1));
+        assert!(game.our_turn && game.redo_move);
+        assert_eq!(preview.unroll_wait_blocks, 15);
+        assert_eq!(
+            game.wait_blocks,
+            preview.unroll_wait_blocks + game.game_timeout.to_u64()
+        );
+        assert_eq!(preview.max_wait_blocks, game.wait_blocks);
+
+        let settled_share = outcome.local_uis[0]
+            .notifications
+            .iter()
+            .find_map(|n| match n {
+                GameNotification::GameSettled { id, our_share, .. } if *id == GameID(1) => {
+                    Some(our_share.clone())
+                }
+                _ => None,
+            })
+            .expect("alice's game settled");
+        assert!(settled_share > Amount::default());
+        assert_eq!(game.our_payout_if_silent, settled_share);
+        assert_eq!(
+            preview.total_payout_if_silent,
+            preview.our_channel_payout.clone() + settled_share
+        );
+
+        assert!(outcome.cradles[0]
+            .preview_go_on_chain(&mut allocator)
+            .is_err());
+    }));
+
+    res.push(("test_session_journal_replays_on_chain_game", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Same script as test_go_on_chain_then_move_queued_and_replayed: it
+        // crosses the handshake, an off-chain game, the unroll and an on-chain
+        // timeout, so every kind of journaled input shows up.  Journaling is
+        // off unless a script asks for it.
+        let mut all_moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        all_moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let moves = vec![
+            SimScriptAction::JournalSessions(DEFAULT_JOURNAL_LIMIT),
+            SimScriptAction::WaitBlocks(5, 0),
+            all_moves[0].clone(),
+            all_moves[1].clone(),
+            SimScriptAction::NerfMessages(0),
+            all_moves[2].clone(),
+            SimScriptAction::GoOnChain(0),
+            all_moves[4].clone(),
+            SimScriptAction::NerfTransactions(1),
+            SimScriptAction::WaitBlocks(120, 0),
+        ];
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .expect("should finish");
+
+        for (i, cradle) in outcome.cradles.iter().enumerate() {
+            let keys = &SessionKeys {
+                identity: outcome.identities[i].private_key.clone(),
+                channel: outcome.private_keys[i].clone(),
+            };
+            let bytes = cradle
+                .journal()
+                .expect("journaling")
+                .to_bytes()
+                .expect("encode");
+            let mut journal = SessionJournal::from_bytes(&bytes).expect("decode");
+            assert!(
+                journal
+                    .entries
+                    .iter()
+                    .any(|e| matches!(e.input, SessionInput::ReportPuzzleAndSolution(..))),
+                "player {i} journal should reach the on-chain phase"
+            );
+
+            let mut replay_allocator = AllocEncoder::new();
+            let mut drained = 0;
+            let report = replay(&mut replay_allocator, &journal, keys, |step| {
+                drained += step.events.len();
+            })
+            .expect("replay");
+            assert_eq!(journal.dropped, 0, "player {i} journal hit its limit");
+            assert_eq!(report.divergence, None, "player {i} replay diverged");
+            assert_eq!(report.steps, journal.entries.len());
+            assert!(drained > 0, "player {i} replay drained no events");
+
+            let tampered = journal
+                .entries
+                .iter()
+                .rposition(|e| !e.output.is_empty())
+                .expect("some step returned a result");
+            journal.entries[tampered].output.push(0);
+            let report = replay(&mut replay_allocator, &journal, keys, |_| {}).expect("replay");
+            assert_eq!(
+                report.divergence.map(|d| d.index),
+                Some(tampered),
+                "player {i} tampered journal"
+            );
+            let other = &SessionKeys {
+                channel: outcome.private_keys[i ^ 1].clone(),
+                ..keys.clone()
+            };
+            assert!(
+                replay(&mut replay_allocator, &journal, other, |_| {}).is_err(),
+                "player {i} journal replayed with the wrong keys"
+            );
+        }
+    }));
+
+    res.push(("test_session_journal_stops_at_its_limit", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // A journal too small for a whole game keeps a replayable prefix and
+        // counts the calls it left out.
+        const LIMIT: usize = 4096;
+        let mut moves = vec![
+            SimScriptAction::JournalSessions(LIMIT),
+            SimScriptAction::WaitBlocks(5, 0),
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|move_number, _| move_number == moves.len()),
+            None,
+        )
+        .expect("should finish");
+
+        for (i, cradle) in outcome.cradles.iter().enumerate() {
+            let journal = cradle.journal().expect("journaling");
+            assert!(journal.dropped > 0, "player {i} journal never filled up");
+            assert!(!journal.entries.is_empty() && journal.recorded <= LIMIT);
+            let journal =
+                SessionJournal::from_bytes(&journal.to_bytes().expect("encode")).expect("decode");
+
+            let mut replay_allocator = AllocEncoder::new();
+            let keys = SessionKeys {
+                identity: outcome.identities[i].private_key.clone(),
+                channel: outcome.private_keys[i].clone(),
+            };
+            let report = replay(&mut replay_allocator, &journal, &keys, |_| {}).expect("replay");
+            assert_eq!(report.divergence, None, "player {i} replay diverged");
+            assert_eq!(report.steps, journal.entries.len());
+        }
+    }));
+
+    res.push(("test_liveness_policy_goes_on_chain_against_stalled_peer", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Like test_go_on_chain_then_move_queued_and_replayed, but nobody
+        // asks to go on chain: Alice's commit never reaches Bob, so Bob holds
+        // the potato mid-game and Alice's liveness policy warns and then
+        // unrolls on its own.
+        let mut all_moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        all_moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let moves = vec![
+            SimScriptAction::WaitBlocks(5, 0),
+            all_moves[0].clone(),
+            all_moves[1].clone(),
+            SimScriptAction::SetLivenessPolicy(
+                0,
+                LivenessPolicy {
+                    stall_warning_blocks: Some(3),
+                    stall_go_on_chain_blocks: Some(8),
+                    ..LivenessPolicy::default()
+                },
+            ),
+            SimScriptAction::NerfMessages(0),
+            all_moves[2].clone(),
+            all_moves[4].clone(),
+            SimScriptAction::NerfTransactions(1),
+            SimScriptAction::WaitBlocks(120, 0),
+        ];
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .expect("should finish");
+
+        let p0_notifs = &outcome.local_uis[0].notifications;
+        let advisory_for = |wanted: ChannelStatus| {
+            p0_notifs.iter().find_map(|n| match n {
+                GameNotification::ChannelStatus {
+                    state, advisory, ..
+                } if *state == wanted => advisory.clone(),
+                _ => None,
+            })
+        };
+        assert_eq!(
+            advisory_for(ChannelStatus::Active).as_deref(),
+            Some("peer has not responded in 3 blocks"),
+            "alice should be warned before going on chain, got: {p0_notifs:?}"
+        );
+        assert_eq!(
+            advisory_for(ChannelStatus::GoingOnChain).as_deref(),
+            Some("peer unresponsive for 8 blocks"),
+            "alice should go on chain for liveness, got: {p0_notifs:?}"
+        );
+        assert!(
+            p0_notifs
+                .iter()
+                .any(|n| matches!(n, GameNotification::GameSettled { outcome, .. } if is_opponent_side_settlement(*outcome))),
+            "alice should get OpponentTimedOut (bob was nerfed), got: {p0_notifs:?}"
+        );
+        assert!(
+            outcome.local_uis[1].notifications.iter().all(|n| !matches!(
+                n,
+                GameNotification::ChannelStatus {
+                    advisory: Some(advisory),
+                    ..
+                } if advisory.starts_with("peer ")
+            )),
+            "bob has no liveness policy and should never be warned"
+        );
+    }));
+
+    res.push(("test_liveness_ignores_cheap_peer_frames", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // As above, but Bob keeps sending potato requests while he sits on
+        // the potato.  They change nothing, so they must not restart Alice's
+        // stall clock: she goes on chain while they are still arriving.
+        let request = bencodex::to_vec(&PeerMessage::RequestPotato(()))
+            .expect("should encode potato request");
+        let mut all_moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        all_moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let mut moves = vec![
+            SimScriptAction::WaitBlocks(5, 0),
+            all_moves[0].clone(),
+            all_moves[1].clone(),
+            SimScriptAction::SetLivenessPolicy(
+                0,
+                LivenessPolicy {
+                    stall_go_on_chain_blocks: Some(8),
+                    ..LivenessPolicy::default()
+                },
+            ),
+            SimScriptAction::NerfMessages(0),
+            all_moves[2].clone(),
+        ];
+        for _ in 0..20 {
+            moves.push(SimScriptAction::InjectRawMessage(0, request.clone()));
+            moves.push(SimScriptAction::WaitBlocks(2, 0));
+        }
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|move_number, cradles| cradles[0].is_on_chain() || move_number == moves.len()),
+            Some(200),
+        )
+        .expect("should finish");
+
+        assert!(
+            outcome.cradles[0].is_on_chain(),
+            "alice should go on chain before the requests stop"
+        );
+        assert!(
+            outcome.local_uis[0].notifications.iter().any(|n| matches!(
+                n,
+                GameNotification::ChannelStatus {
+                    state: ChannelStatus::GoingOnChain,
+                    advisory: Some(advisory),
+                    ..
+                } if advisory == "peer unresponsive for 8 blocks"
+            )),
+            "alice should go on chain for liveness, got: {:?}",
+            outcome.local_uis[0].notifications
+        );
+    }));
+
+    res.push(("test_liveness_policy_withheld_potato", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Bob holds the potato after Alice's commit and never answers her
+        // request for it: his messages are dropped, and the potato requests
+        // injected on his behalf do not hand it over.  Alice's potato policy
+        // warns and then unrolls on its own.
+        let request = bencodex::to_vec(&PeerMessage::RequestPotato(()))
+            .expect("should encode potato request");
+        let mut all_moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        all_moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let mut moves = vec![
+            SimScriptAction::WaitBlocks(5, 0),
+            all_moves[0].clone(),
+            all_moves[1].clone(),
+            all_moves[2].clone(),
+            SimScriptAction::NerfMessages(1),
+            SimScriptAction::SetLivenessPolicy(
+                0,
+                LivenessPolicy {
+                    potato_warning_blocks: Some(3),
+                    potato_go_on_chain_blocks: Some(6),
+                    ..LivenessPolicy::default()
+                },
+            ),
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+        ];
+        for _ in 0..20 {
+            moves.push(SimScriptAction::InjectRawMessage(0, request.clone()));
+            moves.push(SimScriptAction::WaitBlocks(2, 0));
+        }
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|move_number, cradles| cradles[0].is_on_chain() || move_number == moves.len()),
+            Some(200),
+        )
+        .expect("should finish");
+
+        assert!(
+            outcome.cradles[0].is_on_chain(),
+            "alice should go on chain before the requests stop"
+        );
+        let p0_notifs = &outcome.local_uis[0].notifications;
+        let advisory_for = |wanted: ChannelStatus| {
+            p0_notifs.iter().find_map(|n| match n {
+                GameNotification::ChannelStatus {
+                    state, advisory, ..
+                } if *state == wanted => advisory.clone(),
+                _ => None,
+            })
+        };
+        assert_eq!(
+            advisory_for(ChannelStatus::Active).as_deref(),
+            Some("peer has not sent the potato in 3 blocks"),
+            "alice should be warned before going on chain, got: {p0_notifs:?}"
+        );
+        assert_eq!(
+            advisory_for(ChannelStatus::GoingOnChain).as_deref(),
+            Some("peer withheld the potato for 6 blocks"),
+            "alice should go on chain for the potato, got: {p0_notifs:?}"
+        );
+    }));
+
+    res.push(("test_peer_limit_batch_actions_goes_on_chain", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Bob accepts only empty potato passes, so Alice's proposal batch
+        // is a protocol violation.
+        let moves = vec![
+            SimScriptAction::WaitBlocks(5, 0),
+            SimScriptAction::SetPeerLimits(
+                1,
+                PeerLimits {
+                    max_batch_actions: 0,
+                    ..PeerLimits::default()
+                },
+            ),
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::WaitBlocks(20, 0),
+        ];
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|_, cradles| cradles[1].is_on_chain()),
+            None,
+        )
+        .expect("should finish");
+
+        assert!(
+            outcome.cradles[1].is_on_chain(),
+            "oversized batch should send bob on chain, got: {:?}",
+            outcome.local_uis[1].notifications
+        );
+    }));
+
+    res.push(("test_peer_limit_game_message_bytes_goes_on_chain", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // After Bob's seed move, Alice's handler sends her 16-byte seed as a
+        // game message; Bob allows only 8 bytes per move.
+        let mut all_moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        all_moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let moves = vec![
+            SimScriptAction::WaitBlocks(5, 0),
+            SimScriptAction::SetPeerLimits(
+                1,
+                PeerLimits {
+                    max_game_message_bytes: 8,
+                    ..PeerLimits::default()
+                },
+            ),
+            all_moves[0].clone(),
+            all_moves[1].clone(),
+            all_moves[2].clone(),
+            all_moves[3].clone(),
+            SimScriptAction::WaitBlocks(20, 0),
+        ];
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|_, cradles| cradles[1].is_on_chain()),
+            None,
+        )
+        .expect("should finish");
+
+        assert!(
+            outcome.cradles[1].is_on_chain(),
+            "oversized game message should send bob on chain, got: {:?}",
+            outcome.local_uis[1].notifications
+        );
+    }));
+
+    res.push(("test_peer_limit_clvm_cost_goes_on_chain", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Validating Alice's commit costs far more than Bob's budget.
+        let mut all_moves = vec![
+            SimScriptAction::ProposeNewGame(0, ProposeTrigger::Channel),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+        ];
+        all_moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let moves = vec![
+            SimScriptAction::WaitBlocks(5, 0),
+            all_moves[0].clone(),
+            all_moves[1].clone(),
+            SimScriptAction::SetPeerLimits(
+                1,
+                PeerLimits {
+                    max_message_clvm_cost: 1000,
+                    ..PeerLimits::default()
+                },
+            ),
+            all_moves[2].clone(),
+            SimScriptAction::WaitBlocks(20, 0),
+        ];
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|_, cradles| cradles[1].is_on_chain()),
+            None,
+        )
+        .expect("should finish");
+
+        assert!(
+            outcome.cradles[1].is_on_chain(),
+            "over-budget move should send bob on chain, got: {:?}",
+            outcome.local_uis[1].notifications
+        );
+    }));
+
+    res.push(("test_peer_limit_queued_messages_goes_on_chain", &|| {
+        let mut allocator = AllocEncoder::new();
+        let request = bencodex::to_vec(&PeerMessage::RequestPotato(()))
+            .expect("should encode potato request");
+
+        let moves = vec![
+            SimScriptAction::WaitBlocks(5, 0),
+            SimScriptAction::SetPeerLimits(
+                0,
+                PeerLimits {
+                    max_queued_messages: 0,
+                    ..PeerLimits::default()
+                },
+            ),
+            SimScriptAction::InjectRawMessage(0, request),
+            SimScriptAction::WaitBlocks(20, 0),
+        ];
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|_, cradles| cradles[0].is_on_chain()),
+            None,
+        )
+        .expect("should finish");
+
+        assert!(
+            outcome.cradles[0].is_on_chain(),
+            "queue overrun should send alice on chain, got: {:?}",
+            outcome.local_uis[0].notifications
+        );
+    }));
+
+    // ──────────────────────────────────────────────────────────────────
+    // Proposal lifecycle tests
+    // ──────────────────────────────────────────────────────────────────
+
+    res.push(("test_proposal_received_before_channel_coin_report", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Bob's peer messages still arrive, but his watched coin reports are
+        // delayed. Alice can observe the channel coin first, enter Active, and
         // send a proposal batch while Bob is still in the handshake handler.
         // Replaying Bob's coin reports must transition him to OffChainPhase and
         // process the queued proposal.
@@ -5921,7 +7105,11 @@ pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
         let mut allocator = AllocEncoder::new();
 
         let moves = vec![
-            SimScriptAction::ProposeNewGameWithTimeout(0, ProposeTrigger::Channel, 27),
+            SimScriptAction::ProposeNewGameWithTimeout(
+                0,
+                ProposeTrigger::Channel,
+                Timeout::new(27),
+            ),
             SimScriptAction::AcceptProposal(1, GameID(1)),
             SimScriptAction::WaitBlocks(3, 0),
         ];
@@ -5956,6 +7144,45 @@ pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
         );
     }));
 
+    res.push(("test_seconds_timeouts_resolve_on_chain", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Unroll and game timeouts in seconds.  The strict simulator rejects
+        // any spend whose ASSERT_SECONDS_RELATIVE has not yet passed, so the
+        // game settling shows both timeouts matured against block times.
+        let moves = vec![
+            SimScriptAction::UnrollTimeout(Timeout::seconds(300)),
+            SimScriptAction::ProposeNewGameWithTimeout(
+                0,
+                ProposeTrigger::Channel,
+                Timeout::seconds(300),
+            ),
+            SimScriptAction::AcceptProposal(1, GameID(1)),
+            SimScriptAction::GoOnChain(1),
+            SimScriptAction::WaitBlocks(40, 1),
+        ];
+
+        let outcome =
+            run_calpoker_container_with_action_list(&mut allocator, &moves).expect("should finish");
+
+        let p0_notifs = &outcome.local_uis[0].notifications;
+        let p1_notifs = &outcome.local_uis[1].notifications;
+        assert_reward_coin_consistency(p0_notifs, "seconds_timeouts p0");
+        assert_reward_coin_consistency(p1_notifs, "seconds_timeouts p1");
+        assert!(
+            p0_notifs
+                .iter()
+                .any(|n| matches!(n, GameNotification::GameSettled { outcome, .. } if is_our_side_settlement(*outcome))),
+            "player 0 should time out on chain, got: {p0_notifs:?}"
+        );
+        assert!(
+            p1_notifs
+                .iter()
+                .any(|n| matches!(n, GameNotification::GameSettled { outcome, .. } if is_opponent_side_settlement(*outcome))),
+            "player 1 should claim the timeout, got: {p1_notifs:?}"
+        );
+    }));
+
     res.push(("test_proposal_cancel_by_receiver", &|| {
         let mut allocator = AllocEncoder::new();
 
@@ -5999,6 +7226,291 @@ pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
         );
     }));
 
+    res.push(("test_match_alternates_proposer_and_completes", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // A two-hand match.  Alice proposes and opens hand 1; after it
+        // settles Bob's session proposes hand 2, which Bob opens, and Alice's
+        // accepts it without any further calls.
+        let terms = calpoker_match_terms(&mut allocator, Some(2), None);
+        let mut moves = vec![
+            SimScriptAction::StartMatch(0, terms.clone()),
+            SimScriptAction::AcceptMatch(1, GameID(1), terms),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        // Hand 2 deals a different hand; Bob opens it.
+        moves.extend(swap_seats(calpoker_test_moves_with_selected_cards(
+            &mut allocator,
+            GameID(0),
+            &[12, 26, 33, 39],
+            &[0, 9, 18, 34],
+        )));
+        // Bob makes hand 2's last move, so he knows first that it's over.
+        moves.push(SimScriptAction::CleanShutdown(1));
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            None,
+            Some(200),
+        )
+        .expect("should finish");
+
+        for (who, name) in [(0, "Alice"), (1, "Bob")] {
+            let notifs = &outcome.local_uis[who].notifications;
+            let started: Vec<(GameID, u32, bool)> = notifs
+                .iter()
+                .filter_map(|n| match n {
+                    GameNotification::MatchHandStarted {
+                        id,
+                        hand,
+                        we_proposed,
+                    } => Some((*id, *hand, *we_proposed)),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(
+                started,
+                vec![(GameID(1), 1, who == 0), (GameID(0), 2, who == 1)],
+                "{name} should see both hands start, got: {notifs:?}"
+            );
+            assert_eq!(
+                match_ended(notifs),
+                Some((MatchEndReason::Completed, 2)),
+                "{name}'s match should complete after two hands, got: {notifs:?}"
+            );
+        }
+        let scores: Vec<MatchScore> = (0..2)
+            .map(|who| {
+                outcome.local_uis[who]
+                    .notifications
+                    .iter()
+                    .find_map(|n| match n {
+                        GameNotification::MatchEnded { score, .. } => Some(score.clone()),
+                        _ => None,
+                    })
+                    .expect("match ended")
+            })
+            .collect();
+        // Each side's wins are the other's losses.
+        assert_eq!(scores[0].hands_won, scores[1].hands_lost, "{scores:?}");
+        assert_eq!(scores[0].hands_lost, scores[1].hands_won, "{scores:?}");
+        assert_eq!(scores[0].won, scores[1].lost, "{scores:?}");
+        assert_eq!(scores[0].lost, scores[1].won, "{scores:?}");
+        assert!(
+            !outcome.local_uis[0].notifications.iter().any(
+                |n| matches!(n, GameNotification::ProposalMade { id, .. } if *id != GameID(0))
+            ),
+            "no third hand should be proposed"
+        );
+        let (p1_balance, p2_balance) = get_balances_from_outcome(&outcome).expect("should work");
+        assert_eq!(
+            p1_balance + 2 * scores[0].lost.to_u64(),
+            p2_balance + 2 * scores[0].won.to_u64(),
+            "balances should move by the match score"
+        );
+    }));
+
+    res.push(("test_match_stop_reaches_peer", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // An open-ended match.  Alice asks to stop during hand 1.  Bob hears
+        // of it through her StopMatch, or through her declining a hand 2 he
+        // proposed before it arrived; either way both matches end after one
+        // hand.
+        let terms = calpoker_match_terms(&mut allocator, None, None);
+        let mut moves = vec![
+            SimScriptAction::StartMatch(0, terms.clone()),
+            SimScriptAction::AcceptMatch(1, GameID(1), terms),
+        ];
+        let hand = prefix_test_moves(&mut allocator, GameID(1));
+        let (head, tail) = hand.split_at(2);
+        moves.extend(head.iter().cloned());
+        moves.push(SimScriptAction::StopMatch(0));
+        moves.extend(tail.iter().cloned());
+        let move_count = moves.len();
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|move_number, cradles| {
+                move_number >= move_count && cradles.iter().all(|c| c.match_score().is_none())
+            }),
+            Some(200),
+        )
+        .expect("should finish");
+
+        let p0_notifs = &outcome.local_uis[0].notifications;
+        let p1_notifs = &outcome.local_uis[1].notifications;
+        assert_eq!(
+            match_ended(p0_notifs),
+            Some((MatchEndReason::StoppedByUs, 1)),
+            "Alice stopped, got: {p0_notifs:?}"
+        );
+        assert_eq!(
+            match_ended(p1_notifs),
+            Some((MatchEndReason::StoppedByPeer, 1)),
+            "Alice stopped Bob's match, got: {p1_notifs:?}"
+        );
+        for notifs in [p0_notifs, p1_notifs] {
+            assert!(
+                !notifs
+                    .iter()
+                    .any(|n| matches!(n, GameNotification::MatchHandStarted { hand: 2, .. })),
+                "no second hand should start, got: {notifs:?}"
+            );
+        }
+    }));
+
+    res.push(("test_match_stop_by_next_proposer", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Bob, who proposes hand 2, asks to stop during hand 1.  He doesn't
+        // propose it, and his StopMatch ends Alice's match too.
+        let terms = calpoker_match_terms(&mut allocator, None, None);
+        let mut moves = vec![
+            SimScriptAction::StartMatch(0, terms.clone()),
+            SimScriptAction::AcceptMatch(1, GameID(1), terms),
+        ];
+        let hand = prefix_test_moves(&mut allocator, GameID(1));
+        let (head, tail) = hand.split_at(2);
+        moves.extend(head.iter().cloned());
+        moves.push(SimScriptAction::StopMatch(1));
+        moves.extend(tail.iter().cloned());
+        let move_count = moves.len();
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|move_number, cradles| {
+                move_number >= move_count && cradles.iter().all(|c| c.match_score().is_none())
+            }),
+            Some(200),
+        )
+        .expect("should finish");
+
+        let p0_notifs = &outcome.local_uis[0].notifications;
+        let p1_notifs = &outcome.local_uis[1].notifications;
+        assert_eq!(
+            match_ended(p1_notifs),
+            Some((MatchEndReason::StoppedByUs, 1)),
+            "Bob stopped, got: {p1_notifs:?}"
+        );
+        assert_eq!(
+            match_ended(p0_notifs),
+            Some((MatchEndReason::StoppedByPeer, 1)),
+            "Bob's stop should end Alice's match, got: {p0_notifs:?}"
+        );
+        assert!(
+            !p0_notifs
+                .iter()
+                .any(|n| matches!(n, GameNotification::ProposalMade { .. })),
+            "Bob should not propose hand 2, got: {p0_notifs:?}"
+        );
+    }));
+
+    res.push(("test_match_leaves_other_proposals", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Bob's terms differ from Alice's in the parameters only.  His hand 2
+        // proposal is left for Alice's UI instead of being accepted, and her
+        // match carries on waiting for the agreed hand.
+        let terms = calpoker_match_terms(&mut allocator, None, None);
+        let parameters = (Amount::new(100), (false, ()))
+            .to_clvm(&mut allocator)
+            .expect("should encode");
+        let bob_terms = MatchTerms {
+            proposal: GameProposal {
+                parameters: Program::from_nodeptr(&mut allocator, parameters)
+                    .expect("should build"),
+                ..terms.proposal.clone()
+            },
+            ..terms.clone()
+        };
+        let mut moves = vec![
+            SimScriptAction::StartMatch(0, terms),
+            SimScriptAction::AcceptMatch(1, GameID(1), bob_terms),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let move_count = moves.len();
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|move_number, cradles| {
+                move_number >= move_count
+                    && cradles[0]
+                        .proposal_contributions_for_testing()
+                        .is_ok_and(|proposals| proposals.iter().any(|(id, ..)| *id == GameID(0)))
+            }),
+            Some(200),
+        )
+        .expect("should finish");
+
+        let p0_notifs = &outcome.local_uis[0].notifications;
+        assert!(
+            p0_notifs
+                .iter()
+                .any(|n| matches!(n, GameNotification::ProposalMade { id: GameID(0), .. })),
+            "Alice should see Bob's hand 2, got: {p0_notifs:?}"
+        );
+        assert!(
+            !p0_notifs.iter().any(|n| matches!(
+                n,
+                GameNotification::ProposalAccepted { id: GameID(0), .. }
+                    | GameNotification::MatchEnded { .. }
+            )),
+            "Alice should neither accept it nor end her match, got: {p0_notifs:?}"
+        );
+    }));
+
+    res.push(("test_match_stop_loss_ends_match", &|| {
+        let mut allocator = AllocEncoder::new();
+
+        // Alice loses hand 1 (100) with a stop-loss of 100, so her session
+        // ends the match instead of accepting Bob's hand 2.
+        let terms = calpoker_match_terms(&mut allocator, Some(5), None);
+        let alice_terms = MatchTerms {
+            stop_loss: Some(Amount::new(100)),
+            ..terms.clone()
+        };
+        let mut moves = vec![
+            SimScriptAction::StartMatch(0, alice_terms),
+            SimScriptAction::AcceptMatch(1, GameID(1), terms),
+        ];
+        moves.extend(prefix_test_moves(&mut allocator, GameID(1)));
+        let move_count = moves.len();
+
+        let outcome = run_calpoker_container_with_action_list_with_success_predicate(
+            &mut allocator,
+            &moves,
+            Some(&|move_number, cradles| {
+                move_number >= move_count && cradles.iter().all(|c| c.match_score().is_none())
+            }),
+            Some(200),
+        )
+        .expect("should finish");
+
+        let p0_notifs = &outcome.local_uis[0].notifications;
+        assert_eq!(
+            match_ended(p0_notifs),
+            Some((MatchEndReason::StopLoss, 1)),
+            "Alice hit her stop-loss, got: {p0_notifs:?}"
+        );
+        assert!(
+            p0_notifs.iter().any(|n| matches!(
+                n,
+                GameNotification::MatchHandSettled { hand: 1, score, .. }
+                    if score.net_loss() == Amount::new(100)
+            )),
+            "Alice's score should show the loss, got: {p0_notifs:?}"
+        );
+        assert_eq!(
+            match_ended(&outcome.local_uis[1].notifications),
+            Some((MatchEndReason::StoppedByPeer, 1)),
+        );
+    }));
+
     res.push(("propose_attempt_rejected_when_peer_proposal_pending", &|| {
         let mut allocator = AllocEncoder::new();
 
@@ -0,0 +1,378 @@
+//! The embedded `StandardWallet` funding a channel and trading an offer
+//! against the simulator, with no external wallet in the loop.
+
+use std::collections::BTreeSet;
+
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::channel_manager::{ChannelFundingSource, ChannelId, ChannelManager};
+use crate::channel_state::types::ChannelPrivateKeys;
+use crate::common::constants::SINGLETON_LAUNCHER_HASH;
+use crate::common::standard_coin::ChiaIdentity;
+use crate::common::types::{AllocEncoder, Amount, CoinString, PrivateKey, PuzzleHash};
+use crate::game_session::GameSession;
+use crate::offer_file::{decode_offer, encode_offer};
+use crate::session_phases::effects::{GameNotification, GameSessionEvent};
+use crate::simulator::tests::channel_manager_sim::{balance, is_clean, push, session_config};
+use crate::simulator::Simulator;
+use crate::transaction_manager::TransactionManager;
+use crate::wallet::{offer_terms, OfferTerms, StandardWallet};
+
+/// Report the chain state of every coin the wallet tracks or owns.
+fn sync_wallet(simulator: &Simulator, wallet: &mut StandardWallet) {
+    let mut coins = simulator
+        .get_my_coins(&wallet.identity().puzzle_hash)
+        .expect("coins");
+    coins.extend(wallet.tracked_coins());
+    wallet.report_coin_states(&simulator.get_coin_states(&coins));
+}
+
+fn identities(allocator: &mut AllocEncoder, seed: u8, count: usize) -> Vec<ChiaIdentity> {
+    let mut rng = ChaCha8Rng::from_seed([seed; 32]);
+    (0..count)
+        .map(|_| {
+            let pk: PrivateKey = rng.random();
+            ChiaIdentity::new(allocator, pk).expect("identity")
+        })
+        .collect()
+}
+
+/// One round between a `ChannelManager` funding from `wallet` and a lone
+/// peer funding from `peer_wallet`: farm a block, report it, and trade
+/// messages and submissions both ways.
+#[allow(clippy::too_many_arguments)]
+fn manager_round(
+    allocator: &mut AllocEncoder,
+    simulator: &Simulator,
+    farmer: &PuzzleHash,
+    manager: &mut ChannelManager,
+    wallet: &mut StandardWallet,
+    id: ChannelId,
+    peer: &mut TransactionManager<GameSession>,
+    peer_wallet: &mut StandardWallet,
+) {
+    simulator.farm_block(farmer);
+    let height = simulator.get_current_height() as u64;
+    let records = simulator.get_all_coin_states();
+    manager
+        .report_coin_states(allocator, height, &records)
+        .expect("report");
+    peer.report_coin_states(allocator, height, &records)
+        .expect("report");
+    sync_wallet(simulator, wallet);
+    sync_wallet(simulator, peer_wallet);
+
+    let drain = manager
+        .flush_and_collect(allocator, Some(wallet))
+        .expect("flush");
+    assert!(drain.errors.is_empty(), "{:?}", drain.errors);
+    for (_, event) in drain.events {
+        if let GameSessionEvent::OutboundMessage(msg) = event {
+            peer.deliver_message(&msg).expect("deliver");
+        }
+    }
+    let submissions = manager.drain_submissions();
+    assert!(submissions.errors.is_empty(), "{:?}", submissions.errors);
+    for (_, bundle) in submissions.bundles {
+        push(simulator, allocator, &bundle);
+    }
+
+    for event in peer.flush_and_collect(allocator).expect("flush").events {
+        match event {
+            GameSessionEvent::OutboundMessage(msg) => {
+                manager.deliver_message(id, &msg).expect("deliver");
+            }
+            GameSessionEvent::NeedCoinSpend(request) => {
+                let bundle = peer_wallet
+                    .fund_request(allocator, &request, &BTreeSet::new())
+                    .expect("fund");
+                peer.provide_coin_spend_bundle(allocator, bundle)
+                    .expect("fund");
+            }
+            _ => {}
+        }
+    }
+    for bundle in peer.drain_submissions().expect("drain") {
+        push(simulator, allocator, &bundle);
+    }
+}
+
+pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
+    let mut res: Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> = Vec::new();
+
+    res.push(("test_standard_wallet_funds_channel", &|| {
+        let mut allocator = AllocEncoder::new();
+        let ids = identities(&mut allocator, 11, 3);
+        let neutral = ids[2].clone();
+        let simulator = Simulator::new_strict();
+        // Two blocks each: no single reward coin covers the contribution, so
+        // both funding spends have to combine coins.
+        for id in ids[..2].iter() {
+            simulator.farm_block(&id.puzzle_hash);
+            simulator.farm_block(&id.puzzle_hash);
+        }
+        let contribution = 3_000_000_000_000;
+        let fee = 7;
+        let starts: Vec<u64> = ids[..2].iter().map(|id| balance(&simulator, id)).collect();
+
+        let mut rng = ChaCha8Rng::from_seed([12; 32]);
+        let mut wallets: Vec<StandardWallet> = Vec::new();
+        let mut peers: Vec<TransactionManager<GameSession>> = Vec::new();
+        for (index, id) in ids[..2].iter().enumerate() {
+            let mut wallet = StandardWallet::new(id.clone(), Amount::new(fee));
+            sync_wallet(&simulator, &mut wallet);
+            assert_eq!(wallet.balance(), Amount::new(starts[index]));
+            wallets.push(wallet);
+            let keys: ChannelPrivateKeys = rng.random();
+            let config = session_config(&mut allocator, id, index == 0, contribution);
+            peers.push(TransactionManager::new(GameSession::new_with_keys(
+                config, keys,
+            )));
+        }
+        peers[0].start_handshake(&mut allocator).expect("start");
+
+        let mut notifications: [Vec<GameNotification>; 2] = [Vec::new(), Vec::new()];
+        let mut funding_spends = [0; 2];
+        let mut shutting_down = false;
+        for _ in 0..200 {
+            simulator.farm_block(&neutral.puzzle_hash);
+            let height = simulator.get_current_height() as u64;
+            let records = simulator.get_all_coin_states();
+            for (peer, wallet) in peers.iter_mut().zip(wallets.iter_mut()) {
+                peer.report_coin_states(&mut allocator, height, &records)
+                    .expect("report");
+                sync_wallet(&simulator, wallet);
+            }
+
+            for index in 0..2 {
+                let drain = peers[index]
+                    .flush_and_collect(&mut allocator)
+                    .expect("flush");
+                for event in drain.events {
+                    match event {
+                        GameSessionEvent::OutboundMessage(msg) => {
+                            peers[1 - index].deliver_message(&msg).expect("deliver");
+                        }
+                        GameSessionEvent::Notification(n) => notifications[index].push(n),
+                        GameSessionEvent::NeedLauncherCoin => {
+                            let parent = wallets[index]
+                                .launcher_parent(&Amount::new(contribution), &BTreeSet::new())
+                                .expect("launcher parent");
+                            let launcher = CoinString::from_parts(
+                                &parent.to_coin_id(),
+                                &PuzzleHash::from_bytes(SINGLETON_LAUNCHER_HASH),
+                                &Amount::default(),
+                            );
+                            peers[index]
+                                .provide_launcher_coin(&mut allocator, launcher)
+                                .expect("launcher");
+                        }
+                        GameSessionEvent::NeedCoinSpend(request) => {
+                            let bundle = wallets[index]
+                                .fund_request(&mut allocator, &request, &BTreeSet::new())
+                                .expect("fund");
+                            funding_spends[index] = bundle.spends.len();
+                            peers[index]
+                                .provide_coin_spend_bundle(&mut allocator, bundle)
+                                .expect("fund");
+                        }
+                        GameSessionEvent::CoinSolutionRequest(coin) => {
+                            let ps = simulator
+                                .get_puzzle_and_solution(&coin.to_coin_id())
+                                .expect("puzzle and solution");
+                            peers[index]
+                                .report_puzzle_and_solution(
+                                    &mut allocator,
+                                    &coin,
+                                    ps.as_ref().map(|(p, s)| (p, s)),
+                                )
+                                .expect("report");
+                        }
+                        _ => {}
+                    }
+                }
+                for bundle in peers[index].drain_submissions().expect("drain") {
+                    push(&simulator, &mut allocator, &bundle);
+                }
+            }
+
+            if !shutting_down && peers.iter().all(|p| p.channel_established()) {
+                shutting_down = true;
+                for (index, wallet) in wallets.iter().enumerate() {
+                    assert_eq!(
+                        wallet.balance(),
+                        Amount::new(starts[index] - contribution - fee),
+                        "wallet {index} should have paid its contribution and fee"
+                    );
+                }
+                peers[0].shut_down(&mut allocator).expect("shut down");
+            }
+            if shutting_down && notifications.iter().all(|n| is_clean(n)) {
+                break;
+            }
+        }
+
+        assert!(shutting_down, "the channel should open");
+        assert_eq!(funding_spends, [2, 2], "each side should combine two coins");
+        simulator.farm_block(&neutral.puzzle_hash);
+        for (index, wallet) in wallets.iter_mut().enumerate() {
+            assert!(is_clean(&notifications[index]));
+            sync_wallet(&simulator, wallet);
+            let expected = starts[index] - fee;
+            assert_eq!(balance(&simulator, wallet.identity()), expected);
+            assert_eq!(wallet.balance(), Amount::new(expected));
+        }
+    }));
+
+    res.push(("test_standard_wallet_funds_after_removed_channel", &|| {
+        let mut allocator = AllocEncoder::new();
+        let ids = identities(&mut allocator, 15, 4);
+        let (hub, neutral) = (&ids[0], &ids[3]);
+        let simulator = Simulator::new_strict();
+        // The hub's one block only just covers a contribution, so a coin
+        // still held for the removed channel would leave it unable to fund
+        // the next one.
+        simulator.farm_block(&hub.puzzle_hash);
+        for id in ids[1..3].iter() {
+            simulator.farm_block(&id.puzzle_hash);
+            simulator.farm_block(&id.puzzle_hash);
+        }
+        let contribution = 1_900_000_000_000;
+        let fee = 7;
+        let mut wallet = StandardWallet::new(hub.clone(), Amount::new(fee));
+        sync_wallet(&simulator, &mut wallet);
+        let start = wallet.balance().to_u64();
+
+        let mut rng = ChaCha8Rng::from_seed([16; 32]);
+        let mut manager = ChannelManager::new();
+        let mut open = |allocator: &mut AllocEncoder, manager: &mut ChannelManager, peer| {
+            let config = session_config(allocator, hub, true, contribution);
+            let id = manager.open_channel(config, rng.random());
+            manager
+                .channel_mut(id)
+                .expect("channel")
+                .start_handshake(allocator)
+                .expect("start");
+            let config = session_config(allocator, peer, false, contribution);
+            let peer = TransactionManager::new(GameSession::new_with_keys(config, rng.random()));
+            (id, peer)
+        };
+
+        // The first channel is dropped as soon as the wallet has picked its
+        // launcher parent.
+        let (first, mut first_peer) = open(&mut allocator, &mut manager, &ids[1]);
+        let mut first_peer_wallet = StandardWallet::new(ids[1].clone(), Amount::new(fee));
+        for _ in 0..10 {
+            if !manager.reserved_coins().is_empty() {
+                break;
+            }
+            manager_round(
+                &mut allocator,
+                &simulator,
+                &neutral.puzzle_hash,
+                &mut manager,
+                &mut wallet,
+                first,
+                &mut first_peer,
+                &mut first_peer_wallet,
+            );
+        }
+        assert!(wallet.balance().to_u64() < start, "a coin should be held");
+        assert!(manager.remove_channel(first, Some(&mut wallet)).is_some());
+        assert_eq!(
+            wallet.balance().to_u64(),
+            start,
+            "removal should release it"
+        );
+
+        let (second, mut second_peer) = open(&mut allocator, &mut manager, &ids[2]);
+        let mut second_peer_wallet = StandardWallet::new(ids[2].clone(), Amount::new(fee));
+        for _ in 0..50 {
+            let established = manager
+                .channel(second)
+                .is_some_and(|c| c.channel_established());
+            if established && second_peer.channel_established() {
+                break;
+            }
+            manager_round(
+                &mut allocator,
+                &simulator,
+                &neutral.puzzle_hash,
+                &mut manager,
+                &mut wallet,
+                second,
+                &mut second_peer,
+                &mut second_peer_wallet,
+            );
+        }
+        assert!(
+            second_peer.channel_established(),
+            "the second channel should open"
+        );
+        sync_wallet(&simulator, &mut wallet);
+        let expected = start - contribution - fee;
+        assert_eq!(wallet.balance(), Amount::new(expected));
+        assert_eq!(balance(&simulator, hub), expected);
+    }));
+
+    res.push(("test_standard_wallet_offer_round_trip", &|| {
+        let mut allocator = AllocEncoder::new();
+        let ids = identities(&mut allocator, 13, 3);
+        let simulator = Simulator::new_strict();
+        for id in ids.iter() {
+            simulator.farm_block(&id.puzzle_hash);
+        }
+        let [maker_start, taker_start] =
+            [balance(&simulator, &ids[0]), balance(&simulator, &ids[1])];
+        let mut maker = StandardWallet::new(ids[0].clone(), Amount::new(3));
+        let mut taker = StandardWallet::new(ids[1].clone(), Amount::new(5));
+        sync_wallet(&simulator, &mut maker);
+        sync_wallet(&simulator, &mut taker);
+
+        let offered = 1_000;
+        let requested = 300;
+        let offer = maker
+            .create_offer(
+                &mut allocator,
+                &Amount::new(offered),
+                &Amount::new(requested),
+                &BTreeSet::new(),
+            )
+            .expect("create offer");
+        let text = encode_offer(&mut allocator, &offer).expect("encode offer");
+        assert!(text.starts_with("offer1"));
+        let offer = decode_offer(&text).expect("decode offer");
+        let terms = offer_terms(&mut allocator, &offer).expect("offer terms");
+        assert_eq!(
+            terms,
+            OfferTerms {
+                offered: Amount::new(offered),
+                requested: Amount::new(requested),
+            }
+        );
+        let greedy = OfferTerms {
+            offered: Amount::new(offered + 1),
+            ..terms.clone()
+        };
+        assert!(taker
+            .take_offer(&mut allocator, &offer, &greedy, &BTreeSet::new())
+            .is_err());
+        let taken = taker
+            .take_offer(&mut allocator, &offer, &terms, &BTreeSet::new())
+            .expect("take offer");
+        push(&simulator, &mut allocator, &taken);
+        simulator.farm_block(&ids[2].puzzle_hash);
+        sync_wallet(&simulator, &mut maker);
+        sync_wallet(&simulator, &mut taker);
+
+        let maker_end = maker_start - offered + requested - 3;
+        let taker_end = taker_start + offered - requested - 5;
+        assert_eq!(balance(&simulator, &ids[0]), maker_end);
+        assert_eq!(balance(&simulator, &ids[1]), taker_end);
+        assert_eq!(maker.balance(), Amount::new(maker_end));
+        assert_eq!(taker.balance(), Amount::new(taker_end));
+    }));
+
+    res
+}
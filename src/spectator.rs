@@ -0,0 +1,849 @@
+//! Spectators: a read-only, verifiable view of a game for a third party.
+//!
+//! Either player can authorize an observer for one of its games with
+//! [`GameSession::authorize_spectator`].  From then on the session emits
+//! [`SpectatorEvent`]s for that game, which the host forwards to the observer
+//! however it likes.  The feed starts with a snapshot of the game's position
+//! signed off by the granting player, then carries every off-chain move with
+//! the mover's half of the unroll signature over the channel state that
+//! commits to it.
+//!
+//! The grant names both players' keys and hashes the starting position and
+//! state, so the snapshot can't be swapped after signing.  The opponent's keys
+//! are tied to the game by the latest channel state the opponent signed that
+//! holds it: their half of the unroll signature over conditions that create
+//! the game coin curried with both referee keys.  A feeder can only fake that
+//! by playing both seats itself.
+//!
+//! A [`Spectator`] checks every move on its own: the mover's signature, that
+//! the signed state holds the game coin curried with exactly this move, that
+//! the validator is the one the move committed to, and that the validator
+//! accepts the move.  A feed therefore can't invent or alter moves.  It can
+//! only stop early, or withhold a move, which the observer sees as the feed
+//! going quiet.  A game's final move commits to no validator, so that one is
+//! checked with the validator the feed names; with both feeds, the two have to
+//! agree.
+//!
+//! Rendering a move for display needs the their-turn handler of the player
+//! receiving it, and that handler can hold secrets the player hasn't revealed
+//! yet (calpoker's handler carries the preimage of its commitment).  The feed
+//! only sends a handler once the player's next move is on the wire, and
+//! drops handlers that were never followed by a move of ours when the game
+//! ends.  With both players' feeds the observer sees both sides' readable
+//! moves.  Moves can also be held back by `delay_moves` so the observer trails
+//! the live game.  A feed starts only once the opponent has signed a state
+//! holding the game, so moves made before then are in its snapshot and can't
+//! be read from it.
+//!
+//! A second feed from the other player cross-checks everything.  The signed
+//! channel state also tells the observer the channel's balances and the other
+//! games' coins.
+//!
+//! [`GameSession::authorize_spectator`]: crate::game_session::GameSession::authorize_spectator
+
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::channel_state::game_handler::{GameHandler, TheirTurnInputs};
+use crate::channel_state::types::{Evidence, ReadableMove, StateUpdateProgram, ValidationInfo};
+use crate::common::standard_coin::verify_partial_signature;
+use crate::common::types::{
+    Aggsig, AllocEncoder, CoinCondition, Error, GameID, Hash, PrivateKey, Program, ProgramRef,
+    PublicKey, PuzzleHash, Sha256tree,
+};
+use crate::referee::types::{
+    curry_referee_puzzle_hash, GameMoveDetails, InternalStateUpdateArgs, RefereePuzzleArgs,
+    StateUpdateMoveArgs, ValidationInfoHash,
+};
+
+/// What a player lets an observer see of one game, and where it starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectatorGrant {
+    pub game_id: GameID,
+    pub observer: PublicKey,
+    /// Moves stay out of the feed until this many later moves are made.
+    pub delay_moves: u32,
+    /// The granting player first.
+    pub players: [SpectatorPlayer; 2],
+    /// The game coin's puzzle hash at the start position.
+    pub position_hash: PuzzleHash,
+    /// Tree hash of the game state the next move applies to.
+    pub state_hash: PuzzleHash,
+}
+
+impl SpectatorGrant {
+    fn message(&self) -> Hash {
+        let mut bytes = b"spectate".to_vec();
+        bytes.extend_from_slice(&self.game_id.0.to_be_bytes());
+        bytes.extend_from_slice(&self.observer.bytes());
+        bytes.extend_from_slice(&self.delay_moves.to_be_bytes());
+        for player in &self.players {
+            bytes.extend_from_slice(&player.referee_pubkey.bytes());
+            bytes.extend_from_slice(&player.unroll_pubkey.bytes());
+        }
+        bytes.extend_from_slice(self.position_hash.bytes());
+        bytes.extend_from_slice(self.state_hash.bytes());
+        Hash::new(&bytes)
+    }
+}
+
+/// A grant signed with the granting player's referee key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedSpectatorGrant {
+    pub grant: SpectatorGrant,
+    pub signature: Aggsig,
+}
+
+impl SignedSpectatorGrant {
+    pub fn sign(referee_private_key: &PrivateKey, grant: SpectatorGrant) -> Self {
+        let signature = referee_private_key.sign(grant.message().bytes());
+        SignedSpectatorGrant { grant, signature }
+    }
+
+    pub fn verify(&self, referee_pubkey: &PublicKey) -> bool {
+        self.signature
+            .verify(referee_pubkey, self.grant.message().bytes())
+    }
+}
+
+/// One player's keys as the observer needs them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectatorPlayer {
+    /// Curried into the game coin as mover or waiter.
+    pub referee_pubkey: PublicKey,
+    /// Signs this player's half of each unroll state.
+    pub unroll_pubkey: PublicKey,
+}
+
+/// The latest channel state the opponent signed that holds the game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectatorCountersigned {
+    /// The game coin's curried arguments in that state.
+    pub position: Rc<RefereePuzzleArgs>,
+    /// The unroll conditions of that state.
+    pub conditions: ProgramRef,
+    /// The opponent's half of the unroll signature over `conditions`.
+    pub signature: Aggsig,
+}
+
+/// Where a feed starts: the game as of the latest move when the grant was
+/// made.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectatorGameStart {
+    pub grant: SignedSpectatorGrant,
+    /// The game coin's curried arguments.
+    pub position: Rc<RefereePuzzleArgs>,
+    /// The game state the next move applies to.
+    pub state: Rc<Program>,
+    pub countersigned: SpectatorCountersigned,
+}
+
+impl SpectatorGameStart {
+    /// The players as the grant names them, the granting player first.
+    pub fn players(&self) -> &[SpectatorPlayer; 2] {
+        &self.grant.grant.players
+    }
+}
+
+/// Whether `conditions` create the game coin curried with `position`.
+pub(crate) fn conditions_hold_position(
+    allocator: &mut AllocEncoder,
+    conditions: &ProgramRef,
+    position: &RefereePuzzleArgs,
+) -> Result<bool, Error> {
+    let puzzle_hash =
+        curry_referee_puzzle_hash(allocator, &position.referee_coin_puzzle_hash, position)?;
+    let conditions_node = conditions.to_nodeptr(allocator)?;
+    Ok(CoinCondition::from_nodeptr(allocator, conditions_node)?
+        .iter()
+        .any(|c| {
+            matches!(c, CoinCondition::CreateCoin(ph, amt)
+                if *ph == puzzle_hash && *amt == position.amount)
+        }))
+}
+
+/// One move as the feed carries it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectatorMove {
+    pub game_id: GameID,
+    /// Referee key of the player whose feed this is.
+    pub feeder: PublicKey,
+    pub details: GameMoveDetails,
+    /// The validator this move committed to running on it.
+    pub validator: StateUpdateProgram,
+    /// The unroll conditions the mover signed with this move in place.
+    pub conditions: ProgramRef,
+    /// The mover's half of the unroll signature over `conditions`.
+    pub signature: Aggsig,
+    /// The feeder's their-turn handler for this move, when it was the
+    /// opponent's.  Only sent once it can't give anything away.
+    #[serde(default)]
+    pub handler: Option<GameHandler>,
+    /// The feeder's signature on `handler` for this move.
+    #[serde(default)]
+    pub handler_signature: Option<Aggsig>,
+}
+
+impl SpectatorMove {
+    fn handler_message(
+        &self,
+        allocator: &mut AllocEncoder,
+        handler: &GameHandler,
+    ) -> Result<Hash, Error> {
+        let mut bytes = b"spectate handler".to_vec();
+        bytes.extend_from_slice(&self.game_id.0.to_be_bytes());
+        bytes.extend_from_slice(Hash::new(&self.details.basic.move_made).bytes());
+        bytes.extend_from_slice(handler.sha256tree(allocator).bytes());
+        Ok(Hash::new(&bytes))
+    }
+
+    /// Attach the handler, signed with the feeder's referee key.
+    pub fn sign_handler(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        referee_private_key: &PrivateKey,
+    ) -> Result<(), Error> {
+        if let Some(handler) = self.handler.clone() {
+            let message = self.handler_message(allocator, &handler)?;
+            self.handler_signature = Some(referee_private_key.sign(message.bytes()));
+        }
+        Ok(())
+    }
+
+    fn signed_handler(&self, allocator: &mut AllocEncoder) -> Result<Option<GameHandler>, Error> {
+        let (Some(handler), Some(signature)) = (&self.handler, &self.handler_signature) else {
+            return Ok(None);
+        };
+        let message = self.handler_message(allocator, handler)?;
+        if !signature.verify(&self.feeder, message.bytes()) {
+            return Err(Error::StrErr(
+                "spectator feed handler isn't signed by the feeder".to_string(),
+            ));
+        }
+        Ok(Some(handler.clone()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpectatorEvent {
+    Start(Box<SpectatorGameStart>),
+    Move(Box<SpectatorMove>),
+    /// The game settled; the feed sends nothing more.
+    Ended {
+        game_id: GameID,
+        feeder: PublicKey,
+    },
+}
+
+/// The feeding side of one grant: holds moves back until they can go out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpectatorFeed {
+    pub observer: PublicKey,
+    /// Our referee key, which the observer knows this feed by.
+    pub feeder: PublicKey,
+    delay_moves: u32,
+    /// Moves not sent yet, oldest first, with whether they were ours.
+    held: VecDeque<(SpectatorMove, bool)>,
+    /// The start isn't sent yet: the peer hasn't signed a state holding the
+    /// game.  Moves made meanwhile are in the start's snapshot instead.
+    #[serde(default)]
+    pub awaiting_start: bool,
+}
+
+impl SpectatorFeed {
+    pub fn new(observer: PublicKey, feeder: PublicKey, delay_moves: u32) -> Self {
+        SpectatorFeed {
+            observer,
+            feeder,
+            delay_moves,
+            held: VecDeque::new(),
+            awaiting_start: false,
+        }
+    }
+
+    pub fn delay_moves(&self) -> u32 {
+        self.delay_moves
+    }
+
+    /// Queue a move and return the ones that can now be sent.  An opponent's
+    /// move waits for a move of ours after it, since its handler may reveal
+    /// what that move will.
+    pub fn push(&mut self, record: SpectatorMove, ours: bool) -> Vec<SpectatorMove> {
+        self.held.push_back((record, ours));
+        let mut released = Vec::new();
+        while let Some((_, front_ours)) = self.held.front() {
+            let later = self.held.len() - 1;
+            let answered = *front_ours || self.held.iter().skip(1).any(|(_, o)| *o);
+            if later < self.delay_moves as usize || !answered {
+                break;
+            }
+            if let Some((record, _)) = self.held.pop_front() {
+                released.push(record);
+            }
+        }
+        released
+    }
+
+    /// Everything still held, at the end of the game.  Handlers for opponent
+    /// moves we never answered are dropped.
+    pub fn finish(&mut self) -> Vec<SpectatorMove> {
+        let held: Vec<(SpectatorMove, bool)> = self.held.drain(..).collect();
+        let mut answered = false;
+        let mut out: Vec<SpectatorMove> = held
+            .into_iter()
+            .rev()
+            .map(|(mut record, ours)| {
+                answered |= ours;
+                if !answered {
+                    record.handler = None;
+                    record.handler_signature = None;
+                }
+                record
+            })
+            .collect();
+        out.reverse();
+        out
+    }
+}
+
+/// A move the spectator has checked.
+#[derive(Debug, Clone)]
+pub struct ObservedMove {
+    /// Index into [`SpectatedGame::players`].
+    pub mover: usize,
+    pub details: GameMoveDetails,
+    pub validator_hash: Hash,
+    /// The game state after this move.
+    pub state: Rc<Program>,
+    /// The move as the player receiving it read it, once a feed has sent the
+    /// handler for it.
+    pub readable: Option<ReadableMove>,
+    /// The validator rejected the move, or its reader found evidence against
+    /// it.  Nothing after it is accepted.
+    pub illegal: bool,
+}
+
+/// One game as the spectator has seen it.
+#[derive(Debug, Clone)]
+pub struct SpectatedGame {
+    pub game_id: GameID,
+    pub players: [SpectatorPlayer; 2],
+    pub moves: Vec<ObservedMove>,
+    pub ended: bool,
+    /// The game coin's arguments before the first observed move and after
+    /// each one.
+    positions: Vec<Rc<RefereePuzzleArgs>>,
+    start_state: Rc<Program>,
+    /// How many moves each player's feed has delivered, once it has started.
+    cursors: [Option<usize>; 2],
+}
+
+/// What a received event changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpectatorUpdate {
+    Started(GameID),
+    Moved {
+        game_id: GameID,
+        index: usize,
+    },
+    /// An earlier move can now be read.
+    Rendered {
+        game_id: GameID,
+        index: usize,
+    },
+    Ended(GameID),
+}
+
+impl SpectatedGame {
+    fn state_before(&self, index: usize) -> Rc<Program> {
+        if index == 0 {
+            self.start_state.clone()
+        } else {
+            self.moves[index - 1].state.clone()
+        }
+    }
+
+    fn player_index(&self, key: &PublicKey) -> Option<usize> {
+        self.players.iter().position(|p| p.referee_pubkey == *key)
+    }
+
+    fn args_after(position: &RefereePuzzleArgs, record: &SpectatorMove) -> RefereePuzzleArgs {
+        RefereePuzzleArgs {
+            mover_pubkey: position.waiter_pubkey.clone(),
+            waiter_pubkey: position.mover_pubkey.clone(),
+            game_move: GameMoveDetails {
+                validation_program_hash: Some(record.validator.hash().clone()),
+                ..record.details.clone()
+            },
+            validation_program: record.validator.clone(),
+            previous_validation_info_hash: position.game_move.validation_info_hash.clone(),
+            ..position.clone()
+        }
+    }
+
+    fn run_validator(
+        allocator: &mut AllocEncoder,
+        args: &RefereePuzzleArgs,
+        state: Rc<Program>,
+        evidence: Evidence,
+    ) -> Result<Option<Rc<Program>>, Error> {
+        InternalStateUpdateArgs {
+            validation_program: args.validation_program.clone(),
+            referee_args: Rc::new(args.swap()),
+            state_update_args: StateUpdateMoveArgs {
+                state,
+                evidence: evidence.to_program(),
+            },
+        }
+        .run(allocator)
+    }
+
+    /// Check a move that extends the game and record it.
+    fn extend(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        record: &SpectatorMove,
+    ) -> Result<(), Error> {
+        if self.ended || self.moves.last().is_some_and(|m| m.illegal) {
+            return Err(Error::StrErr("spectated game is already over".to_string()));
+        }
+        let position = self.positions[self.positions.len() - 1].clone();
+        if position.game_move.validation_info_hash.is_none() {
+            return Err(Error::StrErr(
+                "spectated game takes no more moves".to_string(),
+            ));
+        }
+        let mover = self
+            .player_index(&position.mover_pubkey)
+            .ok_or_else(|| Error::StrErr("game coin mover isn't a player".to_string()))?;
+        if record.details.basic.move_made.len() > position.game_move.basic.max_move_size {
+            return Err(Error::StrErr("spectated move is too long".to_string()));
+        }
+        if record.details.basic.mover_share > position.amount {
+            return Err(Error::StrErr(
+                "spectated move's mover share exceeds the game amount".to_string(),
+            ));
+        }
+
+        if record.validator.sha256tree(allocator).hash() != record.validator.hash() {
+            return Err(Error::StrErr(
+                "spectated move's validator doesn't match its hash".to_string(),
+            ));
+        }
+        let state = self.state_before(self.moves.len());
+        if let ValidationInfoHash::Hash(committed) = &record.details.validation_info_hash {
+            let validation_info = ValidationInfo::new_state_update(
+                allocator,
+                record.validator.clone(),
+                state.clone(),
+            );
+            if validation_info.hash() != committed {
+                return Err(Error::StrErr(
+                    "spectated move's validator isn't the one it committed to".to_string(),
+                ));
+            }
+        }
+
+        let args = Self::args_after(&position, record);
+        let aggregate =
+            self.players[0].unroll_pubkey.clone() + self.players[1].unroll_pubkey.clone();
+        let conditions_hash = record.conditions.sha256tree(allocator);
+        if !verify_partial_signature(
+            &record.signature,
+            &self.players[mover].unroll_pubkey,
+            &aggregate,
+            conditions_hash.bytes(),
+        ) {
+            return Err(Error::StrErr(
+                "spectated move isn't signed by its mover".to_string(),
+            ));
+        }
+        if !conditions_hold_position(allocator, &record.conditions, &args)? {
+            return Err(Error::StrErr(
+                "signed channel state doesn't hold the game at this move".to_string(),
+            ));
+        }
+
+        // Like the referee, a final move leaves nil behind.  Its validator
+        // may need real evidence to run to the end, so only an outright
+        // rejection counts against it.
+        let (new_state, illegal) = if args.game_move.validation_info_hash.is_none() {
+            let rejected = matches!(
+                Self::run_validator(allocator, &args, state, Evidence::nil()?),
+                Ok(None)
+            );
+            (Rc::new(Program(vec![0x80])), rejected)
+        } else {
+            match Self::run_validator(allocator, &args, state, Evidence::nil()?)? {
+                Some(state) => (state, false),
+                None => (Rc::new(Program(vec![0x80])), true),
+            }
+        };
+        self.positions.push(Rc::new(args));
+        self.moves.push(ObservedMove {
+            mover,
+            details: record.details.clone(),
+            validator_hash: record.validator.hash().clone(),
+            state: new_state,
+            readable: None,
+            illegal,
+        });
+        Ok(())
+    }
+
+    /// Read move `index` with the handler a feed sent for it.
+    fn render(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        index: usize,
+        handler: &GameHandler,
+    ) -> Result<(), Error> {
+        let args = self.positions[index + 1].clone();
+        let pre_state = self.state_before(index);
+        let observed = &self.moves[index];
+        if observed.illegal || observed.readable.is_some() {
+            return Ok(());
+        }
+        let pre_state_node = pre_state.to_nodeptr(allocator)?;
+        let state_node = observed.state.to_nodeptr(allocator)?;
+        let result = handler.call_their_turn_handler(
+            allocator,
+            &TheirTurnInputs {
+                amount: args.amount.clone(),
+                pre_state: pre_state_node,
+                state: state_node,
+                last_move: &observed.details.basic.move_made,
+                last_mover_share: observed.details.basic.mover_share.clone(),
+                new_move: args.game_move.clone(),
+            },
+        )?;
+        let mut illegal = false;
+        for evidence in result.slash_evidence.iter() {
+            if Self::run_validator(allocator, &args, pre_state.clone(), evidence.clone())?.is_none()
+            {
+                illegal = true;
+            }
+        }
+        let observed = &mut self.moves[index];
+        observed.readable = Some(ReadableMove::from_program(result.readable_move.p()));
+        observed.illegal = illegal;
+        Ok(())
+    }
+}
+
+/// Positions compare without the validator hash, which a player's own copy
+/// doesn't always carry, and with the validator by hash, since its name
+/// depends on which player's handler produced it.
+fn same_position(a: &RefereePuzzleArgs, b: &RefereePuzzleArgs) -> bool {
+    let strip = |p: &RefereePuzzleArgs| RefereePuzzleArgs {
+        game_move: GameMoveDetails {
+            validation_program_hash: None,
+            ..p.game_move.clone()
+        },
+        validation_program: a.validation_program.clone(),
+        ..p.clone()
+    };
+    a.validation_program.hash() == b.validation_program.hash() && strip(a) == strip(b)
+}
+
+/// The observer's side: checks feeds and keeps what they show.
+pub struct Spectator {
+    observer: PublicKey,
+    games: BTreeMap<GameID, SpectatedGame>,
+}
+
+impl Spectator {
+    pub fn new(observer: PublicKey) -> Self {
+        Spectator {
+            observer,
+            games: BTreeMap::new(),
+        }
+    }
+
+    pub fn game(&self, game_id: &GameID) -> Option<&SpectatedGame> {
+        self.games.get(game_id)
+    }
+
+    /// Check an event from a feed.  An error means the feed sent something
+    /// that doesn't hold up; nothing is recorded from it.
+    pub fn receive(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        event: &SpectatorEvent,
+    ) -> Result<Option<SpectatorUpdate>, Error> {
+        match event {
+            SpectatorEvent::Start(start) => self.start(allocator, start),
+            SpectatorEvent::Move(record) => self.receive_move(allocator, record),
+            SpectatorEvent::Ended { game_id, feeder } => {
+                let game = self.feed_game(game_id, feeder)?.0;
+                if game.ended {
+                    return Ok(None);
+                }
+                game.ended = true;
+                Ok(Some(SpectatorUpdate::Ended(*game_id)))
+            }
+        }
+    }
+
+    fn start(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        start: &SpectatorGameStart,
+    ) -> Result<Option<SpectatorUpdate>, Error> {
+        let grant = &start.grant.grant;
+        let game_id = grant.game_id;
+        let players = start.players();
+        if grant.observer != self.observer {
+            return Err(Error::StrErr(
+                "spectator grant is for another observer".to_string(),
+            ));
+        }
+        if !start.grant.verify(&players[0].referee_pubkey) {
+            return Err(Error::StrErr(
+                "spectator grant isn't signed by the feeding player".to_string(),
+            ));
+        }
+        let position = &start.position;
+        if curry_referee_puzzle_hash(allocator, &position.referee_coin_puzzle_hash, position)?
+            != grant.position_hash
+            || (*start.state).sha256tree(allocator) != grant.state_hash
+        {
+            return Err(Error::StrErr(
+                "spectator feed starts somewhere other than the grant says".to_string(),
+            ));
+        }
+        let countersigned = &start.countersigned;
+        for args in [position, &countersigned.position] {
+            let coin_keys = [&args.mover_pubkey, &args.waiter_pubkey];
+            if players
+                .iter()
+                .any(|p| !coin_keys.contains(&&p.referee_pubkey))
+            {
+                return Err(Error::StrErr(
+                    "spectator feed's players don't match the game coin".to_string(),
+                ));
+            }
+        }
+        if countersigned.position.nonce != position.nonce
+            || countersigned.position.referee_coin_puzzle_hash != position.referee_coin_puzzle_hash
+        {
+            return Err(Error::StrErr(
+                "countersigned state holds a different game".to_string(),
+            ));
+        }
+        let aggregate = players[0].unroll_pubkey.clone() + players[1].unroll_pubkey.clone();
+        let conditions_hash = countersigned.conditions.sha256tree(allocator);
+        if !verify_partial_signature(
+            &countersigned.signature,
+            &players[1].unroll_pubkey,
+            &aggregate,
+            conditions_hash.bytes(),
+        ) || !conditions_hold_position(
+            allocator,
+            &countersigned.conditions,
+            &countersigned.position,
+        )? {
+            return Err(Error::StrErr(
+                "opponent never signed a state holding this game".to_string(),
+            ));
+        }
+
+        let Some(game) = self.games.get_mut(&game_id) else {
+            self.games.insert(
+                game_id,
+                SpectatedGame {
+                    game_id,
+                    players: players.clone(),
+                    moves: Vec::new(),
+                    ended: false,
+                    positions: vec![start.position.clone()],
+                    start_state: start.state.clone(),
+                    cursors: [Some(0), None],
+                },
+            );
+            return Ok(Some(SpectatorUpdate::Started(game_id)));
+        };
+
+        if players.iter().any(|p| !game.players.contains(p)) {
+            return Err(Error::StrErr(
+                "already watching a different game with this id".to_string(),
+            ));
+        }
+        let feeder = game
+            .player_index(&players[0].referee_pubkey)
+            .ok_or_else(|| Error::StrErr("spectator feeder isn't a player".to_string()))?;
+        if game.cursors[feeder].is_some() {
+            return Err(Error::StrErr(
+                "this player's feed already started".to_string(),
+            ));
+        }
+        let at = game
+            .positions
+            .iter()
+            .position(|p| same_position(p, &start.position))
+            .ok_or_else(|| {
+                Error::StrErr("spectator feed starts at a position not seen yet".to_string())
+            })?;
+        game.cursors[feeder] = Some(at);
+        Ok(None)
+    }
+
+    fn feed_game(
+        &mut self,
+        game_id: &GameID,
+        feeder: &PublicKey,
+    ) -> Result<(&mut SpectatedGame, usize), Error> {
+        let game = self
+            .games
+            .get_mut(game_id)
+            .ok_or_else(|| Error::StrErr("spectator feed for an unknown game".to_string()))?;
+        let index = game
+            .player_index(feeder)
+            .filter(|i| game.cursors[*i].is_some())
+            .ok_or_else(|| Error::StrErr("no spectator grant from this feeder".to_string()))?;
+        Ok((game, index))
+    }
+
+    fn receive_move(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        record: &SpectatorMove,
+    ) -> Result<Option<SpectatorUpdate>, Error> {
+        let game_id = record.game_id;
+        let (live, feeder) = self.feed_game(&game_id, &record.feeder)?;
+        let handler = record.signed_handler(allocator)?;
+        // Work on a copy so a move that checks out but can't be rendered
+        // leaves nothing behind.
+        let mut game = live.clone();
+        let index = game.cursors[feeder].unwrap_or_default();
+
+        let mut update = if index < game.moves.len() {
+            let seen = &game.moves[index];
+            if seen.details.basic != record.details.basic
+                || seen.details.validation_info_hash != record.details.validation_info_hash
+                || seen.validator_hash != *record.validator.hash()
+            {
+                return Err(Error::StrErr(
+                    "spectator feeds disagree about a move".to_string(),
+                ));
+            }
+            None
+        } else {
+            game.extend(allocator, record)?;
+            Some(SpectatorUpdate::Moved { game_id, index })
+        };
+        game.cursors[feeder] = Some(index + 1);
+
+        if let Some(handler) = handler {
+            if game.moves[index].mover != feeder && game.moves[index].readable.is_none() {
+                game.render(allocator, index, &handler)?;
+                update = update.or(Some(SpectatorUpdate::Rendered { game_id, index }));
+            }
+        }
+        *live = game;
+        Ok(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::common::standard_coin::private_to_public_key;
+    use crate::referee::types::GameMoveStateInfo;
+
+    fn record(n: u8, handler: bool) -> SpectatorMove {
+        let mut allocator = AllocEncoder::new();
+        let nil = Rc::new(Program::from_hex("80").unwrap());
+        SpectatorMove {
+            game_id: GameID(1),
+            feeder: PublicKey::default(),
+            details: GameMoveDetails {
+                basic: GameMoveStateInfo {
+                    move_made: vec![n],
+                    mover_share: Default::default(),
+                    max_move_size: 1,
+                    max_move_size_raw: vec![1],
+                },
+                validation_info_hash: ValidationInfoHash::None,
+                validation_program_hash: None,
+            },
+            validator: StateUpdateProgram::new(&mut allocator, "test", nil.clone()),
+            conditions: nil.clone().into(),
+            signature: Aggsig::default(),
+            handler: handler.then(|| GameHandler::TheirTurnHandler(nil.into())),
+            handler_signature: None,
+        }
+    }
+
+    fn moves(released: &[SpectatorMove]) -> Vec<u8> {
+        released
+            .iter()
+            .map(|r| r.details.basic.move_made[0])
+            .collect()
+    }
+
+    #[test]
+    fn opponent_moves_wait_for_our_answer() {
+        let mut feed = SpectatorFeed::new(PublicKey::default(), PublicKey::default(), 0);
+        assert_eq!(moves(&feed.push(record(1, false), true)), vec![1]);
+        assert!(feed.push(record(2, true), false).is_empty());
+        assert_eq!(moves(&feed.push(record(3, false), true)), vec![2, 3]);
+    }
+
+    #[test]
+    fn delay_holds_moves_back() {
+        let mut feed = SpectatorFeed::new(PublicKey::default(), PublicKey::default(), 2);
+        assert!(feed.push(record(1, false), true).is_empty());
+        assert!(feed.push(record(2, true), false).is_empty());
+        assert_eq!(moves(&feed.push(record(3, false), true)), vec![1]);
+        assert_eq!(moves(&feed.push(record(4, true), false)), vec![2]);
+    }
+
+    #[test]
+    fn finish_drops_unanswered_handlers() {
+        let mut feed = SpectatorFeed::new(PublicKey::default(), PublicKey::default(), 5);
+        feed.push(record(1, true), false);
+        feed.push(record(2, false), true);
+        feed.push(record(3, true), false);
+        let rest = feed.finish();
+        assert_eq!(moves(&rest), vec![1, 2, 3]);
+        assert!(rest[0].handler.is_some());
+        assert!(rest[2].handler.is_none());
+    }
+
+    #[test]
+    fn grant_verifies_only_for_signer() {
+        let mut rng = ChaCha8Rng::from_seed([3; 32]);
+        let key: PrivateKey = rng.random();
+        let other: PrivateKey = rng.random();
+        let player = SpectatorPlayer {
+            referee_pubkey: private_to_public_key(&key),
+            unroll_pubkey: private_to_public_key(&key),
+        };
+        let grant = SignedSpectatorGrant::sign(
+            &key,
+            SpectatorGrant {
+                game_id: GameID(4),
+                observer: private_to_public_key(&other),
+                delay_moves: 1,
+                players: [player.clone(), player.clone()],
+                position_hash: PuzzleHash::default(),
+                state_hash: PuzzleHash::default(),
+            },
+        );
+        assert!(grant.verify(&private_to_public_key(&key)));
+        assert!(!grant.verify(&private_to_public_key(&other)));
+        let mut altered = grant.clone();
+        altered.grant.delay_moves = 0;
+        assert!(!altered.verify(&private_to_public_key(&key)));
+        let mut altered = grant.clone();
+        altered.grant.players[1].unroll_pubkey = private_to_public_key(&other);
+        assert!(!altered.verify(&private_to_public_key(&key)));
+    }
+}
@@ -15,7 +15,7 @@ use crate::channel_state::game_start_info::GameStartInfo;
 use crate::channel_state::types::{
     Evidence, HasStateUpdateProgram, ReadableMove, StateUpdateProgram, ValidationInfo,
 };
-use crate::common::load_clvm::read_hex_puzzle;
+use crate::common::puzzle_registry;
 use crate::common::standard_coin::ChiaIdentity;
 #[cfg(test)]
 use crate::common::types::PrivateKey;
@@ -47,7 +47,7 @@ impl DebugGameCurry {
         mover_pk: &PublicKey,
         waiter_pk: &PublicKey,
     ) -> Result<DebugGameCurry, Error> {
-        let raw_program = read_hex_puzzle(allocator, "clsp/test/debug_game.hex")?;
+        let raw_program = puzzle_registry::DEBUG_GAME.to_puzzle(allocator)?;
         let prog_hash = raw_program.sha256tree(allocator);
         Ok(DebugGameCurry {
             count: 0,
@@ -169,8 +169,8 @@ impl BareDebugGameHandler {
                 games.len()
             )));
         }
-        let start_a = games[0].game_start(&game_id, &timeout, true);
-        let start_b = games[0].game_start(&game_id, &timeout, false);
+        let start_a = games[0].game_start(&game_id, &timeout, true, None);
+        let start_b = games[0].game_start(&game_id, &timeout, false, None);
         assert_ne!(start_a.amount, Amount::default());
         assert_ne!(start_b.amount, Amount::default());
         let make_bare_handler = |game_start: &GameStartInfo| -> BareDebugGameHandler {
@@ -343,6 +343,7 @@ impl BareDebugGameHandler {
                 mover_pubkey: mover_pk.clone(),
                 waiter_pubkey: waiter_pk.clone(),
                 amount: self.start.amount.clone(),
+                fee: self.start.fee.clone(),
                 game_move: GameMoveDetails {
                     basic: GameMoveStateInfo {
                         move_made: move_to_check.to_vec(),
@@ -596,7 +597,7 @@ pub fn make_debug_games_with_contributions(
 ) -> Result<[BareDebugGameHandler; 2], Error> {
     let rng_seq0: Vec<Hash> = (0..50).map(|_| rng.random()).collect();
     let gid = GameID(nonce);
-    let referee_coin = read_hex_puzzle(allocator, "clsp/referee/onchain/referee.hex")?;
+    let referee_coin = puzzle_registry::ONCHAIN_REFEREE.to_puzzle(allocator)?;
     let ref_coin_hash = referee_coin.sha256tree(allocator);
     BareDebugGameHandler::new_with_contributions(
         allocator,
@@ -190,7 +190,7 @@ mod sim_tests {
             let notifications = &outcome.local_uis[0].notifications;
             assert!(notifications.iter().any(|notification| matches!(
                 notification,
-                GameNotification::MoveRejected { id, tag, message }
+                GameNotification::MoveRejected { id, tag, message, .. }
                     if *id == GameID(1)
                         && tag == "not_in_dictionary"
                         && message == "XXXXX"
@@ -30,7 +30,7 @@ use crate::session_phases::types::{
     ChannelFundingWallet, PacketSender, PeerMessage, ToLocalUI, WalletSpendInterface,
 };
 #[cfg(test)]
-use crate::session_phases::types::{FromLocalUI, OffChainPhaseInit};
+use crate::session_phases::types::{FromLocalUI, OffChainPhaseInit, PeerLimits};
 use crate::session_phases::OffChainPhase;
 use rand::Rng;
 #[cfg(test)]
@@ -498,6 +498,7 @@ pub fn test_peer_smoke() {
             channel_timeout: Timeout::new(1000),
             unroll_timeout: Timeout::new(15),
             reward_puzzle_hash: reward_puzzle_hash1.clone(),
+            peer_limits: PeerLimits::default(),
         };
         if have_potato {
             Box::new(HandshakeInitiatorPhase::new(phi))
@@ -578,6 +579,8 @@ pub fn test_peer_smoke() {
                     game_type: GameType(b"calpoker".to_vec()),
                     timeout: Timeout::new(15),
                     parameters,
+                    fee: None,
+                    payout: None,
                 }],
             )
             .expect("should run");
@@ -46,6 +46,11 @@ mod sim_tests {
         Aggsig, Amount, CoinID, CoinString, Error, GameID, Hash, PublicKey, Puzzle, PuzzleHash,
         Sha256tree,
     };
+    use crate::game_session::LivenessPolicy;
+    use crate::operator_fee::OperatorFee;
+    use crate::payout::PayoutTerms;
+    use crate::session_match::MatchTerms;
+    use crate::session_phases::types::PeerLimits;
     use crate::simulator::Simulator;
 
     use rand::prelude::*;
@@ -198,13 +203,24 @@ mod sim_tests {
         /// The trigger specifies what event to wait for before proposing.
         ProposeNewGame(usize, ProposeTrigger),
         /// Propose a new game from the specified player with a custom game timeout.
-        ProposeNewGameWithTimeout(usize, ProposeTrigger, u64),
+        ProposeNewGameWithTimeout(usize, ProposeTrigger, Timeout),
+        /// Propose a new game from the specified player with operator fee terms.
+        ProposeNewGameWithFee(usize, ProposeTrigger, OperatorFee),
+        /// Propose a new game from the specified player, paying their share
+        /// as the payout terms say.
+        ProposeNewGameWithPayout(usize, ProposeTrigger, PayoutTerms),
         /// Like ProposeNewGame but with my_turn=false so the receiver moves first.
         ProposeNewGameTheirTurn(usize, ProposeTrigger),
         /// Propose the two asymmetric games that make up one Krunk hand.
         ProposeKrunkGroup(usize, ProposeTrigger),
         /// Go on chain
         GoOnChain(usize),
+        /// Hold the script until the game is on chain and waiting on the
+        /// player's move (player, game_id).
+        WaitForOnChainTurn(usize, GameID),
+        /// Move once the game is on chain and waiting on the player; skipped
+        /// if the game already settled for them (player, game_id, readable).
+        OnChainMove(usize, GameID, ReadableMove),
         /// Wait a number of blocks
         WaitBlocks(usize, usize),
         /// Accept timeout (player, game_id)
@@ -227,10 +243,33 @@ mod sim_tests {
         NerfMessages(usize),
         /// Stop nerfing messages.
         UnNerfMessages,
+        /// Replace a player's session liveness policy.
+        SetLivenessPolicy(usize, LivenessPolicy),
+        /// Use this unroll timeout for both players.  Read when the sessions
+        /// are created; does nothing when reached in the script.
+        UnrollTimeout(Timeout),
+        /// Create both sessions with a journal of this byte limit.  Read when
+        /// the sessions are created; does nothing when reached in the script.
+        JournalSessions(usize),
+        /// Replace a player's inbound peer limits.
+        SetPeerLimits(usize, PeerLimits),
         /// Accept a proposed game. (player, game_id)
         AcceptProposal(usize, GameID),
+        /// Accept a proposed game, paying the acceptor's share as the payout
+        /// terms say. (player, game_id, payout)
+        AcceptProposalWithPayout(usize, GameID, PayoutTerms),
         /// Cancel a proposed game (player, game_id).
         CancelProposal(usize, GameID),
+        /// Start a match once the channel is up (player, terms).
+        StartMatch(usize, MatchTerms),
+        /// Join the match whose first hand is the given proposal, once it
+        /// arrives (player, first hand, terms).
+        AcceptMatch(usize, GameID, MatchTerms),
+        /// Ask to stop the player's match after the current hand.
+        StopMatch(usize),
+        /// Let an observer follow a game once it is accepted (player,
+        /// game_id, observer, delay_moves).
+        AuthorizeSpectator(usize, GameID, PublicKey, u32),
         /// Snapshot the current unroll spend info for later stale unroll.
         SaveUnrollSnapshot(usize),
         /// Force-submit a stale unroll using a previously saved snapshot.
@@ -283,13 +322,29 @@ mod sim_tests {
                 SimScriptAction::ProposeNewGameWithTimeout(p, t, timeout) => {
                     write!(formatter, "ProposeNewGameWithTimeout({p},{t:?},{timeout})")
                 }
+                SimScriptAction::ProposeNewGameWithFee(p, t, fee) => {
+                    write!(formatter, "ProposeNewGameWithFee({p},{t:?},{fee:?})")
+                }
+                SimScriptAction::ProposeNewGameWithPayout(p, t, payout) => {
+                    write!(formatter, "ProposeNewGameWithPayout({p},{t:?},{payout:?})")
+                }
                 SimScriptAction::ProposeNewGameTheirTurn(p, t) => {
                     write!(formatter, "ProposeNewGameTheirTurn({p},{t:?})")
                 }
                 SimScriptAction::ProposeKrunkGroup(p, t) => {
                     write!(formatter, "ProposeKrunkGroup({p},{t:?})")
                 }
+                SimScriptAction::UnrollTimeout(t) => write!(formatter, "UnrollTimeout({t})"),
+                SimScriptAction::JournalSessions(limit) => {
+                    write!(formatter, "JournalSessions({limit})")
+                }
                 SimScriptAction::GoOnChain(p) => write!(formatter, "GoOnChain({p})"),
+                SimScriptAction::WaitForOnChainTurn(p, g) => {
+                    write!(formatter, "WaitForOnChainTurn({p},{g:?})")
+                }
+                SimScriptAction::OnChainMove(p, g, n) => {
+                    write!(formatter, "OnChainMove({p},{g:?},{n:?})")
+                }
                 SimScriptAction::AcceptSettlement(p, g) => {
                     write!(formatter, "AcceptSettlement({p},{g:?})")
                 }
@@ -301,12 +356,31 @@ mod sim_tests {
                 SimScriptAction::ForceUnroll(p) => write!(formatter, "ForceUnroll({p})"),
                 SimScriptAction::NerfMessages(p) => write!(formatter, "NerfMessages({p})"),
                 SimScriptAction::UnNerfMessages => write!(formatter, "UnNerfMessages"),
+                SimScriptAction::SetLivenessPolicy(p, policy) => {
+                    write!(formatter, "SetLivenessPolicy({p},{policy:?})")
+                }
+                SimScriptAction::SetPeerLimits(p, limits) => {
+                    write!(formatter, "SetPeerLimits({p},{limits:?})")
+                }
                 SimScriptAction::AcceptProposal(p, g) => {
                     write!(formatter, "AcceptProposal({p},{g:?})")
                 }
+                SimScriptAction::AcceptProposalWithPayout(p, g, payout) => {
+                    write!(formatter, "AcceptProposalWithPayout({p},{g:?},{payout:?})")
+                }
                 SimScriptAction::CancelProposal(p, g) => {
                     write!(formatter, "CancelProposal({p},{g:?})")
                 }
+                SimScriptAction::StartMatch(p, terms) => {
+                    write!(formatter, "StartMatch({p},{terms:?})")
+                }
+                SimScriptAction::AcceptMatch(p, g, terms) => {
+                    write!(formatter, "AcceptMatch({p},{g:?},{terms:?})")
+                }
+                SimScriptAction::StopMatch(p) => write!(formatter, "StopMatch({p})"),
+                SimScriptAction::AuthorizeSpectator(p, g, _, d) => {
+                    write!(formatter, "AuthorizeSpectator({p},{g:?},{d})")
+                }
                 SimScriptAction::SaveUnrollSnapshot(p) => {
                     write!(formatter, "SaveUnrollSnapshot({p})")
                 }
@@ -431,8 +505,8 @@ mod sim_tests {
 
         let timeout = Timeout::new(15);
 
-        let our_game_start = factory_game.game_start(game_id, &timeout, true);
-        let their_game_start = factory_game.game_start(game_id, &timeout, false);
+        let our_game_start = factory_game.game_start(game_id, &timeout, true, None);
+        let their_game_start = factory_game.game_start(game_id, &timeout, false, None);
 
         let our_start: Rc<GameStartInfo> = Rc::new(our_game_start);
         let their_start: Rc<GameStartInfo> = Rc::new(their_game_start);
@@ -8,7 +8,7 @@ use crate::common::constants::AGG_SIG_ME_ADDITIONAL_DATA;
 use crate::common::standard_coin::{
     get_standard_coin_puzzle, private_to_public_key, puzzle_hash_for_pk,
 };
-use crate::common::types::{AllocEncoder, Amount, Hash, Puzzle, Sha256tree};
+use crate::common::types::{AllocEncoder, Amount, Hash, Puzzle, Sha256tree, Timeout};
 
 #[cfg(feature = "sim-tests")]
 pub(crate) mod sim_tests {
@@ -345,7 +345,9 @@ pub(crate) fn test_unroll_can_verify_own_signature() {
         my_balance: Amount::new(0),
         their_balance: Amount::new(100),
         puzzle_hashes_and_amounts: vec![],
-        unroll_timeout: 15,
+        operator_fees: vec![],
+        routed_payouts: vec![],
+        unroll_timeout: Timeout::new(15),
     };
 
     let _sig1 = unroll_coin_1
@@ -0,0 +1,531 @@
+//! Model-based test of `ChannelState` balance bookkeeping.
+//!
+//! Two real peers are driven through random potato exchanges (proposals,
+//! accepts, cancels, calpoker moves and settlements, batched in random
+//! combinations) next to a reference model of the channel.  After every
+//! potato pass both peers must agree with the model on balances, live games,
+//! proposals, pending settlements, turn and state number, and the channel
+//! total must be conserved.
+
+#[cfg(feature = "sim-tests")]
+pub(crate) mod sim_tests {
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    use clvm_traits::ToClvm;
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::channel_state::game::{FactoryGame, Game};
+    use crate::channel_state::game_start_info::GameStartInfo;
+    use crate::channel_state::types::{
+        read_unroll_puzzle, ChannelEnv, ReadableMove, StateUpdateSignatures,
+    };
+    use crate::common::constants::AGG_SIG_ME_ADDITIONAL_DATA;
+    use crate::common::puzzle_registry;
+    use crate::common::standard_coin::get_standard_coin_puzzle;
+    use crate::common::types::{
+        AllocEncoder, Amount, CoinID, Error, GameID, Hash, Program, Puzzle, Sha256tree,
+    };
+    use crate::referee::types::GameMoveDetails;
+    use crate::simulator::tests::session_phases_sim::{
+        calpoker_test_moves_with_selected_cards, parse_card_lists_from_readable,
+    };
+    use crate::test_support::sim_script::{
+        ChannelHandlerGame, SimScriptAction, DEFAULT_UNROLL_TIME_LOCK,
+    };
+
+    const CONTRIBUTION: u64 = 100;
+    const TOTAL: u64 = 2 * CONTRIBUTION;
+    const BETS: [u64; 4] = [10, 20, 50, 80];
+    const MAX_PROPOSALS: usize = 4;
+    const MAX_BATCH: usize = 3;
+    const SEEDS: u64 = 4;
+    const STEPS: usize = 60;
+
+    #[derive(Clone, Debug)]
+    struct ModelGame {
+        id: GameID,
+        proposer: usize,
+        bet: u64,
+        moves: usize,
+        /// Dealt hands (proposer's first), known once the reveal message
+        /// has gone through.
+        hands: Option<(Vec<usize>, Vec<usize>)>,
+    }
+
+    impl ModelGame {
+        /// Calpoker is proposed with the sender moving first.
+        fn to_move(&self) -> usize {
+            self.proposer ^ (self.moves % 2)
+        }
+    }
+
+    /// What the peers should hold, in absolute player terms.
+    #[derive(Default)]
+    struct Model {
+        out_of_game: [u64; 2],
+        proposals: Vec<ModelGame>,
+        live: Vec<ModelGame>,
+        pending_settlements: [Vec<GameID>; 2],
+        holder: usize,
+        state_number: usize,
+    }
+
+    impl Model {
+        fn allocated(&self) -> u64 {
+            self.live.iter().map(|g| g.bet).sum()
+        }
+    }
+
+    /// One batch entry as the receiver has to replay it.
+    #[derive(Debug)]
+    enum Wire {
+        Propose(GameID, u64),
+        Accept(GameID),
+        Cancel(GameID),
+        Move(GameID, GameMoveDetails),
+        Settle(GameID, u64, Amount),
+    }
+
+    struct Harness<'a> {
+        rng: ChaCha8Rng,
+        factory: Puzzle,
+        factory_games: BTreeMap<u64, FactoryGame>,
+        script: Vec<ReadableMove>,
+        game: ChannelHandlerGame,
+        model: Model,
+        last_signatures: StateUpdateSignatures,
+        label: &'a str,
+    }
+
+    impl Harness<'_> {
+        fn factory_game(&mut self, env: &mut ChannelEnv<'_>, bet: u64) -> FactoryGame {
+            if let Some(game) = self.factory_games.get(&bet) {
+                return game.clone();
+            }
+            let parameters = (bet, (1, ())).to_clvm(env.allocator).expect("parameters");
+            let parameters = Program::from_nodeptr(env.allocator, parameters).expect("program");
+            let game = Game::run_factory(env.allocator, self.factory.clone(), &parameters)
+                .expect("calpoker factory")
+                .remove(0);
+            self.factory_games.insert(bet, game.clone());
+            game
+        }
+
+        fn start(
+            &mut self,
+            env: &mut ChannelEnv<'_>,
+            id: GameID,
+            bet: u64,
+            sender_side: bool,
+        ) -> Rc<GameStartInfo> {
+            let game = self.factory_game(env, bet);
+            Rc::new(game.game_start(&id, &(*DEFAULT_UNROLL_TIME_LOCK).clone(), sender_side, None))
+        }
+
+        fn discards(&mut self, env: &mut ChannelEnv<'_>, hand: &[usize]) -> ReadableMove {
+            let mut picks: Vec<usize> = hand.choose_multiple(&mut self.rng, 4).copied().collect();
+            picks.sort();
+            let node = picks.to_clvm(env.allocator).expect("picks");
+            ReadableMove::from_program(Rc::new(
+                Program::from_nodeptr(env.allocator, node).expect("picks program"),
+            ))
+        }
+
+        /// Pick and apply one sender-side action, or `None` if the potato
+        /// should go out as it is.
+        fn send_one(&mut self, env: &mut ChannelEnv<'_>) -> Option<Wire> {
+            let who = self.model.holder;
+            let peer = who ^ 1;
+            let script_len = self.script.len();
+            let mut choices: Vec<u8> = Vec::new();
+            if self.model.proposals.len() < MAX_PROPOSALS {
+                choices.push(0);
+            }
+            if self.model.proposals.iter().any(|p| p.proposer == peer) {
+                choices.push(1);
+            }
+            if !self.model.proposals.is_empty() {
+                choices.push(2);
+            }
+            if self
+                .model
+                .live
+                .iter()
+                .any(|g| g.to_move() == who && g.moves < script_len)
+            {
+                // Weighted so that games regularly play out to the end.
+                choices.extend([3; 4]);
+            }
+            if self.model.live.iter().any(|g| g.to_move() == who) {
+                choices.push(4);
+            }
+            match choices.choose(&mut self.rng)? {
+                0 => {
+                    let bet = *BETS.choose(&mut self.rng).expect("bets");
+                    let id = GameID(self.game.player(who).ch.allocate_my_nonce());
+                    let start = self.start(env, id, bet, true);
+                    self.game
+                        .player(who)
+                        .ch
+                        .send_propose_game(env, &start, id)
+                        .expect("send_propose_game");
+                    self.model.proposals.push(ModelGame {
+                        id,
+                        proposer: who,
+                        bet,
+                        moves: 0,
+                        hands: None,
+                    });
+                    Some(Wire::Propose(id, bet))
+                }
+                1 => {
+                    let theirs: Vec<usize> = (0..self.model.proposals.len())
+                        .filter(|i| self.model.proposals[*i].proposer == peer)
+                        .collect();
+                    let idx = *theirs.choose(&mut self.rng).expect("peer proposal");
+                    let proposal = self.model.proposals[idx].clone();
+                    let affordable = proposal.bet <= self.model.out_of_game[who]
+                        && proposal.bet <= self.model.out_of_game[peer];
+                    let result = self.game.player(who).ch.send_accept_proposal(&proposal.id);
+                    assert_eq!(
+                        result.is_ok(),
+                        affordable,
+                        "{}: accept of {proposal:?} with balances {:?}: {result:?}",
+                        self.label,
+                        self.model.out_of_game,
+                    );
+                    if !affordable {
+                        return None;
+                    }
+                    self.model.proposals.remove(idx);
+                    self.model.out_of_game[who] -= proposal.bet;
+                    self.model.out_of_game[peer] -= proposal.bet;
+                    self.model.live.push(proposal.clone());
+                    Some(Wire::Accept(proposal.id))
+                }
+                2 => {
+                    let idx = self.rng.random_range(0..self.model.proposals.len());
+                    let proposal = self.model.proposals.remove(idx);
+                    self.game
+                        .player(who)
+                        .ch
+                        .send_cancel_proposal(&proposal.id)
+                        .expect("send_cancel_proposal");
+                    Some(Wire::Cancel(proposal.id))
+                }
+                3 => {
+                    let movable: Vec<usize> = (0..self.model.live.len())
+                        .filter(|i| {
+                            let g = &self.model.live[*i];
+                            g.to_move() == who && g.moves < script_len
+                        })
+                        .collect();
+                    let idx = *movable.choose(&mut self.rng).expect("movable game");
+                    let readable = match (self.model.live[idx].moves, &self.model.live[idx].hands) {
+                        (2, Some((hand, _))) | (3, Some((_, hand))) => {
+                            self.discards(env, &hand.clone())
+                        }
+                        (2 | 3, None) => panic!("{}: discard before hands were dealt", self.label),
+                        (n, _) => self.script[n].clone(),
+                    };
+                    let game = &mut self.model.live[idx];
+                    let entropy: Hash = self.rng.random();
+                    let result = self.game.players[who]
+                        .ch
+                        .send_move_no_finalize(env, &game.id, &readable, entropy)
+                        .unwrap_or_else(|e| {
+                            panic!("{}: move {} in {game:?}: {e:?}", self.label, game.moves)
+                        });
+                    game.moves += 1;
+                    assert_eq!(
+                        result.is_finished,
+                        game.moves == script_len,
+                        "{}: finish flag after move {} in {game:?}",
+                        self.label,
+                        game.moves
+                    );
+                    Some(Wire::Move(game.id, result.game_move))
+                }
+                _ => {
+                    let settleable: Vec<usize> = (0..self.model.live.len())
+                        .filter(|i| self.model.live[*i].to_move() == who)
+                        .collect();
+                    let idx = *settleable.choose(&mut self.rng).expect("settleable game");
+                    let game = self.model.live.remove(idx);
+                    let share = self
+                        .game
+                        .player(who)
+                        .ch
+                        .send_accept_settlement_no_finalize(&game.id)
+                        .expect("send_accept_settlement_no_finalize");
+                    let pot = 2 * game.bet;
+                    assert!(
+                        share.to_u64() <= pot,
+                        "{}: settled share {share:?} exceeds pot {pot}",
+                        self.label
+                    );
+                    self.model.out_of_game[who] += share.to_u64();
+                    self.model.out_of_game[peer] += pot - share.to_u64();
+                    self.model.pending_settlements[who].push(game.id);
+                    Some(Wire::Settle(game.id, pot, share))
+                }
+            }
+        }
+
+        fn receive(
+            &mut self,
+            env: &mut ChannelEnv<'_>,
+            who: usize,
+            wire: &[Wire],
+            signatures: &StateUpdateSignatures,
+            probe: bool,
+        ) -> Result<Vec<(GameID, Vec<u8>)>, Error> {
+            let mut ch = self.game.player(who).ch.clone();
+            let mut messages = Vec::new();
+            for entry in wire {
+                match entry {
+                    Wire::Propose(id, bet) => {
+                        let start = self.start(env, *id, *bet, false);
+                        ch.apply_received_proposal(env, &start, *id)?;
+                    }
+                    Wire::Accept(id) => ch.apply_received_accept_proposal(id)?,
+                    Wire::Cancel(id) => ch.received_cancel_proposal(id)?,
+                    Wire::Move(id, details) => {
+                        let result = ch.apply_received_move(env, id, details)?;
+                        if !result.message.is_empty() {
+                            messages.push((*id, result.message));
+                        }
+                    }
+                    Wire::Settle(id, pot, share) => {
+                        let (ours, _) = ch.apply_received_accept_settlement(id)?;
+                        assert_eq!(
+                            ours.to_u64() + share.to_u64(),
+                            *pot,
+                            "{}: settlement of {id:?} split does not add up",
+                            self.label
+                        );
+                    }
+                }
+            }
+            if wire.is_empty() {
+                ch.received_empty_potato(env, signatures)?;
+            } else {
+                ch.verify_received_batch_signatures(env, signatures)?;
+            }
+            if !probe {
+                self.game.player(who).ch = ch;
+            }
+            Ok(messages)
+        }
+
+        fn step(&mut self, env: &mut ChannelEnv<'_>) {
+            let sender = self.model.holder;
+            let receiver = sender ^ 1;
+            let batch = self.rng.random_range(0..=MAX_BATCH);
+            let wire: Vec<Wire> = (0..batch).filter_map(|_| self.send_one(env)).collect();
+            assert_eq!(
+                self.game.player(sender).ch.amount(false),
+                Amount::new(TOTAL),
+                "{}: sender total drifted mid-batch {wire:?}",
+                self.label
+            );
+
+            let signatures = self
+                .game
+                .player(sender)
+                .ch
+                .update_cached_unroll_state(env)
+                .expect("update_cached_unroll_state");
+
+            let stale = std::mem::replace(&mut self.last_signatures, signatures.clone());
+            assert!(
+                self.receive(env, receiver, &wire, &stale, true).is_err(),
+                "{}: stale signatures accepted for {wire:?}",
+                self.label
+            );
+            let messages = self
+                .receive(env, receiver, &wire, &signatures, false)
+                .unwrap_or_else(|e| panic!("{}: receive {wire:?}: {e:?}", self.label));
+            for (id, message) in messages {
+                let revealed = self
+                    .game
+                    .player(sender)
+                    .ch
+                    .received_message(env, &id, &message)
+                    .unwrap_or_else(|e| panic!("{}: message for {id:?}: {e:?}", self.label));
+                let hands = parse_card_lists_from_readable(env.allocator, revealed)
+                    .unwrap_or_else(|e| panic!("{}: cards for {id:?}: {e:?}", self.label));
+                if let Some(game) = self.model.live.iter_mut().find(|g| g.id == id) {
+                    game.hands = Some(hands);
+                }
+            }
+
+            self.model.state_number += 1;
+            self.model.holder = receiver;
+            self.model.pending_settlements[receiver].clear();
+            self.check(&wire);
+        }
+
+        fn check(&mut self, wire: &[Wire]) {
+            let label = self.label;
+            let mut live: Vec<GameID> = self.model.live.iter().map(|g| g.id).collect();
+            live.sort_by_key(|id| id.0);
+            let mut proposals: Vec<(GameID, Amount, Amount)> = self
+                .model
+                .proposals
+                .iter()
+                .map(|p| (p.id, Amount::new(p.bet), Amount::new(p.bet)))
+                .collect();
+            proposals.sort_by_key(|p| p.0 .0);
+            let allocated = Amount::new(self.model.allocated());
+
+            for who in 0..2 {
+                let model = &self.model;
+                let ch = &self.game.players[who].ch;
+                let ctx = format!("{label}: player {who} after {wire:?}");
+
+                assert_eq!(ch.amount(false), Amount::new(TOTAL), "{ctx}: total");
+                assert_eq!(
+                    ch.my_out_of_game_balance(),
+                    Amount::new(model.out_of_game[who]),
+                    "{ctx}: my out-of-game balance"
+                );
+                assert_eq!(
+                    ch.their_out_of_game_balance(),
+                    Amount::new(model.out_of_game[who ^ 1]),
+                    "{ctx}: their out-of-game balance"
+                );
+                assert_eq!(ch.my_allocated_balance(), allocated, "{ctx}: my allocation");
+                assert_eq!(
+                    ch.their_allocated_balance(),
+                    allocated,
+                    "{ctx}: their allocation"
+                );
+                assert_eq!(ch.state_number(), model.state_number, "{ctx}: state number");
+                assert_eq!(ch.have_potato(), who == model.holder, "{ctx}: potato");
+
+                let mut got_live = ch.live_game_ids();
+                got_live.sort_by_key(|id| id.0);
+                assert_eq!(got_live, live, "{ctx}: live games");
+
+                let mut pending: Vec<GameID> = ch
+                    .all_game_ids()
+                    .into_iter()
+                    .filter(|id| !live.contains(id))
+                    .collect();
+                pending.sort_by_key(|id| id.0);
+                let mut expected_pending = model.pending_settlements[who].clone();
+                expected_pending.sort_by_key(|id| id.0);
+                assert_eq!(pending, expected_pending, "{ctx}: pending settlements");
+
+                let mut got_proposals = ch.proposal_contributions_for_testing();
+                got_proposals.sort_by_key(|p| p.0 .0);
+                assert_eq!(got_proposals, proposals, "{ctx}: proposals");
+
+                for game in &model.live {
+                    assert_eq!(
+                        ch.game_is_my_turn(&game.id),
+                        Some(game.to_move() == who),
+                        "{ctx}: turn in {game:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_seed(seed: u64) {
+        let mut allocator = AllocEncoder::new();
+        let script: Vec<ReadableMove> = calpoker_test_moves_with_selected_cards(
+            &mut allocator,
+            GameID(0),
+            &[32, 36, 41, 49],
+            &[2, 6, 9, 13],
+        )
+        .into_iter()
+        .map(|action| match action {
+            SimScriptAction::Move(_, _, readable, _) => readable,
+            other => panic!("unexpected calpoker script action {other:?}"),
+        })
+        .collect();
+        let factory = puzzle_registry::CALPOKER_GENERATE_CALPOKER_FACTORY
+            .to_puzzle(&mut allocator)
+            .expect("should load");
+
+        let unroll_puzzle = read_unroll_puzzle(&mut allocator).unwrap();
+        let nil = allocator.allocator().nil();
+        let ref_coin_puz = Puzzle::from_nodeptr(&allocator, nil).expect("should work");
+        let ref_coin_ph = ref_coin_puz.sha256tree(&mut allocator);
+        let standard_puzzle = get_standard_coin_puzzle(&mut allocator).expect("should load");
+        let mut env = ChannelEnv {
+            allocator: &mut allocator,
+            referee_coin_puzzle: ref_coin_puz,
+            referee_coin_puzzle_hash: ref_coin_ph,
+            unroll_puzzle,
+            standard_puzzle,
+            agg_sig_me_additional_data: Hash::from_bytes(AGG_SIG_ME_ADDITIONAL_DATA),
+        };
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut game = ChannelHandlerGame::new(
+            &mut rng,
+            &mut env,
+            GameID(0),
+            &CoinID::default(),
+            &[Amount::new(CONTRIBUTION), Amount::new(CONTRIBUTION)],
+            (*DEFAULT_UNROLL_TIME_LOCK).clone(),
+        )
+        .expect("should build");
+        game.finish_handshake(&mut env, 1)
+            .expect("finish_handshake(1)");
+        game.finish_handshake(&mut env, 0)
+            .expect("finish_handshake(0)");
+
+        let holder = if game.players[0].ch.have_potato() {
+            0
+        } else {
+            1
+        };
+        let state_number = game.players[0].ch.state_number();
+        let label = format!("channel model seed {seed}");
+        let mut harness = Harness {
+            rng,
+            factory,
+            factory_games: BTreeMap::new(),
+            script,
+            game,
+            model: Model {
+                out_of_game: [CONTRIBUTION, CONTRIBUTION],
+                holder,
+                state_number,
+                ..Model::default()
+            },
+            last_signatures: StateUpdateSignatures::default(),
+            label: &label,
+        };
+        harness.check(&[]);
+        for _ in 0..STEPS {
+            harness.step(&mut env);
+        }
+    }
+
+    pub(crate) fn test_channel_state_model_conservation() {
+        for seed in 0..SEEDS {
+            check_seed(seed);
+        }
+    }
+}
+
+pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
+    #[allow(unused_mut)]
+    let mut v: Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> = Vec::new();
+    #[cfg(feature = "sim-tests")]
+    {
+        v.push((
+            "test_channel_state_model_conservation",
+            &sim_tests::test_channel_state_model_conservation,
+        ));
+    }
+    v
+}
@@ -421,7 +421,7 @@ fn assert_not_in_dictionary_rejection(
         )
         .unwrap_err();
     match error {
-        Error::GameMoveRejected { tag, message } => {
+        Error::GameMoveRejected { tag, message, .. } => {
             assert_eq!(tag, b"not_in_dictionary");
             assert_eq!(message, word);
         }
@@ -1,11 +1,13 @@
 pub mod calpoker_handlers;
 pub mod calpoker_validation;
 pub mod channel_state;
+pub mod channel_state_model;
 pub mod chialisp;
 pub mod constants;
 pub mod dict_tree_lookup;
 pub mod krunk_handlers;
 pub mod krunk_validation;
+pub mod potato_verify_bench;
 pub mod referee_conditions;
 pub mod spacepoker_handlers;
 pub mod spacepoker_validation;
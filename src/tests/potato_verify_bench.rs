@@ -0,0 +1,227 @@
+//! Cost of receiving a potato as the channel fills with games.
+//!
+//! A potato pass carries two signatures (channel coin and unroll preempt)
+//! whatever the game count, so batching them saves the same fixed amount on
+//! every pass; that is timed once.  What grows with the game count is
+//! rebuilding the unroll conditions the signatures cover, which is timed on
+//! its own and as part of the whole receive with 1, 5 and 20 live games.
+//! Timings are printed with `SIM_TIMING` set; run in release mode for
+//! meaningful numbers:
+//!
+//! ```text
+//! SIM_TIMING=1 SIM_TEST_ONLY=bench_potato_receive \
+//!     cargo test --release --lib --features sim-tests sim_tests -- --nocapture
+//! ```
+
+#[cfg(feature = "sim-tests")]
+pub(crate) mod sim_tests {
+    use std::time::{Duration, Instant};
+
+    use clvm_traits::ToClvm;
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::channel_state::game::Game;
+    use crate::channel_state::types::{read_unroll_puzzle, ChannelEnv, StateUpdateSignatures};
+    use crate::channel_state::{verify_signature_batch, ChannelState};
+    use crate::common::constants::AGG_SIG_ME_ADDITIONAL_DATA;
+    use crate::common::puzzle_registry;
+    use crate::common::standard_coin::get_standard_coin_puzzle;
+    use crate::common::types::{
+        AllocEncoder, Amount, CoinID, Error, GameID, Hash, PrivateKey, Program, Puzzle, Sha256tree,
+    };
+    use crate::test_support::sim_script::{ChannelHandlerGame, DEFAULT_UNROLL_TIME_LOCK};
+
+    const GAME_COUNTS: [usize; 3] = [1, 5, 20];
+    const ITERATIONS: u32 = 10;
+    const BET: u64 = 10;
+
+    /// Open a channel and get `games` calpoker games live in it. Returns the
+    /// channel and which player holds the potato.
+    fn channel_with_games(env: &mut ChannelEnv<'_>, games: usize) -> (ChannelHandlerGame, usize) {
+        let mut rng = ChaCha8Rng::seed_from_u64(games as u64);
+        let contribution = Amount::new(BET * games as u64 + 100);
+        let mut channel = ChannelHandlerGame::new(
+            &mut rng,
+            env,
+            GameID(0),
+            &CoinID::default(),
+            &[contribution.clone(), contribution],
+            (*DEFAULT_UNROLL_TIME_LOCK).clone(),
+        )
+        .expect("should build");
+        channel
+            .finish_handshake(env, 1)
+            .expect("finish_handshake(1)");
+        channel
+            .finish_handshake(env, 0)
+            .expect("finish_handshake(0)");
+        let proposer = if channel.players[0].ch.have_potato() {
+            0
+        } else {
+            1
+        };
+        let acceptor = proposer ^ 1;
+
+        let factory = puzzle_registry::CALPOKER_GENERATE_CALPOKER_FACTORY
+            .to_puzzle(env.allocator)
+            .expect("should load");
+        let parameters = (BET, (1, ())).to_clvm(env.allocator).expect("parameters");
+        let parameters = Program::from_nodeptr(env.allocator, parameters).expect("program");
+        let factory_game = Game::run_factory(env.allocator, factory, &parameters)
+            .expect("calpoker factory")
+            .remove(0);
+        let timeout = (*DEFAULT_UNROLL_TIME_LOCK).clone();
+
+        let mut ids = Vec::with_capacity(games);
+        for _ in 0..games {
+            let id = GameID(channel.player(proposer).ch.allocate_my_nonce());
+            let start = std::rc::Rc::new(factory_game.game_start(&id, &timeout, true, None));
+            channel
+                .player(proposer)
+                .ch
+                .send_propose_game(env, &start, id)
+                .expect("send_propose_game");
+            ids.push(id);
+        }
+        let signatures = channel
+            .player(proposer)
+            .ch
+            .update_cached_unroll_state(env)
+            .expect("update_cached_unroll_state");
+        for id in &ids {
+            let start = std::rc::Rc::new(factory_game.game_start(id, &timeout, false, None));
+            channel
+                .player(acceptor)
+                .ch
+                .apply_received_proposal(env, &start, *id)
+                .expect("apply_received_proposal");
+        }
+        channel
+            .player(acceptor)
+            .ch
+            .verify_received_batch_signatures(env, &signatures)
+            .expect("proposal batch");
+
+        for id in &ids {
+            channel
+                .player(acceptor)
+                .ch
+                .send_accept_proposal(id)
+                .expect("send_accept_proposal");
+        }
+        let signatures = channel
+            .player(acceptor)
+            .ch
+            .update_cached_unroll_state(env)
+            .expect("update_cached_unroll_state");
+        for id in &ids {
+            channel
+                .player(proposer)
+                .ch
+                .apply_received_accept_proposal(id)
+                .expect("apply_received_accept_proposal");
+        }
+        channel
+            .player(proposer)
+            .ch
+            .verify_received_batch_signatures(env, &signatures)
+            .expect("accept batch");
+
+        (channel, proposer)
+    }
+
+    fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            f();
+        }
+        start.elapsed() / ITERATIONS
+    }
+
+    fn bench_games(env: &mut ChannelEnv<'_>, games: usize, timing: bool) {
+        let (mut channel, holder) = channel_with_games(env, games);
+        let receiver = holder ^ 1;
+        let signatures = channel
+            .player(holder)
+            .ch
+            .send_empty_potato(env)
+            .expect("send_empty_potato");
+        let ch: ChannelState = channel.player(receiver).ch.clone();
+        assert_eq!(ch.live_game_ids().len(), games);
+
+        let checks = ch
+            .received_batch_signature_checks(env, &signatures)
+            .expect("signature checks");
+        assert_eq!(checks.len(), 2, "a pass carries two signatures");
+
+        if games == GAME_COUNTS[0] && timing {
+            let one_by_one = time(|| {
+                for check in &checks {
+                    assert!(check.signature.verify(&check.public_key, &check.message));
+                }
+            });
+            let batched = time(|| verify_signature_batch(&checks).expect("batch"));
+            eprintln!("  signatures: one-by-one {one_by_one:.2?}, batched {batched:.2?}");
+        }
+        if timing {
+            let conditions = time(|| {
+                ch.received_batch_signature_checks(env, &signatures)
+                    .expect("signature checks")
+            });
+            let receive = time(|| {
+                ch.clone()
+                    .received_empty_potato(env, &signatures)
+                    .expect("received_empty_potato")
+            });
+            eprintln!("  {games} games: conditions {conditions:.2?}, whole receive {receive:.2?}");
+        }
+
+        // A bad half signature must still be caught, and named, after the
+        // batch fails.
+        let wrong: PrivateKey = ChaCha8Rng::seed_from_u64(99).random();
+        let bad = StateUpdateSignatures {
+            channel_half_sig: wrong.sign(b"not the channel spend"),
+            ..signatures
+        };
+        let error = ch
+            .clone()
+            .received_empty_potato(env, &bad)
+            .expect_err("bad channel signature");
+        assert!(
+            matches!(&error, Error::StrErr(s) if s == "failed to verify signature"),
+            "unexpected error {error:?}"
+        );
+    }
+
+    pub(crate) fn bench_potato_receive() {
+        let timing = std::env::var("SIM_TIMING").is_ok();
+        let mut allocator = AllocEncoder::new();
+        let unroll_puzzle = read_unroll_puzzle(&mut allocator).unwrap();
+        let nil = allocator.allocator().nil();
+        let ref_coin_puz = Puzzle::from_nodeptr(&allocator, nil).expect("should work");
+        let ref_coin_ph = ref_coin_puz.sha256tree(&mut allocator);
+        let standard_puzzle = get_standard_coin_puzzle(&mut allocator).expect("should load");
+        let mut env = ChannelEnv {
+            allocator: &mut allocator,
+            referee_coin_puzzle: ref_coin_puz,
+            referee_coin_puzzle_hash: ref_coin_ph,
+            unroll_puzzle,
+            standard_puzzle,
+            agg_sig_me_additional_data: Hash::from_bytes(AGG_SIG_ME_ADDITIONAL_DATA),
+        };
+        for games in GAME_COUNTS {
+            bench_games(&mut env, games, timing);
+        }
+    }
+}
+
+pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
+    #[allow(unused_mut)]
+    let mut v: Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> = Vec::new();
+    #[cfg(feature = "sim-tests")]
+    {
+        v.push(("bench_potato_receive", &sim_tests::bench_potato_receive));
+    }
+    v
+}
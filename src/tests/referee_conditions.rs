@@ -1,5 +1,8 @@
 use crate::common::load_clvm::read_hex_puzzle;
-use crate::common::types::{chia_dialect, AllocEncoder, Program, Puzzle, Sha256Input, Sha256tree};
+use crate::common::types::{
+    chia_dialect, AllocEncoder, Amount, Hash, Program, Puzzle, PuzzleHash, Sha256Input, Sha256tree,
+};
+use crate::operator_fee::{net_payouts, GameFee};
 use crate::utils::proper_list;
 
 use clvm_traits::ToClvm;
@@ -8,6 +11,10 @@ use clvmr::run_program;
 
 const AMOUNT: i64 = 200;
 const AGG_SIG_UNSAFE_CODE: i64 = 49;
+const CREATE_COIN_CODE: i64 = 51;
+const MOVER_PAYOUT_PH: [u8; 32] = [0x33; 32];
+const WAITER_PAYOUT_PH: [u8; 32] = [0x34; 32];
+const FEE_PH: [u8; 32] = [0x55; 32];
 
 fn sha256_concat(parts: &[&[u8]]) -> [u8; 32] {
     let inputs: Vec<Sha256Input> = parts.iter().map(|b| Sha256Input::Bytes(b)).collect();
@@ -50,30 +57,16 @@ fn shatree_of(allocator: &mut AllocEncoder, node: NodePtr) -> [u8; 32] {
         .bytes()
 }
 
-/// Run the referee slash path with the mock validator.
-/// `validator_return` is what the mock validator will return (placed as previous_state).
-/// `committed_infohash_b` and `committed_max_move_size` are what the referee has committed to.
-fn run_referee_slash_with_mock(
+/// The referee's curried args.  `fee` appends the operator fee terms.
+fn referee_curried_args(
     allocator: &mut AllocEncoder,
-    validator_return: NodePtr,
+    referee_hash: &[u8; 32],
     committed_infohash_b: &[u8; 32],
     committed_max_move_size: i64,
-) -> Result<NodePtr, String> {
-    let referee = load_referee_puzzle(allocator);
-    let referee_clvm = referee.to_clvm(allocator).expect("referee to clvm");
-    let referee_hash: [u8; 32] = *referee.sha256tree(allocator).hash().bytes();
-
-    let mock_validator = load_mock_validator(allocator);
-    let mock_validator_clvm = mock_validator
-        .to_clvm(allocator)
-        .expect("mock validator to clvm");
-    let mock_validator_hash: [u8; 32] = *mock_validator.sha256tree(allocator).hash().bytes();
-
-    // previous_state IS the validator_return value (mock just returns it)
-    let previous_state = validator_return;
-    let previous_state_hash = shatree_of(allocator, previous_state);
-    let infohash_a = sha256_concat(&[&mock_validator_hash, &previous_state_hash]);
-
+    mover_share: i64,
+    infohash_a: &[u8; 32],
+    fee: Option<i64>,
+) -> NodePtr {
     let mover_pk = allocator
         .allocator()
         .new_atom(&[0x11; 48])
@@ -89,38 +82,38 @@ fn run_referee_slash_with_mock(
     let move_node = allocator.allocator().new_atom(&[0x44; 5]).expect("move");
     let max_move_size = committed_max_move_size.to_clvm(allocator).expect("mms");
     let infohash_b = hash_to_node(allocator, committed_infohash_b);
-    let mover_share = 0i64.to_clvm(allocator).expect("mover_share");
-    let infohash_a_node = hash_to_node(allocator, &infohash_a);
+    let mover_share = mover_share.to_clvm(allocator).expect("mover_share");
+    let infohash_a_node = hash_to_node(allocator, infohash_a);
 
-    let evidence = NodePtr::NIL;
-    let payout_ph = allocator
-        .allocator()
-        .new_atom(&[0x33; 32])
-        .expect("payout ph");
+    let mut args = vec![
+        mover_pk,
+        waiter_pk,
+        timeout,
+        amount,
+        mod_hash,
+        nonce,
+        move_node,
+        max_move_size,
+        infohash_b,
+        mover_share,
+        infohash_a_node,
+    ];
+    if let Some(fee) = fee {
+        args.push(hash_to_node(allocator, &FEE_PH));
+        args.push(fee.to_clvm(allocator).expect("fee"));
+    }
+    list_from_nodes(allocator, &args)
+}
 
-    let curried_args = list_from_nodes(
-        allocator,
-        &[
-            mover_pk,
-            waiter_pk,
-            timeout,
-            amount,
-            mod_hash,
-            nonce,
-            move_node,
-            max_move_size,
-            infohash_b,
-            mover_share,
-            infohash_a_node,
-        ],
-    );
-    let slash_args = list_from_nodes(
-        allocator,
-        &[previous_state, mock_validator_clvm, evidence, payout_ph],
-    );
+fn run_referee(
+    allocator: &mut AllocEncoder,
+    referee_clvm: NodePtr,
+    curried_args: NodePtr,
+    solution: NodePtr,
+) -> Result<NodePtr, String> {
     let args = allocator
         .allocator()
-        .new_pair(curried_args, slash_args)
+        .new_pair(curried_args, solution)
         .expect("build referee args");
 
     match run_program(
@@ -135,6 +128,137 @@ fn run_referee_slash_with_mock(
     }
 }
 
+/// Run the referee slash path with the mock validator.
+/// `validator_return` is what the mock validator will return (placed as previous_state).
+/// `committed_infohash_b` and `committed_max_move_size` are what the referee has committed to.
+fn run_referee_slash_with_mock(
+    allocator: &mut AllocEncoder,
+    validator_return: NodePtr,
+    committed_infohash_b: &[u8; 32],
+    committed_max_move_size: i64,
+) -> Result<NodePtr, String> {
+    run_referee_slash_with_fee(
+        allocator,
+        validator_return,
+        committed_infohash_b,
+        committed_max_move_size,
+        None,
+    )
+}
+
+fn run_referee_slash_with_fee(
+    allocator: &mut AllocEncoder,
+    validator_return: NodePtr,
+    committed_infohash_b: &[u8; 32],
+    committed_max_move_size: i64,
+    fee: Option<i64>,
+) -> Result<NodePtr, String> {
+    let referee = load_referee_puzzle(allocator);
+    let referee_clvm = referee.to_clvm(allocator).expect("referee to clvm");
+    let referee_hash: [u8; 32] = *referee.sha256tree(allocator).hash().bytes();
+
+    let mock_validator = load_mock_validator(allocator);
+    let mock_validator_clvm = mock_validator
+        .to_clvm(allocator)
+        .expect("mock validator to clvm");
+    let mock_validator_hash: [u8; 32] = *mock_validator.sha256tree(allocator).hash().bytes();
+
+    // previous_state IS the validator_return value (mock just returns it)
+    let previous_state = validator_return;
+    let previous_state_hash = shatree_of(allocator, previous_state);
+    let infohash_a = sha256_concat(&[&mock_validator_hash, &previous_state_hash]);
+
+    let curried_args = referee_curried_args(
+        allocator,
+        &referee_hash,
+        committed_infohash_b,
+        committed_max_move_size,
+        0,
+        &infohash_a,
+        fee,
+    );
+    let evidence = NodePtr::NIL;
+    let payout_ph = hash_to_node(allocator, &MOVER_PAYOUT_PH);
+    let slash_args = list_from_nodes(
+        allocator,
+        &[previous_state, mock_validator_clvm, evidence, payout_ph],
+    );
+    run_referee(allocator, referee_clvm, curried_args, slash_args)
+}
+
+/// Run the referee timeout path.  A payout puzzle hash is passed only for a
+/// side the Rust payout split says gets paid, as the referee requires.
+fn run_referee_timeout(
+    allocator: &mut AllocEncoder,
+    mover_share: i64,
+    fee: Option<i64>,
+) -> Result<NodePtr, String> {
+    let referee = load_referee_puzzle(allocator);
+    let referee_clvm = referee.to_clvm(allocator).expect("referee to clvm");
+    let referee_hash: [u8; 32] = *referee.sha256tree(allocator).hash().bytes();
+    let curried_args = referee_curried_args(
+        allocator,
+        &referee_hash,
+        &[0x00; 32],
+        5,
+        mover_share,
+        &[0x00; 32],
+        fee,
+    );
+    let (mover_pay, waiter_pay) = expected_payouts(mover_share, fee);
+    let payout_ph = |allocator: &mut AllocEncoder, pay: &Amount, ph: &[u8; 32]| {
+        if *pay == Amount::default() {
+            NodePtr::NIL
+        } else {
+            hash_to_node(allocator, ph)
+        }
+    };
+    let mover_payout_ph = payout_ph(allocator, &mover_pay, &MOVER_PAYOUT_PH);
+    let waiter_payout_ph = payout_ph(allocator, &waiter_pay, &WAITER_PAYOUT_PH);
+    let timeout_args = list_from_nodes(allocator, &[mover_payout_ph, waiter_payout_ph]);
+    run_referee(allocator, referee_clvm, curried_args, timeout_args)
+}
+
+fn fee_for_test(fee: i64) -> GameFee {
+    GameFee {
+        puzzle_hash: PuzzleHash::from_hash(Hash::from_bytes(FEE_PH)),
+        amount: Amount::new(fee as u64),
+    }
+}
+
+fn expected_payouts(mover_share: i64, fee: Option<i64>) -> (Amount, Amount) {
+    let fee = fee.map(fee_for_test);
+    net_payouts(
+        fee.as_ref(),
+        &Amount::new(AMOUNT as u64),
+        &Amount::new(mover_share as u64),
+    )
+    .expect("valid split")
+}
+
+/// The (puzzle hash, amount) of each CREATE_COIN in `output`, in order.  The
+/// test keys aren't valid points, so this doesn't parse the other conditions.
+fn created_coins(allocator: &AllocEncoder, output: NodePtr) -> Vec<(PuzzleHash, Amount)> {
+    let a = allocator.allocator_ref();
+    proper_list(a, output, true)
+        .expect("conditions")
+        .into_iter()
+        .filter_map(|condition| {
+            let fields = proper_list(a, condition, true)?;
+            if a.atom(fields[0]).as_ref() != [CREATE_COIN_CODE as u8] {
+                return None;
+            }
+            let ph = Hash::from_slice(a.atom(fields[1]).as_ref()).expect("puzzle hash");
+            let amount = Amount::from_clvm(allocator, fields[2]).expect("amount");
+            Some((PuzzleHash::from_hash(ph), amount))
+        })
+        .collect()
+}
+
+fn ph(bytes: [u8; 32]) -> PuzzleHash {
+    PuzzleHash::from_hash(Hash::from_bytes(bytes))
+}
+
 /// Validator returns nil → unconditional slash, output = payout_conditions only
 #[test]
 fn test_slash_succeeds_nil() {
@@ -245,6 +369,97 @@ fn test_slash_fails_aligned_no_conditions() {
     );
 }
 
+/// Without fee terms the timeout pays the two shares as they stand.
+#[test]
+fn test_timeout_without_fee() {
+    let mut allocator = AllocEncoder::new();
+    let output = run_referee_timeout(&mut allocator, 150, None).expect("timeout");
+    assert_eq!(
+        created_coins(&allocator, output),
+        vec![
+            (ph(MOVER_PAYOUT_PH), Amount::new(150)),
+            (ph(WAITER_PAYOUT_PH), Amount::new(50)),
+        ]
+    );
+}
+
+/// The fee comes out of the larger share, here the mover's, and is paid last.
+#[test]
+fn test_timeout_fee_from_mover() {
+    let mut allocator = AllocEncoder::new();
+    let output = run_referee_timeout(&mut allocator, 150, Some(10)).expect("timeout");
+    assert_eq!(
+        expected_payouts(150, Some(10)),
+        (Amount::new(140), Amount::new(50))
+    );
+    assert_eq!(
+        created_coins(&allocator, output),
+        vec![
+            (ph(MOVER_PAYOUT_PH), Amount::new(140)),
+            (ph(WAITER_PAYOUT_PH), Amount::new(50)),
+            (ph(FEE_PH), Amount::new(10)),
+        ]
+    );
+}
+
+#[test]
+fn test_timeout_fee_from_waiter() {
+    let mut allocator = AllocEncoder::new();
+    let output = run_referee_timeout(&mut allocator, 40, Some(10)).expect("timeout");
+    assert_eq!(
+        created_coins(&allocator, output),
+        vec![
+            (ph(MOVER_PAYOUT_PH), Amount::new(40)),
+            (ph(WAITER_PAYOUT_PH), Amount::new(150)),
+            (ph(FEE_PH), Amount::new(10)),
+        ]
+    );
+}
+
+/// On a tie the mover pays.
+#[test]
+fn test_timeout_fee_tie_mover_pays() {
+    let mut allocator = AllocEncoder::new();
+    let output = run_referee_timeout(&mut allocator, 100, Some(10)).expect("timeout");
+    assert_eq!(
+        created_coins(&allocator, output),
+        vec![
+            (ph(MOVER_PAYOUT_PH), Amount::new(90)),
+            (ph(WAITER_PAYOUT_PH), Amount::new(100)),
+            (ph(FEE_PH), Amount::new(10)),
+        ]
+    );
+}
+
+/// A waiter left with nothing still sees the fee paid.
+#[test]
+fn test_timeout_fee_mover_takes_all() {
+    let mut allocator = AllocEncoder::new();
+    let output = run_referee_timeout(&mut allocator, AMOUNT, Some(10)).expect("timeout");
+    assert_eq!(
+        created_coins(&allocator, output),
+        vec![
+            (ph(MOVER_PAYOUT_PH), Amount::new(190)),
+            (ph(FEE_PH), Amount::new(10)),
+        ]
+    );
+}
+
+/// A slash pays the pot less the fee, and the fee.
+#[test]
+fn test_slash_pays_fee() {
+    let mut allocator = AllocEncoder::new();
+    let output = run_referee_slash_with_fee(&mut allocator, NodePtr::NIL, &[0x00; 32], 5, Some(10))
+        .expect("slash with nil validator_result should succeed");
+    assert_eq!(
+        created_coins(&allocator, output),
+        vec![
+            (ph(MOVER_PAYOUT_PH), Amount::new(190)),
+            (ph(FEE_PH), Amount::new(10)),
+        ]
+    );
+}
+
 pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
     vec![
         ("test_slash_succeeds_nil", &test_slash_succeeds_nil),
@@ -260,5 +475,20 @@ pub fn test_funs() -> Vec<(&'static str, &'static (dyn Fn() + Send + Sync))> {
             "test_slash_fails_aligned_no_conditions",
             &test_slash_fails_aligned_no_conditions,
         ),
+        ("test_timeout_without_fee", &test_timeout_without_fee),
+        ("test_timeout_fee_from_mover", &test_timeout_fee_from_mover),
+        (
+            "test_timeout_fee_from_waiter",
+            &test_timeout_fee_from_waiter,
+        ),
+        (
+            "test_timeout_fee_tie_mover_pays",
+            &test_timeout_fee_tie_mover_pays,
+        ),
+        (
+            "test_timeout_fee_mover_takes_all",
+            &test_timeout_fee_mover_takes_all,
+        ),
+        ("test_slash_pays_fee", &test_slash_pays_fee),
     ]
 }
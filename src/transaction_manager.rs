@@ -24,10 +24,11 @@
 
 use std::collections::HashMap;
 
+use bencodex::UnknownFields;
 use serde::{Deserialize, Serialize};
 
 use crate::common::types::{
-    AllocEncoder, CoinCondition, CoinID, CoinString, Error, SpendBundle, Timeout,
+    Aggsig, AllocEncoder, CoinCondition, CoinID, CoinSpend, CoinString, Error, SpendBundle, Timeout,
 };
 use crate::game_session::{CoinObservation, DrainResult, GameSession};
 use crate::session_phases::effects::{
@@ -36,14 +37,29 @@ use crate::session_phases::effects::{
 
 /// Raw per-coin chain state as reported by the polling layer for a single
 /// watched coin.  `created_height`/`spent_height` are `None` until the coin is
-/// observed created/spent on-chain.
+/// observed created/spent on-chain.  `created_timestamp` is the timestamp of
+/// the creating block, needed only for coins with seconds timeouts.
 #[derive(Debug, Clone)]
 pub struct CoinStateRecord {
     pub coin: CoinString,
     pub created_height: Option<u64>,
+    pub created_timestamp: Option<u64>,
     pub spent_height: Option<u64>,
 }
 
+impl CoinStateRecord {
+    /// A record without a creation timestamp, as from a feed that reports
+    /// heights only.
+    pub fn new(coin: CoinString, created_height: Option<u64>, spent_height: Option<u64>) -> Self {
+        CoinStateRecord {
+            coin,
+            created_height,
+            created_timestamp: None,
+            spent_height,
+        }
+    }
+}
+
 /// A transaction the manager has handed to the hosting layer for submission,
 /// retained so its outputs can be resubmitted if a reorg rolls them back.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +80,14 @@ struct SubmittedTx {
     /// Absolute height at/after which the transaction can no longer be included
     /// (from an `ASSERT_BEFORE_HEIGHT_ABSOLUTE`).  `None` means no expiry.
     expiry: Option<u64>,
+    /// A matured `WatchedCoin::timeout_spend`, which may share a bundle with
+    /// other claims drained alongside it.
+    #[serde(default)]
+    timeout_claim: bool,
+    /// The aggregated bundle this claim last went out in, if it was merged.
+    /// Components sharing an id succeed or fail together on chain.
+    #[serde(default)]
+    aggregate_id: Option<u64>,
 }
 
 fn expected_output_coins(bundle: &SpendBundle) -> Result<Vec<CoinString>, Error> {
@@ -103,17 +127,74 @@ fn min_expiry(a: Option<u64>, b: Option<u64>) -> Option<u64> {
     }
 }
 
+/// Merge timeout claims into as few bundles as possible.  Claims spend distinct
+/// matured coins and create only payouts, so any two with disjoint inputs can
+/// share a bundle.  The summed signature is carried on the first spend and the
+/// rest are left empty, which keeps the hosts' per-spend aggregation valid.
+/// Returns each merged bundle with the indices of the claims it contains.
+fn aggregate_timeout_claims(claims: &[SpendBundle]) -> Vec<(SpendBundle, Vec<usize>)> {
+    let mut groups: Vec<(std::collections::HashSet<CoinID>, Vec<usize>)> = Vec::new();
+    for (index, claim) in claims.iter().enumerate() {
+        let inputs: std::collections::HashSet<CoinID> =
+            claim.spends.iter().map(|s| s.coin.to_coin_id()).collect();
+        match groups
+            .iter_mut()
+            .find(|(spent, _)| spent.is_disjoint(&inputs))
+        {
+            Some((spent, members)) => {
+                spent.extend(inputs);
+                members.push(index);
+            }
+            None => groups.push((inputs, vec![index])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, members)| {
+            if members.len() == 1 {
+                return (claims[members[0]].clone(), members);
+            }
+            let mut spends: Vec<CoinSpend> = members
+                .iter()
+                .flat_map(|i| claims[*i].spends.iter().cloned())
+                .collect();
+            let mut signature = Aggsig::default();
+            for spend in spends.iter_mut() {
+                signature += std::mem::take(&mut spend.bundle.signature);
+            }
+            if let Some(first) = spends.first_mut() {
+                first.bundle.signature = signature;
+            }
+            let bundle = SpendBundle {
+                name: Some(format!("aggregated timeout claims ({})", members.len())),
+                spends,
+            };
+            (bundle, members)
+        })
+        .collect()
+}
+
 /// Per-watched-coin bookkeeping owned by the manager.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Persisted with stable field tags (see `bencodex::schema`); fields written
+/// by a newer build are carried in `unknown` so a downgrade-then-upgrade does
+/// not lose them.
+#[derive(Debug, Clone, bencodex::Schema)]
 pub struct WatchedCoin {
     pub coin: CoinString,
-    /// Relative timeout (in blocks) registered with the coin.
-    pub timeout_blocks: Timeout,
+    /// Relative timeout registered with the coin, in blocks or seconds.
+    /// Stored under its old tag from when it was always blocks.
+    #[bencodex(tag = "timeout_blocks")]
+    pub timeout: Timeout,
     /// Optional human-readable label for diagnostics.
     pub name: Option<String>,
     /// Confirmed creation height, learned the first time the coin is observed
     /// on-chain.  May change under a reorg (Phase 2).
     pub birthday: Option<u64>,
+    /// Timestamp of the block at `birthday`, when the host reports one.  A
+    /// seconds timeout matures against this rather than the height.
+    #[bencodex(optional)]
+    pub birth_timestamp: Option<u64>,
     /// Confirmed spend height, set once the coin is observed spent.
     pub spent_confirmed_at: Option<u64>,
     /// Whether the eager `timeout_spend` has already been queued for submission
@@ -126,23 +207,39 @@ pub struct WatchedCoin {
     /// manager is the sole submitter and can resubmit across reorgs.
     pub timeout_spend: Option<SpendBundle>,
     /// UI context emitted when the manager submits this timeout spend.
-    #[serde(default)]
+    #[bencodex(optional)]
     pub timeout_claim_semantic: Option<TimeoutClaimSemantic>,
     pub creation_spend: Option<SpendBundle>,
+    #[bencodex(unknown)]
+    pub unknown: UnknownFields,
 }
 
 impl WatchedCoin {
-    fn new(coin: CoinString, timeout_blocks: Timeout, name: Option<String>) -> Self {
+    /// Whether the coin has reached its relative timeout at `height`, or at
+    /// `timestamp` for a seconds timeout.
+    fn is_mature(&self, height: u64, timestamp: u64) -> bool {
+        let timeout = self.timeout.to_u64();
+        if self.timeout.as_seconds().is_some() {
+            self.birthday.is_some()
+                && matches!(self.birth_timestamp, Some(t) if t + timeout <= timestamp)
+        } else {
+            matches!(self.birthday, Some(b) if b + timeout <= height)
+        }
+    }
+
+    fn new(coin: CoinString, timeout: Timeout, name: Option<String>) -> Self {
         WatchedCoin {
             coin,
-            timeout_blocks,
+            timeout,
             name,
             birthday: None,
+            birth_timestamp: None,
             spent_confirmed_at: None,
             claim_submitted: false,
             timeout_spend: None,
             timeout_claim_semantic: None,
             creation_spend: None,
+            unknown: UnknownFields::default(),
         }
     }
 }
@@ -248,6 +345,13 @@ pub struct TransactionManager<C> {
     /// handler (`ASSERT_BEFORE_HEIGHT_ABSOLUTE`), so it lands on the retained
     /// `SubmittedTx` when drained.
     pending_submissions: Vec<(SpendBundle, Option<u64>)>,
+    /// Matured timeout claims awaiting the hosting layer.  Kept apart from
+    /// `pending_submissions` because claims drained together are aggregated.
+    #[serde(default)]
+    pending_timeout_claims: Vec<SpendBundle>,
+    /// Source of `SubmittedTx::aggregate_id`.
+    #[serde(default)]
+    next_aggregate_id: u64,
     /// Events for the hosting layer that were not intercepted by the manager.
     #[serde(skip)]
     pending_events: GameSessionEventQueue,
@@ -272,6 +376,10 @@ pub struct TransactionManager<C> {
     /// snapshot reconciliation path.
     #[serde(default)]
     last_snapshot_height: u64,
+    /// Timestamp of the latest block, from `report_timestamp`.  Seconds
+    /// timeouts mature against it.
+    #[serde(default)]
+    last_timestamp: u64,
     /// Tip of the rollback epoch whose timeout claims have already been
     /// invalidated. A height-only report is normally followed by a same-height
     /// authoritative snapshot; both describe one rollback and must not re-arm
@@ -341,6 +449,8 @@ impl<C> TransactionManager<C> {
             cradle,
             watched_coins: HashMap::new(),
             pending_submissions: Vec::new(),
+            pending_timeout_claims: Vec::new(),
+            next_aggregate_id: 0,
             pending_events: GameSessionEventQueue::default(),
             pending_watch_coins: Vec::new(),
             pending_unwatch_coins: Vec::new(),
@@ -348,6 +458,7 @@ impl<C> TransactionManager<C> {
             confirmation_depth: DEFAULT_CONFIRMATION_DEPTH,
             last_height: 0,
             last_snapshot_height: 0,
+            last_timestamp: 0,
             timeout_rollback_height: None,
             timeout_claim_status_reconciled: false,
             present_coins: std::collections::HashSet::new(),
@@ -389,12 +500,19 @@ impl<C> TransactionManager<C> {
 
     /// Drain transactions queued for submission to the network.  Each drained
     /// transaction is retained (keyed by the coins it spends) so its outputs can
-    /// be resubmitted if a reorg rolls them back.
+    /// be resubmitted if a reorg rolls them back.  Timeout claims drained
+    /// together go out as one aggregated bundle but are still retained one by
+    /// one, so each keeps its own expiry and can be replayed alone.
     pub fn drain_submissions(&mut self) -> Result<Vec<SpendBundle>, Error> {
+        let claims: Vec<(SpendBundle, Option<u64>)> = self
+            .pending_timeout_claims
+            .iter()
+            .map(|bundle| (bundle.clone(), None))
+            .collect();
         // Parse before consuming the queue so a bad bundle does not drop peers.
         let mut new_outputs: Vec<Option<Vec<CoinString>>> =
-            Vec::with_capacity(self.pending_submissions.len());
-        for (bundle, _) in self.pending_submissions.iter() {
+            Vec::with_capacity(self.pending_submissions.len() + claims.len());
+        for (bundle, _) in self.pending_submissions.iter().chain(claims.iter()) {
             let spent_coin_ids: Vec<CoinID> =
                 bundle.spends.iter().map(|s| s.coin.to_coin_id()).collect();
             if self
@@ -408,7 +526,14 @@ impl<C> TransactionManager<C> {
             }
         }
         let out = std::mem::take(&mut self.pending_submissions);
-        for ((bundle, expiry), outputs) in out.iter().zip(new_outputs) {
+        self.pending_timeout_claims.clear();
+        let claim_flags = std::iter::repeat_n(false, out.len()).chain(std::iter::repeat(true));
+        for (((bundle, expiry), outputs), timeout_claim) in out
+            .iter()
+            .chain(claims.iter())
+            .zip(new_outputs)
+            .zip(claim_flags)
+        {
             let spent_coin_ids: Vec<CoinID> =
                 bundle.spends.iter().map(|s| s.coin.to_coin_id()).collect();
             // Don't double-track the same creating transaction across resubmits;
@@ -427,10 +552,47 @@ impl<C> TransactionManager<C> {
                     expected_output_coins: outputs,
                     landed: false,
                     expiry: *expiry,
+                    timeout_claim,
+                    aggregate_id: None,
                 });
             }
         }
-        Ok(out.into_iter().map(|(bundle, _)| bundle).collect())
+        let mut bundles: Vec<SpendBundle> = out.into_iter().map(|(bundle, _)| bundle).collect();
+        let claims: Vec<SpendBundle> = claims.into_iter().map(|(bundle, _)| bundle).collect();
+        for (bundle, members) in aggregate_timeout_claims(&claims) {
+            let aggregate_id = if members.len() > 1 {
+                self.next_aggregate_id += 1;
+                Some(self.next_aggregate_id)
+            } else {
+                None
+            };
+            for index in members {
+                let spent_coin_ids: Vec<CoinID> = claims[index]
+                    .spends
+                    .iter()
+                    .map(|s| s.coin.to_coin_id())
+                    .collect();
+                if let Some(tx) = self
+                    .submitted
+                    .iter_mut()
+                    .find(|t| t.spent_coin_ids == spent_coin_ids)
+                {
+                    tx.aggregate_id = aggregate_id;
+                }
+            }
+            bundles.push(bundle);
+        }
+        Ok(bundles)
+    }
+
+    /// Queue a retained submission again, routing timeout claims back through
+    /// aggregation.
+    fn requeue(&mut self, bundle: SpendBundle, expiry: Option<u64>, timeout_claim: bool) {
+        if timeout_claim {
+            self.pending_timeout_claims.push(bundle);
+        } else {
+            self.pending_submissions.push((bundle, expiry));
+        }
     }
 
     /// Re-queue retained, unexpired submissions after the host has supplied a
@@ -440,9 +602,13 @@ impl<C> TransactionManager<C> {
     pub fn requeue_submitted(&mut self) {
         self.submitted
             .retain(|tx| !matches!(tx.expiry, Some(expiry) if self.last_height >= expiry));
-        for tx in self.submitted.iter() {
-            self.pending_submissions
-                .push((tx.bundle.clone(), tx.expiry));
+        let retained: Vec<(SpendBundle, Option<u64>, bool)> = self
+            .submitted
+            .iter()
+            .map(|tx| (tx.bundle.clone(), tx.expiry, tx.timeout_claim))
+            .collect();
+        for (bundle, expiry, timeout_claim) in retained {
+            self.requeue(bundle, expiry, timeout_claim);
         }
     }
 
@@ -459,7 +625,7 @@ impl<C> TransactionManager<C> {
         self.watched_coins
             .entry(coin.clone())
             .and_modify(|w| {
-                w.timeout_blocks = timeout.clone();
+                w.timeout = timeout.clone();
                 if spend.is_some() {
                     w.timeout_spend = spend.clone();
                     w.timeout_claim_semantic = semantic;
@@ -500,6 +666,17 @@ impl<C> TransactionManager<C> {
 }
 
 impl<C: ManagedGameSession> TransactionManager<C> {
+    /// Record the timestamp of the latest block.  Hosts running seconds
+    /// timeouts call this before each report; the claims it matures are
+    /// queued by the next `report_height` or `report_coin_states`.  There is
+    /// no fallback to heights: without timestamps here and
+    /// `created_timestamp` in the coin records, a seconds timeout never
+    /// matures and its claim is never submitted.  A host that can't supply
+    /// them should configure block timeouts.
+    pub fn report_timestamp(&mut self, timestamp: u64) {
+        self.last_timestamp = timestamp;
+    }
+
     /// Report a trusted chain height when the watched-coin snapshot is not
     /// available or is known partial. This advances handshake protocol clocks
     /// through the manager without inventing coin creations/deletions or
@@ -595,7 +772,7 @@ impl<C: ManagedGameSession> TransactionManager<C> {
     fn evaluate_mature_timeout_claims(&mut self, height: u64) -> Result<(), Error> {
         let mut to_submit: Vec<(SpendBundle, Option<TimeoutClaimSemantic>)> = Vec::new();
         for watched in self.watched_coins.values_mut() {
-            let ripe = matches!(watched.birthday, Some(b) if b + watched.timeout_blocks.to_u64() <= height);
+            let ripe = watched.is_mature(height, self.last_timestamp);
             if ripe && !watched.claim_submitted && watched.spent_confirmed_at.is_none() {
                 if let Some(spend) = &watched.timeout_spend {
                     to_submit.push((spend.clone(), watched.timeout_claim_semantic));
@@ -607,13 +784,14 @@ impl<C: ManagedGameSession> TransactionManager<C> {
             if let Some(semantic) = semantic {
                 self.cradle.session_timeout_claim_submitted(semantic)?;
             }
-            self.pending_submissions.push((spend, None));
+            self.pending_timeout_claims.push(spend);
         }
         Ok(())
     }
 
     fn discard_local_artifacts(&mut self) {
         self.pending_submissions.clear();
+        self.pending_timeout_claims.clear();
         self.pending_events.clear();
         self.pending_watch_coins.clear();
         self.pending_unwatch_coins.clear();
@@ -674,6 +852,7 @@ impl<C: ManagedGameSession> TransactionManager<C> {
                     // transaction that created it can be resubmitted.
                     let was_claim_submitted = watched.claim_submitted;
                     watched.birthday = None;
+                    watched.birth_timestamp = None;
                     watched.claim_submitted = false;
                     if was_claim_submitted {
                         if let Some(semantic) = watched.timeout_claim_semantic {
@@ -715,6 +894,9 @@ impl<C: ManagedGameSession> TransactionManager<C> {
             let was_present = self.present_coins.contains(&rec.coin);
             if let Some(watched) = self.watched_coins.get_mut(&rec.coin) {
                 if let Some(created_height) = rec.created_height {
+                    if rec.created_timestamp.is_some() {
+                        watched.birth_timestamp = rec.created_timestamp;
+                    }
                     if watched.birthday != Some(created_height) {
                         let was_claim_submitted = watched.claim_submitted;
                         watched.birthday = Some(created_height);
@@ -763,13 +945,39 @@ impl<C: ManagedGameSession> TransactionManager<C> {
             }
         }
         if !spent_inputs.is_empty() {
+            let mut broken_aggregates = std::collections::HashSet::new();
             self.submitted.retain(|tx| {
                 let spends_observed_input = tx
                     .spent_coin_ids
                     .iter()
                     .any(|coin_id| spent_inputs.contains(coin_id));
-                !spends_observed_input || tx.landed || tx.expected_output_coins.is_empty()
+                let keep =
+                    !spends_observed_input || tx.landed || tx.expected_output_coins.is_empty();
+                if !keep {
+                    broken_aggregates.extend(tx.aggregate_id);
+                }
+                keep
             });
+            // A conflicting component sinks the whole aggregated bundle, so the
+            // components that never landed are split out and resubmitted
+            // without it.
+            let mut split = Vec::new();
+            for tx in self.submitted.iter_mut() {
+                let unspent = !tx
+                    .spent_coin_ids
+                    .iter()
+                    .any(|coin_id| spent_inputs.contains(coin_id));
+                if tx
+                    .aggregate_id
+                    .is_some_and(|id| broken_aggregates.contains(&id))
+                    && !tx.landed
+                    && unspent
+                {
+                    tx.aggregate_id = None;
+                    split.push(tx.bundle.clone());
+                }
+            }
+            self.pending_timeout_claims.extend(split);
         }
 
         // Created/deleted are the symmetric difference against the previous
@@ -865,7 +1073,7 @@ impl<C: ManagedGameSession> TransactionManager<C> {
                 None => continue,
             };
             // Find (and prune expired) the transaction that created this coin.
-            let mut resubmit: Option<(SpendBundle, Option<u64>)> = None;
+            let mut resubmit: Option<(SpendBundle, Option<u64>, bool)> = None;
             self.submitted.retain(|tx| {
                 if !tx.spent_coin_ids.contains(&parent) {
                     return true;
@@ -873,11 +1081,11 @@ impl<C: ManagedGameSession> TransactionManager<C> {
                 if matches!(tx.expiry, Some(e) if height >= e + CHANNEL_EXPIRY_BUFFER) {
                     return false;
                 }
-                resubmit = Some((tx.bundle.clone(), tx.expiry));
+                resubmit = Some((tx.bundle.clone(), tx.expiry, tx.timeout_claim));
                 true
             });
-            if let Some(submission) = resubmit {
-                self.pending_submissions.push(submission);
+            if let Some((bundle, expiry, timeout_claim)) = resubmit {
+                self.requeue(bundle, expiry, timeout_claim);
             }
         }
     }
@@ -947,7 +1155,8 @@ mod tests {
     use super::*;
     use crate::common::constants::CREATE_COIN;
     use crate::common::types::{
-        Amount, CoinID, CoinSpend, Hash, Program, Puzzle, PuzzleHash, Spend, ToQuotedProgram,
+        Amount, CoinID, CoinSpend, Hash, PrivateKey, Program, Puzzle, PuzzleHash, Spend,
+        ToQuotedProgram,
     };
     use crate::session_phases::effects::{GameSessionEvent, TimeoutClaimSemantic};
     use clvm_traits::ToClvm;
@@ -1171,7 +1380,7 @@ mod tests {
         assert!(drain.events.is_empty());
         assert_eq!(drain.watch_coins, vec![coin.clone()]);
         let watched = mgr.watched_coin(&coin).expect("tracked");
-        assert_eq!(watched.timeout_blocks, Timeout::new(100));
+        assert_eq!(watched.timeout, Timeout::new(100));
         assert_eq!(watched.birthday, None);
         assert_eq!(mgr.snapshot_watched_coins(), vec![coin]);
     }
@@ -1228,30 +1437,18 @@ mod tests {
         mgr.flush_and_collect(&mut allocator).expect("register");
 
         // First sighting at height 10: created.
-        let records = vec![CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(10),
-            spent_height: None,
-        }];
+        let records = vec![CoinStateRecord::new(coin.clone(), Some(10), None)];
         mgr.report_coin_states(&mut allocator, 10, &records)
             .expect("report");
         assert_eq!(mgr.watched_coin(&coin).unwrap().birthday, Some(10));
 
         // Second sighting at height 12, still unspent: no new diff.
-        let records = vec![CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(10),
-            spent_height: None,
-        }];
+        let records = vec![CoinStateRecord::new(coin.clone(), Some(10), None)];
         mgr.report_coin_states(&mut allocator, 12, &records)
             .expect("report");
 
         // Third sighting at height 20: spent.
-        let records = vec![CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(10),
-            spent_height: Some(20),
-        }];
+        let records = vec![CoinStateRecord::new(coin.clone(), Some(10), Some(20))];
         mgr.report_coin_states(&mut allocator, 20, &records)
             .expect("report");
         assert_eq!(
@@ -1288,11 +1485,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             10,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), None)],
         )
         .expect("authoritative snapshot");
         assert_eq!(mgr.watched_coin(&coin).unwrap().birthday, Some(10));
@@ -1317,11 +1510,7 @@ mod tests {
         let coin = test_coin(3);
         let mut mgr = TransactionManager::new(MockGameSession::default());
 
-        let records = vec![CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(5),
-            spent_height: None,
-        }];
+        let records = vec![CoinStateRecord::new(coin.clone(), Some(5), None)];
         mgr.report_coin_states(&mut allocator, 5, &records)
             .expect("report");
 
@@ -1343,11 +1532,7 @@ mod tests {
         let mut mgr = TransactionManager::new(mock);
         mgr.flush_and_collect(&mut allocator).expect("register");
 
-        let live = vec![CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(10),
-            spent_height: None,
-        }];
+        let live = vec![CoinStateRecord::new(coin.clone(), Some(10), None)];
 
         // Before maturity: nothing submitted.
         mgr.report_coin_states(&mut allocator, 14, &live)
@@ -1368,17 +1553,17 @@ mod tests {
     }
 
     #[test]
-    fn mature_semantic_timeout_claim_updates_canonical_session_state() {
+    fn seconds_timeout_matures_on_block_timestamp() {
         let mut allocator = AllocEncoder::new();
-        let coin = test_coin(11);
-        let claim = test_bundle("channel-timeout-claim");
+        let coin = test_coin(10);
+        let claim = test_bundle("timeout-claim");
         let mut mock = MockGameSession::default();
         mock.queue_drain(vec![GameSessionEvent::WatchCoin {
             coin_name: coin.to_coin_id(),
             coin_string: coin.clone(),
-            timeout: Timeout::new(5),
+            timeout: Timeout::seconds(300),
             spend: Some(claim),
-            semantic: Some(TimeoutClaimSemantic::ChannelTimeoutFinish),
+            semantic: None,
         }]);
         let mut mgr = TransactionManager::new(mock);
         mgr.flush_and_collect(&mut allocator).expect("register");
@@ -1386,8 +1571,42 @@ mod tests {
         let live = vec![CoinStateRecord {
             coin: coin.clone(),
             created_height: Some(10),
+            created_timestamp: Some(1_000),
             spent_height: None,
         }];
+
+        // Hundreds of blocks later, but only 299 seconds: not mature.
+        mgr.report_timestamp(1_299);
+        mgr.report_coin_states(&mut allocator, 500, &live)
+            .expect("report");
+        assert!(mgr.drain_submissions().unwrap().is_empty());
+
+        // At 1_000 + 300 the claim is queued.
+        mgr.report_timestamp(1_300);
+        mgr.report_coin_states(&mut allocator, 501, &live)
+            .expect("report");
+        let subs = mgr.drain_submissions().unwrap();
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].name.as_deref(), Some("timeout-claim"));
+    }
+
+    #[test]
+    fn mature_semantic_timeout_claim_updates_canonical_session_state() {
+        let mut allocator = AllocEncoder::new();
+        let coin = test_coin(11);
+        let claim = test_bundle("channel-timeout-claim");
+        let mut mock = MockGameSession::default();
+        mock.queue_drain(vec![GameSessionEvent::WatchCoin {
+            coin_name: coin.to_coin_id(),
+            coin_string: coin.clone(),
+            timeout: Timeout::new(5),
+            spend: Some(claim),
+            semantic: Some(TimeoutClaimSemantic::ChannelTimeoutFinish),
+        }]);
+        let mut mgr = TransactionManager::new(mock);
+        mgr.flush_and_collect(&mut allocator).expect("register");
+
+        let live = vec![CoinStateRecord::new(coin.clone(), Some(10), None)];
         mgr.report_coin_states(&mut allocator, 15, &live)
             .expect("mature claim");
 
@@ -1415,11 +1634,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             10,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), None)],
         )
         .expect("observe birthday");
 
@@ -1446,11 +1661,7 @@ mod tests {
         }]);
         let mut mgr = TransactionManager::new(mock);
         mgr.flush_and_collect(&mut allocator).expect("register");
-        let live = [CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(10),
-            spent_height: None,
-        }];
+        let live = [CoinStateRecord::new(coin.clone(), Some(10), None)];
         mgr.report_coin_states(&mut allocator, 10, &live)
             .expect("observe birthday");
         mgr.report_height(&mut allocator, 15).expect("mature");
@@ -1483,11 +1694,7 @@ mod tests {
         }]);
         let mut mgr = TransactionManager::new(mock);
         mgr.flush_and_collect(&mut allocator).expect("register");
-        let live = [CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(10),
-            spent_height: None,
-        }];
+        let live = [CoinStateRecord::new(coin.clone(), Some(10), None)];
         mgr.report_coin_states(&mut allocator, 15, &live)
             .expect("mature initial claim");
         assert_eq!(mgr.drain_submissions().unwrap().len(), 1);
@@ -1528,11 +1735,7 @@ mod tests {
         }]);
         let mut mgr = TransactionManager::new(mock);
         mgr.flush_and_collect(&mut allocator).expect("register");
-        let live = [CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(10),
-            spent_height: None,
-        }];
+        let live = [CoinStateRecord::new(coin.clone(), Some(10), None)];
         mgr.report_coin_states(&mut allocator, 15, &live)
             .expect("mature");
         assert_eq!(mgr.drain_submissions().unwrap().len(), 1);
@@ -1540,11 +1743,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             16,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: Some(16),
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), Some(16))],
         )
         .expect("confirm timeout spend");
         assert_eq!(
@@ -1566,6 +1765,30 @@ mod tests {
         assert_eq!(mgr.drain_submissions().unwrap().len(), 1);
     }
 
+    #[test]
+    fn watched_coin_keeps_fields_from_newer_builds() {
+        let coin = WatchedCoin::new(test_coin(3), Timeout::new(5), Some("x".to_string()));
+        let bytes = bencodex::to_vec(&coin).expect("serialize");
+        let bencodex::Value::Dict(mut fields) =
+            bencodex::from_slice::<bencodex::Value>(&bytes).expect("untyped")
+        else {
+            panic!("watched coin should encode as a dict");
+        };
+        fields.remove(&bencodex::Key::Text("timeout_claim_semantic".to_string()));
+        fields.insert(
+            bencodex::Key::Text("future_field".to_string()),
+            bencodex::Value::Int(7),
+        );
+        let newer = bencodex::to_vec(&bencodex::Value::Dict(fields)).expect("serialize");
+
+        let restored: WatchedCoin = bencodex::from_slice(&newer).expect("restore");
+        assert_eq!(restored.name.as_deref(), Some("x"));
+        assert_eq!(restored.unknown.0.len(), 1);
+        let resaved = bencodex::to_vec(&restored).expect("serialize");
+        let again: WatchedCoin = bencodex::from_slice(&resaved).expect("restore");
+        assert_eq!(again.unknown, restored.unknown);
+    }
+
     #[test]
     fn restored_submitted_claim_reprojects_canonical_timeout_status() {
         let mut allocator = AllocEncoder::new();
@@ -1575,14 +1798,16 @@ mod tests {
             coin.clone(),
             WatchedCoin {
                 coin,
-                timeout_blocks: Timeout::new(5),
+                timeout: Timeout::new(5),
                 name: None,
                 birthday: Some(10),
+                birth_timestamp: None,
                 spent_confirmed_at: None,
                 claim_submitted: true,
                 timeout_spend: Some(test_bundle("restored-timeout")),
                 timeout_claim_semantic: Some(TimeoutClaimSemantic::ChannelTimeoutFinish),
                 creation_spend: None,
+                unknown: UnknownFields::default(),
             },
         );
 
@@ -1617,11 +1842,11 @@ mod tests {
         mgr.flush_and_collect(&mut allocator).expect("register");
 
         let record = |created_height| {
-            vec![CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(created_height),
-                spent_height: None,
-            }]
+            vec![CoinStateRecord::new(
+                coin.clone(),
+                Some(created_height),
+                None,
+            )]
         };
         mgr.report_coin_states(&mut allocator, 10, &record(10))
             .expect("created");
@@ -1667,11 +1892,7 @@ mod tests {
         }]);
         let mut mgr = TransactionManager::new(mock);
         mgr.flush_and_collect(&mut allocator).expect("register");
-        let record = vec![CoinStateRecord {
-            coin: coin.clone(),
-            created_height: Some(10),
-            spent_height: None,
-        }];
+        let record = vec![CoinStateRecord::new(coin.clone(), Some(10), None)];
 
         mgr.report_coin_states(&mut allocator, 15, &record)
             .expect("mature");
@@ -1707,11 +1928,7 @@ mod tests {
         let mut mgr = TransactionManager::new(mock);
         mgr.flush_and_collect(&mut allocator).expect("register");
 
-        let live = vec![CoinStateRecord {
-            coin,
-            created_height: Some(10),
-            spent_height: None,
-        }];
+        let live = vec![CoinStateRecord::new(coin, Some(10), None)];
         mgr.report_coin_states(&mut allocator, 15, &live)
             .expect("mature claim");
 
@@ -1732,13 +1949,7 @@ mod tests {
         let mut mgr = TransactionManager::new(mock);
         mgr.flush_and_collect(&mut allocator).expect("register");
 
-        let rec = |created: u64| {
-            vec![CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(created),
-                spent_height: None,
-            }]
-        };
+        let rec = |created: u64| vec![CoinStateRecord::new(coin.clone(), Some(created), None)];
 
         // Birthday 10 -> matures and submits at 15.
         mgr.report_coin_states(&mut allocator, 10, &rec(10))
@@ -1774,21 +1985,13 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             14,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: Some(14),
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), Some(14))],
         )
         .expect("report");
         mgr.report_coin_states(
             &mut allocator,
             15,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: Some(14),
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), Some(14))],
         )
         .expect("report");
         assert!(mgr.drain_submissions().unwrap().is_empty());
@@ -1831,11 +2034,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             10,
-            &[CoinStateRecord {
-                coin: child.clone(),
-                created_height: Some(10),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(child.clone(), Some(10), None)],
         )
         .expect("report");
         assert!(mgr.drain_submissions().unwrap().is_empty());
@@ -1904,11 +2103,11 @@ mod tests {
             .report_coin_states(
                 &mut allocator,
                 100,
-                &[CoinStateRecord {
-                    coin: coin.clone(),
-                    created_height: Some(MAX_REPORTED_HEIGHT + 1),
-                    spent_height: None,
-                }],
+                &[CoinStateRecord::new(
+                    coin.clone(),
+                    Some(MAX_REPORTED_HEIGHT + 1),
+                    None
+                )],
             )
             .is_err());
         assert_eq!(mgr.last_height(), 0);
@@ -1933,21 +2132,13 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             10,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), None)],
         )
         .expect("report");
         mgr.report_coin_states(
             &mut allocator,
             20,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: Some(20),
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), Some(20))],
         )
         .expect("report");
         assert_eq!(mgr.watched_coin(&coin).unwrap().birthday, Some(10));
@@ -1962,11 +2153,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             15,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), None)],
         )
         .expect("report");
         assert_eq!(mgr.watched_coin(&coin).unwrap().birthday, Some(10));
@@ -1985,11 +2172,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             12,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(12),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(12), None)],
         )
         .expect("report");
         assert_eq!(mgr.watched_coin(&coin).unwrap().birthday, Some(12));
@@ -2005,11 +2188,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             13,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(13),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(13), None)],
         )
         .expect("report");
         assert_eq!(mgr.watched_coin(&coin).unwrap().birthday, Some(13));
@@ -2029,11 +2208,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             12,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(12),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(12), None)],
         )
         .expect("report");
 
@@ -2071,11 +2246,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             12,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(12),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(12), None)],
         )
         .expect("report");
         assert_eq!(mgr.watched_coin(&coin).unwrap().birthday, Some(12));
@@ -2089,11 +2260,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             8,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(8),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(8), None)],
         )
         .expect("report");
         assert_eq!(mgr.watched_coin(&coin).unwrap().birthday, Some(8));
@@ -2128,11 +2295,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             10,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: None,
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), None)],
         )
         .expect("report");
         // The coin disappears while the height advances: on the full-coin-set
@@ -2167,11 +2330,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             12,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: Some(12),
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), Some(12))],
         )
         .expect("report");
 
@@ -2208,11 +2367,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             100,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(90),
-                spent_height: Some(100),
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(90), Some(100))],
         )
         .expect("report");
         assert!(mgr.watched_coin(&coin).is_some());
@@ -2322,11 +2477,7 @@ mod tests {
         mgr.report_coin_states(
             &mut allocator,
             12,
-            &[CoinStateRecord {
-                coin: coin.clone(),
-                created_height: Some(10),
-                spent_height: Some(12),
-            }],
+            &[CoinStateRecord::new(coin.clone(), Some(10), Some(12))],
         )
         .expect("report");
         mgr.requeue_submitted();
@@ -2359,16 +2510,8 @@ mod tests {
             &mut allocator,
             12,
             &[
-                CoinStateRecord {
-                    coin: coin.clone(),
-                    created_height: Some(10),
-                    spent_height: Some(12),
-                },
-                CoinStateRecord {
-                    coin: child.clone(),
-                    created_height: Some(12),
-                    spent_height: None,
-                },
+                CoinStateRecord::new(coin.clone(), Some(10), Some(12)),
+                CoinStateRecord::new(child.clone(), Some(12), None),
             ],
         )
         .expect("report");
@@ -2412,11 +2555,7 @@ mod tests {
             mgr.report_coin_states(
                 &mut allocator,
                 height,
-                &[CoinStateRecord {
-                    coin: coin.clone(),
-                    created_height: Some(10),
-                    spent_height: None,
-                }],
+                &[CoinStateRecord::new(coin.clone(), Some(10), None)],
             )
             .expect("report");
             assert!(
@@ -2425,4 +2564,103 @@ mod tests {
             );
         }
     }
+
+    /// Two watched coins with eager claims signed by different keys, both
+    /// maturing at height 15.
+    fn two_signed_claims() -> (
+        TransactionManager<MockGameSession>,
+        [CoinString; 2],
+        [Aggsig; 2],
+    ) {
+        let mut allocator = AllocEncoder::new();
+        let coins = [test_coin(50), test_coin(52)];
+        let mut signatures = Vec::new();
+        let mut events = Vec::new();
+        for (i, coin) in coins.iter().enumerate() {
+            let payout = CoinString::from_parts(
+                &coin.to_coin_id(),
+                &PuzzleHash::from_bytes([60 + i as u8; 32]),
+                &Amount::new(1),
+            );
+            let mut claim = test_bundle_spending_creating(&format!("claim-{i}"), coin, &payout);
+            let key = PrivateKey::from_bytes(&[i as u8 + 1; 32]).expect("key");
+            let signature = key.sign(format!("claim-{i}"));
+            claim.spends[0].bundle.signature = signature.clone();
+            signatures.push(signature);
+            events.push(watch_event_with_spend(coin, 5, claim));
+        }
+        let mut mock = MockGameSession::default();
+        mock.queue_drain(events);
+        let mut mgr = TransactionManager::new(mock);
+        mgr.flush_and_collect(&mut allocator).expect("register");
+        let live: Vec<CoinStateRecord> = coins
+            .iter()
+            .map(|coin| CoinStateRecord::new(coin.clone(), Some(10), None))
+            .collect();
+        mgr.report_coin_states(&mut allocator, 15, &live)
+            .expect("report");
+        let signatures: [Aggsig; 2] = signatures.try_into().expect("two signatures");
+        (mgr, coins, signatures)
+    }
+
+    #[test]
+    fn claims_maturing_together_drain_as_one_aggregated_bundle() {
+        let (mut mgr, coins, [sig_a, sig_b]) = two_signed_claims();
+        let subs = mgr.drain_submissions().unwrap();
+        assert_eq!(subs.len(), 1);
+        let spent: std::collections::HashSet<CoinString> =
+            subs[0].spends.iter().map(|s| s.coin.clone()).collect();
+        assert_eq!(spent, coins.iter().cloned().collect());
+        let carried: Vec<Aggsig> = subs[0]
+            .spends
+            .iter()
+            .map(|s| s.bundle.signature.clone())
+            .collect();
+        assert_eq!(carried, vec![sig_a + sig_b, Aggsig::default()]);
+
+        // Each claim is retained on its own, tied to the shared bundle.
+        assert_eq!(mgr.submitted.len(), 2);
+        assert!(mgr.submitted[0].aggregate_id.is_some());
+        assert_eq!(mgr.submitted[0].aggregate_id, mgr.submitted[1].aggregate_id);
+
+        // Replay re-aggregates the retained components.
+        mgr.requeue_submitted();
+        let replay = mgr.drain_submissions().unwrap();
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].spends.len(), 2);
+    }
+
+    #[test]
+    fn conflicting_component_splits_aggregated_claims() {
+        let mut allocator = AllocEncoder::new();
+        let (mut mgr, coins, [_, sig_b]) = two_signed_claims();
+        assert_eq!(mgr.drain_submissions().unwrap().len(), 1);
+
+        // The opponent spent the first coin, so the aggregated bundle cannot
+        // land.  The surviving claim is resubmitted alone.
+        mgr.report_coin_states(
+            &mut allocator,
+            16,
+            &[
+                CoinStateRecord::new(coins[0].clone(), Some(10), Some(16)),
+                CoinStateRecord::new(coins[1].clone(), Some(10), None),
+            ],
+        )
+        .expect("report");
+        let subs = mgr.drain_submissions().unwrap();
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].name.as_deref(), Some("claim-1"));
+        assert_eq!(subs[0].spends[0].bundle.signature, sig_b);
+        assert_eq!(mgr.submitted.len(), 1);
+        assert_eq!(mgr.submitted[0].aggregate_id, None);
+
+        // Split once: later blocks do not rebroadcast it.
+        mgr.report_coin_states(
+            &mut allocator,
+            17,
+            &[CoinStateRecord::new(coins[1].clone(), Some(10), None)],
+        )
+        .expect("report");
+        assert!(mgr.drain_submissions().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,698 @@
+//! Embedded standard-coin wallet.
+//!
+//! Lets a headless or test host fund channels and trade offers without an
+//! external wallet service.  The wallet tracks the standard-puzzle coins of
+//! one [`ChiaIdentity`] from the coin states the host reports, selects coins,
+//! and builds fully signed spends:
+//!
+//! - Channel funding.  [`StandardWallet::fund_request`] answers a
+//!   `CoinSpendRequest` with a spend that carries the request's conditions,
+//!   returns change, and leaves the requested amount plus the wallet's fee as
+//!   deficit.  It also implements [`ChannelFundingSource`], so a
+//!   `ChannelManager` can fund its channels from it directly.
+//! - Offers.  [`StandardWallet::create_offer`] builds a settlement-payment
+//!   offer bundle in the Chia wallet's layout (offered mojos locked in a
+//!   settlement coin, requested payments as placeholder settlement spends),
+//!   and [`StandardWallet::take_offer`] completes such a bundle with our own
+//!   coins into a spendable transaction once its [`offer_terms`] match what
+//!   the user agreed to.  [`crate::offer_file`] carries these bundles as
+//!   `offer1…` files.
+//!
+//! Coins spent by a bundle the wallet built stay pending until the host
+//! reports them spent or hands them back with [`StandardWallet::release`].
+
+use std::collections::BTreeSet;
+
+use clvm_traits::{ClvmEncoder, ToClvm};
+use clvmr::NodePtr;
+use serde::{Deserialize, Serialize};
+
+use crate::channel_manager::ChannelFundingSource;
+use crate::channel_state::types::ChannelEnv;
+use crate::common::constants::{
+    ASSERT_BEFORE_HEIGHT_ABSOLUTE, ASSERT_COIN_ANNOUNCEMENT, ASSERT_PUZZLE_ANNOUNCEMENT,
+    CREATE_COIN, CREATE_COIN_ANNOUNCEMENT, RESERVE_FEE,
+};
+use crate::common::standard_coin::{sign_agg_sig_me, solution_for_conditions, ChiaIdentity};
+use crate::common::types::{
+    Aggsig, AllocEncoder, Amount, CoinCondition, CoinID, CoinSpend, CoinString, Error, IntoErr,
+    Node, Program, Puzzle, PuzzleHash, Sha256Input, Sha256tree, Spend, SpendBundle,
+    ToQuotedProgram,
+};
+use crate::session_phases::handshake::CoinSpendRequest;
+use crate::transaction_manager::CoinStateRecord;
+use crate::utils::proper_list;
+
+/// Message the primary coin of a multi-coin spend announces; every other coin
+/// in the spend asserts it, so none can be spent without the others.
+const LINK_MESSAGE: &[u8] = b"$";
+
+fn coin_amount(coin: &CoinString) -> Result<u64, Error> {
+    coin.to_parts()
+        .map(|(_, _, amount)| amount.to_u64())
+        .ok_or_else(|| Error::StrErr("malformed coin string".to_string()))
+}
+
+fn settlement_puzzle_hash() -> PuzzleHash {
+    PuzzleHash::from_bytes(chia_puzzles::SETTLEMENT_PAYMENT_HASH)
+}
+
+fn create_coin(
+    allocator: &mut AllocEncoder,
+    puzzle_hash: &PuzzleHash,
+    amount: u64,
+) -> Result<Node, Error> {
+    (
+        CREATE_COIN,
+        (puzzle_hash.clone(), (Amount::new(amount), ())),
+    )
+        .to_clvm(allocator)
+        .into_gen()
+        .map(Node)
+}
+
+fn atom_condition(allocator: &mut AllocEncoder, opcode: u32, arg: &[u8]) -> Result<Node, Error> {
+    let arg = Node(
+        allocator
+            .encode_atom(clvm_traits::Atom::Borrowed(arg))
+            .into_gen()?,
+    );
+    (opcode, (arg, ())).to_clvm(allocator).into_gen().map(Node)
+}
+
+/// `ASSERT_PUZZLE_ANNOUNCEMENT` for the announcement a settlement coin makes
+/// when it pays `notarized_payment`.
+fn assert_settlement_announcement(
+    allocator: &mut AllocEncoder,
+    notarized_payment: NodePtr,
+) -> Result<Node, Error> {
+    let message = Node(notarized_payment).sha256tree(allocator);
+    let settlement_ph = settlement_puzzle_hash();
+    let announcement = Sha256Input::Array(vec![
+        Sha256Input::Bytes(settlement_ph.bytes()),
+        Sha256Input::Bytes(message.bytes()),
+    ])
+    .hash();
+    atom_condition(allocator, ASSERT_PUZZLE_ANNOUNCEMENT, announcement.bytes())
+}
+
+/// A notarized payment `(nonce . ((puzzle_hash amount (puzzle_hash))))`, the
+/// shape the settlement puzzle pays out and announces.
+fn notarized_payment(
+    allocator: &mut AllocEncoder,
+    nonce: &PuzzleHash,
+    puzzle_hash: &PuzzleHash,
+    amount: u64,
+) -> Result<NodePtr, Error> {
+    let memos = (puzzle_hash.clone(), ());
+    let payment = (puzzle_hash.clone(), (Amount::new(amount), (memos, ())));
+    (nonce.clone(), (payment, ())).to_clvm(allocator).into_gen()
+}
+
+/// Offers are told apart by a nonce over the coins that fund them.
+fn offer_nonce(allocator: &mut AllocEncoder, coins: &[CoinString]) -> PuzzleHash {
+    let mut ids: Vec<CoinID> = coins.iter().map(|coin| coin.to_coin_id()).collect();
+    ids.sort_by(|a, b| a.bytes().cmp(b.bytes()));
+    ids.sha256tree(allocator)
+}
+
+/// What an offer asks of its taker: the mojos it leaves in settlement coins
+/// for us, and the mojos it wants paid back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OfferTerms {
+    pub offered: Amount,
+    pub requested: Amount,
+}
+
+/// An offer bundle split into the maker's spends, what those spends lock in
+/// settlement coins, and the payments the placeholder spends request.
+struct ParsedOffer {
+    maker_spends: Vec<CoinSpend>,
+    requested_payments: Vec<Node>,
+    requested: u64,
+    offered_coins: Vec<CoinString>,
+}
+
+fn parse_offer(allocator: &mut AllocEncoder, offer: &SpendBundle) -> Result<ParsedOffer, Error> {
+    let settlement_ph = settlement_puzzle_hash();
+    let mut parsed = ParsedOffer {
+        maker_spends: Vec::new(),
+        requested_payments: Vec::new(),
+        requested: 0,
+        offered_coins: Vec::new(),
+    };
+    for spend in &offer.spends {
+        let puzzle = spend.bundle.puzzle.to_program();
+        let solution = spend.bundle.solution.p();
+        let conditions = CoinCondition::from_puzzle_and_solution(allocator, &puzzle, &solution)?;
+        if is_requested_payment(spend) {
+            for condition in conditions {
+                if let CoinCondition::CreateCoin(_, amount) = condition {
+                    parsed.requested += amount.to_u64();
+                }
+            }
+            let solution = solution.to_nodeptr(allocator)?;
+            let payments = proper_list(allocator.allocator_ref(), solution, true)
+                .ok_or_else(|| Error::StrErr("offer payments are not a list".to_string()))?;
+            parsed
+                .requested_payments
+                .extend(payments.into_iter().map(Node));
+            continue;
+        }
+        for condition in conditions {
+            if let CoinCondition::CreateCoin(puzzle_hash, amount) = condition {
+                if puzzle_hash == settlement_ph {
+                    parsed.offered_coins.push(CoinString::from_parts(
+                        &spend.coin.to_coin_id(),
+                        &settlement_ph,
+                        &amount,
+                    ));
+                }
+            }
+        }
+        parsed.maker_spends.push(spend.clone());
+    }
+    Ok(parsed)
+}
+
+fn terms_of(parsed: &ParsedOffer) -> Result<OfferTerms, Error> {
+    let mut offered = 0;
+    for coin in &parsed.offered_coins {
+        offered += coin_amount(coin)?;
+    }
+    Ok(OfferTerms {
+        offered: Amount::new(offered),
+        requested: Amount::new(parsed.requested),
+    })
+}
+
+/// Read the terms of an XCH-only offer, e.g. to show them before taking it.
+pub fn offer_terms(allocator: &mut AllocEncoder, offer: &SpendBundle) -> Result<OfferTerms, Error> {
+    terms_of(&parse_offer(allocator, offer)?)
+}
+
+fn is_requested_payment(spend: &CoinSpend) -> bool {
+    spend
+        .coin
+        .to_parts()
+        .is_some_and(|(parent, puzzle_hash, _)| {
+            parent == CoinID::default() && puzzle_hash == settlement_puzzle_hash()
+        })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StandardWallet {
+    identity: ChiaIdentity,
+    /// Fee left unclaimed by every spend the wallet builds.
+    fee: Amount,
+    coins: BTreeSet<CoinString>,
+    pending: BTreeSet<CoinString>,
+}
+
+impl StandardWallet {
+    pub fn new(identity: ChiaIdentity, fee: Amount) -> Self {
+        StandardWallet {
+            identity,
+            fee,
+            coins: BTreeSet::new(),
+            pending: BTreeSet::new(),
+        }
+    }
+
+    pub fn identity(&self) -> &ChiaIdentity {
+        &self.identity
+    }
+
+    pub fn fee(&self) -> &Amount {
+        &self.fee
+    }
+
+    pub fn set_fee(&mut self, fee: Amount) {
+        self.fee = fee;
+    }
+
+    /// Fold in chain state for coins at our puzzle hash; records for other
+    /// puzzle hashes are ignored.  A coin is spendable while it is created
+    /// and unspent.
+    pub fn report_coin_states(&mut self, records: &[CoinStateRecord]) {
+        for record in records {
+            let ours = record
+                .coin
+                .to_parts()
+                .is_some_and(|(_, puzzle_hash, _)| puzzle_hash == self.identity.puzzle_hash);
+            if !ours {
+                continue;
+            }
+            if record.created_height.is_some() && record.spent_height.is_none() {
+                self.coins.insert(record.coin.clone());
+            } else {
+                self.coins.remove(&record.coin);
+                self.pending.remove(&record.coin);
+            }
+        }
+    }
+
+    /// Every coin the wallet believes unspent, pending or not: the set the
+    /// host should keep polling.
+    pub fn tracked_coins(&self) -> Vec<CoinString> {
+        self.coins.iter().cloned().collect()
+    }
+
+    pub fn spendable_coins(&self) -> Vec<CoinString> {
+        self.coins.difference(&self.pending).cloned().collect()
+    }
+
+    pub fn balance(&self) -> Amount {
+        Amount::new(
+            self.coins
+                .difference(&self.pending)
+                .filter_map(|coin| coin_amount(coin).ok())
+                .sum(),
+        )
+    }
+
+    /// Make coins from a bundle that will never be submitted spendable again.
+    pub fn release(&mut self, coins: &[CoinString]) {
+        for coin in coins {
+            self.pending.remove(coin);
+        }
+    }
+
+    /// Choose spendable coins outside `excluded` worth at least `target`:
+    /// the smallest single coin that covers it, or else the largest coins
+    /// until they do.  Always returns at least one coin.
+    pub fn select_coins(
+        &self,
+        target: &Amount,
+        excluded: &BTreeSet<CoinString>,
+    ) -> Result<Vec<CoinString>, Error> {
+        let mut candidates: Vec<(u64, CoinString)> = self
+            .coins
+            .iter()
+            .filter(|coin| !self.pending.contains(*coin) && !excluded.contains(*coin))
+            .map(|coin| Ok((coin_amount(coin)?, coin.clone())))
+            .collect::<Result<_, Error>>()?;
+        candidates.sort();
+        let target = target.to_u64();
+        if let Some((_, coin)) = candidates.iter().find(|(amount, _)| *amount >= target) {
+            return Ok(vec![coin.clone()]);
+        }
+
+        let mut selected = Vec::new();
+        let mut total = 0;
+        while let Some((amount, coin)) = candidates.pop() {
+            selected.push(coin);
+            total += amount;
+            if total >= target {
+                return Ok(selected);
+            }
+        }
+        Err(Error::StrErr(format!(
+            "insufficient funds: need {target}, have {total} spendable"
+        )))
+    }
+
+    /// Spend `coins`, the first carrying `conditions`, sending everything
+    /// beyond `outgoing` back to us as change and declaring our fee with
+    /// `RESERVE_FEE`.  Marks the coins pending.
+    fn spend_coins(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        coins: &[CoinString],
+        mut conditions: Vec<Node>,
+        outgoing: u64,
+    ) -> Result<Vec<CoinSpend>, Error> {
+        let total: u64 = coins.iter().map(coin_amount).sum::<Result<u64, Error>>()?;
+        let change = total.checked_sub(outgoing).ok_or_else(|| {
+            Error::StrErr(format!("selected {total} mojos cannot cover {outgoing}"))
+        })?;
+        if change > 0 {
+            let puzzle_hash = self.identity.puzzle_hash.clone();
+            conditions.push(create_coin(allocator, &puzzle_hash, change)?);
+        }
+        if self.fee.to_u64() > 0 {
+            conditions.push(Node(
+                (RESERVE_FEE, (self.fee.clone(), ()))
+                    .to_clvm(allocator)
+                    .into_gen()?,
+            ));
+        }
+        let primary = coins
+            .first()
+            .ok_or_else(|| Error::StrErr("no coins to spend".to_string()))?
+            .to_coin_id();
+        if coins.len() > 1 {
+            conditions.push(atom_condition(
+                allocator,
+                CREATE_COIN_ANNOUNCEMENT,
+                LINK_MESSAGE,
+            )?);
+        }
+        let link = Sha256Input::Array(vec![
+            Sha256Input::Bytes(primary.bytes()),
+            Sha256Input::Bytes(LINK_MESSAGE),
+        ])
+        .hash();
+
+        let agg_sig_me_additional_data = ChannelEnv::new(allocator)?.agg_sig_me_additional_data;
+        let mut spends = Vec::with_capacity(coins.len());
+        for (index, coin) in coins.iter().enumerate() {
+            let coin_conditions = if index == 0 {
+                std::mem::take(&mut conditions)
+            } else {
+                vec![atom_condition(
+                    allocator,
+                    ASSERT_COIN_ANNOUNCEMENT,
+                    link.bytes(),
+                )?]
+            };
+            let conditions = coin_conditions.to_clvm(allocator).into_gen()?;
+            let quoted_hash = conditions
+                .to_quoted_program(allocator)?
+                .sha256tree(allocator);
+            let solution = solution_for_conditions(allocator, conditions)?;
+            spends.push(CoinSpend {
+                coin: coin.clone(),
+                bundle: Spend {
+                    puzzle: self.identity.puzzle.clone(),
+                    solution: Program::from_nodeptr(allocator, solution)?.into(),
+                    signature: sign_agg_sig_me(
+                        &self.identity.synthetic_private_key,
+                        quoted_hash.bytes(),
+                        &coin.to_coin_id(),
+                        &agg_sig_me_additional_data,
+                    ),
+                },
+            });
+        }
+        self.pending.extend(coins.iter().cloned());
+        Ok(spends)
+    }
+
+    /// Build the funding spend for a channel's `CoinSpendRequest`: the
+    /// requested amount and our fee leave as deficit, the rest returns as
+    /// change.  When the request names a coin (the launcher's parent) that
+    /// coin is spent first and carries the conditions; it may already be
+    /// pending from [`ChannelFundingSource::launcher_parent`].
+    pub fn fund_request(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        request: &CoinSpendRequest,
+        excluded: &BTreeSet<CoinString>,
+    ) -> Result<SpendBundle, Error> {
+        let outgoing = request.amount.to_u64() + self.fee.to_u64();
+        let coins = if let Some(coin_id) = request.coin_id.as_ref() {
+            let named = self
+                .coins
+                .iter()
+                .find(|coin| coin.to_coin_id() == *coin_id)
+                .cloned()
+                .ok_or_else(|| {
+                    Error::StrErr(format!("requested coin {coin_id:?} is not in the wallet"))
+                })?;
+            let mut coins = vec![named.clone()];
+            let named_amount = coin_amount(&named)?;
+            if named_amount < outgoing {
+                let mut excluded = excluded.clone();
+                excluded.insert(named);
+                coins.extend(self.select_coins(&Amount::new(outgoing - named_amount), &excluded)?);
+            }
+            coins
+        } else {
+            self.select_coins(&Amount::new(outgoing), excluded)?
+        };
+
+        let mut conditions = Vec::with_capacity(request.conditions.len() + 1);
+        for condition in &request.conditions {
+            let mut items = vec![Node(condition.opcode.to_clvm(allocator).into_gen()?)];
+            for arg in &condition.args {
+                items.push(Node(
+                    allocator
+                        .encode_atom(clvm_traits::Atom::Borrowed(arg))
+                        .into_gen()?,
+                ));
+            }
+            conditions.push(Node(items.to_clvm(allocator).into_gen()?));
+        }
+        if let Some(max_height) = request.max_height {
+            let height = Node(max_height.to_clvm(allocator).into_gen()?);
+            conditions.push(Node(
+                (ASSERT_BEFORE_HEIGHT_ABSOLUTE, (height, ()))
+                    .to_clvm(allocator)
+                    .into_gen()?,
+            ));
+        }
+
+        Ok(SpendBundle {
+            name: Some("wallet coin spend request".to_string()),
+            spends: self.spend_coins(allocator, &coins, conditions, outgoing)?,
+        })
+    }
+
+    /// Offer `offered` mojos in exchange for `requested` mojos paid to us.
+    ///
+    /// The result is not spendable on its own: it holds our signed spends,
+    /// which lock the offered amount in a settlement coin and assert that the
+    /// requested payment is made, plus a placeholder settlement spend (parent
+    /// id zero) describing that payment for the taker.
+    pub fn create_offer(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        offered: &Amount,
+        requested: &Amount,
+        excluded: &BTreeSet<CoinString>,
+    ) -> Result<SpendBundle, Error> {
+        let outgoing = offered.to_u64() + self.fee.to_u64();
+        let coins = self.select_coins(&Amount::new(outgoing), excluded)?;
+        let settlement_ph = settlement_puzzle_hash();
+
+        let mut conditions = Vec::new();
+        if offered.to_u64() > 0 {
+            conditions.push(create_coin(allocator, &settlement_ph, offered.to_u64())?);
+        }
+        let mut placeholder = None;
+        if requested.to_u64() > 0 {
+            let nonce = offer_nonce(allocator, &coins);
+            let payment = notarized_payment(
+                allocator,
+                &nonce,
+                &self.identity.puzzle_hash,
+                requested.to_u64(),
+            )?;
+            conditions.push(assert_settlement_announcement(allocator, payment)?);
+            let solution = (Node(payment), ()).to_clvm(allocator).into_gen()?;
+            placeholder = Some(CoinSpend {
+                coin: CoinString::from_parts(
+                    &CoinID::default(),
+                    &settlement_ph,
+                    &Amount::default(),
+                ),
+                bundle: Spend {
+                    puzzle: Puzzle::from_bytes(&chia_puzzles::SETTLEMENT_PAYMENT),
+                    solution: Program::from_nodeptr(allocator, solution)?.into(),
+                    signature: Aggsig::default(),
+                },
+            });
+        }
+
+        let mut spends = self.spend_coins(allocator, &coins, conditions, outgoing)?;
+        spends.extend(placeholder);
+        Ok(SpendBundle {
+            name: Some("offer".to_string()),
+            spends,
+        })
+    }
+
+    /// Accept an offer built by [`StandardWallet::create_offer`] (or any
+    /// XCH-only offer in the same layout): pay its requested payments from
+    /// our coins, claim its offered settlement coins to our puzzle hash, and
+    /// return the complete transaction.  `expected` is what the user agreed
+    /// to, e.g. from [`offer_terms`]; an offer with other terms is refused.
+    pub fn take_offer(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        offer: &SpendBundle,
+        expected: &OfferTerms,
+        excluded: &BTreeSet<CoinString>,
+    ) -> Result<SpendBundle, Error> {
+        let settlement_ph = settlement_puzzle_hash();
+        let settlement_puzzle = Puzzle::from_bytes(&chia_puzzles::SETTLEMENT_PAYMENT);
+
+        let parsed = parse_offer(allocator, offer)?;
+        let terms = terms_of(&parsed)?;
+        if terms != *expected {
+            return Err(Error::StrErr(format!(
+                "offer terms {terms:?} are not the expected {expected:?}"
+            )));
+        }
+        let ParsedOffer {
+            maker_spends,
+            requested_payments,
+            requested,
+            offered_coins,
+        } = parsed;
+
+        let outgoing = requested + self.fee.to_u64();
+        let coins = self.select_coins(&Amount::new(outgoing), excluded)?;
+        let nonce = offer_nonce(allocator, &coins);
+        let mut conditions = Vec::new();
+        let mut claims = Vec::with_capacity(offered_coins.len());
+        for coin in offered_coins {
+            let amount = coin_amount(&coin)?;
+            let puzzle_hash = self.identity.puzzle_hash.clone();
+            let payment = notarized_payment(allocator, &nonce, &puzzle_hash, amount)?;
+            conditions.push(assert_settlement_announcement(allocator, payment)?);
+            let solution = (Node(payment), ()).to_clvm(allocator).into_gen()?;
+            claims.push(CoinSpend {
+                coin,
+                bundle: Spend {
+                    puzzle: settlement_puzzle.clone(),
+                    solution: Program::from_nodeptr(allocator, solution)?.into(),
+                    signature: Aggsig::default(),
+                },
+            });
+        }
+        if requested > 0 {
+            conditions.push(create_coin(allocator, &settlement_ph, requested)?);
+        }
+
+        // Creators precede the ephemeral coins they create.
+        let mut spends = maker_spends;
+        let ours = self.spend_coins(allocator, &coins, conditions, outgoing)?;
+        let primary = ours[0].coin.to_coin_id();
+        spends.extend(ours);
+        if requested > 0 {
+            let payer = CoinString::from_parts(&primary, &settlement_ph, &Amount::new(requested));
+            let solution = requested_payments.to_clvm(allocator).into_gen()?;
+            spends.push(CoinSpend {
+                coin: payer,
+                bundle: Spend {
+                    puzzle: settlement_puzzle.clone(),
+                    solution: Program::from_nodeptr(allocator, solution)?.into(),
+                    signature: Aggsig::default(),
+                },
+            });
+        }
+        spends.extend(claims);
+        Ok(SpendBundle {
+            name: Some("accepted offer".to_string()),
+            spends,
+        })
+    }
+}
+
+impl ChannelFundingSource for StandardWallet {
+    /// Earmark a coin for the launcher's parent: the smallest that covers the
+    /// contribution and fee alone, or else our largest.  It stays pending
+    /// until [`StandardWallet::fund_request`] spends it.
+    fn launcher_parent(
+        &mut self,
+        amount: &Amount,
+        reserved: &BTreeSet<CoinString>,
+    ) -> Result<CoinString, Error> {
+        let target = Amount::new(amount.to_u64() + self.fee.to_u64());
+        let coins = self.select_coins(&target, reserved)?;
+        let coin = coins[0].clone();
+        self.pending.insert(coin.clone());
+        Ok(coin)
+    }
+
+    fn fund(
+        &mut self,
+        allocator: &mut AllocEncoder,
+        request: &CoinSpendRequest,
+        reserved: &BTreeSet<CoinString>,
+    ) -> Result<SpendBundle, Error> {
+        self.fund_request(allocator, request, reserved)
+    }
+
+    fn release(&mut self, coins: &[CoinString]) {
+        StandardWallet::release(self, coins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::common::types::{Hash, PrivateKey};
+
+    fn wallet_with(amounts: &[u64]) -> StandardWallet {
+        let mut allocator = AllocEncoder::new();
+        let private_key = PrivateKey::from_bytes(&[3; 32]).expect("key");
+        let identity = ChiaIdentity::new(&mut allocator, private_key).expect("identity");
+        let records: Vec<CoinStateRecord> = amounts
+            .iter()
+            .enumerate()
+            .map(|(index, amount)| {
+                let coin = CoinString::from_parts(
+                    &CoinID::new(Hash::from_bytes([index as u8 + 1; 32])),
+                    &identity.puzzle_hash,
+                    &Amount::new(*amount),
+                );
+                CoinStateRecord::new(coin, Some(1), None)
+            })
+            .collect();
+        let mut wallet = StandardWallet::new(identity, Amount::default());
+        wallet.report_coin_states(&records);
+        wallet
+    }
+
+    fn amounts(coins: &[CoinString]) -> Vec<u64> {
+        coins
+            .iter()
+            .map(|coin| coin_amount(coin).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn selects_smallest_covering_coin() {
+        let wallet = wallet_with(&[50, 200, 120]);
+        let coins = wallet
+            .select_coins(&Amount::new(100), &BTreeSet::new())
+            .expect("select");
+        assert_eq!(amounts(&coins), vec![120]);
+    }
+
+    #[test]
+    fn combines_largest_coins_when_none_covers() {
+        let wallet = wallet_with(&[50, 80, 10, 70]);
+        let coins = wallet
+            .select_coins(&Amount::new(140), &BTreeSet::new())
+            .expect("select");
+        assert_eq!(amounts(&coins), vec![80, 70]);
+    }
+
+    #[test]
+    fn skips_excluded_pending_and_spent_coins() {
+        let mut wallet = wallet_with(&[100, 200, 300]);
+        let coin = |wallet: &StandardWallet, amount: u64| {
+            wallet
+                .tracked_coins()
+                .into_iter()
+                .find(|coin| coin_amount(coin).unwrap() == amount)
+                .expect("coin")
+        };
+        let small = coin(&wallet, 100);
+        let large = coin(&wallet, 300);
+        wallet.pending.insert(small.clone());
+        wallet.report_coin_states(&[CoinStateRecord::new(coin(&wallet, 200), Some(1), Some(2))]);
+        let excluded: BTreeSet<CoinString> = [large.clone()].into_iter().collect();
+        assert!(wallet.select_coins(&Amount::new(1), &excluded).is_err());
+        assert_eq!(wallet.balance(), Amount::new(300));
+
+        wallet.release(std::slice::from_ref(&small));
+        let chosen = wallet
+            .select_coins(&Amount::new(1), &excluded)
+            .expect("select");
+        assert_eq!(chosen, vec![small]);
+    }
+
+    #[test]
+    fn reports_insufficient_funds() {
+        let wallet = wallet_with(&[10, 20]);
+        assert!(wallet
+            .select_coins(&Amount::new(31), &BTreeSet::new())
+            .is_err());
+    }
+}
@@ -39,11 +39,12 @@ elif [ -f "$STATE_FILE" ] && cmp -s "$CURRENT_STATE" "$STATE_FILE"; then
 fi
 
 SECONDS=0
-find clsp -name '*.hex' -delete
+find clsp \( -name '*.hex' -o -name '*.sym' \) -delete
 
 # CHIALISP_COMPILE is deliberately unique. Cargo tracks it as a build-script
 # input, so this forces one Chialisp compile without deleting Cargo's package
-# cache. Ordinary cargo commands leave it unset and never compile Chialisp.
+# cache. Ordinary cargo commands leave it unset and compile only into OUT_DIR;
+# with it set, build.rs also copies the .hex outputs into clsp/.
 CHIALISP_COMPILE="$(date +%s)-$$-${RANDOM:-0}" cargo build --features sim-server
 
 # Prefer head -n 1 over find's early-exit primary: that primary is GNU-only
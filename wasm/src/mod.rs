@@ -31,17 +31,23 @@ mod gaming_wasm {
     use chia_traits::Streamable;
     use flate2::Decompress;
     use flate2::FlushDecompress;
-    use chia_gaming::game_session::{GameSession, GameSessionConfig, TerminalHandoffCommand};
+    use chia_gaming::game_session::{
+        decode_saved_session, GameSession, GameSessionConfig, LivenessPolicy,
+        TerminalHandoffCommand,
+    };
     use chia_gaming::transaction_manager::{
         CoinStateRecord, ManagerDrain, TransactionManager,
     };
     use chia_gaming::session_phases::effects::{
         FailedGameAction, GameSessionEvent, GameNotification,
     };
+    use chia_gaming::operator_fee::{FeeRate, OperatorFee};
+    use chia_gaming::payout::{PayoutShare, PayoutTerms};
+    use chia_gaming::session_match::MatchTerms;
     use chia_gaming::session_phases::game_collection;
     use chia_gaming::session_phases::handshake::{CoinSpendRequest, RawCoinCondition};
     use chia_gaming::session_phases::proposal::GameProposal;
-    use chia_gaming::session_phases::types::GameFactory;
+    use chia_gaming::session_phases::types::{GameFactory, PeerLimits};
 
     #[cfg(target_arch = "wasm32")]
     use lol_alloc::{FreeListAllocator, LockedAllocator};
@@ -177,6 +183,13 @@ mod gaming_wasm {
         wasm_cache_file(name, data);
     }
 
+    /// Record chialisp call traces for failing handler and validator runs.
+    /// Slow; meant for debugging game handlers.
+    #[wasm_bindgen]
+    pub fn set_clvm_tracing(enabled: bool) {
+        chia_gaming::common::clvm_trace::set_clvm_tracing(enabled);
+    }
+
     fn get_next_id() -> i32 {
         NEXT_ID.with(|n| n.fetch_add(1, Ordering::SeqCst))
     }
@@ -204,6 +217,10 @@ mod gaming_wasm {
         channel_timeout: i32,
         unroll_timeout: i32,
         reward_puzzle_hash: String,
+        #[serde(default)]
+        liveness: LivenessPolicy,
+        #[serde(default)]
+        peer_limits: PeerLimits,
     }
 
     struct GameConfigPartial {
@@ -214,6 +231,8 @@ mod gaming_wasm {
         my_contribution: Amount,
         their_contribution: Amount,
         reward_puzzle_hash: PuzzleHash,
+        liveness: LivenessPolicy,
+        peer_limits: PeerLimits,
         rng_id: i32,
     }
 
@@ -238,6 +257,8 @@ mod gaming_wasm {
             reward_puzzle_hash: PuzzleHash::from_hash(
                 Hash::from_slice(&reward_puzzle_hash_bytes).into_js()?,
             ),
+            liveness: jsconfig.liveness,
+            peer_limits: jsconfig.peer_limits,
             rng_id: jsconfig.rng_id,
         })
     }
@@ -330,6 +351,8 @@ mod gaming_wasm {
                 my_contribution: partial.my_contribution,
                 their_contribution: partial.their_contribution,
                 reward_puzzle_hash: partial.reward_puzzle_hash,
+                liveness: partial.liveness,
+                peer_limits: partial.peer_limits,
             };
 
             let game_cradle = GameSession::new(rng, config);
@@ -355,9 +378,7 @@ mod gaming_wasm {
 
     #[wasm_bindgen]
     pub fn restore_session(data: &[u8], new_seed: &str) -> Result<i32, JsValue> {
-        let cradle: JsGameSession = bencodex::from_slice::<JsGameSession>(data)
-            .map_err(|e| types::Error::StrErr(e.to_string()))
-            .into_js()?;
+        let cradle: JsGameSession = decode_saved_session::<JsGameSession>(data).into_js()?;
         let mut cradle = cradle;
         let hashed = Sha256Input::Bytes(new_seed.as_bytes()).hash();
         cradle.rng = ChaCha8SerializationWrapper(ChaCha8Rng::from_seed(*hashed.bytes()));
@@ -392,7 +413,7 @@ mod gaming_wasm {
         with_game(cid, move |cradle: &mut JsGameSession| {
             let bytes = bencodex::to_vec(&cradle)
                 .map_err(|e| types::Error::StrErr(e.to_string()))?;
-            bencodex::from_slice::<JsGameSession>(&bytes).map_err(|e| {
+            decode_saved_session::<JsGameSession>(&bytes).map_err(|e| {
                 types::Error::StrErr(format!(
                     "serialized cradle failed immediate schema verification: {e}"
                 ))
@@ -465,6 +486,8 @@ mod gaming_wasm {
         /// Full coin string, hex-encoded.
         coin: String,
         created_height: Option<u64>,
+        #[serde(default)]
+        created_timestamp: Option<u64>,
         spent_height: Option<u64>,
     }
 
@@ -484,6 +507,7 @@ mod gaming_wasm {
             records.push(CoinStateRecord {
                 coin: hex_to_coinstring(&r.coin).into_js()?,
                 created_height: r.created_height,
+                created_timestamp: r.created_timestamp,
                 spent_height: r.spent_height,
             });
         }
@@ -495,6 +519,16 @@ mod gaming_wasm {
         with_game_drain(cid, |_| Ok(()))
     }
 
+    /// Record the latest block timestamp, against which seconds timeouts
+    /// mature.  Call before `report_coin_states` or `report_height`.
+    #[wasm_bindgen]
+    pub fn report_timestamp(cid: i32, timestamp: u64) -> Result<(), JsValue> {
+        with_game(cid, move |cradle: &mut JsGameSession| {
+            cradle.cradle.report_timestamp(timestamp);
+            Ok(())
+        })
+    }
+
     /// Advance protocol clocks from a confirmed peak without claiming that an
     /// empty coin list is an authoritative watched-coin snapshot.
     #[wasm_bindgen]
@@ -798,6 +832,95 @@ mod gaming_wasm {
         // Game name
         game_type: String,
         timeout: u64,
+        #[serde(default)]
+        fee: Option<JsOperatorFee>,
+        #[serde(default)]
+        payout: Option<Vec<JsPayoutShare>>,
+    }
+
+    #[derive(Deserialize)]
+    struct JsPayoutShare {
+        puzzle_hash: String,
+        basis_points: u16,
+    }
+
+    fn payout_from_js(payout: &Option<Vec<JsPayoutShare>>) -> Result<Option<PayoutTerms>, JsValue> {
+        let Some(shares) = payout else {
+            return Ok(None);
+        };
+        let destinations = shares
+            .iter()
+            .map(|share| {
+                let puzzle_hash = Hash::from_slice(&check_for_hex(&share.puzzle_hash)?).into_js()?;
+                Ok(PayoutShare {
+                    puzzle_hash: PuzzleHash::from_hash(puzzle_hash),
+                    basis_points: share.basis_points,
+                })
+            })
+            .collect::<Result<Vec<_>, JsValue>>()?;
+        Ok(Some(PayoutTerms::split(destinations).into_js()?))
+    }
+
+    /// Exactly one of `basis_points` and `flat` is set.
+    #[derive(Deserialize)]
+    struct JsOperatorFee {
+        puzzle_hash: String,
+        #[serde(default)]
+        basis_points: Option<u16>,
+        #[serde(default)]
+        flat: Option<u64>,
+    }
+
+    fn operator_fee_from_js(fee: &Option<JsOperatorFee>) -> Result<Option<OperatorFee>, JsValue> {
+        let Some(fee) = fee else {
+            return Ok(None);
+        };
+        let rate = match (fee.basis_points, fee.flat) {
+            (Some(points), None) => FeeRate::BasisPoints(points),
+            (None, Some(flat)) => FeeRate::Flat(Amount::new(flat)),
+            _ => {
+                return Err(JsValue::from_str(
+                    "operator fee needs exactly one of basis_points and flat",
+                ))
+            }
+        };
+        let puzzle_hash = Hash::from_slice(&check_for_hex(&fee.puzzle_hash)?).into_js()?;
+        Ok(Some(OperatorFee {
+            puzzle_hash: PuzzleHash::from_hash(puzzle_hash),
+            rate,
+        }))
+    }
+
+    #[derive(Deserialize)]
+    struct JsMatchTerms {
+        game_type: String,
+        timeout: u64,
+        #[serde(default)]
+        hands: Option<u32>,
+        #[serde(default)]
+        stop_loss: Option<u64>,
+        #[serde(default)]
+        alternate_proposer: bool,
+        #[serde(default)]
+        fee: Option<JsOperatorFee>,
+        #[serde(default)]
+        payout: Option<Vec<JsPayoutShare>>,
+    }
+
+    fn match_terms_from_js(terms: JsValue, parameters: &[u8]) -> Result<MatchTerms, JsValue> {
+        let js_terms: JsMatchTerms = serde_wasm_bindgen::from_value(terms).into_js()?;
+        Ok(MatchTerms {
+            proposal: GameProposal {
+                game_type: GameType(js_terms.game_type.as_bytes().to_vec()),
+                timeout: Timeout::new(js_terms.timeout),
+                parameters: Program::from_bytes(parameters),
+                fee: operator_fee_from_js(&js_terms.fee)?,
+                payout: payout_from_js(&js_terms.payout)?,
+            },
+            hands: js_terms.hands,
+            stop_loss: js_terms.stop_loss.map(Amount::new),
+            alternate_proposer: js_terms.alternate_proposer,
+        })
     }
 
     fn game_id_to_string(id: &GameID) -> String {
@@ -819,16 +942,20 @@ mod gaming_wasm {
         if js_games.len() != params_arr.len() {
             return Err(JsValue::from_str("games and parameters_list must have the same length"));
         }
-        with_game(cid, move |cradle: &mut JsGameSession| {
-            let game_starts: Vec<GameProposal> = js_games
-                .iter()
-                .zip(params_arr.iter())
-                .map(|(g, p)| GameProposal {
+        let game_starts: Vec<GameProposal> = js_games
+            .iter()
+            .zip(params_arr.iter())
+            .map(|(g, p)| {
+                Ok(GameProposal {
                     game_type: GameType(g.game_type.as_bytes().to_vec()),
                     timeout: Timeout::new(g.timeout),
                     parameters: Program::from_bytes(p),
+                    fee: operator_fee_from_js(&g.fee)?,
+                    payout: payout_from_js(&g.payout)?,
                 })
-                .collect();
+            })
+            .collect::<Result<_, JsValue>>()?;
+        with_game(cid, move |cradle: &mut JsGameSession| {
             let ids = cradle.cradle.propose_games(
                 &mut cradle.allocator,
                 &game_starts,
@@ -858,6 +985,25 @@ mod gaming_wasm {
         })
     }
 
+    /// `payout` is a list of `{ puzzle_hash, basis_points }` whose basis
+    /// points sum to 10000.
+    #[wasm_bindgen]
+    pub fn accept_proposal_with_payout(
+        cid: i32,
+        game_id: &str,
+        payout: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let game_id = string_to_game_id(game_id)?;
+        let shares: Vec<JsPayoutShare> = serde_wasm_bindgen::from_value(payout).into_js()?;
+        let payout = payout_from_js(&Some(shares))?
+            .ok_or_else(|| JsValue::from_str("missing payout"))?;
+        with_game_drain(cid, move |cradle: &mut JsGameSession| {
+            cradle
+                .cradle
+                .accept_proposal_with_payout(&mut cradle.allocator, &game_id, &payout)
+        })
+    }
+
     #[wasm_bindgen]
     pub fn cancel_proposal(cid: i32, game_id: &str) -> Result<JsValue, JsValue> {
         let game_id = string_to_game_id(game_id)?;
@@ -868,6 +1014,59 @@ mod gaming_wasm {
         })
     }
 
+    #[wasm_bindgen]
+    pub fn start_match(cid: i32, terms: JsValue, parameters: &[u8]) -> Result<JsValue, JsValue> {
+        let terms = match_terms_from_js(terms, parameters)?;
+        with_game(cid, move |cradle: &mut JsGameSession| {
+            let id = cradle.cradle.start_match(&mut cradle.allocator, terms)?;
+            let dr = cradle
+                .cradle
+                .flush_and_collect(&mut cradle.allocator)?;
+            let events = collect_drain_events(&dr)?;
+            let obj = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&obj, &"id".into(), &JsValue::from_str(&game_id_to_string(&id)));
+            let _ = js_sys::Reflect::set(&obj, &"events".into(), &events);
+            Ok(obj.into())
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn accept_match(
+        cid: i32,
+        game_id: &str,
+        terms: JsValue,
+        parameters: &[u8],
+    ) -> Result<JsValue, JsValue> {
+        let game_id = string_to_game_id(game_id)?;
+        let terms = match_terms_from_js(terms, parameters)?;
+        with_game_drain(cid, move |cradle: &mut JsGameSession| {
+            cradle
+                .cradle
+                .accept_match(&mut cradle.allocator, &game_id, terms)
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn stop_match(cid: i32) -> Result<JsValue, JsValue> {
+        with_game_drain(cid, move |cradle: &mut JsGameSession| cradle.cradle.stop_match())
+    }
+
+    #[wasm_bindgen]
+    pub fn authorize_spectator(
+        cid: i32,
+        game_id: &str,
+        observer: &str,
+        delay_moves: u32,
+    ) -> Result<JsValue, JsValue> {
+        let game_id = string_to_game_id(game_id)?;
+        let observer = PublicKey::from_slice(&check_for_hex(observer)?).into_js()?;
+        with_game_drain(cid, move |cradle: &mut JsGameSession| {
+            cradle
+                .cradle
+                .authorize_spectator(&game_id, observer, delay_moves)
+        })
+    }
+
     pub fn make_move_inner(
         cid: i32,
         id: &str,
@@ -1275,6 +1474,11 @@ mod gaming_wasm {
             GameSessionEvent::WatchCoin { .. } => Err(types::Error::StrErr(
                 "WatchCoin should be intercepted before JS event serialization".to_string(),
             )),
+            GameSessionEvent::GameResultAttested(_) | GameSessionEvent::SpectatorFeed { .. } => {
+                event
+                    .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+                    .into_e()
+            }
         }
     }
 